@@ -64,6 +64,13 @@ pub enum Error {
         source: BoxedError,
     },
 
+    #[snafu(display("Failed to list flow stats in cluster"))]
+    ListFlowStats {
+        #[snafu(implicit)]
+        location: Location,
+        source: BoxedError,
+    },
+
     #[snafu(display("Failed to list flows in catalog {catalog}"))]
     ListFlows {
         #[snafu(implicit)]
@@ -321,6 +328,7 @@ impl ErrorExt for Error {
             | Error::ListFlows { source, .. }
             | Error::ListProcedures { source, .. }
             | Error::ListRegionStats { source, .. }
+            | Error::ListFlowStats { source, .. }
             | Error::ConvertProtoData { source, .. } => source.status_code(),
 
             Error::CreateTable { source, .. } => source.status_code(),
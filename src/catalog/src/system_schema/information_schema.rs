@@ -36,6 +36,8 @@ use common_error::ext::ErrorExt;
 use common_meta::cluster::NodeInfo;
 use common_meta::datanode::RegionStat;
 use common_meta::key::flow::FlowMetadataManager;
+use common_meta::key::flow_stat::{FlowTaskState, FlowTaskStatus};
+use common_meta::key::FlowId;
 use common_procedure::ProcedureInfo;
 use common_recordbatch::SendableRecordBatchStream;
 use datatypes::schema::SchemaRef;
@@ -192,6 +194,7 @@ impl SystemSchemaProviderInner for InformationSchemaProvider {
             )) as _),
             FLOWS => Some(Arc::new(InformationSchemaFlows::new(
                 self.catalog_name.clone(),
+                self.catalog_manager.clone(),
                 self.flow_metadata_manager.clone(),
             )) as _),
             PROCEDURE_INFO => Some(
@@ -338,6 +341,65 @@ pub trait InformationExtension {
 
     /// Gets the region statistics.
     async fn region_stats(&self) -> std::result::Result<Vec<RegionStat>, Self::Error>;
+
+    /// Gets the runtime statistics of flows.
+    async fn flow_stats(&self) -> std::result::Result<Vec<FlowStat>, Self::Error>;
+}
+
+/// The runtime statistics of a flow, reported by the flownode running it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlowStat {
+    pub flow_id: FlowId,
+    /// The running state of the flow, i.e. `running`, `paused` or `failed`
+    pub state: String,
+    /// The last error occurred when running the flow
+    pub last_error: Option<String>,
+    /// How far in milliseconds the flow is behind the current time
+    pub lag_ms: Option<i64>,
+    /// The number of rows in the flow's state
+    pub state_rows: Option<u64>,
+    /// The estimated size in bytes of the flow's state
+    pub state_bytes: Option<u64>,
+}
+
+impl FlowStat {
+    /// Merge the statistics of the same flow reported by another flownode,
+    /// a flow running on multiple flownodes is failed(or paused) if any part of it is
+    pub fn merge(&mut self, other: FlowStat) {
+        let severity = |state: &str| {
+            if state == FlowTaskState::Failed.as_ref() {
+                2
+            } else if state == FlowTaskState::Paused.as_ref() {
+                1
+            } else {
+                0
+            }
+        };
+        if severity(&other.state) > severity(&self.state) {
+            self.state = other.state;
+        }
+        let sum = |a: Option<u64>, b: Option<u64>| match (a, b) {
+            (Some(a), Some(b)) => Some(a + b),
+            (a, b) => a.or(b),
+        };
+        self.last_error = self.last_error.take().or(other.last_error);
+        self.lag_ms = self.lag_ms.max(other.lag_ms);
+        self.state_rows = sum(self.state_rows, other.state_rows);
+        self.state_bytes = sum(self.state_bytes, other.state_bytes);
+    }
+}
+
+impl From<FlowTaskStatus> for FlowStat {
+    fn from(status: FlowTaskStatus) -> Self {
+        Self {
+            flow_id: status.flow_id,
+            state: status.state.as_ref().to_string(),
+            last_error: status.last_error,
+            lag_ms: status.lag_ms,
+            state_rows: status.state_rows,
+            state_bytes: status.state_bytes,
+        }
+    }
 }
 
 pub struct NoopInformationExtension;
@@ -357,4 +419,8 @@ impl InformationExtension for NoopInformationExtension {
     async fn region_stats(&self) -> std::result::Result<Vec<RegionStat>, Self::Error> {
         Ok(vec![])
     }
+
+    async fn flow_stats(&self) -> std::result::Result<Vec<FlowStat>, Self::Error> {
+        Ok(vec![])
+    }
 }
@@ -12,7 +12,9 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::sync::Arc;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
 
 use common_catalog::consts::INFORMATION_SCHEMA_FLOW_TABLE_ID;
 use common_error::ext::BoxedError;
@@ -28,7 +30,9 @@ use datatypes::prelude::ConcreteDataType as CDT;
 use datatypes::scalars::ScalarVectorBuilder;
 use datatypes::schema::{ColumnSchema, Schema, SchemaRef};
 use datatypes::value::Value;
-use datatypes::vectors::{Int64VectorBuilder, StringVectorBuilder, UInt32VectorBuilder, VectorRef};
+use datatypes::vectors::{
+    Int64VectorBuilder, StringVectorBuilder, UInt32VectorBuilder, UInt64VectorBuilder, VectorRef,
+};
 use futures::TryStreamExt;
 use snafu::{OptionExt, ResultExt};
 use store_api::storage::{ScanRequest, TableId};
@@ -36,8 +40,10 @@ use store_api::storage::{ScanRequest, TableId};
 use crate::error::{
     CreateRecordBatchSnafu, FlowInfoNotFoundSnafu, InternalSnafu, JsonSnafu, ListFlowsSnafu, Result,
 };
-use crate::information_schema::{Predicates, FLOWS};
+use crate::information_schema::{FlowStat, Predicates, FLOWS};
 use crate::system_schema::information_schema::InformationTable;
+use crate::system_schema::utils;
+use crate::CatalogManager;
 
 const INIT_CAPACITY: usize = 42;
 
@@ -53,22 +59,30 @@ pub const SOURCE_TABLE_IDS: &str = "source_table_ids";
 pub const SINK_TABLE_NAME: &str = "sink_table_name";
 pub const FLOWNODE_IDS: &str = "flownode_ids";
 pub const OPTIONS: &str = "options";
+pub const STATE: &str = "state";
+pub const LAST_ERROR: &str = "last_error";
+pub const LAG_MS: &str = "lag_ms";
+pub const STATE_ROWS: &str = "state_rows";
+pub const STATE_BYTES: &str = "state_bytes";
 
 /// The `information_schema.flows` to provides information about flows in databases.
 pub(super) struct InformationSchemaFlows {
     schema: SchemaRef,
     catalog_name: String,
+    catalog_manager: Weak<dyn CatalogManager>,
     flow_metadata_manager: Arc<FlowMetadataManager>,
 }
 
 impl InformationSchemaFlows {
     pub(super) fn new(
         catalog_name: String,
+        catalog_manager: Weak<dyn CatalogManager>,
         flow_metadata_manager: Arc<FlowMetadataManager>,
     ) -> Self {
         Self {
             schema: Self::schema(),
             catalog_name,
+            catalog_manager,
             flow_metadata_manager,
         }
     }
@@ -88,6 +102,11 @@ impl InformationSchemaFlows {
                 (SINK_TABLE_NAME, CDT::string_datatype(), false),
                 (FLOWNODE_IDS, CDT::string_datatype(), true),
                 (OPTIONS, CDT::string_datatype(), true),
+                (STATE, CDT::string_datatype(), true),
+                (LAST_ERROR, CDT::string_datatype(), true),
+                (LAG_MS, CDT::int64_datatype(), true),
+                (STATE_ROWS, CDT::uint64_datatype(), true),
+                (STATE_BYTES, CDT::uint64_datatype(), true),
             ]
             .into_iter()
             .map(|(name, ty, nullable)| ColumnSchema::new(name, ty, nullable))
@@ -99,6 +118,7 @@ impl InformationSchemaFlows {
         InformationSchemaFlowsBuilder::new(
             self.schema.clone(),
             self.catalog_name.clone(),
+            self.catalog_manager.clone(),
             &self.flow_metadata_manager,
         )
     }
@@ -140,10 +160,11 @@ impl InformationTable for InformationSchemaFlows {
 
 /// Builds the `information_schema.FLOWS` table row by row
 ///
-/// columns are based on [`FlowInfoValue`]
+/// columns are based on [`FlowInfoValue`] and [`FlowStat`]
 struct InformationSchemaFlowsBuilder {
     schema: SchemaRef,
     catalog_name: String,
+    catalog_manager: Weak<dyn CatalogManager>,
     flow_metadata_manager: Arc<FlowMetadataManager>,
 
     flow_names: StringVectorBuilder,
@@ -156,17 +177,24 @@ struct InformationSchemaFlowsBuilder {
     sink_table_names: StringVectorBuilder,
     flownode_id_groups: StringVectorBuilder,
     option_groups: StringVectorBuilder,
+    states: StringVectorBuilder,
+    last_errors: StringVectorBuilder,
+    lag_mss: Int64VectorBuilder,
+    state_rows: UInt64VectorBuilder,
+    state_bytes: UInt64VectorBuilder,
 }
 
 impl InformationSchemaFlowsBuilder {
     fn new(
         schema: SchemaRef,
         catalog_name: String,
+        catalog_manager: Weak<dyn CatalogManager>,
         flow_metadata_manager: &Arc<FlowMetadataManager>,
     ) -> Self {
         Self {
             schema,
             catalog_name,
+            catalog_manager,
             flow_metadata_manager: flow_metadata_manager.clone(),
 
             flow_names: StringVectorBuilder::with_capacity(INIT_CAPACITY),
@@ -179,6 +207,11 @@ impl InformationSchemaFlowsBuilder {
             sink_table_names: StringVectorBuilder::with_capacity(INIT_CAPACITY),
             flownode_id_groups: StringVectorBuilder::with_capacity(INIT_CAPACITY),
             option_groups: StringVectorBuilder::with_capacity(INIT_CAPACITY),
+            states: StringVectorBuilder::with_capacity(INIT_CAPACITY),
+            last_errors: StringVectorBuilder::with_capacity(INIT_CAPACITY),
+            lag_mss: Int64VectorBuilder::with_capacity(INIT_CAPACITY),
+            state_rows: UInt64VectorBuilder::with_capacity(INIT_CAPACITY),
+            state_bytes: UInt64VectorBuilder::with_capacity(INIT_CAPACITY),
         }
     }
 
@@ -188,6 +221,17 @@ impl InformationSchemaFlowsBuilder {
         let predicates = Predicates::from_scan_request(&request);
 
         let flow_info_manager = self.flow_metadata_manager.clone();
+        let information_extension = utils::information_extension(&self.catalog_manager)?;
+        // a flow may run on multiple flownodes, each reporting its own part
+        let mut flow_stats: HashMap<FlowId, FlowStat> = HashMap::new();
+        for stat in information_extension.flow_stats().await? {
+            match flow_stats.entry(stat.flow_id) {
+                Entry::Occupied(mut entry) => entry.get_mut().merge(stat),
+                Entry::Vacant(entry) => {
+                    entry.insert(stat);
+                }
+            }
+        }
 
         // TODO(discord9): use `AsyncIterator` once it's stable-ish
         let mut stream = flow_info_manager
@@ -213,7 +257,8 @@ impl InformationSchemaFlowsBuilder {
                     catalog_name: catalog_name.to_string(),
                     flow_name: flow_name.to_string(),
                 })?;
-            self.add_flow(&predicates, flow_id.flow_id(), flow_info)?;
+            let flow_stat = flow_stats.get(&flow_id.flow_id());
+            self.add_flow(&predicates, flow_id.flow_id(), flow_info, flow_stat)?;
         }
 
         self.finish()
@@ -224,6 +269,7 @@ impl InformationSchemaFlowsBuilder {
         predicates: &Predicates,
         flow_id: FlowId,
         flow_info: FlowInfoValue,
+        flow_stat: Option<&FlowStat>,
     ) -> Result<()> {
         let row = [
             (FLOW_NAME, &Value::from(flow_info.flow_name().to_string())),
@@ -262,6 +308,12 @@ impl InformationSchemaFlowsBuilder {
                     input: format!("{:?}", flow_info.options()),
                 },
             )?));
+        self.states.push(flow_stat.map(|s| s.state.as_str()));
+        self.last_errors
+            .push(flow_stat.and_then(|s| s.last_error.as_deref()));
+        self.lag_mss.push(flow_stat.and_then(|s| s.lag_ms));
+        self.state_rows.push(flow_stat.and_then(|s| s.state_rows));
+        self.state_bytes.push(flow_stat.and_then(|s| s.state_bytes));
 
         Ok(())
     }
@@ -278,6 +330,11 @@ impl InformationSchemaFlowsBuilder {
             Arc::new(self.sink_table_names.finish()),
             Arc::new(self.flownode_id_groups.finish()),
             Arc::new(self.option_groups.finish()),
+            Arc::new(self.states.finish()),
+            Arc::new(self.last_errors.finish()),
+            Arc::new(self.lag_mss.finish()),
+            Arc::new(self.state_rows.finish()),
+            Arc::new(self.state_bytes.finish()),
         ];
         RecordBatch::new(self.schema.clone(), columns).context(CreateRecordBatchSnafu)
     }
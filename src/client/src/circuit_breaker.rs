@@ -0,0 +1,253 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Failing fast the requests to peers failed consecutively.
+//!
+//! After [CircuitBreakerConfig::failure_threshold] consecutive failures, the circuit of a peer
+//! opens and requests to it fail by [crate::Error::CircuitOpen] without being sent. Once the
+//! cooldown passes, the circuit is half open and a single request is sent to probe the peer,
+//! closing the circuit if it succeeds or opening it again otherwise.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use common_telemetry::warn;
+use parking_lot::{Mutex, RwLock};
+use snafu::ensure;
+
+use crate::error::CircuitOpenSnafu;
+use crate::metrics::METRIC_GRPC_CIRCUIT_BREAKER_OPEN;
+use crate::Result;
+
+/// How the circuit of a peer opens and closes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CircuitBreakerConfig {
+    /// The consecutive failures of requests to a peer to open its circuit.
+    pub failure_threshold: usize,
+    /// How long an open circuit fails requests before probing the peer.
+    pub cooldown: Duration,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            cooldown: Duration::from_secs(10),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Closed { failures: usize },
+    Open { until: Instant },
+    HalfOpen { probing: bool },
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State::Closed { failures: 0 }
+    }
+}
+
+/// The circuits of peers of a client, disabled by default.
+#[derive(Debug, Default)]
+pub(crate) struct CircuitBreakers {
+    config: RwLock<Option<CircuitBreakerConfig>>,
+    states: RwLock<HashMap<String, Arc<Mutex<State>>>>,
+}
+
+impl CircuitBreakers {
+    /// Sets the config, or `None` to disable circuit breaking. Circuits are closed.
+    pub(crate) fn set_config(&self, config: Option<CircuitBreakerConfig>) {
+        *self.config.write() = config;
+        self.states.write().clear();
+    }
+
+    fn state(&self, peer: &str) -> Arc<Mutex<State>> {
+        if let Some(state) = self.states.read().get(peer) {
+            return state.clone();
+        }
+        self.states
+            .write()
+            .entry(peer.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// Returns `true` if requests to the peer would fail fast now.
+    pub(crate) fn is_open(&self, peer: &str) -> bool {
+        if self.config.read().is_none() {
+            return false;
+        }
+        self.states
+            .read()
+            .get(peer)
+            .map_or(false, |state| match *state.lock() {
+                State::Closed { .. } => false,
+                State::Open { until } => Instant::now() < until,
+                State::HalfOpen { probing } => probing,
+            })
+    }
+
+    /// Admits a request to the peer, or fails by [crate::Error::CircuitOpen] if its circuit is
+    /// open. The outcome of the request is reported by [CircuitPermit::record].
+    pub(crate) fn acquire(&self, peer: &str) -> Result<CircuitPermit> {
+        let Some(config) = self.config.read().clone() else {
+            return Ok(CircuitPermit::default());
+        };
+        let state = self.state(peer);
+        let probe = {
+            let mut guard = state.lock();
+            match *guard {
+                State::Closed { .. } => false,
+                State::Open { until } => {
+                    ensure!(Instant::now() >= until, CircuitOpenSnafu { addr: peer });
+                    *guard = State::HalfOpen { probing: true };
+                    true
+                }
+                State::HalfOpen { probing } => {
+                    ensure!(!probing, CircuitOpenSnafu { addr: peer });
+                    *guard = State::HalfOpen { probing: true };
+                    true
+                }
+            }
+        };
+        Ok(CircuitPermit {
+            circuit: Some((config, state, peer.to_string())),
+            probe,
+        })
+    }
+}
+
+/// Admission of a request by the circuit of its peer.
+#[derive(Default)]
+pub(crate) struct CircuitPermit {
+    circuit: Option<(CircuitBreakerConfig, Arc<Mutex<State>>, String)>,
+    probe: bool,
+}
+
+impl CircuitPermit {
+    /// Reports whether the peer responded to the request.
+    pub(crate) fn record(mut self, success: bool) {
+        let Some((config, state, peer)) = self.circuit.take() else {
+            return;
+        };
+        let mut state = state.lock();
+        if success {
+            *state = State::Closed { failures: 0 };
+            return;
+        }
+        let failures = match *state {
+            State::Closed { failures } => failures + 1,
+            // the probe failed, or the circuit was opened by concurrent requests
+            State::Open { .. } | State::HalfOpen { .. } => config.failure_threshold,
+        };
+        if failures < config.failure_threshold.max(1) {
+            *state = State::Closed { failures };
+            return;
+        }
+        if !matches!(*state, State::Open { .. }) {
+            warn!(
+                "Circuit of peer {} is open for {:?} after {} consecutive failures",
+                peer, config.cooldown, failures
+            );
+            METRIC_GRPC_CIRCUIT_BREAKER_OPEN.inc();
+        }
+        *state = State::Open {
+            until: Instant::now() + config.cooldown,
+        };
+    }
+}
+
+impl Drop for CircuitPermit {
+    fn drop(&mut self) {
+        // lets another request probe the peer if the probe is dropped before it's done
+        if let Some((_, state, _)) = self.circuit.take() {
+            let mut state = state.lock();
+            if self.probe && *state == (State::HalfOpen { probing: true }) {
+                *state = State::HalfOpen { probing: false };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PEER: &str = "127.0.0.1:4001";
+
+    fn circuit_breakers(cooldown: Duration) -> CircuitBreakers {
+        let breakers = CircuitBreakers::default();
+        breakers.set_config(Some(CircuitBreakerConfig {
+            failure_threshold: 3,
+            cooldown,
+        }));
+        breakers
+    }
+
+    #[test]
+    fn test_disabled() {
+        let breakers = CircuitBreakers::default();
+        for _ in 0..10 {
+            breakers.acquire(PEER).unwrap().record(false);
+        }
+        assert!(!breakers.is_open(PEER));
+    }
+
+    #[test]
+    fn test_open_after_consecutive_failures() {
+        let breakers = circuit_breakers(Duration::from_secs(60));
+        breakers.acquire(PEER).unwrap().record(false);
+        breakers.acquire(PEER).unwrap().record(false);
+        // a success resets the failures
+        breakers.acquire(PEER).unwrap().record(true);
+        breakers.acquire(PEER).unwrap().record(false);
+        breakers.acquire(PEER).unwrap().record(false);
+        assert!(!breakers.is_open(PEER));
+
+        breakers.acquire(PEER).unwrap().record(false);
+        assert!(breakers.is_open(PEER));
+        assert!(breakers.acquire(PEER).is_err());
+        // other peers aren't affected
+        assert!(breakers.acquire("127.0.0.1:4002").is_ok());
+    }
+
+    #[test]
+    fn test_half_open() {
+        let breakers = circuit_breakers(Duration::ZERO);
+        for _ in 0..3 {
+            breakers.acquire(PEER).unwrap().record(false);
+        }
+
+        // only one request probes the peer
+        let probe = breakers.acquire(PEER).unwrap();
+        assert!(breakers.is_open(PEER));
+        assert!(breakers.acquire(PEER).is_err());
+        // a failed probe opens the circuit again
+        probe.record(false);
+        assert!(matches!(*breakers.state(PEER).lock(), State::Open { .. }));
+
+        // a dropped probe lets another request probe
+        let probe = breakers.acquire(PEER).unwrap();
+        drop(probe);
+        let probe = breakers.acquire(PEER).unwrap();
+        // a successful probe closes the circuit
+        probe.record(true);
+        assert!(!breakers.is_open(PEER));
+        assert_eq!(*breakers.state(PEER).lock(), State::Closed { failures: 0 });
+    }
+}
@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::future::Future;
 use std::sync::Arc;
 
 use api::v1::flow::flow_client::FlowClient as PbFlowClient;
@@ -26,7 +27,9 @@ use snafu::{OptionExt, ResultExt};
 use tonic::codec::CompressionEncoding;
 use tonic::transport::Channel;
 
-use crate::load_balance::{LoadBalance, Loadbalancer};
+use crate::circuit_breaker::{CircuitBreakerConfig, CircuitBreakers};
+use crate::load_balance::{Endpoints, LoadBalance, Loadbalancer};
+use crate::retry::{self, RetryBudget, RetryPolicy};
 use crate::{error, Result};
 
 pub struct FlightClient {
@@ -53,7 +56,22 @@ pub struct Client {
 struct Inner {
     channel_manager: ChannelManager,
     peers: Arc<RwLock<Vec<String>>>,
-    load_balance: Loadbalancer,
+    load_balance: RwLock<Loadbalancer>,
+    endpoints: Endpoints,
+    retry_policy: RwLock<RetryPolicy>,
+    retry_budget: RetryBudget,
+    compression: RwLock<Compression>,
+    circuit_breakers: CircuitBreakers,
+}
+
+/// The encoding to compress requests sent and responses accepted, zstd by default.
+#[derive(Debug, Clone, Copy)]
+struct Compression(Option<CompressionEncoding>);
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self(Some(CompressionEncoding::Zstd))
+    }
 }
 
 impl Inner {
@@ -70,8 +88,25 @@ impl Inner {
     }
 
     fn get_peer(&self) -> Option<String> {
-        let guard = self.peers.read();
-        self.load_balance.get_peer(&guard).cloned()
+        let peers = self.peers.read();
+        // skips peers failed the health check of their channels or with open circuits,
+        // unless none is left
+        let checked = peers
+            .iter()
+            .filter(|peer| {
+                self.channel_manager.is_healthy(peer) && !self.circuit_breakers.is_open(peer)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        let peers = if checked.is_empty() {
+            self.endpoints.healthy_peers(&peers)
+        } else {
+            self.endpoints.healthy_peers(&checked)
+        };
+        self.load_balance
+            .read()
+            .get_peer(&peers, &self.endpoints)
+            .cloned()
     }
 }
 
@@ -130,6 +165,74 @@ impl Client {
         self.inner.set_peers(urls);
     }
 
+    /// Sets how requests of the client are distributed among its peers.
+    pub fn set_load_balance(&self, load_balance: Loadbalancer) {
+        *self.inner.load_balance.write() = load_balance;
+    }
+
+    /// Sets when requests to a peer fail fast after it failed consecutively, or `None` to always
+    /// send them. Disabled by default.
+    pub fn set_circuit_breaker(&self, config: Option<CircuitBreakerConfig>) {
+        self.inner.circuit_breakers.set_config(config);
+    }
+
+    /// Sends the request to `addr` by `send`, counted as in flight to the peer until it's
+    /// responded, and marking the peer as unhealthy if it fails by a transport error.
+    ///
+    /// Fails fast without sending if the circuit of the peer is open.
+    pub(crate) async fn track<T, Fut>(&self, addr: &str, send: Fut) -> Result<T>
+    where
+        Fut: Future<Output = Result<T>>,
+    {
+        let permit = self.inner.circuit_breakers.acquire(addr)?;
+        let _outstanding = self.inner.endpoints.start_request(addr);
+        let res = send.await;
+        match &res {
+            Err(err) if err.is_transport_error() => self.inner.endpoints.mark_unhealthy(addr),
+            _ => self.inner.endpoints.mark_healthy(addr),
+        }
+        let peer_failed = matches!(
+            &res,
+            Err(err) if err.is_transport_error() || err.is_deadline_exceeded()
+        );
+        permit.record(!peer_failed);
+        res
+    }
+
+    /// Sets the encoding to compress requests and accept compressed responses, or `None` to
+    /// disable compression, e.g. if the network is cheaper than CPU.
+    ///
+    /// Clients made afterward use it. Defaults to zstd.
+    pub fn set_compression(&self, encoding: Option<CompressionEncoding>) {
+        *self.inner.compression.write() = Compression(encoding);
+    }
+
+    pub fn compression(&self) -> Option<CompressionEncoding> {
+        self.inner.compression.read().0
+    }
+
+    /// Sets how failed region, flow and flight requests of the client are retried.
+    pub fn set_retry_policy(&self, retry_policy: RetryPolicy) {
+        *self.inner.retry_policy.write() = retry_policy;
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.inner.retry_policy.read().clone()
+    }
+
+    /// Sends the `request` by `send`, retried by the [RetryPolicy] of the client.
+    ///
+    /// Retries of all requests share a budget, so the client backs off from a failing server.
+    pub(crate) async fn retry<R, T, F, Fut>(&self, request: R, send: F) -> Result<T>
+    where
+        R: Clone,
+        F: FnMut(R) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let policy = self.retry_policy();
+        retry::retry(&policy, &self.inner.retry_budget, request, send).await
+    }
+
     pub fn find_channel(&self) -> Result<(String, Channel)> {
         let addr = self
             .inner
@@ -165,42 +268,44 @@ impl Client {
     pub fn make_flight_client(&self) -> Result<FlightClient> {
         let (addr, channel) = self.find_channel()?;
 
-        let client = FlightServiceClient::new(channel)
+        let mut client = FlightServiceClient::new(channel)
             .max_decoding_message_size(self.max_grpc_recv_message_size())
-            .max_encoding_message_size(self.max_grpc_send_message_size())
-            .accept_compressed(CompressionEncoding::Zstd)
-            .send_compressed(CompressionEncoding::Zstd);
+            .max_encoding_message_size(self.max_grpc_send_message_size());
+        if let Some(encoding) = self.compression() {
+            client = client.accept_compressed(encoding).send_compressed(encoding);
+        }
 
         Ok(FlightClient { addr, client })
     }
 
     pub(crate) fn raw_region_client(&self) -> Result<(String, PbRegionClient<Channel>)> {
         let (addr, channel) = self.find_channel()?;
-        let client = PbRegionClient::new(channel)
+        let mut client = PbRegionClient::new(channel)
             .max_decoding_message_size(self.max_grpc_recv_message_size())
-            .max_encoding_message_size(self.max_grpc_send_message_size())
-            .accept_compressed(CompressionEncoding::Zstd)
-            .send_compressed(CompressionEncoding::Zstd);
+            .max_encoding_message_size(self.max_grpc_send_message_size());
+        if let Some(encoding) = self.compression() {
+            client = client.accept_compressed(encoding).send_compressed(encoding);
+        }
         Ok((addr, client))
     }
 
     pub(crate) fn raw_flow_client(&self) -> Result<(String, PbFlowClient<Channel>)> {
         let (addr, channel) = self.find_channel()?;
-        let client = PbFlowClient::new(channel)
+        let mut client = PbFlowClient::new(channel)
             .max_decoding_message_size(self.max_grpc_recv_message_size())
-            .max_encoding_message_size(self.max_grpc_send_message_size())
-            .accept_compressed(CompressionEncoding::Zstd)
-            .send_compressed(CompressionEncoding::Zstd);
+            .max_encoding_message_size(self.max_grpc_send_message_size());
+        if let Some(encoding) = self.compression() {
+            client = client.accept_compressed(encoding).send_compressed(encoding);
+        }
         Ok((addr, client))
     }
 
     pub fn make_prometheus_gateway_client(&self) -> Result<PrometheusGatewayClient<Channel>> {
         let (_, channel) = self.find_channel()?;
-        let client = PrometheusGatewayClient::new(channel)
-            .accept_compressed(CompressionEncoding::Gzip)
-            .accept_compressed(CompressionEncoding::Zstd)
-            .send_compressed(CompressionEncoding::Gzip)
-            .send_compressed(CompressionEncoding::Zstd);
+        let mut client = PrometheusGatewayClient::new(channel);
+        if let Some(encoding) = self.compression() {
+            client = client.accept_compressed(encoding).send_compressed(encoding);
+        }
         Ok(client)
     }
 
@@ -217,7 +322,7 @@ mod tests {
     use std::collections::HashSet;
 
     use super::Inner;
-    use crate::load_balance::Loadbalancer;
+    use crate::load_balance::{Loadbalancer, RoundRobin};
 
     fn mock_peers() -> Vec<String> {
         vec![
@@ -232,7 +337,7 @@ mod tests {
         let inner = Inner::default();
 
         assert!(matches!(
-            inner.load_balance,
+            *inner.load_balance.read(),
             Loadbalancer::Random(crate::load_balance::Random)
         ));
         assert!(inner.get_peer().is_none());
@@ -245,4 +350,17 @@ mod tests {
             assert!(all.contains(&inner.get_peer().unwrap()));
         }
     }
+
+    #[test]
+    fn test_inner_skip_unhealthy_peer() {
+        let inner = Inner::default();
+        *inner.load_balance.write() = Loadbalancer::from(RoundRobin::default());
+        let peers = mock_peers();
+        inner.set_peers(peers.clone());
+
+        inner.endpoints.mark_unhealthy(&peers[1]);
+        for _ in 0..20 {
+            assert_ne!(inner.get_peer().unwrap(), peers[1]);
+        }
+    }
 }
@@ -23,11 +23,13 @@ use moka::future::{Cache, CacheBuilder};
 
 use crate::flow::FlowRequester;
 use crate::region::RegionRequester;
-use crate::Client;
+use crate::{CircuitBreakerConfig, Client, RetryPolicy};
 
 pub struct NodeClients {
     channel_manager: ChannelManager,
     clients: Cache<Peer, Client>,
+    retry_policy: RetryPolicy,
+    circuit_breaker: Option<CircuitBreakerConfig>,
 }
 
 impl Default for NodeClients {
@@ -67,16 +69,33 @@ impl NodeClients {
                 .time_to_live(Duration::from_secs(30 * 60))
                 .time_to_idle(Duration::from_secs(5 * 60))
                 .build(),
+            retry_policy: RetryPolicy::default(),
+            circuit_breaker: None,
         }
     }
 
+    /// Sets how failed requests of clients created afterward are retried.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Sets when requests to a node fail fast by clients created afterward.
+    pub fn with_circuit_breaker(mut self, config: CircuitBreakerConfig) -> Self {
+        self.circuit_breaker = Some(config);
+        self
+    }
+
     pub async fn get_client(&self, datanode: &Peer) -> Client {
         self.clients
             .get_with_by_ref(datanode, async move {
-                Client::with_manager_and_urls(
+                let client = Client::with_manager_and_urls(
                     self.channel_manager.clone(),
                     vec![datanode.addr.clone()],
-                )
+                );
+                client.set_retry_policy(self.retry_policy.clone());
+                client.set_circuit_breaker(self.circuit_breaker.clone());
+                client
             })
             .await
     }
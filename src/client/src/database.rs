@@ -12,6 +12,9 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::future::Future;
+use std::time::Duration;
+
 use api::v1::auth_header::AuthScheme;
 use api::v1::ddl_request::Expr as DdlExpr;
 use api::v1::greptime_database_client::GreptimeDatabaseClient;
@@ -19,28 +22,35 @@ use api::v1::greptime_request::Request;
 use api::v1::query_request::Query;
 use api::v1::{
     AlterExpr, AuthHeader, CreateTableExpr, DdlRequest, GreptimeRequest, InsertRequests,
-    QueryRequest, RequestHeader,
+    PromInstantQuery, PromRangeQuery, QueryRequest, RequestHeader,
 };
-use arrow_flight::Ticket;
+use arrow_flight::{FlightData, Ticket};
 use async_stream::stream;
-use common_error::ext::{BoxedError, ErrorExt};
-use common_grpc::flight::{FlightDecoder, FlightMessage};
-use common_query::Output;
+use common_error::ext::BoxedError;
+use common_grpc::flight::{
+    decode_put_result, do_put_descriptor, FlightDecoder, FlightEncoder, FlightMessage,
+};
+use common_query::{Output, OutputData};
 use common_recordbatch::error::ExternalSnafu;
-use common_recordbatch::RecordBatchStreamWrapper;
+use common_recordbatch::{RecordBatch, RecordBatchStreamWrapper, SendableRecordBatchStream};
 use common_telemetry::error;
 use common_telemetry::tracing_context::W3cTrace;
-use futures_util::StreamExt;
+use datatypes::schema::SchemaRef;
+use futures_util::{Stream, StreamExt};
 use prost::Message;
-use snafu::{ensure, ResultExt};
+use snafu::{ensure, OptionExt, ResultExt};
+use tokio::time::Instant;
 use tonic::metadata::AsciiMetadataKey;
 use tonic::transport::Channel;
+use tonic::Streaming;
 
 use crate::error::{
-    ConvertFlightDataSnafu, Error, FlightGetSnafu, IllegalFlightMessagesSnafu, InvalidAsciiSnafu,
-    ServerSnafu,
+    CollectRecordBatchesSnafu, ConvertFlightDataSnafu, DeadlineExceededSnafu, Error,
+    FlightGetSnafu, FlightPutSnafu, IllegalDatabaseResponseSnafu, IllegalFlightMessagesSnafu,
+    InvalidAsciiSnafu,
 };
-use crate::{from_grpc_response, Client, Result};
+use crate::row::rows_as;
+use crate::{from_grpc_response, Client, FromRow, Result, RetryPolicy, StreamInserter};
 
 #[derive(Clone, Debug, Default)]
 pub struct Database {
@@ -55,6 +65,8 @@ pub struct Database {
     // The time zone indicates the time zone where the user is located.
     // Some queries need to be aware of the user's time zone to perform some specific actions.
     timezone: String,
+    // The timeout of each call, enforced by both the client and the server.
+    timeout: Option<Duration>,
 
     client: Client,
     ctx: FlightContext,
@@ -66,11 +78,13 @@ pub struct DatabaseClient {
 
 fn make_database_client(client: &Client) -> Result<DatabaseClient> {
     let (_, channel) = client.find_channel()?;
-    Ok(DatabaseClient {
-        inner: GreptimeDatabaseClient::new(channel)
-            .max_decoding_message_size(client.max_grpc_recv_message_size())
-            .max_encoding_message_size(client.max_grpc_send_message_size()),
-    })
+    let mut inner = GreptimeDatabaseClient::new(channel)
+        .max_decoding_message_size(client.max_grpc_recv_message_size())
+        .max_encoding_message_size(client.max_grpc_send_message_size());
+    if let Some(encoding) = client.compression() {
+        inner = inner.accept_compressed(encoding).send_compressed(encoding);
+    }
+    Ok(DatabaseClient { inner })
 }
 
 impl Database {
@@ -81,6 +95,7 @@ impl Database {
             schema: schema.into(),
             dbname: String::default(),
             timezone: String::default(),
+            timeout: None,
             client,
             ctx: FlightContext::default(),
         }
@@ -99,6 +114,7 @@ impl Database {
             schema: String::default(),
             timezone: String::default(),
             dbname: dbname.into(),
+            timeout: None,
             client,
             ctx: FlightContext::default(),
         }
@@ -124,6 +140,24 @@ impl Database {
         self.timezone = timezone.into();
     }
 
+    /// Sets the timeout of each call, or `None` to wait until the server responds. A call failed
+    /// by the timeout returns [Error::DeadlineExceeded] and isn't retried.
+    ///
+    /// For streamed query results, the timeout includes reading the stream.
+    /// Clone the [Database] to set a timeout for a single call.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Sets how failed flight requests are retried, shared with others using the same [Client].
+    pub fn set_retry_policy(&self, retry_policy: RetryPolicy) {
+        self.client.set_retry_policy(retry_policy);
+    }
+
     pub fn set_auth(&mut self, auth: AuthScheme) {
         self.ctx.auth_header = Some(AuthHeader {
             auth_scheme: Some(auth),
@@ -139,10 +173,11 @@ impl Database {
         requests: InsertRequests,
         hints: &[(&str, &str)],
     ) -> Result<u32> {
+        let deadline = Deadline::after(self.timeout);
         let mut client = make_database_client(&self.client)?.inner;
         let request = self.to_rpc_request(Request::Inserts(requests));
 
-        let mut request = tonic::Request::new(request);
+        let mut request = tonic_request(request, deadline);
         let metadata = request.metadata_mut();
         for (key, value) in hints {
             let key = AsciiMetadataKey::from_bytes(format!("x-greptime-hint-{}", key).as_bytes())
@@ -160,33 +195,53 @@ impl Database {
             })?;
             metadata.insert(key, value);
         }
-        let response = client.handle(request).await?.into_inner();
+        let response = until_deadline(deadline, client.handle(request))
+            .await??
+            .into_inner();
         from_grpc_response(response)
     }
 
     async fn handle(&self, request: Request) -> Result<u32> {
+        let deadline = Deadline::after(self.timeout);
         let mut client = make_database_client(&self.client)?.inner;
-        let request = self.to_rpc_request(request);
-        let response = client.handle(request).await?.into_inner();
+        let request = tonic_request(self.to_rpc_request(request), deadline);
+        let response = until_deadline(deadline, client.handle(request))
+            .await??
+            .into_inner();
         from_grpc_response(response)
     }
 
+    /// Returns a [StreamInserter] sending inserts in one stream, buffering up to
+    /// `channel_size` batches.
+    pub fn streaming_inserter(&self, channel_size: usize) -> Result<StreamInserter> {
+        let client = make_database_client(&self.client)?.inner;
+        Ok(StreamInserter::new(
+            client,
+            self.request_header(),
+            channel_size,
+        ))
+    }
+
     #[inline]
     fn to_rpc_request(&self, request: Request) -> GreptimeRequest {
         GreptimeRequest {
-            header: Some(RequestHeader {
-                catalog: self.catalog.clone(),
-                schema: self.schema.clone(),
-                authorization: self.ctx.auth_header.clone(),
-                dbname: self.dbname.clone(),
-                timezone: self.timezone.clone(),
-                // TODO(Taylor-lagrange): add client grpc tracing
-                tracing_context: W3cTrace::new(),
-            }),
+            header: Some(self.request_header()),
             request: Some(request),
         }
     }
 
+    fn request_header(&self) -> RequestHeader {
+        RequestHeader {
+            catalog: self.catalog.clone(),
+            schema: self.schema.clone(),
+            authorization: self.ctx.auth_header.clone(),
+            dbname: self.dbname.clone(),
+            timezone: self.timezone.clone(),
+            // TODO(Taylor-lagrange): add client grpc tracing
+            tracing_context: W3cTrace::new(),
+        }
+    }
+
     pub async fn sql<S>(&self, sql: S) -> Result<Output>
     where
         S: AsRef<str>,
@@ -197,6 +252,66 @@ impl Database {
         .await
     }
 
+    /// Executes the query and returns its result as a stream of record batches.
+    ///
+    /// Record batches are decoded from the Flight stream as the returned stream is polled, and
+    /// the server stops sending more once the gRPC flow control window fills up, so a large
+    /// result can be processed incrementally without being buffered in memory.
+    pub async fn sql_stream<S>(&self, sql: S) -> Result<SendableRecordBatchStream>
+    where
+        S: AsRef<str>,
+    {
+        let output = self.sql(sql).await?;
+        match output.data {
+            OutputData::Stream(stream) => Ok(stream),
+            OutputData::RecordBatches(record_batches) => Ok(record_batches.as_stream()),
+            OutputData::AffectedRows(rows) => IllegalDatabaseResponseSnafu {
+                err_msg: format!("Expect record batches from the query, got {rows} affected rows"),
+            }
+            .fail(),
+        }
+    }
+
+    /// Executes the query and maps each row of its result into `T`.
+    pub async fn query_as<T, S>(&self, sql: S) -> Result<Vec<T>>
+    where
+        T: FromRow,
+        S: AsRef<str>,
+    {
+        let mut stream = self.sql_stream(sql).await?;
+        let mut rows = vec![];
+        while let Some(record_batch) = stream.next().await {
+            let record_batch = record_batch.context(CollectRecordBatchesSnafu)?;
+            rows.extend(rows_as(&record_batch)?);
+        }
+        Ok(rows)
+    }
+
+    /// Evaluates the PromQL expression over a range of time, with the same semantics as the
+    /// `/api/v1/query_range` API of Prometheus.
+    ///
+    /// Each series is returned as rows of its labels, the evaluation timestamp and the value.
+    pub async fn promql_range_query(&self, query: PromRangeQuery) -> Result<Output> {
+        self.do_get(Request::Query(QueryRequest {
+            query: Some(Query::PromRangeQuery(query)),
+        }))
+        .await
+    }
+
+    /// Evaluates the PromQL expression at a single point in time, with the same semantics as
+    /// the `/api/v1/query` API of Prometheus.
+    pub async fn promql_instant_query(&self, query: PromInstantQuery) -> Result<Output> {
+        // an instant query is a range query with a single evaluation step
+        self.promql_range_query(PromRangeQuery {
+            query: query.query,
+            start: query.time.clone(),
+            end: query.time,
+            step: "1s".to_string(),
+            lookback: query.lookback,
+        })
+        .await
+    }
+
     pub async fn logical_plan(&self, logical_plan: Vec<u8>) -> Result<Output> {
         self.do_get(Request::Query(QueryRequest {
             query: Some(Query::LogicalPlan(logical_plan)),
@@ -218,36 +333,91 @@ impl Database {
         .await
     }
 
-    async fn do_get(&self, request: Request) -> Result<Output> {
-        let request = self.to_rpc_request(request);
-        let request = Ticket {
-            ticket: request.encode_to_vec().into(),
-        };
+    /// Uploads the record batches into the table by Flight DoPut, without encoding them into
+    /// protobuf rows.
+    ///
+    /// The server rejects the upload if the `schema` doesn't fit the table, otherwise returns the
+    /// result of inserting each record batch in order.
+    pub async fn put_record_batches<S>(
+        &self,
+        table_name: &str,
+        schema: SchemaRef,
+        record_batches: S,
+    ) -> Result<Vec<Result<u32>>>
+    where
+        S: Stream<Item = RecordBatch> + Send + 'static,
+    {
+        let deadline = Deadline::after(self.timeout);
+        let mut encoder = FlightEncoder::default();
+        let mut first = encoder.encode(FlightMessage::Schema(schema));
+        first.flight_descriptor = Some(do_put_descriptor(&self.request_header(), table_name));
+        let flight_data = futures_util::stream::once(async move { first }).chain(
+            record_batches
+                .map(move |record_batch| encoder.encode(FlightMessage::Recordbatch(record_batch))),
+        );
 
         let mut client = self.client.make_flight_client()?;
-
-        let response = client.mut_inner().do_get(request).await.or_else(|e| {
+        let addr = client.addr().to_string();
+        let request = tonic_request(flight_data, deadline);
+        let send = client.mut_inner().do_put(request);
+        let response = until_deadline(deadline, send).await?.or_else(|e| {
             let tonic_code = e.code();
             let e: Error = e.into();
-            let code = e.status_code();
-            let msg = e.to_string();
-            let error =
-                Err(BoxedError::new(ServerSnafu { code, msg }.build())).with_context(|_| {
-                    FlightGetSnafu {
-                        addr: client.addr().to_string(),
-                        tonic_code,
-                    }
-                });
-            error!(
-                "Failed to do Flight get, addr: {}, code: {}, source: {:?}",
-                client.addr(),
+            Err(BoxedError::new(e)).context(FlightPutSnafu {
+                addr: &addr,
                 tonic_code,
-                error
-            );
-            error
+            })
         })?;
 
-        let flight_data_stream = response.into_inner();
+        let mut put_results = response.into_inner();
+        let mut results = vec![];
+        while let Some(put_result) = until_deadline(deadline, put_results.next()).await? {
+            let response = decode_put_result(put_result?).context(ConvertFlightDataSnafu)?;
+            results.push(from_grpc_response(response));
+        }
+        Ok(results)
+    }
+
+    async fn send_ticket(
+        &self,
+        ticket: Ticket,
+        deadline: Option<Deadline>,
+    ) -> Result<Streaming<FlightData>> {
+        let mut client = self.client.make_flight_client()?;
+
+        let addr = client.addr().to_string();
+        let send = async {
+            let request = tonic_request(ticket, deadline);
+            client.mut_inner().do_get(request).await.or_else(|e| {
+                let tonic_code = e.code();
+                let e: Error = e.into();
+                let error = Err(BoxedError::new(e)).context(FlightGetSnafu {
+                    addr: &addr,
+                    tonic_code,
+                });
+                error!(
+                    "Failed to do Flight get, addr: {}, code: {}, source: {:?}",
+                    addr, tonic_code, error
+                );
+                error
+            })
+        };
+        let response = self.client.track(&addr, send).await?;
+        Ok(response.into_inner())
+    }
+
+    async fn do_get(&self, request: Request) -> Result<Output> {
+        let deadline = Deadline::after(self.timeout);
+        let request = self.to_rpc_request(request);
+        let request = Ticket {
+            ticket: request.encode_to_vec().into(),
+        };
+
+        let send = self
+            .client
+            .retry(request, |ticket| self.send_ticket(ticket, deadline));
+        let flight_data_stream = until_deadline(deadline, send).await??;
+
         let mut decoder = FlightDecoder::default();
 
         let mut flight_message_stream = flight_data_stream.map(move |flight_data| {
@@ -256,7 +426,9 @@ impl Database {
                 .and_then(|data| decoder.try_decode(data).context(ConvertFlightDataSnafu))
         });
 
-        let Some(first_flight_message) = flight_message_stream.next().await else {
+        let Some(first_flight_message) =
+            until_deadline(deadline, flight_message_stream.next()).await?
+        else {
             return IllegalFlightMessagesSnafu {
                 reason: "Expect the response not to be empty",
             }
@@ -283,7 +455,16 @@ impl Database {
             }
             FlightMessage::Schema(schema) => {
                 let stream = Box::pin(stream!({
-                    while let Some(flight_message) = flight_message_stream.next().await {
+                    loop {
+                        let next = until_deadline(deadline, flight_message_stream.next()).await;
+                        let flight_message = match next {
+                            Ok(Some(flight_message)) => flight_message,
+                            Ok(None) => break,
+                            Err(e) => {
+                                yield Err(BoxedError::new(e)).context(ExternalSnafu);
+                                break;
+                            }
+                        };
                         let flight_message = flight_message
                             .map_err(BoxedError::new)
                             .context(ExternalSnafu)?;
@@ -317,6 +498,45 @@ struct FlightContext {
     auth_header: Option<AuthHeader>,
 }
 
+/// The deadline of a call, by the timeout of the [Database].
+#[derive(Debug, Clone, Copy)]
+struct Deadline {
+    timeout: Duration,
+    instant: Instant,
+}
+
+impl Deadline {
+    fn after(timeout: Option<Duration>) -> Option<Self> {
+        timeout.map(|timeout| Self {
+            timeout,
+            instant: Instant::now() + timeout,
+        })
+    }
+}
+
+/// Builds a request carrying the time left to the `deadline`, so the server can give up the
+/// request once the client does.
+fn tonic_request<T>(message: T, deadline: Option<Deadline>) -> tonic::Request<T> {
+    let mut request = tonic::Request::new(message);
+    if let Some(deadline) = deadline {
+        request.set_timeout(deadline.instant.saturating_duration_since(Instant::now()));
+    }
+    request
+}
+
+/// Awaits the `fut`, failed by [Error::DeadlineExceeded] if it's not ready by the `deadline`.
+async fn until_deadline<F: Future>(deadline: Option<Deadline>, fut: F) -> Result<F::Output> {
+    let Some(deadline) = deadline else {
+        return Ok(fut.await);
+    };
+    tokio::time::timeout_at(deadline.instant, fut)
+        .await
+        .ok()
+        .context(DeadlineExceededSnafu {
+            timeout: deadline.timeout,
+        })
+}
+
 #[cfg(test)]
 mod tests {
     use std::assert_matches::assert_matches;
@@ -326,6 +546,26 @@ mod tests {
 
     use super::*;
 
+    #[tokio::test]
+    async fn test_until_deadline() {
+        assert_eq!(until_deadline(None, async { 1 }).await.unwrap(), 1);
+
+        let deadline = Deadline::after(Some(Duration::from_secs(10)));
+        assert_eq!(until_deadline(deadline, async { 1 }).await.unwrap(), 1);
+
+        let deadline = Deadline::after(Some(Duration::from_millis(10)));
+        let err = until_deadline(deadline, futures_util::future::pending::<()>())
+            .await
+            .unwrap_err();
+        assert!(err.is_deadline_exceeded());
+        assert!(!err.should_retry());
+
+        let request = tonic_request((), Deadline::after(Some(Duration::from_secs(10))));
+        assert!(request.metadata().get("grpc-timeout").is_some());
+        let request = tonic_request((), None);
+        assert!(request.metadata().get("grpc-timeout").is_none());
+    }
+
     #[test]
     fn test_flight_ctx() {
         let mut ctx = FlightContext::default();
@@ -13,10 +13,11 @@
 // limitations under the License.
 
 use std::any::Any;
+use std::time::Duration;
 
-use common_error::ext::{BoxedError, ErrorExt};
+use common_error::error_info::ErrorInfo;
+use common_error::ext::{BoxedError, ErrorExt, RemoteStackError};
 use common_error::status_code::StatusCode;
-use common_error::{GREPTIME_DB_HEADER_ERROR_CODE, GREPTIME_DB_HEADER_ERROR_MSG};
 use common_macro::stack_trace_debug;
 use snafu::{location, Location, Snafu};
 use tonic::{Code, Status};
@@ -39,6 +40,13 @@ pub enum Error {
         source: BoxedError,
     },
 
+    #[snafu(display("Failed to do Flight put, code: {}", tonic_code))]
+    FlightPut {
+        addr: String,
+        tonic_code: Code,
+        source: BoxedError,
+    },
+
     #[snafu(display("Failed to convert FlightData"))]
     ConvertFlightData {
         #[snafu(implicit)]
@@ -98,6 +106,7 @@ pub enum Error {
     Server {
         code: StatusCode,
         msg: String,
+        source: RemoteStackError,
         #[snafu(implicit)]
         location: Location,
     },
@@ -109,12 +118,61 @@ pub enum Error {
         location: Location,
     },
 
+    #[snafu(display("Failed to stream requests: {}", err_msg))]
+    ClientStreaming {
+        err_msg: String,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
     #[snafu(display("Failed to parse ascii string: {}", value))]
     InvalidAscii {
         value: String,
         #[snafu(implicit)]
         location: Location,
     },
+
+    #[snafu(display("Column {} not found in query result", column))]
+    ColumnNotFound {
+        column: String,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display(
+        "Failed to convert value {} of column {} into {}",
+        value,
+        column,
+        expected
+    ))]
+    ConvertValue {
+        column: String,
+        expected: String,
+        value: String,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display("Failed to collect record batches"))]
+    CollectRecordBatches {
+        #[snafu(implicit)]
+        location: Location,
+        source: common_recordbatch::error::Error,
+    },
+
+    #[snafu(display("Circuit breaker of peer {} is open", addr))]
+    CircuitOpen {
+        addr: String,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display("Request deadline exceeded, timeout: {:?}", timeout))]
+    DeadlineExceeded {
+        timeout: Duration,
+        #[snafu(implicit)]
+        location: Location,
+    },
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -124,10 +182,12 @@ impl ErrorExt for Error {
         match self {
             Error::IllegalFlightMessages { .. }
             | Error::MissingField { .. }
-            | Error::IllegalDatabaseResponse { .. } => StatusCode::Internal,
+            | Error::IllegalDatabaseResponse { .. }
+            | Error::ClientStreaming { .. } => StatusCode::Internal,
 
             Error::Server { code, .. } => *code,
             Error::FlightGet { source, .. }
+            | Error::FlightPut { source, .. }
             | Error::RegionServer { source, .. }
             | Error::FlowServer { source, .. } => source.status_code(),
             Error::CreateChannel { source, .. }
@@ -135,7 +195,11 @@ impl ErrorExt for Error {
             | Error::CreateTlsChannel { source, .. } => source.status_code(),
             Error::IllegalGrpcClientState { .. } => StatusCode::Unexpected,
 
-            Error::InvalidAscii { .. } => StatusCode::InvalidArguments,
+            Error::InvalidAscii { .. }
+            | Error::ColumnNotFound { .. }
+            | Error::ConvertValue { .. } => StatusCode::InvalidArguments,
+            Error::CollectRecordBatches { source, .. } => source.status_code(),
+            Error::DeadlineExceeded { .. } | Error::CircuitOpen { .. } => StatusCode::Cancelled,
         }
     }
 
@@ -146,27 +210,14 @@ impl ErrorExt for Error {
 
 impl From<Status> for Error {
     fn from(e: Status) -> Self {
-        fn get_metadata_value(e: &Status, key: &str) -> Option<String> {
-            e.metadata()
-                .get(key)
-                .and_then(|v| String::from_utf8(v.as_bytes().to_vec()).ok())
-        }
-
-        let code = get_metadata_value(&e, GREPTIME_DB_HEADER_ERROR_CODE)
-            .and_then(|s| {
-                if let Ok(code) = s.parse::<u32>() {
-                    StatusCode::from_u32(code)
-                } else {
-                    None
-                }
-            })
-            .unwrap_or(StatusCode::Unknown);
-
-        let msg = get_metadata_value(&e, GREPTIME_DB_HEADER_ERROR_MSG)
-            .unwrap_or_else(|| e.message().to_string());
+        let info = ErrorInfo::from_status(&e);
+        let code = info.code.unwrap_or(StatusCode::Unknown);
+        let msg = info.msg.unwrap_or_else(|| e.message().to_string());
 
         Self::Server {
             code,
+            source: RemoteStackError::new(msg.clone(), info.stack, info.hops)
+                .with_frames(info.frames),
             msg,
             location: location!(),
         }
@@ -174,23 +225,165 @@ impl From<Status> for Error {
 }
 
 impl Error {
-    pub fn should_retry(&self) -> bool {
-        // TODO(weny): figure out each case of these codes.
-        matches!(
-            self,
-            Self::RegionServer {
-                code: Code::Cancelled,
+    /// Returns `true` if the request failed by the error may succeed if it's sent again.
+    ///
+    /// Errors from servers are retried by the [StatusCode] they carry, or by their tonic code if
+    /// the server didn't tell, e.g. it's unreachable.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::RegionServer { code, source, .. }
+            | Error::FlowServer { code, source, .. }
+            | Error::FlightGet {
+                tonic_code: code,
+                source,
                 ..
-            } | Self::RegionServer {
-                code: Code::DeadlineExceeded,
+            }
+            | Error::FlightPut {
+                tonic_code: code,
+                source,
                 ..
-            } | Self::RegionServer {
-                code: Code::Unavailable,
+            } => match source.status_code() {
+                StatusCode::Unknown => matches!(
+                    code,
+                    Code::Cancelled | Code::DeadlineExceeded | Code::Unavailable | Code::Unknown
+                ),
+                status_code => status_code.is_retryable(),
+            },
+            Error::Server { code, .. } => code.is_retryable(),
+
+            Error::IllegalFlightMessages { .. }
+            | Error::ConvertFlightData { .. }
+            | Error::IllegalGrpcClientState { .. }
+            | Error::MissingField { .. }
+            | Error::CreateChannel { .. }
+            | Error::CreateTlsChannel { .. }
+            | Error::IllegalDatabaseResponse { .. }
+            | Error::ClientStreaming { .. }
+            | Error::InvalidAscii { .. }
+            | Error::ColumnNotFound { .. }
+            | Error::ConvertValue { .. }
+            | Error::CollectRecordBatches { .. } => false,
+
+            // no time is left for another attempt
+            Error::DeadlineExceeded { .. } => false,
+            // fails fast until the cooldown passes
+            Error::CircuitOpen { .. } => false,
+        }
+    }
+
+    /// Returns `true` if the request didn't finish before its deadline, either the timeout of
+    /// the call or the timeout the server enforces.
+    pub fn is_deadline_exceeded(&self) -> bool {
+        match self {
+            Error::DeadlineExceeded { .. } => true,
+            Error::RegionServer { code, .. }
+            | Error::FlowServer { code, .. }
+            | Error::FlightGet {
+                tonic_code: code, ..
+            }
+            | Error::FlightPut {
+                tonic_code: code, ..
+            } => *code == Code::DeadlineExceeded,
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if the server didn't respond, e.g. it's unreachable.
+    pub fn is_transport_error(&self) -> bool {
+        match self {
+            Error::RegionServer { code, source, .. }
+            | Error::FlowServer { code, source, .. }
+            | Error::FlightGet {
+                tonic_code: code,
+                source,
                 ..
-            } | Self::RegionServer {
-                code: Code::Unknown,
+            }
+            | Error::FlightPut {
+                tonic_code: code,
+                source,
                 ..
+            } => *code == Code::Unavailable && source.status_code() == StatusCode::Unknown,
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if the request should be retried, see [Error::is_retryable].
+    pub fn should_retry(&self) -> bool {
+        self.is_retryable()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use snafu::IntoError;
+
+    use super::*;
+
+    fn region_server_error(code: Code, status_code: StatusCode) -> Error {
+        RegionServerSnafu {
+            addr: "127.0.0.1:4001",
+            code,
+        }
+        .into_error(BoxedError::new(
+            ServerSnafu {
+                code: status_code,
+                msg: "mock",
             }
-        )
+            .into_error(RemoteStackError::new("mock".to_string(), vec![], vec![])),
+        ))
+    }
+
+    #[test]
+    fn test_is_retryable() {
+        // decided by the status code from the server
+        for status_code in [
+            StatusCode::RegionNotReady,
+            StatusCode::TableUnavailable,
+            StatusCode::StorageUnavailable,
+        ] {
+            assert!(region_server_error(Code::Internal, status_code).is_retryable());
+        }
+        assert!(
+            !region_server_error(Code::Unavailable, StatusCode::InvalidArguments).is_retryable()
+        );
+
+        // decided by the tonic code if the server didn't tell
+        assert!(region_server_error(Code::Unavailable, StatusCode::Unknown).is_retryable());
+        assert!(!region_server_error(Code::InvalidArgument, StatusCode::Unknown).is_retryable());
+
+        assert!(ServerSnafu {
+            code: StatusCode::RegionBusy,
+            msg: "mock",
+        }
+        .into_error(RemoteStackError::new("mock".to_string(), vec![], vec![]))
+        .is_retryable());
+        assert!(!IllegalGrpcClientStateSnafu { err_msg: "mock" }
+            .build()
+            .is_retryable());
+    }
+
+    #[test]
+    fn test_is_deadline_exceeded() {
+        let err = DeadlineExceededSnafu {
+            timeout: Duration::from_secs(1),
+        }
+        .build();
+        assert!(err.is_deadline_exceeded());
+        assert!(!err.is_retryable());
+
+        let err = region_server_error(Code::DeadlineExceeded, StatusCode::Unknown);
+        assert!(err.is_deadline_exceeded());
+        assert!(err.is_retryable());
+        assert!(!region_server_error(Code::Unavailable, StatusCode::Unknown).is_deadline_exceeded());
+    }
+
+    #[test]
+    fn test_is_transport_error() {
+        assert!(region_server_error(Code::Unavailable, StatusCode::Unknown).is_transport_error());
+        assert!(
+            !region_server_error(Code::Unavailable, StatusCode::RegionNotReady)
+                .is_transport_error()
+        );
+        assert!(!region_server_error(Code::Internal, StatusCode::Unknown).is_transport_error());
     }
 }
@@ -44,6 +44,9 @@ impl Flownode for FlowRequester {
             .map_err(BoxedError::new)
             .context(common_meta::error::ExternalSnafu)
     }
+
+    // TODO: support altering, pausing, resuming, resetting and explaining flows on remote
+    // flownodes once they are in the proto
 }
 
 impl FlowRequester {
@@ -52,23 +55,26 @@ impl FlowRequester {
     }
 
     async fn handle_inner(&self, request: FlowRequest) -> Result<FlowResponse> {
+        self.client
+            .retry(request, |request| self.send_request(request))
+            .await
+    }
+
+    async fn send_request(&self, request: FlowRequest) -> Result<FlowResponse> {
         let (addr, mut client) = self.client.raw_flow_client()?;
 
-        let response = client
-            .handle_create_remove(request)
-            .await
-            .or_else(|e| {
+        let send = async {
+            client.handle_create_remove(request).await.or_else(|e| {
                 let code = e.code();
                 let err: crate::error::Error = e.into();
-                Err(BoxedError::new(err)).context(FlowServerSnafu { addr, code })
-            })?
-            .into_inner();
+                Err(BoxedError::new(err)).context(FlowServerSnafu { addr: &addr, code })
+            })
+        };
+        let response = self.client.track(&addr, send).await?.into_inner();
         Ok(response)
     }
 
     async fn handle_inserts_inner(&self, request: InsertRequests) -> Result<FlowResponse> {
-        let (addr, mut client) = self.client.raw_flow_client()?;
-
         let requests = api::v1::flow::InsertRequests {
             requests: request
                 .requests
@@ -79,16 +85,22 @@ impl FlowRequester {
                 })
                 .collect(),
         };
-
-        let response = client
-            .handle_mirror_request(requests)
+        self.client
+            .retry(requests, |requests| self.send_inserts(requests))
             .await
-            .or_else(|e| {
+    }
+
+    async fn send_inserts(&self, requests: api::v1::flow::InsertRequests) -> Result<FlowResponse> {
+        let (addr, mut client) = self.client.raw_flow_client()?;
+
+        let send = async {
+            client.handle_mirror_request(requests).await.or_else(|e| {
                 let code = e.code();
                 let err: crate::error::Error = e.into();
-                Err(BoxedError::new(err)).context(FlowServerSnafu { addr, code })
-            })?
-            .into_inner();
+                Err(BoxedError::new(err)).context(FlowServerSnafu { addr: &addr, code })
+            })
+        };
+        let response = self.client.track(&addr, send).await?.into_inner();
         Ok(response)
     }
 }
@@ -14,6 +14,7 @@
 
 #![feature(assert_matches)]
 
+pub mod circuit_breaker;
 mod client;
 pub mod client_manager;
 #[cfg(feature = "testing")]
@@ -23,20 +24,31 @@ pub mod flow;
 pub mod load_balance;
 mod metrics;
 pub mod region;
+pub mod retry;
+pub mod row;
+#[cfg(feature = "testing")]
+mod stream_insert;
 
 pub use api;
 use api::v1::greptime_response::Response;
 use api::v1::{AffectedRows, GreptimeResponse};
 pub use common_catalog::consts::{DEFAULT_CATALOG_NAME, DEFAULT_SCHEMA_NAME};
+use common_error::ext::RemoteStackError;
 use common_error::status_code::StatusCode;
 pub use common_query::{Output, OutputData, OutputMeta};
 pub use common_recordbatch::{RecordBatches, SendableRecordBatchStream};
-use snafu::OptionExt;
+use snafu::{IntoError, OptionExt};
+pub use tonic::codec::CompressionEncoding;
 
+pub use self::circuit_breaker::CircuitBreakerConfig;
 pub use self::client::Client;
 #[cfg(feature = "testing")]
 pub use self::database::Database;
 pub use self::error::{Error, Result};
+pub use self::retry::RetryPolicy;
+pub use self::row::{FromRow, FromValue, Row};
+#[cfg(feature = "testing")]
+pub use self::stream_insert::{StreamInsertSummary, StreamInserter};
 use crate::error::{IllegalDatabaseResponseSnafu, ServerSnafu};
 
 pub fn from_grpc_response(response: GreptimeResponse) -> Result<u32> {
@@ -59,10 +71,10 @@ pub fn from_grpc_response(response: GreptimeResponse) -> Result<u32> {
             StatusCode::from_u32(status.status_code).context(IllegalDatabaseResponseSnafu {
                 err_msg: format!("invalid status: {:?}", status),
             })?;
-        ServerSnafu {
+        Err(ServerSnafu {
             code: status_code,
-            msg: status.err_msg,
+            msg: status.err_msg.clone(),
         }
-        .fail()
+        .into_error(RemoteStackError::new(status.err_msg, vec![], vec![])))
     }
 }
@@ -12,18 +12,35 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+//! Distributing requests of a client among its peers.
+//!
+//! Peers failed by transport errors are marked as unhealthy and skipped for a while, unless no
+//! peer is healthy.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
 use enum_dispatch::enum_dispatch;
+use parking_lot::{Mutex, RwLock};
 use rand::seq::SliceRandom;
+use rand::Rng;
+
+/// How long a peer failed by a transport error is skipped.
+pub const DEFAULT_UNHEALTHY_DURATION: Duration = Duration::from_secs(10);
 
 #[enum_dispatch]
 pub trait LoadBalance {
-    fn get_peer<'a>(&self, peers: &'a [String]) -> Option<&'a String>;
+    fn get_peer<'a>(&self, peers: &'a [String], endpoints: &Endpoints) -> Option<&'a String>;
 }
 
 #[enum_dispatch(LoadBalance)]
 #[derive(Debug)]
 pub enum Loadbalancer {
     Random,
+    RoundRobin,
+    LeastOutstanding,
 }
 
 impl Default for Loadbalancer {
@@ -36,31 +53,236 @@ impl Default for Loadbalancer {
 pub struct Random;
 
 impl LoadBalance for Random {
-    fn get_peer<'a>(&self, peers: &'a [String]) -> Option<&'a String> {
+    fn get_peer<'a>(&self, peers: &'a [String], _endpoints: &Endpoints) -> Option<&'a String> {
         peers.choose(&mut rand::thread_rng())
     }
 }
 
+/// Picks peers in turn.
+#[derive(Debug, Default)]
+pub struct RoundRobin {
+    next: AtomicUsize,
+}
+
+impl LoadBalance for RoundRobin {
+    fn get_peer<'a>(&self, peers: &'a [String], _endpoints: &Endpoints) -> Option<&'a String> {
+        if peers.is_empty() {
+            return None;
+        }
+        let next = self.next.fetch_add(1, Ordering::Relaxed);
+        peers.get(next % peers.len())
+    }
+}
+
+/// Picks the peer with the fewest requests in flight, randomly among ties.
+#[derive(Debug, Default)]
+pub struct LeastOutstanding;
+
+impl LoadBalance for LeastOutstanding {
+    fn get_peer<'a>(&self, peers: &'a [String], endpoints: &Endpoints) -> Option<&'a String> {
+        if peers.is_empty() {
+            return None;
+        }
+        // starts from a random peer so ties are spread
+        let start = rand::thread_rng().gen_range(0..peers.len());
+        peers
+            .iter()
+            .cycle()
+            .skip(start)
+            .take(peers.len())
+            .min_by_key(|peer| endpoints.outstanding(peer))
+    }
+}
+
+#[derive(Debug, Default)]
+struct EndpointState {
+    outstanding: AtomicUsize,
+    unhealthy_until: Mutex<Option<Instant>>,
+}
+
+/// The requests in flight and the health of peers, observed from requests sent to them.
+#[derive(Debug)]
+pub struct Endpoints {
+    states: RwLock<HashMap<String, Arc<EndpointState>>>,
+    unhealthy_duration: Duration,
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Self::new(DEFAULT_UNHEALTHY_DURATION)
+    }
+}
+
+impl Endpoints {
+    pub fn new(unhealthy_duration: Duration) -> Self {
+        Self {
+            states: RwLock::new(HashMap::new()),
+            unhealthy_duration,
+        }
+    }
+
+    fn state(&self, peer: &str) -> Arc<EndpointState> {
+        if let Some(state) = self.states.read().get(peer) {
+            return state.clone();
+        }
+        self.states
+            .write()
+            .entry(peer.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// Returns the number of requests in flight to the peer.
+    pub fn outstanding(&self, peer: &str) -> usize {
+        self.states
+            .read()
+            .get(peer)
+            .map_or(0, |state| state.outstanding.load(Ordering::Relaxed))
+    }
+
+    /// Counts a request in flight to the peer until the returned guard is dropped.
+    pub fn start_request(&self, peer: &str) -> OutstandingGuard {
+        let state = self.state(peer);
+        let _ = state.outstanding.fetch_add(1, Ordering::Relaxed);
+        OutstandingGuard { state }
+    }
+
+    /// Returns `false` if the peer failed by a transport error recently.
+    pub fn is_healthy(&self, peer: &str) -> bool {
+        self.states.read().get(peer).map_or(true, |state| {
+            state
+                .unhealthy_until
+                .lock()
+                .map_or(true, |until| until <= Instant::now())
+        })
+    }
+
+    /// Skips the peer for a while since it failed by a transport error.
+    pub fn mark_unhealthy(&self, peer: &str) {
+        *self.state(peer).unhealthy_until.lock() = Some(Instant::now() + self.unhealthy_duration);
+    }
+
+    /// Marks the peer as healthy since it responded.
+    pub fn mark_healthy(&self, peer: &str) {
+        if let Some(state) = self.states.read().get(peer) {
+            *state.unhealthy_until.lock() = None;
+        }
+    }
+
+    /// Returns the healthy ones of `peers`, or all of them if none is healthy.
+    pub fn healthy_peers(&self, peers: &[String]) -> Vec<String> {
+        let healthy = peers
+            .iter()
+            .filter(|peer| self.is_healthy(peer))
+            .cloned()
+            .collect::<Vec<_>>();
+        if healthy.is_empty() {
+            peers.to_vec()
+        } else {
+            healthy
+        }
+    }
+}
+
+/// Counts a request in flight until it's dropped.
+pub struct OutstandingGuard {
+    state: Arc<EndpointState>,
+}
+
+impl Drop for OutstandingGuard {
+    fn drop(&mut self) {
+        let _ = self.state.outstanding.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashSet;
 
-    use super::{LoadBalance, Random};
+    use super::*;
 
-    #[test]
-    fn test_random_lb() {
-        let peers = vec![
+    fn mock_peers() -> Vec<String> {
+        vec![
             "127.0.0.1:3001".to_string(),
             "127.0.0.1:3002".to_string(),
             "127.0.0.1:3003".to_string(),
             "127.0.0.1:3004".to_string(),
-        ];
+        ]
+    }
+
+    #[test]
+    fn test_random_lb() {
+        let peers = mock_peers();
         let all: HashSet<String> = peers.clone().into_iter().collect();
 
         let random = Random;
+        let endpoints = Endpoints::default();
         for _ in 0..100 {
-            let peer = random.get_peer(&peers).unwrap();
+            let peer = random.get_peer(&peers, &endpoints).unwrap();
             assert!(all.contains(peer));
         }
     }
+
+    #[test]
+    fn test_round_robin_lb() {
+        let peers = mock_peers();
+        let round_robin = RoundRobin::default();
+        let endpoints = Endpoints::default();
+        for i in 0..8 {
+            let peer = round_robin.get_peer(&peers, &endpoints).unwrap();
+            assert_eq!(peer, &peers[i % peers.len()]);
+        }
+        assert!(round_robin.get_peer(&[], &endpoints).is_none());
+    }
+
+    #[test]
+    fn test_least_outstanding_lb() {
+        let peers = mock_peers();
+        let least_outstanding = LeastOutstanding;
+        let endpoints = Endpoints::default();
+
+        let mut guards = vec![];
+        for peer in &peers[..3] {
+            guards.push(endpoints.start_request(peer));
+        }
+        for _ in 0..10 {
+            let peer = least_outstanding.get_peer(&peers, &endpoints).unwrap();
+            assert_eq!(peer, &peers[3]);
+        }
+
+        guards.push(endpoints.start_request(&peers[3]));
+        guards.push(endpoints.start_request(&peers[3]));
+        // a finished request isn't counted
+        guards.swap_remove(1);
+        assert_eq!(endpoints.outstanding(&peers[1]), 0);
+        for _ in 0..10 {
+            let peer = least_outstanding.get_peer(&peers, &endpoints).unwrap();
+            assert_eq!(peer, &peers[1]);
+        }
+    }
+
+    #[test]
+    fn test_endpoint_health() {
+        let peers = mock_peers();
+        let endpoints = Endpoints::default();
+        assert_eq!(endpoints.healthy_peers(&peers), peers);
+
+        endpoints.mark_unhealthy(&peers[0]);
+        assert!(!endpoints.is_healthy(&peers[0]));
+        assert_eq!(endpoints.healthy_peers(&peers), peers[1..].to_vec());
+
+        endpoints.mark_healthy(&peers[0]);
+        assert_eq!(endpoints.healthy_peers(&peers), peers);
+
+        // all peers are candidates if none is healthy
+        for peer in &peers {
+            endpoints.mark_unhealthy(peer);
+        }
+        assert_eq!(endpoints.healthy_peers(&peers), peers);
+
+        // unhealthy peers are candidates again after a while
+        let endpoints = Endpoints::new(Duration::ZERO);
+        endpoints.mark_unhealthy(&peers[0]);
+        assert!(endpoints.is_healthy(&peers[0]));
+    }
 }
@@ -39,6 +39,13 @@ lazy_static! {
         register_histogram!("greptime_grpc_truncate_table", "grpc truncate table").unwrap();
     pub static ref METRIC_GRPC_DO_GET: Histogram =
         register_histogram!("greptime_grpc_do_get", "grpc do get").unwrap();
+    pub static ref METRIC_GRPC_RETRY: IntCounter =
+        register_int_counter!("greptime_grpc_retry", "grpc retry").unwrap();
+    pub static ref METRIC_GRPC_CIRCUIT_BREAKER_OPEN: IntCounter = register_int_counter!(
+        "greptime_grpc_circuit_breaker_open",
+        "grpc circuit breaker open"
+    )
+    .unwrap();
     pub static ref METRIC_REGION_REQUEST_GRPC: HistogramVec = register_histogram_vec!(
         "greptime_grpc_region_request",
         "grpc region request",
@@ -18,10 +18,10 @@ use api::region::RegionResponse;
 use api::v1::region::RegionRequest;
 use api::v1::ResponseHeader;
 use arc_swap::ArcSwapOption;
-use arrow_flight::Ticket;
+use arrow_flight::{FlightData, Ticket};
 use async_stream::stream;
 use async_trait::async_trait;
-use common_error::ext::{BoxedError, ErrorExt};
+use common_error::ext::{BoxedError, RemoteStackError};
 use common_error::status_code::StatusCode;
 use common_grpc::flight::{FlightDecoder, FlightMessage};
 use common_meta::error::{self as meta_error, Result as MetaResult};
@@ -33,9 +33,10 @@ use common_telemetry::error;
 use common_telemetry::tracing_context::TracingContext;
 use prost::Message;
 use query::query_engine::DefaultSerializer;
-use snafu::{location, OptionExt, ResultExt};
+use snafu::{location, IntoError, OptionExt, ResultExt};
 use substrait::{DFLogicalSubstraitConvertor, SubstraitPlan};
 use tokio_stream::StreamExt;
+use tonic::Streaming;
 
 use crate::error::{
     self, ConvertFlightDataSnafu, FlightGetSnafu, IllegalDatabaseResponseSnafu,
@@ -93,33 +94,10 @@ impl RegionRequester {
     }
 
     pub async fn do_get_inner(&self, ticket: Ticket) -> Result<SendableRecordBatchStream> {
-        let mut flight_client = self.client.make_flight_client()?;
-        let response = flight_client
-            .mut_inner()
-            .do_get(ticket)
-            .await
-            .map_err(|e| {
-                let tonic_code = e.code();
-                let e: error::Error = e.into();
-                let code = e.status_code();
-                let msg = e.to_string();
-                let error = ServerSnafu { code, msg }
-                    .fail::<()>()
-                    .map_err(BoxedError::new)
-                    .with_context(|_| FlightGetSnafu {
-                        tonic_code,
-                        addr: flight_client.addr().to_string(),
-                    })
-                    .unwrap_err();
-                error!(
-                    e; "Failed to do Flight get, addr: {}, code: {}",
-                    flight_client.addr(),
-                    tonic_code
-                );
-                error
-            })?;
-
-        let flight_data_stream = response.into_inner();
+        let flight_data_stream = self
+            .client
+            .retry(ticket, |ticket| self.send_ticket(ticket))
+            .await?;
         let mut decoder = FlightDecoder::default();
 
         let mut flight_message_stream = flight_data_stream.map(move |flight_data| {
@@ -183,6 +161,31 @@ impl RegionRequester {
         Ok(Box::pin(record_batch_stream))
     }
 
+    async fn send_ticket(&self, ticket: Ticket) -> Result<Streaming<FlightData>> {
+        let mut flight_client = self.client.make_flight_client()?;
+        let addr = flight_client.addr().to_string();
+        let send = async {
+            flight_client.mut_inner().do_get(ticket).await.map_err(|e| {
+                let tonic_code = e.code();
+                let e: error::Error = e.into();
+                let error = Err::<(), _>(BoxedError::new(e))
+                    .with_context(|_| FlightGetSnafu {
+                        tonic_code,
+                        addr: flight_client.addr().to_string(),
+                    })
+                    .unwrap_err();
+                error!(
+                    e; "Failed to do Flight get, addr: {}, code: {}",
+                    flight_client.addr(),
+                    tonic_code
+                );
+                error
+            })
+        };
+        let response = self.client.track(&addr, send).await?;
+        Ok(response.into_inner())
+    }
+
     async fn handle_inner(&self, request: RegionRequest) -> Result<RegionResponse> {
         let request_type = request
             .body
@@ -194,27 +197,37 @@ impl RegionRequester {
             .with_label_values(&[request_type.as_str()])
             .start_timer();
 
+        let response = self
+            .client
+            .retry(request, |request| self.send_request(request))
+            .await?;
+
+        check_response_header(&response.header)?;
+
+        Ok(RegionResponse::from_region_response(response))
+    }
+
+    async fn send_request(
+        &self,
+        request: RegionRequest,
+    ) -> Result<api::v1::region::RegionResponse> {
         let (addr, mut client) = self.client.raw_region_client()?;
 
-        let response = client
-            .handle(request)
-            .await
-            .map_err(|e| {
+        let send = async {
+            client.handle(request).await.map_err(|e| {
                 let code = e.code();
                 let err: error::Error = e.into();
                 // Uses `Error::RegionServer` instead of `Error::Server`
                 error::Error::RegionServer {
-                    addr,
+                    addr: addr.clone(),
                     code,
                     source: BoxedError::new(err),
                     location: location!(),
                 }
-            })?
-            .into_inner();
-
-        check_response_header(&response.header)?;
-
-        Ok(RegionResponse::from_region_response(response))
+            })
+        };
+        let response = self.client.track(&addr, send).await?.into_inner();
+        Ok(response)
     }
 
     pub async fn handle(&self, request: RegionRequest) -> Result<RegionResponse> {
@@ -237,11 +250,12 @@ pub fn check_response_header(header: &Option<ResponseHeader>) -> Result<()> {
             StatusCode::from_u32(status.status_code).context(IllegalDatabaseResponseSnafu {
                 err_msg: format!("unknown server status: {:?}", status),
             })?;
-        ServerSnafu {
+        let msg = status.err_msg.clone();
+        Err(ServerSnafu {
             code,
-            msg: status.err_msg.clone(),
+            msg: msg.clone(),
         }
-        .fail()
+        .into_error(RemoteStackError::new(msg, vec![], vec![])))
     }
 }
 
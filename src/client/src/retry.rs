@@ -0,0 +1,285 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Retrying requests failed by [crate::Error::should_retry] errors with exponential backoff.
+
+use std::future::Future;
+use std::time::Duration;
+
+use common_telemetry::warn;
+use parking_lot::Mutex;
+use rand::Rng;
+
+use crate::metrics::METRIC_GRPC_RETRY;
+use crate::Result;
+
+/// The retries a [RetryBudget] starts with, so a client can retry before sending many requests.
+const INITIAL_RETRY_BUDGET: f64 = 10.0;
+/// The max retries a [RetryBudget] can save up.
+const MAX_RETRY_BUDGET: f64 = 100.0;
+
+/// How requests to a server are retried.
+///
+/// Retrying is disabled by default since a request timed out may have been applied.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// The max attempts of a request, including the first one. `1` never retries.
+    pub max_attempts: usize,
+    /// The delay before the first retry, doubled before each following retry.
+    pub backoff_base: Duration,
+    /// The max delay before a retry.
+    pub max_backoff: Duration,
+    /// Shortens each delay by a random ratio up to it, so clients failed at the same time
+    /// don't retry at the same time. Between `0.0` and `1.0`.
+    pub jitter: f64,
+    /// The ratio of retries to requests allowed, so retries can't overload a struggling server.
+    pub retry_budget: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            backoff_base: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+            jitter: 0.5,
+            retry_budget: 0.1,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Returns the delay before the `retry`-th retry, starting from 1.
+    pub fn backoff(&self, retry: usize) -> Duration {
+        let exp = retry.saturating_sub(1).min(u32::MAX as usize) as u32;
+        let delay = self
+            .backoff_base
+            .checked_mul(2u32.saturating_pow(exp))
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        if jitter == 0.0 {
+            return delay;
+        }
+        delay.mul_f64(1.0 - jitter * rand::thread_rng().gen::<f64>())
+    }
+}
+
+/// Limits the retries of a client to a ratio of its requests.
+///
+/// Each request saves up a part of a retry and each retry spends a whole one, so when a server
+/// keeps failing, the client stops retrying once the saved retries run out.
+#[derive(Debug)]
+pub(crate) struct RetryBudget {
+    balance: Mutex<f64>,
+}
+
+impl Default for RetryBudget {
+    fn default() -> Self {
+        Self {
+            balance: Mutex::new(INITIAL_RETRY_BUDGET),
+        }
+    }
+}
+
+impl RetryBudget {
+    /// Saves up `ratio` of a retry for a request.
+    fn deposit(&self, ratio: f64) {
+        let mut balance = self.balance.lock();
+        *balance = (*balance + ratio.max(0.0)).min(MAX_RETRY_BUDGET);
+    }
+
+    /// Spends a retry, returns false if there isn't one left.
+    fn withdraw(&self) -> bool {
+        let mut balance = self.balance.lock();
+        if *balance < 1.0 {
+            return false;
+        }
+        *balance -= 1.0;
+        true
+    }
+}
+
+/// Sends the `request` by `send`, retrying it by the `policy` while it fails by errors that
+/// [crate::Error::should_retry]. The request is only cloned if it may be retried.
+pub(crate) async fn retry<R, T, F, Fut>(
+    policy: &RetryPolicy,
+    budget: &RetryBudget,
+    request: R,
+    mut send: F,
+) -> Result<T>
+where
+    R: Clone,
+    F: FnMut(R) -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    budget.deposit(policy.retry_budget);
+    let mut attempt = 1;
+    while attempt < policy.max_attempts {
+        match send(request.clone()).await {
+            Err(err) if err.should_retry() && budget.withdraw() => {
+                let backoff = policy.backoff(attempt);
+                warn!(
+                    err; "Request failed at attempt {}/{}, retry in {:?}",
+                    attempt, policy.max_attempts, backoff
+                );
+                METRIC_GRPC_RETRY.inc();
+                tokio::time::sleep(backoff).await;
+                attempt += 1;
+            }
+            res => return res,
+        }
+    }
+    send(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use common_error::ext::{BoxedError, RemoteStackError};
+    use common_error::status_code::StatusCode;
+    use snafu::{location, IntoError};
+    use tonic::Code;
+
+    use super::*;
+    use crate::error::ServerSnafu;
+    use crate::Error;
+
+    fn region_server_error(code: Code) -> Error {
+        Error::RegionServer {
+            addr: "127.0.0.1:4001".to_string(),
+            code,
+            source: BoxedError::new(
+                ServerSnafu {
+                    code: StatusCode::Unknown,
+                    msg: "mock",
+                }
+                .into_error(RemoteStackError::new(
+                    "mock".to_string(),
+                    vec![],
+                    vec![],
+                )),
+            ),
+            location: location!(),
+        }
+    }
+
+    fn retry_policy(max_attempts: usize) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            backoff_base: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(2),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy {
+            backoff_base: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+            jitter: 0.0,
+            ..Default::default()
+        };
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(4), Duration::from_millis(800));
+        assert_eq!(policy.backoff(5), Duration::from_secs(1));
+        assert_eq!(policy.backoff(100), Duration::from_secs(1));
+
+        let policy = RetryPolicy {
+            jitter: 0.5,
+            ..policy
+        };
+        for _ in 0..100 {
+            let backoff = policy.backoff(2);
+            assert!(backoff > Duration::from_millis(100));
+            assert!(backoff <= Duration::from_millis(200));
+        }
+    }
+
+    #[test]
+    fn test_retry_budget() {
+        let budget = RetryBudget::default();
+        for _ in 0..INITIAL_RETRY_BUDGET as usize {
+            assert!(budget.withdraw());
+        }
+        assert!(!budget.withdraw());
+
+        budget.deposit(0.5);
+        assert!(!budget.withdraw());
+        budget.deposit(0.5);
+        assert!(budget.withdraw());
+
+        budget.deposit(MAX_RETRY_BUDGET * 2.0);
+        for _ in 0..MAX_RETRY_BUDGET as usize {
+            assert!(budget.withdraw());
+        }
+        assert!(!budget.withdraw());
+    }
+
+    async fn send_failing(attempts: &AtomicUsize, fails: usize, code: Code) -> Result<usize> {
+        let attempt = attempts.fetch_add(1, Ordering::Relaxed) + 1;
+        if attempt <= fails {
+            Err(region_server_error(code))
+        } else {
+            Ok(attempt)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry() {
+        let budget = RetryBudget::default();
+
+        // succeeds at the last attempt
+        let attempts = AtomicUsize::new(0);
+        let res = retry(&retry_policy(3), &budget, (), |_| {
+            send_failing(&attempts, 2, Code::Unavailable)
+        })
+        .await;
+        assert_eq!(res.unwrap(), 3);
+
+        // fails after running out of attempts
+        let attempts = AtomicUsize::new(0);
+        let res = retry(&retry_policy(3), &budget, (), |_| {
+            send_failing(&attempts, 3, Code::Unavailable)
+        })
+        .await;
+        assert!(res.unwrap_err().should_retry());
+        assert_eq!(attempts.load(Ordering::Relaxed), 3);
+
+        // never retries errors not to be retried
+        let attempts = AtomicUsize::new(0);
+        let res = retry(&retry_policy(3), &budget, (), |_| {
+            send_failing(&attempts, 1, Code::InvalidArgument)
+        })
+        .await;
+        assert!(res.is_err());
+        assert_eq!(attempts.load(Ordering::Relaxed), 1);
+
+        // stops retrying once the budget runs out
+        let budget = RetryBudget::default();
+        let attempts = AtomicUsize::new(0);
+        let res = retry(&retry_policy(100), &budget, (), |_| {
+            send_failing(&attempts, 100, Code::Unavailable)
+        })
+        .await;
+        assert!(res.is_err());
+        assert_eq!(
+            attempts.load(Ordering::Relaxed),
+            INITIAL_RETRY_BUDGET as usize + 1
+        );
+    }
+}
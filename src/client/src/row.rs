@@ -0,0 +1,308 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Mapping rows of query results into user types.
+//!
+//! ```ignore
+//! struct Cpu {
+//!     host: String,
+//!     usage: Option<f64>,
+//!     ts: Timestamp,
+//! }
+//!
+//! impl FromRow for Cpu {
+//!     fn from_row(row: &Row) -> Result<Self> {
+//!         Ok(Self {
+//!             host: row.get("host")?,
+//!             usage: row.get("usage")?,
+//!             ts: row.get("ts")?,
+//!         })
+//!     }
+//! }
+//!
+//! let cpus: Vec<Cpu> = rows_as(&record_batch)?;
+//! ```
+
+use common_recordbatch::RecordBatch;
+use common_time::{Date, DateTime, Timestamp};
+use datatypes::value::Value;
+use snafu::OptionExt;
+
+use crate::error::{ColumnNotFoundSnafu, ConvertValueSnafu};
+use crate::Result;
+
+/// Types that can be built from a row of query results, by getting its columns by name.
+pub trait FromRow: Sized {
+    fn from_row(row: &Row) -> Result<Self>;
+}
+
+/// Types that a value in a column can be converted into.
+pub trait FromValue: Sized {
+    /// Converts the value, returns `None` if it's not of the type or out of range.
+    fn from_value(value: Value) -> Option<Self>;
+}
+
+/// A row of a [RecordBatch].
+pub struct Row<'a> {
+    record_batch: &'a RecordBatch,
+    index: usize,
+}
+
+impl<'a> Row<'a> {
+    /// Returns the value of the column named `column`.
+    pub fn value(&self, column: &str) -> Result<Value> {
+        let vector = self
+            .record_batch
+            .column_by_name(column)
+            .context(ColumnNotFoundSnafu { column })?;
+        Ok(vector.get(self.index))
+    }
+
+    /// Returns the value of the column named `column`, converted into `T`.
+    pub fn get<T: FromValue>(&self, column: &str) -> Result<T> {
+        let value = self.value(column)?;
+        let display = value.to_string();
+        T::from_value(value).with_context(|| ConvertValueSnafu {
+            column,
+            expected: std::any::type_name::<T>(),
+            value: display,
+        })
+    }
+}
+
+/// Maps rows of the record batch into `T`.
+pub fn rows_as<T: FromRow>(record_batch: &RecordBatch) -> Result<Vec<T>> {
+    (0..record_batch.num_rows())
+        .map(|index| {
+            T::from_row(&Row {
+                record_batch,
+                index,
+            })
+        })
+        .collect()
+}
+
+impl FromValue for Value {
+    fn from_value(value: Value) -> Option<Self> {
+        Some(value)
+    }
+}
+
+/// Null converts into `None`, while other values convert into `Some` of `T`.
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Null => Some(None),
+            value => T::from_value(value).map(Some),
+        }
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Boolean(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! impl_from_value_for_integer {
+    ($($Type: ty),*) => {
+        $(
+            /// Converts from integers in range of the type.
+            impl FromValue for $Type {
+                fn from_value(value: Value) -> Option<Self> {
+                    let v: i128 = match value {
+                        Value::UInt8(v) => v.into(),
+                        Value::UInt16(v) => v.into(),
+                        Value::UInt32(v) => v.into(),
+                        Value::UInt64(v) => v.into(),
+                        Value::Int8(v) => v.into(),
+                        Value::Int16(v) => v.into(),
+                        Value::Int32(v) => v.into(),
+                        Value::Int64(v) => v.into(),
+                        _ => return None,
+                    };
+                    v.try_into().ok()
+                }
+            }
+        )*
+    };
+}
+
+impl_from_value_for_integer!(i8, i16, i32, i64, u8, u16, u32, u64);
+
+impl FromValue for f32 {
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Float32(v) => Some(v.0),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for f64 {
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Float32(v) => Some(v.0 as f64),
+            Value::Float64(v) => Some(v.0),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for String {
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::String(v) => Some(v.into_string()),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for Vec<u8> {
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Binary(v) => Some(v.into()),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for Timestamp {
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Timestamp(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for Date {
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Date(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for DateTime {
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::DateTime(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use datatypes::prelude::{ConcreteDataType, VectorRef};
+    use datatypes::schema::{ColumnSchema, Schema};
+    use datatypes::vectors::{
+        Float64Vector, Int32Vector, StringVector, TimestampMillisecondVector,
+    };
+
+    use super::*;
+    use crate::Error;
+
+    #[derive(Debug, PartialEq)]
+    struct Cpu {
+        host: String,
+        usage: Option<f64>,
+        cores: u8,
+        ts: Timestamp,
+    }
+
+    impl FromRow for Cpu {
+        fn from_row(row: &Row) -> Result<Self> {
+            Ok(Self {
+                host: row.get("host")?,
+                usage: row.get("usage")?,
+                cores: row.get("cores")?,
+                ts: row.get("ts")?,
+            })
+        }
+    }
+
+    fn new_record_batch(cores: Vec<i32>) -> RecordBatch {
+        let schema = Arc::new(Schema::new(vec![
+            ColumnSchema::new("host", ConcreteDataType::string_datatype(), false),
+            ColumnSchema::new("usage", ConcreteDataType::float64_datatype(), true),
+            ColumnSchema::new("cores", ConcreteDataType::int32_datatype(), false),
+            ColumnSchema::new(
+                "ts",
+                ConcreteDataType::timestamp_millisecond_datatype(),
+                false,
+            ),
+        ]));
+        let columns: Vec<VectorRef> = vec![
+            Arc::new(StringVector::from(vec!["host1", "host2"])),
+            Arc::new(Float64Vector::from(vec![Some(0.5), None])),
+            Arc::new(Int32Vector::from_vec(cores)),
+            Arc::new(TimestampMillisecondVector::from_vec(vec![1000, 2000])),
+        ];
+        RecordBatch::new(schema, columns).unwrap()
+    }
+
+    #[test]
+    fn test_rows_as() {
+        let cpus: Vec<Cpu> = rows_as(&new_record_batch(vec![4, 8])).unwrap();
+        assert_eq!(
+            cpus,
+            vec![
+                Cpu {
+                    host: "host1".to_string(),
+                    usage: Some(0.5),
+                    cores: 4,
+                    ts: Timestamp::new_millisecond(1000),
+                },
+                Cpu {
+                    host: "host2".to_string(),
+                    usage: None,
+                    cores: 8,
+                    ts: Timestamp::new_millisecond(2000),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_convert_error() {
+        // out of range of u8
+        let err = rows_as::<Cpu>(&new_record_batch(vec![4, 256])).unwrap_err();
+        assert!(
+            matches!(&err, Error::ConvertValue { column, expected, .. } if column == "cores" && expected == "u8"),
+            "{err:?}"
+        );
+
+        let record_batch = new_record_batch(vec![4, 8]);
+        let row = Row {
+            record_batch: &record_batch,
+            index: 1,
+        };
+        // null into a non-optional type
+        assert!(row.get::<f64>("usage").is_err());
+        assert!(row.get::<String>("ts").is_err());
+        assert!(matches!(
+            row.get::<String>("unknown").unwrap_err(),
+            Error::ColumnNotFound { .. }
+        ));
+        assert_eq!(row.get::<i64>("cores").unwrap(), 8);
+    }
+}
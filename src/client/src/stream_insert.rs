@@ -0,0 +1,116 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use api::v1::greptime_database_client::GreptimeDatabaseClient;
+use api::v1::greptime_request::Request;
+use api::v1::{
+    GreptimeRequest, GreptimeResponse, RequestHeader, RowInsertRequest, RowInsertRequests,
+};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::Channel;
+use tonic::{Response, Status};
+
+use crate::error::{ClientStreamingSnafu, Result};
+use crate::from_grpc_response;
+
+/// The summary of the inserts sent by a [StreamInserter].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StreamInsertSummary {
+    /// The number of insert requests sent.
+    pub requests: usize,
+    /// The number of rows sent.
+    pub rows: usize,
+    /// The number of rows inserted, reported by the server.
+    pub affected_rows: u32,
+}
+
+/// Sends inserts in one long-lived client-streaming request, so each batch doesn't pay a
+/// round trip.
+///
+/// Batches are buffered up to the channel size, [StreamInserter::insert] waits while the buffer
+/// is full and fails once the server has ended the stream, e.g. for an error.
+pub struct StreamInserter {
+    sender: mpsc::Sender<GreptimeRequest>,
+    header: RequestHeader,
+    join: JoinHandle<std::result::Result<Response<GreptimeResponse>, Status>>,
+    summary: StreamInsertSummary,
+}
+
+impl StreamInserter {
+    pub(crate) fn new(
+        mut client: GreptimeDatabaseClient<Channel>,
+        header: RequestHeader,
+        channel_size: usize,
+    ) -> StreamInserter {
+        let (sender, receiver) = mpsc::channel(channel_size);
+        let join =
+            tokio::spawn(
+                async move { client.handle_requests(ReceiverStream::new(receiver)).await },
+            );
+        StreamInserter {
+            sender,
+            header,
+            join,
+            summary: StreamInsertSummary::default(),
+        }
+    }
+
+    /// Sends the inserts to the stream.
+    pub async fn insert(&mut self, requests: Vec<RowInsertRequest>) -> Result<()> {
+        let rows = requests
+            .iter()
+            .map(|request| request.rows.as_ref().map_or(0, |rows| rows.rows.len()))
+            .sum::<usize>();
+        let request = GreptimeRequest {
+            header: Some(self.header.clone()),
+            request: Some(Request::RowInserts(RowInsertRequests { inserts: requests })),
+        };
+        self.sender.send(request).await.map_err(|_| {
+            ClientStreamingSnafu {
+                err_msg: "the stream is ended by the server",
+            }
+            .build()
+        })?;
+        self.summary.requests += 1;
+        self.summary.rows += rows;
+        Ok(())
+    }
+
+    /// Returns the inserts sent so far.
+    pub fn sent(&self) -> StreamInsertSummary {
+        self.summary
+    }
+
+    /// Ends the stream and waits for the server to insert all the rows sent.
+    pub async fn finish(self) -> Result<StreamInsertSummary> {
+        drop(self.sender);
+        let response = self
+            .join
+            .await
+            .map_err(|e| {
+                ClientStreamingSnafu {
+                    err_msg: format!("the stream task failed: {e}"),
+                }
+                .build()
+            })??
+            .into_inner();
+        let affected_rows = from_grpc_response(response)?;
+        Ok(StreamInsertSummary {
+            affected_rows,
+            ..self.summary
+        })
+    }
+}
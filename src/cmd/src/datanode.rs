@@ -260,6 +260,13 @@ impl StartCommand {
 
     async fn build(&self, opts: DatanodeOptions) -> Result<Instance> {
         common_runtime::init_global_runtimes(&opts.runtime);
+        common_error::error_info::set_local_node(
+            "datanode",
+            &servers::addrs::resolve_addr(
+                &opts.component.grpc.addr,
+                Some(&opts.component.grpc.hostname),
+            ),
+        );
 
         let guard = common_telemetry::init_global_logging(
             APP_NAME,
@@ -29,7 +29,7 @@ use common_meta::key::TableMetadataManager;
 use common_telemetry::info;
 use common_telemetry::logging::TracingOptions;
 use common_version::{short_version, version};
-use flow::{FlownodeBuilder, FlownodeInstance, FrontendInvoker};
+use flow::{FlownodeBuilder, FlownodeInstance, FrontendInvoker, TableIdNameCache};
 use frontend::heartbeat::handler::invalidate_table_cache::InvalidateTableCacheHandler;
 use meta_client::{MetaClientOptions, MetaClientType};
 use servers::Mode;
@@ -208,6 +208,13 @@ impl StartCommand {
 
     async fn build(&self, opts: FlownodeOptions) -> Result<Instance> {
         common_runtime::init_global_runtimes(&opts.runtime);
+        common_error::error_info::set_local_node(
+            "flownode",
+            &servers::addrs::resolve_addr(
+                &opts.component.grpc.addr,
+                Some(&opts.component.grpc.hostname),
+            ),
+        );
 
         let guard = common_telemetry::init_global_logging(
             APP_NAME,
@@ -254,9 +261,11 @@ impl StartCommand {
         let cached_meta_backend = Arc::new(cached_meta_backend);
 
         // Builds cache registry
+        let table_id_name_cache = Arc::new(TableIdNameCache::default());
         let layered_cache_builder = LayeredCacheRegistryBuilder::default().add_cache_registry(
             CacheRegistryBuilder::default()
                 .add_cache(cached_meta_backend.clone())
+                .add_cache(table_id_name_cache.clone())
                 .build(),
         );
         let fundamental_cache_registry =
@@ -307,7 +316,8 @@ impl StartCommand {
             catalog_manager.clone(),
             flow_metadata_manager,
         )
-        .with_heartbeat_task(heartbeat_task);
+        .with_heartbeat_task(heartbeat_task)
+        .with_table_id_name_cache(table_id_name_cache);
 
         let flownode = flownode_builder.build().await.context(StartFlownodeSnafu)?;
 
@@ -254,6 +254,13 @@ impl StartCommand {
 
     async fn build(&self, opts: FrontendOptions) -> Result<Instance> {
         common_runtime::init_global_runtimes(&opts.runtime);
+        common_error::error_info::set_local_node(
+            "frontend",
+            &servers::addrs::resolve_addr(
+                &opts.component.grpc.addr,
+                Some(&opts.component.grpc.hostname),
+            ),
+        );
 
         let guard = common_telemetry::init_global_logging(
             APP_NAME,
@@ -14,13 +14,18 @@
 
 #![feature(assert_matches, let_chains)]
 
+use std::collections::HashSet;
+use std::sync::Arc;
+
 use async_trait::async_trait;
-use catalog::information_schema::InformationExtension;
+use catalog::information_schema::{FlowStat, InformationExtension};
+use catalog::kvbackend::MetaKvBackend;
 use client::api::v1::meta::ProcedureStatus;
 use common_error::ext::BoxedError;
-use common_meta::cluster::{ClusterInfo, NodeInfo};
+use common_meta::cluster::{ClusterInfo, NodeInfo, Role};
 use common_meta::datanode::RegionStat;
 use common_meta::ddl::{ExecutorContext, ProcedureExecutor};
+use common_meta::key::flow_stat::FlowStatManager;
 use common_meta::rpc::procedure;
 use common_procedure::{ProcedureInfo, ProcedureState};
 use common_telemetry::{error, info};
@@ -170,4 +175,32 @@ impl InformationExtension for DistributedInformationExtension {
             .map_err(BoxedError::new)
             .context(catalog::error::ListRegionStatsSnafu)
     }
+
+    async fn flow_stats(&self) -> std::result::Result<Vec<FlowStat>, Self::Error> {
+        let flownodes = self
+            .meta_client
+            .list_nodes(Some(Role::Flownode))
+            .await
+            .map_err(BoxedError::new)
+            .context(catalog::error::ListNodesSnafu)?
+            .into_iter()
+            .map(|node| node.peer.id)
+            .collect::<HashSet<_>>();
+
+        // status of flows is reported to the kv backend by flownodes, those reported by
+        // flownodes no longer in the cluster are stale
+        let kv_backend = Arc::new(MetaKvBackend::new(self.meta_client.clone()));
+        let flow_stats = FlowStatManager::new(kv_backend)
+            .list()
+            .await
+            .map_err(BoxedError::new)
+            .context(catalog::error::ListFlowStatsSnafu)?;
+
+        Ok(flow_stats
+            .into_iter()
+            .filter(|(node_id, _)| flownodes.contains(node_id))
+            .flat_map(|(_, value)| value.flow_stats)
+            .map(FlowStat::from)
+            .collect())
+    }
 }
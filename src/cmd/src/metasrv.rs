@@ -261,6 +261,10 @@ impl StartCommand {
 
     async fn build(&self, opts: MetasrvOptions) -> Result<Instance> {
         common_runtime::init_global_runtimes(&opts.runtime);
+        common_error::status_code::set_max_error_stack_size(
+            opts.component.max_error_stack_size.as_bytes() as usize,
+        );
+        common_error::error_info::set_local_node("metasrv", &opts.component.server_addr);
 
         let guard = common_telemetry::init_global_logging(
             APP_NAME,
@@ -17,7 +17,7 @@ use std::{fs, path};
 
 use async_trait::async_trait;
 use cache::{build_fundamental_cache_registry, with_default_composite_cache_registry};
-use catalog::information_schema::InformationExtension;
+use catalog::information_schema::{FlowStat, InformationExtension};
 use catalog::kvbackend::KvBackendCatalogManager;
 use clap::Parser;
 use client::api::v1::meta::RegionRole;
@@ -25,7 +25,7 @@ use common_base::Plugins;
 use common_catalog::consts::{MIN_USER_FLOW_ID, MIN_USER_TABLE_ID};
 use common_config::{metadata_store_dir, Configurable, KvBackendConfig};
 use common_error::ext::BoxedError;
-use common_meta::cache::LayeredCacheRegistryBuilder;
+use common_meta::cache::{CacheRegistryBuilder, LayeredCacheRegistryBuilder};
 use common_meta::cache_invalidator::CacheInvalidatorRef;
 use common_meta::cluster::{NodeInfo, NodeStatus};
 use common_meta::datanode::RegionStat;
@@ -34,7 +34,7 @@ use common_meta::ddl::table_meta::{TableMetadataAllocator, TableMetadataAllocato
 use common_meta::ddl::{DdlContext, NoopRegionFailureDetectorControl, ProcedureExecutorRef};
 use common_meta::ddl_manager::DdlManager;
 use common_meta::key::flow::{FlowMetadataManager, FlowMetadataManagerRef};
-use common_meta::key::{TableMetadataManager, TableMetadataManagerRef};
+use common_meta::key::{FlowId, TableMetadataManager, TableMetadataManagerRef};
 use common_meta::kv_backend::KvBackendRef;
 use common_meta::node_manager::NodeManagerRef;
 use common_meta::peer::Peer;
@@ -51,7 +51,9 @@ use datanode::config::{DatanodeOptions, ProcedureConfig, RegionEngineConfig, Sto
 use datanode::datanode::{Datanode, DatanodeBuilder};
 use datanode::region_server::RegionServer;
 use file_engine::config::EngineConfig as FileEngineConfig;
-use flow::{FlowWorkerManager, FlownodeBuilder, FrontendInvoker};
+use flow::{
+    FlowWorkerManager, FlowWorkerManagerRef, FlownodeBuilder, FrontendInvoker, TableIdNameCache,
+};
 use frontend::frontend::FrontendOptions;
 use frontend::instance::builder::FrontendBuilder;
 use frontend::instance::{FrontendInstance, Instance as FeInstance, StandaloneDatanodeManager};
@@ -69,7 +71,7 @@ use servers::http::HttpOptions;
 use servers::tls::{TlsMode, TlsOption};
 use servers::Mode;
 use snafu::ResultExt;
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, RwLock};
 use tracing_appender::non_blocking::WorkerGuard;
 
 use crate::error::{
@@ -432,6 +434,13 @@ impl StartCommand {
     #[allow(clippy::diverging_sub_expression)]
     async fn build(&self, opts: GreptimeOptions<StandaloneOptions>) -> Result<Instance> {
         common_runtime::init_global_runtimes(&opts.runtime);
+        common_error::error_info::set_local_node(
+            "standalone",
+            &servers::addrs::resolve_addr(
+                &opts.component.grpc.addr,
+                Some(&opts.component.grpc.hostname),
+            ),
+        );
 
         let guard = common_telemetry::init_global_logging(
             APP_NAME,
@@ -475,7 +484,12 @@ impl StartCommand {
         .context(StartFrontendSnafu)?;
 
         // Builds cache registry
-        let layered_cache_builder = LayeredCacheRegistryBuilder::default();
+        let table_id_name_cache = Arc::new(TableIdNameCache::default());
+        let layered_cache_builder = LayeredCacheRegistryBuilder::default().add_cache_registry(
+            CacheRegistryBuilder::default()
+                .add_cache(table_id_name_cache.clone())
+                .build(),
+        );
         let fundamental_cache_registry = build_fundamental_cache_registry(kv_backend.clone());
         let layered_cache_registry = Arc::new(
             with_default_composite_cache_registry(
@@ -496,7 +510,7 @@ impl StartCommand {
             procedure_manager.clone(),
         ));
         let catalog_manager = KvBackendCatalogManager::new(
-            information_extension,
+            information_extension.clone(),
             kv_backend.clone(),
             layered_cache_registry.clone(),
             Some(procedure_manager.clone()),
@@ -512,7 +526,8 @@ impl StartCommand {
             table_metadata_manager.clone(),
             catalog_manager.clone(),
             flow_metadata_manager.clone(),
-        );
+        )
+        .with_table_id_name_cache(table_id_name_cache);
         let flownode = Arc::new(
             flow_builder
                 .build()
@@ -520,6 +535,9 @@ impl StartCommand {
                 .map_err(BoxedError::new)
                 .context(OtherSnafu)?,
         );
+        information_extension
+            .set_flow_worker_manager(flownode.flow_worker_manager())
+            .await;
 
         let node_manager = Arc::new(StandaloneDatanodeManager {
             region_server: datanode.region_server(),
@@ -536,6 +554,7 @@ impl StartCommand {
             SequenceBuilder::new(FLOW_ID_SEQ, kv_backend.clone())
                 .initial(MIN_USER_FLOW_ID as u64)
                 .step(10)
+                .max(FlowId::MAX as u64)
                 .build(),
         );
         let wal_options_allocator = Arc::new(WalOptionsAllocator::new(
@@ -630,6 +649,7 @@ impl StartCommand {
                     flow_metadata_manager,
                     flow_metadata_allocator,
                     region_failure_detector_controller: Arc::new(NoopRegionFailureDetectorControl),
+                    flow_quota: Default::default(),
                 },
                 procedure_manager,
                 true,
@@ -658,6 +678,7 @@ pub struct StandaloneInformationExtension {
     region_server: RegionServer,
     procedure_manager: ProcedureManagerRef,
     start_time_ms: u64,
+    flow_worker_manager: RwLock<Option<FlowWorkerManagerRef>>,
 }
 
 impl StandaloneInformationExtension {
@@ -666,8 +687,14 @@ impl StandaloneInformationExtension {
             region_server,
             procedure_manager,
             start_time_ms: common_time::util::current_time_millis() as u64,
+            flow_worker_manager: RwLock::new(None),
         }
     }
+
+    /// Set the flow worker manager, which is built after the catalog manager.
+    pub async fn set_flow_worker_manager(&self, flow_worker_manager: FlowWorkerManagerRef) {
+        *self.flow_worker_manager.write().await = Some(flow_worker_manager);
+    }
 }
 
 #[async_trait::async_trait]
@@ -737,6 +764,13 @@ impl InformationExtension for StandaloneInformationExtension {
             .collect::<Vec<_>>();
         Ok(stats)
     }
+
+    async fn flow_stats(&self) -> std::result::Result<Vec<FlowStat>, Self::Error> {
+        match self.flow_worker_manager.read().await.as_ref() {
+            Some(manager) => Ok(manager.flow_stats().await),
+            None => Ok(vec![]),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -55,6 +55,9 @@ fn test_load_datanode_example_config() {
                 metadata_cache_max_capacity: 100000,
                 metadata_cache_ttl: Duration::from_secs(600),
                 metadata_cache_tti: Duration::from_secs(300),
+                follower_reads: false,
+                follower_read_max_staleness: Duration::from_secs(1),
+                slow_call_threshold: Duration::from_secs(5),
             }),
             wal: DatanodeWalConfig::RaftEngine(RaftEngineConfig {
                 dir: Some("/tmp/greptimedb/wal".to_string()),
@@ -119,6 +122,9 @@ fn test_load_frontend_example_config() {
                 metadata_cache_max_capacity: 100000,
                 metadata_cache_ttl: Duration::from_secs(600),
                 metadata_cache_tti: Duration::from_secs(300),
+                follower_reads: false,
+                follower_read_max_staleness: Duration::from_secs(1),
+                slow_call_threshold: Duration::from_secs(5),
             }),
             logging: LoggingOptions {
                 level: Some("info".to_string()),
@@ -0,0 +1,484 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The error info sent in the header [GREPTIME_DB_HEADER_ERROR_INFO] of a tonic status.
+//!
+//! The error info was sent in separated headers before, which proxies may drop, reorder or
+//! merge. It's now encoded into a single JSON value, tagged with its version so that a newer
+//! encoding can be told apart by its content. The separated headers are still sent for older
+//! clients, and decoded if the error info is absent or of an unknown version.
+
+use std::fmt::{self, Write};
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+use tonic::metadata::MetadataMap;
+
+use crate::status_code::{decode_error_stack, StatusCode};
+use crate::{
+    GREPTIME_DB_HEADER_ERROR_CODE, GREPTIME_DB_HEADER_ERROR_INFO, GREPTIME_DB_HEADER_ERROR_MSG,
+    GREPTIME_DB_HEADER_ERROR_STACK,
+};
+
+/// The version of the current encoding.
+const ERROR_INFO_VERSION: u32 = 1;
+
+/// The error info of a failed request, sent from the server to the client.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ErrorInfo {
+    /// The [StatusCode] of the error, `None` if the server doesn't tell or it's unknown to the
+    /// client.
+    pub code: Option<StatusCode>,
+    /// The message of the error, `None` if it's only carried by the message of the status.
+    pub msg: Option<String>,
+    /// The layers of the error stack, from the outermost one to the innermost one.
+    pub stack: Vec<String>,
+    /// The nodes the error is returned from and then forwarded by, the last one is the server
+    /// the client requests.
+    pub hops: Vec<Hop>,
+    /// Where the layers of the error stack are from, from the outermost one to the innermost
+    /// one. It's empty if the server doesn't send them, see [ErrorInfo::stack_frames].
+    pub frames: Vec<StackFrame>,
+}
+
+/// A node an error is returned from or forwarded by.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hop {
+    /// The role of the node, e.g., `datanode` or `frontend`.
+    pub role: String,
+    /// The address of the node.
+    pub addr: String,
+    /// The [StatusCode] the node returns the error with.
+    pub code: u32,
+}
+
+impl fmt::Display for Hop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match StatusCode::from_u32(self.code) {
+            Some(code) => write!(f, "{} {} ({code})", self.role, self.addr),
+            None => write!(f, "{} {} ({})", self.role, self.addr, self.code),
+        }
+    }
+}
+
+/// Where a layer of the error stack is from.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct StackFrame {
+    /// The type name of the error, e.g., `client::error::Error::Server`.
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub type_name: Option<String>,
+    /// The file the error is created in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// The line the error is created at.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+}
+
+impl StackFrame {
+    /// Parses the location of a layer of the error stack formatted by
+    /// [StackError::debug_fmt](crate::ext::StackError::debug_fmt), for the errors whose frames
+    /// are not sent, e.g., from an older server. The layer ends with `, at <file>:<line>:<column>`
+    /// if it has a location.
+    pub fn parse_layer(layer: &str) -> Self {
+        let Some((_, location)) = layer.rsplit_once(", at ") else {
+            return Self::default();
+        };
+        let mut parts = location.rsplitn(3, ':');
+        let (Some(column), Some(line), Some(file)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Self::default();
+        };
+        match (column.parse::<u32>(), line.parse::<u32>()) {
+            (Ok(_), Ok(line)) if !file.is_empty() => Self {
+                type_name: None,
+                file: Some(file.to_string()),
+                line: Some(line),
+            },
+            _ => Self::default(),
+        }
+    }
+
+    /// Returns `true` if the frame tells where the error is created.
+    pub fn has_location(&self) -> bool {
+        self.file.is_some()
+    }
+}
+
+impl fmt::Display for StackFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.type_name.as_deref().unwrap_or("<unknown>"))?;
+        if let Some(file) = &self.file {
+            write!(f, " at {file}")?;
+            if let Some(line) = self.line {
+                write!(f, ":{line}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The role and the address of this node, appended to the hops of the errors it returns.
+static LOCAL_NODE: OnceLock<(String, String)> = OnceLock::new();
+
+/// Sets the role and the address of this node on startup, so the errors it returns tell where
+/// they are from. It can only be set once.
+pub fn set_local_node(role: &str, addr: &str) {
+    let _ = LOCAL_NODE.set((role.to_string(), addr.to_string()));
+}
+
+/// Returns the role and the address of this node, if they are set.
+pub(crate) fn local_node() -> Option<(&'static str, &'static str)> {
+    LOCAL_NODE
+        .get()
+        .map(|(role, addr)| (role.as_str(), addr.as_str()))
+}
+
+#[derive(Serialize, Deserialize)]
+struct Version {
+    v: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ErrorInfoV1 {
+    v: u32,
+    code: u32,
+    /// The [StatusCategory](crate::status_code::StatusCategory) of the code, for the clients
+    /// unaware of the code. It's derived from the code on decoding.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    category: Option<String>,
+    /// Whether the error is retryable, see [StatusCode::is_retryable]. It's derived from the
+    /// code on decoding.
+    #[serde(default)]
+    retryable: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    msg: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    stack: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    hops: Vec<Hop>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    frames: Vec<StackFrame>,
+}
+
+impl ErrorInfo {
+    /// Encodes the error info into a header value, of the current version.
+    ///
+    /// Non-ASCII characters are escaped, so the value is always a valid header value.
+    pub fn encode(&self) -> String {
+        let code = self.code.unwrap_or(StatusCode::Unknown);
+        let info = ErrorInfoV1 {
+            v: ERROR_INFO_VERSION,
+            code: code as u32,
+            category: Some(code.category().to_string()),
+            retryable: code.is_retryable(),
+            msg: self.msg.clone(),
+            stack: self.stack.clone(),
+            hops: self.hops.clone(),
+            frames: self.frames.clone(),
+        };
+        // Safety: the struct only contains numbers and strings, which are always serializable.
+        let json = serde_json::to_string(&info).unwrap();
+        escape_non_ascii(&json)
+    }
+
+    /// Decodes the error info from a header value, returns `None` if it's malformed or of an
+    /// unknown version.
+    pub fn decode(value: &str) -> Option<Self> {
+        let version = serde_json::from_str::<Version>(value).ok()?;
+        if version.v != ERROR_INFO_VERSION {
+            return None;
+        }
+        let info = serde_json::from_str::<ErrorInfoV1>(value).ok()?;
+        Some(Self {
+            code: StatusCode::from_u32(info.code),
+            msg: info.msg,
+            stack: info.stack,
+            hops: info.hops,
+            frames: info.frames,
+        })
+    }
+
+    /// Decodes the error info from a tonic status, from its details first, see
+    /// [status_details](crate::status_details), then from its metadata.
+    pub fn from_status(status: &tonic::Status) -> Self {
+        Self::from_status_details(status.details())
+            .unwrap_or_else(|| Self::from_metadata(status.metadata()))
+    }
+
+    /// Decodes the error info from the metadata of a tonic status, falls back to the separated
+    /// headers if the error info is absent or of an unknown version.
+    pub fn from_metadata(metadata: &MetadataMap) -> Self {
+        fn get_metadata_value<'a>(metadata: &'a MetadataMap, key: &str) -> Option<&'a str> {
+            metadata
+                .get(key)
+                .and_then(|v| std::str::from_utf8(v.as_bytes()).ok())
+        }
+
+        if let Some(info) =
+            get_metadata_value(metadata, GREPTIME_DB_HEADER_ERROR_INFO).and_then(Self::decode)
+        {
+            return info;
+        }
+
+        Self {
+            code: get_metadata_value(metadata, GREPTIME_DB_HEADER_ERROR_CODE)
+                .and_then(|code| code.parse::<u32>().ok())
+                .and_then(StatusCode::from_u32),
+            msg: get_metadata_value(metadata, GREPTIME_DB_HEADER_ERROR_MSG)
+                .map(ToString::to_string),
+            stack: get_metadata_value(metadata, GREPTIME_DB_HEADER_ERROR_STACK)
+                .map(decode_error_stack)
+                .unwrap_or_default(),
+            hops: vec![],
+            frames: vec![],
+        }
+    }
+
+    /// Returns where the layers of the error stack are from, parsed from the layers by
+    /// [StackFrame::parse_layer] if the server doesn't send them.
+    pub fn stack_frames(&self) -> Vec<StackFrame> {
+        if self.frames.is_empty() {
+            self.stack
+                .iter()
+                .map(|l| StackFrame::parse_layer(l))
+                .collect()
+        } else {
+            self.frames.clone()
+        }
+    }
+
+    /// Returns where the error originates, i.e., the innermost frame with a location, so the
+    /// errors can be aggregated by their origins.
+    pub fn origin(&self) -> Option<StackFrame> {
+        self.stack_frames()
+            .into_iter()
+            .rev()
+            .find(StackFrame::has_location)
+    }
+}
+
+/// Escapes the non-ASCII characters of a JSON text, they only appear in its strings.
+fn escape_non_ascii(json: &str) -> String {
+    if json.is_ascii() {
+        return json.to_string();
+    }
+
+    let mut escaped = String::with_capacity(json.len());
+    for c in json.chars() {
+        if c.is_ascii() {
+            escaped.push(c);
+        } else {
+            let mut buf = [0u16; 2];
+            for unit in c.encode_utf16(&mut buf) {
+                // Safety: writing to a string never fails.
+                write!(escaped, "\\u{:04x}", unit).unwrap();
+            }
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use tonic::metadata::MetadataValue;
+
+    use super::*;
+
+    #[test]
+    fn test_encode_decode_error_info() {
+        let info = ErrorInfo {
+            code: Some(StatusCode::TableNotFound),
+            msg: Some("表 t 不存在 🙈".to_string()),
+            stack: vec![
+                "Failed to get table, at src/a.rs:1:1".to_string(),
+                "Table not found: \"t\"".to_string(),
+            ],
+            hops: vec![
+                Hop {
+                    role: "datanode".to_string(),
+                    addr: "127.0.0.1:3001".to_string(),
+                    code: StatusCode::TableNotFound as u32,
+                },
+                Hop {
+                    role: "frontend".to_string(),
+                    addr: "127.0.0.1:4001".to_string(),
+                    code: StatusCode::TableNotFound as u32,
+                },
+            ],
+            frames: vec![
+                StackFrame {
+                    type_name: Some("catalog::error::Error::GetTable".to_string()),
+                    file: Some("src/a.rs".to_string()),
+                    line: Some(1),
+                },
+                StackFrame::default(),
+            ],
+        };
+        let encoded = info.encode();
+        assert!(encoded.is_ascii());
+        assert!(encoded.starts_with(r#"{"v":1,"code":4001,"category":"user","retryable":false,"#));
+        assert_eq!(ErrorInfo::decode(&encoded).unwrap(), info);
+
+        let info = ErrorInfo {
+            code: Some(StatusCode::Internal),
+            ..Default::default()
+        };
+        assert_eq!(
+            info.encode(),
+            r#"{"v":1,"code":1003,"category":"internal","retryable":true}"#
+        );
+        assert_eq!(ErrorInfo::decode(&info.encode()).unwrap(), info);
+
+        // unknown versions and malformed values are not decoded
+        assert!(ErrorInfo::decode(r#"{"v":2,"code":1003}"#).is_none());
+        assert!(ErrorInfo::decode(r#"{"code":1003}"#).is_none());
+        assert!(ErrorInfo::decode("1003").is_none());
+
+        // unknown fields are ignored, for compatible changes in the same version
+        assert_eq!(
+            ErrorInfo::decode(r#"{"v":1,"code":1003,"hint":"retry later"}"#).unwrap(),
+            info
+        );
+    }
+
+    #[test]
+    fn test_error_info_from_metadata() {
+        let mut metadata = MetadataMap::new();
+        assert_eq!(ErrorInfo::from_metadata(&metadata), ErrorInfo::default());
+
+        // the separated headers
+        let _ = metadata.insert(
+            GREPTIME_DB_HEADER_ERROR_CODE,
+            MetadataValue::from(StatusCode::TableNotFound as u32),
+        );
+        let _ = metadata.insert(
+            GREPTIME_DB_HEADER_ERROR_STACK,
+            MetadataValue::from_static("Failed to get table\tTable not found"),
+        );
+        let legacy = ErrorInfo {
+            code: Some(StatusCode::TableNotFound),
+            msg: None,
+            stack: vec![
+                "Failed to get table".to_string(),
+                "Table not found".to_string(),
+            ],
+            hops: vec![],
+            frames: vec![],
+        };
+        assert_eq!(ErrorInfo::from_metadata(&metadata), legacy);
+
+        // the error info takes precedence
+        let info = ErrorInfo {
+            code: Some(StatusCode::RegionNotFound),
+            msg: Some("Region not found".to_string()),
+            stack: vec![],
+            hops: vec![],
+            frames: vec![],
+        };
+        let _ = metadata.insert(
+            GREPTIME_DB_HEADER_ERROR_INFO,
+            MetadataValue::try_from(info.encode()).unwrap(),
+        );
+        assert_eq!(ErrorInfo::from_metadata(&metadata), info);
+
+        // falls back to the separated headers for an unknown version
+        let _ = metadata.insert(
+            GREPTIME_DB_HEADER_ERROR_INFO,
+            MetadataValue::from_static(r#"{"v":2}"#),
+        );
+        assert_eq!(ErrorInfo::from_metadata(&metadata), legacy);
+    }
+
+    #[test]
+    fn test_hop_display() {
+        let hop = Hop {
+            role: "datanode".to_string(),
+            addr: "127.0.0.1:3001".to_string(),
+            code: StatusCode::RegionNotFound as u32,
+        };
+        assert_eq!(hop.to_string(), "datanode 127.0.0.1:3001 (RegionNotFound)");
+
+        let hop = Hop { code: 9999, ..hop };
+        assert_eq!(hop.to_string(), "datanode 127.0.0.1:3001 (9999)");
+    }
+
+    #[test]
+    fn test_parse_stack_layer() {
+        assert_eq!(
+            StackFrame::parse_layer("Failed to get table, at src/catalog/src/a.rs:12:5"),
+            StackFrame {
+                type_name: None,
+                file: Some("src/catalog/src/a.rs".to_string()),
+                line: Some(12),
+            }
+        );
+        // only the last location counts, the message may contain one
+        assert_eq!(
+            StackFrame::parse_layer("Failed to parse \"a, at b\", at src/a.rs:1:2")
+                .file
+                .unwrap(),
+            "src/a.rs"
+        );
+        for layer in [
+            "Table not found: t",
+            "Failed, at src/a.rs",
+            "Failed, at src/a.rs:x:1",
+            "Failed, at :1:2",
+        ] {
+            assert_eq!(StackFrame::parse_layer(layer), StackFrame::default());
+        }
+    }
+
+    #[test]
+    fn test_error_origin() {
+        // parsed from the layers if the frames are not sent
+        let mut info = ErrorInfo {
+            code: Some(StatusCode::TableNotFound),
+            stack: vec![
+                "Failed to execute, at src/b.rs:3:1".to_string(),
+                "Failed to get table, at src/a.rs:1:1".to_string(),
+                "Table not found: t".to_string(),
+            ],
+            ..Default::default()
+        };
+        let origin = info.origin().unwrap();
+        assert_eq!(origin.to_string(), "<unknown> at src/a.rs:1");
+
+        info.frames = vec![
+            StackFrame {
+                type_name: Some("operator::error::Error::Execute".to_string()),
+                file: Some("src/b.rs".to_string()),
+                line: Some(3),
+            },
+            StackFrame {
+                type_name: Some("catalog::error::Error::GetTable".to_string()),
+                file: Some("src/a.rs".to_string()),
+                line: Some(1),
+            },
+            StackFrame {
+                type_name: Some("catalog::error::TableNotFound".to_string()),
+                file: None,
+                line: None,
+            },
+        ];
+        assert_eq!(info.origin().unwrap(), info.frames[1]);
+        assert_eq!(
+            info.origin().unwrap().to_string(),
+            "catalog::error::Error::GetTable at src/a.rs:1"
+        );
+
+        assert!(ErrorInfo::default().origin().is_none());
+    }
+}
@@ -15,6 +15,7 @@
 use std::any::Any;
 use std::sync::Arc;
 
+use crate::error_info::{Hop, StackFrame};
 use crate::status_code::StatusCode;
 
 /// Extension to [`Error`](std::error::Error) in std.
@@ -74,6 +75,20 @@ pub trait StackError: std::error::Error {
 
     fn next(&self) -> Option<&dyn StackError>;
 
+    /// Pushes where the layers of the error stack are from, a frame for each layer pushed by
+    /// [StackError::debug_fmt]. The default one knows nothing of this error but its next one.
+    fn stack_frames(&self, buf: &mut Vec<StackFrame>) {
+        buf.push(StackFrame::default());
+        if let Some(next) = self.next() {
+            next.stack_frames(buf);
+        }
+    }
+
+    /// Returns the hops of the error if it's returned by another node, see [RemoteStackError].
+    fn remote_hops(&self) -> Option<&[Hop]> {
+        None
+    }
+
     fn last(&self) -> &dyn StackError
     where
         Self: Sized,
@@ -96,6 +111,14 @@ impl<T: ?Sized + StackError> StackError for Arc<T> {
     fn next(&self) -> Option<&dyn StackError> {
         self.as_ref().next()
     }
+
+    fn stack_frames(&self, buf: &mut Vec<StackFrame>) {
+        self.as_ref().stack_frames(buf)
+    }
+
+    fn remote_hops(&self) -> Option<&[Hop]> {
+        self.as_ref().remote_hops()
+    }
 }
 
 impl<T: StackError> StackError for Box<T> {
@@ -106,6 +129,14 @@ impl<T: StackError> StackError for Box<T> {
     fn next(&self) -> Option<&dyn StackError> {
         self.as_ref().next()
     }
+
+    fn stack_frames(&self, buf: &mut Vec<StackFrame>) {
+        self.as_ref().stack_frames(buf)
+    }
+
+    fn remote_hops(&self) -> Option<&[Hop]> {
+        self.as_ref().remote_hops()
+    }
 }
 
 /// An opaque boxed error based on errors that implement [ErrorExt] trait.
@@ -171,6 +202,14 @@ impl StackError for BoxedError {
     fn next(&self) -> Option<&dyn StackError> {
         self.inner.next()
     }
+
+    fn stack_frames(&self, buf: &mut Vec<StackFrame>) {
+        self.inner.stack_frames(buf)
+    }
+
+    fn remote_hops(&self) -> Option<&[Hop]> {
+        self.inner.remote_hops()
+    }
 }
 
 /// Error type with plain error message
@@ -217,3 +256,89 @@ impl StackError for PlainError {
         None
     }
 }
+
+/// The error returned by another node, decoded from the tonic status, see
+/// [ErrorInfo](crate::error_info::ErrorInfo).
+///
+/// Its stack shows the nodes the error is returned from and then forwarded by, followed by the
+/// stack sent by the node, which shows the nodes before it likewise if it's also forwarded.
+#[derive(Debug)]
+pub struct RemoteStackError {
+    msg: String,
+    stack: Vec<String>,
+    hops: Vec<Hop>,
+    frames: Vec<StackFrame>,
+}
+
+impl RemoteStackError {
+    pub fn new(msg: String, stack: Vec<String>, hops: Vec<Hop>) -> Self {
+        Self {
+            msg,
+            stack,
+            hops,
+            frames: vec![],
+        }
+    }
+
+    /// Sets where the layers of the stack are from, they are parsed from the layers if not set.
+    pub fn with_frames(mut self, frames: Vec<StackFrame>) -> Self {
+        self.frames = frames;
+        self
+    }
+
+    /// Returns the layers of the error stack sent by the node, from the outermost layer to the
+    /// innermost one. It's empty if the node doesn't send its stack.
+    pub fn stack(&self) -> &[String] {
+        &self.stack
+    }
+
+    /// Returns the nodes the error is returned from and then forwarded by.
+    pub fn hops(&self) -> &[Hop] {
+        &self.hops
+    }
+}
+
+impl std::fmt::Display for RemoteStackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.msg)
+    }
+}
+
+impl std::error::Error for RemoteStackError {}
+
+impl StackError for RemoteStackError {
+    fn debug_fmt(&self, mut layer: usize, buf: &mut Vec<String>) {
+        if !self.hops.is_empty() {
+            let hops = self
+                .hops
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            buf.push(format!("{layer}: Remote error via {hops}"));
+            layer += 1;
+        }
+        for (i, err) in self.stack.iter().enumerate() {
+            buf.push(format!("{}: {}", layer + i, err));
+        }
+    }
+
+    fn next(&self) -> Option<&dyn StackError> {
+        None
+    }
+
+    fn stack_frames(&self, buf: &mut Vec<StackFrame>) {
+        if !self.hops.is_empty() {
+            buf.push(StackFrame::default());
+        }
+        if self.frames.is_empty() {
+            buf.extend(self.stack.iter().map(|l| StackFrame::parse_layer(l)));
+        } else {
+            buf.extend_from_slice(&self.frames);
+        }
+    }
+
+    fn remote_hops(&self) -> Option<&[Hop]> {
+        Some(&self.hops)
+    }
+}
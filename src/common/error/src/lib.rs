@@ -14,9 +14,12 @@
 
 #![feature(error_iter)]
 
+pub mod error_info;
 pub mod ext;
 pub mod mock;
+pub mod sampler;
 pub mod status_code;
+pub mod status_details;
 
 pub use snafu;
 
@@ -24,3 +27,6 @@ pub use snafu;
 // please define in `src/servers/src/http/header.rs`.
 pub const GREPTIME_DB_HEADER_ERROR_CODE: &str = "x-greptime-err-code";
 pub const GREPTIME_DB_HEADER_ERROR_MSG: &str = "x-greptime-err-msg";
+pub const GREPTIME_DB_HEADER_ERROR_STACK: &str = "x-greptime-err-stack";
+/// The error info encoded in a single value, see [error_info::ErrorInfo].
+pub const GREPTIME_DB_HEADER_ERROR_INFO: &str = "x-greptime-err-info";
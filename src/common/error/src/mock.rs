@@ -17,6 +17,7 @@
 use std::any::Any;
 use std::fmt;
 
+use crate::error_info::StackFrame;
 use crate::ext::{ErrorExt, StackError};
 use crate::status_code::StatusCode;
 
@@ -67,6 +68,8 @@ impl ErrorExt for MockError {
 impl StackError for MockError {
     fn debug_fmt(&self, _: usize, _: &mut Vec<String>) {}
 
+    fn stack_frames(&self, _: &mut Vec<StackFrame>) {}
+
     fn next(&self) -> Option<&dyn StackError> {
         None
     }
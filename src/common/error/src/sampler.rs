@@ -0,0 +1,257 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Sampling of the errors to log, so a failure happening at a high frequency doesn't flood the
+//! logs with identical error stacks.
+//!
+//! Errors are grouped by their [StatusCode] and their origin, i.e., where the root cause is
+//! created, see [ErrorInfo::origin](crate::error_info::ErrorInfo::origin). Only a few errors of
+//! each group are emitted in an interval, the others are suppressed and counted, the count is
+//! told by the next emitted one, see [Sample::suppressed_note].
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::error_info::StackFrame;
+use crate::ext::{ErrorExt, StackError};
+use crate::status_code::StatusCode;
+
+/// The default interval the errors of a group are sampled in.
+pub const DEFAULT_SAMPLE_INTERVAL: Duration = Duration::from_secs(10);
+/// The default max number of errors of a group emitted in an interval.
+pub const DEFAULT_MAX_SAMPLES_PER_INTERVAL: u64 = 10;
+/// The default max number of groups tracked by origin. Errors of new origins are grouped by
+/// their codes only once exceeded, so the memory is bounded.
+pub const DEFAULT_MAX_SAMPLE_KEYS: usize = 1024;
+
+/// The group of an error.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SampleKey {
+    pub code: StatusCode,
+    /// The innermost frame of the error stack with a location, `None` if unknown.
+    pub origin: Option<StackFrame>,
+}
+
+impl SampleKey {
+    pub fn new(code: StatusCode, err: &dyn StackError) -> Self {
+        let mut frames = Vec::new();
+        err.stack_frames(&mut frames);
+        let origin = frames.into_iter().rev().find(StackFrame::has_location);
+        Self { code, origin }
+    }
+}
+
+impl fmt::Display for SampleKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.origin {
+            Some(origin) => write!(f, "{} from {origin}", self.code),
+            None => write!(f, "{}", self.code),
+        }
+    }
+}
+
+/// Whether to emit a sampled error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sample {
+    /// Emits the error, `suppressed` errors of the same group are suppressed since the last
+    /// emitted one.
+    Emit { suppressed: u64 },
+    /// Suppresses the error.
+    Suppress,
+}
+
+impl Sample {
+    pub fn is_emit(&self) -> bool {
+        matches!(self, Sample::Emit { .. })
+    }
+
+    /// Returns the note to log the emitted error with, telling how many errors of its group
+    /// are suppressed since the last emitted one, or an empty string if none is.
+    pub fn suppressed_note(&self) -> String {
+        match self {
+            Sample::Emit { suppressed } if *suppressed > 0 => {
+                format!(", {suppressed} similar errors suppressed")
+            }
+            _ => String::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct SampleState {
+    interval_start: Instant,
+    /// Number of errors emitted in the current interval.
+    emitted: u64,
+    /// Number of errors suppressed since the last emitted one.
+    suppressed: u64,
+}
+
+/// Samples the errors to emit, by their groups, see the [module docs](self).
+#[derive(Debug)]
+pub struct ErrorSampler {
+    interval: Duration,
+    max_samples_per_interval: u64,
+    max_keys: usize,
+    states: Mutex<HashMap<SampleKey, SampleState>>,
+}
+
+impl Default for ErrorSampler {
+    fn default() -> Self {
+        Self::new(DEFAULT_SAMPLE_INTERVAL, DEFAULT_MAX_SAMPLES_PER_INTERVAL)
+    }
+}
+
+impl ErrorSampler {
+    /// Creates a sampler emitting at most `max_samples_per_interval` errors of each group in
+    /// every `interval`.
+    pub fn new(interval: Duration, max_samples_per_interval: u64) -> Self {
+        Self {
+            interval,
+            max_samples_per_interval,
+            max_keys: DEFAULT_MAX_SAMPLE_KEYS,
+            states: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Sets the max number of groups tracked by origin.
+    pub fn with_max_keys(mut self, max_keys: usize) -> Self {
+        self.max_keys = max_keys;
+        self
+    }
+
+    /// Samples the error, grouped by its [StatusCode] and origin.
+    pub fn sample<E: ErrorExt>(&self, err: &E) -> Sample {
+        self.sample_stack(err.status_code(), err)
+    }
+
+    /// Samples the error of the `code`, for the errors not implementing [ErrorExt].
+    pub fn sample_stack(&self, code: StatusCode, err: &dyn StackError) -> Sample {
+        self.sample_key(SampleKey::new(code, err), Instant::now())
+    }
+
+    fn sample_key(&self, key: SampleKey, now: Instant) -> Sample {
+        let mut states = self.states.lock().unwrap();
+        let key = if states.len() >= self.max_keys && !states.contains_key(&key) {
+            SampleKey {
+                code: key.code,
+                origin: None,
+            }
+        } else {
+            key
+        };
+        let state = states.entry(key).or_insert_with(|| SampleState {
+            interval_start: now,
+            emitted: 0,
+            suppressed: 0,
+        });
+
+        if now.saturating_duration_since(state.interval_start) >= self.interval {
+            state.interval_start = now;
+            state.emitted = 0;
+        }
+        if state.emitted < self.max_samples_per_interval {
+            state.emitted += 1;
+            Sample::Emit {
+                suppressed: std::mem::take(&mut state.suppressed),
+            }
+        } else {
+            state.suppressed += 1;
+            Sample::Suppress
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: StatusCode, line: u32) -> SampleKey {
+        SampleKey {
+            code,
+            origin: Some(StackFrame {
+                type_name: Some("Error::Foo".to_string()),
+                file: Some("src/a.rs".to_string()),
+                line: Some(line),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_sample_errors() {
+        let sampler = ErrorSampler::new(Duration::from_secs(10), 2);
+        let now = Instant::now();
+        let a = key(StatusCode::Internal, 1);
+        let b = key(StatusCode::Internal, 2);
+
+        assert_eq!(
+            sampler.sample_key(a.clone(), now),
+            Sample::Emit { suppressed: 0 }
+        );
+        assert!(sampler.sample_key(a.clone(), now).is_emit());
+        assert_eq!(sampler.sample_key(a.clone(), now), Sample::Suppress);
+        assert_eq!(
+            sampler.sample_key(a.clone(), now + Duration::from_secs(9)),
+            Sample::Suppress
+        );
+        // errors of other origins are sampled separately
+        assert!(sampler.sample_key(b.clone(), now).is_emit());
+
+        // the suppressed ones are told in the next interval
+        let now = now + Duration::from_secs(10);
+        assert_eq!(
+            sampler.sample_key(a.clone(), now),
+            Sample::Emit { suppressed: 2 }
+        );
+        assert_eq!(sampler.sample_key(a, now), Sample::Emit { suppressed: 0 });
+
+        assert_eq!(Sample::Emit { suppressed: 0 }.suppressed_note(), "");
+        assert_eq!(
+            Sample::Emit { suppressed: 2 }.suppressed_note(),
+            ", 2 similar errors suppressed"
+        );
+        assert_eq!(Sample::Suppress.suppressed_note(), "");
+    }
+
+    #[test]
+    fn test_sample_too_many_origins() {
+        let sampler = ErrorSampler::new(Duration::from_secs(10), 1).with_max_keys(2);
+        let now = Instant::now();
+        // the errors of new origins are grouped by their codes once the keys exceed
+        let samples = (0..10)
+            .map(|line| sampler.sample_key(key(StatusCode::Internal, line), now))
+            .collect::<Vec<_>>();
+        assert!(samples[..3].iter().all(Sample::is_emit));
+        assert!(samples[3..].iter().all(|s| *s == Sample::Suppress));
+        assert!(sampler
+            .sample_key(key(StatusCode::Unexpected, 10), now)
+            .is_emit());
+        assert_eq!(
+            sampler.sample_key(key(StatusCode::Internal, 0), now),
+            Sample::Suppress
+        );
+        assert_eq!(sampler.states.lock().unwrap().len(), 4);
+
+        let unknown = |code| SampleKey { code, origin: None };
+        assert_eq!(
+            unknown(StatusCode::Internal).to_string(),
+            StatusCode::Internal.to_string()
+        );
+        assert_eq!(
+            key(StatusCode::Internal, 1).to_string(),
+            format!("{} from Error::Foo at src/a.rs:1", StatusCode::Internal)
+        );
+    }
+}
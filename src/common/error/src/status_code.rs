@@ -13,12 +13,21 @@
 // limitations under the License.
 
 use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use strum::{AsRefStr, EnumIter, EnumString, FromRepr};
+use tonic::codegen::http::{HeaderMap, HeaderValue};
+use tonic::metadata::MetadataMap;
 use tonic::Code;
 
+use crate::error_info::{local_node, ErrorInfo, Hop, StackFrame};
+use crate::ext::{ErrorExt, StackError};
+use crate::{
+    GREPTIME_DB_HEADER_ERROR_CODE, GREPTIME_DB_HEADER_ERROR_INFO, GREPTIME_DB_HEADER_ERROR_STACK,
+};
+
 /// Common status code for public API.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, AsRefStr, EnumIter, FromRepr)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumString, AsRefStr, EnumIter, FromRepr)]
 pub enum StatusCode {
     // ====== Begin of common status code ==============
     /// Success.
@@ -116,6 +125,8 @@ pub enum StatusCode {
     // ====== Begin of flow related status code =====
     FlowAlreadyExists = 8000,
     FlowNotFound = 8001,
+    /// The number of flows exceeds the configured limits.
+    FlowQuotaExceeded = 8002,
     // ====== End of flow related status code =====
 }
 
@@ -152,6 +163,7 @@ impl StatusCode {
             | StatusCode::RegionNotFound
             | StatusCode::FlowAlreadyExists
             | StatusCode::FlowNotFound
+            | StatusCode::FlowQuotaExceeded
             | StatusCode::RegionReadonly
             | StatusCode::TableColumnNotFound
             | StatusCode::TableColumnExists
@@ -194,6 +206,7 @@ impl StatusCode {
             | StatusCode::PlanQuery
             | StatusCode::FlowAlreadyExists
             | StatusCode::FlowNotFound
+            | StatusCode::FlowQuotaExceeded
             | StatusCode::RegionNotReady
             | StatusCode::RegionBusy
             | StatusCode::RegionReadonly
@@ -214,11 +227,84 @@ impl StatusCode {
         }
     }
 
+    /// Returns the [StatusCategory] of the error with this code.
+    pub fn category(&self) -> StatusCategory {
+        match self {
+            StatusCode::Success => StatusCategory::Success,
+
+            StatusCode::Unsupported
+            | StatusCode::InvalidArguments
+            | StatusCode::Cancelled
+            | StatusCode::InvalidSyntax
+            | StatusCode::PlanQuery
+            | StatusCode::TableAlreadyExists
+            | StatusCode::TableNotFound
+            | StatusCode::TableColumnNotFound
+            | StatusCode::TableColumnExists
+            | StatusCode::DatabaseNotFound
+            | StatusCode::DatabaseAlreadyExists
+            | StatusCode::RegionAlreadyExists
+            | StatusCode::RegionNotFound
+            | StatusCode::RegionReadonly
+            | StatusCode::FlowAlreadyExists
+            | StatusCode::FlowNotFound
+            | StatusCode::UserNotFound
+            | StatusCode::UnsupportedPasswordType
+            | StatusCode::UserPasswordMismatch
+            | StatusCode::AuthHeaderNotFound
+            | StatusCode::InvalidAuthHeader
+            | StatusCode::AccessDenied
+            | StatusCode::PermissionDenied
+            | StatusCode::RequestOutdated => StatusCategory::User,
+
+            StatusCode::Unknown
+            | StatusCode::Unexpected
+            | StatusCode::Internal
+            | StatusCode::IllegalState
+            | StatusCode::EngineExecuteQuery => StatusCategory::Internal,
+
+            StatusCode::RuntimeResourcesExhausted
+            | StatusCode::RateLimited
+            | StatusCode::RegionBusy
+            | StatusCode::FlowQuotaExceeded => StatusCategory::Resource,
+
+            StatusCode::StorageUnavailable
+            | StatusCode::RegionNotReady
+            | StatusCode::TableUnavailable
+            | StatusCode::External => StatusCategory::Unavailable,
+        }
+    }
+
     pub fn from_u32(value: u32) -> Option<Self> {
         StatusCode::from_repr(value as usize)
     }
 }
 
+/// The category of a [StatusCode], telling what causes the error and how to handle it.
+///
+/// Whether an error is worth retrying is told by [StatusCode::is_retryable], as some errors of
+/// the same category differ, e.g. an exceeded quota is not released by retrying.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AsRefStr, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum StatusCategory {
+    /// Not an error, the request succeeds.
+    Success,
+    /// The request is invalid or not allowed, it fails again if it's sent again as is.
+    User,
+    /// The server fails unexpectedly.
+    Internal,
+    /// The resources are exhausted, e.g. quotas or memory.
+    Resource,
+    /// The server or the services it depends on are unavailable for now.
+    Unavailable,
+}
+
+impl fmt::Display for StatusCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_ref())
+    }
+}
+
 impl fmt::Display for StatusCode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         // The current debug format is suitable to display.
@@ -231,30 +317,300 @@ macro_rules! define_into_tonic_status {
     ($Error: ty) => {
         impl From<$Error> for tonic::Status {
             fn from(err: $Error) -> Self {
-                use tonic::codegen::http::{HeaderMap, HeaderValue};
-                use tonic::metadata::MetadataMap;
-                use $crate::GREPTIME_DB_HEADER_ERROR_CODE;
-
-                let mut headers = HeaderMap::<HeaderValue>::with_capacity(2);
-
-                // If either of the status_code or error msg cannot convert to valid HTTP header value
-                // (which is a very rare case), just ignore. Client will use Tonic status code and message.
-                let status_code = err.status_code();
-                headers.insert(
-                    GREPTIME_DB_HEADER_ERROR_CODE,
-                    HeaderValue::from(status_code as u32),
-                );
-                let root_error = err.output_msg();
-
-                let metadata = MetadataMap::from_headers(headers);
-                tonic::Status::with_metadata(
-                    $crate::status_code::status_to_tonic_code(status_code),
-                    root_error,
-                    metadata,
-                )
+                $crate::status_code::to_tonic_status(&err, false)
             }
         }
     };
+    // Also sends the stack of the error, for services whose clients are other nodes.
+    ($Error: ty, with_stack) => {
+        impl From<$Error> for tonic::Status {
+            fn from(err: $Error) -> Self {
+                $crate::status_code::to_tonic_status(&err, true)
+            }
+        }
+    };
+}
+
+/// The default max size of the error stack sent in the headers.
+pub const DEFAULT_MAX_ERROR_STACK_SIZE: usize = 4096;
+
+/// The max size of the error stack sent in the headers, the layers in the middle of the stack
+/// are dropped if exceeded.
+///
+/// The stack is sent in three encodings, i.e. the header [GREPTIME_DB_HEADER_ERROR_STACK] for
+/// older clients, the [ErrorInfo] in [GREPTIME_DB_HEADER_ERROR_INFO] and the status details,
+/// the size limits all of them in total.
+static MAX_ERROR_STACK_SIZE: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_ERROR_STACK_SIZE);
+
+/// The size reserved for the marker of the truncated layers, including its separator.
+const TRUNCATED_MARKER_SIZE: usize = 32;
+
+/// Sets the max size of the error stack sent in the headers, it's usually set on startup.
+///
+/// The headers of a response are limited by HTTP/2, e.g. 16KiB by default for many gRPC
+/// clients, so a too large stack makes the response fail to be decoded.
+pub fn set_max_error_stack_size(size: usize) {
+    MAX_ERROR_STACK_SIZE.store(size, Ordering::Relaxed);
+}
+
+/// Returns the max size of the error stack sent in the headers.
+pub fn max_error_stack_size() -> usize {
+    MAX_ERROR_STACK_SIZE.load(Ordering::Relaxed)
+}
+
+/// Converts the error to a tonic [Status](tonic::Status), with its [StatusCode] and optionally
+/// its stack in the headers.
+pub fn to_tonic_status<E: ErrorExt>(err: &E, with_stack: bool) -> tonic::Status {
+    let mut headers = HeaderMap::<HeaderValue>::with_capacity(3);
+
+    // If either of the status_code or error msg cannot convert to valid HTTP header value
+    // (which is a very rare case), just ignore. Client will use Tonic status code and message.
+    let status_code = err.status_code();
+    headers.insert(
+        GREPTIME_DB_HEADER_ERROR_CODE,
+        HeaderValue::from(status_code as u32),
+    );
+    let root_error = err.output_msg();
+    let max_size = max_error_stack_size();
+    let hops = error_hops(err, status_code, local_node());
+
+    // The stack is sent in every encoding, so it's truncated further until they fit in the max
+    // size in total.
+    let mut stack_size = max_size;
+    let (stack, encoded_info, details) = loop {
+        let (stack, frames) = if with_stack {
+            error_stack(err, stack_size)
+        } else {
+            (vec![], vec![])
+        };
+        let encoded_stack = with_stack.then(|| stack.join("\t"));
+        // The message is carried by the status anyway, so a too large one is not sent twice.
+        let info = ErrorInfo {
+            code: Some(status_code),
+            msg: (root_error.len() <= max_size).then(|| root_error.clone()),
+            stack,
+            hops: hops.clone(),
+            frames,
+        };
+        let encoded_info = info.encode();
+        let details = info.to_status_details();
+        // The details are sent in base64 without padding.
+        let size = encoded_stack.as_ref().map_or(0, String::len)
+            + encoded_info.len()
+            + (details.len() * 4).div_ceil(3);
+        if !with_stack || size <= max_size || stack_size == 0 {
+            break (encoded_stack, encoded_info, details);
+        }
+        // The stack takes a bit more than a third of the size, so shrinking it by a quarter of
+        // the exceeded size converges in a few rounds.
+        stack_size = stack_size.saturating_sub((size - max_size).div_ceil(4));
+    };
+    if let Some(stack) = stack {
+        if let Ok(value) = HeaderValue::from_bytes(stack.as_bytes()) {
+            let _ = headers.insert(GREPTIME_DB_HEADER_ERROR_STACK, value);
+        }
+    }
+    if let Ok(value) = HeaderValue::from_str(&encoded_info) {
+        let _ = headers.insert(GREPTIME_DB_HEADER_ERROR_INFO, value);
+    }
+
+    let metadata = MetadataMap::from_headers(headers);
+    tonic::Status::with_details_and_metadata(
+        status_to_tonic_code(status_code),
+        root_error,
+        details.into(),
+        metadata,
+    )
+}
+
+/// Returns the hops of the error, the hops of the remote error it's caused by if any, then
+/// this node if it's known.
+fn error_hops(
+    err: &dyn StackError,
+    status_code: StatusCode,
+    local_node: Option<(&str, &str)>,
+) -> Vec<Hop> {
+    let mut hops = Vec::new();
+    let mut current = Some(err);
+    while let Some(err) = current {
+        if let Some(remote_hops) = err.remote_hops() {
+            hops.extend_from_slice(remote_hops);
+            break;
+        }
+        current = err.next();
+    }
+    if let Some((role, addr)) = local_node {
+        hops.push(Hop {
+            role: role.to_string(),
+            addr: addr.to_string(),
+            code: status_code as u32,
+        });
+    }
+    hops
+}
+
+/// Encodes the layers of the error stack into a header value, separated by tabs. The layer
+/// numbers are stripped.
+pub fn encode_error_stack(err: &dyn StackError) -> String {
+    truncate_error_stack(error_stack_layers(err), max_error_stack_size()).join("\t")
+}
+
+/// Returns the layers of the error stack, without the layer numbers and control characters.
+fn error_stack_layers(err: &dyn StackError) -> Vec<String> {
+    let mut lines = Vec::new();
+    err.debug_fmt(0, &mut lines);
+
+    lines
+        .iter()
+        .map(|line| strip_layer_number(line).replace(char::is_control, " "))
+        .collect()
+}
+
+/// Returns the layers of the error stack and where they are from, truncated together by
+/// [truncate_error_stack_with_frames] if they are larger than `max_size`.
+fn error_stack(err: &dyn StackError, max_size: usize) -> (Vec<String>, Vec<StackFrame>) {
+    let mut frames = Vec::new();
+    err.stack_frames(&mut frames);
+    truncate_error_stack_with_frames(error_stack_layers(err), frames, max_size)
+}
+
+/// The size a frame takes in the JSON encoding of the frames, including its separator.
+fn frame_size(frame: &StackFrame) -> usize {
+    serde_json::to_string(frame).map_or(0, |json| json.len() + 1)
+}
+
+/// Truncates the layers of the error stack to make them joined by [encode_error_stack] no
+/// larger than `max_size`.
+///
+/// The outermost layer and the root cause are always kept, as they tell what failed and why.
+/// The layers next to them are kept alternately as many as possible, and the ones in the
+/// middle are replaced by a marker telling how many layers are truncated. A single layer too
+/// large is clipped, ending with `...`.
+pub fn truncate_error_stack(layers: Vec<String>, max_size: usize) -> Vec<String> {
+    truncate_error_stack_with_frames(layers, vec![], max_size).0
+}
+
+/// Truncates the layers of the error stack and where they are from together, like
+/// [truncate_error_stack], to make the layers and the JSON encoding of the frames no larger
+/// than `max_size` in total, roughly.
+///
+/// The frames of the kept layers are kept and the marker takes an empty frame, so the frames
+/// still match the layers one by one. The frames are dropped if they don't match the layers,
+/// or they leave no room for the outermost layer and the root cause.
+pub fn truncate_error_stack_with_frames(
+    mut layers: Vec<String>,
+    mut frames: Vec<StackFrame>,
+    max_size: usize,
+) -> (Vec<String>, Vec<StackFrame>) {
+    if frames.len() != layers.len() {
+        frames.clear();
+    }
+    // Each layer takes its size and a separator, so does its frame.
+    let item_size =
+        |layers: &[String], i: usize| layers[i].len() + 1 + frames.get(i).map_or(0, frame_size);
+    let stack_size = (0..layers.len())
+        .map(|i| item_size(&layers, i))
+        .sum::<usize>();
+    if stack_size <= max_size + 1 {
+        return (layers, frames);
+    }
+
+    let len = layers.len();
+    let marker_size = if frames.is_empty() {
+        TRUNCATED_MARKER_SIZE
+    } else {
+        TRUNCATED_MARKER_SIZE + frame_size(&StackFrame::default())
+    };
+    let budget = if len == 1 {
+        max_size
+    } else {
+        max_size.saturating_sub(marker_size)
+    };
+    let ends_frame_size = match (frames.first(), frames.last()) {
+        (Some(first), Some(last)) if len > 1 => frame_size(first) + frame_size(last),
+        (Some(frame), _) => frame_size(frame),
+        _ => 0,
+    };
+    if ends_frame_size > budget / 2 {
+        return truncate_error_stack_with_frames(layers, vec![], max_size);
+    }
+    let layer_budget = budget - ends_frame_size;
+    if len == 1 {
+        clip_layer(&mut layers[0], layer_budget);
+        return (layers, frames);
+    }
+
+    clip_layer(&mut layers[0], (layer_budget / 2).saturating_sub(1));
+    clip_layer(&mut layers[len - 1], (layer_budget / 2).saturating_sub(1));
+    let mut size = item_size(&layers, 0) + item_size(&layers, len - 1);
+    let (mut front, mut back) = (1, len - 1);
+    let (mut front_full, mut back_full) = (false, false);
+    while front < back && !(front_full && back_full) {
+        if !front_full {
+            let layer_size = item_size(&layers, front);
+            if size + layer_size <= budget {
+                size += layer_size;
+                front += 1;
+            } else {
+                front_full = true;
+            }
+        }
+        if front < back && !back_full {
+            let layer_size = item_size(&layers, back - 1);
+            if size + layer_size <= budget {
+                size += layer_size;
+                back -= 1;
+            } else {
+                back_full = true;
+            }
+        }
+    }
+
+    let truncated = back - front;
+    if truncated > 0 {
+        let _ = layers.splice(
+            front..back,
+            [format!("... {truncated} layers truncated ...")],
+        );
+        if !frames.is_empty() {
+            let _ = frames.splice(front..back, [StackFrame::default()]);
+        }
+    }
+    (layers, frames)
+}
+
+/// Clips the layer to at most `max_size` bytes, ending with `...`.
+fn clip_layer(layer: &mut String, max_size: usize) {
+    const ELLIPSIS: &str = "...";
+
+    if layer.len() <= max_size {
+        return;
+    }
+    let mut end = max_size.saturating_sub(ELLIPSIS.len());
+    while !layer.is_char_boundary(end) {
+        end -= 1;
+    }
+    layer.truncate(end);
+    if max_size >= ELLIPSIS.len() {
+        layer.push_str(ELLIPSIS);
+    }
+}
+
+/// Decodes the layers of the error stack encoded by [encode_error_stack].
+pub fn decode_error_stack(stack: &str) -> Vec<String> {
+    stack
+        .split('\t')
+        .filter(|layer| !layer.is_empty())
+        .map(ToString::to_string)
+        .collect()
+}
+
+/// Strips the leading `<layer>: ` of a line formatted by [StackError::debug_fmt].
+fn strip_layer_number(line: &str) -> &str {
+    match line.split_once(": ") {
+        Some((layer, rest)) if layer.parse::<usize>().is_ok() => rest,
+        _ => line,
+    }
 }
 
 /// Returns the tonic [Code] of a [StatusCode].
@@ -288,6 +644,7 @@ pub fn status_to_tonic_code(status_code: StatusCode) -> Code {
         | StatusCode::RegionNotReady => Code::Unavailable,
         StatusCode::RuntimeResourcesExhausted
         | StatusCode::RateLimited
+        | StatusCode::FlowQuotaExceeded
         | StatusCode::RegionBusy => Code::ResourceExhausted,
         StatusCode::UnsupportedPasswordType
         | StatusCode::UserPasswordMismatch
@@ -304,6 +661,8 @@ mod tests {
     use strum::IntoEnumIterator;
 
     use super::*;
+    use crate::ext::RemoteStackError;
+    use crate::mock::MockError;
 
     fn assert_status_code_display(code: StatusCode, msg: &str) {
         let code_msg = format!("{code}");
@@ -326,6 +685,30 @@ mod tests {
         assert_eq!(StatusCode::from_u32(10000), None);
     }
 
+    #[test]
+    fn test_status_category() {
+        assert_eq!(StatusCode::Success.category(), StatusCategory::Success);
+        assert_eq!(StatusCode::TableNotFound.category(), StatusCategory::User);
+        assert_eq!(StatusCode::Unexpected.category(), StatusCategory::Internal);
+        assert_eq!(StatusCode::RateLimited.category(), StatusCategory::Resource);
+        assert_eq!(
+            StatusCode::StorageUnavailable.category(),
+            StatusCategory::Unavailable
+        );
+        assert_eq!(StatusCategory::Unavailable.to_string(), "unavailable");
+        assert_eq!(
+            "resource".parse::<StatusCategory>().unwrap(),
+            StatusCategory::Resource
+        );
+
+        // errors caused by the users never succeed by retrying
+        for code in StatusCode::iter() {
+            if code.category() == StatusCategory::User {
+                assert!(!code.is_retryable(), "{code} is retryable");
+            }
+        }
+    }
+
     #[test]
     fn test_is_success() {
         assert!(StatusCode::is_success(0));
@@ -333,4 +716,298 @@ mod tests {
         assert!(!StatusCode::is_success(2));
         assert!(!StatusCode::is_success(3));
     }
+
+    /// An error of the stack `layers`.
+    #[derive(Debug)]
+    struct StackedError {
+        layers: Vec<String>,
+    }
+
+    impl fmt::Display for StackedError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.layers[0])
+        }
+    }
+
+    impl std::error::Error for StackedError {}
+
+    impl StackError for StackedError {
+        fn debug_fmt(&self, layer: usize, buf: &mut Vec<String>) {
+            for (i, msg) in self.layers.iter().enumerate() {
+                buf.push(format!("{}: {}", layer + i, msg));
+            }
+        }
+
+        fn next(&self) -> Option<&dyn StackError> {
+            None
+        }
+    }
+
+    impl ErrorExt for StackedError {
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+    }
+
+    #[test]
+    fn test_encode_error_stack() {
+        let err = StackedError {
+            layers: vec![
+                "Failed to handle request, at src/a.rs:1:1".to_string(),
+                "Table not found: t\n".to_string(),
+            ],
+        };
+        let stack = encode_error_stack(&err);
+        assert_eq!(
+            stack,
+            "Failed to handle request, at src/a.rs:1:1\tTable not found: t "
+        );
+        assert_eq!(
+            decode_error_stack(&stack),
+            vec![
+                "Failed to handle request, at src/a.rs:1:1".to_string(),
+                "Table not found: t ".to_string()
+            ]
+        );
+        assert!(decode_error_stack("").is_empty());
+
+        // layers in the middle are dropped if the stack is too large
+        let err = StackedError {
+            layers: vec!["a".repeat(1024); 10],
+        };
+        let stack = encode_error_stack(&err);
+        assert!(stack.len() <= DEFAULT_MAX_ERROR_STACK_SIZE);
+        let layers = decode_error_stack(&stack);
+        assert_eq!(layers.len(), 4);
+        assert_eq!(layers[2], "... 7 layers truncated ...");
+    }
+
+    #[test]
+    fn test_truncate_error_stack_with_frames() {
+        let stack = (0..10).map(|i| format!("layer{i}")).collect::<Vec<_>>();
+        let frames = (0..10)
+            .map(|i| StackFrame {
+                type_name: Some(format!("Error{i}")),
+                file: Some("src/a.rs".to_string()),
+                line: Some(i),
+            })
+            .collect::<Vec<_>>();
+        // each layer takes 7 bytes and its frame takes 45 bytes
+        assert_eq!(
+            truncate_error_stack_with_frames(stack.clone(), frames.clone(), 520),
+            (stack.clone(), frames.clone())
+        );
+
+        // the frames of the kept layers are kept, and the marker takes an empty frame
+        let (layers, truncated) =
+            truncate_error_stack_with_frames(stack.clone(), frames.clone(), 32 + 3 + 52 * 4);
+        assert_eq!(
+            layers,
+            vec![
+                "layer0",
+                "layer1",
+                "... 6 layers truncated ...",
+                "layer8",
+                "layer9"
+            ]
+        );
+        assert_eq!(
+            truncated,
+            vec![
+                frames[0].clone(),
+                frames[1].clone(),
+                StackFrame::default(),
+                frames[8].clone(),
+                frames[9].clone()
+            ]
+        );
+
+        // the frames are dropped if they leave no room for the layers
+        assert_eq!(
+            truncate_error_stack_with_frames(stack.clone(), frames.clone(), 32 + 3 + 100),
+            (stack.clone(), vec![])
+        );
+        // or they don't match the layers
+        assert_eq!(
+            truncate_error_stack_with_frames(stack.clone(), frames[..1].to_vec(), 520),
+            (stack, vec![])
+        );
+    }
+
+    #[test]
+    fn test_truncate_error_stack() {
+        let layers = |names: &[&str]| names.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        let stack = layers(&["outer", "middle", "root"]);
+        assert_eq!(truncate_error_stack(stack.clone(), 17), stack);
+
+        // keeps the outermost layer and the root cause, then the layers next to them
+        let stack = (0..10).map(|i| format!("layer{i}")).collect::<Vec<_>>();
+        let truncated = truncate_error_stack(stack, 32 + 7 * 4);
+        assert_eq!(
+            truncated,
+            layers(&[
+                "layer0",
+                "layer1",
+                "... 6 layers truncated ...",
+                "layer8",
+                "layer9"
+            ])
+        );
+        assert!(truncated.join("\t").len() <= 32 + 7 * 4);
+
+        // too large layers are clipped
+        let stack = vec!["a".repeat(100), "b".repeat(10), "c".repeat(100)];
+        let truncated = truncate_error_stack(stack, 32 + 40);
+        assert_eq!(
+            truncated,
+            vec![
+                format!("{}...", "a".repeat(16)),
+                "... 1 layers truncated ...".to_string(),
+                format!("{}...", "c".repeat(16)),
+            ]
+        );
+        let truncated = truncate_error_stack(vec!["表".repeat(10)], 10);
+        assert_eq!(truncated, vec![format!("{}...", "表".repeat(2))]);
+    }
+
+    /// An error caused by the error returned by another node.
+    #[derive(Debug)]
+    struct ForwardedError {
+        source: RemoteStackError,
+    }
+
+    impl fmt::Display for ForwardedError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "Failed to request datanode")
+        }
+    }
+
+    impl std::error::Error for ForwardedError {}
+
+    impl StackError for ForwardedError {
+        fn debug_fmt(&self, layer: usize, buf: &mut Vec<String>) {
+            buf.push(format!("{layer}: {self}"));
+            self.source.debug_fmt(layer + 1, buf);
+        }
+
+        fn next(&self) -> Option<&dyn StackError> {
+            Some(&self.source)
+        }
+    }
+
+    #[test]
+    fn test_error_hops() {
+        let datanode = Hop {
+            role: "datanode".to_string(),
+            addr: "127.0.0.1:3001".to_string(),
+            code: StatusCode::RegionNotFound as u32,
+        };
+        let err = ForwardedError {
+            source: RemoteStackError::new(
+                "Region not found".to_string(),
+                vec!["Failed to handle request".to_string()],
+                vec![datanode.clone()],
+            ),
+        };
+
+        let hops = error_hops(
+            &err,
+            StatusCode::TableNotFound,
+            Some(("frontend", "127.0.0.1:4001")),
+        );
+        assert_eq!(
+            hops,
+            vec![
+                datanode.clone(),
+                Hop {
+                    role: "frontend".to_string(),
+                    addr: "127.0.0.1:4001".to_string(),
+                    code: StatusCode::TableNotFound as u32,
+                }
+            ]
+        );
+        // this node is not appended if it's unknown
+        assert_eq!(
+            error_hops(&err, StatusCode::TableNotFound, None),
+            vec![datanode]
+        );
+        assert!(error_hops(
+            &MockError::new(StatusCode::Internal),
+            StatusCode::Internal,
+            None
+        )
+        .is_empty());
+
+        // the stack shows where the error is from
+        let mut buf = vec![];
+        err.debug_fmt(0, &mut buf);
+        assert_eq!(
+            buf,
+            vec![
+                "0: Failed to request datanode",
+                "1: Remote error via datanode 127.0.0.1:3001 (RegionNotFound)",
+                "2: Failed to handle request",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_to_tonic_status() {
+        let err = MockError::new(StatusCode::InvalidArguments);
+        let status = to_tonic_status(&err, false);
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert_eq!(
+            status
+                .metadata()
+                .get(GREPTIME_DB_HEADER_ERROR_CODE)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            (StatusCode::InvalidArguments as u32).to_string()
+        );
+        assert!(status
+            .metadata()
+            .get(GREPTIME_DB_HEADER_ERROR_STACK)
+            .is_none());
+
+        let status = to_tonic_status(&err, true);
+        assert!(status
+            .metadata()
+            .get(GREPTIME_DB_HEADER_ERROR_STACK)
+            .is_some());
+
+        let info = ErrorInfo::from_metadata(status.metadata());
+        assert_eq!(info.code, Some(StatusCode::InvalidArguments));
+        assert_eq!(info.msg.as_deref(), Some(status.message()));
+        assert!(status
+            .metadata()
+            .get(GREPTIME_DB_HEADER_ERROR_INFO)
+            .is_some());
+        assert_eq!(
+            ErrorInfo::from_status_details(status.details()).unwrap(),
+            info
+        );
+
+        // all encodings of the stack fit in the max size in total
+        let err = StackedError {
+            layers: vec!["a".repeat(1024); 10],
+        };
+        let status = to_tonic_status(&err, true);
+        let header_size = |key: &str| {
+            status
+                .metadata()
+                .get(key)
+                .map_or(0, |value| value.as_encoded_bytes().len())
+        };
+        let size = header_size(GREPTIME_DB_HEADER_ERROR_STACK)
+            + header_size(GREPTIME_DB_HEADER_ERROR_INFO)
+            + (status.details().len() * 4).div_ceil(3);
+        assert!(size <= DEFAULT_MAX_ERROR_STACK_SIZE, "{size}");
+        // the outermost layer and the root cause are still kept
+        let info = ErrorInfo::from_metadata(status.metadata());
+        assert_eq!(info.stack.len(), 3);
+        assert!(info.stack[0].starts_with('a'));
+        assert!(info.stack[2].starts_with('a'));
+    }
 }
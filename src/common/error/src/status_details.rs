@@ -0,0 +1,295 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The error info carried in the `grpc-status-details-bin` of a tonic status, encoded as a
+//! `google.rpc.Status` so that gRPC clients in other languages can decode it with their
+//! standard tooling, e.g. `status.FromError` in Go or `StatusProto.fromThrowable` in Java.
+//!
+//! The details of the `google.rpc.Status` are:
+//! - a `google.rpc.ErrorInfo`, whose reason is the name of the [StatusCode], and whose metadata
+//!   has the number of the [StatusCode] under the key `code`, its
+//!   [StatusCategory](crate::status_code::StatusCategory) under the key `category`, and whether
+//!   it's retryable under the key `retryable`. If the error is forwarded, its
+//!   [hops](crate::error_info::Hop) are encoded as a JSON array under the key `hops`.
+//! - a `google.rpc.DebugInfo` if the error stack is sent, whose stack entries are the layers of
+//!   the error stack, and whose detail is the JSON array of the
+//!   [frames](crate::error_info::StackFrame) of the stack if they are sent.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use prost::Message;
+
+use crate::error_info::ErrorInfo;
+use crate::status_code::{status_to_tonic_code, StatusCode};
+
+/// The domain of the `google.rpc.ErrorInfo` of GreptimeDB errors.
+pub const ERROR_DOMAIN: &str = "greptime.com";
+
+const ERROR_INFO_TYPE_URL: &str = "type.googleapis.com/google.rpc.ErrorInfo";
+const DEBUG_INFO_TYPE_URL: &str = "type.googleapis.com/google.rpc.DebugInfo";
+const CODE_METADATA_KEY: &str = "code";
+const CATEGORY_METADATA_KEY: &str = "category";
+const RETRYABLE_METADATA_KEY: &str = "retryable";
+const HOPS_METADATA_KEY: &str = "hops";
+
+/// `google.rpc.Status`
+#[derive(Clone, PartialEq, Message)]
+struct RpcStatus {
+    #[prost(int32, tag = "1")]
+    code: i32,
+    #[prost(string, tag = "2")]
+    message: String,
+    #[prost(message, repeated, tag = "3")]
+    details: Vec<Any>,
+}
+
+/// `google.protobuf.Any`
+#[derive(Clone, PartialEq, Message)]
+struct Any {
+    #[prost(string, tag = "1")]
+    type_url: String,
+    #[prost(bytes = "vec", tag = "2")]
+    value: Vec<u8>,
+}
+
+/// `google.rpc.ErrorInfo`
+#[derive(Clone, PartialEq, Message)]
+struct RpcErrorInfo {
+    #[prost(string, tag = "1")]
+    reason: String,
+    #[prost(string, tag = "2")]
+    domain: String,
+    #[prost(map = "string, string", tag = "3")]
+    metadata: HashMap<String, String>,
+}
+
+/// `google.rpc.DebugInfo`
+#[derive(Clone, PartialEq, Message)]
+struct RpcDebugInfo {
+    #[prost(string, repeated, tag = "1")]
+    stack_entries: Vec<String>,
+    #[prost(string, tag = "2")]
+    detail: String,
+}
+
+impl ErrorInfo {
+    /// Encodes the error info into the details of a tonic status.
+    pub fn to_status_details(&self) -> Vec<u8> {
+        let code = self.code.unwrap_or(StatusCode::Unknown);
+        let mut error_info = RpcErrorInfo {
+            reason: code.as_ref().to_string(),
+            domain: ERROR_DOMAIN.to_string(),
+            metadata: HashMap::from([
+                (CODE_METADATA_KEY.to_string(), (code as u32).to_string()),
+                (
+                    CATEGORY_METADATA_KEY.to_string(),
+                    code.category().to_string(),
+                ),
+                (
+                    RETRYABLE_METADATA_KEY.to_string(),
+                    code.is_retryable().to_string(),
+                ),
+            ]),
+        };
+        if !self.hops.is_empty() {
+            // Safety: hops only contain numbers and strings, which are always serializable.
+            let hops = serde_json::to_string(&self.hops).unwrap();
+            let _ = error_info
+                .metadata
+                .insert(HOPS_METADATA_KEY.to_string(), hops);
+        }
+        let mut details = vec![Any {
+            type_url: ERROR_INFO_TYPE_URL.to_string(),
+            value: error_info.encode_to_vec(),
+        }];
+        if !self.stack.is_empty() || !self.frames.is_empty() {
+            let detail = if self.frames.is_empty() {
+                String::new()
+            } else {
+                // Safety: frames only contain numbers and strings, which are always serializable.
+                serde_json::to_string(&self.frames).unwrap()
+            };
+            let debug_info = RpcDebugInfo {
+                stack_entries: self.stack.clone(),
+                detail,
+            };
+            details.push(Any {
+                type_url: DEBUG_INFO_TYPE_URL.to_string(),
+                value: debug_info.encode_to_vec(),
+            });
+        }
+
+        RpcStatus {
+            code: status_to_tonic_code(code) as i32,
+            message: self.msg.clone().unwrap_or_default(),
+            details,
+        }
+        .encode_to_vec()
+    }
+
+    /// Decodes the error info from the details of a tonic status, returns `None` if they are
+    /// malformed or not sent by GreptimeDB.
+    pub fn from_status_details(details: &[u8]) -> Option<Self> {
+        let status = RpcStatus::decode(details).ok()?;
+
+        let mut info = None;
+        let mut stack = Vec::new();
+        let mut frames = Vec::new();
+        for any in status.details {
+            match any.type_url.as_str() {
+                ERROR_INFO_TYPE_URL => {
+                    let error_info = RpcErrorInfo::decode(any.value.as_slice()).ok()?;
+                    if error_info.domain == ERROR_DOMAIN {
+                        info = Some(error_info);
+                    }
+                }
+                DEBUG_INFO_TYPE_URL => {
+                    let debug_info = RpcDebugInfo::decode(any.value.as_slice()).ok()?;
+                    stack = debug_info.stack_entries;
+                    // The detail is free text for others, the frames are parsed from the stack
+                    // if it's not the frames.
+                    frames = serde_json::from_str(&debug_info.detail).unwrap_or_default();
+                }
+                _ => {}
+            }
+        }
+        let info = info?;
+
+        let code = info
+            .metadata
+            .get(CODE_METADATA_KEY)
+            .and_then(|code| code.parse::<u32>().ok())
+            .and_then(StatusCode::from_u32)
+            .or_else(|| StatusCode::from_str(&info.reason).ok());
+        let hops = match info.metadata.get(HOPS_METADATA_KEY) {
+            Some(hops) => serde_json::from_str(hops).ok()?,
+            None => vec![],
+        };
+        Some(Self {
+            code,
+            msg: (!status.message.is_empty()).then_some(status.message),
+            stack,
+            hops,
+            frames,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error_info::{Hop, StackFrame};
+
+    #[test]
+    fn test_status_details() {
+        let info = ErrorInfo {
+            code: Some(StatusCode::TableNotFound),
+            msg: Some("Table not found: t".to_string()),
+            stack: vec![
+                "Failed to get table, at src/a.rs:1:1".to_string(),
+                "Table not found: t".to_string(),
+            ],
+            hops: vec![Hop {
+                role: "datanode".to_string(),
+                addr: "127.0.0.1:3001".to_string(),
+                code: StatusCode::TableNotFound as u32,
+            }],
+            frames: vec![
+                StackFrame {
+                    type_name: Some("catalog::error::Error::GetTable".to_string()),
+                    file: Some("src/a.rs".to_string()),
+                    line: Some(1),
+                },
+                StackFrame::default(),
+            ],
+        };
+        let details = info.to_status_details();
+        assert_eq!(ErrorInfo::from_status_details(&details).unwrap(), info);
+
+        // decodable as a standard google.rpc.Status
+        let status = RpcStatus::decode(details.as_slice()).unwrap();
+        assert_eq!(status.code, tonic::Code::NotFound as i32);
+        assert_eq!(status.message, "Table not found: t");
+        assert_eq!(status.details.len(), 2);
+        let error_info = RpcErrorInfo::decode(status.details[0].value.as_slice()).unwrap();
+        assert_eq!(error_info.reason, "TableNotFound");
+        assert_eq!(error_info.domain, ERROR_DOMAIN);
+        assert_eq!(error_info.metadata["code"], "4001");
+        assert_eq!(error_info.metadata["category"], "user");
+        assert_eq!(error_info.metadata["retryable"], "false");
+        assert_eq!(
+            error_info.metadata["hops"],
+            r#"[{"role":"datanode","addr":"127.0.0.1:3001","code":4001}]"#
+        );
+        let debug_info = RpcDebugInfo::decode(status.details[1].value.as_slice()).unwrap();
+        assert_eq!(debug_info.stack_entries, info.stack);
+        assert_eq!(
+            debug_info.detail,
+            r#"[{"type":"catalog::error::Error::GetTable","file":"src/a.rs","line":1},{}]"#
+        );
+
+        let info = ErrorInfo {
+            code: Some(StatusCode::Internal),
+            msg: None,
+            stack: vec![],
+            hops: vec![],
+            frames: vec![],
+        };
+        let details = info.to_status_details();
+        assert_eq!(ErrorInfo::from_status_details(&details).unwrap(), info);
+
+        // the code is also recognized by the reason
+        let status = RpcStatus {
+            code: tonic::Code::Internal as i32,
+            message: String::new(),
+            details: vec![Any {
+                type_url: ERROR_INFO_TYPE_URL.to_string(),
+                value: RpcErrorInfo {
+                    reason: "RegionNotFound".to_string(),
+                    domain: ERROR_DOMAIN.to_string(),
+                    metadata: HashMap::new(),
+                }
+                .encode_to_vec(),
+            }],
+        };
+        assert_eq!(
+            ErrorInfo::from_status_details(&status.encode_to_vec())
+                .unwrap()
+                .code,
+            Some(StatusCode::RegionNotFound)
+        );
+    }
+
+    #[test]
+    fn test_status_details_not_from_greptime() {
+        assert!(ErrorInfo::from_status_details(b"malformed").is_none());
+        assert!(ErrorInfo::from_status_details(&[]).is_none());
+
+        let status = RpcStatus {
+            code: tonic::Code::Internal as i32,
+            message: "internal".to_string(),
+            details: vec![Any {
+                type_url: ERROR_INFO_TYPE_URL.to_string(),
+                value: RpcErrorInfo {
+                    reason: "QUOTA_EXCEEDED".to_string(),
+                    domain: "example.com".to_string(),
+                    metadata: HashMap::new(),
+                }
+                .encode_to_vec(),
+            }],
+        };
+        assert!(ErrorInfo::from_status_details(&status.encode_to_vec()).is_none());
+    }
+}
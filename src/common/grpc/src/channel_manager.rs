@@ -12,24 +12,33 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::BTreeSet;
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 
+use api::v1::health_check_client::HealthCheckClient;
+use api::v1::HealthCheckRequest;
 use common_base::readable_size::ReadableSize;
-use common_telemetry::info;
+use common_telemetry::{error, info, warn};
 use dashmap::mapref::entry::Entry;
 use dashmap::DashMap;
 use lazy_static::lazy_static;
+use notify::{EventKind, RecursiveMode, Watcher};
 use snafu::{OptionExt, ResultExt};
+use tokio::task::JoinSet;
 use tonic::transport::{
     Certificate, Channel as InnerChannel, ClientTlsConfig, Endpoint, Identity, Uri,
 };
+use tonic::Code;
 use tower::make::MakeConnection;
 
-use crate::error::{CreateChannelSnafu, InvalidConfigFilePathSnafu, InvalidTlsConfigSnafu, Result};
+use crate::error::{
+    CreateChannelSnafu, FileWatchSnafu, InvalidConfigFilePathSnafu, InvalidTlsConfigSnafu, Result,
+};
 
-const RECYCLE_CHANNEL_INTERVAL_SECS: u64 = 60;
+pub const DEFAULT_CHANNEL_IDLE_TIMEOUT_SECS: u64 = 60;
 pub const DEFAULT_GRPC_REQUEST_TIMEOUT_SECS: u64 = 10;
 pub const DEFAULT_GRPC_CONNECT_TIMEOUT_SECS: u64 = 1;
 pub const DEFAULT_MAX_GRPC_RECV_MESSAGE_SIZE: ReadableSize = ReadableSize::mb(512);
@@ -43,9 +52,11 @@ lazy_static! {
 pub struct ChannelManager {
     id: u64,
     config: ChannelConfig,
-    client_tls_config: Option<ClientTlsConfig>,
+    client_tls_config: Arc<RwLock<Option<ClientTlsConfig>>>,
     pool: Arc<Pool>,
     channel_recycle_started: Arc<AtomicBool>,
+    /// Watches the TLS files if set, it's dropped with the manager and all its clones.
+    tls_watcher: Option<Arc<notify::RecommendedWatcher>>,
 }
 
 impl Default for ChannelManager {
@@ -65,9 +76,10 @@ impl ChannelManager {
         Self {
             id,
             config,
-            client_tls_config: None,
+            client_tls_config: Arc::new(RwLock::new(None)),
             pool,
             channel_recycle_started: Arc::new(AtomicBool::new(false)),
+            tls_watcher: None,
         }
     }
 
@@ -78,25 +90,115 @@ impl ChannelManager {
         let path_config = config.client_tls.context(InvalidTlsConfigSnafu {
             msg: "no config input",
         })?;
+        *cm.client_tls_config.write().unwrap() = Some(load_client_tls_config(&path_config)?);
 
-        let server_root_ca_cert = std::fs::read_to_string(path_config.server_ca_cert_path)
-            .context(InvalidConfigFilePathSnafu)?;
-        let server_root_ca_cert = Certificate::from_pem(server_root_ca_cert);
-        let client_cert = std::fs::read_to_string(path_config.client_cert_path)
-            .context(InvalidConfigFilePathSnafu)?;
-        let client_key = std::fs::read_to_string(path_config.client_key_path)
-            .context(InvalidConfigFilePathSnafu)?;
-        let client_identity = Identity::from_pem(client_cert, client_key);
-
-        cm.client_tls_config = Some(
-            ClientTlsConfig::new()
-                .ca_certificate(server_root_ca_cert)
-                .identity(client_identity),
-        );
+        if path_config.watch {
+            cm.watch_tls_config(&path_config)?;
+        }
 
         Ok(cm)
     }
 
+    /// Rereads the certificates and keys from the file system, and drops pooled channels so
+    /// later requests connect with them. Requests holding the old channels are not affected.
+    pub fn reload_tls_config(&self) -> Result<()> {
+        let path_config = self
+            .config
+            .client_tls
+            .as_ref()
+            .context(InvalidTlsConfigSnafu {
+                msg: "no config input",
+            })?;
+        reload_tls_config(path_config, &self.client_tls_config, &self.pool)
+    }
+
+    /// Reloads the TLS config once the certificates or keys change.
+    ///
+    /// The parent directories of the files are watched rather than the files, so the files
+    /// replaced by renaming or by switching symlinks, e.g. the secrets mounted by Kubernetes,
+    /// are noticed too. The watching thread only holds weak references to the manager, and
+    /// exits once the manager and all its clones are dropped.
+    fn watch_tls_config(&mut self, path_config: &ClientTlsOption) -> Result<()> {
+        let files = [
+            &path_config.server_ca_cert_path,
+            &path_config.client_cert_path,
+            &path_config.client_key_path,
+        ]
+        .map(PathBuf::from);
+        let dirs = files
+            .iter()
+            .map(|file| match file.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+                _ => PathBuf::from("."),
+            })
+            .collect::<BTreeSet<_>>();
+
+        let (tx, rx) = std::sync::mpsc::channel::<notify::Result<notify::Event>>();
+        let mut watcher =
+            notify::recommended_watcher(tx).context(FileWatchSnafu { path: "<none>" })?;
+        for dir in &dirs {
+            watcher
+                .watch(dir, RecursiveMode::NonRecursive)
+                .context(FileWatchSnafu {
+                    path: dir.display().to_string(),
+                })?;
+        }
+
+        let id = self.id;
+        let path_config = path_config.clone();
+        let client_tls_config = Arc::downgrade(&self.client_tls_config);
+        let pool = Arc::downgrade(&self.pool);
+        let _handle = std::thread::spawn(move || {
+            let names = files
+                .iter()
+                .filter_map(|file| file.file_name())
+                .collect::<Vec<_>>();
+            let resolve = || {
+                files
+                    .iter()
+                    .map(|file| std::fs::canonicalize(file).ok())
+                    .collect::<Vec<_>>()
+            };
+            let mut targets = resolve();
+            // The channel is closed once the watcher is dropped with the manager.
+            while let Ok(res) = rx.recv() {
+                let Ok(event) = res else {
+                    continue;
+                };
+                if !matches!(event.kind, EventKind::Modify(_) | EventKind::Create(_)) {
+                    continue;
+                }
+                // A file is changed in place or replaced by renaming, or the symlink it's
+                // resolved by is switched.
+                let new_targets = resolve();
+                let changed = event
+                    .paths
+                    .iter()
+                    .any(|path| path.file_name().is_some_and(|name| names.contains(&name)))
+                    || new_targets != targets;
+                if !changed {
+                    continue;
+                }
+                targets = new_targets;
+
+                let (Some(client_tls_config), Some(pool)) =
+                    (client_tls_config.upgrade(), pool.upgrade())
+                else {
+                    break;
+                };
+                info!(
+                    "ChannelManager: {}, detected TLS cert/key file change: {:?}",
+                    id, event
+                );
+                if let Err(err) = reload_tls_config(&path_config, &client_tls_config, &pool) {
+                    error!(err; "Failed to reload TLS client config");
+                }
+            }
+        });
+        self.tls_watcher = Some(Arc::new(watcher));
+        Ok(())
+    }
+
     pub fn config(&self) -> &ChannelConfig {
         &self.config
     }
@@ -110,6 +212,16 @@ impl ChannelManager {
             return Ok(inner_ch);
         }
 
+        if let Some(max_channels) = self.config.max_channels {
+            // Evicts before acquiring the entry, since iterating the pool while holding
+            // an entry deadlocks. So the limit may be exceeded by concurrent creations.
+            while self.pool.len() >= max_channels.max(1) {
+                if !self.pool.evict_least_accessed() {
+                    break;
+                }
+            }
+        }
+
         // It will acquire the write lock.
         let entry = match self.pool.entry(addr.to_string()) {
             Entry::Occupied(entry) => {
@@ -120,11 +232,7 @@ impl ChannelManager {
                 let endpoint = self.build_endpoint(addr)?;
                 let inner_channel = endpoint.connect_lazy();
 
-                let channel = Channel {
-                    channel: inner_channel,
-                    access: AtomicUsize::new(1),
-                    use_default_connector: true,
-                };
+                let channel = Channel::new(inner_channel, true);
                 entry.insert(channel)
             }
         };
@@ -145,11 +253,7 @@ impl ChannelManager {
         let addr = addr.as_ref();
         let endpoint = self.build_endpoint(addr)?;
         let inner_channel = endpoint.connect_with_connector_lazy(connector);
-        let channel = Channel {
-            channel: inner_channel.clone(),
-            access: AtomicUsize::new(1),
-            use_default_connector: false,
-        };
+        let channel = Channel::new(inner_channel.clone(), false);
         self.pool.put(addr, channel);
 
         Ok(inner_channel)
@@ -162,8 +266,19 @@ impl ChannelManager {
         self.pool.retain_channel(f);
     }
 
+    /// Returns `false` if the channel to `addr` failed its last health check.
+    ///
+    /// Always `true` for addresses not pooled or if health checking is disabled.
+    pub fn is_healthy(&self, addr: &str) -> bool {
+        self.pool
+            .channels
+            .get(addr)
+            .map_or(true, |ch| ch.is_healthy())
+    }
+
     fn build_endpoint(&self, addr: &str) -> Result<Endpoint> {
-        let http_prefix = if self.client_tls_config.is_some() {
+        let client_tls_config = self.client_tls_config.read().unwrap().clone();
+        let http_prefix = if client_tls_config.is_some() {
             "https"
         } else {
             "http"
@@ -202,9 +317,9 @@ impl ChannelManager {
         if let Some(enabled) = self.config.http2_adaptive_window {
             endpoint = endpoint.http2_adaptive_window(enabled);
         }
-        if let Some(tls_config) = &self.client_tls_config {
+        if let Some(tls_config) = client_tls_config {
             endpoint = endpoint
-                .tls_config(tls_config.clone())
+                .tls_config(tls_config)
                 .context(CreateChannelSnafu)?;
         }
 
@@ -225,13 +340,25 @@ impl ChannelManager {
         }
 
         let pool = self.pool.clone();
-        let _handle = common_runtime::spawn_global(async {
-            recycle_channel_in_loop(pool, RECYCLE_CHANNEL_INTERVAL_SECS).await;
+        let idle_timeout = self.config.idle_timeout;
+        let _handle = common_runtime::spawn_global(async move {
+            recycle_channel_in_loop(pool, idle_timeout).await;
         });
         info!(
             "ChannelManager: {}, channel recycle is started, running in the background!",
             self.id
         );
+
+        if let Some(interval) = self.config.health_check_interval {
+            let pool = self.pool.clone();
+            let _handle = common_runtime::spawn_global(async move {
+                check_channel_health_in_loop(pool, interval).await;
+            });
+            info!(
+                "ChannelManager: {}, channel health check is started, interval: {:?}",
+                self.id, interval
+            );
+        }
     }
 }
 
@@ -240,6 +367,36 @@ pub struct ClientTlsOption {
     pub server_ca_cert_path: String,
     pub client_cert_path: String,
     pub client_key_path: String,
+    /// Reload the certificates and keys once the files change.
+    pub watch: bool,
+}
+
+fn load_client_tls_config(path_config: &ClientTlsOption) -> Result<ClientTlsConfig> {
+    let server_root_ca_cert = std::fs::read_to_string(&path_config.server_ca_cert_path)
+        .context(InvalidConfigFilePathSnafu)?;
+    let server_root_ca_cert = Certificate::from_pem(server_root_ca_cert);
+    let client_cert = std::fs::read_to_string(&path_config.client_cert_path)
+        .context(InvalidConfigFilePathSnafu)?;
+    let client_key = std::fs::read_to_string(&path_config.client_key_path)
+        .context(InvalidConfigFilePathSnafu)?;
+    let client_identity = Identity::from_pem(client_cert, client_key);
+
+    Ok(ClientTlsConfig::new()
+        .ca_certificate(server_root_ca_cert)
+        .identity(client_identity))
+}
+
+/// Reloads the TLS config from the files, and drops the pooled channels using it.
+fn reload_tls_config(
+    path_config: &ClientTlsOption,
+    client_tls_config: &RwLock<Option<ClientTlsConfig>>,
+    pool: &Pool,
+) -> Result<()> {
+    let tls_config = load_client_tls_config(path_config)?;
+    *client_tls_config.write().unwrap() = Some(tls_config);
+    // channels with custom connectors don't use the TLS config
+    pool.retain_channel(|_, ch| !ch.use_default_connector());
+    Ok(())
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -261,6 +418,12 @@ pub struct ChannelConfig {
     pub max_recv_message_size: ReadableSize,
     // Max gRPC sending(encoding) message size
     pub max_send_message_size: ReadableSize,
+    /// Max channels in the pool, the least accessed one is evicted to create a new one.
+    pub max_channels: Option<usize>,
+    /// Channels not accessed during this timeout are dropped from the pool.
+    pub idle_timeout: Duration,
+    /// Interval of checking the health of pooled channels, disabled if `None`.
+    pub health_check_interval: Option<Duration>,
 }
 
 impl Default for ChannelConfig {
@@ -281,6 +444,9 @@ impl Default for ChannelConfig {
             client_tls: None,
             max_recv_message_size: DEFAULT_MAX_GRPC_RECV_MESSAGE_SIZE,
             max_send_message_size: DEFAULT_MAX_GRPC_SEND_MESSAGE_SIZE,
+            max_channels: None,
+            idle_timeout: Duration::from_secs(DEFAULT_CHANNEL_IDLE_TIMEOUT_SECS),
+            health_check_interval: None,
         }
     }
 }
@@ -381,6 +547,31 @@ impl ChannelConfig {
         self.client_tls = Some(client_tls_option);
         self
     }
+
+    /// Set the max channels in the pool, one for each address.
+    ///
+    /// Unlimited by default.
+    pub fn max_channels(mut self, max_channels: usize) -> Self {
+        self.max_channels = Some(max_channels);
+        self
+    }
+
+    /// Set the timeout after which a channel not accessed is dropped from the pool.
+    ///
+    /// Default is 60 seconds.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = timeout;
+        self
+    }
+
+    /// Set the interval of checking the health of pooled channels by the gRPC health check
+    /// service.
+    ///
+    /// Disabled by default.
+    pub fn health_check_interval(mut self, interval: Duration) -> Self {
+        self.health_check_interval = Some(interval);
+        self
+    }
 }
 
 #[derive(Debug)]
@@ -388,9 +579,19 @@ pub struct Channel {
     channel: InnerChannel,
     access: AtomicUsize,
     use_default_connector: bool,
+    healthy: AtomicBool,
 }
 
 impl Channel {
+    fn new(channel: InnerChannel, use_default_connector: bool) -> Self {
+        Self {
+            channel,
+            access: AtomicUsize::new(1),
+            use_default_connector,
+            healthy: AtomicBool::new(true),
+        }
+    }
+
     #[inline]
     pub fn access(&self) -> usize {
         self.access.load(Ordering::Relaxed)
@@ -405,6 +606,12 @@ impl Channel {
     pub fn increase_access(&self) {
         let _ = self.access.fetch_add(1, Ordering::Relaxed);
     }
+
+    /// Returns `false` if the channel failed its last health check.
+    #[inline]
+    pub fn is_healthy(&self) -> bool {
+        self.healthy.load(Ordering::Relaxed)
+    }
 }
 
 #[derive(Debug, Default)]
@@ -435,6 +642,33 @@ impl Pool {
         let _ = self.channels.insert(addr.to_string(), channel);
     }
 
+    fn len(&self) -> usize {
+        self.channels.len()
+    }
+
+    /// Removes the channel accessed least since the last recycling, returns `false` if the pool
+    /// is empty.
+    fn evict_least_accessed(&self) -> bool {
+        let addr = self
+            .channels
+            .iter()
+            .min_by_key(|ch| ch.access())
+            .map(|ch| ch.key().clone());
+        match addr {
+            Some(addr) => {
+                let _ = self.channels.remove(&addr);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn set_healthy(&self, addr: &str, healthy: bool) {
+        if let Some(ch) = self.channels.get(addr) {
+            ch.healthy.store(healthy, Ordering::Relaxed);
+        }
+    }
+
     fn retain_channel<F>(&self, f: F)
     where
         F: FnMut(&String, &mut Channel) -> bool,
@@ -443,8 +677,8 @@ impl Pool {
     }
 }
 
-async fn recycle_channel_in_loop(pool: Arc<Pool>, interval_secs: u64) {
-    let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+async fn recycle_channel_in_loop(pool: Arc<Pool>, idle_timeout: Duration) {
+    let mut interval = tokio::time::interval(idle_timeout);
 
     loop {
         let _ = interval.tick().await;
@@ -452,6 +686,44 @@ async fn recycle_channel_in_loop(pool: Arc<Pool>, interval_secs: u64) {
     }
 }
 
+async fn check_channel_health_in_loop(pool: Arc<Pool>, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+
+    loop {
+        let _ = ticker.tick().await;
+        let mut checks = JoinSet::new();
+        for ch in pool.channels.iter() {
+            let addr = ch.key().clone();
+            let channel = ch.channel.clone();
+            let _ = checks.spawn(async move { (check_health(channel, interval).await, addr) });
+        }
+        while let Some(res) = checks.join_next().await {
+            let Ok((healthy, addr)) = res else {
+                continue;
+            };
+            if !healthy {
+                warn!("Channel to {} failed the health check", addr);
+            }
+            pool.set_healthy(&addr, healthy);
+        }
+    }
+}
+
+/// Checks the health of the channel by the gRPC health check service.
+///
+/// The server is considered healthy if it's reachable, even if it doesn't serve health checks.
+async fn check_health(channel: InnerChannel, timeout: Duration) -> bool {
+    let mut client = HealthCheckClient::new(channel);
+    match tokio::time::timeout(timeout, client.health_check(HealthCheckRequest {})).await {
+        Ok(Ok(_)) => true,
+        Ok(Err(status)) => !matches!(
+            status.code(),
+            Code::Unavailable | Code::Unknown | Code::DeadlineExceeded
+        ),
+        Err(_) => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use tower::service_fn;
@@ -521,6 +793,9 @@ mod tests {
                 client_tls: None,
                 max_recv_message_size: DEFAULT_MAX_GRPC_RECV_MESSAGE_SIZE,
                 max_send_message_size: DEFAULT_MAX_GRPC_SEND_MESSAGE_SIZE,
+                max_channels: None,
+                idle_timeout: Duration::from_secs(DEFAULT_CHANNEL_IDLE_TIMEOUT_SECS),
+                health_check_interval: None,
             },
             default_cfg
         );
@@ -542,7 +817,11 @@ mod tests {
                 server_ca_cert_path: "some_server_path".to_string(),
                 client_cert_path: "some_cert_path".to_string(),
                 client_key_path: "some_key_path".to_string(),
-            });
+                watch: false,
+            })
+            .max_channels(16)
+            .idle_timeout(Duration::from_secs(30))
+            .health_check_interval(Duration::from_secs(5));
 
         assert_eq!(
             ChannelConfig {
@@ -562,9 +841,13 @@ mod tests {
                     server_ca_cert_path: "some_server_path".to_string(),
                     client_cert_path: "some_cert_path".to_string(),
                     client_key_path: "some_key_path".to_string(),
+                    watch: false,
                 }),
                 max_recv_message_size: DEFAULT_MAX_GRPC_RECV_MESSAGE_SIZE,
                 max_send_message_size: DEFAULT_MAX_GRPC_SEND_MESSAGE_SIZE,
+                max_channels: Some(16),
+                idle_timeout: Duration::from_secs(30),
+                health_check_interval: Some(Duration::from_secs(5)),
             },
             cfg
         );
@@ -624,4 +907,89 @@ mod tests {
             true
         });
     }
+
+    #[tokio::test]
+    async fn test_max_channels() {
+        let mgr = ChannelManager::with_config(ChannelConfig::new().max_channels(2));
+        // Do not start recycle
+        mgr.channel_recycle_started.store(true, Ordering::Relaxed);
+
+        for _ in 0..3 {
+            let _ = mgr.get("addr1").unwrap();
+        }
+        let _ = mgr.get("addr2").unwrap();
+        let _ = mgr.get("addr3").unwrap();
+
+        // addr2 is evicted since it's accessed least
+        assert_eq!(2, mgr.pool.len());
+        assert_eq!(Some(3), mgr.pool.get_access("addr1"));
+        assert_eq!(None, mgr.pool.get_access("addr2"));
+        assert_eq!(Some(1), mgr.pool.get_access("addr3"));
+    }
+
+    #[tokio::test]
+    async fn test_check_health() {
+        let mgr = ChannelManager::new();
+        // Do not start recycle
+        mgr.channel_recycle_started.store(true, Ordering::Relaxed);
+
+        // nothing listens on the port
+        let addr = "127.0.0.1:1";
+        let channel = mgr.get(addr).unwrap();
+        assert!(mgr.is_healthy(addr));
+        assert!(!check_health(channel, Duration::from_secs(3)).await);
+
+        mgr.pool.set_healthy(addr, false);
+        assert!(!mgr.is_healthy(addr));
+        mgr.pool.set_healthy(addr, true);
+        assert!(mgr.is_healthy(addr));
+        assert!(mgr.is_healthy("unknown_addr"));
+    }
+
+    #[tokio::test]
+    async fn test_watch_tls_config() {
+        let dir = common_test_util::temp_dir::create_temp_dir("test_watch_tls_config");
+        for file in ["ca.pem", "client.pem", "client.key"] {
+            let _ = std::fs::copy(format!("tests/tls/{file}"), dir.path().join(file)).unwrap();
+        }
+        let path = |file: &str| dir.path().join(file).to_str().unwrap().to_string();
+        let config = ChannelConfig::new().client_tls_config(ClientTlsOption {
+            server_ca_cert_path: path("ca.pem"),
+            client_cert_path: path("client.pem"),
+            client_key_path: path("client.key"),
+            watch: true,
+        });
+        let mgr = ChannelManager::with_tls_config(config).unwrap();
+        // Do not start recycle
+        mgr.channel_recycle_started.store(true, Ordering::Relaxed);
+        let _ = mgr.get("127.0.0.1:0").unwrap();
+        assert_eq!(1, mgr.pool.len());
+
+        // the file replaced by renaming is noticed
+        let _ = std::fs::copy("tests/tls/client.key", dir.path().join("client.key.tmp")).unwrap();
+        std::fs::rename(
+            dir.path().join("client.key.tmp"),
+            dir.path().join("client.key"),
+        )
+        .unwrap();
+        for _ in 0..50 {
+            if mgr.pool.len() == 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(0, mgr.pool.len());
+
+        // the watching thread doesn't keep the manager alive, it may only hold it shortly
+        // while handling the events left
+        let pool = Arc::downgrade(&mgr.pool);
+        drop(mgr);
+        for _ in 0..50 {
+            if pool.strong_count() == 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(0, pool.strong_count());
+    }
 }
@@ -15,10 +15,15 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
-use api::v1::{AffectedRows, FlightMetadata, Metrics};
+use api::v1::greptime_response::Response;
+use api::v1::{
+    AffectedRows, FlightMetadata, GreptimeResponse, Metrics, RequestHeader, ResponseHeader, Status,
+};
+use arrow_flight::flight_descriptor::DescriptorType;
 use arrow_flight::utils::flight_data_to_arrow_batch;
-use arrow_flight::{FlightData, SchemaAsIpc};
+use arrow_flight::{FlightData, FlightDescriptor, PutResult, SchemaAsIpc};
 use common_base::bytes::Bytes;
+use common_error::status_code::StatusCode;
 use common_recordbatch::{RecordBatch, RecordBatches};
 use datatypes::arrow;
 use datatypes::arrow::datatypes::Schema as ArrowSchema;
@@ -224,6 +229,69 @@ pub fn flight_messages_to_recordbatches(messages: Vec<FlightMessage>) -> Result<
     }
 }
 
+/// Builds the descriptor of a DoPut stream inserting record batches into `table_name`, sent with
+/// the schema as the first message of the stream.
+///
+/// The table is the path of the descriptor and the request `header` is its command.
+pub fn do_put_descriptor(header: &RequestHeader, table_name: &str) -> FlightDescriptor {
+    FlightDescriptor {
+        r#type: DescriptorType::Path as i32,
+        cmd: header.encode_to_vec().into(),
+        path: vec![table_name.to_string()],
+    }
+}
+
+/// Parses the request header and the table name from the descriptor of a DoPut stream, see
+/// [do_put_descriptor].
+pub fn parse_do_put_descriptor(descriptor: &FlightDescriptor) -> Result<(RequestHeader, String)> {
+    let [table_name] = descriptor.path.as_slice() else {
+        return InvalidFlightDataSnafu {
+            reason: format!(
+                "Expecting the table name as the only path of DoPut descriptor, actual: {:?}",
+                descriptor.path
+            ),
+        }
+        .fail();
+    };
+    let header = RequestHeader::decode(descriptor.cmd.clone()).context(DecodeFlightDataSnafu)?;
+    Ok((header, table_name.clone()))
+}
+
+/// Encodes the result of putting a record batch, sent by the server for each record batch of a
+/// DoPut stream in order.
+pub fn encode_put_result(result: std::result::Result<usize, (StatusCode, String)>) -> PutResult {
+    let (status, rows) = match result {
+        Ok(rows) => (
+            Status {
+                status_code: StatusCode::Success as u32,
+                err_msg: String::new(),
+            },
+            rows,
+        ),
+        Err((status_code, err_msg)) => (
+            Status {
+                status_code: status_code as u32,
+                err_msg,
+            },
+            0,
+        ),
+    };
+    let response = GreptimeResponse {
+        header: Some(ResponseHeader {
+            status: Some(status),
+        }),
+        response: Some(Response::AffectedRows(AffectedRows { value: rows as _ })),
+    };
+    PutResult {
+        app_metadata: response.encode_to_vec().into(),
+    }
+}
+
+/// Decodes the [GreptimeResponse] of putting a record batch from the [PutResult].
+pub fn decode_put_result(result: PutResult) -> Result<GreptimeResponse> {
+    GreptimeResponse::decode(result.app_metadata).context(DecodeFlightDataSnafu)
+}
+
 fn build_none_flight_msg() -> Bytes {
     let mut builder = FlatBufferBuilder::new();
 
@@ -249,6 +317,44 @@ mod test {
     use super::*;
     use crate::Error;
 
+    #[test]
+    fn test_do_put_descriptor() {
+        let header = RequestHeader {
+            dbname: "greptime-public".to_string(),
+            ..Default::default()
+        };
+        let descriptor = do_put_descriptor(&header, "demo");
+        let (decoded, table_name) = parse_do_put_descriptor(&descriptor).unwrap();
+        assert_eq!(decoded, header);
+        assert_eq!(table_name, "demo");
+
+        let descriptor = FlightDescriptor::new_path(vec!["a".to_string(), "b".to_string()]);
+        assert!(matches!(
+            parse_do_put_descriptor(&descriptor),
+            Err(Error::InvalidFlightData { .. })
+        ));
+    }
+
+    #[test]
+    fn test_put_result() {
+        let response = decode_put_result(encode_put_result(Ok(3))).unwrap();
+        let status = response.header.unwrap().status.unwrap();
+        assert_eq!(status.status_code, StatusCode::Success as u32);
+        assert_eq!(
+            response.response,
+            Some(Response::AffectedRows(AffectedRows { value: 3 }))
+        );
+
+        let response = decode_put_result(encode_put_result(Err((
+            StatusCode::InvalidArguments,
+            "bad".to_string(),
+        ))))
+        .unwrap();
+        let status = response.header.unwrap().status.unwrap();
+        assert_eq!(status.status_code, StatusCode::InvalidArguments as u32);
+        assert_eq!(status.err_msg, "bad");
+    }
+
     #[test]
     fn test_try_decode() {
         let arrow_schema = ArrowSchema::new(vec![Field::new("n", DataType::Int32, true)]);
@@ -26,6 +26,7 @@ async fn test_mtls_config() {
         server_ca_cert_path: "tests/tls/wrong_ca.pem".to_string(),
         client_cert_path: "tests/tls/wrong_client.pem".to_string(),
         client_key_path: "tests/tls/wrong_client.key".to_string(),
+        watch: false,
     });
 
     let re = ChannelManager::with_tls_config(config);
@@ -36,6 +37,7 @@ async fn test_mtls_config() {
         server_ca_cert_path: "tests/tls/ca.pem".to_string(),
         client_cert_path: "tests/tls/client.pem".to_string(),
         client_key_path: "tests/tls/corrupted".to_string(),
+        watch: false,
     });
 
     let re = ChannelManager::with_tls_config(config).unwrap();
@@ -47,9 +49,49 @@ async fn test_mtls_config() {
         server_ca_cert_path: "tests/tls/ca.pem".to_string(),
         client_cert_path: "tests/tls/client.pem".to_string(),
         client_key_path: "tests/tls/client.key".to_string(),
+        watch: false,
     });
 
     let re = ChannelManager::with_tls_config(config).unwrap();
     let re = re.get("127.0.0.1:0");
     let _ = re.unwrap();
 }
+
+#[tokio::test]
+async fn test_reload_tls_config() {
+    let dir = common_test_util::temp_dir::create_temp_dir("test_reload_tls_config");
+    for file in ["ca.pem", "client.pem", "client.key"] {
+        let _ = std::fs::copy(format!("tests/tls/{file}"), dir.path().join(file)).unwrap();
+    }
+    let path = |file: &str| dir.path().join(file).to_str().unwrap().to_string();
+    let config = ChannelConfig::new().client_tls_config(ClientTlsOption {
+        server_ca_cert_path: path("ca.pem"),
+        client_cert_path: path("client.pem"),
+        client_key_path: path("client.key"),
+        watch: false,
+    });
+
+    let cm = ChannelManager::with_tls_config(config).unwrap();
+    let _ = cm.get("127.0.0.1:0").unwrap();
+    let mut channels = 0;
+    cm.retain_channel(|_, _| {
+        channels += 1;
+        true
+    });
+    assert_eq!(channels, 1);
+
+    // channels are rebuilt with the reloaded config
+    cm.reload_tls_config().unwrap();
+    let mut channels = 0;
+    cm.retain_channel(|_, _| {
+        channels += 1;
+        true
+    });
+    assert_eq!(channels, 0);
+    let _ = cm.get("127.0.0.1:0").unwrap();
+
+    // keeps the config if it fails to reload
+    std::fs::remove_file(dir.path().join("client.key")).unwrap();
+    assert!(cm.reload_tls_config().is_err());
+    let _ = cm.get("127.0.0.1:0").unwrap();
+}
@@ -33,6 +33,7 @@ pub fn stack_trace_style_impl(args: TokenStream2, input: TokenStream2) -> TokenS
     }
 
     let debug_fmt_fn = build_debug_fmt_impl(enum_name.clone(), variants.clone());
+    let stack_frames_fn = build_stack_frames_impl(enum_name.clone(), variants.clone());
     let next_fn = build_next_impl(enum_name.clone(), variants);
     let debug_impl = build_debug_impl(enum_name.clone());
 
@@ -42,6 +43,7 @@ pub fn stack_trace_style_impl(args: TokenStream2, input: TokenStream2) -> TokenS
 
         impl ::common_error::ext::StackError for #enum_name {
             #debug_fmt_fn
+            #stack_frames_fn
             #next_fn
         }
 
@@ -71,6 +73,28 @@ fn build_debug_fmt_impl(enum_name: Ident, variants: Vec<ErrorVariant>) -> TokenS
     }
 }
 
+/// Generate `stack_frames` fn.
+///
+/// The generated fn will be like:
+/// ```rust, ignore
+/// fn stack_frames(&self, buf: &mut Vec<::common_error::error_info::StackFrame>);
+/// ```
+fn build_stack_frames_impl(enum_name: Ident, variants: Vec<ErrorVariant>) -> TokenStream2 {
+    let match_arms = variants
+        .iter()
+        .map(|v| v.to_stack_frames_match_arm())
+        .collect::<Vec<_>>();
+
+    quote! {
+        fn stack_frames(&self, buf: &mut Vec<::common_error::error_info::StackFrame>) {
+            use #enum_name::*;
+            match self {
+                #(#match_arms)*
+            }
+        }
+    }
+}
+
 /// Generate `next` fn.
 ///
 /// The generated fn will be like:
@@ -241,6 +265,72 @@ impl ErrorVariant {
         }
     }
 
+    /// Convert self into an match arm that will be used in [build_stack_frames_impl].
+    ///
+    /// The generated match arm will be like:
+    /// ```rust, ignore
+    ///     ErrorKindWithSource { source, location, .. } => {
+    ///         buf.push(StackFrame { type_name, file: Some(location.file), line: Some(location.line) });
+    ///         source.stack_frames(buf);
+    ///     },
+    ///     ErrorKindWithExternalCause { error, .. } => {
+    ///         buf.push(StackFrame { type_name, file: None, line: None });
+    ///         buf.push(StackFrame { type_name: Some(type_name_of_val(error)), file: None, line: None });
+    ///     }
+    /// ```
+    ///
+    /// The frames match the layers pushed by the match arm of [Self::to_debug_match_arm].
+    fn to_stack_frames_match_arm(&self) -> TokenStream2 {
+        let name = &self.name;
+        let fields = &self.fields;
+        let cfg = if let Some(cfg) = &self.cfg_attr {
+            quote_spanned!(cfg.span() => #cfg)
+        } else {
+            quote! {}
+        };
+
+        let location = if self.has_location {
+            quote! {
+                file: Some(location.file.to_string()),
+                line: Some(location.line),
+            }
+        } else {
+            quote! {
+                file: None,
+                line: None,
+            }
+        };
+        let next = if self.has_source {
+            quote! {
+                source.stack_frames(buf);
+            }
+        } else if self.has_external_cause {
+            quote! {
+                buf.push(::common_error::error_info::StackFrame {
+                    type_name: Some(std::any::type_name_of_val(error).to_string()),
+                    file: None,
+                    line: None,
+                });
+            }
+        } else {
+            quote! {}
+        };
+
+        quote_spanned! {
+            self.span => #cfg #[allow(unused_variables)] #name { #(#fields),* } => {
+                buf.push(::common_error::error_info::StackFrame {
+                    type_name: Some(format!(
+                        "{}::{}",
+                        std::any::type_name::<Self>(),
+                        stringify!(#name)
+                    )),
+                    #location
+                });
+                #next
+            },
+        }
+    }
+
     /// Convert self into an match arm that will be used in [build_next_impl].
     ///
     /// The generated match arm will be like:
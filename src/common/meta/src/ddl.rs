@@ -20,7 +20,7 @@ use common_telemetry::tracing_context::W3cTrace;
 use store_api::storage::{RegionId, RegionNumber, TableId};
 
 use crate::cache_invalidator::CacheInvalidatorRef;
-use crate::ddl::flow_meta::FlowMetadataAllocatorRef;
+use crate::ddl::flow_meta::{FlowMetadataAllocatorRef, FlowQuotaOptions};
 use crate::ddl::table_meta::TableMetadataAllocatorRef;
 use crate::error::Result;
 use crate::key::flow::FlowMetadataManagerRef;
@@ -32,6 +32,7 @@ use crate::rpc::ddl::{SubmitDdlTaskRequest, SubmitDdlTaskResponse};
 use crate::rpc::procedure::{MigrateRegionRequest, MigrateRegionResponse, ProcedureStateResponse};
 use crate::{ClusterId, DatanodeId};
 
+pub mod alter_flow;
 pub mod alter_logical_tables;
 pub mod alter_table;
 pub mod create_database;
@@ -46,6 +47,7 @@ pub mod drop_table;
 pub mod drop_view;
 pub mod flow_meta;
 mod physical_table_metadata;
+pub mod reassign_flow;
 pub mod table_meta;
 #[cfg(any(test, feature = "testing"))]
 pub mod test_util;
@@ -151,6 +153,8 @@ pub struct DdlContext {
     pub flow_metadata_allocator: FlowMetadataAllocatorRef,
     /// controller of region failure detector.
     pub region_failure_detector_controller: RegionFailureDetectorControllerRef,
+    /// Limits of flows.
+    pub flow_quota: FlowQuotaOptions,
 }
 
 impl DdlContext {
@@ -0,0 +1,332 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashSet;
+
+use async_trait::async_trait;
+use common_catalog::format_full_flow_name;
+use common_procedure::error::{FromJsonSnafu, ToJsonSnafu};
+use common_procedure::{
+    Context as ProcedureContext, LockKey, Procedure, Result as ProcedureResult, Status,
+};
+use common_telemetry::info;
+use futures::future::join_all;
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use snafu::{ensure, OptionExt, ResultExt};
+use strum::AsRefStr;
+
+use super::utils::{add_peer_context_if_needed, handle_retry_error};
+use crate::cache_invalidator::Context;
+use crate::ddl::DdlContext;
+use crate::error::{self, Result};
+use crate::flow_name::FlowName;
+use crate::instruction::CacheIdent;
+use crate::key::flow::flow_info::FlowInfoValue;
+use crate::key::flow::flow_route::FlowRouteValue;
+use crate::key::table_name::TableNameKey;
+use crate::key::{DeserializedValueWithBytes, FlowId};
+use crate::lock_key::{CatalogLock, FlowNameLock};
+use crate::node_manager::AlterFlowRequest;
+use crate::rpc::ddl::{AlterFlowTask, QueryContext};
+use crate::{metrics, ClusterId};
+
+/// The procedure for altering options or the query of a flow.
+///
+/// Flownodes running the flow apply a new `expire_after` or comment in place, and rebuild the
+/// dataflow if its query or options are changed, keeping its state unless asked to discard it.
+pub struct AlterFlowProcedure {
+    /// The context of procedure runtime.
+    pub(crate) context: DdlContext,
+    /// The serializable data.
+    pub(crate) data: AlterFlowData,
+}
+
+impl AlterFlowProcedure {
+    pub const TYPE_NAME: &'static str = "metasrv-procedure::AlterFlow";
+
+    pub fn new(
+        cluster_id: ClusterId,
+        task: AlterFlowTask,
+        query_context: QueryContext,
+        context: DdlContext,
+    ) -> Self {
+        Self {
+            context,
+            data: AlterFlowData {
+                state: AlterFlowState::Prepare,
+                cluster_id,
+                task,
+                query_context,
+                flow_id: None,
+                flow_info_value: None,
+                new_flow_info_value: None,
+                flow_route_values: vec![],
+            },
+        }
+    }
+
+    pub fn from_json(json: &str, context: DdlContext) -> ProcedureResult<Self> {
+        let data: AlterFlowData = serde_json::from_str(json).context(FromJsonSnafu)?;
+
+        Ok(Self { context, data })
+    }
+
+    /// Fetches the flow metadata and computes the new [FlowInfoValue].
+    /// - Early returns if nothing is changed.
+    /// - Throws an error if flow not exists.
+    pub(crate) async fn on_prepare(&mut self) -> Result<Status> {
+        let catalog_name = &self.data.task.catalog_name;
+        let flow_name = &self.data.task.flow_name;
+        let flow_metadata_manager = &self.context.flow_metadata_manager;
+
+        let flow_id = flow_metadata_manager
+            .flow_name_manager()
+            .get(catalog_name, flow_name)
+            .await?
+            .with_context(|| error::FlowNotFoundSnafu {
+                flow_name: format_full_flow_name(catalog_name, flow_name),
+            })?
+            .flow_id();
+        let flow_info_value = flow_metadata_manager
+            .flow_info_manager()
+            .get_raw(flow_id)
+            .await?
+            .with_context(|| error::FlowNotFoundSnafu {
+                flow_name: format_full_flow_name(catalog_name, flow_name),
+            })?;
+        let flow_route_values = flow_metadata_manager
+            .flow_route_manager()
+            .routes(flow_id)
+            .map_ok(|(_, value)| value)
+            .try_collect::<Vec<_>>()
+            .await?;
+        ensure!(
+            !flow_route_values.is_empty(),
+            error::FlowRouteNotFoundSnafu {
+                flow_name: format_full_flow_name(catalog_name, flow_name),
+            }
+        );
+
+        if self.data.task.sql.is_some() {
+            self.ensure_same_source_tables(flow_info_value.get_inner_ref())
+                .await?;
+        }
+
+        let new_flow_info_value = self.data.task.apply(flow_info_value.get_inner_ref());
+        if &new_flow_info_value == flow_info_value.get_inner_ref() && !self.data.task.discard_state
+        {
+            return Ok(Status::done_with_output(flow_id));
+        }
+
+        self.data.flow_id = Some(flow_id);
+        self.data.flow_info_value = Some(flow_info_value);
+        self.data.new_flow_info_value = Some(new_flow_info_value);
+        self.data.flow_route_values = flow_route_values;
+        self.data.state = AlterFlowState::AlterFlows;
+        Ok(Status::executing(true))
+    }
+
+    /// Ensures the new query reads from the same tables as the flow, since changing source tables
+    /// would change where inserts of the tables are mirrored to.
+    async fn ensure_same_source_tables(&self, flow_info: &FlowInfoValue) -> Result<()> {
+        let keys = self
+            .data
+            .task
+            .source_table_names
+            .iter()
+            .map(|name| TableNameKey::new(&name.catalog_name, &name.schema_name, &name.table_name))
+            .collect::<Vec<_>>();
+        let source_table_ids = self
+            .context
+            .table_metadata_manager
+            .table_name_manager()
+            .batch_get(keys)
+            .await?
+            .into_iter()
+            .zip(&self.data.task.source_table_names)
+            .map(|(value, name)| {
+                Ok(value
+                    .with_context(|| error::TableNotFoundSnafu {
+                        table_name: name.to_string(),
+                    })?
+                    .table_id())
+            })
+            .collect::<Result<HashSet<_>>>()?;
+
+        ensure!(
+            source_table_ids == flow_info.source_table_ids().iter().copied().collect(),
+            error::UnsupportedSnafu {
+                operation: format!(
+                    "changing source tables of flow {}",
+                    format_full_flow_name(&self.data.task.catalog_name, &self.data.task.flow_name)
+                ),
+            }
+        );
+        Ok(())
+    }
+
+    async fn on_flownode_alter_flows(&mut self) -> Result<Status> {
+        // Safety: checked
+        let flow_id = self.data.flow_id.unwrap();
+        let new_flow_info_value = self.data.new_flow_info_value.as_ref().unwrap();
+        let mut alter_flow_tasks = Vec::with_capacity(self.data.flow_route_values.len());
+
+        for FlowRouteValue { peer } in &self.data.flow_route_values {
+            let requester = self.context.node_manager.flownode(peer).await;
+            let request = AlterFlowRequest {
+                flow_id,
+                expire_after: new_flow_info_value.expire_after,
+                comment: new_flow_info_value.comment.clone(),
+                sql: new_flow_info_value.raw_sql.clone(),
+                flow_options: new_flow_info_value.options.clone(),
+                discard_state: self.data.task.discard_state,
+            };
+            alter_flow_tasks.push(async move {
+                requester
+                    .alter_flow(request)
+                    .await
+                    .map_err(add_peer_context_if_needed(peer.clone()))
+            });
+        }
+
+        join_all(alter_flow_tasks)
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>>>()?;
+
+        self.data.state = AlterFlowState::UpdateMetadata;
+        Ok(Status::executing(true))
+    }
+
+    async fn on_update_metadata(&mut self) -> Result<Status> {
+        // Safety: checked
+        let flow_id = self.data.flow_id.unwrap();
+        self.context
+            .flow_metadata_manager
+            .update_flow_info(
+                flow_id,
+                self.data.flow_info_value.as_ref().unwrap(),
+                self.data.new_flow_info_value.clone().unwrap(),
+            )
+            .await?;
+        info!("Updated flow metadata for flow {flow_id}");
+        self.data.state = AlterFlowState::InvalidateFlowCache;
+        Ok(Status::executing(true))
+    }
+
+    async fn on_broadcast(&mut self) -> Result<Status> {
+        // Safety: checked
+        let flow_id = self.data.flow_id.unwrap();
+        let ctx = Context {
+            subject: Some("Invalidate flow cache by altering flow".to_string()),
+        };
+
+        self.context
+            .cache_invalidator
+            .invalidate(
+                &ctx,
+                &[
+                    CacheIdent::FlowId(flow_id),
+                    CacheIdent::FlowName(FlowName {
+                        catalog_name: self.data.task.catalog_name.clone(),
+                        flow_name: self.data.task.flow_name.clone(),
+                    }),
+                ],
+            )
+            .await?;
+
+        Ok(Status::done_with_output(flow_id))
+    }
+}
+
+impl AlterFlowTask {
+    /// Returns the [FlowInfoValue] with options and query of this task applied.
+    pub(crate) fn apply(&self, flow_info: &FlowInfoValue) -> FlowInfoValue {
+        let mut new_flow_info = flow_info.clone();
+        if let Some(expire_after) = self.expire_after {
+            new_flow_info.expire_after = expire_after;
+        }
+        if let Some(comment) = &self.comment {
+            new_flow_info.comment = comment.clone();
+        }
+        new_flow_info.options.extend(self.flow_options.clone());
+        if let Some(sql) = &self.sql {
+            new_flow_info.raw_sql = sql.clone();
+        }
+        new_flow_info
+    }
+}
+
+#[async_trait]
+impl Procedure for AlterFlowProcedure {
+    fn type_name(&self) -> &str {
+        Self::TYPE_NAME
+    }
+
+    async fn execute(&mut self, _ctx: &ProcedureContext) -> ProcedureResult<Status> {
+        let state = &self.data.state;
+        let _timer = metrics::METRIC_META_PROCEDURE_ALTER_FLOW
+            .with_label_values(&[state.as_ref()])
+            .start_timer();
+
+        match self.data.state {
+            AlterFlowState::Prepare => self.on_prepare().await,
+            AlterFlowState::AlterFlows => self.on_flownode_alter_flows().await,
+            AlterFlowState::UpdateMetadata => self.on_update_metadata().await,
+            AlterFlowState::InvalidateFlowCache => self.on_broadcast().await,
+        }
+        .map_err(handle_retry_error)
+    }
+
+    fn dump(&self) -> ProcedureResult<String> {
+        serde_json::to_string(&self.data).context(ToJsonSnafu)
+    }
+
+    fn lock_key(&self) -> LockKey {
+        let catalog_name = &self.data.task.catalog_name;
+        let flow_name = &self.data.task.flow_name;
+
+        LockKey::new(vec![
+            CatalogLock::Read(catalog_name).into(),
+            FlowNameLock::new(catalog_name, flow_name).into(),
+        ])
+    }
+}
+
+/// The serializable data
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct AlterFlowData {
+    state: AlterFlowState,
+    cluster_id: ClusterId,
+    task: AlterFlowTask,
+    query_context: QueryContext,
+    pub(crate) flow_id: Option<FlowId>,
+    pub(crate) flow_info_value: Option<DeserializedValueWithBytes<FlowInfoValue>>,
+    pub(crate) new_flow_info_value: Option<FlowInfoValue>,
+    pub(crate) flow_route_values: Vec<FlowRouteValue>,
+}
+
+/// The state of alter flow
+#[derive(Debug, Serialize, Deserialize, AsRefStr, PartialEq)]
+enum AlterFlowState {
+    /// Prepares to alter the flow
+    Prepare,
+    /// Alters flows on flownode
+    AlterFlows,
+    /// Updates metadata
+    UpdateMetadata,
+    /// Invalidate flow cache
+    InvalidateFlowCache,
+}
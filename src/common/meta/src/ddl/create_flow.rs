@@ -17,16 +17,18 @@ mod metadata;
 use std::collections::BTreeMap;
 
 use api::v1::flow::flow_request::Body as PbFlowRequest;
-use api::v1::flow::{CreateRequest, FlowRequest, FlowRequestHeader};
+use api::v1::flow::{CreateRequest, DropRequest, FlowRequest, FlowRequestHeader};
 use api::v1::ExpireAfter;
 use async_trait::async_trait;
 use common_catalog::format_full_flow_name;
-use common_procedure::error::{FromJsonSnafu, ToJsonSnafu};
+use common_error::ext::ErrorExt;
+use common_error::status_code::StatusCode;
+use common_procedure::error::{Error as ProcedureError, FromJsonSnafu, ToJsonSnafu};
 use common_procedure::{
     Context as ProcedureContext, LockKey, Procedure, Result as ProcedureResult, Status,
 };
-use common_telemetry::info;
 use common_telemetry::tracing_context::TracingContext;
+use common_telemetry::{info, warn};
 use futures::future::join_all;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
@@ -123,18 +125,21 @@ impl CreateFlowProcedure {
             .await?;
         // TODO(discord9): due to undefined behavior in flow's plan in how to transform types in mfp, sometime flow can't deduce correct schema
         // and require manually create sink table
+        // The schema of the existing sink table is checked against the output of the query
+        // by flownodes on creating the flow, the procedure is rolled back on mismatch.
         if exists {
             common_telemetry::warn!("Table already exists, table: {}", sink_table_name);
         }
 
         self.collect_source_tables().await?;
         self.allocate_flow_id().await?;
+        self.ensure_flow_quota().await?;
         self.data.state = CreateFlowState::CreateFlows;
 
         Ok(Status::executing(true))
     }
 
-    async fn on_flownode_create_flows(&mut self) -> Result<Status> {
+    pub(crate) async fn on_flownode_create_flows(&mut self) -> Result<Status> {
         // Safety: must be allocated.
         let mut create_flow = Vec::with_capacity(self.data.peers.len());
         for peer in &self.data.peers {
@@ -167,7 +172,7 @@ impl CreateFlowProcedure {
     ///
     /// Abort(not-retry):
     /// - Failed to create table metadata.
-    async fn on_create_metadata(&mut self) -> Result<Status> {
+    pub(crate) async fn on_create_metadata(&mut self) -> Result<Status> {
         // Safety: The flow id must be allocated.
         let flow_id = self.data.flow_id.unwrap();
         // TODO(weny): Support `or_replace`.
@@ -181,6 +186,58 @@ impl CreateFlowProcedure {
         Ok(Status::executing(true))
     }
 
+    /// Deletes the metadata of the flow if it's created, which is written in a single transaction.
+    pub(crate) async fn on_rollback_delete_metadata(&self) -> Result<()> {
+        let Some(flow_id) = self.data.flow_id else {
+            return Ok(());
+        };
+        let flow_info = self
+            .context
+            .flow_metadata_manager
+            .flow_info_manager()
+            .get(flow_id)
+            .await?;
+        if let Some(flow_info) = flow_info {
+            self.context
+                .flow_metadata_manager
+                .destroy_flow_metadata(flow_id, &flow_info)
+                .await?;
+            info!("Deleted flow metadata for flow {flow_id} on rollback");
+        }
+        Ok(())
+    }
+
+    /// Drops the flow on flownodes it may be created on, a flow not found is ignored.
+    pub(crate) async fn on_rollback_drop_flows(&self) -> Result<()> {
+        let Some(flow_id) = self.data.flow_id else {
+            return Ok(());
+        };
+        let mut drop_flow_tasks = Vec::with_capacity(self.data.peers.len());
+        for peer in &self.data.peers {
+            let requester = self.context.node_manager.flownode(peer).await;
+            let request = FlowRequest {
+                body: Some(PbFlowRequest::Drop(DropRequest {
+                    flow_id: Some(api::v1::FlowId { id: flow_id }),
+                })),
+                ..Default::default()
+            };
+            drop_flow_tasks.push(async move {
+                if let Err(err) = requester.handle(request).await {
+                    if err.status_code() != StatusCode::FlowNotFound {
+                        return Err(add_peer_context_if_needed(peer.clone())(err));
+                    }
+                }
+                Ok(())
+            });
+        }
+
+        join_all(drop_flow_tasks)
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>>>()?;
+        Ok(())
+    }
+
     async fn on_broadcast(&mut self) -> Result<Status> {
         // Safety: The flow id must be allocated.
         let flow_id = self.data.flow_id.unwrap();
@@ -229,6 +286,27 @@ impl Procedure for CreateFlowProcedure {
         serde_json::to_string(&self.data).context(ToJsonSnafu)
     }
 
+    fn rollback_supported(&self) -> bool {
+        matches!(
+            self.data.state,
+            CreateFlowState::CreateFlows | CreateFlowState::CreateMetadata
+        )
+    }
+
+    async fn rollback(&mut self, _: &ProcedureContext) -> ProcedureResult<()> {
+        warn!(
+            "Rolling back the create flow procedure, flow: {}",
+            format_full_flow_name(&self.data.task.catalog_name, &self.data.task.flow_name)
+        );
+        // metadata first, so a flow is never left in metadata without running on flownodes
+        self.on_rollback_delete_metadata()
+            .await
+            .map_err(ProcedureError::external)?;
+        self.on_rollback_drop_flows()
+            .await
+            .map_err(ProcedureError::external)
+    }
+
     fn lock_key(&self) -> LockKey {
         let catalog_name = &self.data.task.catalog_name;
         let flow_name = &self.data.task.flow_name;
@@ -12,23 +12,47 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use snafu::OptionExt;
+use std::collections::BTreeSet;
+
+use common_catalog::format_full_flow_name;
+use common_telemetry::warn;
+use futures::TryStreamExt;
+use snafu::{ensure, OptionExt};
 
 use crate::ddl::create_flow::CreateFlowProcedure;
 use crate::error::{self, Result};
 use crate::key::table_name::TableNameKey;
 
+/// The max number of allocated flow ids in a row that are already used before giving up.
+const MAX_FLOW_ID_COLLISIONS: usize = 1024;
+
 impl CreateFlowProcedure {
-    /// Allocates the [FlowId].
+    /// Allocates the [FlowId] and flownodes of the flow.
+    ///
+    /// Ids already used by existing flows are skipped, e.g., the flow id sequence is reset.
     pub(crate) async fn allocate_flow_id(&mut self) -> Result<()> {
         //TODO(weny, ruihang): We doesn't support the partitions. It's always be 1, now.
         let partitions = 1;
         let cluster_id = self.data.cluster_id;
-        let (flow_id, peers) = self
-            .context
-            .flow_metadata_allocator
-            .create(cluster_id, partitions)
-            .await?;
+        let allocator = &self.context.flow_metadata_allocator;
+        let flow_info_manager = self.context.flow_metadata_manager.flow_info_manager();
+
+        let mut flow_id = allocator.allocate_flow_id().await?;
+        let mut collisions = 0;
+        while flow_info_manager.get_raw(flow_id).await?.is_some() {
+            warn!("Flow id {flow_id} is already used by an existing flow, allocate another one");
+            collisions += 1;
+            ensure!(
+                collisions < MAX_FLOW_ID_COLLISIONS,
+                error::NextSequenceSnafu {
+                    err_msg: format!(
+                        "{collisions} allocated flow ids are already used, the last one is {flow_id}"
+                    ),
+                }
+            );
+            flow_id = allocator.allocate_flow_id().await?;
+        }
+        let peers = allocator.allocate_peers(cluster_id, partitions).await?;
         self.data.flow_id = Some(flow_id);
         self.data.peers = peers;
 
@@ -71,4 +95,79 @@ impl CreateFlowProcedure {
         self.data.source_table_ids = source_table_ids;
         Ok(())
     }
+
+    /// Ensures the flow doesn't exceed the [FlowQuotaOptions](crate::ddl::flow_meta::FlowQuotaOptions)
+    /// once created on the allocated flownodes.
+    ///
+    /// Flows being created concurrently aren't counted, so the limits are soft ones.
+    pub(crate) async fn ensure_flow_quota(&self) -> Result<()> {
+        let quota = &self.context.flow_quota;
+        let flow_metadata_manager = &self.context.flow_metadata_manager;
+        let flow_name =
+            format_full_flow_name(&self.data.task.catalog_name, &self.data.task.flow_name);
+
+        if let Some(max_flows) = quota.max_flows {
+            let flows = flow_metadata_manager
+                .flow_info_manager()
+                .flow_ids()
+                .try_collect::<Vec<_>>()
+                .await?
+                .len();
+            ensure!(
+                flows < max_flows,
+                error::FlowQuotaExceededSnafu {
+                    flow_name: &flow_name,
+                    reason: format!(
+                        "there are {flows} flows in the cluster, the limit is {max_flows}"
+                    ),
+                }
+            );
+        }
+
+        if let Some(max_flows) = quota.max_flows_per_source_table {
+            for table_id in &self.data.source_table_ids {
+                // a flow has a key for each of its partitions
+                let flows = flow_metadata_manager
+                    .table_flow_manager()
+                    .flows(*table_id)
+                    .map_ok(|(key, _)| key.flow_id())
+                    .try_collect::<BTreeSet<_>>()
+                    .await?
+                    .len();
+                ensure!(
+                    flows < max_flows,
+                    error::FlowQuotaExceededSnafu {
+                        flow_name: &flow_name,
+                        reason: format!(
+                            "there are {flows} flows reading source table {table_id}, the limit is {max_flows}"
+                        ),
+                    }
+                );
+            }
+        }
+
+        if let Some(max_flows) = quota.max_flows_per_flownode {
+            for peer in &self.data.peers {
+                let flows = flow_metadata_manager
+                    .flownode_flow_manager()
+                    .flows(peer.id)
+                    .map_ok(|(flow_id, _)| flow_id)
+                    .try_collect::<BTreeSet<_>>()
+                    .await?
+                    .len();
+                ensure!(
+                    flows < max_flows,
+                    error::FlowQuotaExceededSnafu {
+                        flow_name: &flow_name,
+                        reason: format!(
+                            "there are {flows} flows on flownode {}, the limit is {max_flows}",
+                            peer.id
+                        ),
+                    }
+                );
+            }
+        }
+
+        Ok(())
+    }
 }
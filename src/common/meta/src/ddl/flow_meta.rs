@@ -14,14 +14,29 @@
 
 use std::sync::Arc;
 
+use serde::{Deserialize, Serialize};
+use snafu::OptionExt;
 use tonic::async_trait;
 
-use crate::error::Result;
+use crate::error::{self, Result};
 use crate::key::FlowId;
 use crate::peer::Peer;
 use crate::sequence::SequenceRef;
 use crate::ClusterId;
 
+/// The limits of flows checked on creating flows, so a shared cluster isn't overloaded by
+/// unbounded standing queries. A limit of `None` means unlimited.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FlowQuotaOptions {
+    /// The max number of flows in the cluster.
+    pub max_flows: Option<usize>,
+    /// The max number of flows running on a flownode.
+    pub max_flows_per_flownode: Option<usize>,
+    /// The max number of flows reading a source table.
+    pub max_flows_per_source_table: Option<usize>,
+}
+
 /// The reference of [FlowMetadataAllocator].
 pub type FlowMetadataAllocatorRef = Arc<FlowMetadataAllocator>;
 
@@ -54,9 +69,16 @@ impl FlowMetadataAllocator {
     }
 
     /// Allocates a the [FlowId].
+    ///
+    /// Ids are reserved from the sequence in ranges like table ids, the sequence should be built
+    /// with a max no more than [FlowId::MAX], instead of wrapping around to reused ids.
     pub(crate) async fn allocate_flow_id(&self) -> Result<FlowId> {
-        let flow_id = self.flow_id_sequence.next().await? as FlowId;
-        Ok(flow_id)
+        let flow_id = self.flow_id_sequence.next().await?;
+        FlowId::try_from(flow_id)
+            .ok()
+            .context(error::NextSequenceSnafu {
+                err_msg: format!("flow id {flow_id} exceeds the max flow id {}", FlowId::MAX),
+            })
     }
 
     /// Allocates the [FlowId] and [Peer]s.
@@ -73,6 +95,17 @@ impl FlowMetadataAllocator {
 
         Ok((flow_id, peers))
     }
+
+    /// Allocates [Peer]s for partitions of an existing flow, i.e. moving them to other flownodes.
+    pub async fn allocate_peers(
+        &self,
+        cluster_id: ClusterId,
+        partitions: usize,
+    ) -> Result<Vec<Peer>> {
+        self.partition_peer_allocator
+            .alloc(cluster_id, partitions)
+            .await
+    }
 }
 
 /// Allocates [Peer]s for partitions.
@@ -93,3 +126,34 @@ impl PartitionPeerAllocator for NoopPartitionPeerAllocator {
         Ok(vec![Peer::default(); partitions])
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kv_backend::memory::MemoryKvBackend;
+    use crate::kv_backend::KvBackendRef;
+    use crate::sequence::SequenceBuilder;
+
+    #[tokio::test]
+    async fn test_allocate_flow_id_exhausted() {
+        let kv_backend: KvBackendRef = Arc::new(MemoryKvBackend::new());
+        let sequence = SequenceBuilder::new("flow-test", kv_backend)
+            .initial(FlowId::MAX as u64 - 1)
+            .step(10)
+            .max(FlowId::MAX as u64)
+            .build();
+        let allocator = FlowMetadataAllocator::with_noop_peer_allocator(Arc::new(sequence));
+        assert_eq!(allocator.allocate_flow_id().await.unwrap(), FlowId::MAX - 1);
+        let err = allocator.allocate_flow_id().await.unwrap_err();
+        assert!(matches!(err, error::Error::NextSequence { .. }));
+
+        // The sequence isn't bounded by flow ids
+        let kv_backend: KvBackendRef = Arc::new(MemoryKvBackend::new());
+        let sequence = SequenceBuilder::new("flow-test", kv_backend)
+            .initial(FlowId::MAX as u64 + 1)
+            .build();
+        let allocator = FlowMetadataAllocator::with_noop_peer_allocator(Arc::new(sequence));
+        let err = allocator.allocate_flow_id().await.unwrap_err();
+        assert!(matches!(err, error::Error::NextSequence { .. }));
+    }
+}
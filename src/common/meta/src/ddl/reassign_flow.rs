@@ -0,0 +1,333 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use api::v1::flow::flow_request::Body as PbFlowRequest;
+use api::v1::flow::{CreateRequest, FlowRequest, FlowRequestHeader};
+use api::v1::ExpireAfter;
+use async_trait::async_trait;
+use common_procedure::error::{FromJsonSnafu, ToJsonSnafu};
+use common_procedure::{
+    Context as ProcedureContext, LockKey, Procedure, Result as ProcedureResult, Status,
+};
+use common_telemetry::info;
+use common_telemetry::tracing_context::TracingContext;
+use futures::future::join_all;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use session::context::QueryContextBuilder;
+use snafu::ResultExt;
+use strum::AsRefStr;
+
+use super::utils::{add_peer_context_if_needed, handle_retry_error};
+use crate::cache_invalidator::Context;
+use crate::ddl::DdlContext;
+use crate::error::Result;
+use crate::instruction::{CacheIdent, CreateFlow, DropFlow};
+use crate::key::flow::flow_info::FlowInfoValue;
+use crate::key::flow::flow_route::FlowRouteValue;
+use crate::key::{DeserializedValueWithBytes, FlowId, FlowPartitionId};
+use crate::lock_key::{CatalogLock, FlowNameLock};
+use crate::peer::Peer;
+use crate::rpc::ddl::QueryContext;
+use crate::{metrics, ClusterId, FlownodeId};
+
+/// The option asking flownodes to backfill a created flow from existing data of its source tables.
+const FLOW_OPT_KEY_BACKFILL: &str = "backfill";
+
+/// The procedure moving partitions of a flow from a failed flownode to healthy ones.
+///
+/// The flow is created on the new flownodes with backfill, so its state is rebuilt from the
+/// source tables, then its routes are updated. The failed flownode doesn't recover the flow
+/// once it comes back since the flow is no longer routed to it.
+pub struct ReassignFlowProcedure {
+    /// The context of procedure runtime.
+    pub(crate) context: DdlContext,
+    /// The serializable data.
+    pub(crate) data: ReassignFlowData,
+}
+
+impl ReassignFlowProcedure {
+    pub const TYPE_NAME: &'static str = "metasrv-procedure::ReassignFlow";
+
+    pub fn new(
+        cluster_id: ClusterId,
+        flow_id: FlowId,
+        catalog_name: String,
+        flow_name: String,
+        from_flownode_id: FlownodeId,
+        context: DdlContext,
+    ) -> Self {
+        Self {
+            context,
+            data: ReassignFlowData {
+                state: ReassignFlowState::Prepare,
+                cluster_id,
+                flow_id,
+                catalog_name,
+                flow_name,
+                from_flownode_id,
+                flow_info_value: None,
+                new_flow_info_value: None,
+                new_flow_routes: vec![],
+            },
+        }
+    }
+
+    pub fn from_json(json: &str, context: DdlContext) -> ProcedureResult<Self> {
+        let data: ReassignFlowData = serde_json::from_str(json).context(FromJsonSnafu)?;
+
+        Ok(Self { context, data })
+    }
+
+    /// Fetches the flow metadata and allocates new flownodes for partitions on the failed one.
+    /// - Early returns if the flow is dropped or no longer on the failed flownode.
+    pub(crate) async fn on_prepare(&mut self) -> Result<Status> {
+        let flow_id = self.data.flow_id;
+        let from_flownode_id = self.data.from_flownode_id;
+
+        let Some(flow_info_value) = self
+            .context
+            .flow_metadata_manager
+            .flow_info_manager()
+            .get_raw(flow_id)
+            .await?
+        else {
+            info!("Flow {flow_id} is dropped, skip reassigning it");
+            return Ok(Status::done());
+        };
+        let partitions = flow_info_value
+            .flownode_ids()
+            .iter()
+            .filter(|(_, flownode_id)| **flownode_id == from_flownode_id)
+            .map(|(partition_id, _)| *partition_id)
+            .collect::<Vec<_>>();
+        if partitions.is_empty() {
+            info!("Flow {flow_id} is not on flownode {from_flownode_id}, skip reassigning it");
+            return Ok(Status::done_with_output(flow_id));
+        }
+
+        let peers = self
+            .context
+            .flow_metadata_allocator
+            .allocate_peers(self.data.cluster_id, partitions.len())
+            .await?;
+        let mut new_flow_info_value = flow_info_value.get_inner_ref().clone();
+        let mut new_flow_routes = Vec::with_capacity(partitions.len());
+        for (partition_id, peer) in partitions.into_iter().zip(peers) {
+            new_flow_info_value
+                .flownode_ids
+                .insert(partition_id, peer.id);
+            new_flow_routes.push((partition_id, FlowRouteValue { peer }));
+        }
+
+        self.data.flow_info_value = Some(flow_info_value);
+        self.data.new_flow_info_value = Some(new_flow_info_value);
+        self.data.new_flow_routes = new_flow_routes;
+        self.data.state = ReassignFlowState::CreateFlows;
+        Ok(Status::executing(true))
+    }
+
+    async fn on_flownode_create_flows(&mut self) -> Result<Status> {
+        // Safety: checked
+        let flow_info_value = self.data.new_flow_info_value.as_ref().unwrap();
+        let query_context: QueryContext = Arc::new(
+            QueryContextBuilder::default()
+                .current_catalog(flow_info_value.catalog_name.clone())
+                .build(),
+        )
+        .into();
+        let mut create_flow = Vec::with_capacity(self.data.new_flow_routes.len());
+
+        for (_, FlowRouteValue { peer }) in &self.data.new_flow_routes {
+            let requester = self.context.node_manager.flownode(peer).await;
+            let request = FlowRequest {
+                header: Some(FlowRequestHeader {
+                    tracing_context: TracingContext::from_current_span().to_w3c(),
+                    query_context: Some(query_context.clone().into()),
+                }),
+                body: Some(PbFlowRequest::Create(self.create_request(flow_info_value))),
+            };
+            create_flow.push(async move {
+                requester
+                    .handle(request)
+                    .await
+                    .map_err(add_peer_context_if_needed(peer.clone()))
+            });
+        }
+
+        join_all(create_flow)
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>>>()?;
+
+        self.data.state = ReassignFlowState::UpdateMetadata;
+        Ok(Status::executing(true))
+    }
+
+    /// Returns the request creating the flow on a new flownode, which backfills the flow
+    /// since its state on the failed flownode is lost.
+    fn create_request(&self, flow_info_value: &FlowInfoValue) -> CreateRequest {
+        let mut flow_options = flow_info_value.options.clone();
+        flow_options.insert(FLOW_OPT_KEY_BACKFILL.to_string(), "true".to_string());
+
+        CreateRequest {
+            flow_id: Some(api::v1::FlowId {
+                id: self.data.flow_id,
+            }),
+            source_table_ids: flow_info_value
+                .source_table_ids
+                .iter()
+                .map(|table_id| api::v1::TableId { id: *table_id })
+                .collect_vec(),
+            sink_table_name: Some(flow_info_value.sink_table_name.clone().into()),
+            // Always be true
+            create_if_not_exists: true,
+            expire_after: flow_info_value
+                .expire_after
+                .map(|value| ExpireAfter { value }),
+            comment: flow_info_value.comment.clone(),
+            sql: flow_info_value.raw_sql.clone(),
+            flow_options,
+        }
+    }
+
+    async fn on_update_metadata(&mut self) -> Result<Status> {
+        let flow_id = self.data.flow_id;
+        // Safety: checked
+        self.context
+            .flow_metadata_manager
+            .update_flow_routes(
+                flow_id,
+                self.data.flow_info_value.as_ref().unwrap(),
+                self.data.new_flow_info_value.clone().unwrap(),
+                self.data.new_flow_routes.clone(),
+            )
+            .await?;
+        info!(
+            "Updated routes of flow {flow_id}, moved from flownode {} to {:?}",
+            self.data.from_flownode_id,
+            self.new_peers().iter().map(|peer| peer.id).collect_vec()
+        );
+        self.data.state = ReassignFlowState::InvalidateFlowCache;
+        Ok(Status::executing(true))
+    }
+
+    async fn on_broadcast(&mut self) -> Result<Status> {
+        let flow_id = self.data.flow_id;
+        // Safety: checked
+        let source_table_ids = self
+            .data
+            .new_flow_info_value
+            .as_ref()
+            .unwrap()
+            .source_table_ids
+            .clone();
+        let ctx = Context {
+            subject: Some("Invalidate flow cache by reassigning flow".to_string()),
+        };
+
+        self.context
+            .cache_invalidator
+            .invalidate(
+                &ctx,
+                &[
+                    CacheIdent::DropFlow(DropFlow {
+                        source_table_ids: source_table_ids.clone(),
+                        flownode_ids: vec![self.data.from_flownode_id],
+                    }),
+                    CacheIdent::CreateFlow(CreateFlow {
+                        source_table_ids,
+                        flownodes: self.new_peers(),
+                    }),
+                    CacheIdent::FlowId(flow_id),
+                ],
+            )
+            .await?;
+
+        Ok(Status::done_with_output(flow_id))
+    }
+
+    fn new_peers(&self) -> Vec<Peer> {
+        self.data
+            .new_flow_routes
+            .iter()
+            .map(|(_, route)| route.peer.clone())
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Procedure for ReassignFlowProcedure {
+    fn type_name(&self) -> &str {
+        Self::TYPE_NAME
+    }
+
+    async fn execute(&mut self, _ctx: &ProcedureContext) -> ProcedureResult<Status> {
+        let state = &self.data.state;
+        let _timer = metrics::METRIC_META_PROCEDURE_REASSIGN_FLOW
+            .with_label_values(&[state.as_ref()])
+            .start_timer();
+
+        match self.data.state {
+            ReassignFlowState::Prepare => self.on_prepare().await,
+            ReassignFlowState::CreateFlows => self.on_flownode_create_flows().await,
+            ReassignFlowState::UpdateMetadata => self.on_update_metadata().await,
+            ReassignFlowState::InvalidateFlowCache => self.on_broadcast().await,
+        }
+        .map_err(handle_retry_error)
+    }
+
+    fn dump(&self) -> ProcedureResult<String> {
+        serde_json::to_string(&self.data).context(ToJsonSnafu)
+    }
+
+    fn lock_key(&self) -> LockKey {
+        let catalog_name = &self.data.catalog_name;
+        let flow_name = &self.data.flow_name;
+
+        LockKey::new(vec![
+            CatalogLock::Read(catalog_name).into(),
+            FlowNameLock::new(catalog_name, flow_name).into(),
+        ])
+    }
+}
+
+/// The serializable data
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct ReassignFlowData {
+    state: ReassignFlowState,
+    cluster_id: ClusterId,
+    pub(crate) flow_id: FlowId,
+    catalog_name: String,
+    flow_name: String,
+    pub(crate) from_flownode_id: FlownodeId,
+    pub(crate) flow_info_value: Option<DeserializedValueWithBytes<FlowInfoValue>>,
+    pub(crate) new_flow_info_value: Option<FlowInfoValue>,
+    pub(crate) new_flow_routes: Vec<(FlowPartitionId, FlowRouteValue)>,
+}
+
+/// The state of reassign flow
+#[derive(Debug, Serialize, Deserialize, AsRefStr, PartialEq)]
+enum ReassignFlowState {
+    /// Prepares to reassign the flow
+    Prepare,
+    /// Creates flows on new flownodes
+    CreateFlows,
+    /// Updates metadata
+    UpdateMetadata,
+    /// Invalidate flow cache
+    InvalidateFlowCache,
+}
@@ -17,6 +17,7 @@ use api::v1::region::InsertRequests;
 use common_telemetry::debug;
 
 use crate::error::Result;
+use crate::node_manager::AlterFlowRequest;
 use crate::peer::Peer;
 use crate::test_util::MockFlownodeHandler;
 
@@ -40,4 +41,9 @@ impl MockFlownodeHandler for NaiveFlownodeHandler {
     ) -> Result<FlowResponse> {
         unreachable!()
     }
+
+    async fn alter_flow(&self, peer: &Peer, request: AlterFlowRequest) -> Result<()> {
+        debug!("Returning Ok(()) for request: {request:?}, peer: {peer:?}");
+        Ok(())
+    }
 }
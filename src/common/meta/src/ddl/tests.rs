@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod alter_flow;
 mod alter_logical_tables;
 mod alter_table;
 mod create_flow;
@@ -22,3 +23,4 @@ mod drop_database;
 mod drop_flow;
 mod drop_table;
 mod drop_view;
+mod reassign_flow;
@@ -0,0 +1,198 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::assert_matches::assert_matches;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use common_catalog::consts::{DEFAULT_CATALOG_NAME, DEFAULT_SCHEMA_NAME};
+use common_procedure_test::execute_procedure_until_done;
+use session::context::QueryContext;
+use table::table_name::TableName;
+
+use crate::ddl::alter_flow::AlterFlowProcedure;
+use crate::ddl::test_util::create_table::test_create_table_task;
+use crate::ddl::test_util::flownode_handler::NaiveFlownodeHandler;
+use crate::ddl::tests::create_flow::create_test_flow;
+use crate::error;
+use crate::key::table_route::TableRouteValue;
+use crate::rpc::ddl::AlterFlowTask;
+use crate::test_util::{new_ddl_context, MockFlownodeManager};
+
+fn test_alter_flow_task(
+    flow_name: &str,
+    expire_after: Option<Option<i64>>,
+    comment: Option<&str>,
+) -> AlterFlowTask {
+    AlterFlowTask {
+        catalog_name: DEFAULT_CATALOG_NAME.to_string(),
+        flow_name: flow_name.to_string(),
+        expire_after,
+        comment: comment.map(|c| c.to_string()),
+        flow_options: HashMap::new(),
+        sql: None,
+        source_table_names: vec![],
+        discard_state: false,
+    }
+}
+
+#[tokio::test]
+async fn test_alter_flow_not_found() {
+    let cluster_id = 1;
+    let node_manager = Arc::new(MockFlownodeManager::new(NaiveFlownodeHandler));
+    let ddl_context = new_ddl_context(node_manager);
+    let task = test_alter_flow_task("my_flow", Some(None), None);
+    let query_ctx = QueryContext::arc().into();
+    let mut procedure = AlterFlowProcedure::new(cluster_id, task, query_ctx, ddl_context);
+    let err = procedure.on_prepare().await.unwrap_err();
+    assert_matches!(err, error::Error::FlowNotFound { .. });
+}
+
+#[tokio::test]
+async fn test_alter_flow() {
+    // create a flow
+    let cluster_id = 1;
+    let table_id = 1024;
+    let source_table_names = vec![TableName::new(
+        DEFAULT_CATALOG_NAME,
+        DEFAULT_SCHEMA_NAME,
+        "my_source_table",
+    )];
+    let sink_table_name =
+        TableName::new(DEFAULT_CATALOG_NAME, DEFAULT_SCHEMA_NAME, "my_sink_table");
+    let node_manager = Arc::new(MockFlownodeManager::new(NaiveFlownodeHandler));
+    let ddl_context = new_ddl_context(node_manager);
+
+    let task = test_create_table_task("my_source_table", table_id);
+    ddl_context
+        .table_metadata_manager
+        .create_table_metadata(
+            task.table_info.clone(),
+            TableRouteValue::physical(vec![]),
+            HashMap::new(),
+        )
+        .await
+        .unwrap();
+    let flow_id = create_test_flow(
+        &ddl_context,
+        cluster_id,
+        "my_flow",
+        source_table_names,
+        sink_table_name,
+    )
+    .await;
+    let flow_info = ddl_context
+        .flow_metadata_manager
+        .flow_info_manager()
+        .get(flow_id)
+        .await
+        .unwrap()
+        .unwrap();
+
+    // Alters the flow
+    let task = test_alter_flow_task("my_flow", Some(Some(600)), Some("altered"));
+    let expected = task.apply(&flow_info);
+    let query_ctx = QueryContext::arc().into();
+    let mut procedure = AlterFlowProcedure::new(cluster_id, task, query_ctx, ddl_context.clone());
+    execute_procedure_until_done(&mut procedure).await;
+
+    let altered = ddl_context
+        .flow_metadata_manager
+        .flow_info_manager()
+        .get(flow_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(altered, expected);
+    assert_eq!(altered.expire_after(), Some(600));
+    assert_eq!(altered.comment(), "altered");
+    assert_eq!(altered.raw_sql(), flow_info.raw_sql());
+
+    // Alters again with the same options, nothing changes
+    let task = test_alter_flow_task("my_flow", Some(Some(600)), Some("altered"));
+    let query_ctx = QueryContext::arc().into();
+    let mut procedure = AlterFlowProcedure::new(cluster_id, task, query_ctx, ddl_context.clone());
+    execute_procedure_until_done(&mut procedure).await;
+    assert!(procedure.data.flow_id.is_none());
+}
+
+#[tokio::test]
+async fn test_alter_flow_query() {
+    let cluster_id = 1;
+    let source_table_name =
+        TableName::new(DEFAULT_CATALOG_NAME, DEFAULT_SCHEMA_NAME, "my_source_table");
+    let other_table_name = TableName::new(DEFAULT_CATALOG_NAME, DEFAULT_SCHEMA_NAME, "other_table");
+    let sink_table_name =
+        TableName::new(DEFAULT_CATALOG_NAME, DEFAULT_SCHEMA_NAME, "my_sink_table");
+    let node_manager = Arc::new(MockFlownodeManager::new(NaiveFlownodeHandler));
+    let ddl_context = new_ddl_context(node_manager);
+    for (table_name, table_id) in [("my_source_table", 1024), ("other_table", 1025)] {
+        let task = test_create_table_task(table_name, table_id);
+        ddl_context
+            .table_metadata_manager
+            .create_table_metadata(
+                task.table_info.clone(),
+                TableRouteValue::physical(vec![]),
+                HashMap::new(),
+            )
+            .await
+            .unwrap();
+    }
+    let flow_id = create_test_flow(
+        &ddl_context,
+        cluster_id,
+        "my_flow",
+        vec![source_table_name.clone()],
+        sink_table_name,
+    )
+    .await;
+
+    // Changes the query and options, other options are kept
+    let mut task = test_alter_flow_task("my_flow", None, None);
+    task.sql = Some("SELECT max(n) FROM my_source_table".to_string());
+    task.source_table_names = vec![source_table_name];
+    task.flow_options = HashMap::from([("emit_interval".to_string(), "10s".to_string())]);
+    let query_ctx = QueryContext::arc().into();
+    let mut procedure = AlterFlowProcedure::new(cluster_id, task, query_ctx, ddl_context.clone());
+    execute_procedure_until_done(&mut procedure).await;
+    let altered = ddl_context
+        .flow_metadata_manager
+        .flow_info_manager()
+        .get(flow_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(altered.raw_sql(), "SELECT max(n) FROM my_source_table");
+    assert_eq!(
+        altered.options().get("emit_interval").map(String::as_str),
+        Some("10s")
+    );
+
+    // Discarding state rebuilds the flow even if nothing else is changed
+    let mut task = test_alter_flow_task("my_flow", None, None);
+    task.discard_state = true;
+    let query_ctx = QueryContext::arc().into();
+    let mut procedure = AlterFlowProcedure::new(cluster_id, task, query_ctx, ddl_context.clone());
+    execute_procedure_until_done(&mut procedure).await;
+    assert_eq!(procedure.data.flow_id, Some(flow_id));
+
+    // The new query can't read from other tables
+    let mut task = test_alter_flow_task("my_flow", None, None);
+    task.sql = Some("SELECT max(n) FROM other_table".to_string());
+    task.source_table_names = vec![other_table_name];
+    let query_ctx = QueryContext::arc().into();
+    let mut procedure = AlterFlowProcedure::new(cluster_id, task, query_ctx, ddl_context);
+    let err = procedure.on_prepare().await.unwrap_err();
+    assert_matches!(err, error::Error::Unsupported { .. });
+}
@@ -17,18 +17,25 @@ use std::collections::HashMap;
 use std::sync::Arc;
 
 use common_catalog::consts::{DEFAULT_CATALOG_NAME, DEFAULT_SCHEMA_NAME};
-use common_procedure_test::execute_procedure_until_done;
+use common_error::ext::ErrorExt;
+use common_error::status_code::StatusCode;
+use common_procedure::Procedure;
+use common_procedure_test::{execute_procedure_until_done, new_test_procedure_context};
 use session::context::QueryContext;
 use table::table_name::TableName;
 
-use crate::ddl::create_flow::CreateFlowProcedure;
+use crate::ddl::create_flow::{CreateFlowProcedure, CreateFlowState};
+use crate::ddl::flow_meta::{FlowMetadataAllocator, FlowQuotaOptions};
 use crate::ddl::test_util::create_table::test_create_table_task;
 use crate::ddl::test_util::flownode_handler::NaiveFlownodeHandler;
 use crate::ddl::DdlContext;
 use crate::key::table_route::TableRouteValue;
 use crate::key::FlowId;
+use crate::kv_backend::memory::MemoryKvBackend;
+use crate::kv_backend::KvBackendRef;
 use crate::rpc::ddl::CreateFlowTask;
-use crate::test_util::{new_ddl_context, MockFlownodeManager};
+use crate::sequence::SequenceBuilder;
+use crate::test_util::{new_ddl_context, new_ddl_context_with_kv_backend, MockFlownodeManager};
 use crate::{error, ClusterId};
 
 pub(crate) fn test_create_flow_task(
@@ -147,3 +154,190 @@ async fn test_create_flow() {
     let err = procedure.on_prepare().await.unwrap_err();
     assert_matches!(err, error::Error::FlowAlreadyExists { .. });
 }
+
+#[tokio::test]
+async fn test_create_flow_rollback() {
+    let cluster_id = 1;
+    let table_id = 1024;
+    let source_table_names = vec![TableName::new(
+        DEFAULT_CATALOG_NAME,
+        DEFAULT_SCHEMA_NAME,
+        "my_source_table",
+    )];
+    let sink_table_name =
+        TableName::new(DEFAULT_CATALOG_NAME, DEFAULT_SCHEMA_NAME, "my_sink_table");
+    let node_manager = Arc::new(MockFlownodeManager::new(NaiveFlownodeHandler));
+    let ddl_context = new_ddl_context(node_manager);
+
+    let task = test_create_table_task("my_source_table", table_id);
+    ddl_context
+        .table_metadata_manager
+        .create_table_metadata(
+            task.table_info.clone(),
+            TableRouteValue::physical(vec![]),
+            HashMap::new(),
+        )
+        .await
+        .unwrap();
+
+    let task = test_create_flow_task("my_flow", source_table_names, sink_table_name, false);
+    let query_ctx = QueryContext::arc().into();
+    let mut procedure = CreateFlowProcedure::new(cluster_id, task, query_ctx, ddl_context.clone());
+    procedure.on_prepare().await.unwrap();
+    procedure.on_flownode_create_flows().await.unwrap();
+    procedure.on_create_metadata().await.unwrap();
+    let flow_id = procedure.data.flow_id.unwrap();
+    // Fails before the cache invalidation.
+    procedure.data.state = CreateFlowState::CreateMetadata;
+    assert!(procedure.rollback_supported());
+
+    let ctx = new_test_procedure_context();
+    procedure.rollback(&ctx).await.unwrap();
+    // Rollback again
+    procedure.rollback(&ctx).await.unwrap();
+
+    let flow_metadata_manager = &ddl_context.flow_metadata_manager;
+    assert!(!flow_metadata_manager
+        .flow_name_manager()
+        .exists(DEFAULT_CATALOG_NAME, "my_flow")
+        .await
+        .unwrap());
+    assert!(flow_metadata_manager
+        .flow_info_manager()
+        .get(flow_id)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_create_flow_quota_exceeded() {
+    let cluster_id = 1;
+    let table_id = 1024;
+    let source_table_names = vec![TableName::new(
+        DEFAULT_CATALOG_NAME,
+        DEFAULT_SCHEMA_NAME,
+        "my_source_table",
+    )];
+    let sink_table_name =
+        TableName::new(DEFAULT_CATALOG_NAME, DEFAULT_SCHEMA_NAME, "my_sink_table");
+    let node_manager = Arc::new(MockFlownodeManager::new(NaiveFlownodeHandler));
+    let mut ddl_context = new_ddl_context(node_manager);
+
+    let task = test_create_table_task("my_source_table", table_id);
+    ddl_context
+        .table_metadata_manager
+        .create_table_metadata(
+            task.table_info.clone(),
+            TableRouteValue::physical(vec![]),
+            HashMap::new(),
+        )
+        .await
+        .unwrap();
+    create_test_flow(
+        &ddl_context,
+        cluster_id,
+        "my_flow",
+        source_table_names.clone(),
+        sink_table_name.clone(),
+    )
+    .await;
+
+    let quotas = [
+        FlowQuotaOptions {
+            max_flows: Some(1),
+            ..Default::default()
+        },
+        FlowQuotaOptions {
+            max_flows_per_flownode: Some(1),
+            ..Default::default()
+        },
+        FlowQuotaOptions {
+            max_flows_per_source_table: Some(1),
+            ..Default::default()
+        },
+    ];
+    for quota in quotas {
+        ddl_context.flow_quota = quota;
+        let task = test_create_flow_task(
+            "my_flow2",
+            source_table_names.clone(),
+            sink_table_name.clone(),
+            false,
+        );
+        let query_ctx = QueryContext::arc().into();
+        let mut procedure =
+            CreateFlowProcedure::new(cluster_id, task, query_ctx, ddl_context.clone());
+        let err = procedure.on_prepare().await.unwrap_err();
+        assert_matches!(err, error::Error::FlowQuotaExceeded { .. });
+        assert_eq!(err.status_code(), StatusCode::FlowQuotaExceeded);
+    }
+
+    // Creates within the limits
+    ddl_context.flow_quota = FlowQuotaOptions {
+        max_flows: Some(2),
+        max_flows_per_flownode: Some(2),
+        max_flows_per_source_table: Some(2),
+    };
+    create_test_flow(
+        &ddl_context,
+        cluster_id,
+        "my_flow2",
+        source_table_names,
+        sink_table_name,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_create_flow_skip_used_flow_id() {
+    let cluster_id = 1;
+    let table_id = 1024;
+    let source_table_names = vec![TableName::new(
+        DEFAULT_CATALOG_NAME,
+        DEFAULT_SCHEMA_NAME,
+        "my_source_table",
+    )];
+    let sink_table_name =
+        TableName::new(DEFAULT_CATALOG_NAME, DEFAULT_SCHEMA_NAME, "my_sink_table");
+    let kv_backend: KvBackendRef = Arc::new(MemoryKvBackend::new());
+    let node_manager = Arc::new(MockFlownodeManager::new(NaiveFlownodeHandler));
+    let mut ddl_context = new_ddl_context_with_kv_backend(node_manager, kv_backend.clone());
+
+    let task = test_create_table_task("my_source_table", table_id);
+    ddl_context
+        .table_metadata_manager
+        .create_table_metadata(
+            task.table_info.clone(),
+            TableRouteValue::physical(vec![]),
+            HashMap::new(),
+        )
+        .await
+        .unwrap();
+    let flow_id = create_test_flow(
+        &ddl_context,
+        cluster_id,
+        "my_flow",
+        source_table_names.clone(),
+        sink_table_name.clone(),
+    )
+    .await;
+    assert_eq!(flow_id, 1024);
+
+    // The sequence is reset and allocates the used id again
+    ddl_context.flow_metadata_allocator =
+        Arc::new(FlowMetadataAllocator::with_noop_peer_allocator(Arc::new(
+            SequenceBuilder::new("flow-test-reset", kv_backend)
+                .initial(1024)
+                .build(),
+        )));
+    let flow_id = create_test_flow(
+        &ddl_context,
+        cluster_id,
+        "my_flow2",
+        source_table_names,
+        sink_table_name,
+    )
+    .await;
+    assert_eq!(flow_id, 1025);
+}
@@ -0,0 +1,133 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use common_catalog::consts::{DEFAULT_CATALOG_NAME, DEFAULT_SCHEMA_NAME};
+use common_procedure_test::execute_procedure_until_done;
+use futures::TryStreamExt;
+use table::table_name::TableName;
+
+use crate::ddl::flow_meta::{FlowMetadataAllocator, PartitionPeerAllocator};
+use crate::ddl::reassign_flow::ReassignFlowProcedure;
+use crate::ddl::test_util::create_table::test_create_table_task;
+use crate::ddl::test_util::flownode_handler::NaiveFlownodeHandler;
+use crate::ddl::tests::create_flow::create_test_flow;
+use crate::error::Result;
+use crate::key::table_route::TableRouteValue;
+use crate::kv_backend::memory::MemoryKvBackend;
+use crate::kv_backend::KvBackendRef;
+use crate::peer::Peer;
+use crate::sequence::SequenceBuilder;
+use crate::test_util::{new_ddl_context_with_kv_backend, MockFlownodeManager};
+use crate::ClusterId;
+
+struct StaticPartitionPeerAllocator(Peer);
+
+#[async_trait]
+impl PartitionPeerAllocator for StaticPartitionPeerAllocator {
+    async fn alloc(&self, _cluster_id: ClusterId, partitions: usize) -> Result<Vec<Peer>> {
+        Ok(vec![self.0.clone(); partitions])
+    }
+}
+
+#[tokio::test]
+async fn test_reassign_flow() {
+    // create a flow on flownode 0
+    let cluster_id = 1;
+    let table_id = 1024;
+    let source_table_names = vec![TableName::new(
+        DEFAULT_CATALOG_NAME,
+        DEFAULT_SCHEMA_NAME,
+        "my_source_table",
+    )];
+    let sink_table_name =
+        TableName::new(DEFAULT_CATALOG_NAME, DEFAULT_SCHEMA_NAME, "my_sink_table");
+    let kv_backend: KvBackendRef = Arc::new(MemoryKvBackend::new());
+    let node_manager = Arc::new(MockFlownodeManager::new(NaiveFlownodeHandler));
+    let mut ddl_context = new_ddl_context_with_kv_backend(node_manager, kv_backend.clone());
+
+    let task = test_create_table_task("my_source_table", table_id);
+    ddl_context
+        .table_metadata_manager
+        .create_table_metadata(
+            task.table_info.clone(),
+            TableRouteValue::physical(vec![]),
+            HashMap::new(),
+        )
+        .await
+        .unwrap();
+    let flow_id = create_test_flow(
+        &ddl_context,
+        cluster_id,
+        "my_flow",
+        source_table_names,
+        sink_table_name,
+    )
+    .await;
+
+    // Moves the flow to flownode 1
+    ddl_context.flow_metadata_allocator = Arc::new(FlowMetadataAllocator::with_peer_allocator(
+        Arc::new(SequenceBuilder::new("flow-test", kv_backend).build()),
+        Arc::new(StaticPartitionPeerAllocator(Peer::empty(1))),
+    ));
+    let mut procedure = ReassignFlowProcedure::new(
+        cluster_id,
+        flow_id,
+        DEFAULT_CATALOG_NAME.to_string(),
+        "my_flow".to_string(),
+        0,
+        ddl_context.clone(),
+    );
+    execute_procedure_until_done(&mut procedure).await;
+
+    let flow_info = ddl_context
+        .flow_metadata_manager
+        .flow_info_manager()
+        .get(flow_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(flow_info.flownode_ids(), &BTreeMap::from([(0, 1)]));
+    let flows = ddl_context
+        .flow_metadata_manager
+        .flownode_flow_manager()
+        .flows(0)
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+    assert!(flows.is_empty());
+    let flows = ddl_context
+        .flow_metadata_manager
+        .flownode_flow_manager()
+        .flows(1)
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+    assert_eq!(flows, vec![(flow_id, 0)]);
+
+    // The flow is no longer on flownode 0
+    let mut procedure = ReassignFlowProcedure::new(
+        cluster_id,
+        flow_id,
+        DEFAULT_CATALOG_NAME.to_string(),
+        "my_flow".to_string(),
+        0,
+        ddl_context.clone(),
+    );
+    let status = procedure.on_prepare().await.unwrap();
+    assert!(status.is_done());
+}
@@ -12,6 +12,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::HashSet;
+
 use api::v1::region::{
     region_request, RegionRequest, RegionRequestHeader, TruncateRequest as PbTruncateRegionRequest,
 };
@@ -20,9 +22,10 @@ use common_procedure::error::{FromJsonSnafu, ToJsonSnafu};
 use common_procedure::{
     Context as ProcedureContext, LockKey, Procedure, Result as ProcedureResult, Status,
 };
-use common_telemetry::debug;
 use common_telemetry::tracing_context::TracingContext;
+use common_telemetry::{debug, warn};
 use futures::future::join_all;
+use futures::TryStreamExt;
 use serde::{Deserialize, Serialize};
 use snafu::{ensure, ResultExt};
 use store_api::storage::RegionId;
@@ -182,8 +185,47 @@ impl TruncateTableProcedure {
             .into_iter()
             .collect::<Result<Vec<_>>>()?;
 
+        self.reset_flows().await;
+
         Ok(Status::done())
     }
+
+    /// Asks flownodes to recompute flows reading from the truncated table, failures are only
+    /// logged since the table is truncated anyway
+    async fn reset_flows(&self) {
+        let table_id = self.data.table_id();
+        let peers = match self
+            .context
+            .flow_metadata_manager
+            .table_flow_manager()
+            .flows(table_id)
+            .map_ok(|(_, value)| value.peer)
+            .try_collect::<HashSet<_>>()
+            .await
+        {
+            Ok(peers) => peers,
+            Err(err) => {
+                warn!(err; "Failed to find flownodes of flows reading from table {}", table_id);
+                return;
+            }
+        };
+
+        let mut reset_tasks = Vec::with_capacity(peers.len());
+        for peer in peers {
+            let requester = self.context.node_manager.flownode(&peer).await;
+            reset_tasks.push(async move {
+                requester
+                    .reset_source_table(table_id)
+                    .await
+                    .map_err(add_peer_context_if_needed(peer))
+            });
+        }
+        for result in join_all(reset_tasks).await {
+            if let Err(err) = result {
+                warn!(err; "Failed to reset flows reading from truncated table {}", table_id);
+            }
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
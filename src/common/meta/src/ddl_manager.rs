@@ -24,6 +24,7 @@ use derive_builder::Builder;
 use snafu::{ensure, OptionExt, ResultExt};
 use store_api::storage::TableId;
 
+use crate::ddl::alter_flow::AlterFlowProcedure;
 use crate::ddl::alter_logical_tables::AlterLogicalTablesProcedure;
 use crate::ddl::alter_table::AlterTableProcedure;
 use crate::ddl::create_database::CreateDatabaseProcedure;
@@ -35,6 +36,7 @@ use crate::ddl::drop_database::DropDatabaseProcedure;
 use crate::ddl::drop_flow::DropFlowProcedure;
 use crate::ddl::drop_table::DropTableProcedure;
 use crate::ddl::drop_view::DropViewProcedure;
+use crate::ddl::reassign_flow::ReassignFlowProcedure;
 use crate::ddl::truncate_table::TruncateTableProcedure;
 use crate::ddl::{utils, DdlContext, ExecutorContext, ProcedureExecutor};
 use crate::error::{
@@ -45,20 +47,21 @@ use crate::error::{
 };
 use crate::key::table_info::TableInfoValue;
 use crate::key::table_name::TableNameKey;
-use crate::key::{DeserializedValueWithBytes, TableMetadataManagerRef};
+use crate::key::{DeserializedValueWithBytes, FlowId, TableMetadataManagerRef};
 use crate::rpc::ddl::DdlTask::{
-    AlterLogicalTables, AlterTable, CreateDatabase, CreateFlow, CreateLogicalTables, CreateTable,
-    CreateView, DropDatabase, DropFlow, DropLogicalTables, DropTable, DropView, TruncateTable,
+    AlterFlow, AlterLogicalTables, AlterTable, CreateDatabase, CreateFlow, CreateLogicalTables,
+    CreateTable, CreateView, DropDatabase, DropFlow, DropLogicalTables, DropTable, DropView,
+    TruncateTable,
 };
 use crate::rpc::ddl::{
-    AlterTableTask, CreateDatabaseTask, CreateFlowTask, CreateTableTask, CreateViewTask,
-    DropDatabaseTask, DropFlowTask, DropTableTask, DropViewTask, QueryContext,
+    AlterFlowTask, AlterTableTask, CreateDatabaseTask, CreateFlowTask, CreateTableTask,
+    CreateViewTask, DropDatabaseTask, DropFlowTask, DropTableTask, DropViewTask, QueryContext,
     SubmitDdlTaskRequest, SubmitDdlTaskResponse, TruncateTableTask,
 };
 use crate::rpc::procedure;
 use crate::rpc::procedure::{MigrateRegionRequest, MigrateRegionResponse, ProcedureStateResponse};
 use crate::rpc::router::RegionRoute;
-use crate::ClusterId;
+use crate::{ClusterId, FlownodeId};
 
 pub type DdlManagerRef = Arc<DdlManager>;
 
@@ -131,6 +134,8 @@ impl DdlManager {
             AlterLogicalTablesProcedure,
             DropTableProcedure,
             DropFlowProcedure,
+            AlterFlowProcedure,
+            ReassignFlowProcedure,
             TruncateTableProcedure,
             CreateDatabaseProcedure,
             DropDatabaseProcedure,
@@ -323,6 +328,45 @@ impl DdlManager {
         self.submit_procedure(procedure_with_id).await
     }
 
+    /// Submits and executes an alter flow task.
+    #[tracing::instrument(skip_all)]
+    pub async fn submit_alter_flow_task(
+        &self,
+        cluster_id: ClusterId,
+        alter_flow: AlterFlowTask,
+        query_context: QueryContext,
+    ) -> Result<(ProcedureId, Option<Output>)> {
+        let context = self.create_context();
+        let procedure = AlterFlowProcedure::new(cluster_id, alter_flow, query_context, context);
+        let procedure_with_id = ProcedureWithId::with_random_id(Box::new(procedure));
+
+        self.submit_procedure(procedure_with_id).await
+    }
+
+    /// Submits and executes a task moving partitions of the flow off the failed flownode.
+    #[tracing::instrument(skip_all)]
+    pub async fn submit_reassign_flow_task(
+        &self,
+        cluster_id: ClusterId,
+        flow_id: FlowId,
+        catalog_name: String,
+        flow_name: String,
+        from_flownode_id: FlownodeId,
+    ) -> Result<(ProcedureId, Option<Output>)> {
+        let context = self.create_context();
+        let procedure = ReassignFlowProcedure::new(
+            cluster_id,
+            flow_id,
+            catalog_name,
+            flow_name,
+            from_flownode_id,
+            context,
+        );
+        let procedure_with_id = ProcedureWithId::with_random_id(Box::new(procedure));
+
+        self.submit_procedure(procedure_with_id).await
+    }
+
     /// Submits and executes a drop view task.
     #[tracing::instrument(skip_all)]
     pub async fn submit_drop_view_task(
@@ -614,6 +658,28 @@ async fn handle_drop_flow_task(
     })
 }
 
+async fn handle_alter_flow_task(
+    ddl_manager: &DdlManager,
+    cluster_id: ClusterId,
+    alter_flow_task: AlterFlowTask,
+    query_context: QueryContext,
+) -> Result<SubmitDdlTaskResponse> {
+    let (id, _) = ddl_manager
+        .submit_alter_flow_task(cluster_id, alter_flow_task.clone(), query_context)
+        .await?;
+
+    let procedure_id = id.to_string();
+    info!(
+        "Flow {}.{} is altered via procedure_id {id:?}",
+        alter_flow_task.catalog_name, alter_flow_task.flow_name,
+    );
+
+    Ok(SubmitDdlTaskResponse {
+        key: procedure_id.into(),
+        ..Default::default()
+    })
+}
+
 async fn handle_drop_view_task(
     ddl_manager: &DdlManager,
     cluster_id: ClusterId,
@@ -784,6 +850,15 @@ impl ProcedureExecutor for DdlManager {
                 DropFlow(drop_flow_task) => {
                     handle_drop_flow_task(self, cluster_id, drop_flow_task).await
                 }
+                AlterFlow(alter_flow_task) => {
+                    handle_alter_flow_task(
+                        self,
+                        cluster_id,
+                        alter_flow_task,
+                        request.query_context.into(),
+                    )
+                    .await
+                }
                 CreateView(create_view_task) => {
                     handle_create_view_task(self, cluster_id, create_view_task).await
                 }
@@ -902,6 +977,7 @@ mod tests {
                 flow_metadata_allocator,
                 memory_region_keeper: Arc::new(MemoryRegionKeeper::default()),
                 region_failure_detector_controller: Arc::new(NoopRegionFailureDetectorControl),
+                flow_quota: Default::default(),
             },
             procedure_manager.clone(),
             true,
@@ -336,6 +336,14 @@ pub enum Error {
         location: Location,
     },
 
+    #[snafu(display("Failed to create flow {}, {}", flow_name, reason))]
+    FlowQuotaExceeded {
+        flow_name: String,
+        reason: String,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
     #[snafu(display("Schema already exists, catalog:{}, schema: {}", catalog, schema))]
     SchemaAlreadyExists {
         catalog: String,
@@ -723,6 +731,7 @@ impl ErrorExt for Error {
             FlowNotFound { .. } => StatusCode::FlowNotFound,
             FlowRouteNotFound { .. } => StatusCode::Unexpected,
             FlowAlreadyExists { .. } => StatusCode::FlowAlreadyExists,
+            FlowQuotaExceeded { .. } => StatusCode::FlowQuotaExceeded,
 
             ViewNotFound { .. } | TableNotFound { .. } => StatusCode::TableNotFound,
             ViewAlreadyExists { .. } | TableAlreadyExists { .. } => StatusCode::TableAlreadyExists,
@@ -90,6 +90,7 @@
 pub mod catalog_name;
 pub mod datanode_table;
 pub mod flow;
+pub mod flow_stat;
 pub mod node_address;
 pub mod schema_name;
 pub mod table_info;
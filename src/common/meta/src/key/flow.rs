@@ -38,8 +38,8 @@ use crate::key::flow::flow_name::FlowNameManager;
 use crate::key::flow::flownode_flow::FlownodeFlowManager;
 pub use crate::key::flow::table_flow::{TableFlowManager, TableFlowManagerRef};
 use crate::key::txn_helper::TxnOpGetResponseSet;
-use crate::key::{FlowId, MetadataKey};
-use crate::kv_backend::txn::Txn;
+use crate::key::{DeserializedValueWithBytes, FlowId, MetadataKey};
+use crate::kv_backend::txn::{Txn, TxnOp};
 use crate::kv_backend::KvBackendRef;
 use crate::rpc::store::BatchDeleteRequest;
 
@@ -230,6 +230,123 @@ impl FlowMetadataManager {
         Ok(())
     }
 
+    /// Updates the [FlowInfoValue] of the flow and returns an error if different metadata exists.
+    ///
+    /// Only options not affecting the placement of the flow (i.e. source tables and flownodes) are expected to be changed.
+    pub async fn update_flow_info(
+        &self,
+        flow_id: FlowId,
+        current_flow_info: &DeserializedValueWithBytes<FlowInfoValue>,
+        new_flow_info: FlowInfoValue,
+    ) -> Result<()> {
+        let (update_flow_txn, on_update_flow_failure) =
+            self.flow_info_manager
+                .build_update_txn(flow_id, current_flow_info, &new_flow_info)?;
+
+        let mut resp = self.kv_backend.txn(update_flow_txn).await?;
+        // Checks whether metadata was already updated.
+        if !resp.succeeded {
+            let mut set = TxnOpGetResponseSet::from(&mut resp.responses);
+            let remote_flow =
+                on_update_flow_failure(&mut set)?.with_context(|| error::UnexpectedSnafu {
+                    err_msg: format!(
+                        "Reads the empty flow during the updating flow, flow_id: {flow_id}"
+                    ),
+                })?;
+            let op_name = "updating flow";
+            ensure_values!(*remote_flow, new_flow_info, op_name);
+        }
+
+        Ok(())
+    }
+
+    /// Moves partitions of the flow to the flownodes in `new_flow_routes`, updating the
+    /// [FlowInfoValue] and routes of the flow, returns an error if different metadata exists.
+    ///
+    /// `new_flow_info` is expected to only differ in the flownodes of the moved partitions.
+    pub async fn update_flow_routes(
+        &self,
+        flow_id: FlowId,
+        current_flow_info: &DeserializedValueWithBytes<FlowInfoValue>,
+        new_flow_info: FlowInfoValue,
+        new_flow_routes: Vec<(FlowPartitionId, FlowRouteValue)>,
+    ) -> Result<()> {
+        let (update_flow_txn, on_update_flow_failure) =
+            self.flow_info_manager
+                .build_update_txn(flow_id, current_flow_info, &new_flow_info)?;
+
+        // Deletes keys of the flownodes the partitions are moved from.
+        let source_table_ids = current_flow_info.source_table_ids();
+        let mut delete_ops = Vec::new();
+        for (partition_id, route) in &new_flow_routes {
+            let Some(&flownode_id) = current_flow_info.flownode_ids().get(partition_id) else {
+                continue;
+            };
+            if flownode_id == route.peer.id {
+                continue;
+            }
+            delete_ops.push(TxnOp::Delete(
+                FlownodeFlowKey::new(flownode_id, flow_id, *partition_id).to_bytes(),
+            ));
+            for &table_id in source_table_ids {
+                delete_ops.push(TxnOp::Delete(
+                    TableFlowKey::new(table_id, flownode_id, flow_id, *partition_id).to_bytes(),
+                ));
+            }
+        }
+
+        let create_flow_routes_txn = self
+            .flow_route_manager
+            .build_create_txn(flow_id, new_flow_routes.clone())?;
+
+        let create_flownode_flow_txn = self.flownode_flow_manager.build_create_txn(
+            flow_id,
+            new_flow_routes
+                .iter()
+                .map(|(partition_id, route)| (*partition_id, route.peer.id)),
+        );
+
+        let create_table_flow_txn = self.table_flow_manager.build_create_txn(
+            flow_id,
+            new_flow_routes
+                .into_iter()
+                .map(|(partition_id, route)| (partition_id, TableFlowValue { peer: route.peer }))
+                .collect(),
+            source_table_ids,
+        )?;
+
+        let txn = Txn::merge_all(vec![
+            update_flow_txn,
+            Txn::new().and_then(delete_ops),
+            create_flow_routes_txn,
+            create_flownode_flow_txn,
+            create_table_flow_txn,
+        ]);
+        info!(
+            "Updating routes of flow {}.{}({}), with {} txn operations",
+            new_flow_info.catalog_name,
+            new_flow_info.flow_name,
+            flow_id,
+            txn.max_operations()
+        );
+
+        let mut resp = self.kv_backend.txn(txn).await?;
+        // Checks whether metadata was already updated.
+        if !resp.succeeded {
+            let mut set = TxnOpGetResponseSet::from(&mut resp.responses);
+            let remote_flow =
+                on_update_flow_failure(&mut set)?.with_context(|| error::UnexpectedSnafu {
+                    err_msg: format!(
+                        "Reads the empty flow during the updating flow routes, flow_id: {flow_id}"
+                    ),
+                })?;
+            let op_name = "updating flow routes";
+            ensure_values!(*remote_flow, new_flow_info, op_name);
+        }
+
+        Ok(())
+    }
+
     fn flow_metadata_keys(&self, flow_id: FlowId, flow_value: &FlowInfoValue) -> Vec<Vec<u8>> {
         let source_table_ids = flow_value.source_table_ids();
         let mut keys =
@@ -560,4 +677,148 @@ mod tests {
         // Ensures all keys are deleted
         assert!(mem_kv.is_empty())
     }
+
+    #[tokio::test]
+    async fn test_update_flow_info() {
+        let mem_kv = Arc::new(MemoryKvBackend::default());
+        let flow_metadata_manager = FlowMetadataManager::new(mem_kv.clone());
+        let flow_id = 10;
+        let flow_value = test_flow_info_value("flow", [(0, 1u64)].into(), vec![1024, 1025, 1026]);
+        let flow_routes = vec![(
+            0u32,
+            FlowRouteValue {
+                peer: Peer::empty(1),
+            },
+        )];
+        flow_metadata_manager
+            .create_flow_metadata(flow_id, flow_value.clone(), flow_routes.clone())
+            .await
+            .unwrap();
+
+        let current = flow_metadata_manager
+            .flow_info_manager()
+            .get_raw(flow_id)
+            .await
+            .unwrap()
+            .unwrap();
+        let mut new_flow_value = flow_value.clone();
+        new_flow_value.expire_after = Some(600);
+        new_flow_value.comment = "altered".to_string();
+        flow_metadata_manager
+            .update_flow_info(flow_id, &current, new_flow_value.clone())
+            .await
+            .unwrap();
+        // Updates again.
+        flow_metadata_manager
+            .update_flow_info(flow_id, &current, new_flow_value.clone())
+            .await
+            .unwrap();
+        let got = flow_metadata_manager
+            .flow_info_manager()
+            .get(flow_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(got, new_flow_value);
+
+        // Updates with a stale value.
+        let mut another_flow_value = flow_value.clone();
+        another_flow_value.comment = "another".to_string();
+        let err = flow_metadata_manager
+            .update_flow_info(flow_id, &current, another_flow_value)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Reads the different value"));
+    }
+
+    #[tokio::test]
+    async fn test_update_flow_routes() {
+        let mem_kv = Arc::new(MemoryKvBackend::default());
+        let flow_metadata_manager = FlowMetadataManager::new(mem_kv.clone());
+        let flow_id = 10;
+        let flow_value = test_flow_info_value("flow", [(0, 1u64)].into(), vec![1024, 1025]);
+        let flow_routes = vec![(
+            0u32,
+            FlowRouteValue {
+                peer: Peer::empty(1),
+            },
+        )];
+        flow_metadata_manager
+            .create_flow_metadata(flow_id, flow_value.clone(), flow_routes.clone())
+            .await
+            .unwrap();
+
+        let current = flow_metadata_manager
+            .flow_info_manager()
+            .get_raw(flow_id)
+            .await
+            .unwrap()
+            .unwrap();
+        let mut new_flow_value = flow_value.clone();
+        new_flow_value.flownode_ids = [(0, 2u64)].into();
+        let new_flow_routes = vec![(
+            0u32,
+            FlowRouteValue {
+                peer: Peer::empty(2),
+            },
+        )];
+        flow_metadata_manager
+            .update_flow_routes(
+                flow_id,
+                &current,
+                new_flow_value.clone(),
+                new_flow_routes.clone(),
+            )
+            .await
+            .unwrap();
+        // Updates again.
+        flow_metadata_manager
+            .update_flow_routes(
+                flow_id,
+                &current,
+                new_flow_value.clone(),
+                new_flow_routes.clone(),
+            )
+            .await
+            .unwrap();
+
+        let got = flow_metadata_manager
+            .flow_info_manager()
+            .get(flow_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(got, new_flow_value);
+        let routes = flow_metadata_manager
+            .flow_route_manager()
+            .routes(flow_id)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(routes[0].1.peer, Peer::empty(2));
+        let old_flownode_flows = flow_metadata_manager
+            .flownode_flow_manager()
+            .flows(1)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert!(old_flownode_flows.is_empty());
+        let new_flownode_flows = flow_metadata_manager
+            .flownode_flow_manager()
+            .flows(2)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(new_flownode_flows, vec![(flow_id, 0)]);
+        for table_id in [1024, 1025] {
+            let table_flows = flow_metadata_manager
+                .table_flow_manager()
+                .flows(table_id)
+                .try_collect::<Vec<_>>()
+                .await
+                .unwrap();
+            assert_eq!(table_flows.len(), 1);
+            assert_eq!(table_flows[0].0.flownode_id(), 2);
+        }
+    }
 }
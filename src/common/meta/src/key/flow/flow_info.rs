@@ -15,6 +15,8 @@
 use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 
+use futures::stream::BoxStream;
+use futures::TryStreamExt;
 use lazy_static::lazy_static;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
@@ -25,9 +27,14 @@ use table::table_name::TableName;
 use crate::error::{self, Result};
 use crate::key::flow::FlowScoped;
 use crate::key::txn_helper::TxnOpGetResponseSet;
-use crate::key::{DeserializedValueWithBytes, FlowId, FlowPartitionId, MetadataKey, MetadataValue};
+use crate::key::{
+    BytesAdapter, DeserializedValueWithBytes, FlowId, FlowPartitionId, MetadataKey, MetadataValue,
+};
 use crate::kv_backend::txn::Txn;
 use crate::kv_backend::KvBackendRef;
+use crate::range_stream::{PaginationStream, DEFAULT_PAGE_SIZE};
+use crate::rpc::store::RangeRequest;
+use crate::rpc::KeyValue;
 use crate::FlownodeId;
 
 const FLOW_INFO_KEY_PREFIX: &str = "info";
@@ -63,6 +70,13 @@ impl FlowInfoKey {
         FlowInfoKey(FlowScoped::new(inner))
     }
 
+    /// The prefix used to retrieve all [FlowInfoKey]s.
+    pub fn range_start_key() -> Vec<u8> {
+        let inner = BytesAdapter::from(format!("{FLOW_INFO_KEY_PREFIX}/").into_bytes());
+
+        FlowScoped::new(inner).to_bytes()
+    }
+
     /// Returns the [FlowId].
     pub fn flow_id(&self) -> FlowId {
         self.0.flow_id
@@ -180,12 +194,34 @@ pub struct FlowInfoManager {
     kv_backend: KvBackendRef,
 }
 
+/// Decodes `KeyValue` to [FlowInfoKey].
+pub fn flow_info_key_decoder(kv: KeyValue) -> Result<FlowInfoKey> {
+    FlowInfoKey::from_bytes(&kv.key)
+}
+
 impl FlowInfoManager {
     /// Returns a new [FlowInfoManager].
     pub fn new(kv_backend: KvBackendRef) -> Self {
         Self { kv_backend }
     }
 
+    /// Retrieves ids of all flows.
+    pub fn flow_ids(&self) -> BoxStream<'static, Result<FlowId>> {
+        let req = RangeRequest::new()
+            .with_prefix(FlowInfoKey::range_start_key())
+            .with_keys_only();
+
+        let stream = PaginationStream::new(
+            self.kv_backend.clone(),
+            req,
+            DEFAULT_PAGE_SIZE,
+            Arc::new(flow_info_key_decoder),
+        )
+        .into_stream();
+
+        Box::pin(stream.map_ok(|key| key.flow_id()))
+    }
+
     /// Returns the [FlowInfoValue] of specified `flow_id`.
     pub async fn get(&self, flow_id: FlowId) -> Result<Option<FlowInfoValue>> {
         let key = FlowInfoKey::new(flow_id).to_bytes();
@@ -196,6 +232,19 @@ impl FlowInfoManager {
             .transpose()
     }
 
+    /// Returns the [FlowInfoValue] of specified `flow_id` with its raw bytes, used for updating it.
+    pub async fn get_raw(
+        &self,
+        flow_id: FlowId,
+    ) -> Result<Option<DeserializedValueWithBytes<FlowInfoValue>>> {
+        let key = FlowInfoKey::new(flow_id).to_bytes();
+        self.kv_backend
+            .get(&key)
+            .await?
+            .map(|x| DeserializedValueWithBytes::from_inner_slice(&x.value))
+            .transpose()
+    }
+
     /// Builds a create flow transaction.
     /// It is expected that the `__flow/info/{flow_id}` wasn't occupied.
     /// Otherwise, the transaction will retrieve existing value.
@@ -215,6 +264,29 @@ impl FlowInfoManager {
             TxnOpGetResponseSet::decode_with(TxnOpGetResponseSet::filter(key)),
         ))
     }
+
+    /// Builds an update flow transaction.
+    /// It is expected that the `__flow/info/{flow_id}` is still `current_flow_value`.
+    /// Otherwise, the transaction will retrieve existing value.
+    pub(crate) fn build_update_txn(
+        &self,
+        flow_id: FlowId,
+        current_flow_value: &DeserializedValueWithBytes<FlowInfoValue>,
+        new_flow_value: &FlowInfoValue,
+    ) -> Result<(
+        Txn,
+        impl FnOnce(&mut TxnOpGetResponseSet) -> FlowInfoDecodeResult,
+    )> {
+        let key = FlowInfoKey::new(flow_id).to_bytes();
+        let raw_value = current_flow_value.get_raw_bytes();
+        let new_raw_value = new_flow_value.try_as_raw_value()?;
+        let txn = Txn::compare_and_put(key.clone(), raw_value, new_raw_value);
+
+        Ok((
+            txn,
+            TxnOpGetResponseSet::decode_with(TxnOpGetResponseSet::filter(key)),
+        ))
+    }
 }
 
 #[cfg(test)]
@@ -233,4 +305,9 @@ mod tests {
         let key = FlowInfoKey::from_bytes(&bytes).unwrap();
         assert_eq!(key.flow_id(), 2);
     }
+
+    #[test]
+    fn test_key_start_range() {
+        assert_eq!(b"__flow/info/".to_vec(), FlowInfoKey::range_start_key());
+    }
 }
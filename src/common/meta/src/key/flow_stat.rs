@@ -0,0 +1,212 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::Display;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use snafu::{OptionExt, ResultExt};
+use strum::AsRefStr;
+
+use crate::error::{self, InvalidMetadataSnafu, Result};
+use crate::key::{FlowId, MetadataKey};
+use crate::kv_backend::KvBackendRef;
+use crate::rpc::store::{PutRequest, RangeRequest};
+use crate::FlownodeId;
+
+pub const FLOW_STAT_KEY_PREFIX: &str = "__flow_stat";
+
+lazy_static! {
+    static ref FLOW_STAT_KEY_PATTERN: Regex =
+        Regex::new(&format!("^{FLOW_STAT_KEY_PREFIX}/([0-9]+)$")).unwrap();
+}
+
+/// The key stores status of flows running on a flownode, written by the flownode periodically.
+///
+/// The layout: `__flow_stat/{flownode_id}`
+#[derive(Debug, PartialEq)]
+pub struct FlowStatKey {
+    pub node_id: FlownodeId,
+}
+
+impl FlowStatKey {
+    pub fn new(node_id: FlownodeId) -> Self {
+        Self { node_id }
+    }
+
+    /// The prefix used to retrieve all [FlowStatKey]s.
+    pub fn range_prefix() -> Vec<u8> {
+        format!("{FLOW_STAT_KEY_PREFIX}/").into_bytes()
+    }
+}
+
+impl MetadataKey<'_, FlowStatKey> for FlowStatKey {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.to_string().into_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<FlowStatKey> {
+        let key = std::str::from_utf8(bytes).map_err(|e| {
+            InvalidMetadataSnafu {
+                err_msg: format!(
+                    "FlowStatKey '{}' is not a valid UTF8 string: {e}",
+                    String::from_utf8_lossy(bytes)
+                ),
+            }
+            .build()
+        })?;
+        let captures = FLOW_STAT_KEY_PATTERN
+            .captures(key)
+            .context(InvalidMetadataSnafu {
+                err_msg: format!("Invalid FlowStatKey '{key}'"),
+            })?;
+        // Safety: pass the regex check above
+        let node_id = captures[1].parse::<FlownodeId>().unwrap();
+        Ok(FlowStatKey::new(node_id))
+    }
+}
+
+impl Display for FlowStatKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", FLOW_STAT_KEY_PREFIX, self.node_id)
+    }
+}
+
+/// The status of flows running on a flownode.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlowStatValue {
+    /// The status of each flow.
+    pub flow_stats: Vec<FlowTaskStatus>,
+    /// The timestamp in milliseconds when the status is reported.
+    pub report_time_ms: i64,
+}
+
+/// The status of a flow task, reported by the flownode running it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlowTaskStatus {
+    pub flow_id: FlowId,
+    pub state: FlowTaskState,
+    /// The last error occurred when running the flow.
+    pub last_error: Option<String>,
+    /// The timestamp in milliseconds the flow has computed up to.
+    pub watermark_ms: Option<i64>,
+    /// The number of input rows processed by the flow.
+    pub rows_processed: u64,
+    /// How far in milliseconds the flow's output lags behind the current time.
+    pub lag_ms: Option<i64>,
+    /// The number of rows in the flow's state.
+    pub state_rows: Option<u64>,
+    /// The estimated size in bytes of the flow's state.
+    pub state_bytes: Option<u64>,
+}
+
+/// The running state of a flow task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, AsRefStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum FlowTaskState {
+    Running,
+    Paused,
+    /// The flow got errors, it stays failed until it's resumed or recreated.
+    Failed,
+}
+
+/// The manager of [FlowStatKey]s.
+pub struct FlowStatManager {
+    kv_backend: KvBackendRef,
+}
+
+impl FlowStatManager {
+    pub fn new(kv_backend: KvBackendRef) -> Self {
+        Self { kv_backend }
+    }
+
+    /// Puts the status of flows running on the flownode, replacing the last one.
+    pub async fn put(&self, node_id: FlownodeId, value: &FlowStatValue) -> Result<()> {
+        let value = serde_json::to_vec(value).context(error::SerdeJsonSnafu)?;
+        let req = PutRequest::new()
+            .with_key(FlowStatKey::new(node_id).to_bytes())
+            .with_value(value);
+        self.kv_backend.put(req).await?;
+        Ok(())
+    }
+
+    /// Returns the status of flows last reported by the flownode.
+    pub async fn get(&self, node_id: FlownodeId) -> Result<Option<FlowStatValue>> {
+        let key = FlowStatKey::new(node_id).to_bytes();
+        self.kv_backend
+            .get(&key)
+            .await?
+            .map(|kv| serde_json::from_slice(&kv.value).context(error::SerdeJsonSnafu))
+            .transpose()
+    }
+
+    /// Returns the status of flows last reported by each flownode.
+    pub async fn list(&self) -> Result<Vec<(FlownodeId, FlowStatValue)>> {
+        let req = RangeRequest::new().with_prefix(FlowStatKey::range_prefix());
+        let resp = self.kv_backend.range(req).await?;
+        resp.kvs
+            .into_iter()
+            .map(|kv| {
+                let key = FlowStatKey::from_bytes(&kv.key)?;
+                let value = serde_json::from_slice(&kv.value).context(error::SerdeJsonSnafu)?;
+                Ok((key.node_id, value))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::kv_backend::memory::MemoryKvBackend;
+
+    #[test]
+    fn test_flow_stat_key() {
+        let key = FlowStatKey::new(3);
+        assert_eq!("__flow_stat/3", key.to_string());
+        let key2 = FlowStatKey::from_bytes(&key.to_bytes()).unwrap();
+        assert_eq!(key, key2);
+        assert!(FlowStatKey::from_bytes(b"__flow_stat/a").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_flow_stat_manager() {
+        let manager = FlowStatManager::new(Arc::new(MemoryKvBackend::default()));
+        assert!(manager.get(1).await.unwrap().is_none());
+
+        let value = FlowStatValue {
+            flow_stats: vec![FlowTaskStatus {
+                flow_id: 1024,
+                state: FlowTaskState::Failed,
+                last_error: Some("error".to_string()),
+                watermark_ms: Some(1000),
+                rows_processed: 10,
+                lag_ms: Some(5),
+                state_rows: None,
+                state_bytes: None,
+            }],
+            report_time_ms: 1000,
+        };
+        manager.put(1, &value).await.unwrap();
+        manager.put(2, &FlowStatValue::default()).await.unwrap();
+        assert_eq!(Some(value.clone()), manager.get(1).await.unwrap());
+        assert_eq!("failed", value.flow_stats[0].state.as_ref());
+
+        let stats = manager.list().await.unwrap();
+        assert_eq!(vec![(1, value), (2, FlowStatValue::default())], stats);
+    }
+}
@@ -57,6 +57,18 @@ lazy_static! {
         &["step"]
     )
         .unwrap();
+    pub static ref METRIC_META_PROCEDURE_ALTER_FLOW: HistogramVec = register_histogram_vec!(
+        "greptime_meta_procedure_alter_flow",
+        "meta procedure alter flow",
+        &["step"]
+    )
+    .unwrap();
+    pub static ref METRIC_META_PROCEDURE_REASSIGN_FLOW: HistogramVec = register_histogram_vec!(
+        "greptime_meta_procedure_reassign_flow",
+        "meta procedure reassign flow",
+        &["step"]
+    )
+    .unwrap();
     pub static ref METRIC_META_PROCEDURE_DROP_VIEW: HistogramVec = register_histogram_vec!(
         "greptime_meta_procedure_drop_view",
         "meta procedure drop view",
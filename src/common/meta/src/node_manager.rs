@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use api::region::RegionResponse;
@@ -20,9 +21,13 @@ use api::v1::region::{InsertRequests, RegionRequest};
 pub use common_base::AffectedRows;
 use common_query::request::QueryRequest;
 use common_recordbatch::SendableRecordBatchStream;
+use table::metadata::TableId;
+use table::table_name::TableName;
 
-use crate::error::Result;
+use crate::error::{Result, UnsupportedSnafu};
+use crate::key::FlowId;
 use crate::peer::Peer;
+use crate::rpc::ddl::QueryContext;
 
 /// The trait for handling requests to datanode.
 #[async_trait::async_trait]
@@ -36,12 +41,79 @@ pub trait Datanode: Send + Sync {
 
 pub type DatanodeRef = Arc<dyn Datanode>;
 
+/// The request to alter a flow running on a flownode, with the whole new definition of the flow.
+///
+/// Only `expire_after` and `comment` can be changed in place, the dataflow is rebuilt if the query
+/// or options are changed or `discard_state` is set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlterFlowRequest {
+    pub flow_id: FlowId,
+    /// Duration in seconds, `None` to never expire.
+    pub expire_after: Option<i64>,
+    pub comment: String,
+    pub sql: String,
+    pub flow_options: HashMap<String, String>,
+    /// Rebuilds the dataflow with empty state, instead of keeping its state when possible.
+    pub discard_state: bool,
+}
+
+/// The request to explain a flow, which is planned on a flownode without being created.
+#[derive(Debug, Clone)]
+pub struct ExplainFlowRequest {
+    pub source_table_ids: Vec<TableId>,
+    pub sink_table_name: TableName,
+    pub sql: String,
+    pub flow_options: HashMap<String, String>,
+    pub query_context: QueryContext,
+}
+
 /// The trait for handling requests to flownode
 #[async_trait::async_trait]
 pub trait Flownode: Send + Sync {
     async fn handle(&self, request: FlowRequest) -> Result<FlowResponse>;
 
     async fn handle_inserts(&self, request: InsertRequests) -> Result<FlowResponse>;
+
+    /// Alters the flow, in place if only its `expire_after` or `comment` is changed.
+    async fn alter_flow(&self, request: AlterFlowRequest) -> Result<()> {
+        UnsupportedSnafu {
+            operation: format!("alter flow {}", request.flow_id),
+        }
+        .fail()
+    }
+
+    /// Pauses the flow, which stops reading from sources and writing to sinks but keeps its state.
+    async fn pause_flow(&self, flow_id: FlowId) -> Result<()> {
+        UnsupportedSnafu {
+            operation: format!("pause flow {flow_id}"),
+        }
+        .fail()
+    }
+
+    /// Resumes the paused flow.
+    async fn resume_flow(&self, flow_id: FlowId) -> Result<()> {
+        UnsupportedSnafu {
+            operation: format!("resume flow {flow_id}"),
+        }
+        .fail()
+    }
+
+    /// Plans the flow without creating it, returns the description of its plan.
+    async fn explain_flow(&self, request: ExplainFlowRequest) -> Result<String> {
+        UnsupportedSnafu {
+            operation: format!("explain flow on {}", request.sink_table_name),
+        }
+        .fail()
+    }
+
+    /// Recomputes all flows reading from the table, after rows of the table are deleted or
+    /// the table is truncated, since such changes can't be mirrored to flows as inserts are.
+    async fn reset_source_table(&self, table_id: TableId) -> Result<()> {
+        UnsupportedSnafu {
+            operation: format!("reset flows of table {table_id}"),
+        }
+        .fail()
+    }
 }
 
 pub type FlownodeRef = Arc<dyn Flownode>;
@@ -22,6 +22,12 @@ use std::fmt::{Display, Formatter};
 
 use api::v1::meta::{KeyValue as PbKeyValue, ResponseHeader as PbResponseHeader};
 
+/// The gRPC metadata key of the term of the metasrv leader, attached to its responses.
+pub const META_LEADER_TERM: &str = "x-greptime-meta-leader-term";
+/// The gRPC metadata key of the remaining milliseconds of the lease of the metasrv leader,
+/// attached to its responses.
+pub const META_LEADER_LEASE_REMAINING: &str = "x-greptime-meta-leader-lease-remaining-ms";
+
 #[derive(Debug, Clone)]
 pub struct ResponseHeader(PbResponseHeader);
 
@@ -59,6 +59,7 @@ pub enum DdlTask {
     DropDatabase(DropDatabaseTask),
     CreateFlow(CreateFlowTask),
     DropFlow(DropFlowTask),
+    AlterFlow(AlterFlowTask),
     CreateView(CreateViewTask),
     DropView(DropViewTask),
 }
@@ -74,6 +75,11 @@ impl DdlTask {
         DdlTask::DropFlow(expr)
     }
 
+    /// Creates a [`DdlTask`] to alter a flow.
+    pub fn new_alter_flow(expr: AlterFlowTask) -> Self {
+        DdlTask::AlterFlow(expr)
+    }
+
     /// Creates a [`DdlTask`] to drop a view.
     pub fn new_drop_view(expr: DropViewTask) -> Self {
         DdlTask::DropView(expr)
@@ -231,6 +237,10 @@ impl TryFrom<Task> for DdlTask {
     }
 }
 
+/// The gRPC metadata key of the idempotency key of a DDL task, with which metasrv runs the task
+/// once even if it's submitted again, e.g., retried by a client after the response is lost.
+pub const DDL_IDEMPOTENCY_KEY: &str = "x-greptime-ddl-idempotency-key";
+
 #[derive(Clone)]
 pub struct SubmitDdlTaskRequest {
     pub query_context: QueryContextRef,
@@ -274,6 +284,13 @@ impl TryFrom<SubmitDdlTaskRequest> for PbDdlTaskRequest {
             DdlTask::DropDatabase(task) => Task::DropDatabaseTask(task.try_into()?),
             DdlTask::CreateFlow(task) => Task::CreateFlowTask(task.into()),
             DdlTask::DropFlow(task) => Task::DropFlowTask(task.into()),
+            // TODO: add alter flow task to the proto, it's only submitted to the local procedure executor for now
+            DdlTask::AlterFlow(_) => {
+                return error::UnsupportedSnafu {
+                    operation: "submitting alter flow task to remote metasrv",
+                }
+                .fail()
+            }
             DdlTask::CreateView(task) => Task::CreateViewTask(task.try_into()?),
             DdlTask::DropView(task) => Task::DropViewTask(task.into()),
         };
@@ -1073,6 +1090,29 @@ impl From<DropFlowTask> for PbDropFlowTask {
     }
 }
 
+/// Alter flow, changing options or the query of the flow without recreating it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlterFlowTask {
+    pub catalog_name: String,
+    pub flow_name: String,
+    /// Duration in seconds, `None` to keep it unchanged and `Some(None)` to remove it.
+    pub expire_after: Option<Option<i64>>,
+    /// `None` to keep it unchanged.
+    pub comment: Option<String>,
+    /// Options overriding the options of the same keys, others are kept unchanged.
+    #[serde(default)]
+    pub flow_options: HashMap<String, String>,
+    /// The new query, `None` to keep it unchanged.
+    #[serde(default)]
+    pub sql: Option<String>,
+    /// The tables the new query reads from, which must be the current source tables of the flow.
+    #[serde(default)]
+    pub source_table_names: Vec<TableName>,
+    /// Rebuilds the flow with empty state, instead of keeping its state when possible.
+    #[serde(default)]
+    pub discard_state: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QueryContext {
     current_catalog: String,
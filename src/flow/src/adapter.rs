@@ -16,51 +16,87 @@
 //! and communicating with other parts of the database
 #![warn(unused_imports)]
 
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant, SystemTime};
 
-use api::v1::{RowDeleteRequest, RowDeleteRequests, RowInsertRequest, RowInsertRequests};
+use api::v1::{
+    RowDeleteRequest, RowDeleteRequests, RowInsertRequest, RowInsertRequests, SemanticType,
+};
+use catalog::information_schema::FlowStat;
+use common_base::readable_size::ReadableSize;
+use common_catalog::consts::{DEFAULT_CATALOG_NAME, DEFAULT_SCHEMA_NAME};
 use common_config::Configurable;
-use common_error::ext::BoxedError;
+use common_error::ext::{BoxedError, ErrorExt};
+use common_error::sampler::{ErrorSampler, Sample};
+use common_error::status_code::StatusCode;
+use common_meta::key::flow_stat::{FlowStatManager, FlowStatValue, FlowTaskState, FlowTaskStatus};
 use common_meta::key::TableMetadataManagerRef;
 use common_runtime::JoinHandle;
 use common_telemetry::logging::{LoggingOptions, TracingOptions};
-use common_telemetry::{debug, info, trace};
-use datatypes::schema::ColumnSchema;
+use common_telemetry::{debug, info, trace, warn};
 use datatypes::value::Value;
 use greptime_proto::v1;
 use itertools::Itertools;
 use meta_client::MetaClientOptions;
+use prometheus::IntGaugeVec;
 use query::QueryEngine;
 use serde::{Deserialize, Serialize};
 use servers::grpc::GrpcOptions;
 use servers::heartbeat_options::HeartbeatOptions;
 use servers::Mode;
-use session::context::QueryContext;
+use session::context::{QueryContext, QueryContextBuilder};
 use snafu::{ensure, OptionExt, ResultExt};
-use store_api::storage::{ConcreteDataType, RegionId};
-use table::metadata::TableId;
+use store_api::storage::RegionId;
+use table::metadata::{TableId, TableVersion};
 use tokio::sync::broadcast::error::TryRecvError;
 use tokio::sync::{broadcast, watch, Mutex, RwLock};
 
+pub use crate::adapter::checkpoint::CheckpointOptions;
+use crate::adapter::checkpoint::{CheckpointStore, FlowCheckpoint};
+use crate::adapter::dead_letter::error_table_name;
+pub use crate::adapter::flow_options::FlowOptions;
 pub(crate) use crate::adapter::node_context::FlownodeContext;
+use crate::adapter::paused::PausedFlowStore;
 use crate::adapter::table_source::TableSource;
-use crate::adapter::util::column_schemas_to_proto;
-use crate::adapter::worker::{create_worker, Worker, WorkerHandle};
-use crate::compute::ErrCollector;
+pub use crate::adapter::table_source::{TableIdNameCache, TableIdNameCacheRef};
+use crate::adapter::util::{
+    check_sink_table_schema, column_schemas_to_proto, sink_table_schema_from_flow,
+};
+use crate::adapter::worker::{create_worker, ReplaceMode, Worker, WorkerHandle};
+use crate::compute::{DataflowCheckpoint, ErrCollector, OperatorError, SpillOptions};
 use crate::df_optimizer::sql_to_flow_plan;
-use crate::error::{EvalSnafu, ExternalSnafu, InternalSnafu, TableNotFoundSnafu, UnexpectedSnafu};
-use crate::expr::{Batch, GlobalId};
-use crate::metrics::{METRIC_FLOW_INSERT_ELAPSED, METRIC_FLOW_RUN_INTERVAL_MS};
-use crate::repr::{self, DiffRow, Row, BATCH_SIZE};
+use crate::error::{
+    EvalSnafu, ExternalSnafu, FlowNotFoundSnafu, InternalSnafu, InvalidQuerySnafu,
+    ReportFlowStatSnafu, ShuttingDownSnafu, StateLimitExceededSnafu, TableNotFoundSnafu,
+    UnexpectedSnafu,
+};
+use crate::expr::{Batch, GlobalId, ScalarExpr};
+use crate::metrics::{
+    flow_metric_value, remove_flow_metrics, METRIC_FLOW_ERRORS, METRIC_FLOW_INPUT_ROWS,
+    METRIC_FLOW_INSERT_ELAPSED, METRIC_FLOW_OUTPUT_ROWS, METRIC_FLOW_PROCESSING_LAG_MS,
+    METRIC_FLOW_RUN_INTERVAL_MS, METRIC_FLOW_STATE_BYTES, METRIC_FLOW_STATE_ROWS,
+    METRIC_FLOW_WATERMARK_MS,
+};
+use crate::plan::Plan;
+use crate::repr::{self, DiffRow, RelationDesc, Row, BATCH_SIZE};
 
+mod backfill;
+pub(crate) mod checkpoint;
+mod dead_letter;
+mod explain;
+pub(crate) mod flow_options;
 mod flownode_impl;
 mod parse_expr;
+mod paused;
+mod profile;
+mod schema_change;
 #[cfg(test)]
 mod tests;
 mod util;
-mod worker;
+pub(crate) mod worker;
 
 pub(crate) mod node_context;
 mod table_source;
@@ -73,11 +109,83 @@ pub const AUTO_CREATED_PLACEHOLDER_TS_COL: &str = "__ts_placeholder";
 
 pub const UPDATE_AT_TS_COL: &str = "update_at";
 
+/// Max number of recent errors kept for each flow, see [`FlowWorkerManager::task_errors`]
+const MAX_RETAINED_FLOW_ERRORS: usize = 1024;
+
 // TODO(discord9): refactor common types for flow to a separate module
 /// FlowId is a unique identifier for a flow task
 pub type FlowId = u64;
 pub type TableName = [String; 3];
 
+/// Definition of a flow created in this flownode, as given by the create request
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlowInfo {
+    pub flow_id: FlowId,
+    pub sink_table_name: TableName,
+    pub source_table_ids: Vec<TableId>,
+    pub expire_after: Option<i64>,
+    pub comment: Option<String>,
+    pub sql: String,
+    pub options: FlowOptions,
+    /// fingerprint of the flow's plan, see [`TypedPlan::fingerprint`](crate::plan::TypedPlan::fingerprint)
+    pub fingerprint: u64,
+    /// versions of the source tables the flow is planned against
+    #[serde(default)]
+    pub source_versions: BTreeMap<TableId, TableVersion>,
+    /// catalog the flow's query is planned in
+    #[serde(default = "default_catalog")]
+    pub catalog: String,
+    /// schema the flow's query is planned in
+    #[serde(default = "default_schema")]
+    pub schema: String,
+}
+
+fn default_catalog() -> String {
+    DEFAULT_CATALOG_NAME.to_string()
+}
+
+fn default_schema() -> String {
+    DEFAULT_SCHEMA_NAME.to_string()
+}
+
+impl FlowInfo {
+    /// The context the flow's query is planned in
+    pub fn query_context(&self) -> QueryContext {
+        QueryContextBuilder::default()
+            .current_catalog(self.catalog.clone())
+            .current_schema(self.schema.clone())
+            .build()
+    }
+}
+
+/// Options of running flows
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FlowConfig {
+    /// Number of single-threaded workers that flows are sharded across, each flow runs on
+    /// exactly one of them
+    pub num_workers: usize,
+    /// Directory the state of flows exceeding their memory budget is spilled to, flows' state
+    /// is kept in memory if not set
+    pub spill_dir: Option<String>,
+    /// Memory budget of each flow's state, which can be overridden by the flow's `memory_budget`
+    pub state_memory_budget: ReadableSize,
+    /// Table errors of all flows are written to, as `table`, `schema.table` or
+    /// `catalog.schema.table`, errors are only kept in memory if not set
+    pub error_table: Option<String>,
+}
+
+impl Default for FlowConfig {
+    fn default() -> Self {
+        Self {
+            num_workers: (common_config::utils::get_cpus() / 2).max(1),
+            spill_dir: None,
+            state_memory_budget: ReadableSize::gb(1),
+            error_table: None,
+        }
+    }
+}
+
 /// Options for flow node
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(default)]
@@ -90,6 +198,8 @@ pub struct FlownodeOptions {
     pub logging: LoggingOptions,
     pub tracing: TracingOptions,
     pub heartbeat: HeartbeatOptions,
+    pub checkpoint: CheckpointOptions,
+    pub flow: FlowConfig,
 }
 
 impl Default for FlownodeOptions {
@@ -103,6 +213,8 @@ impl Default for FlownodeOptions {
             logging: LoggingOptions::default(),
             tracing: TracingOptions::default(),
             heartbeat: HeartbeatOptions::default(),
+            checkpoint: CheckpointOptions::default(),
+            flow: FlowConfig::default(),
         }
     }
 }
@@ -116,8 +228,9 @@ pub type FlowWorkerManagerRef = Arc<FlowWorkerManager>;
 ///
 /// The choice of timestamp is just using current system timestamp for now
 pub struct FlowWorkerManager {
-    /// The handler to the worker that will run the dataflow
-    /// which is `!Send` so a handle is used
+    /// The handlers to the workers that will run the dataflow
+    /// which is `!Send` so a handle is used, each flow runs on the worker chosen by
+    /// [`FlowWorkerManager::worker_of`]
     pub worker_handles: Vec<Mutex<WorkerHandle>>,
     /// The query engine that will be used to parse the query and convert it to a dataflow plan
     pub query_engine: Arc<dyn QueryEngine>,
@@ -127,13 +240,44 @@ pub struct FlowWorkerManager {
     /// contains mapping from table name to global id, and table schema
     node_context: RwLock<FlownodeContext>,
     flow_err_collectors: RwLock<BTreeMap<FlowId, ErrCollector>>,
+    /// the last error reported by each flow, shown in `information_schema.flows`
+    flow_last_errors: RwLock<BTreeMap<FlowId, String>>,
+    /// recent errors raised by operators of each flow, see [`FlowWorkerManager::task_errors`]
+    flow_errors: RwLock<BTreeMap<FlowId, VecDeque<OperatorError>>>,
+    /// samples errors of flows to log, so a flow failing on every row doesn't flood the logs
+    error_sampler: ErrorSampler,
+    /// table errors of all flows are written to, see [`FlowWorkerManager::set_error_table`]
+    error_table: Option<TableName>,
+    /// flows which got errors, they stay failed until resumed or recreated
+    failed_flows: RwLock<BTreeSet<FlowId>>,
+    /// flows which are paused, see [`FlowWorkerManager::pause_flow`]
+    paused_flows: RwLock<BTreeSet<FlowId>>,
+    /// where paused flows are persisted, so they stay paused after restart
+    paused_flow_store: PausedFlowStore,
+    /// where status of flows are reported to, see [`FlowWorkerManager::report_flow_stats`]
+    flow_stat_manager: FlowStatManager,
+    /// definition of each flow created in this flownode
+    flow_infos: RwLock<BTreeMap<FlowId, FlowInfo>>,
+    /// where to persist checkpoints of flows' state, no checkpoint is taken if not set
+    checkpoint_store: Option<CheckpointStore>,
+    /// where flows spill their state to, with the default memory budget of each flow's state,
+    /// see [`FlowWorkerManager::set_spill`]
+    spill: Option<(PathBuf, ReadableSize)>,
+    /// when the results of each flow are last written to its sink table, used to throttle
+    /// flows with `emit_interval`
+    flow_last_emits: RwLock<BTreeMap<FlowId, Instant>>,
     src_send_buf_lens: RwLock<BTreeMap<TableId, watch::Receiver<usize>>>,
     tick_manager: FlowTickManager,
     node_id: Option<u32>,
+    /// Set once the manager starts shutting down, inserts are rejected since then,
+    /// see [`FlowWorkerManager::drain`]
+    shutting_down: AtomicBool,
     /// Lock for flushing, will be `read` by `handle_inserts` and `write` by `flush_flow`
     ///
     /// So that a series of event like `inserts -> flush` can be handled correctly
     flush_lock: RwLock<()>,
+    /// Lock for resetting flows, see [`FlowWorkerManager::reset_flows_of_table`]
+    reset_lock: Mutex<()>,
 }
 
 /// Building FlownodeManager
@@ -153,6 +297,8 @@ impl FlowWorkerManager {
             table_meta.table_info_manager().clone(),
             table_meta.table_name_manager().clone(),
         );
+        let paused_flow_store = PausedFlowStore::new(table_meta.kv_backend().clone());
+        let flow_stat_manager = FlowStatManager::new(table_meta.kv_backend().clone());
         let node_context = FlownodeContext::default();
         let tick_manager = FlowTickManager::new();
         let worker_handles = Vec::new();
@@ -163,10 +309,24 @@ impl FlowWorkerManager {
             frontend_invoker: RwLock::new(None),
             node_context: RwLock::new(node_context),
             flow_err_collectors: Default::default(),
+            flow_last_errors: Default::default(),
+            flow_errors: Default::default(),
+            error_sampler: Default::default(),
+            error_table: None,
+            failed_flows: Default::default(),
+            paused_flows: Default::default(),
+            paused_flow_store,
+            flow_stat_manager,
+            flow_infos: Default::default(),
+            checkpoint_store: None,
+            spill: None,
+            flow_last_emits: Default::default(),
             src_send_buf_lens: Default::default(),
             tick_manager,
             node_id,
+            shutting_down: AtomicBool::new(false),
             flush_lock: RwLock::new(()),
+            reset_lock: Mutex::new(()),
         }
     }
 
@@ -186,6 +346,50 @@ impl FlowWorkerManager {
     pub fn add_worker_handle(&mut self, handle: WorkerHandle) {
         self.worker_handles.push(Mutex::new(handle));
     }
+
+    /// The index of the worker a newly created flow runs on
+    ///
+    /// flows are sharded across workers by their ids, inputs are routed to the worker running
+    /// a flow by the flow's subscription to its source tables, see [`FlownodeContext::send`]
+    fn worker_of(&self, flow_id: FlowId) -> usize {
+        (flow_id % self.worker_handles.len() as u64) as usize
+    }
+
+    /// set where to persist checkpoints of flows' state, so flows can be resumed from them after restart
+    pub(crate) fn set_checkpoint_store(&mut self, store: CheckpointStore) {
+        self.checkpoint_store = Some(store);
+    }
+
+    /// Spill the state of flows exceeding `memory_budget` to `dir`, each flow in its own
+    /// subdirectory
+    ///
+    /// Files left by a previous run are removed, since the state is restored from checkpoints
+    pub(crate) fn set_spill(
+        &mut self,
+        dir: PathBuf,
+        memory_budget: ReadableSize,
+    ) -> Result<(), Error> {
+        match std::fs::remove_dir_all(&dir) {
+            Ok(()) => (),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => (),
+            Err(e) => InternalSnafu {
+                reason: format!("Failed to clean spill directory {}: {e}", dir.display()),
+            }
+            .fail()?,
+        }
+        self.spill = Some((dir, memory_budget));
+        Ok(())
+    }
+
+    /// Write errors of all flows to the given table, see [`FlowConfig::error_table`]
+    pub(crate) fn set_error_table(&mut self, name: &str) {
+        self.error_table = Some(error_table_name(name));
+    }
+
+    /// set the cache of table name <-> table id mapping, which should be invalidated by DDL
+    pub fn set_table_id_name_cache(&mut self, cache: TableIdNameCacheRef) {
+        self.table_info_source.set_cache(cache);
+    }
 }
 
 #[derive(Debug)]
@@ -225,24 +429,105 @@ pub fn diff_row_to_request(rows: Vec<DiffRow>) -> Vec<DiffRequest> {
     reqs
 }
 
+/// Convert batches sent to a sink to requests, rows without a timestamp are at time 0
 pub fn batches_to_rows_req(batches: Vec<Batch>) -> Result<Vec<DiffRequest>, Error> {
-    let mut reqs = Vec::new();
+    let mut rows = Vec::new();
     for batch in batches {
-        let mut rows = Vec::with_capacity(batch.row_count());
-        for i in 0..batch.row_count() {
-            let row = batch.get_row(i).context(EvalSnafu)?;
-            rows.push((Row::new(row), 0));
+        rows.extend(batch.into_diff_rows(0).context(EvalSnafu)?);
+    }
+    Ok(diff_row_to_request(rows))
+}
+
+/// Convert changes to a sink table into upserts keyed by `key_indices`, i.e. the columns of the
+/// table's primary key and time index
+///
+/// A table keeps the last row written for each key, so only the final row of a key is written,
+/// and a key is only deleted if no row of it is left. This also makes an update, i.e. deleting
+/// the old row and inserting the new one of the same key, correct in whatever order they come.
+pub fn diff_requests_to_upserts(reqs: Vec<DiffRequest>, key_indices: &[usize]) -> Vec<DiffRequest> {
+    // key -> row -> (net count of the row, timestamp and sequence of its last change)
+    let mut changes: BTreeMap<Row, BTreeMap<Row, (repr::Diff, repr::Timestamp, usize)>> =
+        BTreeMap::new();
+    let mut seq = 0;
+    for req in reqs {
+        let (rows, diff) = match req {
+            DiffRequest::Insert(rows) => (rows, 1),
+            DiffRequest::Delete(rows) => (rows, -1),
+        };
+        for (row, ts) in rows {
+            // key columns not in the row are filled in the same way for all rows
+            let key = Row::new(
+                key_indices
+                    .iter()
+                    .filter_map(|i| row.get(*i).cloned())
+                    .collect(),
+            );
+            let change = changes
+                .entry(key)
+                .or_default()
+                .entry(row)
+                .or_insert((0, ts, seq));
+            *change = (change.0 + diff, ts, seq);
+            seq += 1;
+        }
+    }
+
+    let (mut inserts, mut deletes) = (Vec::new(), Vec::new());
+    for rows in changes.into_values() {
+        let last_of = |positive: bool| {
+            rows.iter()
+                .filter(|(_, (cnt, _, _))| if positive { *cnt > 0 } else { *cnt < 0 })
+                .max_by_key(|(_, (_, _, seq))| *seq)
+                .map(|(row, (_, ts, _))| (row.clone(), *ts))
+        };
+        if let Some(insert) = last_of(true) {
+            inserts.push(insert);
+        } else if let Some(delete) = last_of(false) {
+            deletes.push(delete);
+        }
+    }
+    [DiffRequest::Delete(deletes), DiffRequest::Insert(inserts)]
+        .into_iter()
+        .filter(|req| req.len() > 0)
+        .collect()
+}
+
+/// Max number of retries of writing back to a sink table on retryable errors
+const WRITEBACK_MAX_RETRIES: u32 = 3;
+/// The interval before first retry of writing back, doubled for each following retry
+const WRITEBACK_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Call `write` until it succeeds, fails with an error not retryable or runs out of retries
+async fn retry_writeback<F, Fut>(
+    table_name: &TableName,
+    mut write: F,
+) -> common_frontend::error::Result<()>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = common_frontend::error::Result<common_query::Output>>,
+{
+    let mut retries = 0;
+    loop {
+        match write().await {
+            Ok(_) => return Ok(()),
+            Err(err) if retries < WRITEBACK_MAX_RETRIES && err.status_code().is_retryable() => {
+                warn!(err; "Failed to write back to table {}, retrying", table_name.join("."));
+                tokio::time::sleep(WRITEBACK_RETRY_INTERVAL * 2u32.pow(retries)).await;
+                retries += 1;
+            }
+            Err(err) => return Err(err),
         }
-        reqs.push(DiffRequest::Insert(rows));
     }
-    Ok(reqs)
 }
 
 /// This impl block contains methods to send writeback requests to frontend
 impl FlowWorkerManager {
     /// Return the number of requests it made
-    pub async fn send_writeback_requests(&self) -> Result<usize, Error> {
-        let all_reqs = self.generate_writeback_request().await?;
+    ///
+    /// set `throttle` to hold back results of flows whose `emit_interval` has not elapsed since
+    /// their last write
+    pub async fn send_writeback_requests(&self, throttle: bool) -> Result<usize, Error> {
+        let all_reqs = self.generate_writeback_request(throttle).await?;
         if all_reqs.is_empty() || all_reqs.iter().all(|v| v.1.is_empty()) {
             return Ok(0);
         }
@@ -257,6 +542,14 @@ impl FlowWorkerManager {
             let (is_ts_placeholder, proto_schema) =
                 self.try_fetch_or_create_table(&table_name).await?;
             let schema_len = proto_schema.len();
+            let key_indices = proto_schema
+                .iter()
+                .positions(|col| {
+                    col.semantic_type == SemanticType::Tag as i32
+                        || col.semantic_type == SemanticType::Timestamp as i32
+                })
+                .collect_vec();
+            let reqs = diff_requests_to_upserts(reqs, &key_indices);
 
             trace!(
                 "Sending {} writeback requests to table {}, reqs total rows={}",
@@ -265,10 +558,11 @@ impl FlowWorkerManager {
                 reqs.iter().map(|r| r.len()).sum::<usize>()
             );
             let now = self.tick_manager.tick();
+            let cascaded_source = self.node_context.read().await.cascaded_source(&table_name);
             for req in reqs {
                 match req {
                     DiffRequest::Insert(insert) => {
-                        let rows_proto: Vec<v1::Row> = insert
+                        let rows: Vec<Row> = insert
                             .into_iter()
                             .map(|(mut row, _ts)| {
                                 // extend `update_at` col if needed
@@ -309,29 +603,39 @@ impl FlowWorkerManager {
                                     }
                                     .fail()?;
                                 }
-                                Ok(row.into())
+                                Ok(row)
                             })
                             .collect::<Result<Vec<_>, Error>>()?;
-                        let table_name = table_name.last().unwrap().clone();
+                        let cascaded_rows = cascaded_source.map(|_| rows.clone());
+                        let rows_proto: Vec<v1::Row> = rows.into_iter().map(Into::into).collect();
                         let req = RowInsertRequest {
-                            table_name,
+                            table_name: table_name.last().unwrap().clone(),
                             rows: Some(v1::Rows {
                                 schema: proto_schema.clone(),
                                 rows: rows_proto,
                             }),
                         };
                         req_cnt += 1;
-                        self.frontend_invoker
-                            .read()
-                            .await
-                            .as_ref()
-                            .with_context(|| UnexpectedSnafu {
-                                reason: "Expect a frontend invoker for flownode to write back",
-                            })?
-                            .row_inserts(RowInsertRequests { inserts: vec![req] }, ctx.clone())
-                            .await
-                            .map_err(BoxedError::new)
-                            .with_context(|_| ExternalSnafu {})?;
+                        let invoker = self.frontend_invoker.read().await;
+                        let invoker = invoker.as_ref().with_context(|| UnexpectedSnafu {
+                            reason: "Expect a frontend invoker for flownode to write back",
+                        })?;
+                        retry_writeback(&table_name, || {
+                            invoker.row_inserts(
+                                RowInsertRequests {
+                                    inserts: vec![req.clone()],
+                                },
+                                ctx.clone(),
+                            )
+                        })
+                        .await
+                        .map_err(BoxedError::new)
+                        .with_context(|_| ExternalSnafu {})?;
+                        // feed the rows written to flows reading the sink table in this flownode
+                        if let (Some(source_id), Some(rows)) = (cascaded_source, cascaded_rows) {
+                            let rows = rows.into_iter().map(|row| (row, now, 1)).collect_vec();
+                            self.node_context.read().await.send(source_id, rows).await?;
+                        }
                     }
                     DiffRequest::Delete(remove) => {
                         info!("original remove rows={:?}", remove);
@@ -344,9 +648,8 @@ impl FlowWorkerManager {
                                 row.into()
                             })
                             .collect::<Vec<_>>();
-                        let table_name = table_name.last().unwrap().clone();
                         let req = RowDeleteRequest {
-                            table_name,
+                            table_name: table_name.last().unwrap().clone(),
                             rows: Some(v1::Rows {
                                 schema: proto_schema.clone(),
                                 rows: rows_proto,
@@ -354,17 +657,21 @@ impl FlowWorkerManager {
                         };
 
                         req_cnt += 1;
-                        self.frontend_invoker
-                            .read()
-                            .await
-                            .as_ref()
-                            .with_context(|| UnexpectedSnafu {
-                                reason: "Expect a frontend invoker for flownode to write back",
-                            })?
-                            .row_deletes(RowDeleteRequests { deletes: vec![req] }, ctx.clone())
-                            .await
-                            .map_err(BoxedError::new)
-                            .with_context(|_| ExternalSnafu {})?;
+                        let invoker = self.frontend_invoker.read().await;
+                        let invoker = invoker.as_ref().with_context(|| UnexpectedSnafu {
+                            reason: "Expect a frontend invoker for flownode to write back",
+                        })?;
+                        retry_writeback(&table_name, || {
+                            invoker.row_deletes(
+                                RowDeleteRequests {
+                                    deletes: vec![req.clone()],
+                                },
+                                ctx.clone(),
+                            )
+                        })
+                        .await
+                        .map_err(BoxedError::new)
+                        .with_context(|_| ExternalSnafu {})?;
                     }
                 }
             }
@@ -373,25 +680,56 @@ impl FlowWorkerManager {
     }
 
     /// Generate writeback request for all sink table
+    ///
+    /// with `throttle`, results of flows whose `emit_interval` has not elapsed since their last
+    /// write are kept in the sink receiver until the next call
     pub async fn generate_writeback_request(
         &self,
+        throttle: bool,
     ) -> Result<BTreeMap<TableName, Vec<DiffRequest>>, Error> {
         trace!("Start to generate writeback request");
+        let emit_intervals: BTreeMap<_, _> = self
+            .flow_infos
+            .read()
+            .await
+            .iter()
+            .filter_map(|(flow_id, info)| Some((*flow_id, info.options.emit_interval?)))
+            .collect();
+        let mut last_emits = self.flow_last_emits.write().await;
+        let now = Instant::now();
         let mut output = BTreeMap::new();
         let mut total_row_count = 0;
-        for (name, sink_recv) in self
-            .node_context
-            .write()
-            .await
-            .sink_receiver
-            .iter_mut()
-            .map(|(n, (_s, r))| (n, r))
-        {
+        let mut node_ctx = self.node_context.write().await;
+        let FlownodeContext {
+            sink_receiver,
+            sink_to_flow,
+            ..
+        } = &mut *node_ctx;
+        for (name, sink_recv) in sink_receiver.iter_mut().map(|(n, (_s, r))| (n, r)) {
+            if let Some(flow_id) = sink_to_flow.get(name) {
+                let emit_interval = emit_intervals.get(flow_id);
+                let last_emit = last_emits.get(flow_id);
+                if let (true, Some(interval), Some(last)) = (throttle, emit_interval, last_emit) {
+                    if now.duration_since(*last) < *interval {
+                        continue;
+                    }
+                }
+                if emit_interval.is_some() {
+                    last_emits.insert(*flow_id, now);
+                }
+            }
             let mut batches = Vec::new();
+            let mut row_count = 0;
             while let Ok(batch) = sink_recv.try_recv() {
-                total_row_count += batch.row_count();
+                row_count += batch.row_count();
                 batches.push(batch);
             }
+            total_row_count += row_count;
+            if let Some(flow_id) = sink_to_flow.get(name) {
+                METRIC_FLOW_OUTPUT_ROWS
+                    .with_label_values(&[flow_id.to_string().as_str()])
+                    .inc_by(row_count as u64);
+            }
             let reqs = batches_to_rows_req(batches)?;
             output.insert(name.clone(), reqs);
         }
@@ -450,71 +788,43 @@ impl FlowWorkerManager {
                     name: format!("Table name = {:?}", table_name),
                 })?
                 .clone();
-            // TODO(discord9): use default key from schema
-            let primary_keys = schema
-                .typ()
-                .keys
-                .first()
-                .map(|v| {
-                    v.column_indices
-                        .iter()
-                        .map(|i| {
-                            schema
-                                .get_name(*i)
-                                .clone()
-                                .unwrap_or_else(|| format!("col_{i}"))
-                        })
-                        .collect_vec()
-                })
-                .unwrap_or_default();
-            let update_at = ColumnSchema::new(
-                UPDATE_AT_TS_COL,
-                ConcreteDataType::timestamp_millisecond_datatype(),
-                true,
-            );
-
-            let original_schema = schema
-                .typ()
-                .column_types
-                .clone()
-                .into_iter()
-                .enumerate()
-                .map(|(idx, typ)| {
-                    let name = schema
-                        .names
-                        .get(idx)
-                        .cloned()
-                        .flatten()
-                        .unwrap_or(format!("col_{}", idx));
-                    let ret = ColumnSchema::new(name, typ.scalar_type, typ.nullable);
-                    if schema.typ().time_index == Some(idx) {
-                        ret.with_time_index(true)
-                    } else {
-                        ret
-                    }
-                })
-                .collect_vec();
-
-            let mut with_auto_added_col = original_schema.clone();
-            with_auto_added_col.push(update_at);
-
-            // if no time index, add one as placeholder
-            let no_time_index = schema.typ().time_index.is_none();
-            if no_time_index {
-                let ts_col = ColumnSchema::new(
-                    AUTO_CREATED_PLACEHOLDER_TS_COL,
-                    ConcreteDataType::timestamp_millisecond_datatype(),
-                    true,
-                )
-                .with_time_index(true);
-                with_auto_added_col.push(ts_col);
-            }
-
-            (primary_keys, with_auto_added_col, no_time_index)
+            sink_table_schema_from_flow(&schema)
         };
         let proto_schema = column_schemas_to_proto(schema, &primary_keys)?;
         Ok((is_ts_placeholder, proto_schema))
     }
+
+    /// Create the sink table of a flow from its output schema, see [`sink_table_schema_from_flow`]
+    ///
+    /// it's kept even if the flow fails to be created, like the one created on write back
+    async fn create_sink_table(
+        &self,
+        flow_id: FlowId,
+        table_name: &TableName,
+        schema: &RelationDesc,
+    ) -> Result<(), Error> {
+        let (primary_keys, column_schemas, _) = sink_table_schema_from_flow(schema);
+        let proto_schema = column_schemas_to_proto(column_schemas, &primary_keys)?;
+        let invoker = self.frontend_invoker.read().await;
+        let invoker = invoker.as_ref().with_context(|| UnexpectedSnafu {
+            reason: "Expect a frontend invoker for flownode to create sink table",
+        })?;
+        invoker
+            .create_table(
+                table_name,
+                &proto_schema,
+                format!("Created by flow {flow_id}"),
+            )
+            .await
+            .map_err(BoxedError::new)
+            .context(ExternalSnafu)?;
+        info!(
+            "Created sink table {} of flow {}",
+            table_name.join("."),
+            flow_id
+        );
+        Ok(())
+    }
 }
 
 /// Flow Runtime related methods
@@ -530,18 +840,75 @@ impl FlowWorkerManager {
         })
     }
 
-    /// log all flow errors
+    /// log all flow errors, flows with errors are marked as failed until they are resumed or recreated
+    ///
+    /// The recent errors of each flow are kept, and written to the error table if set
     pub async fn log_all_errors(&self) {
+        let mut failed_flows = BTreeSet::new();
+        let mut new_errors = Vec::new();
         for (f_id, f_err) in self.flow_err_collectors.read().await.iter() {
             let all_errors = f_err.get_all().await;
             if !all_errors.is_empty() {
-                let all_errors = all_errors
-                    .into_iter()
-                    .map(|i| format!("{:?}", i))
-                    .join("\n");
-                common_telemetry::error!("Flow {} has following errors: {}", f_id, all_errors);
+                new_errors.push((*f_id, all_errors.clone()));
+                failed_flows.insert(*f_id);
+                METRIC_FLOW_ERRORS
+                    .with_label_values(&[f_id.to_string().as_str()])
+                    .inc_by(all_errors.len() as u64);
+                if let Some(last) = all_errors.last() {
+                    self.flow_last_errors
+                        .write()
+                        .await
+                        .insert(*f_id, format!("{:?}", last.error));
+                }
+                // eval errors are internal errors of the flow, see `Error::Eval`
+                let sampled_errors = all_errors
+                    .iter()
+                    .filter_map(|i| {
+                        match self
+                            .error_sampler
+                            .sample_stack(StatusCode::Internal, i.error.as_ref())
+                        {
+                            Sample::Emit { suppressed: 0 } => Some(format!("{:?}", i.error)),
+                            Sample::Emit { suppressed } => Some(format!(
+                                "{:?}\n({} similar errors suppressed)",
+                                i.error, suppressed
+                            )),
+                            Sample::Suppress => None,
+                        }
+                    })
+                    .collect_vec();
+                if !sampled_errors.is_empty() {
+                    common_telemetry::error!(
+                        "Flow {} has following errors: {}",
+                        f_id,
+                        sampled_errors.join("\n")
+                    );
+                }
             }
         }
+        self.failed_flows.write().await.extend(failed_flows);
+
+        let mut flow_errors = self.flow_errors.write().await;
+        for (flow_id, errors) in &new_errors {
+            let retained = flow_errors.entry(*flow_id).or_default();
+            retained.extend(errors.iter().cloned());
+            let overflow = retained.len().saturating_sub(MAX_RETAINED_FLOW_ERRORS);
+            retained.drain(..overflow);
+        }
+        drop(flow_errors);
+        if let Err(err) = self.write_flow_errors(new_errors).await {
+            common_telemetry::error!(err; "Failed to write flow errors to the error table");
+        }
+    }
+
+    /// Recent errors raised by operators of the flow, oldest first
+    pub async fn task_errors(&self, task_id: FlowId) -> Vec<OperatorError> {
+        self.flow_errors
+            .read()
+            .await
+            .get(&task_id)
+            .map(|errors| errors.iter().cloned().collect())
+            .unwrap_or_default()
     }
 
     /// Trigger dataflow running, and then send writeback request to the source sender
@@ -552,22 +919,32 @@ impl FlowWorkerManager {
         let default_interval = Duration::from_secs(1);
         let mut avg_spd = 0; // rows/sec
         let mut since_last_run = tokio::time::Instant::now();
+        let mut last_checkpoints = BTreeMap::new();
         loop {
-            // TODO(discord9): only run when new inputs arrive or scheduled to
-            let row_cnt = self.run_available(true).await.unwrap_or_else(|err| {
+            // flows only run when their tick policy allows
+            let row_cnt = self.run_available(true, false).await.unwrap_or_else(|err| {
                 common_telemetry::error!(err;"Run available errors");
                 0
             });
 
-            if let Err(err) = self.send_writeback_requests().await {
+            if let Err(err) = self.send_writeback_requests(true).await {
                 common_telemetry::error!(err;"Send writeback request errors");
             };
+            if let Err(err) = self.send_dead_letters().await {
+                common_telemetry::error!(err;"Send dead letters errors");
+            };
             self.log_all_errors().await;
+            self.check_state_limits().await;
+
+            if let Err(err) = self.checkpoint_due_flows(&mut last_checkpoints).await {
+                common_telemetry::error!(err;"Checkpoint flows errors");
+            }
 
             // determine if need to shutdown
             match &shutdown.as_mut().map(|s| s.try_recv()) {
                 Some(Ok(())) => {
                     info!("Shutdown flow's main loop");
+                    self.drain().await;
                     break;
                 }
                 Some(Err(TryRecvError::Empty)) => (),
@@ -608,25 +985,78 @@ impl FlowWorkerManager {
         self.frontend_invoker.write().await.take();
     }
 
+    /// Drain the flownode before shutting down, so a restart doesn't lose results of inserts
+    /// already accepted:
+    /// 1. stop accepting new inserts
+    /// 2. run all flows until the inputs buffered are consumed
+    /// 3. write the final results to sink tables
+    /// 4. checkpoint all flows
+    pub async fn drain(&self) {
+        /// a bound of rounds, in case some flow keeps producing output without new inputs
+        const MAX_DRAIN_ROUNDS: usize = 16;
+
+        if self.shutting_down.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        info!("Draining flownode before shutdown");
+        for _ in 0..MAX_DRAIN_ROUNDS {
+            let row_cnt = self.run_available(true, true).await.unwrap_or_else(|err| {
+                common_telemetry::error!(err;"Run available errors while draining");
+                0
+            });
+            let req_cnt = self
+                .send_writeback_requests(false)
+                .await
+                .unwrap_or_else(|err| {
+                    common_telemetry::error!(err;"Send writeback request errors while draining");
+                    0
+                });
+            if row_cnt == 0 && req_cnt == 0 {
+                break;
+            }
+        }
+        if let Err(err) = self.send_dead_letters().await {
+            common_telemetry::error!(err;"Send dead letters errors while draining");
+        }
+        self.log_all_errors().await;
+        if let Err(err) = self.checkpoint_flows().await {
+            common_telemetry::error!(err;"Checkpoint flows errors");
+        }
+        info!("Flownode drained");
+    }
+
     /// Run all available subgraph in the flow node
     /// This will try to run all dataflow in this node
     ///
     /// set `blocking` to true to wait until lock is acquired
     /// and false to return immediately if lock is not acquired
+    ///
+    /// set `force` to run all flows regardless of their tick policy, so inputs already sent to
+    /// them are processed, i.e. on flush
+    ///
     /// return numbers of rows send to worker
-    /// TODO(discord9): add flag for subgraph that have input since last run
-    pub async fn run_available(&self, blocking: bool) -> Result<usize, Error> {
+    pub async fn run_available(&self, blocking: bool, force: bool) -> Result<usize, Error> {
         let mut row_cnt = 0;
 
         let now = self.tick_manager.tick();
-        for worker in self.worker_handles.iter() {
+        if blocking {
+            // workers run on their own threads, so wait for them in parallel
             // TODO(discord9): consider how to handle error in individual worker
-            if blocking {
-                worker.lock().await.run_available(now, blocking).await?;
-            } else if let Ok(worker) = worker.try_lock() {
-                worker.run_available(now, blocking).await?;
-            } else {
-                return Ok(row_cnt);
+            futures::future::try_join_all(self.worker_handles.iter().map(|worker| async move {
+                worker
+                    .lock()
+                    .await
+                    .run_available(now, blocking, force)
+                    .await
+            }))
+            .await?;
+        } else {
+            for worker in self.worker_handles.iter() {
+                if let Ok(worker) = worker.try_lock() {
+                    worker.run_available(now, blocking, force).await?;
+                } else {
+                    return Ok(row_cnt);
+                }
             }
         }
         // check row send and rows remain in send buf
@@ -658,12 +1088,26 @@ impl FlowWorkerManager {
         region_id: RegionId,
         rows: Vec<DiffRow>,
     ) -> Result<(), Error> {
+        ensure!(
+            !self.shutting_down.load(Ordering::Acquire),
+            ShuttingDownSnafu
+        );
         let rows_len = rows.len();
         let table_id = region_id.table_id();
         let _timer = METRIC_FLOW_INSERT_ELAPSED
             .with_label_values(&[table_id.to_string().as_str()])
             .start_timer();
-        self.node_context.read().await.send(table_id, rows).await?;
+        let node_ctx = self.node_context.read().await;
+        // rows of a cascaded source are already fed by the flow writing to it, the ones
+        // mirrored back from the frontend are duplicates
+        if node_ctx.is_cascaded_source(&table_id) {
+            trace!(
+                "Skip write request for cascaded source table_id={}",
+                table_id
+            );
+            return Ok(());
+        }
+        node_ctx.send(table_id, rows).await?;
         trace!(
             "Handling write request for table_id={} with {} rows",
             table_id,
@@ -685,9 +1129,288 @@ impl FlowWorkerManager {
             }
         }
         self.node_context.write().await.remove_flow(flow_id);
+        self.flow_err_collectors.write().await.remove(&flow_id);
+        self.flow_last_errors.write().await.remove(&flow_id);
+        self.flow_errors.write().await.remove(&flow_id);
+        self.failed_flows.write().await.remove(&flow_id);
+        self.paused_flows.write().await.remove(&flow_id);
+        self.flow_infos.write().await.remove(&flow_id);
+        self.flow_last_emits.write().await.remove(&flow_id);
+        remove_flow_metrics(flow_id);
+        // the flow is already removed, a leftover checkpoint is ignored once its plan changed
+        // and overwritten if the flow is created again, so failing to delete it is not an error
+        if let Some(store) = &self.checkpoint_store {
+            if let Err(err) = store.delete(flow_id).await {
+                warn!(err; "Failed to delete checkpoint of flow {}", flow_id);
+            }
+        }
+        // flow ids are never reused, so a leftover mark of paused flow is harmless
+        if let Err(err) = self.paused_flow_store.set_paused(flow_id, false).await {
+            warn!(err; "Failed to delete paused mark of flow {}", flow_id);
+        }
+        Ok(())
+    }
+
+    /// alter options of a flow in place, without rebuilding its dataflow
+    ///
+    /// a changed `expire_after` is applied to the expire state of all arrangements of the flow,
+    /// which expire keys by it from the next compaction on
+    pub async fn alter_flow(
+        &self,
+        flow_id: FlowId,
+        expire_after: Option<i64>,
+        comment: Option<String>,
+    ) -> Result<(), Error> {
+        let Some(mut flow_info) = self.flow_info(flow_id).await else {
+            return FlowNotFoundSnafu { id: flow_id }.fail();
+        };
+        if flow_info.expire_after != expire_after {
+            let mut found = false;
+            for handle in self.worker_handles.iter() {
+                if handle
+                    .lock()
+                    .await
+                    .alter_flow(flow_id, expire_after)
+                    .await?
+                {
+                    found = true;
+                    break;
+                }
+            }
+            ensure!(found, FlowNotFoundSnafu { id: flow_id });
+        }
+
+        flow_info.expire_after = expire_after;
+        flow_info.comment = comment;
+        self.flow_infos.write().await.insert(flow_id, flow_info);
+        info!("Successfully alter flow with id={}", flow_id);
+        Ok(())
+    }
+
+    /// rebuild the dataflow of a flow with a new query or options
+    ///
+    /// the flow's state is kept if the new query is planned the same as the old one, i.e. only
+    /// options are changed, unless `discard_state` is set. The query is planned in the catalog
+    /// and schema the flow is created in, and the old dataflow keeps running until the new one
+    /// is built, so the flow is left as is if it can't be rebuilt and the error is returned.
+    pub async fn rebuild_flow(
+        &self,
+        flow_id: FlowId,
+        expire_after: Option<i64>,
+        comment: Option<String>,
+        sql: String,
+        options: FlowOptions,
+        discard_state: bool,
+    ) -> Result<(), Error> {
+        let Some(old) = self.flow_info(flow_id).await else {
+            return FlowNotFoundSnafu { id: flow_id }.fail();
+        };
+        // so the rebuilt flow isn't restored from the persisted checkpoint either
+        if discard_state {
+            if let Some(store) = &self.checkpoint_store {
+                store.delete(flow_id).await?;
+            }
+        }
+        let replace = if discard_state {
+            ReplaceMode::DiscardState
+        } else {
+            ReplaceMode::KeepState
+        };
+        // the old dataflow keeps running until the new one is built, so the flow is kept as is
+        // if it fails to be rebuilt
+        self.create_or_replace_flow(
+            flow_id,
+            old.sink_table_name.clone(),
+            &old.source_table_ids,
+            false,
+            expire_after,
+            comment,
+            sql,
+            options,
+            Some(old.query_context()),
+            replace,
+        )
+        .await?;
+        self.failed_flows.write().await.remove(&flow_id);
+        info!("Successfully rebuild flow with id={}", flow_id);
         Ok(())
     }
 
+    /// pause a flow, it stops reading from sources and writing to sinks but keeps its state
+    ///
+    /// useful during maintenance of the sink table, inputs of a paused flow are buffered and
+    /// processed once it's resumed, the flow stays paused after the flownode restarts
+    pub async fn pause_flow(&self, flow_id: FlowId) -> Result<(), Error> {
+        self.set_flow_paused(flow_id, true).await?;
+        info!("Paused flow with id={}", flow_id);
+        Ok(())
+    }
+
+    /// resume a paused flow, it continues from the state it's paused with
+    pub async fn resume_flow(&self, flow_id: FlowId) -> Result<(), Error> {
+        self.set_flow_paused(flow_id, false).await?;
+        info!("Resumed flow with id={}", flow_id);
+        Ok(())
+    }
+
+    /// whether the flow is paused
+    pub async fn is_flow_paused(&self, flow_id: FlowId) -> bool {
+        self.paused_flows.read().await.contains(&flow_id)
+    }
+
+    async fn set_flow_paused(&self, flow_id: FlowId, paused: bool) -> Result<(), Error> {
+        let Some(flow_info) = self.flow_info(flow_id).await else {
+            return FlowNotFoundSnafu { id: flow_id }.fail();
+        };
+        // persisted first, so a flow is never paused in memory only
+        self.paused_flow_store.set_paused(flow_id, paused).await?;
+        let mut found = false;
+        for handle in self.worker_handles.iter() {
+            if handle.lock().await.set_flow_paused(flow_id, paused).await? {
+                found = true;
+                break;
+            }
+        }
+        ensure!(found, FlowNotFoundSnafu { id: flow_id });
+
+        let node_ctx = self.node_context.read().await;
+        for table_id in &flow_info.source_table_ids {
+            if let Some(sender) = node_ctx.source_sender.get(table_id) {
+                sender.set_paused(flow_id, paused);
+            }
+        }
+        if paused {
+            self.paused_flows.write().await.insert(flow_id);
+        } else {
+            self.paused_flows.write().await.remove(&flow_id);
+            // resuming a flow gives it a fresh start
+            self.failed_flows.write().await.remove(&flow_id);
+        }
+        Ok(())
+    }
+
+    /// List ids of all flows created in this flownode, in ascending order
+    pub async fn list_flows(&self) -> Vec<FlowId> {
+        self.flow_infos.read().await.keys().cloned().collect()
+    }
+
+    /// Get the definition of the flow, or `None` if no such flow in this flownode
+    pub async fn flow_info(&self, flow_id: FlowId) -> Option<FlowInfo> {
+        self.flow_infos.read().await.get(&flow_id).cloned()
+    }
+
+    /// Get the runtime statistics of all flows in this flownode
+    pub async fn flow_stats(&self) -> Vec<FlowStat> {
+        self.flow_task_status()
+            .await
+            .into_iter()
+            .map(FlowStat::from)
+            .collect()
+    }
+
+    /// Mark the flow as failed with the error, shown in `information_schema.flows`
+    pub(crate) async fn mark_flow_failed(&self, flow_id: FlowId, err: &Error) {
+        self.flow_last_errors
+            .write()
+            .await
+            .insert(flow_id, format!("{:?}", err));
+        self.failed_flows.write().await.insert(flow_id);
+    }
+
+    /// Mark flows whose state has more rows than their `max_state_rows` as failed
+    pub(crate) async fn check_state_limits(&self) {
+        let limits = self
+            .flow_infos
+            .read()
+            .await
+            .iter()
+            .filter_map(|(flow_id, info)| Some((*flow_id, info.options.max_state_rows?)))
+            .collect_vec();
+        for (flow_id, limit) in limits {
+            let Some(rows) = flow_metric_value(&*METRIC_FLOW_STATE_ROWS, flow_id) else {
+                continue;
+            };
+            if rows as u64 > limit && !self.failed_flows.read().await.contains(&flow_id) {
+                let err = StateLimitExceededSnafu {
+                    flow_id,
+                    rows: rows as u64,
+                    limit,
+                }
+                .build();
+                common_telemetry::error!(err; "Flow {} exceeds its state limit", flow_id);
+                self.mark_flow_failed(flow_id, &err).await;
+            }
+        }
+    }
+
+    /// Report the status of all flows in this flownode to metasrv, see [`FlowStatManager`]
+    ///
+    /// Does nothing in standalone mode, where the status is read from the manager directly
+    pub async fn report_flow_stats(&self) -> Result<(), Error> {
+        let Some(node_id) = self.node_id else {
+            return Ok(());
+        };
+        let value = FlowStatValue {
+            flow_stats: self.flow_task_status().await,
+            report_time_ms: common_time::util::current_time_millis(),
+        };
+        self.flow_stat_manager
+            .put(node_id.into(), &value)
+            .await
+            .context(ReportFlowStatSnafu { id: node_id })
+    }
+
+    /// Get the status of all flows in this flownode
+    pub async fn flow_task_status(&self) -> Vec<FlowTaskStatus> {
+        let last_errors = self.flow_last_errors.read().await;
+        let failed_flows = self.failed_flows.read().await;
+        let paused_flows = self.paused_flows.read().await;
+        self.flow_infos
+            .read()
+            .await
+            .keys()
+            .filter_map(|flow_id| {
+                let Ok(id) = u32::try_from(*flow_id) else {
+                    warn!(
+                        "Flow id {} out of range, skip reporting its status",
+                        flow_id
+                    );
+                    return None;
+                };
+                let metric =
+                    |metric: &IntGaugeVec| flow_metric_value(metric, *flow_id).map(|v| v as i64);
+                let state = if paused_flows.contains(flow_id) {
+                    FlowTaskState::Paused
+                } else if failed_flows.contains(flow_id) {
+                    FlowTaskState::Failed
+                } else {
+                    FlowTaskState::Running
+                };
+                Some(FlowTaskStatus {
+                    flow_id: id,
+                    state,
+                    last_error: last_errors.get(flow_id).cloned(),
+                    watermark_ms: metric(&METRIC_FLOW_WATERMARK_MS),
+                    rows_processed: flow_metric_value(&*METRIC_FLOW_INPUT_ROWS, *flow_id)
+                        .map(|v| v as u64)
+                        .unwrap_or_default(),
+                    lag_ms: metric(&METRIC_FLOW_PROCESSING_LAG_MS),
+                    state_rows: metric(&METRIC_FLOW_STATE_ROWS).map(|v| v as u64),
+                    state_bytes: metric(&METRIC_FLOW_STATE_BYTES).map(|v| v as u64),
+                })
+            })
+            .collect()
+    }
+
+    /// Get the fingerprint of the flow's plan, see [`TypedPlan::fingerprint`](crate::plan::TypedPlan::fingerprint)
+    pub async fn flow_fingerprint(&self, flow_id: FlowId) -> Option<u64> {
+        self.flow_infos
+            .read()
+            .await
+            .get(&flow_id)
+            .map(|info| info.fingerprint)
+    }
+
     /// Return task id if a new task is created, otherwise return None
     ///
     /// steps to create task:
@@ -703,9 +1426,45 @@ impl FlowWorkerManager {
         expire_after: Option<i64>,
         comment: Option<String>,
         sql: String,
-        flow_options: HashMap<String, String>,
+        options: FlowOptions,
+        query_ctx: Option<QueryContext>,
+    ) -> Result<Option<FlowId>, Error> {
+        self.create_or_replace_flow(
+            flow_id,
+            sink_table_name,
+            source_table_ids,
+            create_if_not_exists,
+            expire_after,
+            comment,
+            sql,
+            options,
+            query_ctx,
+            ReplaceMode::No,
+        )
+        .await
+    }
+
+    /// Like [`FlowWorkerManager::create_flow`], but replace the running dataflow of the flow as
+    /// `replace` tells once the new one is built, instead of failing
+    ///
+    /// The state of the old dataflow is only kept if the plan is unchanged, the last persisted
+    /// checkpoint isn't restored when replacing
+    #[allow(clippy::too_many_arguments)]
+    async fn create_or_replace_flow(
+        &self,
+        flow_id: FlowId,
+        sink_table_name: TableName,
+        source_table_ids: &[TableId],
+        create_if_not_exists: bool,
+        expire_after: Option<i64>,
+        comment: Option<String>,
+        sql: String,
+        options: FlowOptions,
         query_ctx: Option<QueryContext>,
+        replace: ReplaceMode,
     ) -> Result<Option<FlowId>, Error> {
+        // `EXPIRE AFTER` takes precedence over `allowed_lateness`, both are in seconds
+        let expire_after = expire_after.or(options.allowed_lateness.map(|d| d.as_secs() as i64));
         if create_if_not_exists {
             // check if the task already exists
             for handle in self.worker_handles.iter() {
@@ -725,18 +1484,90 @@ impl FlowWorkerManager {
         node_ctx
             .assign_global_id_to_table(&self.table_info_source, Some(sink_table_name.clone()), None)
             .await?;
+        ensure!(
+            !node_ctx.forms_cycle(flow_id, source_table_ids, &sink_table_name),
+            InvalidQuerySnafu {
+                reason: format!(
+                    "Flow {} writing to table {} forms a cycle with other flows",
+                    flow_id,
+                    sink_table_name.join(".")
+                ),
+            }
+        );
 
         node_ctx.register_task_src_sink(flow_id, source_table_ids, sink_table_name.clone());
 
+        let (catalog, schema) = match &query_ctx {
+            Some(ctx) => (ctx.current_catalog().to_string(), ctx.current_schema()),
+            None => (default_catalog(), default_schema()),
+        };
         node_ctx.query_context = query_ctx.map(Arc::new);
         // construct a active dataflow state with it
         let flow_plan = sql_to_flow_plan(&mut node_ctx, &self.query_engine, &sql).await?;
+        let sink_exists = self
+            .check_sink_table(&sink_table_name, &flow_plan.schema)
+            .await?;
+        if !sink_exists && options.create_sink_table {
+            self.create_sink_table(flow_id, &sink_table_name, &flow_plan.schema)
+                .await?;
+        }
+        let expire_when = match &options.expire_when {
+            Some(expr) => Some(
+                self.plan_expire_when(&mut node_ctx, source_table_ids, expr)
+                    .await?,
+            ),
+            None => None,
+        };
 
-        debug!("Flow {:?}'s Plan is {:?}", flow_id, flow_plan);
+        // a re-submitted flow with the same plan maps to the same dataflow, no need to rebuild it
+        let table_ids: BTreeMap<_, _> = source_table_ids
+            .iter()
+            .filter_map(|id| {
+                node_ctx
+                    .table_repr
+                    .get_by_table_id(id)
+                    .map(|(_, global_id)| (global_id, *id))
+            })
+            .collect();
+        let fingerprint = flow_plan.fingerprint(&table_ids, &sink_table_name);
+        let old_fingerprint = self.flow_infos.read().await.get(&flow_id).map(|info| {
+            let unchanged = info.expire_after == expire_after
+                && info.options.expire_when == options.expire_when;
+            (info.fingerprint, unchanged)
+        });
+        let unchanged = old_fingerprint == Some((fingerprint, true));
+        if unchanged && replace == ReplaceMode::No {
+            for handle in self.worker_handles.iter() {
+                if handle.lock().await.contains_flow(flow_id).await? {
+                    info!("Flow {} is unchanged, skip rebuilding it", flow_id);
+                    return Ok(None);
+                }
+            }
+        }
+
+        debug!(
+            "Flow {:?}'s Plan is\n{}",
+            flow_id,
+            flow_plan.explain(&node_ctx)
+        );
         node_ctx.assign_table_schema(&sink_table_name, flow_plan.schema.clone())?;
 
-        let _ = comment;
-        let _ = flow_options;
+        let flow_info = FlowInfo {
+            flow_id,
+            sink_table_name: sink_table_name.clone(),
+            source_table_ids: source_table_ids.to_vec(),
+            expire_after,
+            comment,
+            sql,
+            options,
+            fingerprint,
+            source_versions: source_table_ids
+                .iter()
+                .filter_map(|id| Some((*id, *node_ctx.table_versions.get(id)?)))
+                .collect(),
+            catalog,
+            schema,
+        };
 
         // TODO(discord9): add more than one handles
         let sink_id = node_ctx.table_repr.get_by_name(&sink_table_name).unwrap().1;
@@ -751,15 +1582,46 @@ impl FlowWorkerManager {
             .map(|id| {
                 node_ctx
                     .get_source_by_global_id(id)
-                    .map(|s| s.get_receiver())
+                    .map(|s| s.get_receiver(flow_id))
             })
             .collect::<Result<Vec<_>, _>>()?;
-        let err_collector = ErrCollector::default();
-        self.flow_err_collectors
-            .write()
-            .await
-            .insert(flow_id, err_collector.clone());
-        let handle = &self.worker_handles[0].lock().await;
+        // a paused flow stays paused when it's created again, i.e. after restart
+        let paused = self.paused_flow_store.is_paused(flow_id).await?;
+        if paused {
+            for id in &source_ids {
+                node_ctx
+                    .get_source_by_global_id(id)?
+                    .set_paused(flow_id, true);
+            }
+        }
+        let err_collector = if flow_info.options.dead_letter_table.is_some() {
+            ErrCollector::with_dead_letters()
+        } else {
+            ErrCollector::default()
+        };
+        let replace = match replace {
+            ReplaceMode::KeepState
+                if old_fingerprint.is_some_and(|(old, _)| old != fingerprint) =>
+            {
+                info!("Flow {}'s plan is changed, discard its state", flow_id);
+                ReplaceMode::DiscardState
+            }
+            replace => replace,
+        };
+        // the state of a replaced dataflow is handed over by the worker instead
+        let checkpoint = match replace {
+            ReplaceMode::No => self.load_checkpoint(flow_id, fingerprint).await,
+            ReplaceMode::KeepState | ReplaceMode::DiscardState => None,
+        };
+        let spill = self.spill.as_ref().map(|(dir, budget)| SpillOptions {
+            dir: dir.join(flow_id.to_string()),
+            memory_budget: flow_info
+                .options
+                .memory_budget
+                .unwrap_or(*budget)
+                .as_bytes() as usize,
+        });
+        let handle = &self.worker_handles[self.worker_of(flow_id)].lock().await;
         let create_request = worker::Request::Create {
             flow_id,
             plan: flow_plan,
@@ -768,13 +1630,198 @@ impl FlowWorkerManager {
             source_ids,
             src_recvs: source_receivers,
             expire_after,
+            expire_when,
             create_if_not_exists,
-            err_collector,
+            replace,
+            err_collector: err_collector.clone(),
+            checkpoint,
+            paused,
+            spill,
+            eviction: flow_info.options.eviction(),
+            tick_policy: flow_info.options.tick_policy(),
         };
         handle.create_flow(create_request).await?;
+        self.flow_err_collectors
+            .write()
+            .await
+            .insert(flow_id, err_collector);
+        self.flow_infos.write().await.insert(flow_id, flow_info);
+        if paused {
+            self.paused_flows.write().await.insert(flow_id);
+            info!("Flow {} is paused before, keep it paused", flow_id);
+        }
         info!("Successfully create flow with id={}", flow_id);
         Ok(Some(flow_id))
     }
+
+    /// Check the output of the flow against the schema of its sink table if the table exists,
+    /// return whether it exists
+    async fn check_sink_table(
+        &self,
+        sink_table_name: &TableName,
+        flow_schema: &RelationDesc,
+    ) -> Result<bool, Error> {
+        let Some(sink_table_id) = self
+            .table_info_source
+            .get_table_id_from_name(sink_table_name)
+            .await?
+        else {
+            return Ok(false);
+        };
+        let sink_table_info = self
+            .table_info_source
+            .get_table_info_value(&sink_table_id)
+            .await?
+            .with_context(|| TableNotFoundSnafu {
+                name: sink_table_name.join("."),
+            })?;
+        check_sink_table_schema(
+            &sink_table_name.join("."),
+            flow_schema,
+            &sink_table_info.table_info.meta.schema.column_schemas,
+        )?;
+        Ok(true)
+    }
+
+    /// Plan the flow's `EXPIRE WHEN` into an expression over the columns of its source table
+    ///
+    /// The expression is the time a key of the flow's state expires at, it's only supported
+    /// for flows with exactly one source table
+    async fn plan_expire_when(
+        &self,
+        node_ctx: &mut FlownodeContext,
+        source_table_ids: &[TableId],
+        expire_when: &str,
+    ) -> Result<ScalarExpr, Error> {
+        let [table_id] = source_table_ids else {
+            return InvalidQuerySnafu {
+                reason: format!(
+                    "EXPIRE WHEN is only supported for flows with one source table, found {}",
+                    source_table_ids.len()
+                ),
+            }
+            .fail();
+        };
+        let [catalog, schema, table] = self.table_info_source.get_table_name(table_id).await?;
+        let quote = |name: &str| format!("\"{}\"", name.replace('"', "\"\""));
+        let sql = format!(
+            "SELECT {} FROM {}.{}.{}",
+            expire_when,
+            quote(&catalog),
+            quote(&schema),
+            quote(&table)
+        );
+        let plan = sql_to_flow_plan(node_ctx, &self.query_engine, &sql).await?;
+        let expr = match plan.plan {
+            Plan::Get { .. } if plan.schema.arity() == 1 => ScalarExpr::Column(0),
+            Plan::Mfp { input, mfp }
+                if matches!(input.plan, Plan::Get { .. }) && mfp.projection.len() == 1 =>
+            {
+                mfp.output_expr(0)?
+            }
+            _ => InvalidQuerySnafu {
+                reason: format!(
+                    "EXPIRE WHEN must be a single expression over columns of the source table, found: {}",
+                    expire_when
+                ),
+            }
+            .fail()?,
+        };
+        Ok(expr)
+    }
+
+    /// Load the last checkpoint of the flow if it's taken from a plan with the same `fingerprint`
+    ///
+    /// a checkpoint is only an optimization, so failing to load it is not an error
+    async fn load_checkpoint(
+        &self,
+        flow_id: FlowId,
+        fingerprint: u64,
+    ) -> Option<DataflowCheckpoint> {
+        let store = self.checkpoint_store.as_ref()?;
+        match store.load(flow_id).await {
+            Ok(Some(checkpoint)) if checkpoint.fingerprint == fingerprint => {
+                info!(
+                    "Resume flow {} from checkpoint at time {}",
+                    flow_id, checkpoint.state.as_of
+                );
+                Some(checkpoint.state)
+            }
+            Ok(Some(_)) => {
+                info!("Flow {}'s plan is changed, ignore its checkpoint", flow_id);
+                None
+            }
+            Ok(None) => None,
+            Err(err) => {
+                warn!(err; "Failed to load checkpoint of flow {}", flow_id);
+                None
+            }
+        }
+    }
+
+    /// Take checkpoints of all flows' state and persist them, return the number of flows checkpointed
+    pub async fn checkpoint_flows(&self) -> Result<usize, Error> {
+        let flows = self
+            .flow_infos
+            .read()
+            .await
+            .iter()
+            .map(|(flow_id, info)| (*flow_id, info.fingerprint))
+            .collect_vec();
+        self.checkpoint_flows_of(flows).await
+    }
+
+    /// Take checkpoints of flows whose checkpoint interval has elapsed since `last_checkpoints`,
+    /// which is updated with the flows checkpointed
+    ///
+    /// the interval is the flow's `checkpoint_interval` if given, otherwise the checkpoint store's
+    async fn checkpoint_due_flows(
+        &self,
+        last_checkpoints: &mut BTreeMap<FlowId, Instant>,
+    ) -> Result<usize, Error> {
+        let Some(store) = &self.checkpoint_store else {
+            return Ok(0);
+        };
+        let now = Instant::now();
+        let flow_infos = self.flow_infos.read().await;
+        last_checkpoints.retain(|flow_id, _| flow_infos.contains_key(flow_id));
+        let flows = flow_infos
+            .iter()
+            .filter(|(flow_id, info)| {
+                let interval = info.options.checkpoint_interval.unwrap_or(store.interval());
+                let last = last_checkpoints.entry(**flow_id).or_insert(now);
+                now.duration_since(*last) >= interval
+            })
+            .map(|(flow_id, info)| (*flow_id, info.fingerprint))
+            .collect_vec();
+        drop(flow_infos);
+        for (flow_id, _) in &flows {
+            last_checkpoints.insert(*flow_id, now);
+        }
+        self.checkpoint_flows_of(flows).await
+    }
+
+    /// Take checkpoints of the given flows with the fingerprints of their plans
+    async fn checkpoint_flows_of(&self, flows: Vec<(FlowId, u64)>) -> Result<usize, Error> {
+        let Some(store) = &self.checkpoint_store else {
+            return Ok(0);
+        };
+        let mut cnt = 0;
+        for (flow_id, fingerprint) in flows {
+            for handle in self.worker_handles.iter() {
+                let Some(state) = handle.lock().await.checkpoint_flow(flow_id).await? else {
+                    continue;
+                };
+                store
+                    .save(flow_id, &FlowCheckpoint { fingerprint, state })
+                    .await?;
+                cnt += 1;
+                break;
+            }
+        }
+        debug!("Checkpointed {} flows", cnt);
+        Ok(cnt)
+    }
 }
 
 /// FlowTickManager is a manager for flow tick, which trakc flow execution progress
@@ -0,0 +1,203 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Backfilling a newly created flow with the existing data of its source tables, so it starts
+//! with results computed from all data instead of only data inserted after its creation, and
+//! resetting flows whose source tables have rows deleted the same way
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use api::v1;
+use api::v1::{RowDeleteRequest, RowDeleteRequests};
+use common_error::ext::BoxedError;
+use common_telemetry::{error, info};
+use futures::StreamExt;
+use session::context::QueryContext;
+use snafu::{OptionExt, ResultExt};
+use table::metadata::TableId;
+
+use crate::adapter::{FlowId, FlowWorkerManager, TableName};
+use crate::error::{Error, EvalSnafu, ExternalSnafu, FlowNotFoundSnafu, UnexpectedSnafu};
+use crate::expr::Batch;
+use crate::metrics::METRIC_FLOW_INPUT_ROWS;
+use crate::repr::Row;
+
+/// How often to check whether the flow has consumed the rows backfilled
+const BACKFILL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+impl FlowWorkerManager {
+    /// Feed all existing rows of the flow's source tables to the flow, return the number of rows
+    ///
+    /// Rows are only sent to this flow and ahead of newly inserted rows, and at most one batch
+    /// is pending at a time, so inserts to the source tables are slowed down while backfilling.
+    /// Rows inserted concurrently with the scan may be seen twice.
+    pub async fn backfill_flow(&self, flow_id: FlowId) -> Result<usize, Error> {
+        let flow_info = self
+            .flow_info(flow_id)
+            .await
+            .context(FlowNotFoundSnafu { id: flow_id })?;
+        let mut row_cnt = 0;
+        for table_id in &flow_info.source_table_ids {
+            let table_name = self.table_info_source.get_table_name(table_id).await?;
+            let mut stream = self
+                .frontend_invoker
+                .read()
+                .await
+                .as_ref()
+                .context(UnexpectedSnafu {
+                    reason: "Expect a frontend invoker for flownode to backfill flows",
+                })?
+                .scan_table(&table_name)
+                .await?;
+            while let Some(batch) = stream.next().await {
+                let batch = batch.map_err(BoxedError::new).context(ExternalSnafu)?;
+                let num_rows = batch.num_rows();
+                let batch =
+                    Batch::try_new(batch.columns().to_vec(), num_rows).context(EvalSnafu)?;
+                if !self.send_backfill_batch(flow_id, *table_id, batch).await? {
+                    info!("Flow {} is removed while backfilling, stop it", flow_id);
+                    return Ok(row_cnt);
+                }
+                METRIC_FLOW_INPUT_ROWS
+                    .with_label_values(&[flow_id.to_string().as_str()])
+                    .inc_by(num_rows as u64);
+                row_cnt += num_rows;
+            }
+        }
+        info!("Backfilled flow {} with {} rows", flow_id, row_cnt);
+        Ok(row_cnt)
+    }
+
+    /// Recompute all flows reading from the table from scratch, return the number of flows reset
+    ///
+    /// Deleting rows from a table or truncating it can't be fed to its flows as retractions,
+    /// since a delete only carries the key of rows, so each flow is rebuilt with an empty state,
+    /// its sink table is cleared, and it's backfilled with the data left in its source tables.
+    /// This is as expensive as creating the flow again, and the flow's results are incomplete
+    /// until it's backfilled.
+    pub async fn reset_flows_of_table(&self, table_id: TableId) -> Result<usize, Error> {
+        // resetting the same flow concurrently could leave it half rebuilt
+        let _guard = self.reset_lock.lock().await;
+        let flow_ids = self
+            .node_context
+            .read()
+            .await
+            .source_to_tasks
+            .get(&table_id)
+            .cloned()
+            .unwrap_or_default();
+        for flow_id in &flow_ids {
+            self.reset_flow(*flow_id).await?;
+        }
+        if !flow_ids.is_empty() {
+            info!(
+                "Reset flows {:?} since rows of their source table {} are deleted",
+                flow_ids, table_id
+            );
+        }
+        Ok(flow_ids.len())
+    }
+
+    async fn reset_flow(&self, flow_id: FlowId) -> Result<(), Error> {
+        let info = self
+            .flow_info(flow_id)
+            .await
+            .context(FlowNotFoundSnafu { id: flow_id })?;
+        let paused = self.is_flow_paused(flow_id).await;
+        self.remove_flow(flow_id).await?;
+        self.clear_sink_table(&info.sink_table_name).await?;
+
+        let query_ctx = info.query_context();
+        self.create_flow(
+            flow_id,
+            info.sink_table_name,
+            &info.source_table_ids,
+            false,
+            info.expire_after,
+            info.comment,
+            info.sql,
+            info.options,
+            Some(query_ctx),
+        )
+        .await?;
+        if paused {
+            self.pause_flow(flow_id).await?;
+        }
+        if let Err(err) = self.backfill_flow(flow_id).await {
+            error!(err; "Failed to backfill flow {} after reset", flow_id);
+            self.mark_flow_failed(flow_id, &err).await;
+        }
+        Ok(())
+    }
+
+    /// Delete all rows of the sink table, which are results computed from the data before reset
+    async fn clear_sink_table(&self, table_name: &TableName) -> Result<(), Error> {
+        let (_, proto_schema) = self.try_fetch_or_create_table(table_name).await?;
+        let ctx = Arc::new(QueryContext::with(&table_name[0], &table_name[1]));
+        let invoker = self.frontend_invoker.read().await;
+        let invoker = invoker.as_ref().context(UnexpectedSnafu {
+            reason: "Expect a frontend invoker for flownode to reset flows",
+        })?;
+        let mut stream = invoker.scan_table(table_name).await?;
+        while let Some(batch) = stream.next().await {
+            let batch = batch.map_err(BoxedError::new).context(ExternalSnafu)?;
+            let rows = batch
+                .rows()
+                .map(|row| Row::new(row).into())
+                .collect::<Vec<_>>();
+            if rows.is_empty() {
+                continue;
+            }
+            let req = RowDeleteRequest {
+                table_name: table_name[2].clone(),
+                rows: Some(v1::Rows {
+                    schema: proto_schema.clone(),
+                    rows,
+                }),
+            };
+            invoker
+                .row_deletes(RowDeleteRequests { deletes: vec![req] }, ctx.clone())
+                .await
+                .map_err(BoxedError::new)
+                .context(ExternalSnafu)?;
+        }
+        Ok(())
+    }
+
+    /// Wait until no batch is pending for the flow, then send the batch to it, a paused flow
+    /// isn't waited for since it doesn't consume any batch until resumed
+    ///
+    /// return false if the flow is removed
+    async fn send_backfill_batch(
+        &self,
+        flow_id: FlowId,
+        table_id: TableId,
+        batch: Batch,
+    ) -> Result<bool, Error> {
+        loop {
+            let paused = self.is_flow_paused(flow_id).await;
+            {
+                let node_ctx = self.node_context.read().await;
+                let Some(sender) = node_ctx.source_sender.get(&table_id) else {
+                    return Ok(false);
+                };
+                if paused || sender.pending_len(flow_id) == 0 {
+                    return Ok(sender.send_to_flow(flow_id, batch));
+                }
+            }
+            tokio::time::sleep(BACKFILL_POLL_INTERVAL).await;
+        }
+    }
+}
@@ -0,0 +1,292 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Persisting checkpoints of flows' state, so a restarted flownode can resume flows from them
+//! instead of starting with empty state
+
+use std::time::Duration;
+
+use common_base::readable_size::ReadableSize;
+use common_meta::kv_backend::KvBackendRef;
+use common_meta::rpc::store::{DeleteRangeRequest, PutRequest};
+use serde::{Deserialize, Serialize};
+use snafu::ResultExt;
+
+use crate::adapter::FlowId;
+use crate::compute::DataflowCheckpoint;
+use crate::error::{
+    CheckpointStoreSnafu, CheckpointTooLargeSnafu, Error, SerdeCheckpointSnafu, UnexpectedSnafu,
+};
+
+/// Prefix of the keys of flows' checkpoints in kv backend, followed by `/{flow_id}`
+///
+/// Kept apart from the `__flow/` prefix of flow metadata, since checkpoints are not metadata
+/// and are written much more often
+pub const FLOW_CHECKPOINT_KEY_PREFIX: &str = "__flow_checkpoint";
+
+/// Options of checkpointing flows' state
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CheckpointOptions {
+    /// Whether to checkpoint flows' state
+    pub enable: bool,
+    /// How often the state of all flows is checkpointed
+    #[serde(with = "humantime_serde")]
+    pub interval: Duration,
+    /// A checkpoint is split into chunks of this size, which should be less than the max value size
+    /// of the kv backend(i.e. 1.5MiB by default for etcd)
+    pub chunk_size: ReadableSize,
+    /// Checkpoints larger than this are not saved, the flow just start with empty state after restart
+    pub max_size: ReadableSize,
+}
+
+impl Default for CheckpointOptions {
+    fn default() -> Self {
+        Self {
+            enable: true,
+            interval: Duration::from_secs(60),
+            chunk_size: ReadableSize::mb(1),
+            max_size: ReadableSize::mb(64),
+        }
+    }
+}
+
+/// A checkpoint of a flow's state
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlowCheckpoint {
+    /// fingerprint of the plan the state is computed by, a checkpoint can only be restored into a
+    /// flow with the same plan, see [`TypedPlan::fingerprint`](crate::plan::TypedPlan::fingerprint)
+    pub fingerprint: u64,
+    pub state: DataflowCheckpoint,
+}
+
+/// Stored under `{FLOW_CHECKPOINT_KEY_PREFIX}/{flow_id}`, tells where the chunks of the last
+/// checkpoint are, which are stored under `{FLOW_CHECKPOINT_KEY_PREFIX}/{flow_id}/{version}/{idx}`
+///
+/// The manifest is written after all chunks, so a partially written checkpoint is never loaded
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct CheckpointManifest {
+    version: u64,
+    chunks: usize,
+}
+
+/// Store of flows' checkpoints in a kv backend, which is the metasrv's in distributed mode so a
+/// flow can be resumed in any flownode
+#[derive(Clone)]
+pub struct CheckpointStore {
+    kv_backend: KvBackendRef,
+    opts: CheckpointOptions,
+}
+
+impl CheckpointStore {
+    pub fn new(kv_backend: KvBackendRef, opts: CheckpointOptions) -> Self {
+        Self { kv_backend, opts }
+    }
+
+    /// How often the state of all flows should be checkpointed
+    pub fn interval(&self) -> Duration {
+        self.opts.interval
+    }
+
+    fn manifest_key(flow_id: FlowId) -> Vec<u8> {
+        format!("{FLOW_CHECKPOINT_KEY_PREFIX}/{flow_id}").into_bytes()
+    }
+
+    fn chunks_prefix(flow_id: FlowId) -> String {
+        format!("{FLOW_CHECKPOINT_KEY_PREFIX}/{flow_id}/")
+    }
+
+    fn version_prefix(flow_id: FlowId, version: u64) -> String {
+        format!("{}{version}/", Self::chunks_prefix(flow_id))
+    }
+
+    fn chunk_key(flow_id: FlowId, version: u64, idx: usize) -> Vec<u8> {
+        format!("{}{idx}", Self::version_prefix(flow_id, version)).into_bytes()
+    }
+
+    async fn load_manifest(&self, flow_id: FlowId) -> Result<Option<CheckpointManifest>, Error> {
+        let Some(kv) = self
+            .kv_backend
+            .get(&Self::manifest_key(flow_id))
+            .await
+            .context(CheckpointStoreSnafu { id: flow_id })?
+        else {
+            return Ok(None);
+        };
+        serde_json::from_slice(&kv.value)
+            .map(Some)
+            .context(SerdeCheckpointSnafu { id: flow_id })
+    }
+
+    /// Save the checkpoint of the flow, overwriting the previous one
+    ///
+    /// Fail if the checkpoint is larger than the configured `max_size`, in which case the previous
+    /// checkpoint is removed, since it's outdated
+    pub async fn save(&self, flow_id: FlowId, checkpoint: &FlowCheckpoint) -> Result<(), Error> {
+        let value = serde_json::to_vec(checkpoint).context(SerdeCheckpointSnafu { id: flow_id })?;
+        let max_size = self.opts.max_size.as_bytes() as usize;
+        if value.len() > max_size {
+            self.delete(flow_id).await?;
+            return CheckpointTooLargeSnafu {
+                id: flow_id,
+                size: value.len(),
+                max_size,
+            }
+            .fail();
+        }
+
+        let prev = self.load_manifest(flow_id).await?;
+        let version = prev.as_ref().map(|m| m.version + 1).unwrap_or_default();
+        let chunk_size = (self.opts.chunk_size.as_bytes() as usize).max(1);
+        let chunks = value.chunks(chunk_size);
+        let manifest = CheckpointManifest {
+            version,
+            chunks: chunks.len(),
+        };
+        // one chunk per request, so a request never exceeds the size limit of the kv backend
+        for (idx, chunk) in chunks.enumerate() {
+            let req = PutRequest::new()
+                .with_key(Self::chunk_key(flow_id, version, idx))
+                .with_value(chunk);
+            self.kv_backend
+                .put(req)
+                .await
+                .context(CheckpointStoreSnafu { id: flow_id })?;
+        }
+        let manifest =
+            serde_json::to_vec(&manifest).context(SerdeCheckpointSnafu { id: flow_id })?;
+        self.kv_backend
+            .put(
+                PutRequest::new()
+                    .with_key(Self::manifest_key(flow_id))
+                    .with_value(manifest),
+            )
+            .await
+            .context(CheckpointStoreSnafu { id: flow_id })?;
+
+        if let Some(prev) = prev {
+            self.kv_backend
+                .delete_range(
+                    DeleteRangeRequest::new()
+                        .with_prefix(Self::version_prefix(flow_id, prev.version)),
+                )
+                .await
+                .context(CheckpointStoreSnafu { id: flow_id })?;
+        }
+        Ok(())
+    }
+
+    /// Load the last checkpoint of the flow, if any
+    pub async fn load(&self, flow_id: FlowId) -> Result<Option<FlowCheckpoint>, Error> {
+        let Some(manifest) = self.load_manifest(flow_id).await? else {
+            return Ok(None);
+        };
+        let mut value = Vec::new();
+        for idx in 0..manifest.chunks {
+            let Some(kv) = self
+                .kv_backend
+                .get(&Self::chunk_key(flow_id, manifest.version, idx))
+                .await
+                .context(CheckpointStoreSnafu { id: flow_id })?
+            else {
+                return UnexpectedSnafu {
+                    reason: format!(
+                        "Chunk {idx} of checkpoint version {} of flow {flow_id} is missing",
+                        manifest.version
+                    ),
+                }
+                .fail();
+            };
+            value.extend(kv.value);
+        }
+        serde_json::from_slice(&value)
+            .map(Some)
+            .context(SerdeCheckpointSnafu { id: flow_id })
+    }
+
+    /// Delete the checkpoint of the flow, if any
+    pub async fn delete(&self, flow_id: FlowId) -> Result<(), Error> {
+        self.kv_backend
+            .delete(&Self::manifest_key(flow_id), false)
+            .await
+            .context(CheckpointStoreSnafu { id: flow_id })?;
+        self.kv_backend
+            .delete_range(DeleteRangeRequest::new().with_prefix(Self::chunks_prefix(flow_id)))
+            .await
+            .context(CheckpointStoreSnafu { id: flow_id })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use common_meta::kv_backend::memory::MemoryKvBackend;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_checkpoint_store() {
+        let opts = CheckpointOptions {
+            chunk_size: ReadableSize(16),
+            ..Default::default()
+        };
+        let kv_backend = Arc::new(MemoryKvBackend::new());
+        let store = CheckpointStore::new(kv_backend.clone(), opts);
+        assert_eq!(store.load(1).await.unwrap(), None);
+
+        let checkpoint = FlowCheckpoint {
+            fingerprint: 42,
+            state: DataflowCheckpoint {
+                as_of: 1000,
+                arrangements: vec![Default::default()],
+            },
+        };
+        store.save(1, &checkpoint).await.unwrap();
+        assert_eq!(store.load(1).await.unwrap(), Some(checkpoint.clone()));
+        assert_eq!(store.load(2).await.unwrap(), None);
+
+        // saving again replaces chunks of the previous checkpoint
+        let chunks = kv_backend.len();
+        assert!(chunks > 2);
+        store.save(1, &checkpoint).await.unwrap();
+        assert_eq!(kv_backend.len(), chunks);
+        assert_eq!(store.load(1).await.unwrap(), Some(checkpoint.clone()));
+
+        store.delete(1).await.unwrap();
+        assert_eq!(store.load(1).await.unwrap(), None);
+        assert!(kv_backend.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_too_large() {
+        let opts = CheckpointOptions {
+            max_size: ReadableSize(16),
+            ..Default::default()
+        };
+        let store = CheckpointStore::new(Arc::new(MemoryKvBackend::new()), opts);
+        let checkpoint = FlowCheckpoint {
+            fingerprint: 42,
+            state: DataflowCheckpoint {
+                as_of: 1000,
+                arrangements: vec![Default::default()],
+            },
+        };
+        let err = store.save(1, &checkpoint).await.unwrap_err();
+        assert!(matches!(err, Error::CheckpointTooLarge { .. }), "{err:?}");
+        assert_eq!(store.load(1).await.unwrap(), None);
+    }
+}
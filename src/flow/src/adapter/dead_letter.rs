@@ -0,0 +1,348 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Writing rows dropped by flows because of evaluation errors to dead-letter tables, as well as
+//! errors of all flows to the error table, so users can audit them
+
+use std::sync::Arc;
+
+use api::v1::{RowInsertRequest, RowInsertRequests, Rows};
+use common_catalog::consts::{DEFAULT_CATALOG_NAME, DEFAULT_PRIVATE_SCHEMA_NAME};
+use common_error::ext::BoxedError;
+use common_telemetry::warn;
+use common_time::util::current_time_millis;
+use datatypes::schema::ColumnSchema;
+use datatypes::value::Value;
+use itertools::Itertools;
+use session::context::QueryContext;
+use snafu::{OptionExt, ResultExt};
+use store_api::mito_engine_options::APPEND_MODE_KEY;
+use store_api::storage::ConcreteDataType;
+
+use crate::adapter::util::column_schemas_to_proto;
+use crate::adapter::{FlowId, FlowWorkerManager, TableName};
+use crate::compute::{DeadLetter, OperatorError};
+use crate::error::{Error, ExternalSnafu, UnexpectedSnafu};
+use crate::repr::Row;
+
+pub const DEAD_LETTER_FLOW_ID_COL: &str = "flow_id";
+pub const DEAD_LETTER_OPERATOR_COL: &str = "operator";
+pub const DEAD_LETTER_ERROR_COL: &str = "error";
+pub const DEAD_LETTER_ROW_COL: &str = "input_row";
+pub const DEAD_LETTER_TS_COL: &str = "ts";
+
+/// Resolve the name of dead-letter table given in flow options against the flow's sink table
+pub(crate) fn dead_letter_table_name(name: &str, sink_table_name: &TableName) -> TableName {
+    let parts = name.split('.').collect_vec();
+    match parts.as_slice() {
+        [catalog, schema, table] => [catalog.to_string(), schema.to_string(), table.to_string()],
+        [schema, table] => [
+            sink_table_name[0].clone(),
+            schema.to_string(),
+            table.to_string(),
+        ],
+        _ => [
+            sink_table_name[0].clone(),
+            sink_table_name[1].clone(),
+            name.to_string(),
+        ],
+    }
+}
+
+/// Resolve the name of the error table given in flownode options, the table is placed in
+/// the private schema of the default catalog if not specified
+pub(crate) fn error_table_name(name: &str) -> TableName {
+    dead_letter_table_name(
+        name,
+        &[
+            DEFAULT_CATALOG_NAME.to_string(),
+            DEFAULT_PRIVATE_SCHEMA_NAME.to_string(),
+            String::new(),
+        ],
+    )
+}
+
+/// A row of a dead-letter table or the error table
+struct ErrorRow {
+    flow_id: FlowId,
+    operator: &'static str,
+    error: String,
+    input_row: Option<Row>,
+    ts: i64,
+}
+
+/// Build the insert request of dead letters of the flow, reported at `now`
+fn dead_letters_to_request(
+    table: &str,
+    flow_id: FlowId,
+    dead_letters: Vec<DeadLetter>,
+    now: i64,
+) -> Result<RowInsertRequest, Error> {
+    let rows = dead_letters
+        .into_iter()
+        .map(|dead_letter| ErrorRow {
+            flow_id,
+            operator: dead_letter.operator,
+            error: dead_letter.error,
+            input_row: Some(dead_letter.row),
+            ts: now,
+        })
+        .collect();
+    error_rows_to_request(table, rows)
+}
+
+/// Build the insert request of errors of flows, each at the time it's raised
+fn flow_errors_to_request(
+    table: &str,
+    errors: Vec<(FlowId, Vec<OperatorError>)>,
+) -> Result<RowInsertRequest, Error> {
+    let rows = errors
+        .into_iter()
+        .flat_map(|(flow_id, errors)| {
+            errors.into_iter().map(move |err| ErrorRow {
+                flow_id,
+                operator: err.operator,
+                error: format!("{:?}", err.error),
+                input_row: err.row,
+                ts: err.ts,
+            })
+        })
+        .collect();
+    error_rows_to_request(table, rows)
+}
+
+fn error_rows_to_request(table: &str, rows: Vec<ErrorRow>) -> Result<RowInsertRequest, Error> {
+    let schema = vec![
+        ColumnSchema::new(
+            DEAD_LETTER_FLOW_ID_COL,
+            ConcreteDataType::uint64_datatype(),
+            false,
+        ),
+        ColumnSchema::new(
+            DEAD_LETTER_OPERATOR_COL,
+            ConcreteDataType::string_datatype(),
+            false,
+        ),
+        ColumnSchema::new(
+            DEAD_LETTER_ERROR_COL,
+            ConcreteDataType::string_datatype(),
+            true,
+        ),
+        ColumnSchema::new(
+            DEAD_LETTER_ROW_COL,
+            ConcreteDataType::string_datatype(),
+            true,
+        ),
+        ColumnSchema::new(
+            DEAD_LETTER_TS_COL,
+            ConcreteDataType::timestamp_millisecond_datatype(),
+            false,
+        )
+        .with_time_index(true),
+    ];
+    let primary_keys = vec![
+        DEAD_LETTER_FLOW_ID_COL.to_string(),
+        DEAD_LETTER_OPERATOR_COL.to_string(),
+    ];
+    let schema = column_schemas_to_proto(schema, &primary_keys)?;
+    let rows = rows
+        .into_iter()
+        .map(|row| {
+            let input_row = row
+                .input_row
+                .map(|input_row| Value::from(format!("({})", input_row.iter().join(", "))))
+                .unwrap_or(Value::Null);
+            Row::new(vec![
+                Value::from(row.flow_id),
+                Value::from(row.operator),
+                Value::from(row.error),
+                input_row,
+                Value::from(common_time::Timestamp::new_millisecond(row.ts)),
+            ])
+            .into()
+        })
+        .collect();
+    Ok(RowInsertRequest {
+        table_name: table.to_string(),
+        rows: Some(Rows { schema, rows }),
+    })
+}
+
+impl FlowWorkerManager {
+    /// Write rows failed to be evaluated to the dead-letter table of each flow, return the
+    /// number of rows written
+    ///
+    /// Dead-letter tables are created on demand in append mode. Dead letters failed to be
+    /// written are dropped, as they're only kept for auditing.
+    pub async fn send_dead_letters(&self) -> Result<usize, Error> {
+        let mut all_dead_letters = Vec::new();
+        for (flow_id, err_collector) in self.flow_err_collectors.read().await.iter() {
+            if !err_collector.collects_dead_letters() {
+                continue;
+            }
+            let dead_letters = err_collector.take_dead_letters().await;
+            if dead_letters.is_empty() {
+                continue;
+            }
+            let Some(table_name) = self.flow_info(*flow_id).await.and_then(|info| {
+                info.options
+                    .dead_letter_table
+                    .as_ref()
+                    .map(|name| dead_letter_table_name(name, &info.sink_table_name))
+            }) else {
+                continue;
+            };
+            all_dead_letters.push((*flow_id, table_name, dead_letters));
+        }
+
+        let now = current_time_millis();
+        let mut row_cnt = 0;
+        for (flow_id, table_name, dead_letters) in all_dead_letters {
+            let num_rows = dead_letters.len();
+            let req = dead_letters_to_request(&table_name[2], flow_id, dead_letters, now)?;
+            match self.write_error_rows(&table_name, req).await {
+                Ok(_) => row_cnt += num_rows,
+                Err(err) => warn!(
+                    err; "Failed to write {} dead letters of flow {} to table {}",
+                    num_rows, flow_id, table_name.join(".")
+                ),
+            }
+        }
+        Ok(row_cnt)
+    }
+
+    /// Write errors of flows to the error table if it's set, errors failed to be written are
+    /// dropped, as they're still logged and kept in memory
+    pub(crate) async fn write_flow_errors(
+        &self,
+        errors: Vec<(FlowId, Vec<OperatorError>)>,
+    ) -> Result<(), Error> {
+        let Some(table_name) = &self.error_table else {
+            return Ok(());
+        };
+        if errors.is_empty() {
+            return Ok(());
+        }
+        let req = flow_errors_to_request(&table_name[2], errors)?;
+        self.write_error_rows(table_name, req).await
+    }
+
+    /// Write rows to a dead-letter table or the error table, which is created on demand in
+    /// append mode
+    async fn write_error_rows(
+        &self,
+        table_name: &TableName,
+        req: RowInsertRequest,
+    ) -> Result<(), Error> {
+        let mut ctx = QueryContext::with(&table_name[0], &table_name[1]);
+        ctx.set_extension(APPEND_MODE_KEY, "true");
+        let invoker = self.frontend_invoker.read().await;
+        let invoker = invoker.as_ref().with_context(|| UnexpectedSnafu {
+            reason: "Expect a frontend invoker for flownode to write errors",
+        })?;
+        invoker
+            .row_inserts(RowInsertRequests { inserts: vec![req] }, Arc::new(ctx))
+            .await
+            .map_err(BoxedError::new)
+            .context(ExternalSnafu)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use api::v1::SemanticType;
+
+    use super::*;
+    use crate::expr::EvalError;
+
+    #[test]
+    fn test_dead_letter_table_name() {
+        let sink = [
+            "greptime".to_string(),
+            "public".to_string(),
+            "out".to_string(),
+        ];
+        assert_eq!(
+            ["greptime", "public", "dlq"].map(String::from),
+            dead_letter_table_name("dlq", &sink)
+        );
+        assert_eq!(
+            ["greptime", "audit", "dlq"].map(String::from),
+            dead_letter_table_name("audit.dlq", &sink)
+        );
+        assert_eq!(
+            ["other", "audit", "dlq"].map(String::from),
+            dead_letter_table_name("other.audit.dlq", &sink)
+        );
+    }
+
+    #[test]
+    fn test_dead_letters_to_request() {
+        let err = EvalError::DivisionByZero {
+            location: snafu::location!(),
+        };
+        let dead_letter = DeadLetter {
+            row: Row::new(vec![Value::from(1i64), Value::Null]),
+            operator: "mfp",
+            error: format!("{:?}", err),
+        };
+        let req = dead_letters_to_request("dlq", 42, vec![dead_letter], 1000).unwrap();
+        assert_eq!("dlq", req.table_name);
+        let rows = req.rows.unwrap();
+        let semantic_types = rows.schema.iter().map(|c| c.semantic_type).collect_vec();
+        assert_eq!(
+            vec![
+                SemanticType::Tag as i32,
+                SemanticType::Tag as i32,
+                SemanticType::Field as i32,
+                SemanticType::Field as i32,
+                SemanticType::Timestamp as i32,
+            ],
+            semantic_types
+        );
+        assert_eq!(1, rows.rows.len());
+        assert_eq!(5, rows.rows[0].values.len());
+    }
+
+    #[test]
+    fn test_flow_errors_to_request() {
+        assert_eq!(
+            ["greptime", "greptime_private", "flow_errors"].map(String::from),
+            error_table_name("flow_errors")
+        );
+
+        let err = |row: Option<Row>, ts| OperatorError {
+            operator: "mfp",
+            row,
+            error: Arc::new(EvalError::DivisionByZero {
+                location: snafu::location!(),
+            }),
+            ts,
+        };
+        let errors = vec![
+            (1, vec![err(Some(Row::new(vec![Value::from(1i64)])), 1000)]),
+            (2, vec![err(None, 2000)]),
+        ];
+        let req = flow_errors_to_request("flow_errors", errors).unwrap();
+        let rows = req.rows.unwrap();
+        assert_eq!(2, rows.rows.len());
+        // a row is only taken if the flow collects dead letters
+        assert_eq!(
+            None, rows.rows[1].values[3].value_data,
+            "{:?}",
+            rows.rows[1]
+        );
+    }
+}
@@ -0,0 +1,127 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Explaining a flow without creating it, so unsupported queries or a mismatched sink table are
+//! found before the flow is created
+
+use std::fmt::Write;
+use std::sync::Arc;
+
+use itertools::Itertools;
+use session::context::QueryContext;
+use table::metadata::TableId;
+
+use crate::adapter::node_context::FlownodeContext;
+use crate::adapter::{FlowOptions, FlowWorkerManager, TableName};
+use crate::df_optimizer::sql_to_flow_plan;
+use crate::error::Error;
+use crate::repr::RelationDesc;
+
+impl FlowWorkerManager {
+    /// Plan the flow like [`FlowWorkerManager::create_flow`] but without creating it, describe
+    /// its output columns, its plan and the operators keeping state
+    pub async fn explain_flow(
+        &self,
+        source_table_ids: &[TableId],
+        sink_table_name: &TableName,
+        sql: &str,
+        options: &FlowOptions,
+        query_ctx: Option<QueryContext>,
+    ) -> Result<String, Error> {
+        // planned in a context of its own, so nothing is registered to the running flows
+        let mut node_ctx = FlownodeContext::default();
+        for source in source_table_ids {
+            node_ctx
+                .assign_global_id_to_table(&self.table_info_source, None, Some(*source))
+                .await?;
+        }
+        node_ctx.query_context = query_ctx.map(Arc::new);
+        let plan = sql_to_flow_plan(&mut node_ctx, &self.query_engine, sql).await?;
+        let sink_exists = self.check_sink_table(sink_table_name, &plan.schema).await?;
+        let expire_when = match &options.expire_when {
+            Some(expr) => Some(
+                self.plan_expire_when(&mut node_ctx, source_table_ids, expr)
+                    .await?,
+            ),
+            None => None,
+        };
+
+        let mut output = String::new();
+        let _ = writeln!(
+            output,
+            "Sink: {}{}",
+            sink_table_name.join("."),
+            if sink_exists { "" } else { " (not exists)" }
+        );
+        let _ = writeln!(output, "Output: {}", fmt_columns(&plan.schema));
+        if let Some(expr) = expire_when {
+            let _ = writeln!(output, "Expire when: {expr}");
+        }
+        let _ = writeln!(output, "Plan:");
+        for line in plan.explain(&node_ctx).lines() {
+            let _ = writeln!(output, "  {line}");
+        }
+        let state = plan.explain_state();
+        if state.is_empty() {
+            let _ = writeln!(output, "State: none");
+        } else {
+            let _ = writeln!(output, "State:");
+            for line in state.lines() {
+                let _ = writeln!(output, "  {line}");
+            }
+        }
+        Ok(output)
+    }
+}
+
+/// Format columns like `number: UInt32, ts: TimestampMillisecond NULL`
+fn fmt_columns(schema: &RelationDesc) -> String {
+    schema
+        .typ()
+        .column_types
+        .iter()
+        .enumerate()
+        .map(|(i, typ)| {
+            let name = schema
+                .names
+                .get(i)
+                .cloned()
+                .flatten()
+                .unwrap_or_else(|| format!("#{i}"));
+            let null = if typ.nullable { " NULL" } else { "" };
+            format!("{name}: {}{null}", typ.scalar_type)
+        })
+        .join(", ")
+}
+
+#[cfg(test)]
+mod test {
+    use datatypes::data_type::ConcreteDataType as CDT;
+
+    use super::*;
+    use crate::repr::{ColumnType, RelationType};
+
+    #[test]
+    fn test_fmt_columns() {
+        let schema = RelationType::new(vec![
+            ColumnType::new(CDT::uint32_datatype(), false),
+            ColumnType::new(CDT::timestamp_millisecond_datatype(), true),
+        ])
+        .into_named(vec![Some("number".to_string()), None]);
+        assert_eq!(
+            fmt_columns(&schema),
+            "number: UInt32, #1: TimestampMillisecond NULL"
+        );
+    }
+}
@@ -0,0 +1,331 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Options of a flow given in `WITH` of `CREATE FLOW`, parsed and validated before the flow
+//! is created
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use common_base::readable_size::ReadableSize;
+use humantime_serde::re::humantime;
+use serde::{Deserialize, Serialize};
+use snafu::{ensure, OptionExt};
+
+use crate::compute::{EvictionOptions, TickPolicy};
+use crate::error::{Error, InvalidFlowOptionSnafu};
+use crate::utils::EvictionPolicy;
+
+/// Whether to backfill a newly created flow, see [`FlowWorkerManager::backfill_flow`](crate::adapter::FlowWorkerManager::backfill_flow)
+pub const FLOW_OPT_KEY_BACKFILL: &str = "backfill";
+/// Whether to create the sink table from the output of the flow when creating the flow if it
+/// doesn't exist, with the time window as its time index and group keys as its primary keys,
+/// instead of on the first write back
+pub const FLOW_OPT_KEY_CREATE_SINK_TABLE: &str = "create_sink_table";
+/// The table rows failed to be evaluated by a flow are written to, as `table`, `schema.table`
+/// or `catalog.schema.table`, defaults to the catalog and schema of the flow's sink table
+pub const FLOW_OPT_KEY_DEAD_LETTER_TABLE: &str = "dead_letter_table";
+/// The time each key of a flow's state expires at, as an expression over the columns of the
+/// flow's source table, i.e. `EXPIRE WHEN ts + INTERVAL '1 hour'`
+///
+/// It's given by `EXPIRE WHEN` of `CREATE FLOW`, which is carried in flow options since the
+/// create flow request has no field for it
+pub const FLOW_OPT_KEY_EXPIRE_WHEN: &str = "expire_when";
+/// The minimal interval between writing the flow's results to its sink table
+pub const FLOW_OPT_KEY_EMIT_INTERVAL: &str = "emit_interval";
+/// How late a row can arrive and still update the flow's results, used as `EXPIRE AFTER` if
+/// it's not given
+pub const FLOW_OPT_KEY_ALLOWED_LATENESS: &str = "allowed_lateness";
+/// The max number of rows in the flow's state, the flow fails once it's exceeded
+pub const FLOW_OPT_KEY_MAX_STATE_ROWS: &str = "max_state_rows";
+/// The interval of checkpointing the flow's state, overriding the flownode's
+pub const FLOW_OPT_KEY_CHECKPOINT_INTERVAL: &str = "checkpoint_interval";
+/// The memory budget of the flow's state, cold keys are spilled to disk once it's exceeded,
+/// overriding the flownode's
+pub const FLOW_OPT_KEY_MEMORY_BUDGET: &str = "memory_budget";
+/// The flow only ticks(advances its time and processes inputs) at most once per interval
+pub const FLOW_OPT_KEY_TICK_INTERVAL: &str = "tick_interval";
+/// The flow only ticks once inputs arrive, and waits at most this long for more inputs to be
+/// processed in the same tick, conflicts with `tick_interval`
+pub const FLOW_OPT_KEY_MAX_BATCH_DELAY: &str = "max_batch_delay";
+
+/// The max estimated size of the flow's state, keys are evicted once it's exceeded, so results
+/// of evicted keys are wrong but the flow can't take up all memory of the flownode
+pub const FLOW_OPT_KEY_MAX_STATE_SIZE: &str = "max_state_size";
+/// Which keys are evicted first once `max_state_size` is exceeded, `lru` for the least recently
+/// updated ones(the default), `oldest_timestamp` for the ones with the oldest event timestamp
+pub const FLOW_OPT_KEY_STATE_EVICTION: &str = "state_eviction";
+
+/// Typed options of a flow
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlowOptions {
+    pub backfill: bool,
+    pub create_sink_table: bool,
+    pub dead_letter_table: Option<String>,
+    pub expire_when: Option<String>,
+    pub emit_interval: Option<Duration>,
+    pub allowed_lateness: Option<Duration>,
+    pub max_state_rows: Option<u64>,
+    pub checkpoint_interval: Option<Duration>,
+    pub memory_budget: Option<ReadableSize>,
+    pub tick_interval: Option<Duration>,
+    pub max_batch_delay: Option<Duration>,
+    pub max_state_size: Option<ReadableSize>,
+    pub state_eviction: Option<EvictionPolicy>,
+}
+
+impl FlowOptions {
+    /// Parse options given in `WITH` of `CREATE FLOW`, fails on unknown options and invalid values
+    pub fn parse(options: &HashMap<String, String>) -> Result<Self, Error> {
+        let mut opts = FlowOptions::default();
+        for (key, value) in options {
+            match key.as_str() {
+                FLOW_OPT_KEY_BACKFILL => opts.backfill = parse_bool(key, value)?,
+                FLOW_OPT_KEY_CREATE_SINK_TABLE => opts.create_sink_table = parse_bool(key, value)?,
+                FLOW_OPT_KEY_DEAD_LETTER_TABLE => {
+                    opts.dead_letter_table = Some(parse_non_empty(key, value)?)
+                }
+                FLOW_OPT_KEY_EXPIRE_WHEN => opts.expire_when = Some(parse_non_empty(key, value)?),
+                FLOW_OPT_KEY_EMIT_INTERVAL => {
+                    opts.emit_interval = Some(parse_duration(key, value)?)
+                }
+                FLOW_OPT_KEY_ALLOWED_LATENESS => {
+                    opts.allowed_lateness = Some(parse_duration(key, value)?)
+                }
+                FLOW_OPT_KEY_MAX_STATE_ROWS => {
+                    let rows = value.parse::<u64>().ok().filter(|rows| *rows > 0);
+                    ensure!(
+                        rows.is_some(),
+                        InvalidFlowOptionSnafu {
+                            key,
+                            reason: format!("expect a positive integer, found '{value}'"),
+                        }
+                    );
+                    opts.max_state_rows = rows;
+                }
+                FLOW_OPT_KEY_CHECKPOINT_INTERVAL => {
+                    opts.checkpoint_interval = Some(parse_duration(key, value)?)
+                }
+                FLOW_OPT_KEY_MEMORY_BUDGET => opts.memory_budget = Some(parse_size(key, value)?),
+                FLOW_OPT_KEY_TICK_INTERVAL => {
+                    opts.tick_interval = Some(parse_duration(key, value)?)
+                }
+                FLOW_OPT_KEY_MAX_BATCH_DELAY => {
+                    opts.max_batch_delay = Some(parse_duration(key, value)?)
+                }
+                FLOW_OPT_KEY_MAX_STATE_SIZE => opts.max_state_size = Some(parse_size(key, value)?),
+                FLOW_OPT_KEY_STATE_EVICTION => {
+                    let policy = match value.to_ascii_lowercase().as_str() {
+                        "lru" => EvictionPolicy::Lru,
+                        "oldest_timestamp" => EvictionPolicy::OldestTimestamp,
+                        _ => InvalidFlowOptionSnafu {
+                            key,
+                            reason: format!("expect 'lru' or 'oldest_timestamp', found '{value}'"),
+                        }
+                        .fail()?,
+                    };
+                    opts.state_eviction = Some(policy);
+                }
+                _ => InvalidFlowOptionSnafu {
+                    key,
+                    reason: "unknown flow option",
+                }
+                .fail()?,
+            }
+        }
+        ensure!(
+            opts.tick_interval.is_none() || opts.max_batch_delay.is_none(),
+            InvalidFlowOptionSnafu {
+                key: FLOW_OPT_KEY_MAX_BATCH_DELAY,
+                reason: format!("conflicts with '{FLOW_OPT_KEY_TICK_INTERVAL}'"),
+            }
+        );
+        ensure!(
+            opts.state_eviction.is_none() || opts.max_state_size.is_some(),
+            InvalidFlowOptionSnafu {
+                key: FLOW_OPT_KEY_STATE_EVICTION,
+                reason: format!("requires '{FLOW_OPT_KEY_MAX_STATE_SIZE}'"),
+            }
+        );
+        Ok(opts)
+    }
+
+    /// When the flow ticks, every time the flownode runs flows by default
+    pub fn tick_policy(&self) -> TickPolicy {
+        match (self.tick_interval, self.max_batch_delay) {
+            (Some(interval), _) => TickPolicy::Interval(interval.as_millis() as i64),
+            (None, Some(max_delay)) => TickPolicy::OnData {
+                max_delay: max_delay.as_millis() as i64,
+            },
+            (None, None) => TickPolicy::EveryRun,
+        }
+    }
+
+    /// When and how to evict keys of the flow's state, never by default
+    pub fn eviction(&self) -> Option<EvictionOptions> {
+        self.max_state_size.map(|size| EvictionOptions {
+            max_state_size: size.as_bytes() as usize,
+            policy: self.state_eviction.unwrap_or_default(),
+        })
+    }
+}
+
+fn parse_bool(key: &str, value: &str) -> Result<bool, Error> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => InvalidFlowOptionSnafu {
+            key,
+            reason: format!("expect 'true' or 'false', found '{value}'"),
+        }
+        .fail(),
+    }
+}
+
+fn parse_non_empty(key: &str, value: &str) -> Result<String, Error> {
+    ensure!(
+        !value.trim().is_empty(),
+        InvalidFlowOptionSnafu {
+            key,
+            reason: "expect a non-empty value",
+        }
+    );
+    Ok(value.to_string())
+}
+
+fn parse_size(key: &str, value: &str) -> Result<ReadableSize, Error> {
+    let size = value.parse::<ReadableSize>().ok().filter(|s| s.0 > 0);
+    size.with_context(|| InvalidFlowOptionSnafu {
+        key,
+        reason: format!("expect a non-zero size like '64MiB', found '{value}'"),
+    })
+}
+
+fn parse_duration(key: &str, value: &str) -> Result<Duration, Error> {
+    let duration = humantime::parse_duration(value).map_err(|e| {
+        InvalidFlowOptionSnafu {
+            key,
+            reason: format!("expect a duration like '10s', found '{value}': {e}"),
+        }
+        .build()
+    })?;
+    ensure!(
+        !duration.is_zero(),
+        InvalidFlowOptionSnafu {
+            key,
+            reason: "expect a non-zero duration",
+        }
+    );
+    Ok(duration)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn options(kvs: &[(&str, &str)]) -> HashMap<String, String> {
+        kvs.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_flow_options() {
+        assert_eq!(
+            FlowOptions::default(),
+            FlowOptions::parse(&HashMap::new()).unwrap()
+        );
+
+        let opts = FlowOptions::parse(&options(&[
+            ("backfill", "TRUE"),
+            ("create_sink_table", "true"),
+            ("dead_letter_table", "dlq"),
+            ("emit_interval", "10s"),
+            ("allowed_lateness", "1h"),
+            ("max_state_rows", "1000"),
+            ("checkpoint_interval", "5m"),
+            ("memory_budget", "64MiB"),
+            ("max_batch_delay", "500ms"),
+            ("max_state_size", "1GiB"),
+            ("state_eviction", "oldest_timestamp"),
+        ]))
+        .unwrap();
+        assert_eq!(
+            FlowOptions {
+                backfill: true,
+                create_sink_table: true,
+                dead_letter_table: Some("dlq".to_string()),
+                expire_when: None,
+                emit_interval: Some(Duration::from_secs(10)),
+                allowed_lateness: Some(Duration::from_secs(3600)),
+                max_state_rows: Some(1000),
+                checkpoint_interval: Some(Duration::from_secs(300)),
+                memory_budget: Some(ReadableSize::mb(64)),
+                tick_interval: None,
+                max_batch_delay: Some(Duration::from_millis(500)),
+                max_state_size: Some(ReadableSize::gb(1)),
+                state_eviction: Some(EvictionPolicy::OldestTimestamp),
+            },
+            opts
+        );
+        assert_eq!(TickPolicy::OnData { max_delay: 500 }, opts.tick_policy());
+        assert_eq!(TickPolicy::EveryRun, FlowOptions::default().tick_policy());
+        assert_eq!(
+            Some(EvictionOptions {
+                max_state_size: 1 << 30,
+                policy: EvictionPolicy::OldestTimestamp,
+            }),
+            opts.eviction()
+        );
+        assert_eq!(None, FlowOptions::default().eviction());
+    }
+
+    #[test]
+    fn test_parse_invalid_flow_options() {
+        let cases = [
+            (("foo", "bar"), "unknown flow option"),
+            (("backfill", "yes"), "expect 'true' or 'false'"),
+            (("dead_letter_table", " "), "expect a non-empty value"),
+            (("emit_interval", "10"), "expect a duration"),
+            (("checkpoint_interval", "0s"), "expect a non-zero duration"),
+            (("max_state_rows", "0"), "expect a positive integer"),
+            (("memory_budget", "a lot"), "expect a non-zero size"),
+            (("tick_interval", "0s"), "expect a non-zero duration"),
+            (("max_state_size", "0"), "expect a non-zero size"),
+            (
+                ("state_eviction", "fifo"),
+                "expect 'lru' or 'oldest_timestamp'",
+            ),
+        ];
+        for ((key, value), expected) in cases {
+            let err = FlowOptions::parse(&options(&[(key, value)])).unwrap_err();
+            let msg = err.to_string();
+            assert!(msg.contains(key), "{msg}");
+            assert!(msg.contains(expected), "{msg}");
+        }
+
+        let err = FlowOptions::parse(&options(&[
+            ("tick_interval", "1s"),
+            ("max_batch_delay", "1s"),
+        ]))
+        .unwrap_err();
+        assert!(err.to_string().contains("conflicts with"), "{err}");
+
+        let err = FlowOptions::parse(&options(&[("state_eviction", "lru")])).unwrap_err();
+        assert!(
+            err.to_string().contains("requires 'max_state_size'"),
+            "{err}"
+        );
+    }
+}
@@ -14,24 +14,22 @@
 
 //! impl `FlowNode` trait for FlowNodeManager so standalone can call them
 
-use std::collections::HashMap;
-
 use api::v1::flow::{
     flow_request, CreateRequest, DropRequest, FlowRequest, FlowResponse, FlushFlow,
 };
 use api::v1::region::InsertRequests;
 use common_error::ext::BoxedError;
 use common_meta::error::{ExternalSnafu, Result, UnexpectedSnafu};
-use common_meta::node_manager::Flownode;
-use common_telemetry::{debug, trace};
+use common_meta::node_manager::{AlterFlowRequest, ExplainFlowRequest, Flownode};
+use common_telemetry::{debug, error};
 use itertools::Itertools;
-use snafu::{OptionExt, ResultExt};
+use snafu::ResultExt;
 use store_api::storage::RegionId;
+use table::metadata::TableId;
 
-use crate::adapter::FlowWorkerManager;
-use crate::error::InternalSnafu;
+use crate::adapter::{FlowOptions, FlowWorkerManager};
+use crate::error::{Error, FlowNotFoundSnafu};
 use crate::metrics::METRIC_FLOW_TASK_COUNT;
-use crate::repr::{self, DiffRow};
 
 fn to_meta_err(err: crate::error::Error) -> common_meta::error::Error {
     // TODO(discord9): refactor this
@@ -65,6 +63,21 @@ impl Flownode for FlowWorkerManager {
                     sink_table_name.table_name,
                 ];
                 let expire_after = expire_after.map(|e| e.value);
+                let options = FlowOptions::parse(&flow_options).map_err(to_meta_err)?;
+                let backfill = options.backfill;
+                // a flow moved from a failed flownode is backfilled from scratch instead of resumed
+                // from the checkpoint taken there, so no input is counted twice
+                if backfill && self.flow_info(task_id.id as u64).await.is_none() {
+                    if let Some(store) = &self.checkpoint_store {
+                        store.delete(task_id.id as u64).await.map_err(to_meta_err)?;
+                    }
+                }
+                // so the flow is planned against the latest schema of its source tables
+                for table_id in &source_table_ids {
+                    self.handle_source_table_altered(*table_id)
+                        .await
+                        .map_err(to_meta_err)?;
+                }
                 let ret = self
                     .create_flow(
                         task_id.id as u64,
@@ -74,11 +87,19 @@ impl Flownode for FlowWorkerManager {
                         expire_after,
                         Some(comment),
                         sql,
-                        flow_options,
+                        options,
                         query_ctx,
                     )
                     .await
                     .map_err(to_meta_err)?;
+                // only flows newly created by DDL are backfilled, not those recovered after restart
+                if let Some(flow_id) = ret.filter(|_| backfill) {
+                    // the flow is created anyway, a failed backfill marks it as failed
+                    if let Err(err) = self.backfill_flow(flow_id).await {
+                        error!(err; "Failed to backfill flow {}", flow_id);
+                        self.mark_flow_failed(flow_id, &err).await;
+                    }
+                }
                 METRIC_FLOW_TASK_COUNT.inc();
                 Ok(FlowResponse {
                     affected_flows: ret
@@ -112,8 +133,11 @@ impl Flownode for FlowWorkerManager {
                     .flush_all_sender()
                     .await
                     .map_err(to_meta_err)?;
-                let rows_send = self.run_available(true).await.map_err(to_meta_err)?;
-                let row = self.send_writeback_requests().await.map_err(to_meta_err)?;
+                let rows_send = self.run_available(true, true).await.map_err(to_meta_err)?;
+                let row = self
+                    .send_writeback_requests(false)
+                    .await
+                    .map_err(to_meta_err)?;
 
                 debug!(
                     "Done to flush flow_id={:?} with {} input rows flushed, {} rows sended and {} output rows flushed",
@@ -153,63 +177,109 @@ impl Flownode for FlowWorkerManager {
             // TODO(discord9): reconsider time assignment mechanism
             let now = self.tick_manager.tick();
 
-            let fetch_order = {
-                let ctx = self.node_context.read().await;
-                let table_col_names = ctx
-                    .table_repr
-                    .get_by_table_id(&table_id)
-                    .map(|r| r.1)
-                    .and_then(|id| ctx.schema.get(&id))
-                    .map(|desc| &desc.names)
-                    .context(UnexpectedSnafu {
-                        err_msg: format!("Table not found: {}", table_id),
-                    })?;
-                let table_col_names = table_col_names
-                    .iter().enumerate()
-                    .map(|(idx,name)| match name {
-                        Some(name) => Ok(name.clone()),
-                        None => InternalSnafu {
-                            reason: format!("Expect column {idx} of table id={table_id} to have name in table schema, found None"),
-                        }
-                        .fail().map_err(BoxedError::new).context(ExternalSnafu),
-                    })
-                    .collect::<Result<Vec<_>>>()?;
-                let name_to_col = HashMap::<_, _>::from_iter(
-                    insert_schema
-                        .iter()
-                        .enumerate()
-                        .map(|(i, name)| (&name.column_name, i)),
+            let mut rows = self.node_context.read().await.decode_inserts(
+                table_id,
+                &insert_schema,
+                &rows_proto,
+                now,
+            );
+            // the source table may be altered since the flownode learnt its schema, adapt flows
+            // to the new schema and decode again
+            if matches!(rows, Err(Error::SourceSchemaMismatch { .. }))
+                && self
+                    .handle_source_table_altered(table_id)
+                    .await
+                    .map_err(to_meta_err)?
+            {
+                rows = self.node_context.read().await.decode_inserts(
+                    table_id,
+                    &insert_schema,
+                    &rows_proto,
+                    now,
                 );
-                let fetch_order: Vec<usize> = table_col_names
-                    .iter()
-                    .map(|names| {
-                        name_to_col.get(names).copied().context(UnexpectedSnafu {
-                            err_msg: format!("Column not found: {}", names),
-                        })
-                    })
-                    .try_collect()?;
-                if !fetch_order.iter().enumerate().all(|(i, &v)| i == v) {
-                    trace!("Reordering columns: {:?}", fetch_order)
-                }
-                fetch_order
-            };
-
-            let rows: Vec<DiffRow> = rows_proto
-                .into_iter()
-                .map(|r| {
-                    let r = repr::Row::from(r);
-                    let reordered = fetch_order
-                        .iter()
-                        .map(|&i| r.inner[i].clone())
-                        .collect_vec();
-                    repr::Row::new(reordered)
-                })
-                .map(|r| (r, now, 1))
-                .collect_vec();
+            }
+            let rows = rows.map_err(to_meta_err)?;
             self.handle_write_request(region_id.into(), rows)
                 .await
                 .map_err(to_meta_err)?;
         }
         Ok(Default::default())
     }
+
+    async fn alter_flow(&self, request: AlterFlowRequest) -> Result<()> {
+        let AlterFlowRequest {
+            flow_id,
+            expire_after,
+            comment,
+            sql,
+            flow_options,
+            discard_state,
+        } = request;
+        let flow_id = u64::from(flow_id);
+        let options = FlowOptions::parse(&flow_options).map_err(to_meta_err)?;
+        let Some(flow_info) = self.flow_info(flow_id).await else {
+            return FlowNotFoundSnafu { id: flow_id }
+                .fail()
+                .map_err(to_meta_err);
+        };
+        let result = if discard_state || flow_info.sql != sql || flow_info.options != options {
+            self.rebuild_flow(
+                flow_id,
+                expire_after,
+                Some(comment),
+                sql,
+                options,
+                discard_state,
+            )
+            .await
+        } else {
+            self.alter_flow(flow_id, expire_after, Some(comment)).await
+        };
+        result.map_err(to_meta_err)
+    }
+
+    async fn explain_flow(&self, request: ExplainFlowRequest) -> Result<String> {
+        let ExplainFlowRequest {
+            source_table_ids,
+            sink_table_name,
+            sql,
+            flow_options,
+            query_context,
+        } = request;
+        let sink_table_name = [
+            sink_table_name.catalog_name,
+            sink_table_name.schema_name,
+            sink_table_name.table_name,
+        ];
+        let options = FlowOptions::parse(&flow_options).map_err(to_meta_err)?;
+        let query_ctx = api::v1::QueryContext::from(query_context).into();
+        self.explain_flow(
+            &source_table_ids,
+            &sink_table_name,
+            &sql,
+            &options,
+            Some(query_ctx),
+        )
+        .await
+        .map_err(to_meta_err)
+    }
+
+    async fn pause_flow(&self, flow_id: common_meta::key::FlowId) -> Result<()> {
+        self.pause_flow(u64::from(flow_id))
+            .await
+            .map_err(to_meta_err)
+    }
+
+    async fn resume_flow(&self, flow_id: common_meta::key::FlowId) -> Result<()> {
+        self.resume_flow(u64::from(flow_id))
+            .await
+            .map_err(to_meta_err)
+    }
+
+    async fn reset_source_table(&self, table_id: TableId) -> Result<()> {
+        self.reset_flows_of_table(table_id)
+            .await
+            .map(|_| ())
+            .map_err(to_meta_err)
+    }
 }
@@ -14,22 +14,25 @@
 
 //! Node context, prone to change with every incoming requests
 
-use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
+use api::helper::ColumnDataTypeWrapper;
+use api::v1::{ColumnSchema, Row as ProtoRow};
 use common_telemetry::trace;
+use datatypes::data_type::ConcreteDataType;
 use session::context::QueryContext;
 use snafu::{OptionExt, ResultExt};
-use table::metadata::TableId;
-use tokio::sync::{broadcast, mpsc, RwLock};
+use table::metadata::{TableId, TableVersion};
+use tokio::sync::{mpsc, RwLock};
 
 use crate::adapter::{FlowId, TableName, TableSource};
-use crate::error::{Error, EvalSnafu, TableNotFoundSnafu};
+use crate::error::{Error, EvalSnafu, SourceSchemaMismatchSnafu, TableNotFoundSnafu};
 use crate::expr::error::InternalSnafu;
 use crate::expr::{Batch, GlobalId};
-use crate::metrics::METRIC_FLOW_INPUT_BUF_SIZE;
-use crate::repr::{DiffRow, RelationDesc, BATCH_SIZE, BROADCAST_CAP, SEND_BUF_CAP};
+use crate::metrics::{METRIC_FLOW_INPUT_BUF_SIZE, METRIC_FLOW_INPUT_ROWS};
+use crate::repr::{DiffRow, RelationDesc, Row, Timestamp, BATCH_SIZE, BROADCAST_CAP, SEND_BUF_CAP};
 
 /// A context that holds the information of the dataflow
 #[derive(Default, Debug)]
@@ -39,7 +42,7 @@ pub struct FlownodeContext {
     /// mapping from task to sink table, useful for sending data back to the client when a task is done running
     pub flow_to_sink: BTreeMap<FlowId, TableName>,
     pub sink_to_flow: BTreeMap<TableName, FlowId>,
-    /// broadcast sender for source table, any incoming write request will be sent to the source table's corresponding sender
+    /// sender for source table, any incoming write request will be sent to the source table's corresponding sender
     ///
     /// Note that we are getting insert requests with table id, so we should use table id as the key
     pub source_sender: BTreeMap<TableId, SourceSender>,
@@ -51,32 +54,92 @@ pub struct FlownodeContext {
         BTreeMap<TableName, (mpsc::UnboundedSender<Batch>, mpsc::UnboundedReceiver<Batch>)>,
     /// the schema of the table, query from metasrv or inferred from TypedPlan
     pub schema: HashMap<GlobalId, RelationDesc>,
+    /// the version of source tables whose schema above is queried from metasrv
+    pub table_versions: HashMap<TableId, TableVersion>,
     /// All the tables that have been registered in the worker
     pub table_repr: IdToNameMap,
     pub query_context: Option<Arc<QueryContext>>,
 }
 
-/// a simple broadcast sender with backpressure, bounded capacity and blocking on send when send buf is full
+/// a sender of source table with backpressure, bounded capacity and blocking on send when send buf is full
 /// note that it wouldn't evict old data, so it's possible to block forever if the receiver is slow
 ///
-/// receiver still use tokio broadcast channel, since only sender side need to know
-/// backpressure and adjust dataflow running duration to avoid blocking
+/// each dataflow reading the source has its own bounded channel, and a batch is only moved out of
+/// the send buf when every channel has room for it, so a slow dataflow slows down the writes to
+/// the source instead of missing rows
+///
+/// a paused dataflow doesn't hold back others, batches it can't take are buffered in its
+/// sender and sent before any new batch once it's resumed, so it doesn't miss any row
 #[derive(Debug)]
 pub struct SourceSender {
     // TODO(discord9): make it all Vec<DiffRow>?
-    sender: broadcast::Sender<Batch>,
+    /// one sender per dataflow reading this source, with `BROADCAST_CAP` batches of capacity
+    senders: std::sync::Mutex<Vec<FlowSender>>,
     send_buf_tx: mpsc::Sender<Batch>,
     send_buf_rx: RwLock<mpsc::Receiver<Batch>>,
     send_buf_row_cnt: AtomicUsize,
 }
 
+/// The sending half of a dataflow's channel reading from a source
+#[derive(Debug)]
+struct FlowSender {
+    flow_id: FlowId,
+    tx: mpsc::Sender<Batch>,
+    /// whether the dataflow is paused, in which case it's not waited for
+    paused: bool,
+    /// batches which are sent while the dataflow is paused and its channel is full, in order
+    pending: VecDeque<Batch>,
+}
+
+impl FlowSender {
+    /// move pending batches into the channel as many as it can take
+    fn send_pending(&mut self) {
+        while let Some(batch) = self.pending.pop_front() {
+            match self.tx.try_send(batch) {
+                Ok(()) => (),
+                Err(mpsc::error::TrySendError::Full(batch)) => {
+                    self.pending.push_front(batch);
+                    break;
+                }
+                // the dataflow is removed meanwhile
+                Err(mpsc::error::TrySendError::Closed(_)) => self.pending.clear(),
+            }
+        }
+    }
+
+    /// whether the dataflow can take one more batch, a paused one always can since it's buffered
+    fn has_credit(&self) -> bool {
+        self.paused || (self.pending.is_empty() && self.tx.capacity() > 0)
+    }
+
+    /// send the batch after all pending ones, buffer it if the dataflow is paused and can't take it
+    fn send(&mut self, batch: Batch) -> Result<(), Error> {
+        if !self.pending.is_empty() {
+            self.pending.push_back(batch);
+            return Ok(());
+        }
+        match self.tx.try_send(batch) {
+            // the dataflow is removed meanwhile
+            Ok(()) | Err(mpsc::error::TrySendError::Closed(_)) => Ok(()),
+            Err(mpsc::error::TrySendError::Full(batch)) if self.paused => {
+                self.pending.push_back(batch);
+                Ok(())
+            }
+            Err(mpsc::error::TrySendError::Full(_)) => InternalSnafu {
+                reason: "Source channel of flow is full while having credit".to_string(),
+            }
+            .fail()
+            .with_context(|_| EvalSnafu),
+        }
+    }
+}
+
 impl Default for SourceSender {
     fn default() -> Self {
         // TODO(discord9): the capacity is arbitrary, we can adjust it later, might also want to limit the max number of rows in send buf
         let (send_buf_tx, send_buf_rx) = mpsc::channel(SEND_BUF_CAP);
         Self {
-            // TODO(discord9): found a better way then increase this to prevent lagging and hence missing input data
-            sender: broadcast::Sender::new(SEND_BUF_CAP),
+            senders: Default::default(),
             send_buf_tx,
             send_buf_rx: RwLock::new(send_buf_rx),
             send_buf_row_cnt: AtomicUsize::new(0),
@@ -87,19 +150,74 @@ impl Default for SourceSender {
 impl SourceSender {
     /// max number of iterations to try flush send buf
     const MAX_ITERATIONS: usize = 16;
-    pub fn get_receiver(&self) -> broadcast::Receiver<Batch> {
-        self.sender.subscribe()
+
+    /// create a new bounded channel for a dataflow to read from this source
+    pub fn get_receiver(&self, flow_id: FlowId) -> mpsc::Receiver<Batch> {
+        let (tx, rx) = mpsc::channel(BROADCAST_CAP);
+        self.senders.lock().unwrap().push(FlowSender {
+            flow_id,
+            tx,
+            paused: false,
+            pending: VecDeque::new(),
+        });
+        rx
+    }
+
+    /// pause or resume sending to the dataflow of the flow
+    pub fn set_paused(&self, flow_id: FlowId, paused: bool) {
+        for sender in self.senders.lock().unwrap().iter_mut() {
+            if sender.flow_id == flow_id {
+                sender.paused = paused;
+            }
+        }
+    }
+
+    /// send the batch only to the dataflow of the flow, after any batch pending for it, i.e. rows
+    /// backfilled into a newly created flow
+    ///
+    /// return false if no such dataflow, e.g. the flow is removed
+    pub fn send_to_flow(&self, flow_id: FlowId, batch: Batch) -> bool {
+        let mut senders = self.senders.lock().unwrap();
+        let Some(sender) = senders
+            .iter_mut()
+            .find(|s| s.flow_id == flow_id && !s.tx.is_closed())
+        else {
+            return false;
+        };
+        sender.pending.push_back(batch);
+        sender.send_pending();
+        true
+    }
+
+    /// number of batches pending for the dataflow of the flow, which its channel can't take yet
+    pub fn pending_len(&self, flow_id: FlowId) -> usize {
+        self.senders
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|s| s.flow_id == flow_id)
+            .map(|s| s.pending.len())
+            .sum()
+    }
+
+    /// Whether every running dataflow reading this source can take one more batch, also drop the
+    /// channels of dataflows which are removed and move pending batches of resumed ones
+    fn has_credit(&self) -> bool {
+        let mut senders = self.senders.lock().unwrap();
+        senders.retain(|s| !s.tx.is_closed());
+        senders.iter_mut().for_each(FlowSender::send_pending);
+        senders.iter().all(FlowSender::has_credit)
     }
 
     /// send as many as possible rows from send buf
-    /// until send buf is empty or broadchannel is full
+    /// until send buf is empty or any dataflow's channel is full
     pub async fn try_flush(&self) -> Result<usize, Error> {
         let mut row_cnt = 0;
         loop {
             let mut send_buf = self.send_buf_rx.write().await;
-            // if inner sender channel is empty or send buf is empty, there
+            // if any dataflow's channel is full or send buf is empty, there
             // is nothing to do for now, just break
-            if self.sender.len() >= BROADCAST_CAP || send_buf.is_empty() {
+            if !self.has_credit() || send_buf.is_empty() {
                 break;
             }
             // TODO(discord9): send rows instead so it's just moving a point
@@ -107,15 +225,10 @@ impl SourceSender {
                 let len = batch.row_count();
                 self.send_buf_row_cnt.fetch_sub(len, Ordering::SeqCst);
                 row_cnt += len;
-                self.sender
-                    .send(batch)
-                    .map_err(|err| {
-                        InternalSnafu {
-                            reason: format!("Failed to send row, error = {:?}", err),
-                        }
-                        .build()
-                    })
-                    .with_context(|_| EvalSnafu)?;
+                // only the flusher holding `send_buf` sends, so capacity checked above is still there
+                for s in self.senders.lock().unwrap().iter_mut() {
+                    s.send(batch.clone())?;
+                }
             }
         }
         if row_cnt > 0 {
@@ -164,6 +277,11 @@ impl FlownodeContext {
             .with_context(|| TableNotFoundSnafu {
                 name: table_id.to_string(),
             })?;
+        for flow_id in self.source_to_tasks.get(&table_id).into_iter().flatten() {
+            METRIC_FLOW_INPUT_ROWS
+                .with_label_values(&[flow_id.to_string().as_str()])
+                .inc_by(rows.len() as u64);
+        }
         sender.send_rows(rows).await
     }
 
@@ -182,7 +300,7 @@ impl FlownodeContext {
 impl FlownodeContext {
     /// mapping source table to task, and sink table to task in worker context
     ///
-    /// also add their corresponding broadcast sender/receiver
+    /// also add their corresponding source sender/sink receiver
     pub fn register_task_src_sink(
         &mut self,
         task_id: FlowId,
@@ -215,6 +333,73 @@ impl FlownodeContext {
         }
     }
 
+    /// The name of a source table of flows in this flownode, if known
+    fn source_table_name(&self, table_id: &TableId) -> Option<TableName> {
+        self.table_repr
+            .get_by_table_id(table_id)
+            .and_then(|(name, _)| name)
+    }
+
+    /// The source table the sink table is cascaded to, if some flow in this flownode reads the
+    /// sink table of another flow in this flownode
+    ///
+    /// results written to such sink table are fed to the source directly, instead of round
+    /// tripping through the frontend
+    pub fn cascaded_source(&self, sink_table_name: &TableName) -> Option<TableId> {
+        if !self.sink_to_flow.contains_key(sink_table_name) {
+            return None;
+        }
+        self.source_to_tasks
+            .keys()
+            .find(|id| self.source_table_name(id).as_ref() == Some(sink_table_name))
+            .copied()
+    }
+
+    /// Whether the source table is fed by the sink of some flow in this flownode, see
+    /// [`FlownodeContext::cascaded_source`]
+    pub fn is_cascaded_source(&self, table_id: &TableId) -> bool {
+        self.source_table_name(table_id)
+            .is_some_and(|name| self.sink_to_flow.contains_key(&name))
+    }
+
+    /// Whether a flow reading `source_table_ids` and writing to `sink_table_name` would form a
+    /// cycle with other flows in this flownode, i.e. its output is fed back to its input
+    ///
+    /// the source tables must be assigned global ids first so their names are known
+    pub fn forms_cycle(
+        &self,
+        flow_id: FlowId,
+        source_table_ids: &[TableId],
+        sink_table_name: &TableName,
+    ) -> bool {
+        let sources: BTreeSet<_> = source_table_ids
+            .iter()
+            .filter_map(|id| self.source_table_name(id))
+            .collect();
+        let mut visited = BTreeSet::new();
+        let mut to_visit = vec![sink_table_name.clone()];
+        while let Some(table) = to_visit.pop() {
+            if sources.contains(&table) {
+                return true;
+            }
+            if !visited.insert(table.clone()) {
+                continue;
+            }
+            for (source_id, tasks) in &self.source_to_tasks {
+                if self.source_table_name(source_id).as_ref() != Some(&table) {
+                    continue;
+                }
+                to_visit.extend(
+                    tasks
+                        .iter()
+                        .filter(|task| **task != flow_id)
+                        .filter_map(|task| self.flow_to_sink.get(task).cloned()),
+                );
+            }
+        }
+        false
+    }
+
     /// try add source sender, if already exist, do nothing
     pub fn add_source_sender_if_not_exist(&mut self, table_id: TableId) {
         let _sender = self.source_sender.entry(table_id).or_default();
@@ -319,9 +504,11 @@ impl FlownodeContext {
             let global_id = self.new_global_id();
 
             if let Some(table_id) = table_id {
-                let (known_table_name, schema) = srv_map.get_table_name_schema(&table_id).await?;
+                let (known_table_name, schema, version) =
+                    srv_map.get_table_name_schema(&table_id).await?;
                 table_name = table_name.or(Some(known_table_name));
                 self.schema.insert(global_id, schema);
+                self.table_versions.insert(table_id, version);
             } // if we don't have table id, it means database havn't assign one yet or we don't need it
 
             self.table_repr.insert(table_name, table_id, global_id);
@@ -329,6 +516,84 @@ impl FlownodeContext {
         }
     }
 
+    /// Decode rows mirrored from a region of source table `table_id` into [`DiffRow`]s at time
+    /// `now`, in the column order of the table's schema known by the flownode
+    ///
+    /// The schema of inserted rows is checked against the known one, so rows of a table altered
+    /// after the flownode learnt its schema are rejected instead of being misread
+    pub fn decode_inserts(
+        &self,
+        table_id: TableId,
+        insert_schema: &[ColumnSchema],
+        rows: &[ProtoRow],
+        now: Timestamp,
+    ) -> Result<Vec<DiffRow>, Error> {
+        let desc = self
+            .table_repr
+            .get_by_table_id(&table_id)
+            .and_then(|(_, gid)| self.schema.get(&gid))
+            .with_context(|| TableNotFoundSnafu {
+                name: format!("table id={}", table_id),
+            })?;
+        let mismatch = |reason: String| SourceSchemaMismatchSnafu { table_id, reason }.fail();
+
+        let name_to_col = insert_schema
+            .iter()
+            .enumerate()
+            .map(|(i, col)| (col.column_name.as_str(), (i, col)))
+            .collect::<HashMap<_, _>>();
+        let mut fetch_order = Vec::with_capacity(desc.names.len());
+        for (idx, (name, typ)) in desc.names.iter().zip(&desc.typ.column_types).enumerate() {
+            let Some(name) = name else {
+                return mismatch(format!("column {} of the known schema has no name", idx));
+            };
+            let Some((i, col)) = name_to_col.get(name.as_str()) else {
+                return mismatch(format!("column {} not found in inserted rows", name));
+            };
+            let datatype =
+                ColumnDataTypeWrapper::try_new(col.datatype, col.datatype_extension.clone())
+                    .map(ConcreteDataType::from)
+                    .ok();
+            if datatype.as_ref() != Some(&typ.scalar_type) {
+                return mismatch(format!(
+                    "column {} is of type {:?} in inserted rows, expect {:?}",
+                    name, datatype, typ.scalar_type
+                ));
+            }
+            fetch_order.push(*i);
+        }
+
+        rows.iter()
+            .map(|row| {
+                let Some(row) = Row::from_proto_columns(row, &fetch_order) else {
+                    return mismatch(format!(
+                        "expect {} values in an inserted row, found {}",
+                        insert_schema.len(),
+                        row.values.len()
+                    ));
+                };
+                Ok((row, now, 1))
+            })
+            .collect()
+    }
+
+    /// Whether rows of a source table in the `new` schema can still be decoded in the `known`
+    /// one, i.e. all columns known are kept with the same types, as rows are decoded by names
+    pub fn can_decode_as(known: &RelationDesc, new: &RelationDesc) -> bool {
+        known
+            .names
+            .iter()
+            .zip(&known.typ.column_types)
+            .all(|(name, typ)| {
+                new.names
+                    .iter()
+                    .zip(&new.typ.column_types)
+                    .any(|(new_name, new_typ)| {
+                        name.is_some() && new_name == name && new_typ.scalar_type == typ.scalar_type
+                    })
+            })
+    }
+
     /// Assign a schema to a table
     ///
     pub fn assign_table_schema(
@@ -395,3 +660,220 @@ impl IdToNameMap {
         self.global_id_to_name_id.get(global_id).cloned()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use datatypes::value::Value;
+
+    use super::*;
+
+    #[test]
+    fn test_cascaded_flows() {
+        let name = |table: &str| ["greptime", "public", table].map(String::from);
+        let mut ctx = FlownodeContext::default();
+        for (id, table) in [(1, "numbers"), (2, "out_1"), (3, "out_2")] {
+            let gid = ctx.new_global_id();
+            ctx.table_repr.insert(Some(name(table)), Some(id), gid);
+        }
+        // numbers -> flow 1 -> out_1 -> flow 2 -> out_2
+        ctx.register_task_src_sink(1, &[1], name("out_1"));
+        assert_eq!(None, ctx.cascaded_source(&name("out_1")));
+        ctx.register_task_src_sink(2, &[2], name("out_2"));
+        assert_eq!(Some(2), ctx.cascaded_source(&name("out_1")));
+        assert_eq!(None, ctx.cascaded_source(&name("out_2")));
+        assert!(ctx.is_cascaded_source(&2));
+        assert!(!ctx.is_cascaded_source(&1));
+
+        // out_2 -> flow 3 -> numbers closes the loop
+        assert!(ctx.forms_cycle(3, &[3], &name("numbers")));
+        assert!(ctx.forms_cycle(3, &[1], &name("numbers")));
+        assert!(!ctx.forms_cycle(3, &[3], &name("out_3")));
+        // flow 1 replaced by itself doesn't count
+        assert!(!ctx.forms_cycle(1, &[1], &name("out_1")));
+    }
+
+    #[test]
+    fn test_decode_inserts() {
+        use api::v1::value::ValueData;
+        use api::v1::{ColumnDataType, SemanticType};
+
+        use crate::repr::{ColumnType, RelationType};
+
+        let mut ctx = FlownodeContext::default();
+        let gid = ctx.new_global_id();
+        ctx.table_repr.insert(None, Some(1), gid);
+        let desc = RelationType::new(vec![
+            ColumnType::new(ConcreteDataType::string_datatype(), false),
+            ColumnType::new(ConcreteDataType::int64_datatype(), true),
+        ])
+        .into_named(vec![Some("host".to_string()), Some("cpu".to_string())]);
+        ctx.schema.insert(gid, desc);
+
+        let column = |name: &str, datatype: ColumnDataType| ColumnSchema {
+            column_name: name.to_string(),
+            datatype: datatype as i32,
+            semantic_type: SemanticType::Field as i32,
+            ..Default::default()
+        };
+        let value = |v: ValueData| api::v1::Value {
+            value_data: Some(v),
+        };
+        // columns are inserted in a different order than the table's schema
+        let schema = vec![
+            column("cpu", ColumnDataType::Int64),
+            column("host", ColumnDataType::String),
+        ];
+        let rows = vec![ProtoRow {
+            values: vec![
+                value(ValueData::I64Value(3)),
+                value(ValueData::StringValue("a".to_string())),
+            ],
+        }];
+        let decoded = ctx.decode_inserts(1, &schema, &rows, 5).unwrap();
+        let expected = Row::new(vec![Value::from("a"), Value::from(3i64)]);
+        assert_eq!(decoded, vec![(expected, 5, 1)]);
+
+        // a column of another type or missing is rejected
+        let altered = vec![
+            column("cpu", ColumnDataType::Float64),
+            column("host", ColumnDataType::String),
+        ];
+        let res = ctx.decode_inserts(1, &altered, &rows, 5);
+        assert!(matches!(res, Err(Error::SourceSchemaMismatch { .. })));
+        let res = ctx.decode_inserts(1, &schema[..1], &rows, 5);
+        assert!(matches!(res, Err(Error::SourceSchemaMismatch { .. })));
+        let res = ctx.decode_inserts(2, &schema, &rows, 5);
+        assert!(matches!(res, Err(Error::TableNotFound { .. })));
+    }
+
+    #[test]
+    fn test_can_decode_as() {
+        use crate::repr::{ColumnType, RelationType};
+
+        let desc = |columns: &[(&str, ConcreteDataType)]| {
+            RelationType::new(
+                columns
+                    .iter()
+                    .map(|(_, typ)| ColumnType::new(typ.clone(), true))
+                    .collect(),
+            )
+            .into_named(
+                columns
+                    .iter()
+                    .map(|(name, _)| Some(name.to_string()))
+                    .collect(),
+            )
+        };
+        let known = desc(&[
+            ("host", ConcreteDataType::string_datatype()),
+            ("cpu", ConcreteDataType::int64_datatype()),
+        ]);
+        // a column added or columns reordered
+        let added = desc(&[
+            ("cpu", ConcreteDataType::int64_datatype()),
+            ("mem", ConcreteDataType::int64_datatype()),
+            ("host", ConcreteDataType::string_datatype()),
+        ]);
+        assert!(FlownodeContext::can_decode_as(&known, &added));
+        // a column dropped or of another type
+        let dropped = desc(&[("host", ConcreteDataType::string_datatype())]);
+        assert!(!FlownodeContext::can_decode_as(&known, &dropped));
+        let modified = desc(&[
+            ("host", ConcreteDataType::string_datatype()),
+            ("cpu", ConcreteDataType::float64_datatype()),
+        ]);
+        assert!(!FlownodeContext::can_decode_as(&known, &modified));
+    }
+
+    #[tokio::test]
+    async fn test_source_sender_backpressure() {
+        let sender = SourceSender::default();
+        let mut fast = sender.get_receiver(0);
+        let slow = sender.get_receiver(1);
+        for i in 0..=BROADCAST_CAP {
+            let row = (Row::new(vec![Value::from(i as u32)]), 0, 1);
+            sender.send_rows(vec![row]).await.unwrap();
+        }
+
+        // the slow receiver's channel is full, so the last row stays in send buf
+        assert_eq!(sender.try_flush().await.unwrap(), BROADCAST_CAP);
+        while fast.try_recv().is_ok() {}
+        assert_eq!(sender.try_flush().await.unwrap(), 0);
+
+        // the row is sent once the slow receiver is gone
+        drop(slow);
+        assert_eq!(sender.try_flush().await.unwrap(), 1);
+        assert_eq!(fast.try_recv().unwrap().row_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_source_sender_paused() {
+        let sender = SourceSender::default();
+        let mut running = sender.get_receiver(0);
+        let mut paused = sender.get_receiver(1);
+        sender.set_paused(1, true);
+        for i in 0..BROADCAST_CAP * 2 {
+            let row = (Row::new(vec![Value::from(i as u32)]), 0, 1);
+            sender.send_rows(vec![row]).await.unwrap();
+            // the paused receiver doesn't block the running one
+            assert_eq!(sender.try_flush().await.unwrap(), 1);
+            assert_eq!(running.try_recv().unwrap().row_count(), 1);
+        }
+
+        // the paused receiver gets all batches in order, the ones its channel can't take are
+        // buffered and sent once it's resumed
+        let mut received = vec![];
+        while let Ok(batch) = paused.try_recv() {
+            received.push(batch.get_row(0).unwrap()[0].clone());
+        }
+        assert_eq!(received.len(), BROADCAST_CAP);
+        sender.set_paused(1, false);
+        assert_eq!(sender.try_flush().await.unwrap(), 0);
+        while let Ok(batch) = paused.try_recv() {
+            received.push(batch.get_row(0).unwrap()[0].clone());
+        }
+        let expected = (0..BROADCAST_CAP * 2)
+            .map(|i| Value::from(i as u32))
+            .collect::<Vec<_>>();
+        assert_eq!(received, expected);
+
+        // once resumed, it holds back others again
+        for i in 0..=BROADCAST_CAP {
+            let row = (Row::new(vec![Value::from(i as u32)]), 0, 1);
+            sender.send_rows(vec![row]).await.unwrap();
+        }
+        assert_eq!(sender.try_flush().await.unwrap(), BROADCAST_CAP);
+    }
+
+    #[tokio::test]
+    async fn test_source_sender_send_to_flow() {
+        let sender = SourceSender::default();
+        let mut other = sender.get_receiver(0);
+        let mut backfilled = sender.get_receiver(1);
+        for i in 0..BROADCAST_CAP + 1 {
+            let batch = Batch::try_from_rows(vec![Row::new(vec![Value::from(i as u32)])]).unwrap();
+            assert!(sender.send_to_flow(1, batch));
+        }
+        assert!(!sender.send_to_flow(2, Batch::empty()));
+        // only the flow backfilled gets the rows, the ones its channel can't take are pending
+        assert!(other.try_recv().is_err());
+        assert_eq!(sender.pending_len(1), 1);
+
+        // new rows are sent after the pending ones
+        let row = (Row::new(vec![Value::from(BROADCAST_CAP as u32 + 1)]), 0, 1);
+        sender.send_rows(vec![row]).await.unwrap();
+        let mut received = vec![];
+        while received.len() < BROADCAST_CAP + 2 {
+            sender.try_flush().await.unwrap();
+            while let Ok(batch) = backfilled.try_recv() {
+                received.push(batch.get_row(0).unwrap()[0].clone());
+            }
+        }
+        let expected = (0..BROADCAST_CAP + 2)
+            .map(|i| Value::from(i as u32))
+            .collect::<Vec<_>>();
+        assert_eq!(received, expected);
+        assert_eq!(sender.pending_len(1), 0);
+        assert_eq!(other.try_recv().unwrap().row_count(), 1);
+    }
+}
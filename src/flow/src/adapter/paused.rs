@@ -0,0 +1,90 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Persisting which flows are paused, so a flow stays paused after the flownode restarts
+
+use common_meta::kv_backend::KvBackendRef;
+use common_meta::rpc::store::PutRequest;
+use snafu::ResultExt;
+
+use crate::adapter::FlowId;
+use crate::error::{Error, PausedFlowStoreSnafu};
+
+/// Prefix of the keys marking paused flows in kv backend, followed by `/{flow_id}`
+pub const FLOW_PAUSED_KEY_PREFIX: &str = "__flow_paused";
+
+/// Store of paused flows in a kv backend, which is the metasrv's in distributed mode so a
+/// flow stays paused in any flownode
+#[derive(Clone)]
+pub struct PausedFlowStore {
+    kv_backend: KvBackendRef,
+}
+
+impl PausedFlowStore {
+    pub fn new(kv_backend: KvBackendRef) -> Self {
+        Self { kv_backend }
+    }
+
+    fn key(flow_id: FlowId) -> Vec<u8> {
+        format!("{FLOW_PAUSED_KEY_PREFIX}/{flow_id}").into_bytes()
+    }
+
+    /// Mark the flow as paused or not
+    pub async fn set_paused(&self, flow_id: FlowId, paused: bool) -> Result<(), Error> {
+        if paused {
+            self.kv_backend
+                .put(PutRequest::new().with_key(Self::key(flow_id)))
+                .await
+                .context(PausedFlowStoreSnafu { id: flow_id })?;
+        } else {
+            self.kv_backend
+                .delete(&Self::key(flow_id), false)
+                .await
+                .context(PausedFlowStoreSnafu { id: flow_id })?;
+        }
+        Ok(())
+    }
+
+    /// Whether the flow is marked as paused
+    pub async fn is_paused(&self, flow_id: FlowId) -> Result<bool, Error> {
+        self.kv_backend
+            .exists(&Self::key(flow_id))
+            .await
+            .context(PausedFlowStoreSnafu { id: flow_id })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use common_meta::kv_backend::memory::MemoryKvBackend;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_paused_flow_store() {
+        let store = PausedFlowStore::new(Arc::new(MemoryKvBackend::new()));
+        assert!(!store.is_paused(1).await.unwrap());
+
+        store.set_paused(1, true).await.unwrap();
+        assert!(store.is_paused(1).await.unwrap());
+        assert!(!store.is_paused(2).await.unwrap());
+
+        store.set_paused(1, false).await.unwrap();
+        assert!(!store.is_paused(1).await.unwrap());
+        // resuming a flow which is not paused is fine
+        store.set_paused(2, false).await.unwrap();
+    }
+}
@@ -0,0 +1,110 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Profiling the operators of a flow, to find out which operator dominates a slow flow
+
+use std::fmt::Write;
+use std::time::Duration;
+
+use common_telemetry::info;
+
+use crate::adapter::{FlowId, FlowWorkerManager};
+use crate::compute::OperatorProfile;
+use crate::error::{Error, FlowNotFoundSnafu};
+
+impl FlowWorkerManager {
+    /// Enable or disable profiling of a flow, statistics of the flow are reset when it's enabled
+    ///
+    /// Profiling adds a little overhead to every run of each operator, so it's disabled by default
+    pub async fn set_flow_profiling(&self, flow_id: FlowId, enable: bool) -> Result<(), Error> {
+        self.profile_flow(flow_id, Some(enable)).await?;
+        info!("Set profiling of flow with id={} to {}", flow_id, enable);
+        Ok(())
+    }
+
+    /// Execution statistics of each operator of a flow since its profiling is enabled, in the
+    /// order operators are rendered
+    pub async fn flow_profiles(&self, flow_id: FlowId) -> Result<Vec<OperatorProfile>, Error> {
+        self.profile_flow(flow_id, None).await
+    }
+
+    /// Like `EXPLAIN ANALYZE`, describe the execution statistics of each operator of a flow,
+    /// one operator per line
+    pub async fn explain_analyze_flow(&self, flow_id: FlowId) -> Result<String, Error> {
+        let profiles = self.flow_profiles(flow_id).await?;
+        Ok(explain_profiles(&profiles))
+    }
+
+    async fn profile_flow(
+        &self,
+        flow_id: FlowId,
+        enable: Option<bool>,
+    ) -> Result<Vec<OperatorProfile>, Error> {
+        for handle in self.worker_handles.iter() {
+            if let Some(profiles) = handle.lock().await.profile_flow(flow_id, enable).await? {
+                return Ok(profiles);
+            }
+        }
+        FlowNotFoundSnafu { id: flow_id }.fail()
+    }
+}
+
+/// Describe each operator as `name: ticks=.., rows=.., cpu_time=.. (..%)`, where the percentage is
+/// its share of the cpu time of all operators
+fn explain_profiles(profiles: &[OperatorProfile]) -> String {
+    let total = profiles.iter().map(|p| p.cpu_time).sum::<Duration>();
+    let mut output = String::new();
+    for profile in profiles {
+        let percent = if total.is_zero() {
+            0.0
+        } else {
+            profile.cpu_time.as_secs_f64() / total.as_secs_f64() * 100.0
+        };
+        let _ = writeln!(
+            output,
+            "{}: ticks={}, rows={}, cpu_time={:?} ({:.1}%)",
+            profile.name, profile.ticks, profile.rows, profile.cpu_time, percent
+        );
+    }
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_explain_profiles() {
+        let profiles = vec![
+            OperatorProfile {
+                name: "source_batch",
+                ticks: 4,
+                rows: 100,
+                cpu_time: Duration::from_millis(1),
+            },
+            OperatorProfile {
+                name: "reduce_batch",
+                ticks: 2,
+                rows: 100,
+                cpu_time: Duration::from_millis(3),
+            },
+        ];
+        assert_eq!(
+            explain_profiles(&profiles),
+            "source_batch: ticks=4, rows=100, cpu_time=1ms (25.0%)\n\
+             reduce_batch: ticks=2, rows=100, cpu_time=3ms (75.0%)\n"
+        );
+        assert_eq!(explain_profiles(&[]), "");
+    }
+}
@@ -0,0 +1,130 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Adapting existing flows to their altered source tables
+//!
+//! Rows of source tables are decoded by column names, so altering a table without touching the
+//! columns known by the flownode(i.e. adding columns) only needs the new version to be recorded.
+//! Otherwise flows reading the table are planned again against its new schema, and those whose
+//! query no longer type-checks are marked as failed.
+
+use common_error::ext::ErrorExt;
+use common_telemetry::{error, info};
+use table::metadata::{TableId, TableVersion};
+
+use crate::adapter::node_context::FlownodeContext;
+use crate::adapter::{FlowId, FlowWorkerManager};
+use crate::error::{Error, SourceTableAlteredSnafu};
+
+impl FlowWorkerManager {
+    /// Adapt flows reading the table to its latest schema if it's altered since the flownode
+    /// learnt its schema, return whether the table is altered since then
+    pub async fn handle_source_table_altered(&self, table_id: TableId) -> Result<bool, Error> {
+        // rebuilding the same flow concurrently could leave it half rebuilt
+        let _guard = self.reset_lock.lock().await;
+        let (_, new_schema, new_version) = self
+            .table_info_source
+            .get_table_name_schema(&table_id)
+            .await?;
+
+        let mut node_ctx = self.node_context.write().await;
+        let Some(&old_version) = node_ctx.table_versions.get(&table_id) else {
+            return Ok(false);
+        };
+        if new_version <= old_version {
+            return Ok(false);
+        }
+        let Some((_, gid)) = node_ctx.table_repr.get_by_table_id(&table_id) else {
+            return Ok(false);
+        };
+        let flow_ids = node_ctx
+            .source_to_tasks
+            .get(&table_id)
+            .cloned()
+            .unwrap_or_default();
+        node_ctx.table_versions.insert(table_id, new_version);
+        let compatible = node_ctx
+            .schema
+            .get(&gid)
+            .is_some_and(|known| FlownodeContext::can_decode_as(known, &new_schema));
+        if compatible {
+            drop(node_ctx);
+            let mut flow_infos = self.flow_infos.write().await;
+            for flow_id in &flow_ids {
+                if let Some(info) = flow_infos.get_mut(flow_id) {
+                    info.source_versions.insert(table_id, new_version);
+                }
+            }
+            info!(
+                "Source table {} of flows {:?} is altered from version {} to {}, columns known are unchanged",
+                table_id, flow_ids, old_version, new_version
+            );
+            return Ok(true);
+        }
+        node_ctx.schema.insert(gid, new_schema);
+        drop(node_ctx);
+
+        for flow_id in flow_ids {
+            self.replan_flow(flow_id, table_id, old_version, new_version)
+                .await;
+        }
+        Ok(true)
+    }
+
+    /// Plan the flow again against the new schema of its source table, keeping its state if
+    /// it's planned the same, or mark it as failed if it can't be planned
+    async fn replan_flow(
+        &self,
+        flow_id: FlowId,
+        table_id: TableId,
+        from: TableVersion,
+        to: TableVersion,
+    ) {
+        let Some(info) = self.flow_info(flow_id).await else {
+            return;
+        };
+        let res = self
+            .rebuild_flow(
+                flow_id,
+                info.expire_after,
+                info.comment.clone(),
+                info.sql.clone(),
+                info.options.clone(),
+                false,
+            )
+            .await;
+        match res {
+            Ok(()) => info!(
+                "Flow {} is planned again against version {} of its source table {}",
+                flow_id, to, table_id
+            ),
+            Err(err) => {
+                let err = SourceTableAlteredSnafu {
+                    flow_id,
+                    table_id,
+                    from,
+                    to,
+                    reason: err.output_msg(),
+                }
+                .build();
+                error!(err; "Failed to adapt flow {} to its altered source table", flow_id);
+                // the flow is kept without a dataflow, so it's shown as failed until it's
+                // altered or dropped
+                self.node_context.write().await.remove_flow(flow_id);
+                self.flow_infos.write().await.insert(flow_id, info);
+                self.mark_flow_failed(flow_id, &err).await;
+            }
+        }
+    }
+}
@@ -14,11 +14,16 @@
 
 //! How to query table information from database
 
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
 use common_error::ext::BoxedError;
+use common_meta::cache_invalidator::{CacheInvalidator, Context};
+use common_meta::instruction::CacheIdent;
 use common_meta::key::table_info::{TableInfoManager, TableInfoValue};
 use common_meta::key::table_name::{TableNameKey, TableNameManager};
 use snafu::{OptionExt, ResultExt};
-use table::metadata::TableId;
+use table::metadata::{TableId, TableVersion};
 
 use crate::adapter::TableName;
 use crate::error::{
@@ -26,11 +31,88 @@ use crate::error::{
 };
 use crate::repr::{self, ColumnType, RelationDesc, RelationType};
 
+/// Arc-ed [`TableIdNameCache`]
+pub type TableIdNameCacheRef = Arc<TableIdNameCache>;
+
+/// In-memory cache of table name <-> table id mapping
+///
+/// It should be registered to the node's cache registry, so entries of dropped or renamed tables
+/// are invalidated by DDL
+#[derive(Debug, Default)]
+pub struct TableIdNameCache {
+    inner: RwLock<IdNameMap>,
+}
+
+/// Both directions of the mapping, kept in sync under one lock
+#[derive(Debug, Default)]
+struct IdNameMap {
+    /// table name -> table id
+    name_to_id: HashMap<TableName, TableId>,
+    /// table id -> table name
+    id_to_name: HashMap<TableId, TableName>,
+}
+
+impl TableIdNameCache {
+    pub fn get_table_id(&self, name: &TableName) -> Option<TableId> {
+        self.inner.read().unwrap().name_to_id.get(name).copied()
+    }
+
+    pub fn get_table_name(&self, table_id: &TableId) -> Option<TableName> {
+        self.inner.read().unwrap().id_to_name.get(table_id).cloned()
+    }
+
+    pub fn insert(&self, name: TableName, table_id: TableId) {
+        let mut inner = self.inner.write().unwrap();
+        inner.name_to_id.insert(name.clone(), table_id);
+        inner.id_to_name.insert(table_id, name);
+    }
+
+    /// Remove the mapping of the table with given id, from both directions
+    fn remove_by_id(&self, table_id: &TableId) {
+        let mut inner = self.inner.write().unwrap();
+        if let Some(name) = inner.id_to_name.remove(table_id) {
+            inner.name_to_id.remove(&name);
+        }
+    }
+
+    /// Remove the mapping of the table with given name, from both directions
+    fn remove_by_name(&self, name: &TableName) {
+        let mut inner = self.inner.write().unwrap();
+        if let Some(table_id) = inner.name_to_id.remove(name) {
+            inner.id_to_name.remove(&table_id);
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl CacheInvalidator for TableIdNameCache {
+    async fn invalidate(
+        &self,
+        _ctx: &Context,
+        caches: &[CacheIdent],
+    ) -> common_meta::error::Result<()> {
+        for cache in caches {
+            match cache {
+                CacheIdent::TableId(table_id) => self.remove_by_id(table_id),
+                CacheIdent::TableName(name) => self.remove_by_name(&[
+                    name.catalog_name.clone(),
+                    name.schema_name.clone(),
+                    name.table_name.clone(),
+                ]),
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+}
+
 /// mapping of table name <-> table id should be query from tableinfo manager
 pub struct TableSource {
     /// for query `TableId -> TableName` mapping
     table_info_manager: TableInfoManager,
     table_name_manager: TableNameManager,
+    /// cache of the mapping queried from the managers above
+    cache: TableIdNameCacheRef,
 }
 
 impl TableSource {
@@ -38,9 +120,15 @@ impl TableSource {
         TableSource {
             table_info_manager,
             table_name_manager,
+            cache: Default::default(),
         }
     }
 
+    /// Use a cache shared with the node's cache registry, so it's invalidated by DDL
+    pub fn set_cache(&mut self, cache: TableIdNameCacheRef) {
+        self.cache = cache;
+    }
+
     pub async fn get_table_id_from_proto_name(
         &self,
         name: &greptime_proto::v1::TableName,
@@ -63,6 +151,9 @@ impl TableSource {
 
     /// If the table havn't been created in database, the tableId returned would be null
     pub async fn get_table_id_from_name(&self, name: &TableName) -> Result<Option<TableId>, Error> {
+        if let Some(table_id) = self.cache.get_table_id(name) {
+            return Ok(Some(table_id));
+        }
         let ret = self
             .table_name_manager
             .get(TableNameKey::new(&name[0], &name[1], &name[2]))
@@ -71,12 +162,19 @@ impl TableSource {
                 msg: format!("Table name = {:?}, couldn't found table id", name),
             })?
             .map(|id| id.table_id());
+        if let Some(table_id) = ret {
+            self.cache.insert(name.clone(), table_id);
+        }
         Ok(ret)
     }
 
     /// query metasrv about the table name and table id
     pub async fn get_table_name(&self, table_id: &TableId) -> Result<TableName, Error> {
-        self.table_info_manager
+        if let Some(name) = self.cache.get_table_name(table_id) {
+            return Ok(name);
+        }
+        let name = self
+            .table_info_manager
             .get(*table_id)
             .await
             .map_err(BoxedError::new)
@@ -85,7 +183,9 @@ impl TableSource {
                 reason: format!("Table id = {:?}, couldn't found table name", table_id),
             })
             .map(|name| name.table_name())
-            .map(|name| [name.catalog_name, name.schema_name, name.table_name])
+            .map(|name| [name.catalog_name, name.schema_name, name.table_name])?;
+        self.cache.insert(name.clone(), *table_id);
+        Ok(name)
     }
 
     /// query metasrv about the `TableInfoValue` and table id
@@ -103,10 +203,12 @@ impl TableSource {
             .map(|v| v.into_inner()))
     }
 
+    /// query metasrv about the name, schema and version of the table, the version is bumped
+    /// every time the table is altered
     pub async fn get_table_name_schema(
         &self,
         table_id: &TableId,
-    ) -> Result<(TableName, RelationDesc), Error> {
+    ) -> Result<(TableName, RelationDesc, TableVersion), Error> {
         let table_info_value = self
             .get_table_info_value(table_id)
             .await?
@@ -121,6 +223,7 @@ impl TableSource {
             table_name.table_name,
         ];
 
+        let version = table_info_value.table_info.ident.version;
         let raw_schema = table_info_value.table_info.meta.schema;
         let (column_types, col_names): (Vec<_>, Vec<_>) = raw_schema
             .column_schemas
@@ -153,6 +256,39 @@ impl TableSource {
                 },
                 names: col_names,
             },
+            version,
         ))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_table_id_name_cache_invalidate() {
+        let cache = TableIdNameCache::default();
+        let name = |t: &str| ["greptime".to_string(), "public".to_string(), t.to_string()];
+        cache.insert(name("numbers"), 1024);
+        cache.insert(name("numbers_with_ts"), 1025);
+        assert_eq!(cache.get_table_id(&name("numbers")), Some(1024));
+        assert_eq!(cache.get_table_name(&1025), Some(name("numbers_with_ts")));
+
+        cache
+            .invalidate(&Context::default(), &[CacheIdent::TableId(1024)])
+            .await
+            .unwrap();
+        assert_eq!(cache.get_table_id(&name("numbers")), None);
+        assert_eq!(cache.get_table_name(&1024), None);
+
+        let table_name = table::table_name::TableName::new("greptime", "public", "numbers_with_ts");
+        cache
+            .invalidate(&Context::default(), &[CacheIdent::TableName(table_name)])
+            .await
+            .unwrap();
+        assert_eq!(cache.get_table_id(&name("numbers_with_ts")), None);
+        assert_eq!(cache.get_table_name(&1025), None);
+    }
+}
@@ -62,3 +62,30 @@ pub fn new_test_table_info_with_name<I: IntoIterator<Item = u32>>(
 ///
 /// containing several default table info and schema
 fn mock_harness_flow_node_manager() {}
+
+#[test]
+fn test_diff_requests_to_upserts() {
+    let row = |k: i64, v: i64| Row::new(vec![Value::from(k), Value::from(v)]);
+    let reqs = vec![
+        DiffRequest::Insert(vec![(row(1, 1), 0), (row(2, 1), 0), (row(3, 1), 0)]),
+        // update key 1 with the insert before the delete, and key 2 twice
+        DiffRequest::Insert(vec![(row(1, 2), 1), (row(2, 2), 1), (row(2, 3), 2)]),
+        DiffRequest::Delete(vec![(row(1, 1), 1), (row(2, 1), 1), (row(2, 2), 2)]),
+        // key 3 is inserted and deleted in the same round, key 4 written before is deleted
+        DiffRequest::Delete(vec![(row(3, 1), 3), (row(4, 1), 3)]),
+        DiffRequest::Insert(vec![(row(4, 1), 4)]),
+        DiffRequest::Delete(vec![(row(4, 1), 5)]),
+    ];
+    let upserts = diff_requests_to_upserts(reqs, &[0]);
+    assert_eq!(upserts.len(), 2);
+    match &upserts[0] {
+        DiffRequest::Delete(rows) => assert_eq!(rows, &vec![(row(4, 1), 5)]),
+        req => panic!("Expect deletes, found {req:?}"),
+    }
+    match &upserts[1] {
+        DiffRequest::Insert(rows) => assert_eq!(rows, &vec![(row(1, 2), 1), (row(2, 3), 2)]),
+        req => panic!("Expect inserts, found {req:?}"),
+    }
+
+    assert!(diff_requests_to_upserts(vec![DiffRequest::Insert(vec![])], &[0]).is_empty());
+}
@@ -16,11 +16,14 @@ use api::helper::ColumnDataTypeWrapper;
 use api::v1::column_def::options_from_column_schema;
 use api::v1::{ColumnDataType, ColumnDataTypeExtension, SemanticType};
 use common_error::ext::BoxedError;
+use datatypes::data_type::ConcreteDataType;
 use datatypes::schema::ColumnSchema;
 use itertools::Itertools;
 use snafu::ResultExt;
 
-use crate::error::{Error, ExternalSnafu};
+use crate::adapter::{AUTO_CREATED_PLACEHOLDER_TS_COL, UPDATE_AT_TS_COL};
+use crate::error::{Error, ExternalSnafu, InvalidQuerySnafu};
+use crate::repr::RelationDesc;
 
 /// convert `ColumnSchema` lists to it's corresponding proto type
 pub fn column_schemas_to_proto(
@@ -60,3 +63,263 @@ pub fn column_schemas_to_proto(
         .collect();
     Ok(ret)
 }
+
+/// Derive the schema of a sink table from the output of a flow, returns the primary keys, the
+/// columns and whether a placeholder time index is added
+///
+/// the group keys are the primary keys and the time window is the time index, followed by the
+/// `update_at` column and a placeholder time index if the flow has no time window
+pub fn sink_table_schema_from_flow(
+    schema: &RelationDesc,
+) -> (Vec<String>, Vec<ColumnSchema>, bool) {
+    // TODO(discord9): use default key from schema
+    let primary_keys = schema
+        .typ()
+        .keys
+        .first()
+        .map(|v| {
+            v.column_indices
+                .iter()
+                .map(|i| {
+                    schema
+                        .get_name(*i)
+                        .clone()
+                        .unwrap_or_else(|| format!("col_{i}"))
+                })
+                .collect_vec()
+        })
+        .unwrap_or_default();
+    let update_at = ColumnSchema::new(
+        UPDATE_AT_TS_COL,
+        ConcreteDataType::timestamp_millisecond_datatype(),
+        true,
+    );
+
+    let original_schema = schema
+        .typ()
+        .column_types
+        .clone()
+        .into_iter()
+        .enumerate()
+        .map(|(idx, typ)| {
+            let name = schema
+                .names
+                .get(idx)
+                .cloned()
+                .flatten()
+                .unwrap_or(format!("col_{}", idx));
+            let ret = ColumnSchema::new(name, typ.scalar_type, typ.nullable);
+            if schema.typ().time_index == Some(idx) {
+                ret.with_time_index(true)
+            } else {
+                ret
+            }
+        })
+        .collect_vec();
+
+    let mut with_auto_added_col = original_schema.clone();
+    with_auto_added_col.push(update_at);
+
+    // if no time index, add one as placeholder
+    let no_time_index = schema.typ().time_index.is_none();
+    if no_time_index {
+        let ts_col = ColumnSchema::new(
+            AUTO_CREATED_PLACEHOLDER_TS_COL,
+            ConcreteDataType::timestamp_millisecond_datatype(),
+            true,
+        )
+        .with_time_index(true);
+        with_auto_added_col.push(ts_col);
+    }
+
+    (primary_keys, with_auto_added_col, no_time_index)
+}
+
+/// Check the output of a flow can be written back to an existing sink table
+///
+/// results are written back by position, so the sink table must have the output columns of
+/// the flow in the same order with the same names, types and nullability, optionally followed
+/// by a millisecond `update_at` column and the placeholder time index of an auto created table
+pub fn check_sink_table_schema(
+    sink_table_name: &str,
+    flow_schema: &RelationDesc,
+    sink_columns: &[ColumnSchema],
+) -> Result<(), Error> {
+    let typ = flow_schema.typ();
+    let output_len = typ.column_types.len();
+    let sink_time_index = sink_columns.iter().position(|c| c.is_time_index());
+    let is_auto_created = sink_columns.last().is_some_and(|c| {
+        c.name == AUTO_CREATED_PLACEHOLDER_TS_COL && sink_time_index == Some(sink_columns.len() - 1)
+    });
+
+    let mut mismatches = Vec::new();
+    if sink_columns.len() < output_len {
+        mismatches.push(format!(
+            "the query outputs {} columns but the sink table has only {}",
+            output_len,
+            sink_columns.len()
+        ));
+    }
+    for (idx, (col_type, sink_col)) in typ.column_types.iter().zip(sink_columns).enumerate() {
+        if let Some(name) = flow_schema.get_name(idx) {
+            if *name != sink_col.name {
+                mismatches.push(format!(
+                    "column {} is named `{}` in the query but `{}` in the sink table",
+                    idx, name, sink_col.name
+                ));
+            }
+        }
+        if col_type.scalar_type != sink_col.data_type {
+            mismatches.push(format!(
+                "column `{}` is of type {} in the query but {} in the sink table",
+                sink_col.name, col_type.scalar_type, sink_col.data_type
+            ));
+        }
+        // the time index may not be inferred from the query, whose time window is never null
+        if col_type.nullable && !sink_col.is_nullable() && sink_time_index != Some(idx) {
+            mismatches.push(format!(
+                "column `{}` is nullable in the query but not in the sink table",
+                sink_col.name
+            ));
+        }
+    }
+    for (idx, sink_col) in sink_columns.iter().enumerate().skip(output_len) {
+        let is_update_at = idx == output_len
+            && sink_col.data_type == ConcreteDataType::timestamp_millisecond_datatype();
+        let is_placeholder = is_auto_created && idx == sink_columns.len() - 1;
+        if !is_update_at && !is_placeholder {
+            mismatches.push(format!(
+                "column `{}` of the sink table is not in the output of the query",
+                sink_col.name
+            ));
+        }
+    }
+    if let Some(time_index) = typ.time_index {
+        if sink_time_index != Some(time_index) {
+            mismatches.push(format!(
+                "the time index of the query is column {} but {} in the sink table",
+                time_index,
+                sink_time_index
+                    .map(|idx| format!("column {idx}"))
+                    .unwrap_or_else(|| "none".to_string())
+            ));
+        }
+    }
+
+    if !mismatches.is_empty() {
+        return InvalidQuerySnafu {
+            reason: format!(
+                "Schema of sink table {} doesn't match the output of the flow: {}",
+                sink_table_name,
+                mismatches.join("; ")
+            ),
+        }
+        .fail();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::repr::{ColumnType, RelationType};
+
+    fn flow_schema() -> RelationDesc {
+        RelationType::new(vec![
+            ColumnType::new(ConcreteDataType::int64_datatype(), true),
+            ColumnType::new(ConcreteDataType::timestamp_millisecond_datatype(), false),
+        ])
+        .with_time_index(Some(1))
+        .into_named(vec![
+            Some("cnt".to_string()),
+            Some("time_window".to_string()),
+        ])
+    }
+
+    #[test]
+    fn test_sink_table_schema_from_flow() {
+        let schema = flow_schema();
+        let (primary_keys, columns, is_ts_placeholder) = sink_table_schema_from_flow(&schema);
+        assert!(primary_keys.is_empty());
+        assert!(!is_ts_placeholder);
+        assert_eq!(
+            vec!["cnt", "time_window", UPDATE_AT_TS_COL],
+            columns.iter().map(|c| c.name.as_str()).collect_vec()
+        );
+        assert!(columns[1].is_time_index());
+        check_sink_table_schema("sink", &schema, &columns).unwrap();
+
+        let schema = RelationType::new(vec![
+            ColumnType::new(ConcreteDataType::string_datatype(), true),
+            ColumnType::new(ConcreteDataType::int64_datatype(), true),
+        ])
+        .with_key(vec![0])
+        .into_named(vec![Some("host".to_string()), None]);
+        let (primary_keys, columns, is_ts_placeholder) = sink_table_schema_from_flow(&schema);
+        assert_eq!(vec!["host".to_string()], primary_keys);
+        assert!(is_ts_placeholder);
+        assert_eq!(
+            vec![
+                "host",
+                "col_1",
+                UPDATE_AT_TS_COL,
+                AUTO_CREATED_PLACEHOLDER_TS_COL
+            ],
+            columns.iter().map(|c| c.name.as_str()).collect_vec()
+        );
+        assert!(columns[3].is_time_index());
+        check_sink_table_schema("sink", &schema, &columns).unwrap();
+    }
+
+    #[test]
+    fn test_check_sink_table_schema() {
+        let schema = flow_schema();
+        let sink = vec![
+            ColumnSchema::new("cnt", ConcreteDataType::int64_datatype(), true),
+            ColumnSchema::new(
+                "time_window",
+                ConcreteDataType::timestamp_millisecond_datatype(),
+                false,
+            )
+            .with_time_index(true),
+            ColumnSchema::new(
+                "update_at",
+                ConcreteDataType::timestamp_millisecond_datatype(),
+                true,
+            ),
+        ];
+        check_sink_table_schema("sink", &schema, &sink).unwrap();
+        // without `update_at`
+        check_sink_table_schema("sink", &schema, &sink[..2]).unwrap();
+
+        let sink = vec![
+            ColumnSchema::new("count", ConcreteDataType::int32_datatype(), false),
+            ColumnSchema::new(
+                "time_window",
+                ConcreteDataType::timestamp_millisecond_datatype(),
+                true,
+            ),
+            ColumnSchema::new(
+                "ts",
+                ConcreteDataType::timestamp_millisecond_datatype(),
+                false,
+            )
+            .with_time_index(true),
+            ColumnSchema::new("extra", ConcreteDataType::string_datatype(), true),
+        ];
+        let err = check_sink_table_schema("sink", &schema, &sink)
+            .unwrap_err()
+            .to_string();
+        for expected in [
+            "column 0 is named `cnt` in the query but `count` in the sink table",
+            "column `count` is of type Int64 in the query but Int32 in the sink table",
+            "column `count` is nullable in the query but not in the sink table",
+            "column `extra` of the sink table is not in the output of the query",
+            "the time index of the query is column 1 but column 2 in the sink table",
+        ] {
+            assert!(err.contains(expected), "{err}");
+        }
+        // `ts` at the position of `update_at` is allowed
+        assert!(!err.contains("column `ts`"), "{err}");
+    }
+}
@@ -18,16 +18,24 @@ use std::collections::{BTreeMap, VecDeque};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
-use common_telemetry::info;
+use common_telemetry::{info, warn};
 use enum_as_inner::EnumAsInner;
 use hydroflow::scheduled::graph::Hydroflow;
-use snafu::ensure;
-use tokio::sync::{broadcast, mpsc, oneshot, Mutex};
+use snafu::{ensure, ResultExt};
+use tokio::sync::{mpsc, oneshot, Mutex};
 
 use crate::adapter::FlowId;
-use crate::compute::{Context, DataflowState, ErrCollector};
-use crate::error::{Error, FlowAlreadyExistSnafu, InternalSnafu, UnexpectedSnafu};
-use crate::expr::{Batch, GlobalId};
+use crate::compute::{
+    Context, DataflowCheckpoint, DataflowState, ErrCollector, EvictionOptions, OperatorProfile,
+    SpillOptions, TickPolicy,
+};
+use crate::error::{Error, EvalSnafu, FlowAlreadyExistSnafu, InternalSnafu, UnexpectedSnafu};
+use crate::expr::{Batch, GlobalId, ScalarExpr};
+use crate::metrics::{
+    METRIC_FLOW_LATE_ROWS, METRIC_FLOW_LATE_ROWS_BY_KEY, METRIC_FLOW_MAX_LATENESS_MS,
+    METRIC_FLOW_PROCESSING_LAG_MS, METRIC_FLOW_PROCESSING_TIME, METRIC_FLOW_STATE_BYTES,
+    METRIC_FLOW_STATE_EVICTED_KEYS, METRIC_FLOW_STATE_ROWS, METRIC_FLOW_WATERMARK_MS,
+};
 use crate::plan::TypedPlan;
 use crate::repr::{self, DiffRow};
 
@@ -54,6 +62,8 @@ pub(crate) struct ActiveDataflowState<'subgraph> {
     df: Hydroflow<'subgraph>,
     state: DataflowState,
     err_collector: ErrCollector,
+    /// a paused dataflow is not run, so it neither reads from sources nor writes to sinks
+    paused: bool,
 }
 
 impl std::fmt::Debug for ActiveDataflowState<'_> {
@@ -62,6 +72,7 @@ impl std::fmt::Debug for ActiveDataflowState<'_> {
             .field("df", &"<Hydroflow>")
             .field("state", &self.state)
             .field("err_collector", &self.err_collector)
+            .field("paused", &self.paused)
             .finish()
     }
 }
@@ -72,6 +83,7 @@ impl Default for ActiveDataflowState<'_> {
             df: Hydroflow::new(),
             state: DataflowState::default(),
             err_collector: ErrCollector::default(),
+            paused: false,
         }
     }
 }
@@ -91,6 +103,7 @@ impl<'subgraph> ActiveDataflowState<'subgraph> {
             local_scope: Default::default(),
             input_collection_batch: Default::default(),
             local_scope_batch: Default::default(),
+            shared_arrangements: Default::default(),
         }
     }
 
@@ -156,22 +169,122 @@ impl WorkerHandle {
     /// `blocking` indicate whether it will wait til all dataflows are finished computing if true or
     /// just start computing and return immediately if false
     ///
+    /// `force` runs all dataflows regardless of their [`TickPolicy`], so inputs already sent to them
+    /// are processed
+    ///
     /// the returned error is unrecoverable, and the worker should be shutdown/rebooted
-    pub async fn run_available(&self, now: repr::Timestamp, blocking: bool) -> Result<(), Error> {
+    pub async fn run_available(
+        &self,
+        now: repr::Timestamp,
+        blocking: bool,
+        force: bool,
+    ) -> Result<(), Error> {
         common_telemetry::trace!("Running available with blocking={}", blocking);
+        let req = Request::RunAvail {
+            now,
+            blocking,
+            force,
+        };
         if blocking {
-            let resp = self
-                .itc_client
-                .call_with_resp(Request::RunAvail { now, blocking })
-                .await?;
+            let resp = self.itc_client.call_with_resp(req).await?;
             common_telemetry::trace!("Running available with response={:?}", resp);
             Ok(())
         } else {
-            self.itc_client
-                .call_no_resp(Request::RunAvail { now, blocking })
+            self.itc_client.call_no_resp(req)
         }
     }
 
+    /// tick the flow to `now` regardless of its [`TickPolicy`], return false if no such flow in
+    /// this worker
+    pub async fn tick_flow(&self, flow_id: FlowId, now: repr::Timestamp) -> Result<bool, Error> {
+        let req = Request::Tick { flow_id, now };
+        let ret = self.itc_client.call_with_resp(req).await?;
+
+        ret.into_tick().map_err(|ret| {
+            InternalSnafu {
+                reason: format!(
+                    "Flow Node/Worker itc failed, expect Response::Tick, found {ret:?}"
+                ),
+            }
+            .build()
+        })
+    }
+
+    /// take a checkpoint of the flow's state, return `None` if no such flow in this worker
+    pub async fn checkpoint_flow(
+        &self,
+        flow_id: FlowId,
+    ) -> Result<Option<DataflowCheckpoint>, Error> {
+        let req = Request::Checkpoint { flow_id };
+        let ret = self.itc_client.call_with_resp(req).await?;
+
+        ret.into_checkpoint().map_err(|ret| {
+            InternalSnafu {
+                reason: format!(
+                    "Flow Node/Worker itc failed, expect Response::Checkpoint, found {ret:?}"
+                ),
+            }
+            .build()
+        })?
+    }
+
+    /// change `expire_after` of the flow in place, return false if no such flow in this worker
+    pub async fn alter_flow(
+        &self,
+        flow_id: FlowId,
+        expire_after: Option<repr::Duration>,
+    ) -> Result<bool, Error> {
+        let req = Request::Alter {
+            flow_id,
+            expire_after,
+        };
+        let ret = self.itc_client.call_with_resp(req).await?;
+
+        ret.into_alter().map_err(|ret| {
+            InternalSnafu {
+                reason: format!(
+                    "Flow Node/Worker itc failed, expect Response::Alter, found {ret:?}"
+                ),
+            }
+            .build()
+        })
+    }
+
+    /// pause or resume the flow, return false if no such flow in this worker
+    pub async fn set_flow_paused(&self, flow_id: FlowId, paused: bool) -> Result<bool, Error> {
+        let req = Request::Pause { flow_id, paused };
+        let ret = self.itc_client.call_with_resp(req).await?;
+
+        ret.into_pause().map_err(|ret| {
+            InternalSnafu {
+                reason: format!(
+                    "Flow Node/Worker itc failed, expect Response::Pause, found {ret:?}"
+                ),
+            }
+            .build()
+        })
+    }
+
+    /// enable or disable profiling of the flow if `enable` is given, return the execution statistics
+    /// of each operator of the flow, or `None` if no such flow in this worker
+    pub async fn profile_flow(
+        &self,
+        flow_id: FlowId,
+        enable: Option<bool>,
+    ) -> Result<Option<Vec<OperatorProfile>>, Error> {
+        let req = Request::Profile { flow_id, enable };
+        let ret = self.itc_client.call_with_resp(req).await?;
+
+        ret.into_profile().map_err(|ret| {
+            InternalSnafu {
+                reason: format!(
+                    "Flow Node/Worker itc failed, expect Response::Profile, found {ret:?}"
+                ),
+            }
+            .build()
+        })
+    }
+
     pub async fn contains_flow(&self, flow_id: FlowId) -> Result<bool, Error> {
         let req = Request::ContainTask { flow_id };
         let ret = self.itc_client.call_with_resp(req).await?;
@@ -229,24 +342,37 @@ impl<'s> Worker<'s> {
         sink_id: GlobalId,
         sink_sender: mpsc::UnboundedSender<Batch>,
         source_ids: &[GlobalId],
-        src_recvs: Vec<broadcast::Receiver<Batch>>,
+        src_recvs: Vec<mpsc::Receiver<Batch>>,
         // TODO(discord9): set expire duration for all arrangement and compare to sys timestamp instead
         expire_after: Option<repr::Duration>,
+        expire_when: Option<ScalarExpr>,
         create_if_not_exists: bool,
         err_collector: ErrCollector,
+        checkpoint: Option<DataflowCheckpoint>,
+        paused: bool,
+        spill: Option<SpillOptions>,
+        eviction: Option<EvictionOptions>,
+        tick_policy: TickPolicy,
+        replace: ReplaceMode,
     ) -> Result<Option<FlowId>, Error> {
         let already_exists = self.task_states.contains_key(&flow_id);
-        match (already_exists, create_if_not_exists) {
-            (true, true) => return Ok(None),
-            (true, false) => FlowAlreadyExistSnafu { id: flow_id }.fail()?,
-            (false, _) => (),
+        match (already_exists, create_if_not_exists, replace) {
+            (true, _, ReplaceMode::KeepState | ReplaceMode::DiscardState) => (),
+            (true, true, ReplaceMode::No) => return Ok(None),
+            (true, false, ReplaceMode::No) => FlowAlreadyExistSnafu { id: flow_id }.fail()?,
+            (false, _, _) => (),
         };
 
         let mut cur_task_state = ActiveDataflowState::<'s> {
             err_collector,
+            paused,
             ..Default::default()
         };
         cur_task_state.state.set_expire_after(expire_after);
+        cur_task_state.state.set_expire_when(expire_when);
+        cur_task_state.state.set_spill(spill);
+        cur_task_state.state.set_eviction(eviction);
+        cur_task_state.state.set_tick_policy(tick_policy);
 
         {
             let mut ctx = cur_task_state.new_ctx(sink_id);
@@ -258,7 +384,34 @@ impl<'s> Worker<'s> {
             let rendered = ctx.render_plan_batch(plan)?;
             ctx.render_unbounded_sink_batch(rendered, sink_sender);
         }
-        self.task_states.insert(flow_id, cur_task_state);
+        // the running dataflow is only replaced once the new one is rendered, so it keeps
+        // running if the new one fails to render
+        let checkpoint = match (replace, self.task_states.get_mut(&flow_id)) {
+            (ReplaceMode::KeepState, Some(old)) => {
+                // consume what's already sent to the old dataflow, no more rows are sent to it
+                // since then as sources are flushed with the flownode context the caller locks
+                if !old.paused {
+                    old.run_available();
+                }
+                match old.state.checkpoint() {
+                    Ok(checkpoint) => Some(checkpoint),
+                    Err(err) => {
+                        warn!(err; "Failed to take the state of flow {} to replace it", flow_id);
+                        None
+                    }
+                }
+            }
+            _ => checkpoint,
+        };
+        if let Some(checkpoint) = checkpoint {
+            // a checkpoint is only an optimization, so start from empty state if it can't be restored
+            if let Err(err) = cur_task_state.state.restore(checkpoint) {
+                warn!(err; "Failed to restore flow {} from checkpoint", flow_id);
+            }
+        }
+        if self.task_states.insert(flow_id, cur_task_state).is_some() {
+            info!("Replaced the dataflow of flow {}", flow_id);
+        }
         Ok(Some(flow_id))
     }
 
@@ -306,13 +459,69 @@ impl<'s> Worker<'s> {
     }
 
     /// run with tick acquired from tick manager(usually means system time)
-    /// TODO(discord9): better tick management
-    pub fn run_tick(&mut self, now: repr::Timestamp) {
-        for (_flow_id, task_state) in self.task_states.iter_mut() {
-            task_state.set_current_ts(now);
-            task_state.run_available();
+    ///
+    /// only dataflows whose [`TickPolicy`] allows to tick at `now` are run, unless `force` is true
+    pub fn run_tick(&mut self, now: repr::Timestamp, force: bool) {
+        for (flow_id, task_state) in self.task_states.iter_mut() {
+            if task_state.paused || !(force || task_state.state.should_tick(now)) {
+                continue;
+            }
+            Self::tick_flow(*flow_id, task_state, now);
+        }
+    }
+
+    /// set the current time of the dataflow to `now` and run it
+    fn tick_flow(flow_id: FlowId, task_state: &mut ActiveDataflowState, now: repr::Timestamp) {
+        let label = flow_id.to_string();
+        let _timer = METRIC_FLOW_PROCESSING_TIME
+            .with_label_values(&[label.as_str()])
+            .start_timer();
+        task_state.set_current_ts(now);
+        let executed = task_state.run_available();
+        // how far the result computed up to `now` lags behind the current time
+        METRIC_FLOW_PROCESSING_LAG_MS
+            .with_label_values(&[label.as_str()])
+            .set((common_time::util::current_time_millis() - now).max(0));
+        METRIC_FLOW_WATERMARK_MS
+            .with_label_values(&[label.as_str()])
+            .set(now);
+        // state only changes when some subgraph is executed
+        if executed {
+            task_state.state.spill_to_budget();
+            let evicted = task_state.state.evict_to_limit();
+            if evicted > 0 {
+                METRIC_FLOW_STATE_EVICTED_KEYS
+                    .with_label_values(&[label.as_str()])
+                    .inc_by(evicted as u64);
+                warn!(
+                    "Evicted {} keys from the state of flow {} exceeding its max state size, results of them may be wrong",
+                    evicted, flow_id
+                );
+            }
+            let late_data = task_state.err_collector.take_late_data();
+            if late_data.rows > 0 {
+                METRIC_FLOW_LATE_ROWS
+                    .with_label_values(&[label.as_str()])
+                    .inc_by(late_data.rows);
+                for (key_prefix, rows) in late_data.rows_by_key_prefix {
+                    METRIC_FLOW_LATE_ROWS_BY_KEY
+                        .with_label_values(&[label.as_str(), key_prefix.as_str()])
+                        .inc_by(rows);
+                }
+                let max_lateness = METRIC_FLOW_MAX_LATENESS_MS.with_label_values(&[label.as_str()]);
+                max_lateness.set(max_lateness.get().max(late_data.max_lateness));
+            }
+            let (rows, bytes) = task_state.state.state_size();
+            METRIC_FLOW_STATE_ROWS
+                .with_label_values(&[label.as_str()])
+                .set(rows as i64);
+            METRIC_FLOW_STATE_BYTES
+                .with_label_values(&[label.as_str()])
+                .set(bytes as i64);
         }
+        task_state.state.ticked(now);
     }
+
     /// handle request, return response if any, Err if receive shutdown signal
     ///
     /// return `Err(())` if receive shutdown request
@@ -326,8 +535,15 @@ impl<'s> Worker<'s> {
                 source_ids,
                 src_recvs,
                 expire_after,
+                expire_when,
                 create_if_not_exists,
                 err_collector,
+                checkpoint,
+                paused,
+                spill,
+                eviction,
+                tick_policy,
+                replace,
             } => {
                 let task_create_result = self.create_flow(
                     flow_id,
@@ -337,8 +553,15 @@ impl<'s> Worker<'s> {
                     &source_ids,
                     src_recvs,
                     expire_after,
+                    expire_when,
                     create_if_not_exists,
                     err_collector,
+                    checkpoint,
+                    paused,
+                    spill,
+                    eviction,
+                    tick_policy,
+                    replace,
                 );
                 Some(Response::Create {
                     result: task_create_result,
@@ -348,8 +571,12 @@ impl<'s> Worker<'s> {
                 let ret = self.remove_flow(flow_id);
                 Some(Response::Remove { result: ret })
             }
-            Request::RunAvail { now, blocking } => {
-                self.run_tick(now);
+            Request::RunAvail {
+                now,
+                blocking,
+                force,
+            } => {
+                self.run_tick(now, force);
                 if blocking {
                     Some(Response::RunAvail)
                 } else {
@@ -360,12 +587,77 @@ impl<'s> Worker<'s> {
                 let ret = self.task_states.contains_key(&flow_id);
                 Some(Response::ContainTask { result: ret })
             }
+            Request::Checkpoint { flow_id } => {
+                let ret = self
+                    .task_states
+                    .get(&flow_id)
+                    .map(|task_state| task_state.state.checkpoint().context(EvalSnafu))
+                    .transpose();
+                Some(Response::Checkpoint { result: ret })
+            }
+            Request::Alter {
+                flow_id,
+                expire_after,
+            } => {
+                let ret = match self.task_states.get_mut(&flow_id) {
+                    Some(task_state) => {
+                        task_state.state.alter_expire_after(expire_after);
+                        true
+                    }
+                    None => false,
+                };
+                Some(Response::Alter { result: ret })
+            }
+            Request::Pause { flow_id, paused } => {
+                let ret = match self.task_states.get_mut(&flow_id) {
+                    Some(task_state) => {
+                        task_state.paused = paused;
+                        true
+                    }
+                    None => false,
+                };
+                Some(Response::Pause { result: ret })
+            }
+            Request::Tick { flow_id, now } => {
+                let ret = match self.task_states.get_mut(&flow_id) {
+                    Some(task_state) => {
+                        // a paused flow is not run even if it's ticked manually
+                        if !task_state.paused {
+                            Self::tick_flow(flow_id, task_state, now);
+                        }
+                        true
+                    }
+                    None => false,
+                };
+                Some(Response::Tick { result: ret })
+            }
+            Request::Profile { flow_id, enable } => {
+                let ret = self.task_states.get(&flow_id).map(|task_state| {
+                    if let Some(enable) = enable {
+                        task_state.state.set_profiling(enable);
+                    }
+                    task_state.state.profiles()
+                });
+                Some(Response::Profile { result: ret })
+            }
             Request::Shutdown => return Err(()),
         };
         Ok(ret)
     }
 }
 
+/// How a running dataflow of the flow is handled when the flow is created again
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplaceMode {
+    /// It's kept, and the creation fails unless `create_if_not_exists`
+    No,
+    /// It's replaced once the new dataflow is rendered, the new one starts from its state
+    KeepState,
+    /// It's replaced once the new dataflow is rendered, the new one starts from the given
+    /// checkpoint or empty state
+    DiscardState,
+}
+
 #[derive(Debug, EnumAsInner)]
 pub enum Request {
     Create {
@@ -374,10 +666,24 @@ pub enum Request {
         sink_id: GlobalId,
         sink_sender: mpsc::UnboundedSender<Batch>,
         source_ids: Vec<GlobalId>,
-        src_recvs: Vec<broadcast::Receiver<Batch>>,
+        src_recvs: Vec<mpsc::Receiver<Batch>>,
         expire_after: Option<repr::Duration>,
+        /// the time each key expires at over the source table, see [`DataflowState::set_expire_when`]
+        expire_when: Option<ScalarExpr>,
         create_if_not_exists: bool,
         err_collector: ErrCollector,
+        /// state to restore the flow from right after it's rendered
+        checkpoint: Option<DataflowCheckpoint>,
+        /// whether the flow is created paused, see [`Request::Pause`]
+        paused: bool,
+        /// spill the flow's state to disk once it exceeds the memory budget if set
+        spill: Option<SpillOptions>,
+        /// evict keys of the flow's state once it exceeds the max state size if set
+        eviction: Option<EvictionOptions>,
+        /// when the flow ticks
+        tick_policy: TickPolicy,
+        /// whether to replace the running dataflow of the flow, see [`ReplaceMode`]
+        replace: ReplaceMode,
     },
     Remove {
         flow_id: FlowId,
@@ -386,10 +692,33 @@ pub enum Request {
     RunAvail {
         now: repr::Timestamp,
         blocking: bool,
+        /// run all flows regardless of their [`TickPolicy`]
+        force: bool,
+    },
+    /// Tick the flow to the given time regardless of its [`TickPolicy`]
+    Tick {
+        flow_id: FlowId,
+        now: repr::Timestamp,
     },
     ContainTask {
         flow_id: FlowId,
     },
+    Checkpoint {
+        flow_id: FlowId,
+    },
+    Alter {
+        flow_id: FlowId,
+        expire_after: Option<repr::Duration>,
+    },
+    Pause {
+        flow_id: FlowId,
+        paused: bool,
+    },
+    /// enable or disable profiling of the flow if `enable` is given, and get its execution statistics
+    Profile {
+        flow_id: FlowId,
+        enable: Option<bool>,
+    },
     Shutdown,
 }
 
@@ -405,6 +734,21 @@ enum Response {
     ContainTask {
         result: bool,
     },
+    Checkpoint {
+        result: Result<Option<DataflowCheckpoint>, Error>,
+    },
+    Alter {
+        result: bool,
+    },
+    Pause {
+        result: bool,
+    },
+    Profile {
+        result: Option<Vec<OperatorProfile>>,
+    },
+    Tick {
+        result: bool,
+    },
     RunAvail,
 }
 
@@ -499,7 +843,7 @@ mod test {
         });
         let handle = rx.await.unwrap();
         let src_ids = vec![GlobalId::User(1)];
-        let (tx, rx) = broadcast::channel::<Batch>(1024);
+        let (tx, rx) = mpsc::channel::<Batch>(1024);
         let (sink_tx, mut sink_rx) = mpsc::unbounded_channel::<Batch>();
         let (flow_id, plan) = (
             1,
@@ -518,17 +862,183 @@ mod test {
             source_ids: src_ids,
             src_recvs: vec![rx],
             expire_after: None,
+            expire_when: None,
             create_if_not_exists: true,
             err_collector: ErrCollector::default(),
+            checkpoint: None,
+            paused: false,
+            spill: None,
+            eviction: None,
+            tick_policy: TickPolicy::EveryRun,
+            replace: ReplaceMode::No,
         };
         assert_eq!(
             handle.create_flow(create_reqs).await.unwrap(),
             Some(flow_id)
         );
-        tx.send(Batch::empty()).unwrap();
-        handle.run_available(0, true).await.unwrap();
+        tx.send(Batch::empty()).await.unwrap();
+        handle.run_available(0, true, false).await.unwrap();
         assert_eq!(sink_rx.recv().await.unwrap(), Batch::empty());
         drop(handle);
         worker_thread_handle.join().unwrap();
     }
+
+    #[tokio::test]
+    async fn test_tick_policy() {
+        let (tx, rx) = oneshot::channel();
+        let worker_thread_handle = std::thread::spawn(move || {
+            let (handle, mut worker) = create_worker();
+            tx.send(handle).unwrap();
+            worker.run();
+        });
+        let handle = rx.await.unwrap();
+        let plan = TypedPlan {
+            plan: Plan::Get {
+                id: Id::Global(GlobalId::User(1)),
+            },
+            schema: RelationType::new(vec![]).into_unnamed(),
+        };
+        let mut inputs = BTreeMap::new();
+        let mut outputs = BTreeMap::new();
+        let policies = [
+            (1, TickPolicy::Manual),
+            (2, TickPolicy::Interval(10)),
+            (3, TickPolicy::OnData { max_delay: 5 }),
+        ];
+        for (flow_id, tick_policy) in policies {
+            let (tx, rx) = mpsc::channel::<Batch>(1024);
+            let (sink_tx, sink_rx) = mpsc::unbounded_channel::<Batch>();
+            let create_reqs = Request::Create {
+                flow_id,
+                plan: plan.clone(),
+                sink_id: GlobalId::User(1),
+                sink_sender: sink_tx,
+                source_ids: vec![GlobalId::User(1)],
+                src_recvs: vec![rx],
+                expire_after: None,
+                expire_when: None,
+                create_if_not_exists: true,
+                err_collector: ErrCollector::default(),
+                checkpoint: None,
+                paused: false,
+                spill: None,
+                eviction: None,
+                tick_policy,
+                replace: ReplaceMode::No,
+            };
+            handle.create_flow(create_reqs).await.unwrap();
+            inputs.insert(flow_id, tx);
+            outputs.insert(flow_id, sink_rx);
+        }
+        let output_cnt = |outputs: &mut BTreeMap<FlowId, mpsc::UnboundedReceiver<Batch>>| {
+            outputs
+                .iter_mut()
+                .map(|(flow_id, rx)| {
+                    let mut cnt = 0;
+                    while rx.try_recv().is_ok() {
+                        cnt += 1;
+                    }
+                    (*flow_id, cnt)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // an input batch arrives before each run, the interval flow ticks on the first run and
+        // once 10ms has passed, the on-data flow ticks once the input has waited 5ms
+        let expected = [
+            (0, vec![(1, 0), (2, 1), (3, 0)]),
+            (5, vec![(1, 0), (2, 0), (3, 2)]),
+            (10, vec![(1, 0), (2, 2), (3, 0)]),
+        ];
+        for (now, expected) in expected {
+            for tx in inputs.values() {
+                tx.try_send(Batch::empty()).unwrap();
+            }
+            handle.run_available(now, true, false).await.unwrap();
+            assert_eq!(output_cnt(&mut outputs), expected, "at now={now}");
+        }
+
+        // the manual flow only ticks when requested, with the time given
+        assert!(handle.tick_flow(1, 42).await.unwrap());
+        assert!(!handle.tick_flow(4, 42).await.unwrap());
+        assert_eq!(output_cnt(&mut outputs), vec![(1, 3), (2, 0), (3, 0)]);
+
+        // all flows tick when forced
+        handle.run_available(11, true, true).await.unwrap();
+        assert_eq!(output_cnt(&mut outputs), vec![(1, 0), (2, 0), (3, 1)]);
+
+        drop(handle);
+        worker_thread_handle.join().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_replace_flow() {
+        let (tx, rx) = oneshot::channel();
+        let worker_thread_handle = std::thread::spawn(move || {
+            let (handle, mut worker) = create_worker();
+            tx.send(handle).unwrap();
+            worker.run();
+        });
+        let handle = rx.await.unwrap();
+        let create_reqs = |src_recv, sink_tx, replace| Request::Create {
+            flow_id: 1,
+            plan: TypedPlan {
+                plan: Plan::Get {
+                    id: Id::Global(GlobalId::User(1)),
+                },
+                schema: RelationType::new(vec![]).into_unnamed(),
+            },
+            sink_id: GlobalId::User(1),
+            sink_sender: sink_tx,
+            source_ids: vec![GlobalId::User(1)],
+            src_recvs: vec![src_recv],
+            expire_after: None,
+            expire_when: None,
+            create_if_not_exists: false,
+            err_collector: ErrCollector::default(),
+            checkpoint: None,
+            paused: false,
+            spill: None,
+            eviction: None,
+            tick_policy: TickPolicy::EveryRun,
+            replace,
+        };
+
+        let (old_tx, old_rx) = mpsc::channel::<Batch>(1024);
+        let (old_sink_tx, mut old_sink_rx) = mpsc::unbounded_channel::<Batch>();
+        handle
+            .create_flow(create_reqs(old_rx, old_sink_tx, ReplaceMode::No))
+            .await
+            .unwrap();
+        old_tx.try_send(Batch::empty()).unwrap();
+
+        // the flow exists
+        let (_new_tx, new_rx) = mpsc::channel::<Batch>(1024);
+        let (new_sink_tx, _) = mpsc::unbounded_channel::<Batch>();
+        assert!(handle
+            .create_flow(create_reqs(new_rx, new_sink_tx, ReplaceMode::No))
+            .await
+            .is_err());
+        assert!(!old_tx.is_closed());
+
+        // the old dataflow consumes what's sent to it before it's replaced
+        let (new_tx, new_rx) = mpsc::channel::<Batch>(1024);
+        let (new_sink_tx, mut new_sink_rx) = mpsc::unbounded_channel::<Batch>();
+        assert_eq!(
+            handle
+                .create_flow(create_reqs(new_rx, new_sink_tx, ReplaceMode::KeepState))
+                .await
+                .unwrap(),
+            Some(1)
+        );
+        assert_eq!(old_sink_rx.recv().await.unwrap(), Batch::empty());
+        assert!(old_tx.is_closed());
+
+        new_tx.try_send(Batch::empty()).unwrap();
+        handle.run_available(0, true, false).await.unwrap();
+        assert_eq!(new_sink_rx.recv().await.unwrap(), Batch::empty());
+
+        drop(handle);
+        worker_thread_handle.join().unwrap();
+    }
 }
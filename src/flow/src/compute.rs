@@ -19,5 +19,7 @@ mod state;
 mod types;
 
 pub(crate) use render::Context;
-pub(crate) use state::DataflowState;
-pub(crate) use types::ErrCollector;
+pub(crate) use state::{
+    DataflowCheckpoint, DataflowState, EvictionOptions, OperatorProfile, SpillOptions, TickPolicy,
+};
+pub(crate) use types::{DeadLetter, ErrCollector, OperatorError};
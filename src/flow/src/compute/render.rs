@@ -28,13 +28,17 @@ use super::state::Scheduler;
 use crate::compute::state::DataflowState;
 use crate::compute::types::{Collection, CollectionBundle, ErrCollector, Toff};
 use crate::error::{Error, InvalidQuerySnafu, NotImplementedSnafu};
-use crate::expr::{self, Batch, GlobalId, LocalId};
+use crate::expr::{self, Batch, GlobalId, LocalId, ScalarExpr};
 use crate::plan::{Plan, TypedPlan};
 use crate::repr::{self, DiffRow};
 
+mod join;
 mod map;
 mod reduce;
 mod src_sink;
+mod top_k;
+mod union;
+mod window;
 
 /// The Context for build a Operator with id of `GlobalId`
 pub struct Context<'referred, 'df> {
@@ -57,6 +61,14 @@ pub struct Context<'referred, 'df> {
     ///
     /// TODO(discord9): consider if use Vec<(LocalId, CollectionBundle)> instead
     pub local_scope_batch: Vec<BTreeMap<LocalId, CollectionBundle<Batch>>>,
+    /// arrangements shared by operators arranging the same plan by the same key, so only one
+    /// copy of the state is kept in this dataflow, each bundle has the shared arrangement keyed by
+    /// the key in its `arranged`, and the updates already applied to the arrangement as its collection
+    ///
+    /// There is a false positive in using `Vec<ScalarExpr>` as key due to `ScalarExpr::Literal`
+    /// contain a `Value` which have `bytes` variant
+    #[allow(clippy::mutable_key_type)]
+    pub shared_arrangements: BTreeMap<(TypedPlan, Vec<ScalarExpr>), CollectionBundle>,
     // Collect all errors in this operator's evaluation
     pub err_collector: ErrCollector,
 }
@@ -71,6 +83,7 @@ impl Drop for Context<'_, '_> {
                     .flat_map(|v| v.into_iter())
                     .map(|(_k, v)| v),
             )
+            .chain(std::mem::take(&mut self.shared_arrangements).into_values())
         {
             bundle.collection.into_inner().drop(self.df);
             drop(bundle.arranged);
@@ -141,6 +154,14 @@ impl Context<'_, '_> {
                 reason: "Union is still WIP",
             }
             .fail(),
+            Plan::TopK { .. } => NotImplementedSnafu {
+                reason: "TopK is not supported in batch mode",
+            }
+            .fail(),
+            Plan::Window { .. } => NotImplementedSnafu {
+                reason: "Window is not supported in batch mode",
+            }
+            .fail(),
         }
     }
 
@@ -158,14 +179,24 @@ impl Context<'_, '_> {
                 key_val_plan,
                 reduce_plan,
             } => self.render_reduce(input, key_val_plan, reduce_plan, plan.schema.typ),
-            Plan::Join { .. } => NotImplementedSnafu {
-                reason: "Join is still WIP",
-            }
-            .fail(),
-            Plan::Union { .. } => NotImplementedSnafu {
-                reason: "Union is still WIP",
-            }
-            .fail(),
+            Plan::Join { inputs, plan } => self.render_join(inputs, plan),
+            Plan::Union {
+                inputs,
+                consolidate_output,
+            } => self.render_union(inputs, consolidate_output),
+            Plan::TopK {
+                input,
+                order_by,
+                limit,
+                per_key,
+                append_only,
+            } => self.render_top_k(input, order_by, limit, per_key, append_only),
+            Plan::Window {
+                input,
+                partition_by,
+                order_by,
+                exprs,
+            } => self.render_window(input, partition_by, order_by, exprs),
         }
     }
 
@@ -183,11 +214,13 @@ impl Context<'_, '_> {
         // TODO(discord9): better way to schedule future run
         let scheduler = self.compute_state.get_scheduler();
         let scheduler_inner = scheduler.clone();
-        let err_collector = self.err_collector.clone();
+        let err_collector = self.err_collector.with_operator("constant");
+        let profiler = self.compute_state.profile_operator("constant_batch");
 
         let subgraph_id =
             self.df
                 .add_subgraph_source("ConstantBatch", send_port, move |_ctx, send_port| {
+                    let _timer = profiler.start();
                     // find the first timestamp that is greater than now
                     // use filter_map
 
@@ -197,9 +230,9 @@ impl Context<'_, '_> {
                     let not_great_than_now = after;
 
                     not_great_than_now.into_iter().for_each(|(_ts, rows)| {
+                        profiler.add_rows(rows.len());
                         err_collector.run(|| {
-                            let rows = rows.into_iter().map(|(row, _ts, _diff)| row).collect();
-                            let batch = Batch::try_from_rows(rows)?;
+                            let batch = Batch::try_from_diff_rows(rows)?;
                             send_port.give(vec![batch]);
                             Ok(())
                         });
@@ -228,10 +261,12 @@ impl Context<'_, '_> {
         // TODO(discord9): better way to schedule future run
         let scheduler = self.compute_state.get_scheduler();
         let scheduler_inner = scheduler.clone();
+        let profiler = self.compute_state.profile_operator("constant");
 
         let subgraph_id =
             self.df
                 .add_subgraph_source("Constant", send_port, move |_ctx, send_port| {
+                    let _timer = profiler.start();
                     // find the first timestamp that is greater than now
                     // use filter_map
 
@@ -241,6 +276,7 @@ impl Context<'_, '_> {
                     let not_great_than_now = after;
 
                     not_great_than_now.into_iter().for_each(|(_ts, rows)| {
+                        profiler.add_rows(rows.len());
                         send_port.give(rows);
                     });
                     // schedule the next run
@@ -416,6 +452,7 @@ mod test {
             local_scope: Default::default(),
             input_collection_batch: BTreeMap::new(),
             local_scope_batch: Default::default(),
+            shared_arrangements: Default::default(),
             err_collector,
         }
     }
@@ -458,6 +495,72 @@ mod test {
         assert_eq!(*cnt.borrow(), 3);
     }
 
+    /// test if updates of the same row sent to a sink in one run are consolidated
+    #[test]
+    fn test_unbounded_sink_consolidate() {
+        let mut df = Hydroflow::new();
+        let mut state = DataflowState::default();
+        let mut ctx = harness_test_ctx(&mut df, &mut state);
+
+        let rows = vec![
+            (Row::new(vec![2i64.into()]), 1, 1),
+            (Row::new(vec![1i64.into()]), 1, 1),
+            (Row::new(vec![1i64.into()]), 1, -1),
+            (Row::new(vec![2i64.into()]), 1, 1),
+        ];
+        let collection = ctx.render_constant(rows);
+        let (sender, mut recv) = tokio::sync::mpsc::unbounded_channel();
+        ctx.render_unbounded_sink(collection, sender);
+        drop(ctx);
+
+        state.set_current_ts(1);
+        state.run_available_with_schedule(&mut df);
+        let mut output = vec![];
+        while let Ok(row) = recv.try_recv() {
+            output.push(row);
+        }
+        assert_eq!(output, vec![(Row::new(vec![2i64.into()]), 1, 2)]);
+    }
+
+    /// test if operators are only profiled when profiling is enabled
+    #[test]
+    fn test_profile_operator() {
+        let mut df = Hydroflow::new();
+        let mut state = DataflowState::default();
+        let mut ctx = harness_test_ctx(&mut df, &mut state);
+
+        let rows = vec![
+            (Row::empty(), 1, 1),
+            (Row::empty(), 2, 1),
+            (Row::empty(), 3, 1),
+        ];
+        let bundle = ctx.render_constant(rows);
+        let _output = get_output_handle(&mut ctx, bundle);
+        drop(ctx);
+
+        state.set_current_ts(1);
+        state.run_available_with_schedule(&mut df);
+        let profiles = state.profiles();
+        assert_eq!(profiles.len(), 1);
+        assert_eq!((profiles[0].name, profiles[0].ticks), ("constant", 0));
+
+        state.set_profiling(true);
+        for now in 2..4 {
+            state.set_current_ts(now);
+            state.run_available_with_schedule(&mut df);
+        }
+        let profiles = state.profiles();
+        assert_eq!((profiles[0].ticks, profiles[0].rows), (2, 2));
+
+        // statistics are reset once profiling is enabled again
+        state.set_profiling(false);
+        state.set_profiling(true);
+        assert_eq!(
+            (state.profiles()[0].ticks, state.profiles()[0].rows),
+            (0, 0)
+        );
+    }
+
     /// a simple example to show how to use source and sink
     #[test]
     fn example_source_sink() {
@@ -0,0 +1,1562 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+
+use datatypes::value::Value;
+use hydroflow::scheduled::graph_ext::GraphExt;
+use itertools::Itertools;
+use snafu::{ensure, OptionExt};
+
+use crate::compute::render::Context;
+use crate::compute::types::{Arranged, Collection, CollectionBundle, ErrCollector, Toff};
+use crate::error::{Error, InvalidQuerySnafu, PlanSnafu};
+use crate::expr::error::InternalSnafu;
+use crate::expr::{EvalError, SafeMfpPlan, ScalarExpr};
+use crate::plan::{
+    DeltaJoinPlan, JoinFilter, JoinKind, JoinPlan, LinearJoinPlan, LinearStagePlan, TypedPlan,
+};
+use crate::repr::{self, Diff, DiffRow, Row};
+use crate::utils::{ArrangeHandler, KeyExpiryManager};
+
+impl Context<'_, '_> {
+    /// Render a join plan.
+    ///
+    /// A linear join is rendered as a chain of binary joins, each stage join the stream(the joined result so far)
+    /// with a new lookup relation, see [`JoinStage`] for how a binary join is maintained
+    ///
+    /// A delta join is rendered as one path of lookups per input, see [`Context::render_delta_join`]
+    pub fn render_join(
+        &mut self,
+        inputs: Vec<TypedPlan>,
+        plan: JoinPlan,
+    ) -> Result<CollectionBundle, Error> {
+        match plan {
+            JoinPlan::Linear(plan) => self.render_linear_join(inputs, plan),
+            JoinPlan::Delta(plan) => self.render_delta_join(inputs, plan),
+        }
+    }
+
+    fn render_linear_join(
+        &mut self,
+        inputs: Vec<TypedPlan>,
+        plan: LinearJoinPlan,
+    ) -> Result<CollectionBundle, Error> {
+        let mut inputs = inputs.into_iter().map(Some).collect_vec();
+        let mut take_input = |idx: usize| {
+            inputs
+                .get_mut(idx)
+                .and_then(Option::take)
+                .with_context(|| InvalidQuerySnafu {
+                    reason: format!("Join input {} not found or used more than once", idx),
+                })
+        };
+
+        let source = take_input(plan.source_relation)?;
+        // time index of the stream, used to expire join state by event time
+        let mut time_index = source.schema.typ().time_index;
+        let mut stream = self.render_plan(source)?;
+        if let Some(closure) = plan.initial_closure {
+            time_index = time_index.and_then(|idx| closure_output_index(&closure.before, idx));
+            stream = self.render_join_filter(stream, closure);
+        }
+
+        for stage in plan.stage_plans {
+            let lookup = take_input(stage.lookup_relation)?;
+            let stream_time_index =
+                time_index.and_then(|idx| stage.stream_thinning.iter().position(|c| *c == idx));
+            let lookup_time_index = lookup.schema.typ().time_index;
+            time_index = match stage.kind {
+                JoinKind::Semi | JoinKind::Anti => stream_time_index,
+                _ => stream_time_index
+                    .or(lookup_time_index.map(|idx| stage.stream_thinning.len() + idx))
+                    .and_then(|idx| closure_output_index(&stage.closure.before, idx)),
+            };
+
+            // only inner join can share its lookup index, see `JoinStage::shared_lookup`
+            let shared_key =
+                (stage.kind == JoinKind::Inner).then(|| (lookup.clone(), stage.lookup_key.clone()));
+            let shared = match &shared_key {
+                Some(shared_key) => self.get_shared_lookup(shared_key)?,
+                None => None,
+            };
+            // arrangements are created in the same order whether shared or not, so checkpoints of
+            // the same plan can always be restored
+            let (lookup, stream_index, lookup_index) = match shared {
+                Some((lookup, lookup_index)) => {
+                    let stream_index =
+                        self.new_join_index(stage.stream_key.len(), stream_time_index);
+                    (lookup, stream_index, lookup_index)
+                }
+                None => {
+                    let lookup = self.render_plan(lookup)?;
+                    let stream_index =
+                        self.new_join_index(stage.stream_key.len(), stream_time_index);
+                    let lookup_index =
+                        self.new_join_index(stage.lookup_key.len(), lookup_time_index);
+                    let lookup = match shared_key {
+                        Some(shared_key) => {
+                            self.share_lookup_index(shared_key, lookup, lookup_index.clone())?
+                        }
+                        None => lookup,
+                    };
+                    (lookup, stream_index, lookup_index)
+                }
+            };
+            let mut state = JoinStage::new(stage, stream_index, lookup_index);
+            state.shared_lookup = shared_key.is_some();
+            stream = self.render_join_stage(stream, lookup, state);
+        }
+
+        if let Some(closure) = plan.final_closure {
+            stream = self.render_join_filter(stream, closure);
+        }
+        Ok(stream)
+    }
+
+    /// Render a delta join, which only keeps an index of each input by each key it's looked up by,
+    /// instead of the intermediate results of a chain of binary joins
+    ///
+    /// All paths are rendered in one subgraph, which handles the updates of each input in order:
+    /// updates of input `i` are joined along its path with the indexes of other inputs, then
+    /// applied to the indexes of input `i`, so the output of a tick is
+    /// `d(A ⋈ B ⋈ C) = dA ⋈ B ⋈ C + A' ⋈ dB ⋈ C + A' ⋈ B' ⋈ dC`, where `A'` is `A` after applying `dA`
+    #[allow(clippy::mutable_key_type)]
+    fn render_delta_join(
+        &mut self,
+        inputs: Vec<TypedPlan>,
+        plan: DeltaJoinPlan,
+    ) -> Result<CollectionBundle, Error> {
+        let is_valid = plan.path_plans.len() == inputs.len()
+            && plan
+                .path_plans
+                .iter()
+                .enumerate()
+                .all(|(i, path)| path.source_relation == i);
+        ensure!(
+            is_valid,
+            PlanSnafu {
+                reason: "Delta join should have exactly one path starting from each input",
+            }
+        );
+        let time_indexes = inputs
+            .iter()
+            .map(|input| input.schema.typ().time_index)
+            .collect_vec();
+        let inputs = inputs
+            .into_iter()
+            .map(|input| {
+                self.render_plan(input)
+                    .map(|bundle| bundle.collection.into_inner())
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // paths looking up an input by the same key share its index, which is created in the
+        // order of paths and stages, so checkpoints of the same plan can always be restored
+        let mut indexes: BTreeMap<(usize, Vec<ScalarExpr>), JoinIndex> = BTreeMap::new();
+        let mut paths = Vec::with_capacity(plan.path_plans.len());
+        for path in plan.path_plans {
+            let mut lookups = Vec::with_capacity(path.stage_plans.len());
+            for stage in &path.stage_plans {
+                let time_index =
+                    time_indexes
+                        .get(stage.lookup_relation)
+                        .with_context(|| PlanSnafu {
+                            reason: format!("Delta join input {} not found", stage.lookup_relation),
+                        })?;
+                let key = (stage.lookup_relation, stage.lookup_key.clone());
+                let index = match indexes.get(&key) {
+                    Some(index) => index.clone(),
+                    None => {
+                        let index = self.new_join_index(stage.lookup_key.len(), *time_index);
+                        indexes.insert(key, index.clone());
+                        index
+                    }
+                };
+                lookups.push(index);
+            }
+            paths.push(DeltaPath {
+                plan: path,
+                lookups,
+            });
+        }
+        // the indexes updates of each input are applied to
+        let mut input_indexes = vec![vec![]; inputs.len()];
+        for ((relation, key), index) in indexes {
+            input_indexes[relation].push((key, index));
+        }
+
+        let (send_port, recv_port) = self.df.make_edge::<_, Toff>("delta_join");
+        let now = self.compute_state.current_time_ref();
+        let err_collector = self.err_collector.with_operator("join");
+        let profiler = self.compute_state.profile_operator("delta_join");
+
+        self.df.add_subgraph_n_m(
+            "delta_join",
+            inputs,
+            vec![send_port],
+            move |_ctx, recvs, sends| {
+                let _timer = profiler.start();
+                let now = *now.borrow();
+                let mut output = Vec::new();
+                for ((recv, path), indexes) in recvs.iter().zip(&paths).zip(&input_indexes) {
+                    let updates = recv
+                        .take_inner()
+                        .into_iter()
+                        .flat_map(|v| v.into_iter())
+                        .inspect(|_| profiler.add_rows(1));
+                    for (row, ts, diff) in updates {
+                        let keys = indexes
+                            .iter()
+                            .map(|(key, _)| {
+                                err_collector.run(|| eval_join_key(key, &row)).flatten()
+                            })
+                            .collect_vec();
+                        let expired = indexes.iter().zip(&keys).any(|((_, index), key)| {
+                            is_expired(index, key, &row, now, &err_collector)
+                        });
+                        if expired {
+                            continue;
+                        }
+                        if let Some(joined) = err_collector.run(|| path.join(&row, now)) {
+                            output.extend(
+                                joined
+                                    .into_iter()
+                                    .map(|(joined, joined_diff)| (joined, ts, diff * joined_diff)),
+                            );
+                        }
+                        // a null key never match anything, so it's not kept in the index
+                        for ((_, index), key) in indexes.iter().zip(keys) {
+                            if let Some(key) = key {
+                                err_collector.run(|| index.update(&key, &row, ts, diff, now));
+                            }
+                        }
+                    }
+                }
+                for (_, index) in input_indexes.iter().flatten() {
+                    err_collector.run(|| index.compact_to(now));
+                }
+                if let Some(send) = sends.first() {
+                    send.give(output);
+                }
+            },
+        );
+
+        Ok(CollectionBundle::from_collection(Collection::from_port(
+            recv_port,
+        )))
+    }
+
+    /// Create the index of one side of a join stage, which is an arrangement of this dataflow,
+    /// so it's expired, checkpointed and accounted for like other state
+    ///
+    /// Keys are expired by the event time of rows if the dataflow has `expire_after` and the side has a time index
+    fn new_join_index(&mut self, key_arity: usize, time_index: Option<usize>) -> JoinIndex {
+        let arrange = self.compute_state.new_arrange(None);
+        arrange.set_full_arrangement(true);
+        if let Some(time_index) = time_index {
+            let expire_man = KeyExpiryManager::new(
+                self.compute_state.expire_after(),
+                Some(ScalarExpr::Column(key_arity + time_index)),
+            );
+            arrange.write().set_expire_state(expire_man);
+        }
+        JoinIndex::new(arrange, key_arity)
+    }
+
+    /// Get the lookup side of an inner join stage already rendered by another stage looking up
+    /// the same plan by the same key, with the index shared with that stage
+    #[allow(clippy::mutable_key_type)]
+    fn get_shared_lookup(
+        &mut self,
+        shared_key: &(TypedPlan, Vec<ScalarExpr>),
+    ) -> Result<Option<(CollectionBundle, JoinIndex)>, Error> {
+        let Some(bundle) = self.shared_arrangements.get(shared_key) else {
+            return Ok(None);
+        };
+        let (key, arranged) = bundle.arranged.first_key_value().context(PlanSnafu {
+            reason: "Shared lookup of join has no arrangement",
+        })?;
+        let arrange = arranged
+            .arrangement
+            .clone_full_arrange()
+            .context(PlanSnafu {
+                reason: "No write is expected at this point",
+            })?;
+        let index = JoinIndex::new(arrange, key.len());
+        let collection = bundle.collection.clone(self.df);
+        Ok(Some((CollectionBundle::from_collection(collection), index)))
+    }
+
+    /// Maintain `index` of the lookup side of an inner join stage in its own subgraph, and
+    /// register it in [`Context::shared_arrangements`] so other stages looking up the same plan
+    /// by the same key reuse it instead of building their own, return the lookup updates
+    /// passed through the subgraph after being applied to the index
+    ///
+    /// Since updates are only passed to stages after they are applied, a stage always finds
+    /// the lookup updates it receives already in the index, see [`JoinStage::apply_updates`]
+    #[allow(clippy::mutable_key_type)]
+    fn share_lookup_index(
+        &mut self,
+        shared_key: (TypedPlan, Vec<ScalarExpr>),
+        lookup: CollectionBundle,
+        index: JoinIndex,
+    ) -> Result<CollectionBundle, Error> {
+        let (send_port, recv_port) = self.df.make_edge::<_, Toff>("join_arrange");
+        let now = self.compute_state.current_time_ref();
+        let err_collector = self.err_collector.with_operator("join");
+        let lookup_key = shared_key.1.clone();
+        let writer = index.clone();
+        let profiler = self.compute_state.profile_operator("join_arrange");
+
+        self.df.add_subgraph_in_out(
+            "join_arrange",
+            lookup.collection.into_inner(),
+            send_port,
+            move |_ctx, recv, send| {
+                let _timer = profiler.start();
+                let now = *now.borrow();
+                let updates = recv
+                    .take_inner()
+                    .into_iter()
+                    .flat_map(|v| v.into_iter())
+                    .collect_vec();
+                profiler.add_rows(updates.len());
+                for (row, ts, diff) in &updates {
+                    let key = err_collector
+                        .run(|| eval_join_key(&lookup_key, row))
+                        .flatten();
+                    if is_expired(&writer, &key, row, now, &err_collector) {
+                        continue;
+                    }
+                    // a null key never match anything, so it's not kept in the index
+                    if let Some(key) = key {
+                        err_collector.run(|| writer.update(&key, row, *ts, *diff, now));
+                    }
+                }
+                err_collector.run(|| writer.compact_to(now));
+                send.give(updates);
+            },
+        );
+
+        let shared = CollectionBundle {
+            collection: Collection::from_port(recv_port),
+            arranged: BTreeMap::from([(shared_key.1.clone(), Arranged::new(index.arrange))]),
+        };
+        let collection = shared.collection.clone(self.df);
+        self.shared_arrangements.insert(shared_key, shared);
+        Ok(CollectionBundle::from_collection(collection))
+    }
+
+    /// Render one binary join stage between `stream` and `lookup`
+    fn render_join_stage(
+        &mut self,
+        stream: CollectionBundle,
+        lookup: CollectionBundle,
+        mut state: JoinStage,
+    ) -> CollectionBundle {
+        let (send_port, recv_port) = self.df.make_edge::<_, Toff>("join");
+        let now = self.compute_state.current_time_ref();
+        let err_collector = self.err_collector.with_operator("join");
+        let profiler = self.compute_state.profile_operator("join");
+
+        self.df.add_subgraph_2in_out(
+            "join",
+            stream.collection.into_inner(),
+            lookup.collection.into_inner(),
+            send_port,
+            move |_ctx, stream_recv, lookup_recv, send| {
+                let _timer = profiler.start();
+                let stream_updates = stream_recv
+                    .take_inner()
+                    .into_iter()
+                    .flat_map(|v| v.into_iter())
+                    .inspect(|_| profiler.add_rows(1));
+                let lookup_updates = lookup_recv
+                    .take_inner()
+                    .into_iter()
+                    .flat_map(|v| v.into_iter())
+                    .inspect(|_| profiler.add_rows(1));
+                let output = state.apply_updates(
+                    stream_updates,
+                    lookup_updates,
+                    *now.borrow(),
+                    &err_collector,
+                );
+                send.give(output);
+            },
+        );
+
+        CollectionBundle::from_collection(Collection::from_port(recv_port))
+    }
+
+    /// Apply a [`JoinFilter`] to every row of `input`
+    fn render_join_filter(
+        &mut self,
+        input: CollectionBundle,
+        filter: JoinFilter,
+    ) -> CollectionBundle {
+        let (send_port, recv_port) = self.df.make_edge::<_, Toff>("join_filter");
+        let err_collector = self.err_collector.with_operator("join");
+        let profiler = self.compute_state.profile_operator("join_filter");
+
+        self.df.add_subgraph_in_out(
+            "join_filter",
+            input.collection.into_inner(),
+            send_port,
+            move |_ctx, recv, send| {
+                let _timer = profiler.start();
+                let data = recv
+                    .take_inner()
+                    .into_iter()
+                    .flat_map(|v| v.into_iter())
+                    .inspect(|_| profiler.add_rows(1));
+                let output = data
+                    .filter_map(|(row, ts, diff)| {
+                        err_collector
+                            .run(|| apply_join_filter(&filter, row.inner))
+                            .flatten()
+                            .map(|row| (row, ts, diff))
+                    })
+                    .collect_vec();
+                send.give(output);
+            },
+        );
+
+        CollectionBundle::from_collection(Collection::from_port(recv_port))
+    }
+}
+
+/// Get the output column of `mfp` which is a copy of the input column `idx`, if any
+fn closure_output_index(mfp: &SafeMfpPlan, idx: usize) -> Option<usize> {
+    mfp.mfp.projection.iter().position(|c| *c == idx)
+}
+
+/// Index rows of one side of a join by their join key, also keep track of the multiplicity of each row
+///
+/// The index is an arrangement keyed by the join key followed by the row, with the multiplicity of the row
+/// being the diff, so a row is removed from the state once it's fully retracted or expired
+#[derive(Clone)]
+struct JoinIndex {
+    arrange: ArrangeHandler,
+    /// number of columns of the join key, which is the prefix of keys in the arrangement
+    key_arity: usize,
+}
+
+impl JoinIndex {
+    fn new(arrange: ArrangeHandler, key_arity: usize) -> Self {
+        Self { arrange, key_arity }
+    }
+
+    fn index_key(key: &Row, row: &Row) -> Row {
+        Row::new(key.iter().chain(row.iter()).cloned().collect())
+    }
+
+    /// Return how much time the row is already expired by, if the row is expired
+    fn expired_by(
+        &self,
+        key: &Row,
+        row: &Row,
+        now: repr::Timestamp,
+    ) -> Result<Option<repr::Duration>, EvalError> {
+        match self.arrange.read().get_expire_state() {
+            Some(expire_man) => expire_man.get_expire_duration(now, &Self::index_key(key, row)),
+            None => Ok(None),
+        }
+    }
+
+    fn update(
+        &self,
+        key: &Row,
+        row: &Row,
+        ts: repr::Timestamp,
+        diff: Diff,
+        now: repr::Timestamp,
+    ) -> Result<(), EvalError> {
+        let index_key = Self::index_key(key, row);
+        self.arrange
+            .write()
+            .apply_updates(now, vec![((index_key, Row::empty()), ts, diff)])?;
+        Ok(())
+    }
+
+    /// Get all rows with the given join key and their multiplicity
+    fn get(&self, key: &Row, now: repr::Timestamp) -> Vec<(Row, Diff)> {
+        self.arrange
+            .read()
+            .get_by_prefix(now, key)
+            .into_iter()
+            .filter(|(_, (_, _, diff))| *diff != 0)
+            .map(|(index_key, (_, _, diff))| {
+                (Row::new(index_key.inner[self.key_arity..].to_vec()), diff)
+            })
+            .collect()
+    }
+
+    /// Consolidate all updates up to `now`, which also remove expired rows from the state
+    fn compact_to(&self, now: repr::Timestamp) -> Result<(), EvalError> {
+        self.arrange.write().compact_to(now)?;
+        Ok(())
+    }
+}
+
+/// One path of a delta join, which joins updates of its source input with the indexes of all
+/// other inputs, one stage after another
+struct DeltaPath {
+    plan: LinearJoinPlan,
+    /// index of the lookup input of each stage, by the lookup key of the stage
+    lookups: Vec<JoinIndex>,
+}
+
+impl DeltaPath {
+    /// Join an update of the source input along the path, return the joined rows with their
+    /// multiplicity relative to the update
+    fn join(&self, row: &Row, now: repr::Timestamp) -> Result<Vec<(Row, Diff)>, EvalError> {
+        let row = match &self.plan.initial_closure {
+            Some(closure) => apply_join_filter(closure, row.inner.clone())?,
+            None => Some(row.clone()),
+        };
+        let mut stream = row.into_iter().map(|row| (row, 1)).collect_vec();
+        for (stage, index) in self.plan.stage_plans.iter().zip(&self.lookups) {
+            let mut joined = Vec::new();
+            for (row, diff) in stream {
+                let Some(key) = eval_join_key(&stage.stream_key, &row)? else {
+                    continue;
+                };
+                let row = thin_row(&stage.stream_thinning, row)?;
+                for (lookup_row, lookup_diff) in index.get(&key, now) {
+                    if let Some(output) = join_rows(&stage.closure, &row, &lookup_row)? {
+                        joined.push((output, diff * lookup_diff));
+                    }
+                }
+            }
+            stream = joined;
+        }
+        let Some(closure) = &self.plan.final_closure else {
+            return Ok(stream);
+        };
+        let mut output = Vec::with_capacity(stream.len());
+        for (row, diff) in stream {
+            if let Some(row) = apply_join_filter(closure, row.inner)? {
+                output.push((row, diff));
+            }
+        }
+        Ok(output)
+    }
+}
+
+/// State of a binary join stage, which index both sides by their join key
+///
+/// Updates from both sides in the same tick are joined as `d(S ⋈ L) = dS ⋈ L + S' ⋈ dL`,
+/// where `S'` is the stream side after applying `dS`
+///
+/// For outer join, the null padded rows of every key touched in this tick are computed
+/// before and after applying the updates, and the difference is output, so a padded row
+/// is retracted once a matching row arrives on the other side(and output again once all
+/// matching rows are retracted). Semi and anti join are maintained in the same way,
+/// see [`JoinStage::derived_rows`]
+///
+/// Outputs keep the timestamp of the update producing them
+struct JoinStage {
+    plan: LinearStagePlan,
+    /// the closure without its predicates, used to map and project outer join outputs
+    /// whose matching is already decided by the full closure
+    output_closure: SafeMfpPlan,
+    stream_index: JoinIndex,
+    lookup_index: JoinIndex,
+    /// whether the lookup index is shared with other stages and maintained by its own subgraph,
+    /// see [`Context::share_lookup_index`], so this stage only reads it
+    shared_lookup: bool,
+}
+
+impl JoinStage {
+    fn new(plan: LinearStagePlan, stream_index: JoinIndex, lookup_index: JoinIndex) -> Self {
+        let mut output_closure = plan.closure.before.clone();
+        output_closure.mfp.predicates.clear();
+        Self {
+            plan,
+            output_closure,
+            stream_index,
+            lookup_index,
+            shared_lookup: false,
+        }
+    }
+
+    fn apply_updates(
+        &mut self,
+        stream_updates: impl IntoIterator<Item = DiffRow>,
+        lookup_updates: impl IntoIterator<Item = DiffRow>,
+        now: repr::Timestamp,
+        err_collector: &ErrCollector,
+    ) -> Vec<DiffRow> {
+        let mut output = Vec::new();
+
+        let stream_updates = stream_updates
+            .into_iter()
+            .filter_map(|(row, ts, diff)| {
+                err_collector.run(|| {
+                    let key = eval_join_key(&self.plan.stream_key, &row)?;
+                    let row = thin_row(&self.plan.stream_thinning, row)?;
+                    Ok((key, row, ts, diff))
+                })
+            })
+            .filter(|(key, row, _, _)| {
+                !is_expired(&self.stream_index, key, row, now, err_collector)
+            })
+            .collect_vec();
+        let lookup_updates = lookup_updates
+            .into_iter()
+            .filter_map(|(row, ts, diff)| {
+                err_collector.run(|| {
+                    let key = eval_join_key(&self.plan.lookup_key, &row)?;
+                    Ok((key, row, ts, diff))
+                })
+            })
+            .filter(|(key, row, _, _)| {
+                !is_expired(&self.lookup_index, key, row, now, err_collector)
+            })
+            .collect_vec();
+
+        // derived rows of touched keys before applying updates, with the latest timestamp of updates to the key
+        let mut derived_before = BTreeMap::new();
+        if self.plan.kind != JoinKind::Inner {
+            let touched_keys = stream_updates
+                .iter()
+                .chain(lookup_updates.iter())
+                .filter_map(|(key, _, ts, _)| key.as_ref().map(|key| (key, *ts)));
+            for (key, ts) in touched_keys {
+                if let Some((_, latest)) = derived_before.get_mut(key) {
+                    *latest = ts.max(*latest);
+                } else if let Some(derived) = err_collector.run(|| self.derived_rows(key, now)) {
+                    derived_before.insert(key.clone(), (derived, ts));
+                }
+            }
+        }
+
+        if self.shared_lookup {
+            // the shared index already has the lookup updates applied, i.e. it's `L'` instead of `L`,
+            // so join as `d(S ⋈ L) = S ⋈ dL + dS ⋈ L'` instead
+            self.join_lookup_updates(lookup_updates, now, err_collector, &mut output);
+            self.join_stream_updates(stream_updates, now, err_collector, &mut output);
+        } else {
+            self.join_stream_updates(stream_updates, now, err_collector, &mut output);
+            self.join_lookup_updates(lookup_updates, now, err_collector, &mut output);
+        }
+
+        for (key, (before, ts)) in derived_before {
+            let Some(after) = err_collector.run(|| self.derived_rows(&key, now)) else {
+                continue;
+            };
+            let mut changes: BTreeMap<Row, Diff> = BTreeMap::new();
+            for (row, diff) in before {
+                *changes.entry(row).or_default() -= diff;
+            }
+            for (row, diff) in after {
+                *changes.entry(row).or_default() += diff;
+            }
+            output.extend(
+                changes
+                    .into_iter()
+                    .filter(|(_, diff)| *diff != 0)
+                    .map(|(row, diff)| (row, ts, diff)),
+            );
+        }
+
+        err_collector.run(|| self.stream_index.compact_to(now));
+        if !self.shared_lookup {
+            err_collector.run(|| self.lookup_index.compact_to(now));
+        }
+
+        output
+    }
+
+    /// Join stream updates with the lookup index, then apply them to the stream index
+    fn join_stream_updates(
+        &self,
+        updates: Vec<(Option<Row>, Row, repr::Timestamp, Diff)>,
+        now: repr::Timestamp,
+        err_collector: &ErrCollector,
+        output: &mut Vec<DiffRow>,
+    ) {
+        for (key, row, ts, diff) in updates {
+            let Some(key) = key else {
+                // a null key never match anything
+                if self.plan.kind.pad_stream() {
+                    err_collector.run(|| {
+                        output.extend(
+                            self.output_row(self.pad_stream_row(&row))?
+                                .map(|row| (row, ts, diff)),
+                        );
+                        Ok(())
+                    });
+                } else if self.plan.kind == JoinKind::Anti {
+                    output.push((row, ts, diff));
+                }
+                continue;
+            };
+            err_collector.run(|| {
+                if self.plan.kind.output_pairs() {
+                    for (lookup_row, lookup_diff) in self.lookup_index.get(&key, now) {
+                        if let Some(joined) = self.join_pair(&row, &lookup_row)? {
+                            output.push((joined, ts, diff * lookup_diff));
+                        }
+                    }
+                }
+                self.stream_index.update(&key, &row, ts, diff, now)
+            });
+        }
+    }
+
+    /// Join lookup updates with the stream index, then apply them to the lookup index if it's
+    /// not shared
+    fn join_lookup_updates(
+        &self,
+        updates: Vec<(Option<Row>, Row, repr::Timestamp, Diff)>,
+        now: repr::Timestamp,
+        err_collector: &ErrCollector,
+        output: &mut Vec<DiffRow>,
+    ) {
+        for (key, row, ts, diff) in updates {
+            let Some(key) = key else {
+                if self.plan.kind.pad_lookup() {
+                    err_collector.run(|| {
+                        output.extend(
+                            self.output_row(self.pad_lookup_row(&row))?
+                                .map(|row| (row, ts, diff)),
+                        );
+                        Ok(())
+                    });
+                }
+                continue;
+            };
+            err_collector.run(|| {
+                if self.plan.kind.output_pairs() {
+                    for (stream_row, stream_diff) in self.stream_index.get(&key, now) {
+                        if let Some(joined) = self.join_pair(&stream_row, &row)? {
+                            output.push((joined, ts, stream_diff * diff));
+                        }
+                    }
+                }
+                if self.shared_lookup {
+                    return Ok(());
+                }
+                self.lookup_index.update(&key, &row, ts, diff, now)
+            });
+        }
+    }
+
+    /// Join a pair of rows with the same key, return `None` if the closure filter out the joined row
+    fn join_pair(&self, stream_row: &Row, lookup_row: &Row) -> Result<Option<Row>, EvalError> {
+        join_rows(&self.plan.closure, stream_row, lookup_row)
+    }
+
+    /// Map and project a (padded) row of an outer join with the closure, without its predicates
+    fn output_row(&self, row: Row) -> Result<Option<Row>, EvalError> {
+        self.output_closure
+            .evaluate_into(&mut row.unpack(), &mut Row::empty())
+    }
+
+    /// Compute the output of the given key that is not a simple pair of joined rows, i.e.
+    /// - for outer join, the null padded rows which can't find any matching row on the other side
+    /// - for semi(anti) join, the stream rows which can(can't) find any matching row on the lookup side
+    fn derived_rows(&self, key: &Row, now: repr::Timestamp) -> Result<Vec<(Row, Diff)>, EvalError> {
+        let mut ret = Vec::new();
+        let stream_rows = self.stream_index.get(key, now);
+        let lookup_rows = self.lookup_index.get(key, now);
+        if matches!(self.plan.kind, JoinKind::Semi | JoinKind::Anti) {
+            let want_match = self.plan.kind == JoinKind::Semi;
+            for (stream_row, stream_diff) in stream_rows {
+                let matched = has_match(&lookup_rows, |lookup_row| {
+                    self.join_pair(&stream_row, lookup_row)
+                })?;
+                if matched == want_match {
+                    ret.push((stream_row, stream_diff));
+                }
+            }
+            return Ok(ret);
+        }
+        if self.plan.kind.pad_stream() {
+            for (stream_row, stream_diff) in &stream_rows {
+                if !has_match(&lookup_rows, |lookup_row| {
+                    self.join_pair(stream_row, lookup_row)
+                })? {
+                    if let Some(row) = self.output_row(self.pad_stream_row(stream_row))? {
+                        ret.push((row, *stream_diff));
+                    }
+                }
+            }
+        }
+        if self.plan.kind.pad_lookup() {
+            for (lookup_row, lookup_diff) in &lookup_rows {
+                if !has_match(&stream_rows, |stream_row| {
+                    self.join_pair(stream_row, lookup_row)
+                })? {
+                    if let Some(row) = self.output_row(self.pad_lookup_row(lookup_row))? {
+                        ret.push((row, *lookup_diff));
+                    }
+                }
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Pad a (thinned) stream row with nulls for the lookup columns
+    fn pad_stream_row(&self, row: &Row) -> Row {
+        let nulls = std::iter::repeat(Value::Null).take(self.plan.lookup_arity);
+        Row::new(row.iter().cloned().chain(nulls).collect())
+    }
+
+    /// Pad a lookup row with nulls for the retained stream columns
+    fn pad_lookup_row(&self, row: &Row) -> Row {
+        let nulls = std::iter::repeat(Value::Null).take(self.plan.stream_thinning.len());
+        Row::new(nulls.chain(row.iter().cloned()).collect())
+    }
+}
+
+/// Check if the row of a join side is already expired, expired rows are ignored like in reduce
+fn is_expired(
+    index: &JoinIndex,
+    key: &Option<Row>,
+    row: &Row,
+    now: repr::Timestamp,
+    err_collector: &ErrCollector,
+) -> bool {
+    let Some(key) = key else {
+        // rows with null key are not kept in the state
+        return false;
+    };
+    let Some(expired_by) = err_collector
+        .run(|| index.expired_by(key, row, now))
+        .flatten()
+    else {
+        return false;
+    };
+    err_collector.push_late_row(key, expired_by);
+    true
+}
+
+/// Check if any of `candidates` with positive multiplicity can be joined
+fn has_match(
+    candidates: &[(Row, Diff)],
+    mut join: impl FnMut(&Row) -> Result<Option<Row>, EvalError>,
+) -> Result<bool, EvalError> {
+    candidates.iter().try_fold(false, |matched, (row, diff)| {
+        if matched || *diff <= 0 {
+            return Ok(matched);
+        }
+        Ok(join(row)?.is_some())
+    })
+}
+
+/// Evaluate the join key of a row, return `None` if any part of the key is null,
+/// since null never equals to anything in a equi-join
+fn eval_join_key(key: &[ScalarExpr], row: &Row) -> Result<Option<Row>, EvalError> {
+    let mut ret = Vec::with_capacity(key.len());
+    for expr in key {
+        let val = expr.eval(&row.inner)?;
+        if val.is_null() {
+            return Ok(None);
+        }
+        ret.push(val);
+    }
+    Ok(Some(Row::new(ret)))
+}
+
+/// Only retain the given columns of the row
+fn thin_row(thinning: &[usize], row: Row) -> Result<Row, EvalError> {
+    let values = thinning
+        .iter()
+        .map(|idx| {
+            row.get(*idx).cloned().with_context(|| InternalSnafu {
+                reason: format!("Index {} out of bound for row {:?}", idx, row),
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Row::new(values))
+}
+
+fn join_rows(
+    closure: &JoinFilter,
+    stream_row: &Row,
+    lookup_row: &Row,
+) -> Result<Option<Row>, EvalError> {
+    let values = stream_row
+        .iter()
+        .chain(lookup_row.iter())
+        .cloned()
+        .collect_vec();
+    apply_join_filter(closure, values)
+}
+
+/// Check the `ready_equivalences` and then apply the mfp in `filter`, return `None` if the row is filtered out
+fn apply_join_filter(
+    filter: &JoinFilter,
+    mut values: Vec<Value>,
+) -> Result<Option<Row>, EvalError> {
+    for equivalence in &filter.ready_equivalences {
+        let mut evaluated = equivalence.iter().map(|expr| expr.eval(&values));
+        let Some(first) = evaluated.next().transpose()? else {
+            continue;
+        };
+        if first.is_null() {
+            return Ok(None);
+        }
+        for val in evaluated {
+            if val? != first {
+                return Ok(None);
+            }
+        }
+    }
+    filter.before.evaluate_into(&mut values, &mut Row::empty())
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::collections::BTreeMap;
+    use std::rc::Rc;
+
+    use datatypes::data_type::ConcreteDataType;
+    use hydroflow::scheduled::graph::Hydroflow;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::compute::render::test::{get_output_handle, harness_test_ctx, run_and_check};
+    use crate::compute::state::DataflowState;
+    use crate::expr::{self, BinaryFunc, GlobalId, MapFilterProject};
+    use crate::plan::Plan;
+    use crate::repr::{ColumnType, RelationType};
+    use crate::utils::Arrangement;
+
+    fn get_plan(id: u64, typ: Vec<ConcreteDataType>) -> TypedPlan {
+        let typ = RelationType::new(typ.into_iter().map(ColumnType::new_nullable).collect());
+        Plan::Get {
+            id: expr::Id::Global(GlobalId::User(id)),
+        }
+        .with_types(typ.into_unnamed())
+    }
+
+    /// join `(id, name)` with `(id, value)` on `id` and keep rows with `value > 5`
+    #[test]
+    fn test_render_inner_join() {
+        let mut df = Hydroflow::new();
+        let mut state = DataflowState::default();
+        let mut ctx = harness_test_ctx(&mut df, &mut state);
+
+        let left_rows = vec![
+            (Row::new(vec![1i64.into(), "a".into()]), 0, 1),
+            (Row::new(vec![Value::Null, "null".into()]), 0, 1),
+            (Row::new(vec![2i64.into(), "b".into()]), 1, 1),
+            (Row::new(vec![1i64.into(), "a".into()]), 3, -1),
+        ];
+        let right_rows = vec![
+            (Row::new(vec![1i64.into(), 10i64.into()]), 0, 1),
+            (Row::new(vec![Value::Null, 10i64.into()]), 0, 1),
+            (Row::new(vec![2i64.into(), 1i64.into()]), 1, 1),
+            (Row::new(vec![1i64.into(), 20i64.into()]), 2, 1),
+            (Row::new(vec![2i64.into(), 30i64.into()]), 2, 1),
+        ];
+        let left = ctx.render_constant(left_rows);
+        let right = ctx.render_constant(right_rows);
+        ctx.insert_global(GlobalId::User(1), left);
+        ctx.insert_global(GlobalId::User(2), right);
+
+        let left = get_plan(
+            1,
+            vec![
+                ConcreteDataType::int64_datatype(),
+                ConcreteDataType::string_datatype(),
+            ],
+        );
+        let right = get_plan(
+            2,
+            vec![
+                ConcreteDataType::int64_datatype(),
+                ConcreteDataType::int64_datatype(),
+            ],
+        );
+        let schema = left.schema.clone().concat(right.schema.clone());
+        let join_plan = JoinPlan::Linear(LinearJoinPlan {
+            source_relation: 0,
+            source_key: Some(vec![ScalarExpr::Column(0)]),
+            initial_closure: None,
+            stage_plans: vec![LinearStagePlan {
+                lookup_relation: 1,
+                stream_key: vec![ScalarExpr::Column(0)],
+                stream_thinning: vec![0, 1],
+                lookup_key: vec![ScalarExpr::Column(0)],
+                lookup_arity: 2,
+                closure: JoinFilter {
+                    ready_equivalences: vec![],
+                    before: MapFilterProject::new(4)
+                        .filter(vec![ScalarExpr::Column(3).call_binary(
+                            ScalarExpr::literal(5i64.into(), ConcreteDataType::int64_datatype()),
+                            BinaryFunc::Gt,
+                        )])
+                        .unwrap()
+                        .project(vec![1, 3])
+                        .unwrap()
+                        .into_safe(),
+                },
+                kind: JoinKind::Inner,
+            }],
+            final_closure: None,
+        });
+        let join = Plan::Join {
+            inputs: vec![left, right],
+            plan: join_plan,
+        }
+        .with_types(schema);
+
+        let bundle = ctx
+            .render_mfp(Box::new(join), MapFilterProject::new(2))
+            .unwrap();
+        let output = get_output_handle(&mut ctx, bundle);
+        drop(ctx);
+
+        let expected = BTreeMap::from([
+            (0, vec![(Row::new(vec!["a".into(), 10i64.into()]), 0, 1)]),
+            (
+                2,
+                vec![
+                    (Row::new(vec!["a".into(), 20i64.into()]), 2, 1),
+                    (Row::new(vec!["b".into(), 30i64.into()]), 2, 1),
+                ],
+            ),
+            (
+                3,
+                vec![
+                    (Row::new(vec!["a".into(), 10i64.into()]), 3, -1),
+                    (Row::new(vec!["a".into(), 20i64.into()]), 3, -1),
+                ],
+            ),
+        ]);
+        run_and_check(&mut state, &mut df, 0..5, expected, output);
+    }
+
+    /// join `(id, name)` with `(id, value)` on `id` twice, both stages look up the same
+    /// relation by the same key, so they share one lookup index
+    #[test]
+    fn test_render_shared_lookup_join() {
+        let mut df = Hydroflow::new();
+        let mut state = DataflowState::default();
+        let mut ctx = harness_test_ctx(&mut df, &mut state);
+
+        let left_rows = vec![(Row::new(vec![1i64.into(), "a".into()]), 0, 1)];
+        let right_rows = vec![
+            (Row::new(vec![1i64.into(), 10i64.into()]), 0, 1),
+            (Row::new(vec![1i64.into(), 20i64.into()]), 2, 1),
+        ];
+        let left = ctx.render_constant(left_rows);
+        let right = ctx.render_constant(right_rows);
+        ctx.insert_global(GlobalId::User(1), left);
+        ctx.insert_global(GlobalId::User(2), right);
+
+        let types = |typ: ConcreteDataType| vec![ConcreteDataType::int64_datatype(), typ];
+        let left = get_plan(1, types(ConcreteDataType::string_datatype()));
+        let right = get_plan(2, types(ConcreteDataType::int64_datatype()));
+        let schema = left
+            .schema
+            .clone()
+            .concat(right.schema.clone())
+            .concat(right.schema.clone());
+        let stage = |lookup_relation, stream_arity: usize, project| LinearStagePlan {
+            lookup_relation,
+            stream_key: vec![ScalarExpr::Column(0)],
+            stream_thinning: (0..stream_arity).collect(),
+            lookup_key: vec![ScalarExpr::Column(0)],
+            lookup_arity: 2,
+            closure: JoinFilter {
+                ready_equivalences: vec![],
+                before: MapFilterProject::new(stream_arity + 2)
+                    .project(project)
+                    .unwrap()
+                    .into_safe(),
+            },
+            kind: JoinKind::Inner,
+        };
+        let join_plan = JoinPlan::Linear(LinearJoinPlan {
+            source_relation: 0,
+            source_key: Some(vec![ScalarExpr::Column(0)]),
+            initial_closure: None,
+            // `(id, name, value)` after the first stage, `(name, value, value)` after the second
+            stage_plans: vec![stage(1, 2, vec![0, 1, 3]), stage(2, 3, vec![1, 2, 4])],
+            final_closure: None,
+        });
+        let join = Plan::Join {
+            inputs: vec![left, right.clone(), right],
+            plan: join_plan,
+        }
+        .with_types(schema);
+
+        let bundle = ctx.render_plan(join).unwrap();
+        let output = Rc::new(RefCell::new(vec![]));
+        let output_inner = output.clone();
+        let _subgraph = ctx.df.add_subgraph_sink(
+            "test_output",
+            bundle.collection.into_inner(),
+            move |_ctx, recv| {
+                let data = recv.take_inner().into_iter().flat_map(|v| v.into_iter());
+                output_inner.borrow_mut().extend(data);
+            },
+        );
+        drop(ctx);
+
+        let row = |name: &str, v1: i64, v2: i64| Row::new(vec![name.into(), v1.into(), v2.into()]);
+        let expected = BTreeMap::from([
+            (0, vec![(row("a", 10, 10), 0, 1)]),
+            (
+                2,
+                vec![
+                    (row("a", 10, 20), 2, 1),
+                    (row("a", 20, 10), 2, 1),
+                    (row("a", 20, 20), 2, 1),
+                ],
+            ),
+        ]);
+        for now in 0..4 {
+            state.set_current_ts(now);
+            state.run_available_with_schedule(&mut df);
+            assert!(state.get_err_collector().is_empty());
+            let mut output = std::mem::take(&mut *output.borrow_mut());
+            output.sort();
+            assert_eq!(output, expected.get(&now).cloned().unwrap_or_default());
+        }
+        // two stream indexes and one shared lookup index
+        assert_eq!(state.checkpoint().unwrap().arrangements.len(), 3);
+    }
+
+    /// join `(id, name)` with `(id, value)` on `id` with given join kind and join condition
+    fn outer_stage(kind: JoinKind, filter: Vec<ScalarExpr>) -> LinearStagePlan {
+        LinearStagePlan {
+            lookup_relation: 1,
+            stream_key: vec![ScalarExpr::Column(0)],
+            stream_thinning: vec![0, 1],
+            lookup_key: vec![ScalarExpr::Column(0)],
+            lookup_arity: 2,
+            closure: JoinFilter {
+                ready_equivalences: vec![],
+                before: MapFilterProject::new(4).filter(filter).unwrap().into_safe(),
+            },
+            kind,
+        }
+    }
+
+    /// left join `(id, name)` with `(id, value)`, with the right side arriving both before and after the left side
+    #[test]
+    fn test_render_left_join() {
+        let mut df = Hydroflow::new();
+        let mut state = DataflowState::default();
+        let mut ctx = harness_test_ctx(&mut df, &mut state);
+
+        let left_rows = vec![
+            (Row::new(vec![1i64.into(), "a".into()]), 0, 1),
+            (Row::new(vec![Value::Null, "n".into()]), 0, 1),
+            (Row::new(vec![2i64.into(), "b".into()]), 3, 1),
+            (Row::new(vec![1i64.into(), "a".into()]), 4, -1),
+        ];
+        let right_rows = vec![
+            (Row::new(vec![1i64.into(), 10i64.into()]), 1, 1),
+            (Row::new(vec![2i64.into(), 20i64.into()]), 2, 1),
+            (Row::new(vec![1i64.into(), 10i64.into()]), 3, -1),
+        ];
+        let left = ctx.render_constant(left_rows);
+        let right = ctx.render_constant(right_rows);
+        ctx.insert_global(GlobalId::User(1), left);
+        ctx.insert_global(GlobalId::User(2), right);
+
+        let left = get_plan(
+            1,
+            vec![
+                ConcreteDataType::int64_datatype(),
+                ConcreteDataType::string_datatype(),
+            ],
+        );
+        let right = get_plan(
+            2,
+            vec![
+                ConcreteDataType::int64_datatype(),
+                ConcreteDataType::int64_datatype(),
+            ],
+        );
+        let schema = left.schema.clone().concat(right.schema.clone());
+        let join_plan = JoinPlan::Linear(LinearJoinPlan {
+            source_relation: 0,
+            source_key: Some(vec![ScalarExpr::Column(0)]),
+            initial_closure: None,
+            stage_plans: vec![outer_stage(JoinKind::Left, vec![])],
+            final_closure: None,
+        });
+        let join = Plan::Join {
+            inputs: vec![left, right],
+            plan: join_plan,
+        }
+        .with_types(schema);
+
+        let bundle = ctx
+            .render_mfp(Box::new(join), MapFilterProject::new(4))
+            .unwrap();
+        let output = get_output_handle(&mut ctx, bundle);
+        drop(ctx);
+
+        let row =
+            |id: Value, name: Value, id2: Value, value: Value| Row::new(vec![id, name, id2, value]);
+        let expected = BTreeMap::from([
+            (
+                0,
+                vec![
+                    (row(Value::Null, "n".into(), Value::Null, Value::Null), 0, 1),
+                    (row(1i64.into(), "a".into(), Value::Null, Value::Null), 0, 1),
+                ],
+            ),
+            // matching row arrives, retract the null padded row
+            (
+                1,
+                vec![
+                    (
+                        row(1i64.into(), "a".into(), 1i64.into(), 10i64.into()),
+                        1,
+                        1,
+                    ),
+                    (
+                        row(1i64.into(), "a".into(), Value::Null, Value::Null),
+                        1,
+                        -1,
+                    ),
+                ],
+            ),
+            // matching row is retracted, output the null padded row again
+            (
+                3,
+                vec![
+                    (
+                        row(2i64.into(), "b".into(), 2i64.into(), 20i64.into()),
+                        3,
+                        1,
+                    ),
+                    (
+                        row(1i64.into(), "a".into(), 1i64.into(), 10i64.into()),
+                        3,
+                        -1,
+                    ),
+                    (row(1i64.into(), "a".into(), Value::Null, Value::Null), 3, 1),
+                ],
+            ),
+            (
+                4,
+                vec![(
+                    row(1i64.into(), "a".into(), Value::Null, Value::Null),
+                    4,
+                    -1,
+                )],
+            ),
+        ]);
+        run_and_check(&mut state, &mut df, 0..5, expected, output);
+    }
+
+    /// inner join `left` with `right` on `left.l = right.r`, planned like a `JoinRel`
+    fn inner_join(left: TypedPlan, right: TypedPlan, l: usize, r: usize) -> TypedPlan {
+        let left_arity = left.schema.typ().column_types.len();
+        let right_arity = right.schema.typ().column_types.len();
+        let columns = left
+            .schema
+            .typ()
+            .column_types
+            .iter()
+            .chain(right.schema.typ().column_types.iter())
+            .cloned()
+            .collect();
+        let join_plan = JoinPlan::Linear(LinearJoinPlan {
+            source_relation: 0,
+            source_key: None,
+            initial_closure: None,
+            stage_plans: vec![LinearStagePlan {
+                lookup_relation: 1,
+                stream_key: vec![ScalarExpr::Column(l)],
+                stream_thinning: (0..left_arity).collect(),
+                lookup_key: vec![ScalarExpr::Column(r)],
+                lookup_arity: right_arity,
+                closure: JoinFilter {
+                    ready_equivalences: vec![],
+                    before: MapFilterProject::new(left_arity + right_arity).into_safe(),
+                },
+                kind: JoinKind::Inner,
+            }],
+            final_closure: None,
+        });
+        Plan::Join {
+            inputs: vec![left, right],
+            plan: join_plan,
+        }
+        .with_types(RelationType::new(columns).into_unnamed())
+    }
+
+    /// join `(id, name)` with `(id, cid)` on `id` and then with `(cid, value)` on `cid`, which
+    /// is optimized into a delta join
+    #[test]
+    fn test_render_delta_join() {
+        let mut df = Hydroflow::new();
+        let mut state = DataflowState::default();
+        let mut ctx = harness_test_ctx(&mut df, &mut state);
+
+        let a_rows = vec![
+            (Row::new(vec![1i64.into(), "a".into()]), 0, 1),
+            (Row::new(vec![1i64.into(), "a".into()]), 2, -1),
+        ];
+        let b_rows = vec![
+            (Row::new(vec![1i64.into(), 10i64.into()]), 0, 1),
+            (Row::new(vec![Value::Null, 10i64.into()]), 0, 1),
+        ];
+        let c_rows = vec![
+            (Row::new(vec![10i64.into(), 100i64.into()]), 0, 1),
+            (Row::new(vec![10i64.into(), 200i64.into()]), 1, 1),
+        ];
+        for (id, rows) in [(1, a_rows), (2, b_rows), (3, c_rows)] {
+            let bundle = ctx.render_constant(rows);
+            ctx.insert_global(GlobalId::User(id), bundle);
+        }
+
+        let int = ConcreteDataType::int64_datatype;
+        let a = get_plan(1, vec![int(), ConcreteDataType::string_datatype()]);
+        let b = get_plan(2, vec![int(), int()]);
+        let c = get_plan(3, vec![int(), int()]);
+        let join = inner_join(inner_join(a, b, 0, 0), c, 3, 0)
+            .optimize()
+            .unwrap();
+        assert!(matches!(
+            join.plan,
+            Plan::Join {
+                plan: JoinPlan::Delta(_),
+                ..
+            }
+        ));
+
+        let bundle = ctx
+            .render_mfp(Box::new(join), MapFilterProject::new(6))
+            .unwrap();
+        let output = get_output_handle(&mut ctx, bundle);
+        drop(ctx);
+
+        let joined = |value: i64| {
+            Row::new(vec![
+                1i64.into(),
+                "a".into(),
+                1i64.into(),
+                10i64.into(),
+                10i64.into(),
+                value.into(),
+            ])
+        };
+        // all inputs arrive at the same tick, the joined row is output exactly once
+        let expected = BTreeMap::from([
+            (0, vec![(joined(100), 0, 1)]),
+            (1, vec![(joined(200), 1, 1)]),
+            (2, vec![(joined(100), 2, -1), (joined(200), 2, -1)]),
+        ]);
+        run_and_check(&mut state, &mut df, 0..4, expected, output);
+    }
+
+    /// create a join stage with both sides indexed in full arrangements without expiry
+    fn new_stage(plan: LinearStagePlan) -> JoinStage {
+        let new_index = |key_arity| {
+            let arrange = ArrangeHandler::from(Arrangement::default());
+            arrange.set_full_arrangement(true);
+            JoinIndex::new(arrange, key_arity)
+        };
+        let stream_index = new_index(plan.stream_key.len());
+        let lookup_index = new_index(plan.lookup_key.len());
+        JoinStage::new(plan, stream_index, lookup_index)
+    }
+
+    /// left join `(id, name)` with `(id, value)` on `id`, and project `name` and `value` out,
+    /// the projection applies to both matched and null padded rows
+    #[test]
+    fn test_left_join_stage_project() {
+        let err_collector = ErrCollector::default();
+        let mut plan = outer_stage(JoinKind::Left, vec![]);
+        plan.closure.before = MapFilterProject::new(4)
+            .project(vec![1, 3])
+            .unwrap()
+            .into_safe();
+        let mut stage = new_stage(plan);
+        let row = |name: &str, value: Value| Row::new(vec![name.into(), value]);
+
+        let output = stage.apply_updates(
+            vec![(Row::new(vec![1i64.into(), "a".into()]), 1, 1)],
+            vec![],
+            2,
+            &err_collector,
+        );
+        // output keep the timestamp of input
+        assert_eq!(output, vec![(row("a", Value::Null), 1, 1)]);
+
+        let output = stage.apply_updates(
+            vec![],
+            vec![(Row::new(vec![1i64.into(), 10i64.into()]), 3, 1)],
+            3,
+            &err_collector,
+        );
+        assert_eq!(
+            output,
+            vec![
+                (row("a", 10i64.into()), 3, 1),
+                (row("a", Value::Null), 3, -1)
+            ]
+        );
+        assert!(err_collector.is_empty());
+    }
+
+    /// rows that are already expired are not joined and not kept in the state
+    #[test]
+    fn test_join_stage_expire() {
+        let err_collector = ErrCollector::default();
+        let plan = outer_stage(JoinKind::Inner, vec![]);
+        let new_index = |key_arity| {
+            let arrange = ArrangeHandler::from(Arrangement::default());
+            arrange.set_full_arrangement(true);
+            // expire by the `id` column, which is the first column after the join key
+            arrange.write().set_expire_state(KeyExpiryManager::new(
+                Some(10),
+                Some(ScalarExpr::Column(key_arity)),
+            ));
+            JoinIndex::new(arrange, key_arity)
+        };
+        let mut stage = JoinStage::new(plan, new_index(1), new_index(1));
+        let left = |id: i64| (Row::new(vec![id.into(), "a".into()]), 0, 1);
+        let right = |id: i64| (Row::new(vec![id.into(), 1i64.into()]), 0, 1);
+
+        assert_eq!(
+            stage.apply_updates(vec![left(1), left(20)], vec![], 20, &err_collector),
+            vec![]
+        );
+        let output = stage.apply_updates(vec![], vec![right(1), right(20)], 20, &err_collector);
+        assert_eq!(
+            output,
+            vec![(
+                Row::new(vec![20i64.into(), "a".into(), 20i64.into(), 1i64.into()]),
+                0,
+                1
+            )]
+        );
+        let (rows, _) = stage.stream_index.arrange.read().state_size();
+        assert_eq!(rows, 1);
+        assert!(err_collector.is_empty());
+    }
+
+    /// full join `(id, name)` with `(id, value)` on `id` and `value > 15`
+    #[test]
+    fn test_full_join_stage() {
+        let err_collector = ErrCollector::default();
+        let mut stage = new_stage(outer_stage(
+            JoinKind::Full,
+            vec![ScalarExpr::Column(3).call_binary(
+                ScalarExpr::literal(15i64.into(), ConcreteDataType::int64_datatype()),
+                BinaryFunc::Gt,
+            )],
+        ));
+        let row =
+            |id: Value, name: Value, id2: Value, value: Value| Row::new(vec![id, name, id2, value]);
+        let left = |id: i64, name: &str| (Row::new(vec![id.into(), name.into()]), 0, 1);
+        let right = |id: i64, value: i64| (Row::new(vec![id.into(), value.into()]), 0, 1);
+
+        // both sides are unmatched
+        let output = stage.apply_updates(vec![left(1, "a")], vec![right(2, 20)], 0, &err_collector);
+        assert_eq!(
+            output,
+            vec![
+                (row(1i64.into(), "a".into(), Value::Null, Value::Null), 0, 1),
+                (
+                    row(Value::Null, Value::Null, 2i64.into(), 20i64.into()),
+                    0,
+                    1
+                ),
+            ]
+        );
+
+        // same key but filtered out by join condition, both rows stay unmatched
+        let output = stage.apply_updates(vec![], vec![right(1, 10)], 1, &err_collector);
+        assert_eq!(
+            output,
+            vec![(
+                row(Value::Null, Value::Null, 1i64.into(), 10i64.into()),
+                1,
+                1
+            )]
+        );
+
+        // left side arrives after right side, retract the null padded right row
+        let output = stage.apply_updates(vec![left(2, "b")], vec![], 2, &err_collector);
+        assert_eq!(
+            output,
+            vec![
+                (
+                    row(2i64.into(), "b".into(), 2i64.into(), 20i64.into()),
+                    2,
+                    1
+                ),
+                (
+                    row(Value::Null, Value::Null, 2i64.into(), 20i64.into()),
+                    2,
+                    -1
+                ),
+            ]
+        );
+        assert!(err_collector.is_empty());
+    }
+
+    /// semi and anti join `(id, name)` with `(id, value)` on `id`
+    #[test]
+    fn test_semi_anti_join_stage() {
+        let err_collector = ErrCollector::default();
+        let mut semi = new_stage(outer_stage(JoinKind::Semi, vec![]));
+        let mut anti = new_stage(outer_stage(JoinKind::Anti, vec![]));
+        let left =
+            |id: i64, name: &str, diff: Diff| (Row::new(vec![id.into(), name.into()]), 0, diff);
+        let right =
+            |id: i64, value: i64, diff: Diff| (Row::new(vec![id.into(), value.into()]), 0, diff);
+        let name = |id: i64, name: &str| Row::new(vec![id.into(), name.into()]);
+
+        let stream = vec![left(1, "a", 1), left(2, "b", 1)];
+        let lookup = vec![right(1, 10, 1), right(1, 11, 1)];
+        assert_eq!(
+            semi.apply_updates(stream.clone(), lookup.clone(), 0, &err_collector),
+            vec![(name(1, "a"), 0, 1)]
+        );
+        assert_eq!(
+            anti.apply_updates(stream, lookup, 0, &err_collector),
+            vec![(name(2, "b"), 0, 1)]
+        );
+
+        // retracting one of the matching rows does not change the output
+        let lookup = vec![right(1, 10, -1)];
+        assert_eq!(
+            semi.apply_updates(vec![], lookup.clone(), 1, &err_collector),
+            vec![]
+        );
+        assert_eq!(
+            anti.apply_updates(vec![], lookup, 1, &err_collector),
+            vec![]
+        );
+        let lookup = vec![right(1, 11, -1), right(2, 20, 1)];
+        assert_eq!(
+            semi.apply_updates(vec![], lookup.clone(), 2, &err_collector),
+            vec![(name(1, "a"), 2, -1), (name(2, "b"), 2, 1)]
+        );
+        assert_eq!(
+            anti.apply_updates(vec![], lookup, 2, &err_collector),
+            vec![(name(1, "a"), 2, 1), (name(2, "b"), 2, -1)]
+        );
+        assert!(err_collector.is_empty());
+    }
+}
@@ -42,7 +42,8 @@ impl Context<'_, '_> {
         // This closure capture following variables:
         let mfp_plan = MfpPlan::create_from(mfp)?;
 
-        let err_collector = self.err_collector.clone();
+        let err_collector = self.err_collector.with_operator("mfp");
+        let profiler = self.compute_state.profile_operator("mfp_batch");
 
         // TODO(discord9): better way to schedule future run
         let scheduler = self.compute_state.get_scheduler();
@@ -52,11 +53,13 @@ impl Context<'_, '_> {
             input.collection.into_inner(),
             out_send_port,
             move |_ctx, recv, send| {
+                let _timer = profiler.start();
                 // mfp only need to passively receive updates from recvs
                 let src_data = recv.take_inner().into_iter().flat_map(|v| v.into_iter());
 
                 let output_batches = src_data
                     .filter_map(|mut input_batch| {
+                        profiler.add_rows(input_batch.row_count());
                         err_collector.run(|| {
                             let res_batch = mfp_plan.mfp.eval_batch_into(&mut input_batch)?;
                             Ok(res_batch)
@@ -109,7 +112,8 @@ impl Context<'_, '_> {
         let mfp_plan = MfpPlan::create_from(mfp)?;
         let now = self.compute_state.current_time_ref();
 
-        let err_collector = self.err_collector.clone();
+        let err_collector = self.err_collector.with_operator("mfp");
+        let profiler = self.compute_state.profile_operator("mfp");
 
         // TODO(discord9): better way to schedule future run
         let scheduler = self.compute_state.get_scheduler();
@@ -120,8 +124,13 @@ impl Context<'_, '_> {
             input.collection.into_inner(),
             out_send_port,
             move |_ctx, recv, send| {
+                let _timer = profiler.start();
                 // mfp only need to passively receive updates from recvs
-                let data = recv.take_inner().into_iter().flat_map(|v| v.into_iter());
+                let data = recv
+                    .take_inner()
+                    .into_iter()
+                    .flat_map(|v| v.into_iter())
+                    .inspect(|_| profiler.add_rows(1));
 
                 mfp_subgraph(
                     &arrange_handler_inner,
@@ -137,6 +146,13 @@ impl Context<'_, '_> {
 
         // register current subgraph in scheduler for future scheduling
         scheduler.set_cur_subgraph(subgraph);
+        let timed_arrange = arrange_handler
+            .clone_future_only()
+            .with_context(|| PlanSnafu {
+                reason: "No write is expected at this point",
+            })?;
+        self.compute_state
+            .register_timed_arrange(timed_arrange, subgraph);
 
         let arranged = BTreeMap::from([(
             (0..output_arity).map(ScalarExpr::Column).collect_vec(),
@@ -232,6 +248,7 @@ fn eval_mfp_core(
 ) -> Vec<KeyValDiffRow> {
     let mut all_updates = Vec::new();
     for (mut row, _sys_time, diff) in input.into_iter() {
+        let input_row = err_collector.collects_dead_letters().then(|| row.clone());
         // this updates is expected to be only zero, one or two rows
         let updates = mfp_plan.evaluate::<EvalError>(&mut row.inner, now, diff);
         // TODO(discord9): refactor error handling
@@ -240,7 +257,7 @@ fn eval_mfp_core(
             .filter_map(|r| match r {
                 Ok((key, ts, diff)) => Some(((key, Row::empty()), ts, diff)),
                 Err((err, _ts, _diff)) => {
-                    err_collector.push_err(err);
+                    err_collector.push_row_err(input_row.clone(), "mfp", err);
                     None
                 }
             })
@@ -339,6 +356,67 @@ mod test {
         run_and_check(&mut state, &mut df, 0..5, expected_output, output);
     }
 
+    /// test if a temporal filter restored from a checkpoint still schedules the deletes
+    /// of rows in it, even if the dataflow has already run before restoring
+    #[test]
+    fn test_restore_mfp_with_temporal() {
+        let typ = RelationType::new(vec![ColumnType::new_nullable(
+            ConcreteDataType::int64_datatype(),
+        )]);
+        // temporal filter: col(0) > now
+        let mfp = MapFilterProject::new(1)
+            .filter(vec![ScalarExpr::Column(0)
+                .call_unary(expr::UnaryFunc::Cast(ConcreteDataType::datetime_datatype()))
+                .call_binary(
+                    ScalarExpr::CallUnmaterializable(expr::UnmaterializableFunc::Now),
+                    BinaryFunc::Gt,
+                )])
+            .unwrap();
+        let render = |df: &mut Hydroflow<'static>, state: &mut DataflowState| {
+            let mut ctx = harness_test_ctx(df, state);
+            let (sender, recv) = tokio::sync::mpsc::unbounded_channel();
+            let collection = ctx.render_source(recv).unwrap();
+            ctx.insert_global(GlobalId::User(1), collection);
+            let input_plan = Plan::Get {
+                id: expr::Id::Global(GlobalId::User(1)),
+            };
+            let bundle = ctx
+                .render_mfp(
+                    Box::new(input_plan.with_types(typ.clone().into_unnamed())),
+                    mfp.clone(),
+                )
+                .unwrap();
+            (sender, get_output_handle(&mut ctx, bundle))
+        };
+
+        let mut df = Hydroflow::new();
+        let mut state = DataflowState::default();
+        let (sender, output) = render(&mut df, &mut state);
+        for i in 1..=2i64 {
+            sender.send((Row::new(vec![i.into()]), 0, 1)).unwrap();
+        }
+        let expected = BTreeMap::from([(
+            0,
+            vec![
+                (Row::new(vec![1i64.into()]), 0, 1),
+                (Row::new(vec![2i64.into()]), 0, 1),
+            ],
+        )]);
+        run_and_check(&mut state, &mut df, 0..1, expected, output);
+        let checkpoint = state.checkpoint().unwrap();
+
+        let mut df = Hydroflow::new();
+        let mut state = DataflowState::default();
+        let (_sender, output) = render(&mut df, &mut state);
+        run_and_check(&mut state, &mut df, 0..1, BTreeMap::new(), output.clone());
+        state.restore(checkpoint).unwrap();
+        let expected = BTreeMap::from([
+            (1, vec![(Row::new(vec![1i64.into()]), 1, -1)]),
+            (2, vec![(Row::new(vec![2i64.into()]), 2, -1)]),
+        ]);
+        run_and_check(&mut state, &mut df, 1..4, expected, output);
+    }
+
     /// test if mfp operator without temporal filter works properly
     /// that is it filter the rows correctly
     #[test]
@@ -387,7 +465,7 @@ mod test {
         let mut state = DataflowState::default();
         let mut ctx = harness_test_ctx(&mut df, &mut state);
 
-        let (sender, recv) = tokio::sync::broadcast::channel(1000);
+        let (sender, recv) = tokio::sync::mpsc::unbounded_channel();
         let collection = ctx.render_source(recv).unwrap();
         ctx.insert_global(GlobalId::User(1), collection);
         let input_plan = Plan::Get {
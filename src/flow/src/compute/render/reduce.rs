@@ -20,7 +20,7 @@ use common_telemetry::trace;
 use datatypes::data_type::ConcreteDataType;
 use datatypes::prelude::DataType;
 use datatypes::value::{ListValue, Value};
-use datatypes::vectors::{BooleanVector, NullVector};
+use datatypes::vectors::{BooleanVector, Int64Vector, NullVector};
 use hydroflow::scheduled::graph_ext::GraphExt;
 use itertools::Itertools;
 use snafu::{ensure, OptionExt, ResultExt};
@@ -28,16 +28,18 @@ use snafu::{ensure, OptionExt, ResultExt};
 use crate::compute::render::{Context, SubgraphArg};
 use crate::compute::types::{Arranged, Collection, CollectionBundle, ErrCollector, Toff};
 use crate::error::{Error, NotImplementedSnafu, PlanSnafu};
-use crate::expr::error::{ArrowSnafu, DataAlreadyExpiredSnafu, DataTypeSnafu, InternalSnafu};
-use crate::expr::{Accum, Accumulator, Batch, EvalError, ScalarExpr, VectorDiff};
-use crate::plan::{AccumulablePlan, AggrWithIndex, KeyValPlan, ReducePlan, TypedPlan};
+use crate::expr::error::{ArrowSnafu, DataTypeSnafu, InternalSnafu};
+use crate::expr::{
+    Accum, Accumulator, Batch, EvalError, Id, MapFilterProject, ScalarExpr, VectorDiff,
+};
+use crate::plan::{AccumulablePlan, AggrWithIndex, KeyValPlan, Plan, ReducePlan, TypedPlan};
 use crate::repr::{self, DiffRow, KeyValDiffRow, RelationType, Row};
 use crate::utils::{ArrangeHandler, ArrangeReader, ArrangeWriter, KeyExpiryManager};
 
 impl Context<'_, '_> {
     const REDUCE_BATCH: &'static str = "reduce_batch";
     /// Like `render_reduce`, but for batch mode, and only barebone implementation
-    /// no support for distinct aggregation for now
+    /// which only support accumulable reduce plan
     // There is a false positive in using `Vec<ScalarExpr>` as key due to `Value` have `bytes` variant
     #[allow(clippy::mutable_key_type)]
     pub fn render_reduce_batch(
@@ -48,12 +50,6 @@ impl Context<'_, '_> {
         output_type: &RelationType,
     ) -> Result<CollectionBundle<Batch>, Error> {
         let accum_plan = if let ReducePlan::Accumulable(accum_plan) = reduce_plan {
-            if !accum_plan.distinct_aggrs.is_empty() {
-                NotImplementedSnafu {
-                    reason: "Distinct aggregation is not supported in batch mode",
-                }
-                .fail()?
-            }
             accum_plan.clone()
         } else {
             NotImplementedSnafu {
@@ -62,6 +58,7 @@ impl Context<'_, '_> {
             .fail()?
         };
 
+        let expire_state = self.reduce_expire_state(&input, key_val_plan, output_type);
         let input = self.render_plan_batch(*input)?;
 
         // first assembly key&val to separate key and val columns(since this is batch mode)
@@ -73,11 +70,7 @@ impl Context<'_, '_> {
         // TODO(discord9): config global expire time from self
         let arrange_handler = self.compute_state.new_arrange(None);
 
-        if let (Some(time_index), Some(expire_after)) =
-            (output_type.time_index, self.compute_state.expire_after())
-        {
-            let expire_man =
-                KeyExpiryManager::new(Some(expire_after), Some(ScalarExpr::Column(time_index)));
+        if let Some(expire_man) = expire_state.clone() {
             arrange_handler.write().set_expire_state(expire_man);
         }
 
@@ -85,17 +78,21 @@ impl Context<'_, '_> {
         let arrange_handler_inner = arrange_handler.clone_full_arrange().context(PlanSnafu {
             reason: "No write is expected at this point",
         })?;
+        let distinct_input =
+            self.add_accum_distinct_input_arrange(reduce_plan, expire_state, output_key_arity);
         let key_val_plan = key_val_plan.clone();
 
         let now = self.compute_state.current_time_ref();
 
-        let err_collector = self.err_collector.clone();
+        let err_collector = self.err_collector.with_operator("reduce");
 
         // TODO(discord9): better way to schedule future run
         let scheduler = self.compute_state.get_scheduler();
 
         let scheduler_inner = scheduler.clone();
 
+        let profiler = self.compute_state.profile_operator(Self::REDUCE_BATCH);
+
         let (out_send_port, out_recv_port) =
             self.df.make_edge::<_, Toff<Batch>>(Self::REDUCE_BATCH);
 
@@ -104,6 +101,7 @@ impl Context<'_, '_> {
             input.collection.into_inner(),
             out_send_port,
             move |_ctx, recv, send| {
+                let _timer = profiler.start();
                 let now = *(now.borrow());
                 let arrange = arrange_handler_inner.clone();
                 // mfp only need to passively receive updates from recvs
@@ -112,9 +110,11 @@ impl Context<'_, '_> {
                     .into_iter()
                     .flat_map(|v| v.into_iter())
                     .collect_vec();
+                profiler.add_rows(src_data.iter().map(|b| b.row_count()).sum());
 
                 reduce_batch_subgraph(
                     &arrange,
+                    &distinct_input,
                     src_data,
                     &key_val_plan,
                     &accum_plan,
@@ -154,6 +154,7 @@ impl Context<'_, '_> {
         reduce_plan: ReducePlan,
         output_type: RelationType,
     ) -> Result<CollectionBundle, Error> {
+        let expire_state = self.reduce_expire_state(&input, &key_val_plan, &output_type);
         let input = self.render_plan(*input)?;
         // first assembly key&val that's ((Row, Row), tick, diff)
         // Then stream kvs through a reduce operator
@@ -164,11 +165,7 @@ impl Context<'_, '_> {
         // TODO(discord9): config global expire time from self
         let arrange_handler = self.compute_state.new_arrange(None);
 
-        if let (Some(time_index), Some(expire_after)) =
-            (output_type.time_index, self.compute_state.expire_after())
-        {
-            let expire_man =
-                KeyExpiryManager::new(Some(expire_after), Some(ScalarExpr::Column(time_index)));
+        if let Some(expire_man) = expire_state.clone() {
             arrange_handler.write().set_expire_state(expire_man);
         }
 
@@ -177,21 +174,29 @@ impl Context<'_, '_> {
             reason: "No write is expected at this point",
         })?;
 
-        let distinct_input = self.add_accum_distinct_input_arrange(&reduce_plan);
+        let distinct_input = self.add_accum_distinct_input_arrange(
+            &reduce_plan,
+            expire_state.clone(),
+            output_key_arity,
+        );
+        let distinct_count = self.add_distinct_count_arrange(&reduce_plan, expire_state);
 
         let reduce_arrange = ReduceArrange {
             output_arrange: arrange_handler_inner,
             distinct_input,
+            distinct_count,
         };
 
         let now = self.compute_state.current_time_ref();
 
-        let err_collector = self.err_collector.clone();
+        let err_collector = self.err_collector.with_operator("reduce");
 
         // TODO(discord9): better way to schedule future run
         let scheduler = self.compute_state.get_scheduler();
         let scheduler_inner = scheduler.clone();
 
+        let profiler = self.compute_state.profile_operator(Self::REDUCE);
+
         let (out_send_port, out_recv_port) = self.df.make_edge::<_, Toff>(Self::REDUCE);
 
         let subgraph = self.df.add_subgraph_in_out(
@@ -199,12 +204,14 @@ impl Context<'_, '_> {
             input.collection.into_inner(),
             out_send_port,
             move |_ctx, recv, send| {
+                let _timer = profiler.start();
                 // mfp only need to passively receive updates from recvs
                 let data = recv
                     .take_inner()
                     .into_iter()
                     .flat_map(|v| v.into_iter())
                     .collect_vec();
+                profiler.add_rows(data.len());
 
                 reduce_subgraph(
                     &reduce_arrange,
@@ -236,16 +243,67 @@ impl Context<'_, '_> {
         Ok(bundle)
     }
 
+    /// Add an arrangement to count the multiplicity of each distinct row for distinct reduce plan,
+    /// so that a row is only retracted from output when all copies of it are removed
+    ///
+    /// The arrangement shares the same expire rule as the output arrangement
+    fn add_distinct_count_arrange(
+        &mut self,
+        reduce_plan: &ReducePlan,
+        expire_state: Option<KeyExpiryManager>,
+    ) -> Option<ArrangeHandler> {
+        matches!(reduce_plan, ReducePlan::Distinct).then(|| {
+            let arr = self.compute_state.new_arrange(None);
+            arr.set_full_arrangement(true);
+            if let Some(expire_man) = expire_state {
+                arr.write().set_expire_state(expire_man);
+            }
+            arr
+        })
+    }
+
+    /// The expire state of the output arrangement of a reduce, which is keyed by the reduce's key
+    ///
+    /// Keys expire at the flow's `EXPIRE WHEN` if it can be evaluated on the key, otherwise
+    /// they're expired by the time index and `expire_after`
+    fn reduce_expire_state(
+        &self,
+        input: &TypedPlan,
+        key_val_plan: &KeyValPlan,
+        output_type: &RelationType,
+    ) -> Option<KeyExpiryManager> {
+        if let Some(expire_at) = self
+            .compute_state
+            .expire_when()
+            .and_then(|expr| expire_when_over_key(expr, input, &key_val_plan.key_plan.mfp))
+        {
+            return Some(KeyExpiryManager::new_expire_when(expire_at));
+        }
+        output_type.time_index.map(|time_index| {
+            KeyExpiryManager::new(
+                self.compute_state.expire_after(),
+                Some(ScalarExpr::Column(time_index)),
+            )
+        })
+    }
+
     /// Contrast to it name, it's for adding distinct input for
     /// accumulable reduce plan with distinct input,
     /// like `select COUNT(DISTINCT col) from table`
     ///
     /// The return value is optional a list of arrangement, which is created for distinct input, and should be the
     /// same length as the distinct aggregation in accumulable reduce plan
+    ///
+    /// Each arrangement count the multiplicity of each distinct value per group, keyed by the
+    /// group's key followed by the value, so values of a group expire with the group if the
+    /// output arrangement's expire state only depends on the key
     fn add_accum_distinct_input_arrange(
         &mut self,
         reduce_plan: &ReducePlan,
+        expire_state: Option<KeyExpiryManager>,
+        key_arity: usize,
     ) -> Option<Vec<ArrangeHandler>> {
+        let expire_state = expire_state.filter(|s| s.is_evaluated_from_prefix(key_arity));
         match reduce_plan {
             ReducePlan::Distinct => None,
             ReducePlan::Accumulable(AccumulablePlan { distinct_aggrs, .. }) => {
@@ -253,6 +311,9 @@ impl Context<'_, '_> {
                     std::iter::repeat_with(|| {
                         let arr = self.compute_state.new_arrange(None);
                         arr.set_full_arrangement(true);
+                        if let Some(expire_man) = expire_state.clone() {
+                            arr.write().set_expire_state(expire_man);
+                        }
                         arr
                     })
                     .take(distinct_aggrs.len())
@@ -282,6 +343,8 @@ pub struct ReduceArrange {
     /// The distinct input arrangement for accumulable reduce plan
     /// only used when accumulable reduce plan has distinct aggregation
     distinct_input: Option<Vec<ArrangeHandler>>,
+    /// The multiplicity of each distinct row, only used by distinct reduce plan
+    distinct_count: Option<ArrangeHandler>,
 }
 
 fn batch_split_by_key_val(
@@ -289,7 +352,6 @@ fn batch_split_by_key_val(
     key_val_plan: &KeyValPlan,
     err_collector: &ErrCollector,
 ) -> (Batch, Batch) {
-    let row_count = batch.row_count();
     let mut key_batch = Batch::empty();
     let mut val_batch = Batch::empty();
 
@@ -304,18 +366,56 @@ fn batch_split_by_key_val(
         Ok(())
     });
 
-    // deal with empty key or val
+    // deal with empty key or val, which keep the diffs and timestamps of input rows
+    let without_columns = || {
+        let mut empty = batch.clone();
+        empty.batch_mut().clear();
+        empty
+    };
     if key_batch.row_count() == 0 && key_batch.column_count() == 0 {
-        key_batch.set_row_count(row_count);
+        key_batch = without_columns();
     }
 
     if val_batch.row_count() == 0 && val_batch.column_count() == 0 {
-        val_batch.set_row_count(row_count);
+        val_batch = without_columns();
     }
 
     (key_batch, val_batch)
 }
 
+/// Rewrite `expr` over columns of the source table into one over the key of a reduce
+///
+/// Return None if the reduce isn't reading the source table directly, or `expr` references
+/// columns of the source table which are not part of the key
+fn expire_when_over_key(
+    expr: &ScalarExpr,
+    input: &TypedPlan,
+    key_plan: &MapFilterProject,
+) -> Option<ScalarExpr> {
+    // the column in source table of each column of the reduce's input
+    let input_to_source = match &input.plan {
+        Plan::Get { id: Id::Global(_) } => (0..input.schema.arity()).map(Some).collect_vec(),
+        Plan::Mfp { input: src, mfp } if matches!(src.plan, Plan::Get { id: Id::Global(_) }) => mfp
+            .projection
+            .iter()
+            .map(|&col| (col < mfp.input_arity).then_some(col))
+            .collect_vec(),
+        _ => return None,
+    };
+    let mut source_to_key = BTreeMap::new();
+    for (key_col, &col) in key_plan.projection.iter().enumerate() {
+        if col >= key_plan.input_arity {
+            continue;
+        }
+        if let Some(Some(source_col)) = input_to_source.get(col) {
+            source_to_key.entry(*source_col).or_insert(key_col);
+        }
+    }
+    let mut expr = expr.clone();
+    expr.permute_map(&source_to_key).ok()?;
+    Some(expr)
+}
+
 /// split a row into key and val by evaluate the key and val plan
 fn split_rows_to_key_val(
     rows: impl IntoIterator<Item = DiffRow>,
@@ -325,7 +425,7 @@ fn split_rows_to_key_val(
     let mut row_buf = Row::new(vec![]);
     rows.into_iter().filter_map(
         move |(mut row, sys_time, diff): DiffRow| -> Option<KeyValDiffRow> {
-            err_collector.run(|| {
+            err_collector.run_on_row("reduce", &mut row, |row| {
                 let len = row.len();
                 if let Some(key) = key_val_plan
                     .key_plan
@@ -351,6 +451,7 @@ fn split_rows_to_key_val(
 
 fn reduce_batch_subgraph(
     arrange: &ArrangeHandler,
+    distinct_input: &Option<Vec<ArrangeHandler>>,
     src_data: impl IntoIterator<Item = Batch>,
     key_val_plan: &KeyValPlan,
     accum_plan: &AccumulablePlan,
@@ -477,14 +578,17 @@ fn reduce_batch_subgraph(
     for (key, val_batches) in key_to_many_vals {
         err_collector.run(|| -> Result<(), _> {
             let (accums, _, _) = arrange.get(now, &key).unwrap_or_default();
-            let accum_list =
-                from_accum_values_to_live_accums(accums.unpack(), accum_plan.simple_aggrs.len())?;
+            let accum_list = from_accum_values_to_live_accums(
+                accums.inner.clone(),
+                accum_plan.full_aggrs.len(),
+            )?;
 
             let mut accum_output = AccumOutput::new();
             for AggrWithIndex {
                 expr,
                 input_idx,
                 output_idx,
+                filter_idx,
             } in accum_plan.simple_aggrs.iter()
             {
                 let cur_accum_value = accum_list.get(*output_idx).cloned().unwrap_or_default();
@@ -495,6 +599,10 @@ fn reduce_batch_subgraph(
                 };
 
                 for val_batch in val_batches.iter() {
+                    let val_batch = match filter_idx {
+                        Some(filter_idx) => filter_val_batch(val_batch, *filter_idx)?,
+                        None => val_batch.clone(),
+                    };
                     // if batch is empty, input null instead
                     let cur_input = val_batch
                         .batch()
@@ -502,7 +610,8 @@ fn reduce_batch_subgraph(
                         .cloned()
                         .unwrap_or_else(|| Arc::new(NullVector::new(val_batch.row_count())));
                     let len = cur_input.len();
-                    cur_accum.update_batch(&expr.func, VectorDiff::from(cur_input))?;
+                    let cur_input = VectorDiff::try_new(cur_input, val_batch.diffs().cloned())?;
+                    cur_accum.update_batch(&expr.func, cur_input)?;
 
                     trace!("Reduce accum after take {} rows: {:?}", len, cur_accum);
                 }
@@ -514,12 +623,93 @@ fn reduce_batch_subgraph(
                 accum_output.insert_accum(*output_idx, cur_accum_value);
             }
 
+            for (
+                distinct_idx,
+                AggrWithIndex {
+                    expr,
+                    input_idx,
+                    output_idx,
+                    filter_idx,
+                },
+            ) in accum_plan.distinct_aggrs.iter().enumerate()
+            {
+                let input_arrange = distinct_input
+                    .as_ref()
+                    .and_then(|v| v.get(distinct_idx))
+                    .with_context(|| InternalSnafu {
+                        reason: format!(
+                            "Distinct input arrangement of distinct aggregation {} not found",
+                            distinct_idx
+                        ),
+                    })?;
+                let cur_accum_value = accum_list.get(*output_idx).cloned().unwrap_or_default();
+                let mut cur_accum = if cur_accum_value.is_empty() {
+                    Accum::new_accum(&expr.func.clone())?
+                } else {
+                    Accum::try_into_accum(&expr.func, cur_accum_value)?
+                };
+
+                let mut input_type = None;
+                let mut values = Vec::new();
+                for val_batch in val_batches.iter() {
+                    let val_batch = match filter_idx {
+                        Some(filter_idx) => filter_val_batch(val_batch, *filter_idx)?,
+                        None => val_batch.clone(),
+                    };
+                    let Some(cur_input) = val_batch.batch().get(*input_idx) else {
+                        continue;
+                    };
+                    input_type = Some(cur_input.data_type());
+                    values.extend(VectorDiff::try_new(
+                        cur_input.clone(),
+                        val_batch.diffs().cloned(),
+                    )?);
+                }
+                // only values first seen or last removed in this group are accumulated
+                let mut new_values =
+                    update_distinct_values(input_arrange, &key, values, now, err_collector);
+                // same as row mode, min/max is recomputed from all distinct values left in the
+                // group once a value is retracted
+                let is_min_max = expr.func.is_min() || expr.func.is_max();
+                if is_min_max && new_values.iter().any(|(_, diff)| *diff < 0) {
+                    cur_accum = Accum::new_accum(&expr.func)?;
+                    new_values = input_arrange
+                        .read()
+                        .get_by_prefix(now, &key)
+                        .into_iter()
+                        .filter(|(_, (_, _, cnt))| *cnt > 0)
+                        .filter_map(|(key_val, _)| key_val.inner.last().cloned().map(|v| (v, 1)))
+                        .collect_vec();
+                }
+                if let Some(input_type) = input_type {
+                    let mut builder = input_type.create_mutable_vector(new_values.len());
+                    for (v, _) in new_values.iter() {
+                        builder
+                            .try_push_value_ref(v.as_value_ref())
+                            .context(DataTypeSnafu {
+                                msg: "Failed to push value",
+                            })?;
+                    }
+                    let diffs = Int64Vector::from_iter_values(new_values.iter().map(|(_, d)| *d));
+                    let new_values =
+                        VectorDiff::try_new(builder.to_vector(), Some(Arc::new(diffs)))?;
+                    cur_accum.update_batch(&expr.func, new_values)?;
+                }
+                let final_output = cur_accum.eval(&expr.func)?;
+                accum_output.insert_output(*output_idx, final_output);
+                accum_output.insert_accum(*output_idx, cur_accum.into_state());
+            }
+
             let (new_accums, res_val_row) = accum_output.into_accum_output()?;
+            // the output of a group is unchanged if its accumulators are, so it's not sent again
+            let unchanged = !accums.is_empty() && accums.inner == new_accums;
 
             let arrange_update = ((key.clone(), Row::new(new_accums)), now, 1);
             all_arrange_updates.push(arrange_update);
 
-            all_output_dict.insert(key, Row::from(res_val_row));
+            if !unchanged {
+                all_output_dict.insert(key, Row::from(res_val_row));
+            }
 
             Ok(())
         });
@@ -594,6 +784,7 @@ fn reduce_subgraph(
     ReduceArrange {
         output_arrange: arrange,
         distinct_input,
+        distinct_count,
     }: &ReduceArrange,
     data: impl IntoIterator<Item = DiffRow>,
     key_val_plan: &KeyValPlan,
@@ -613,6 +804,7 @@ fn reduce_subgraph(
     match reduce_plan {
         ReducePlan::Distinct => reduce_distinct_subgraph(
             arrange,
+            distinct_count.as_ref(),
             key_val,
             SubgraphArg {
                 now,
@@ -677,6 +869,54 @@ fn eval_distinct_core(
         .collect_vec()
 }
 
+/// Update the multiplicity of each key in `count`, and only return updates that make a key
+/// first seen(`+1`) or last removed(`-1`)
+///
+/// updates of already expired keys are ignored
+fn update_distinct_count(
+    count: &ArrangeHandler,
+    kv: impl IntoIterator<Item = KeyValDiffRow>,
+    now: repr::Timestamp,
+    err_collector: &ErrCollector,
+) -> Vec<KeyValDiffRow> {
+    let mut count = count.write();
+    // keep track of the multiplicity changed within the current input
+    let mut inner_map: BTreeMap<Row, repr::Diff> = BTreeMap::new();
+    let mut count_updates = Vec::new();
+    let mut ret = Vec::new();
+    for ((key, val), ts, diff) in kv {
+        if let Some(expire_man) = count.get_expire_state() {
+            let expired = err_collector
+                .run(|| expire_man.get_expire_duration(now, &key))
+                .flatten();
+            if let Some(expired_by) = expired {
+                err_collector.push_late_row(&key, expired_by);
+                continue;
+            }
+        }
+        let old_cnt = match inner_map.get(&key) {
+            Some(cnt) => *cnt,
+            None => count.get(now, &key).map(|(_, _, cnt)| cnt).unwrap_or(0),
+        };
+        let new_cnt = old_cnt + diff;
+        inner_map.insert(key.clone(), new_cnt);
+        count_updates.push(((key.clone(), Row::empty()), ts, diff));
+
+        if old_cnt <= 0 && new_cnt > 0 {
+            ret.push(((key, val), ts, 1));
+        } else if old_cnt > 0 && new_cnt <= 0 {
+            ret.push(((key, val), ts, -1));
+        }
+    }
+
+    err_collector.run(|| {
+        count.apply_updates(now, count_updates)?;
+        count.compact_to(now)?;
+        Ok(())
+    });
+    ret
+}
+
 /// eval distinct reduce plan, output the distinct, and update the arrangement
 ///
 /// This function is extracted because also want to use it to update distinct input of accumulable reduce plan
@@ -725,6 +965,7 @@ fn update_reduce_distinct_arrange(
 /// since it's from a Collection Bundle, where future inserts are stored in arrange
 fn reduce_distinct_subgraph(
     arrange: &ArrangeHandler,
+    distinct_count: Option<&ArrangeHandler>,
     kv: impl IntoIterator<Item = KeyValDiffRow>,
     SubgraphArg {
         now,
@@ -733,6 +974,10 @@ fn reduce_distinct_subgraph(
         send,
     }: SubgraphArg,
 ) {
+    let kv = match distinct_count {
+        Some(count) => update_distinct_count(count, kv, now, err_collector),
+        None => kv.into_iter().collect_vec(),
+    };
     let ret = update_reduce_distinct_arrange(arrange, kv, now, err_collector).collect_vec();
 
     // no future updates should exist here
@@ -801,14 +1046,10 @@ fn reduce_accum_subgraph(
             err_collector.run(|| {
                 if let Some(expired) = expire_man.get_expire_duration(now, &key)? {
                     is_expired = true;
-                    // expired data is ignored in computation, and a simple warning is logged
-                    common_telemetry::warn!(
-                        "Data already expired: {}",
-                        DataAlreadyExpiredSnafu {
-                            expired_by: expired,
-                        }
-                        .build()
-                    );
+                    // expired data is ignored in computation, and accounted as late data
+                    for _ in &value_diffs {
+                        err_collector.push_late_row(&key, expired);
+                    }
                     Ok(())
                 } else {
                     Ok(())
@@ -859,6 +1100,7 @@ fn reduce_accum_subgraph(
         eval_distinct_aggrs(
             distinct_aggrs,
             distinct_input,
+            &key,
             &accums,
             &accum_ranges,
             &col_diffs,
@@ -874,12 +1116,16 @@ fn reduce_accum_subgraph(
         // get and append results
         err_collector.run(|| {
             let (new_accums, res_val_row) = accum_output.into_accum_output()?;
+            // the output of a group is unchanged if its accumulators are, so it's not sent again
+            let unchanged = !accums.is_empty() && accums == new_accums;
 
             // construct the updates and save it
             all_updates.push(((key.clone(), Row::new(new_accums)), now, 1));
-            let mut key_val = key;
-            key_val.extend(res_val_row);
-            all_outputs.push((key_val, now, 1));
+            if !unchanged {
+                let mut key_val = key;
+                key_val.extend(res_val_row);
+                all_outputs.push((key_val, now, 1));
+            }
             Ok(())
         });
     }
@@ -934,6 +1180,7 @@ fn eval_simple_aggrs(
         expr,
         input_idx,
         output_idx,
+        filter_idx,
     } in simple_aggrs
     {
         let cur_accum_range = accum_ranges[*output_idx].clone(); // range of current accum
@@ -942,7 +1189,16 @@ fn eval_simple_aggrs(
             .unwrap_or_default()
             .iter()
             .cloned();
-        let cur_col_diff = col_diffs[*input_idx].iter().cloned();
+        // rows not satisfying the aggregate's filter are skipped before accumulating
+        let cur_col_diff = col_diffs[*input_idx]
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| {
+                filter_idx
+                    .map(|f| col_diffs[f][*i].0 == Value::Boolean(true))
+                    .unwrap_or(true)
+            })
+            .map(|(_, v)| v.clone());
 
         // actual eval aggregation function
         if let Some((res, new_accum)) =
@@ -1018,9 +1274,13 @@ impl AccumOutput {
 }
 
 /// Eval distinct aggregate functions with distinct input arrange
+///
+/// Only values first seen or last removed in the group of `key` are fed to the aggregate function
+#[allow(clippy::too_many_arguments)]
 fn eval_distinct_aggrs(
-    distinct_aggrs: &Vec<AggrWithIndex>,
+    distinct_aggrs: &[AggrWithIndex],
     distinct_input: &Option<Vec<ArrangeHandler>>,
+    key: &Row,
     accums: &[Value],
     accum_ranges: &[Range<usize>],
     col_diffs: &[Vec<(Value, i64)>],
@@ -1032,43 +1292,116 @@ fn eval_distinct_aggrs(
         send: _,
     }: SubgraphArg,
 ) {
-    for AggrWithIndex {
-        expr,
-        input_idx,
-        output_idx,
-    } in distinct_aggrs
+    for (
+        distinct_idx,
+        AggrWithIndex {
+            expr,
+            input_idx,
+            output_idx,
+            filter_idx,
+        },
+    ) in distinct_aggrs.iter().enumerate()
     {
+        let Some(input_arrange) = err_collector.run(|| {
+            distinct_input
+                .as_ref()
+                .and_then(|v| v.get(distinct_idx))
+                .with_context(|| InternalSnafu {
+                    reason: format!(
+                        "Distinct input arrangement of distinct aggregation {} not found",
+                        distinct_idx
+                    ),
+                })
+        }) else {
+            continue;
+        };
         let cur_accum_range = accum_ranges[*output_idx].clone(); // range of current accum
         let cur_old_accum = accums
             .get(cur_accum_range)
             .unwrap_or_default()
             .iter()
             .cloned();
-        let cur_col_diff = col_diffs[*input_idx].iter().cloned();
-        // first filter input with distinct
-        let input_arrange = distinct_input
-            .as_ref()
-            .and_then(|v| v[*input_idx].clone_full_arrange())
-            .expect("A full distinct input arrangement should exist");
-        let kv = cur_col_diff.map(|(v, d)| ((Row::new(vec![v]), Row::empty()), now, d));
+        // rows not satisfying the aggregate's filter are skipped before deduplicating
+        let cur_col_diff = col_diffs[*input_idx]
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| {
+                filter_idx
+                    .map(|f| col_diffs[f][*i].0 == Value::Boolean(true))
+                    .unwrap_or(true)
+            })
+            .map(|(_, v)| v.clone());
         let col_diff_distinct =
-            update_reduce_distinct_arrange(&input_arrange, kv, now, err_collector).map(
-                |(row, _ts, diff)| (row.get(0).expect("Row should not be empty").clone(), diff),
-            );
-        let col_diff_distinct = {
-            let res = col_diff_distinct.collect_vec();
-            res.into_iter()
+            update_distinct_values(input_arrange, key, cur_col_diff, now, err_collector);
+
+        // the accumulator of min/max only keeps the current min/max, so once a value is
+        // retracted, it's recomputed from all distinct values left in the group
+        let is_min_max = expr.func.is_min() || expr.func.is_max();
+        let res = if is_min_max && col_diff_distinct.iter().any(|(_, diff)| *diff < 0) {
+            let values = input_arrange
+                .read()
+                .get_by_prefix(now, key)
+                .into_iter()
+                .filter(|(_, (_, _, cnt))| *cnt > 0)
+                .filter_map(|(key_val, _)| key_val.inner.last().cloned().map(|v| (v, 1)))
+                .collect_vec();
+            err_collector.run(|| expr.func.eval_diff_accumulable(std::iter::empty(), values))
+        } else {
+            err_collector.run(|| {
+                expr.func
+                    .eval_diff_accumulable(cur_old_accum, col_diff_distinct)
+            })
         };
-        // actual eval aggregation function
-        let (res, new_accum) = expr
-            .func
-            .eval_diff_accumulable(cur_old_accum, col_diff_distinct)
-            .unwrap();
-        accum_output.insert_accum(*output_idx, new_accum);
-        accum_output.insert_output(*output_idx, res);
+
+        if let Some((res, new_accum)) = res {
+            accum_output.insert_accum(*output_idx, new_accum);
+            accum_output.insert_output(*output_idx, res);
+        } // else just collect error and continue
     }
 }
 
+/// Update the multiplicity of `values` in the group of `key` in a distinct input arrangement,
+/// and only return values first seen(`+1`) or last removed(`-1`) in the group
+///
+/// The distinct input arrangement is keyed by the group's key followed by the value
+fn update_distinct_values(
+    distinct_input: &ArrangeHandler,
+    key: &Row,
+    values: impl IntoIterator<Item = (Value, repr::Diff)>,
+    now: repr::Timestamp,
+    err_collector: &ErrCollector,
+) -> Vec<(Value, repr::Diff)> {
+    let kv = values.into_iter().map(|(v, d)| {
+        let mut key_val = key.clone();
+        key_val.extend([v]);
+        ((key_val, Row::empty()), now, d)
+    });
+    update_distinct_count(distinct_input, kv, now, err_collector)
+        .into_iter()
+        .filter_map(|((mut key_val, _), _, d)| key_val.inner.pop().map(|v| (v, d)))
+        .collect_vec()
+}
+
+/// Keep only the rows of value batch whose value at `filter_idx` is `true`
+fn filter_val_batch(val_batch: &Batch, filter_idx: usize) -> Result<Batch, EvalError> {
+    let filter = val_batch
+        .batch()
+        .get(filter_idx)
+        .with_context(|| InternalSnafu {
+            reason: format!("Filter column {} not found in value batch", filter_idx),
+        })?;
+    let filter = filter
+        .as_any()
+        .downcast_ref::<BooleanVector>()
+        .with_context(|| InternalSnafu {
+            reason: format!(
+                "Expect filter column to be boolean, found {:?}",
+                filter.data_type()
+            ),
+        })?;
+    val_batch.filter(filter)
+}
+
 fn check_no_future_updates<'a>(
     all_arrange_used: impl IntoIterator<Item = ArrangeWriter<'a>>,
     err_collector: &ErrCollector,
@@ -1177,6 +1510,53 @@ mod test {
     use crate::plan::Plan;
     use crate::repr::{ColumnType, RelationType};
 
+    #[test]
+    fn test_expire_when_over_key() {
+        // source columns: number, ts
+        let source = TypedPlan {
+            plan: Plan::Get {
+                id: expr::Id::Global(GlobalId::User(1)),
+            },
+            schema: RelationType::new(vec![
+                ColumnType::new(CDT::int64_datatype(), false),
+                ColumnType::new(CDT::timestamp_millisecond_datatype(), false),
+            ])
+            .into_unnamed(),
+        };
+        // GROUP BY ts
+        let key_plan = MapFilterProject::new(2).project([1]).unwrap();
+        let expire_when = ScalarExpr::Column(1).call_binary(
+            ScalarExpr::literal(1000i64.into(), CDT::int64_datatype()),
+            BinaryFunc::AddInt64,
+        );
+        assert_eq!(
+            expire_when_over_key(&expire_when, &source, &key_plan),
+            Some(ScalarExpr::Column(0).call_binary(
+                ScalarExpr::literal(1000i64.into(), CDT::int64_datatype()),
+                BinaryFunc::AddInt64,
+            ))
+        );
+        // `number` is not in the key
+        assert_eq!(
+            expire_when_over_key(&ScalarExpr::Column(0), &source, &key_plan),
+            None
+        );
+
+        // reading the source through a mfp swapping the columns
+        let input = TypedPlan {
+            schema: source.schema.clone(),
+            plan: Plan::Mfp {
+                input: Box::new(source),
+                mfp: MapFilterProject::new(2).project([1, 0]).unwrap(),
+            },
+        };
+        let key_plan = MapFilterProject::new(2).project([0]).unwrap();
+        assert_eq!(
+            expire_when_over_key(&ScalarExpr::Column(1), &input, &key_plan),
+            Some(ScalarExpr::Column(0))
+        );
+    }
+
     /// SELECT sum(number) FROM numbers_with_ts GROUP BY tumble(ts, '1 second', '2021-07-01 00:00:00')
     /// input table columns: number, ts
     /// expected: sum(number), window_start, window_end
@@ -1501,6 +1881,51 @@ mod test {
         run_and_check(&mut state, &mut df, 6..7, expected, output);
     }
 
+    /// SELECT DISTINCT col FROM table
+    ///
+    /// a distinct row is only retracted when all of its copies are removed
+    #[test]
+    fn test_distinct_multiplicity() {
+        let mut df = Hydroflow::new();
+        let mut state = DataflowState::default();
+        let mut ctx = harness_test_ctx(&mut df, &mut state);
+
+        let rows = vec![
+            (Row::new(vec![1i64.into()]), 1, 1),
+            (Row::new(vec![1i64.into()]), 2, 1),
+            (Row::new(vec![1i64.into()]), 3, -1),
+            (Row::new(vec![1i64.into()]), 4, -1),
+        ];
+        let collection = ctx.render_constant(rows);
+        ctx.insert_global(GlobalId::User(1), collection);
+        let input_plan = Plan::Get {
+            id: expr::Id::Global(GlobalId::User(1)),
+        };
+        let typ = RelationType::new(vec![ColumnType::new_nullable(
+            ConcreteDataType::int64_datatype(),
+        )]);
+        let key_val_plan = KeyValPlan {
+            key_plan: MapFilterProject::new(1).project([0]).unwrap().into_safe(),
+            val_plan: MapFilterProject::new(1).project([]).unwrap().into_safe(),
+        };
+        let bundle = ctx
+            .render_reduce(
+                Box::new(input_plan.with_types(typ.into_unnamed())),
+                key_val_plan,
+                ReducePlan::Distinct,
+                RelationType::empty(),
+            )
+            .unwrap();
+
+        let output = get_output_handle(&mut ctx, bundle);
+        drop(ctx);
+        let expected = BTreeMap::from([
+            (1, vec![(Row::new(vec![1i64.into()]), 1, 1)]),
+            (4, vec![(Row::new(vec![1i64.into()]), 4, -1)]),
+        ]);
+        run_and_check(&mut state, &mut df, 1..5, expected, output);
+    }
+
     /// Batch Mode Reduce Evaluation
     /// SELECT SUM(col) FROM table
     ///
@@ -1600,6 +2025,70 @@ mod test {
         }
     }
 
+    /// SELECT SUM(col) FROM table, with retracted rows carried in the diffs of batches
+    #[test]
+    fn test_batch_reduce_accum_with_retraction() {
+        let mut df = Hydroflow::new();
+        let mut state = DataflowState::default();
+        let now = state.current_time_ref();
+        let mut ctx = harness_test_ctx(&mut df, &mut state);
+
+        let rows = vec![
+            (Row::new(vec![1i64.into()]), 1, 1),
+            (Row::new(vec![2i64.into()]), 2, 1),
+            (Row::new(vec![1i64.into()]), 3, -1),
+            (Row::new(vec![5i64.into()]), 3, 1),
+        ];
+        let input_plan = Plan::Constant { rows };
+        let typ = RelationType::new(vec![ColumnType::new_nullable(
+            ConcreteDataType::int64_datatype(),
+        )]);
+        let key_val_plan = KeyValPlan {
+            key_plan: MapFilterProject::new(1).project([]).unwrap().into_safe(),
+            val_plan: MapFilterProject::new(1).project([0]).unwrap().into_safe(),
+        };
+        let sum = AggregateExpr {
+            func: AggregateFunc::SumInt64,
+            expr: ScalarExpr::Column(0),
+            distinct: false,
+        };
+        let reduce_plan = ReducePlan::Accumulable(AccumulablePlan {
+            full_aggrs: vec![sum.clone()],
+            simple_aggrs: vec![AggrWithIndex::new(sum, 0, 0)],
+            distinct_aggrs: vec![],
+        });
+        let bundle = ctx
+            .render_reduce_batch(
+                Box::new(input_plan.with_types(typ.into_unnamed())),
+                &key_val_plan,
+                &reduce_plan,
+                &RelationType::empty(),
+            )
+            .unwrap();
+
+        let now_inner = now.clone();
+        let expected = BTreeMap::<i64, i64>::from([(1, 1), (2, 3), (3, 7)]);
+        ctx.df.add_subgraph_sink(
+            "test_sink",
+            bundle.collection.into_inner(),
+            move |_ctx, recv| {
+                let now = *now_inner.borrow();
+                let res = recv.take_inner().into_iter().flatten().collect_vec();
+                if let Some(expected) = expected.get(&now) {
+                    let batch = Batch::try_from_rows(vec![Row::new(vec![(*expected).into()])]);
+                    assert_eq!(res.first(), Some(&batch.unwrap()));
+                }
+            },
+        );
+        drop(ctx);
+
+        for now in 1..4 {
+            state.set_current_ts(now);
+            state.run_available_with_schedule(&mut df);
+            assert!(state.get_err_collector().is_empty());
+        }
+    }
+
     /// SELECT SUM(col) FROM table
     ///
     /// table schema:
@@ -1675,6 +2164,79 @@ mod test {
         run_and_check(&mut state, &mut df, 1..7, expected, output);
     }
 
+    /// SELECT SUM(col) FILTER (WHERE col > 1) FROM table
+    ///
+    /// table schema:
+    /// | name | type  |
+    /// |------|-------|
+    /// | col  | Int64 |
+    #[test]
+    fn test_reduce_accum_with_filter() {
+        let mut df = Hydroflow::new();
+        let mut state = DataflowState::default();
+        let mut ctx = harness_test_ctx(&mut df, &mut state);
+
+        let rows = vec![
+            (Row::new(vec![1i64.into()]), 1, 1),
+            (Row::new(vec![2i64.into()]), 1, 1),
+            (Row::new(vec![3i64.into()]), 1, 1),
+            (Row::new(vec![1i64.into()]), 2, 1),
+            (Row::new(vec![3i64.into()]), 3, -1),
+        ];
+        let collection = ctx.render_constant(rows.clone());
+        ctx.insert_global(GlobalId::User(1), collection);
+        let input_plan = Plan::Get {
+            id: expr::Id::Global(GlobalId::User(1)),
+        };
+        let typ = RelationType::new(vec![ColumnType::new_nullable(
+            ConcreteDataType::int64_datatype(),
+        )]);
+        // value row is (col, col > 1)
+        let filter = ScalarExpr::Column(0).call_binary(
+            ScalarExpr::Literal(1i64.into(), CDT::int64_datatype()),
+            BinaryFunc::Gt,
+        );
+        let key_val_plan = KeyValPlan {
+            key_plan: MapFilterProject::new(1).project([]).unwrap().into_safe(),
+            val_plan: MapFilterProject::new(1)
+                .map([filter])
+                .unwrap()
+                .project([0, 1])
+                .unwrap()
+                .into_safe(),
+        };
+
+        let aggr_expr = AggregateExpr {
+            func: AggregateFunc::SumInt64,
+            expr: ScalarExpr::Column(0),
+            distinct: false,
+        };
+        let accum_plan = AccumulablePlan {
+            full_aggrs: vec![aggr_expr.clone()],
+            simple_aggrs: vec![AggrWithIndex::new(aggr_expr, 0, 0).with_filter(Some(1))],
+            distinct_aggrs: vec![],
+        };
+
+        let reduce_plan = ReducePlan::Accumulable(accum_plan);
+        let bundle = ctx
+            .render_reduce(
+                Box::new(input_plan.with_types(typ.into_unnamed())),
+                key_val_plan,
+                reduce_plan,
+                RelationType::empty(),
+            )
+            .unwrap();
+
+        let output = get_output_handle(&mut ctx, bundle);
+        drop(ctx);
+        // rows not satisfying the filter are not accumulated, so the unchanged output isn't sent again
+        let expected = BTreeMap::from([
+            (1, vec![(Row::new(vec![5i64.into()]), 1, 1)]),
+            (3, vec![(Row::new(vec![2i64.into()]), 3, 1)]),
+        ]);
+        run_and_check(&mut state, &mut df, 1..4, expected, output);
+    }
+
     /// SELECT SUM(DISTINCT col) FROM table
     ///
     /// table schema:
@@ -1827,9 +2389,7 @@ mod test {
             (2, vec![(Row::new(vec![2i64.into()]), 2, 1)]),
             (3, vec![(Row::new(vec![5i64.into()]), 3, 1)]),
             (4, vec![(Row::new(vec![6i64.into()]), 4, 1)]),
-            (5, vec![(Row::new(vec![6i64.into()]), 5, 1)]),
-            (6, vec![(Row::new(vec![6i64.into()]), 6, 1)]),
-            (7, vec![(Row::new(vec![6i64.into()]), 7, 1)]),
+            // no new distinct value, so the output is unchanged and not sent again
         ]);
         run_and_check(&mut state, &mut df, 1..7, expected, output);
     }
@@ -1925,4 +2485,260 @@ mod test {
         ]);
         run_and_check(&mut state, &mut df, 1..7, expected, output);
     }
+
+    /// SELECT k, SUM(DISTINCT v) FROM table GROUP BY k
+    ///
+    /// table schema:
+    /// | name | type  |
+    /// |------|-------|
+    /// | k    | Int64 |
+    /// | v    | Int64 |
+    ///
+    /// distinct values are tracked per group with multiplicity
+    #[test]
+    fn test_group_by_reduce_distinct_accum() {
+        let mut df = Hydroflow::new();
+        let mut state = DataflowState::default();
+        let mut ctx = harness_test_ctx(&mut df, &mut state);
+
+        let rows = vec![
+            (Row::new(vec![1i64.into(), 1i64.into()]), 1, 1),
+            (Row::new(vec![2i64.into(), 1i64.into()]), 1, 1),
+            (Row::new(vec![1i64.into(), 1i64.into()]), 2, 1),
+            (Row::new(vec![2i64.into(), 2i64.into()]), 2, 1),
+            // one of the two `1` of group 1 is removed
+            (Row::new(vec![1i64.into(), 1i64.into()]), 3, -1),
+            // the last `1` of group 1 is removed
+            (Row::new(vec![1i64.into(), 1i64.into()]), 4, -1),
+        ];
+        let collection = ctx.render_constant(rows.clone());
+        ctx.insert_global(GlobalId::User(1), collection);
+        let input_plan = Plan::Get {
+            id: expr::Id::Global(GlobalId::User(1)),
+        };
+        let typ = RelationType::new(vec![
+            ColumnType::new_nullable(ConcreteDataType::int64_datatype()),
+            ColumnType::new_nullable(ConcreteDataType::int64_datatype()),
+        ]);
+        let key_val_plan = KeyValPlan {
+            key_plan: MapFilterProject::new(2).project([0]).unwrap().into_safe(),
+            val_plan: MapFilterProject::new(2).project([1]).unwrap().into_safe(),
+        };
+
+        let distinct_aggrs = vec![AggrWithIndex::new(
+            AggregateExpr {
+                func: AggregateFunc::SumInt64,
+                expr: ScalarExpr::Column(0),
+                distinct: true,
+            },
+            0,
+            0,
+        )];
+        let accum_plan = AccumulablePlan {
+            full_aggrs: vec![AggregateExpr {
+                func: AggregateFunc::SumInt64,
+                expr: ScalarExpr::Column(0),
+                distinct: true,
+            }],
+            simple_aggrs: vec![],
+            distinct_aggrs,
+        };
+
+        let reduce_plan = ReducePlan::Accumulable(accum_plan);
+        let bundle = ctx
+            .render_reduce(
+                Box::new(input_plan.with_types(typ.into_unnamed())),
+                key_val_plan,
+                reduce_plan,
+                RelationType::empty(),
+            )
+            .unwrap();
+
+        let output = get_output_handle(&mut ctx, bundle);
+        drop(ctx);
+        let expected = BTreeMap::from([
+            (
+                1,
+                vec![
+                    (Row::new(vec![1i64.into(), 1i64.into()]), 1, 1),
+                    (Row::new(vec![2i64.into(), 1i64.into()]), 1, 1),
+                ],
+            ),
+            (
+                2,
+                vec![
+                    (Row::new(vec![1i64.into(), 1i64.into()]), 2, 1),
+                    (Row::new(vec![2i64.into(), 3i64.into()]), 2, 1),
+                ],
+            ),
+            // group 1 still has a `1` at 3, so its output is unchanged and not sent again
+            (4, vec![(Row::new(vec![1i64.into(), 0i64.into()]), 4, 1)]),
+        ]);
+        run_and_check(&mut state, &mut df, 1..7, expected, output);
+    }
+
+    /// SELECT MAX(col) FROM table, with max computed from distinct values for input with retraction
+    ///
+    /// table schema:
+    /// | name | type  |
+    /// |------|-------|
+    /// | col  | Int64 |
+    #[test]
+    fn test_retractable_max() {
+        let mut df = Hydroflow::new();
+        let mut state = DataflowState::default();
+        let mut ctx = harness_test_ctx(&mut df, &mut state);
+
+        let rows = vec![
+            (Row::new(vec![1i64.into()]), 1, 1),
+            (Row::new(vec![3i64.into()]), 1, 1),
+            (Row::new(vec![3i64.into()]), 1, 1),
+            // one `3` is still left
+            (Row::new(vec![3i64.into()]), 2, -1),
+            // the current max is retracted, the next one is brought back
+            (Row::new(vec![3i64.into()]), 3, -1),
+            (Row::new(vec![2i64.into()]), 4, 1),
+        ];
+        let collection = ctx.render_constant(rows.clone());
+        ctx.insert_global(GlobalId::User(1), collection);
+        let input_plan = Plan::Get {
+            id: expr::Id::Global(GlobalId::User(1)),
+        };
+        let typ = RelationType::new(vec![ColumnType::new_nullable(
+            ConcreteDataType::int64_datatype(),
+        )]);
+        let key_val_plan = KeyValPlan {
+            key_plan: MapFilterProject::new(1).project([]).unwrap().into_safe(),
+            val_plan: MapFilterProject::new(1).project([0]).unwrap().into_safe(),
+        };
+
+        let max = AggregateExpr {
+            func: AggregateFunc::MaxInt64,
+            expr: ScalarExpr::Column(0),
+            distinct: true,
+        };
+        let accum_plan = AccumulablePlan {
+            full_aggrs: vec![max.clone()],
+            simple_aggrs: vec![],
+            distinct_aggrs: vec![AggrWithIndex::new(max, 0, 0)],
+        };
+
+        let reduce_plan = ReducePlan::Accumulable(accum_plan);
+        let bundle = ctx
+            .render_reduce(
+                Box::new(input_plan.with_types(typ.into_unnamed())),
+                key_val_plan,
+                reduce_plan,
+                RelationType::empty(),
+            )
+            .unwrap();
+
+        let output = get_output_handle(&mut ctx, bundle);
+        drop(ctx);
+        let expected = BTreeMap::from([
+            (1, vec![(Row::new(vec![3i64.into()]), 1, 1)]),
+            // one of the two `3` is retracted, max is unchanged and not sent again
+            (3, vec![(Row::new(vec![1i64.into()]), 3, 1)]),
+            (4, vec![(Row::new(vec![2i64.into()]), 4, 1)]),
+        ]);
+        run_and_check(&mut state, &mut df, 1..7, expected, output);
+    }
+
+    /// Batch Mode Reduce Evaluation
+    /// SELECT SUM(DISTINCT col) FROM table
+    ///
+    /// table schema:
+    /// | name | type  |
+    /// |------|-------|
+    /// | col  | Int64 |
+    #[test]
+    fn test_batch_reduce_distinct_accum() {
+        let mut df = Hydroflow::new();
+        let mut state = DataflowState::default();
+        let now = state.current_time_ref();
+        let mut ctx = harness_test_ctx(&mut df, &mut state);
+
+        let rows = vec![
+            (Row::new(vec![1i64.into()]), 1, 1),
+            (Row::new(vec![2i64.into()]), 2, 1),
+            (Row::new(vec![2i64.into()]), 2, 1),
+            (Row::new(vec![3i64.into()]), 3, 1),
+            (Row::new(vec![1i64.into()]), 4, 1),
+            (Row::new(vec![2i64.into()]), 5, 1),
+            (Row::new(vec![4i64.into()]), 6, 1),
+        ];
+        let input_plan = Plan::Constant { rows: rows.clone() };
+
+        let typ = RelationType::new(vec![ColumnType::new_nullable(
+            ConcreteDataType::int64_datatype(),
+        )]);
+        let key_val_plan = KeyValPlan {
+            key_plan: MapFilterProject::new(1).project([]).unwrap().into_safe(),
+            val_plan: MapFilterProject::new(1).project([0]).unwrap().into_safe(),
+        };
+
+        let distinct_aggrs = vec![AggrWithIndex::new(
+            AggregateExpr {
+                func: AggregateFunc::SumInt64,
+                expr: ScalarExpr::Column(0),
+                distinct: true,
+            },
+            0,
+            0,
+        )];
+        let accum_plan = AccumulablePlan {
+            full_aggrs: vec![AggregateExpr {
+                func: AggregateFunc::SumInt64,
+                expr: ScalarExpr::Column(0),
+                distinct: true,
+            }],
+            simple_aggrs: vec![],
+            distinct_aggrs,
+        };
+
+        let reduce_plan = ReducePlan::Accumulable(accum_plan);
+        let bundle = ctx
+            .render_reduce_batch(
+                Box::new(input_plan.with_types(typ.into_unnamed())),
+                &key_val_plan,
+                &reduce_plan,
+                &RelationType::empty(),
+            )
+            .unwrap();
+
+        {
+            let now_inner = now.clone();
+            let expected = BTreeMap::<i64, Vec<i64>>::from([
+                (1, vec![1i64]),
+                (2, vec![3i64]),
+                (3, vec![6i64]),
+                (6, vec![10i64]),
+            ]);
+            let collection = bundle.collection;
+            ctx.df
+                .add_subgraph_sink("test_sink", collection.into_inner(), move |_ctx, recv| {
+                    let now = *now_inner.borrow();
+                    let data = recv.take_inner();
+                    let res = data.into_iter().flat_map(|v| v.into_iter()).collect_vec();
+
+                    if let Some(expected) = expected.get(&now) {
+                        let batch = expected.iter().map(|v| Value::from(*v)).collect_vec();
+                        let batch = Batch::try_from_rows(vec![batch.into()]).unwrap();
+                        assert_eq!(res.first(), Some(&batch));
+                    }
+                });
+            drop(ctx);
+
+            for now in 1..7 {
+                state.set_current_ts(now);
+                state.run_available_with_schedule(&mut df);
+                if !state.get_err_collector().is_empty() {
+                    panic!(
+                        "Errors occur: {:?}",
+                        state.get_err_collector().get_all_blocking()
+                    )
+                }
+            }
+        }
+    }
 }
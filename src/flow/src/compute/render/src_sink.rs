@@ -20,9 +20,9 @@ use common_telemetry::{debug, trace};
 use hydroflow::scheduled::graph_ext::GraphExt;
 use itertools::Itertools;
 use snafu::OptionExt;
-use tokio::sync::broadcast::error::TryRecvError;
 use tokio::sync::{broadcast, mpsc};
 
+use crate::compute::render::union::consolidate;
 use crate::compute::render::Context;
 use crate::compute::types::{Arranged, Collection, CollectionBundle, Toff};
 use crate::error::{Error, PlanSnafu};
@@ -35,19 +35,24 @@ impl Context<'_, '_> {
     /// simply send the batch to downstream, without fancy features like buffering
     pub fn render_source_batch(
         &mut self,
-        mut src_recv: broadcast::Receiver<Batch>,
+        src_recv: mpsc::Receiver<Batch>,
     ) -> Result<CollectionBundle<Batch>, Error> {
         debug!("Rendering Source Batch");
+        // pending input in the channel can trigger a tick of the dataflow
+        let src_recv = self.compute_state.register_input(src_recv);
         let (send_port, recv_port) = self.df.make_edge::<_, Toff<Batch>>("source_batch");
 
         let schd = self.compute_state.get_scheduler();
         let inner_schd = schd.clone();
         let now = self.compute_state.current_time_ref();
-        let err_collector = self.err_collector.clone();
+        let err_collector = self.err_collector.with_operator("source");
+        let profiler = self.compute_state.profile_operator("source_batch");
 
         let sub = self
             .df
             .add_subgraph_source("source_batch", send_port, move |_ctx, send| {
+                let _timer = profiler.start();
+                let mut src_recv = src_recv.borrow_mut();
                 let mut total_batches = vec![];
                 let mut total_row_count = 0;
                 loop {
@@ -56,20 +61,10 @@ impl Context<'_, '_> {
                             total_row_count += batch.row_count();
                             total_batches.push(batch);
                         }
-                        Err(TryRecvError::Empty) => {
+                        Err(mpsc::error::TryRecvError::Empty) => {
                             break;
                         }
-                        Err(TryRecvError::Lagged(lag_offset)) => {
-                            // use `err_collector` instead of `error!` to locate which operator caused the error
-                            err_collector.run(|| -> Result<(), EvalError> {
-                                InternalSnafu {
-                                    reason: format!("Flow missing {} rows behind", lag_offset),
-                                }
-                                .fail()
-                            });
-                            break;
-                        }
-                        Err(TryRecvError::Closed) => {
+                        Err(mpsc::error::TryRecvError::Disconnected) => {
                             err_collector.run(|| -> Result<(), EvalError> {
                                 InternalSnafu {
                                     reason: "Source Batch Channel is closed".to_string(),
@@ -86,6 +81,7 @@ impl Context<'_, '_> {
                     total_row_count,
                     total_batches.len()
                 );
+                profiler.add_rows(total_row_count);
                 send.give(total_batches);
 
                 let now = *now.borrow();
@@ -98,11 +94,13 @@ impl Context<'_, '_> {
         Ok(bundle)
     }
 
-    /// Render a source which comes from brocast channel into the dataflow
+    /// Render a source of rows into the dataflow, only the tests of the operators over rows use
+    /// it since flows read batches by [`Context::render_source_batch`]
+    ///
     /// will immediately send updates not greater than `now` and buffer the rest in arrangement
     pub fn render_source(
         &mut self,
-        mut src_recv: broadcast::Receiver<DiffRow>,
+        mut src_recv: mpsc::UnboundedReceiver<DiffRow>,
     ) -> Result<CollectionBundle, Error> {
         debug!("Rendering Source");
         let (send_port, recv_port) = self.df.make_edge::<_, Toff>("source");
@@ -117,11 +115,13 @@ impl Context<'_, '_> {
         let schd = self.compute_state.get_scheduler();
         let inner_schd = schd.clone();
         let now = self.compute_state.current_time_ref();
-        let err_collector = self.err_collector.clone();
+        let err_collector = self.err_collector.with_operator("source");
+        let profiler = self.compute_state.profile_operator("source");
 
         let sub = self
             .df
             .add_subgraph_source("source", send_port, move |_ctx, send| {
+                let _timer = profiler.start();
                 let now = *now.borrow();
                 // write lock to prevent unexpected mutation
                 let mut arranged = arrange_handler_inner.write();
@@ -131,7 +131,6 @@ impl Context<'_, '_> {
                 let prev_avail = arr.into_iter().map(|((k, _), t, d)| (k, t, d));
                 let mut to_send = Vec::new();
                 let mut to_arrange = Vec::new();
-                // TODO(discord9): handling tokio broadcast error
                 loop {
                     match src_recv.try_recv() {
                         Ok((r, t, d)) => {
@@ -141,27 +140,22 @@ impl Context<'_, '_> {
                                 to_arrange.push(((r, Row::empty()), t, d));
                             }
                         }
-                        Err(TryRecvError::Empty) => {
-                            break;
-                        }
-                        Err(TryRecvError::Lagged(lag_offset)) => {
-                            common_telemetry::error!("Flow missing {} rows behind", lag_offset);
+                        Err(mpsc::error::TryRecvError::Empty) => {
                             break;
                         }
-                        Err(err) => {
+                        Err(mpsc::error::TryRecvError::Disconnected) => {
                             err_collector.run(|| -> Result<(), EvalError> {
                                 InternalSnafu {
-                                    reason: format!(
-                                        "Error receiving from broadcast channel: {}",
-                                        err
-                                    ),
+                                    reason: "Source channel is closed".to_string(),
                                 }
                                 .fail()
                             });
+                            break;
                         }
                     }
                 }
                 let all = prev_avail.chain(to_send).collect_vec();
+                profiler.add_rows(all.len() + to_arrange.len());
                 if !to_arrange.is_empty() {
                     debug!("Source Operator buffered {} rows", to_arrange.len());
                 }
@@ -190,10 +184,13 @@ impl Context<'_, '_> {
             arranged: _,
         } = bundle;
 
+        let profiler = self.compute_state.profile_operator("sink_batch");
+
         let _sink = self.df.add_subgraph_sink(
             "UnboundedSinkBatch",
             collection.into_inner(),
             move |_ctx, recv| {
+                let _timer = profiler.start();
                 let data = recv.take_inner();
                 let mut row_count = 0;
                 let mut batch_count = 0;
@@ -207,6 +204,7 @@ impl Context<'_, '_> {
                     }
                 }
                 trace!("sink send {} rows in {} batches", row_count, batch_count);
+                profiler.add_rows(row_count);
             },
         );
     }
@@ -221,16 +219,24 @@ impl Context<'_, '_> {
             arranged: _,
         } = bundle;
 
+        let profiler = self.compute_state.profile_operator("sink");
+
         let _sink = self.df.add_subgraph_sink(
             "UnboundedSink",
             collection.into_inner(),
             move |_ctx, recv| {
+                let _timer = profiler.start();
                 let data = recv.take_inner();
+                let row_count = data.iter().map(|i| i.len()).sum::<usize>();
+                profiler.add_rows(row_count);
+                // updates of the same row in one run cancel each other out or merge into one
+                let data = consolidate(data.into_iter().flat_map(|i| i.into_iter()));
                 debug!(
-                    "render_unbounded_sink: send {} rows",
-                    data.iter().map(|i| i.len()).sum::<usize>()
+                    "render_unbounded_sink: send {} rows consolidated from {} rows",
+                    data.len(),
+                    row_count
                 );
-                for row in data.into_iter().flat_map(|i| i.into_iter()) {
+                for row in data {
                     // if the sender is closed, stop sending
                     if sender.is_closed() {
                         common_telemetry::error!("UnboundedSink is closed");
@@ -259,7 +265,7 @@ impl Context<'_, '_> {
             .df
             .add_subgraph_sink("Sink", collection.into_inner(), move |_ctx, recv| {
                 let data = recv.take_inner();
-                buf.extend(data.into_iter().flat_map(|i| i.into_iter()));
+                buf.extend(consolidate(data.into_iter().flat_map(|i| i.into_iter())));
                 if sender.len() >= BROADCAST_CAP {
                     return;
                 } else {
@@ -0,0 +1,490 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+use datatypes::value::Value;
+use hydroflow::scheduled::graph_ext::GraphExt;
+use itertools::Itertools;
+use snafu::OptionExt;
+
+use crate::compute::render::Context;
+use crate::compute::types::{Collection, CollectionBundle, ErrCollector, Toff};
+use crate::error::Error;
+use crate::expr::error::InternalSnafu;
+use crate::expr::{EvalError, ScalarExpr};
+use crate::plan::{ColumnOrder, TypedPlan};
+use crate::repr::{self, Diff, DiffRow, Row};
+use crate::utils::{ArrangeHandler, KeyExpiryManager};
+
+impl Context<'_, '_> {
+    /// Render a incremental top-k operator, see [`TopKState`] for how the top-k of each group is maintained
+    ///
+    /// If the input is `append_only`, only the top-k rows of each group are kept
+    pub fn render_top_k(
+        &mut self,
+        input: Box<TypedPlan>,
+        order_by: Vec<ColumnOrder>,
+        limit: usize,
+        per_key: Vec<usize>,
+        append_only: bool,
+    ) -> Result<CollectionBundle, Error> {
+        let time_index = input.schema.typ().time_index;
+        let input = self.render_plan(*input)?;
+        let (send_port, recv_port) = self.df.make_edge::<_, Toff>("top_k");
+        let now = self.compute_state.current_time_ref();
+        let err_collector = self.err_collector.with_operator("top_k");
+        let groups = self.new_group_index(per_key.len(), time_index);
+        let mut state =
+            TopKState::new(order_by, limit, per_key, groups).with_append_only(append_only);
+
+        let profiler = self.compute_state.profile_operator("top_k");
+
+        self.df.add_subgraph_in_out(
+            "top_k",
+            input.collection.into_inner(),
+            send_port,
+            move |_ctx, recv, send| {
+                let _timer = profiler.start();
+                let data = recv
+                    .take_inner()
+                    .into_iter()
+                    .flat_map(|v| v.into_iter())
+                    .inspect(|_| profiler.add_rows(1));
+                let output = state.apply_updates(data, *now.borrow(), &err_collector);
+                send.give(output);
+            },
+        );
+
+        Ok(CollectionBundle::from_collection(Collection::from_port(
+            recv_port,
+        )))
+    }
+
+    /// Create a [`GroupIndex`] backed by an arrangement of this dataflow, so it's expired, checkpointed
+    /// and accounted for like other state
+    ///
+    /// Rows are expired by their event time if the dataflow has `expire_after` and the input has a time index
+    pub(super) fn new_group_index(
+        &mut self,
+        group_arity: usize,
+        time_index: Option<usize>,
+    ) -> GroupIndex {
+        let arrange = self.compute_state.new_arrange(None);
+        arrange.set_full_arrangement(true);
+        if let Some(time_index) = time_index {
+            let expire_man = KeyExpiryManager::new(
+                self.compute_state.expire_after(),
+                Some(ScalarExpr::Column(group_arity + time_index)),
+            );
+            arrange.write().set_expire_state(expire_man);
+        }
+        GroupIndex::new(arrange, group_arity)
+    }
+}
+
+/// Value of a sort column, ordered according to its [`ColumnOrder`]
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub(super) enum SortValue {
+    Asc {
+        /// nulls are ranked by this flag first, so they can be placed either first or last
+        null_rank: bool,
+        value: Value,
+    },
+    Desc {
+        null_rank: bool,
+        value: Reverse<Value>,
+    },
+}
+
+impl SortValue {
+    pub(super) fn new(order: &ColumnOrder, value: Value) -> Self {
+        let null_rank = if order.nulls_last {
+            value.is_null()
+        } else {
+            !value.is_null()
+        };
+        if order.desc {
+            Self::Desc {
+                null_rank,
+                value: Reverse(value),
+            }
+        } else {
+            Self::Asc { null_rank, value }
+        }
+    }
+}
+
+/// All rows of a group, sorted by sort key and then the row itself, with their multiplicity
+pub(super) type GroupRows = BTreeMap<(Vec<SortValue>, Row), Diff>;
+
+/// Rows of each group with their multiplicity, used by operators that need all rows of a group(i.e. top-k and window)
+///
+/// Kept in an arrangement keyed by the group followed by the row, with the multiplicity of the row being the diff,
+/// so a row is removed from the state once it's fully retracted or expired
+pub(super) struct GroupIndex {
+    arrange: ArrangeHandler,
+    /// number of columns of the group, which is the prefix of keys in the arrangement
+    group_arity: usize,
+}
+
+impl GroupIndex {
+    pub(super) fn new(arrange: ArrangeHandler, group_arity: usize) -> Self {
+        Self {
+            arrange,
+            group_arity,
+        }
+    }
+
+    /// Add `diff` to the multiplicity of `row` in `group`, already expired rows are ignored
+    ///
+    /// return how much time the row is already expired by, if it's ignored
+    pub(super) fn update(
+        &self,
+        group: &Row,
+        row: &Row,
+        ts: repr::Timestamp,
+        diff: Diff,
+        now: repr::Timestamp,
+    ) -> Result<Option<repr::Duration>, EvalError> {
+        let key = Row::new(group.iter().chain(row.iter()).cloned().collect());
+        self.arrange
+            .write()
+            .apply_updates(now, vec![((key, Row::empty()), ts, diff)])
+    }
+
+    /// All rows of the given group, sorted by `order_by`
+    pub(super) fn get(
+        &self,
+        group: &Row,
+        order_by: &[ColumnOrder],
+        now: repr::Timestamp,
+    ) -> Result<GroupRows, EvalError> {
+        let mut rows = GroupRows::new();
+        for (key, (_, _, diff)) in self.arrange.read().get_by_prefix(now, group) {
+            let row = Row::new(key.inner[self.group_arity..].to_vec());
+            rows.insert((sort_key(order_by, &row)?, row), diff);
+        }
+        Ok(rows)
+    }
+
+    /// Consolidate all updates up to `now`, which also remove expired rows from the state
+    pub(super) fn compact_to(&self, now: repr::Timestamp) -> Result<(), EvalError> {
+        self.arrange.write().compact_to(now)?;
+        Ok(())
+    }
+}
+
+/// State of a top-k operator
+///
+/// All rows of each group are kept(instead of only the top-k rows) so that when a row in the top-k
+/// is retracted, the next row can be brought into the top-k. For each group touched in a tick,
+/// its top-k is computed before and after applying the updates, and the difference is output
+///
+/// If the input is append-only, a row out of the top-k can never be brought back, so it's removed
+/// from the state instead
+struct TopKState {
+    order_by: Vec<ColumnOrder>,
+    limit: usize,
+    per_key: Vec<usize>,
+    groups: GroupIndex,
+    append_only: bool,
+}
+
+impl TopKState {
+    fn new(
+        order_by: Vec<ColumnOrder>,
+        limit: usize,
+        per_key: Vec<usize>,
+        groups: GroupIndex,
+    ) -> Self {
+        Self {
+            order_by,
+            limit,
+            per_key,
+            groups,
+            append_only: false,
+        }
+    }
+
+    /// Only keep the top-k rows of each group, which requires the input to be append-only
+    fn with_append_only(mut self, append_only: bool) -> Self {
+        self.append_only = append_only;
+        self
+    }
+
+    fn apply_updates(
+        &mut self,
+        updates: impl IntoIterator<Item = DiffRow>,
+        now: repr::Timestamp,
+        err_collector: &ErrCollector,
+    ) -> Vec<DiffRow> {
+        let updates = updates
+            .into_iter()
+            .filter_map(|(row, ts, diff)| {
+                err_collector.run(|| {
+                    let group = project_row(&self.per_key, &row)?;
+                    Ok((group, row, ts, diff))
+                })
+            })
+            .collect_vec();
+
+        // top-k of touched groups before applying updates
+        let mut before = BTreeMap::new();
+        for (group, _, _, _) in &updates {
+            if !before.contains_key(group) {
+                if let Some(top_k) = err_collector.run(|| self.top_k(group, now)) {
+                    before.insert(group.clone(), top_k);
+                }
+            }
+        }
+
+        for (group, row, ts, diff) in updates {
+            let expired = err_collector.run(|| self.groups.update(&group, &row, ts, diff, now));
+            if let Some(expired_by) = expired.flatten() {
+                err_collector.push_late_row(&group, expired_by);
+            }
+        }
+
+        let mut output = Vec::new();
+        for (group, before) in before {
+            let Some(after) = err_collector.run(|| self.top_k(&group, now)) else {
+                continue;
+            };
+            if self.append_only {
+                err_collector.run(|| self.evict_out_of_top_k(&group, now));
+            }
+            let mut changes: BTreeMap<Row, Diff> = BTreeMap::new();
+            for (row, diff) in before {
+                *changes.entry(row).or_default() -= diff;
+            }
+            for (row, diff) in after {
+                *changes.entry(row).or_default() += diff;
+            }
+            output.extend(
+                changes
+                    .into_iter()
+                    .filter(|(_, diff)| *diff != 0)
+                    .map(|(row, diff)| (row, now, diff)),
+            );
+        }
+        err_collector.run(|| self.groups.compact_to(now));
+        output
+    }
+
+    /// Remove rows out of the top-k of the given group from the state
+    fn evict_out_of_top_k(&self, group: &Row, now: repr::Timestamp) -> Result<(), EvalError> {
+        let rows = self.groups.get(group, &self.order_by, now)?;
+        let mut remaining = self.limit as Diff;
+        for ((_, row), diff) in rows {
+            if diff <= 0 {
+                continue;
+            }
+            let kept = diff.min(remaining);
+            remaining -= kept;
+            let evicted = diff - kept;
+            if evicted > 0 {
+                self.groups.update(group, &row, now, -evicted, now)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Current top-k rows of the given group, with their multiplicity
+    fn top_k(&self, group: &Row, now: repr::Timestamp) -> Result<Vec<(Row, Diff)>, EvalError> {
+        let mut ret = Vec::new();
+        let rows = self.groups.get(group, &self.order_by, now)?;
+        let mut remaining = self.limit as Diff;
+        for ((_, row), diff) in rows {
+            if remaining <= 0 {
+                break;
+            }
+            // rows with non-positive multiplicity are not visible
+            if diff <= 0 {
+                continue;
+            }
+            let diff = diff.min(remaining);
+            remaining -= diff;
+            ret.push((row, diff));
+        }
+        Ok(ret)
+    }
+}
+
+/// Sort key of a row according to `order_by`
+pub(super) fn sort_key(order_by: &[ColumnOrder], row: &Row) -> Result<Vec<SortValue>, EvalError> {
+    order_by
+        .iter()
+        .map(|order| project_value(order.column, row).map(|v| SortValue::new(order, v)))
+        .collect()
+}
+
+pub(super) fn project_value(column: usize, row: &Row) -> Result<Value, EvalError> {
+    row.get(column).cloned().with_context(|| InternalSnafu {
+        reason: format!("Index {} out of bound for row {:?}", column, row),
+    })
+}
+
+pub(super) fn project_row(columns: &[usize], row: &Row) -> Result<Row, EvalError> {
+    let values = columns
+        .iter()
+        .map(|c| project_value(*c, row))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Row::new(values))
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::utils::Arrangement;
+
+    fn row(host: &str, errors: i64) -> Row {
+        Row::new(vec![host.into(), errors.into()])
+    }
+
+    /// create a group index in a full arrangement without expiry
+    fn new_group_index(group_arity: usize) -> GroupIndex {
+        let arrange = ArrangeHandler::from(Arrangement::default());
+        arrange.set_full_arrangement(true);
+        GroupIndex::new(arrange, group_arity)
+    }
+
+    /// top 2 hosts by error count
+    #[test]
+    fn test_top_k_with_retraction() {
+        let err_collector = ErrCollector::default();
+        let mut state = TopKState::new(
+            vec![ColumnOrder::new(1, true, true)],
+            2,
+            vec![],
+            new_group_index(0),
+        );
+
+        let output = state.apply_updates(
+            vec![
+                (row("a", 1), 0, 1),
+                (row("b", 5), 0, 1),
+                (row("c", 3), 0, 1),
+            ],
+            0,
+            &err_collector,
+        );
+        assert_eq!(output, vec![(row("b", 5), 0, 1), (row("c", 3), 0, 1)]);
+
+        // a row enter the top-k and push out the last one
+        let output = state.apply_updates(vec![(row("a", 10), 1, 1)], 1, &err_collector);
+        assert_eq!(output, vec![(row("a", 10), 1, 1), (row("c", 3), 1, -1)]);
+
+        // retract a row in top-k, the next row is brought back
+        let output = state.apply_updates(vec![(row("b", 5), 2, -1)], 2, &err_collector);
+        assert_eq!(output, vec![(row("b", 5), 2, -1), (row("c", 3), 2, 1)]);
+
+        // retract a row out of top-k, nothing changed
+        let output = state.apply_updates(vec![(row("a", 1), 3, -1)], 3, &err_collector);
+        assert_eq!(output, vec![]);
+        assert!(err_collector.is_empty());
+    }
+
+    /// rows out of the top-k of an append-only input are removed from the state
+    #[test]
+    fn test_append_only_top_k() {
+        let err_collector = ErrCollector::default();
+        let groups = new_group_index(0);
+        let arrange = groups.arrange.clone();
+        let mut state = TopKState::new(vec![ColumnOrder::new(1, true, true)], 2, vec![], groups)
+            .with_append_only(true);
+
+        let output = state.apply_updates(
+            vec![
+                (row("a", 1), 0, 1),
+                (row("b", 5), 0, 1),
+                (row("c", 3), 0, 1),
+            ],
+            0,
+            &err_collector,
+        );
+        assert_eq!(output, vec![(row("b", 5), 0, 1), (row("c", 3), 0, 1)]);
+
+        let output = state.apply_updates(vec![(row("a", 10), 1, 1)], 1, &err_collector);
+        assert_eq!(output, vec![(row("a", 10), 1, 1), (row("c", 3), 1, -1)]);
+
+        let kept = arrange
+            .read()
+            .get_by_prefix(1, &Row::empty())
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect_vec();
+        assert_eq!(kept, vec![row("a", 10), row("b", 5)]);
+        assert!(err_collector.is_empty());
+    }
+
+    /// rows already expired by the error count(as event time) are not kept in the state
+    #[test]
+    fn test_top_k_expired() {
+        let err_collector = ErrCollector::default();
+        let arrange = ArrangeHandler::from(Arrangement::default());
+        arrange.set_full_arrangement(true);
+        arrange
+            .write()
+            .set_expire_state(KeyExpiryManager::new(Some(5), Some(ScalarExpr::Column(1))));
+        let mut state = TopKState::new(
+            vec![ColumnOrder::new(1, false, true)],
+            1,
+            vec![],
+            GroupIndex::new(arrange, 0),
+        );
+
+        let output = state.apply_updates(
+            vec![(row("a", 1), 10, 1), (row("b", 6), 10, 1)],
+            10,
+            &err_collector,
+        );
+        assert_eq!(output, vec![(row("b", 6), 10, 1)]);
+        assert!(err_collector.is_empty());
+    }
+
+    /// top 1 row of each host, with nulls sorted first
+    #[test]
+    fn test_top_k_per_key() {
+        let err_collector = ErrCollector::default();
+        let mut state = TopKState::new(
+            vec![ColumnOrder::new(1, false, false)],
+            1,
+            vec![0],
+            new_group_index(1),
+        );
+
+        let output = state.apply_updates(
+            vec![
+                (row("a", 2), 0, 1),
+                (row("a", 1), 0, 1),
+                (row("b", 3), 0, 1),
+                (Row::new(vec!["b".into(), Value::Null]), 0, 1),
+            ],
+            0,
+            &err_collector,
+        );
+        assert_eq!(
+            output,
+            vec![
+                (row("a", 1), 0, 1),
+                (Row::new(vec!["b".into(), Value::Null]), 0, 1),
+            ]
+        );
+        assert!(err_collector.is_empty());
+    }
+}
@@ -0,0 +1,169 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+
+use itertools::Itertools;
+
+use crate::compute::render::Context;
+use crate::compute::types::{Collection, CollectionBundle, Toff};
+use crate::error::Error;
+use crate::plan::TypedPlan;
+use crate::repr::{self, Diff, DiffRow, Row};
+
+impl Context<'_, '_> {
+    /// Render a multiset union, which simply merge updates from all inputs into one collection
+    ///
+    /// if `consolidate_output` is true, updates of the same row and timestamp are merged
+    /// and the ones that cancel each other out are dropped
+    pub fn render_union(
+        &mut self,
+        inputs: Vec<TypedPlan>,
+        consolidate_output: bool,
+    ) -> Result<CollectionBundle, Error> {
+        let inputs = inputs
+            .into_iter()
+            .map(|input| {
+                self.render_plan(input)
+                    .map(|bundle| bundle.collection.into_inner())
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let (send_port, recv_port) = self.df.make_edge::<_, Toff>("union");
+        let profiler = self.compute_state.profile_operator("union");
+        self.df.add_subgraph_n_m(
+            "union",
+            inputs,
+            vec![send_port],
+            move |_ctx, recvs, sends| {
+                let _timer = profiler.start();
+                let data = recvs
+                    .iter()
+                    .flat_map(|recv| recv.take_inner())
+                    .flat_map(|v| v.into_iter())
+                    .inspect(|_| profiler.add_rows(1));
+                let output = if consolidate_output {
+                    consolidate(data)
+                } else {
+                    data.collect_vec()
+                };
+                if let Some(send) = sends.first() {
+                    send.give(output);
+                }
+            },
+        );
+
+        Ok(CollectionBundle::from_collection(Collection::from_port(
+            recv_port,
+        )))
+    }
+}
+
+/// Sum up the diffs of the same row at the same time, and drop those with zero diff
+///
+/// The output is ordered by row then time, so updates of the same key are adjacent
+pub(super) fn consolidate(updates: impl IntoIterator<Item = DiffRow>) -> Vec<DiffRow> {
+    let mut consolidated: BTreeMap<(Row, repr::Timestamp), Diff> = BTreeMap::new();
+    for (row, ts, diff) in updates {
+        *consolidated.entry((row, ts)).or_default() += diff;
+    }
+    consolidated
+        .into_iter()
+        .filter(|(_, diff)| *diff != 0)
+        .map(|((row, ts), diff)| (row, ts, diff))
+        .collect_vec()
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use datatypes::data_type::ConcreteDataType;
+    use hydroflow::scheduled::graph::Hydroflow;
+
+    use super::*;
+    use crate::compute::render::test::{get_output_handle, harness_test_ctx, run_and_check};
+    use crate::compute::state::DataflowState;
+    use crate::expr::{self, GlobalId, MapFilterProject};
+    use crate::plan::Plan;
+    use crate::repr::{ColumnType, RelationType};
+
+    fn get_plan(id: u64) -> TypedPlan {
+        let typ = RelationType::new(vec![ColumnType::new_nullable(
+            ConcreteDataType::int64_datatype(),
+        )]);
+        Plan::Get {
+            id: expr::Id::Global(GlobalId::User(id)),
+        }
+        .with_types(typ.into_unnamed())
+    }
+
+    fn render_union_of_two(consolidate_output: bool, expected: BTreeMap<i64, Vec<DiffRow>>) {
+        let mut df = Hydroflow::new();
+        let mut state = DataflowState::default();
+        let mut ctx = harness_test_ctx(&mut df, &mut state);
+
+        let left_rows = vec![
+            (Row::new(vec![1i64.into()]), 0, 1),
+            (Row::new(vec![2i64.into()]), 1, 1),
+        ];
+        let right_rows = vec![
+            (Row::new(vec![1i64.into()]), 0, 1),
+            (Row::new(vec![2i64.into()]), 1, -1),
+        ];
+        let left = ctx.render_constant(left_rows);
+        let right = ctx.render_constant(right_rows);
+        ctx.insert_global(GlobalId::User(1), left);
+        ctx.insert_global(GlobalId::User(2), right);
+
+        let union = Plan::Union {
+            inputs: vec![get_plan(1), get_plan(2)],
+            consolidate_output,
+        }
+        .with_types(get_plan(1).schema);
+        let bundle = ctx
+            .render_mfp(Box::new(union), MapFilterProject::new(1))
+            .unwrap();
+        let output = get_output_handle(&mut ctx, bundle);
+        drop(ctx);
+        run_and_check(&mut state, &mut df, 0..3, expected, output);
+    }
+
+    #[test]
+    fn test_render_union() {
+        let expected = BTreeMap::from([
+            (
+                0,
+                vec![
+                    (Row::new(vec![1i64.into()]), 0, 1),
+                    (Row::new(vec![1i64.into()]), 0, 1),
+                ],
+            ),
+            (
+                1,
+                vec![
+                    (Row::new(vec![2i64.into()]), 1, 1),
+                    (Row::new(vec![2i64.into()]), 1, -1),
+                ],
+            ),
+        ]);
+        render_union_of_two(false, expected);
+    }
+
+    #[test]
+    fn test_render_union_consolidate() {
+        let expected = BTreeMap::from([(0, vec![(Row::new(vec![1i64.into()]), 0, 2)])]);
+        render_union_of_two(true, expected);
+    }
+}
@@ -0,0 +1,533 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+
+use datatypes::data_type::ConcreteDataType;
+use datatypes::value::Value;
+use hydroflow::scheduled::graph_ext::GraphExt;
+use itertools::Itertools;
+use snafu::{ensure, OptionExt};
+
+use crate::compute::render::top_k::{project_row, project_value, GroupIndex};
+use crate::compute::render::Context;
+use crate::compute::types::{Collection, CollectionBundle, ErrCollector, Toff};
+use crate::error::Error;
+use crate::expr::error::{InternalSnafu, OverflowSnafu, TypeMismatchSnafu};
+use crate::expr::EvalError;
+use crate::plan::{ColumnOrder, FrameUnits, TypedPlan, WindowExpr, WindowFrame, WindowFunc};
+use crate::repr::{self, value_to_internal_ts, Diff, DiffRow, Row};
+
+impl Context<'_, '_> {
+    /// Render a window operator, see [`WindowState`] for how the window values are maintained
+    pub fn render_window(
+        &mut self,
+        input: Box<TypedPlan>,
+        partition_by: Vec<usize>,
+        order_by: Vec<ColumnOrder>,
+        exprs: Vec<WindowExpr>,
+    ) -> Result<CollectionBundle, Error> {
+        let time_index = input.schema.typ().time_index;
+        let input = self.render_plan(*input)?;
+        let (send_port, recv_port) = self.df.make_edge::<_, Toff>("window");
+        let now = self.compute_state.current_time_ref();
+        let err_collector = self.err_collector.with_operator("window");
+        let partitions = self.new_group_index(partition_by.len(), time_index);
+        let mut state = WindowState::new(partition_by, order_by, exprs, partitions);
+
+        let profiler = self.compute_state.profile_operator("window");
+
+        self.df.add_subgraph_in_out(
+            "window",
+            input.collection.into_inner(),
+            send_port,
+            move |_ctx, recv, send| {
+                let _timer = profiler.start();
+                let data = recv
+                    .take_inner()
+                    .into_iter()
+                    .flat_map(|v| v.into_iter())
+                    .inspect(|_| profiler.add_rows(1));
+                let output = state.apply_updates(data, *now.borrow(), &err_collector);
+                send.give(output);
+            },
+        );
+
+        Ok(CollectionBundle::from_collection(Collection::from_port(
+            recv_port,
+        )))
+    }
+}
+
+/// State of a window operator
+///
+/// All rows of each partition are kept sorted, since a single row change can shift the window
+/// values(i.e. `row_number` or `lag`) of all rows after it. For each partition touched in a tick,
+/// the output rows are computed before and after applying the updates, and the difference is output
+struct WindowState {
+    partition_by: Vec<usize>,
+    order_by: Vec<ColumnOrder>,
+    exprs: Vec<WindowExpr>,
+    partitions: GroupIndex,
+}
+
+impl WindowState {
+    fn new(
+        partition_by: Vec<usize>,
+        order_by: Vec<ColumnOrder>,
+        exprs: Vec<WindowExpr>,
+        partitions: GroupIndex,
+    ) -> Self {
+        Self {
+            partition_by,
+            order_by,
+            exprs,
+            partitions,
+        }
+    }
+
+    fn apply_updates(
+        &mut self,
+        updates: impl IntoIterator<Item = DiffRow>,
+        now: repr::Timestamp,
+        err_collector: &ErrCollector,
+    ) -> Vec<DiffRow> {
+        let updates = updates
+            .into_iter()
+            .filter_map(|(row, ts, diff)| {
+                err_collector.run(|| {
+                    let partition = project_row(&self.partition_by, &row)?;
+                    Ok((partition, row, ts, diff))
+                })
+            })
+            .collect_vec();
+
+        // output of touched partitions before applying updates
+        let mut before = BTreeMap::new();
+        for (partition, _, _, _) in &updates {
+            if !before.contains_key(partition) {
+                let rows = err_collector
+                    .run(|| self.eval_partition(partition, now))
+                    .unwrap_or_default();
+                before.insert(partition.clone(), rows);
+            }
+        }
+
+        for (partition, row, ts, diff) in updates {
+            let expired =
+                err_collector.run(|| self.partitions.update(&partition, &row, ts, diff, now));
+            if let Some(expired_by) = expired.flatten() {
+                err_collector.push_late_row(&partition, expired_by);
+            }
+        }
+
+        let mut output = Vec::new();
+        for (partition, before) in before {
+            let after = err_collector
+                .run(|| self.eval_partition(&partition, now))
+                .unwrap_or_default();
+            let mut changes: BTreeMap<Row, Diff> = BTreeMap::new();
+            for row in before {
+                *changes.entry(row).or_default() -= 1;
+            }
+            for row in after {
+                *changes.entry(row).or_default() += 1;
+            }
+            output.extend(
+                changes
+                    .into_iter()
+                    .filter(|(_, diff)| *diff != 0)
+                    .map(|(row, diff)| (row, now, diff)),
+            );
+        }
+        err_collector.run(|| self.partitions.compact_to(now));
+        output
+    }
+
+    /// Current output rows of the given partition, i.e. each input row with window values appended
+    ///
+    /// a row with multiplicity `n` is treated as `n` separate rows, so each copy is output once
+    fn eval_partition(&self, partition: &Row, now: repr::Timestamp) -> Result<Vec<Row>, EvalError> {
+        let rows = self.partitions.get(partition, &self.order_by, now)?;
+        // rows with non-positive multiplicity are not visible
+        let rows = rows
+            .iter()
+            .filter(|(_, diff)| **diff > 0)
+            .flat_map(|((_, row), diff)| std::iter::repeat(row).take(*diff as usize))
+            .collect_vec();
+
+        let columns = self
+            .exprs
+            .iter()
+            .map(|expr| self.eval_expr(expr, &rows))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut output = rows.into_iter().cloned().collect_vec();
+        for column in columns {
+            for (row, value) in output.iter_mut().zip(column) {
+                row.extend([value]);
+            }
+        }
+        Ok(output)
+    }
+
+    /// Evaluate a window function for each of the sorted rows of a partition
+    fn eval_expr(&self, expr: &WindowExpr, rows: &[&Row]) -> Result<Vec<Value>, EvalError> {
+        let arg = || {
+            expr.arg.with_context(|| InternalSnafu {
+                reason: format!("Window function {:?} expect an argument", expr.func),
+            })
+        };
+        let shifted = |i: Option<usize>| -> Result<Value, EvalError> {
+            match i.and_then(|i| rows.get(i)) {
+                Some(row) => project_value(arg()?, row),
+                None => Ok(Value::Null),
+            }
+        };
+        match expr.func {
+            WindowFunc::RowNumber => Ok((1..=rows.len() as u64).map(Value::from).collect()),
+            WindowFunc::Lag { offset } => (0..rows.len())
+                .map(|i| shifted(i.checked_sub(offset)))
+                .collect(),
+            WindowFunc::Lead { offset } => (0..rows.len())
+                .map(|i| shifted(i.checked_add(offset)))
+                .collect(),
+            WindowFunc::Sum => {
+                let arg = arg()?;
+                let frames = self.eval_frames(&expr.frame, rows)?;
+                frames
+                    .into_iter()
+                    .map(|frame| {
+                        let values = frame
+                            .into_iter()
+                            .map(|j| project_value(arg, rows[j]))
+                            .collect::<Result<Vec<_>, _>>()?;
+                        sum_values(values)
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// The indices of rows in the frame of each row
+    fn eval_frames(
+        &self,
+        frame: &WindowFrame,
+        rows: &[&Row],
+    ) -> Result<Vec<Vec<usize>>, EvalError> {
+        let len = rows.len() as i64;
+        match frame.units {
+            FrameUnits::Rows => Ok((0..len)
+                .map(|i| {
+                    let start = frame.preceding.map(|p| (i - p).max(0)).unwrap_or(0);
+                    let end = frame
+                        .following
+                        .map(|f| (i + f).min(len - 1))
+                        .unwrap_or(len - 1);
+                    (start..=end).map(|j| j as usize).collect_vec()
+                })
+                .collect()),
+            FrameUnits::Range => {
+                let order = self.order_by.first().with_context(|| InternalSnafu {
+                    reason: "Range frame requires a sort column",
+                })?;
+                // rows with null sort value are only peers of each other
+                let keys = rows
+                    .iter()
+                    .map(|row| {
+                        let value = project_value(order.column, row)?;
+                        if value.is_null() {
+                            Ok(None)
+                        } else {
+                            value_to_internal_ts(value).map(Some)
+                        }
+                    })
+                    .collect::<Result<Vec<_>, EvalError>>()?;
+                // for descending order, preceding rows have greater sort values
+                let (below, above) = if order.desc {
+                    (frame.following, frame.preceding)
+                } else {
+                    (frame.preceding, frame.following)
+                };
+                Ok(keys
+                    .iter()
+                    .map(|cur| {
+                        keys.iter()
+                            .positions(|key| match (cur, key) {
+                                (Some(cur), Some(key)) => {
+                                    below.map(|b| *key >= cur - b).unwrap_or(true)
+                                        && above.map(|a| *key <= cur + a).unwrap_or(true)
+                                }
+                                (None, None) => true,
+                                _ => false,
+                            })
+                            .collect_vec()
+                    })
+                    .collect())
+            }
+        }
+    }
+}
+
+/// Sum up non-null values, integers are summed as `i64` and floats as `f64`
+///
+/// return null if there is no non-null value
+fn sum_values(values: Vec<Value>) -> Result<Value, EvalError> {
+    let mut int_sum: Option<i64> = None;
+    let mut float_sum: Option<f64> = None;
+    for value in values {
+        let int = match value {
+            Value::Null => continue,
+            Value::Float32(v) => {
+                *float_sum.get_or_insert(0.0) += v.0 as f64;
+                continue;
+            }
+            Value::Float64(v) => {
+                *float_sum.get_or_insert(0.0) += v.0;
+                continue;
+            }
+            Value::Int8(v) => v as i64,
+            Value::Int16(v) => v as i64,
+            Value::Int32(v) => v as i64,
+            Value::Int64(v) => v,
+            Value::UInt8(v) => v as i64,
+            Value::UInt16(v) => v as i64,
+            Value::UInt32(v) => v as i64,
+            Value::UInt64(v) => i64::try_from(v).map_err(|_| OverflowSnafu.build())?,
+            other => {
+                return TypeMismatchSnafu {
+                    expected: ConcreteDataType::int64_datatype(),
+                    actual: other.data_type(),
+                }
+                .fail()
+            }
+        };
+        let sum = int_sum.get_or_insert(0);
+        *sum = sum.checked_add(int).context(OverflowSnafu)?;
+    }
+    ensure!(
+        int_sum.is_none() || float_sum.is_none(),
+        InternalSnafu {
+            reason: "Can't sum up integers and floats together",
+        }
+    );
+    Ok(float_sum
+        .map(Value::from)
+        .or_else(|| int_sum.map(Value::from))
+        .unwrap_or(Value::Null))
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::utils::{ArrangeHandler, Arrangement};
+
+    fn row(host: &str, ts: i64, cpu: i64) -> Row {
+        Row::new(vec![host.into(), ts.into(), cpu.into()])
+    }
+
+    /// create a group index in a full arrangement without expiry
+    fn new_group_index(group_arity: usize) -> GroupIndex {
+        let arrange = ArrangeHandler::from(Arrangement::default());
+        arrange.set_full_arrangement(true);
+        GroupIndex::new(arrange, group_arity)
+    }
+
+    fn output(input: Row, window_values: Vec<Value>) -> Row {
+        let mut row = input;
+        row.extend(window_values);
+        row
+    }
+
+    /// row_number, lag, lead and a moving sum of two preceding rows, over rows of each host sorted by ts
+    #[test]
+    fn test_window_rows_frame() {
+        let err_collector = ErrCollector::default();
+        let exprs = vec![
+            WindowExpr {
+                func: WindowFunc::RowNumber,
+                arg: None,
+                frame: Default::default(),
+            },
+            WindowExpr {
+                func: WindowFunc::Lag { offset: 1 },
+                arg: Some(2),
+                frame: Default::default(),
+            },
+            WindowExpr {
+                func: WindowFunc::Lead { offset: 1 },
+                arg: Some(2),
+                frame: Default::default(),
+            },
+            WindowExpr {
+                func: WindowFunc::Sum,
+                arg: Some(2),
+                frame: WindowFrame {
+                    units: FrameUnits::Rows,
+                    preceding: Some(2),
+                    following: Some(0),
+                },
+            },
+        ];
+        let mut state = WindowState::new(
+            vec![0],
+            vec![ColumnOrder::new(1, false, true)],
+            exprs,
+            new_group_index(1),
+        );
+
+        let out = state.apply_updates(
+            vec![(row("a", 1, 10), 0, 1), (row("a", 3, 30), 0, 1)],
+            0,
+            &err_collector,
+        );
+        assert_eq!(
+            out,
+            vec![
+                (
+                    output(
+                        row("a", 1, 10),
+                        vec![1u64.into(), Value::Null, 30i64.into(), 10i64.into()]
+                    ),
+                    0,
+                    1
+                ),
+                (
+                    output(
+                        row("a", 3, 30),
+                        vec![2u64.into(), 10i64.into(), Value::Null, 40i64.into()]
+                    ),
+                    0,
+                    1
+                ),
+            ]
+        );
+
+        // an out of order row is inserted in the middle, shifting window values of the row after it
+        let out = state.apply_updates(vec![(row("a", 2, 20), 1, 1)], 1, &err_collector);
+        assert_eq!(
+            out,
+            vec![
+                (
+                    output(
+                        row("a", 1, 10),
+                        vec![1u64.into(), Value::Null, 20i64.into(), 10i64.into()]
+                    ),
+                    1,
+                    1
+                ),
+                (
+                    output(
+                        row("a", 1, 10),
+                        vec![1u64.into(), Value::Null, 30i64.into(), 10i64.into()]
+                    ),
+                    1,
+                    -1
+                ),
+                (
+                    output(
+                        row("a", 2, 20),
+                        vec![2u64.into(), 10i64.into(), 30i64.into(), 30i64.into()]
+                    ),
+                    1,
+                    1
+                ),
+                (
+                    output(
+                        row("a", 3, 30),
+                        vec![2u64.into(), 10i64.into(), Value::Null, 40i64.into()]
+                    ),
+                    1,
+                    -1
+                ),
+                (
+                    output(
+                        row("a", 3, 30),
+                        vec![3u64.into(), 20i64.into(), Value::Null, 60i64.into()]
+                    ),
+                    1,
+                    1
+                ),
+            ]
+        );
+
+        // other partitions are not affected
+        let out = state.apply_updates(vec![(row("b", 1, 5), 2, 1)], 2, &err_collector);
+        assert_eq!(
+            out,
+            vec![(
+                output(
+                    row("b", 1, 5),
+                    vec![1u64.into(), Value::Null, Value::Null, 5i64.into()]
+                ),
+                2,
+                1
+            )]
+        );
+        assert!(err_collector.is_empty());
+    }
+
+    /// moving sum over the last 10 ms, which is time-bounded instead of row-bounded
+    #[test]
+    fn test_window_range_frame() {
+        let err_collector = ErrCollector::default();
+        let exprs = vec![WindowExpr {
+            func: WindowFunc::Sum,
+            arg: Some(2),
+            frame: WindowFrame {
+                units: FrameUnits::Range,
+                preceding: Some(10),
+                following: Some(0),
+            },
+        }];
+        let mut state = WindowState::new(
+            vec![],
+            vec![ColumnOrder::new(1, false, true)],
+            exprs,
+            new_group_index(0),
+        );
+
+        let out = state.apply_updates(
+            vec![
+                (row("a", 0, 1), 0, 1),
+                (row("a", 5, 2), 0, 1),
+                (row("a", 15, 4), 0, 1),
+            ],
+            0,
+            &err_collector,
+        );
+        assert_eq!(
+            out,
+            vec![
+                (output(row("a", 0, 1), vec![1i64.into()]), 0, 1),
+                (output(row("a", 5, 2), vec![3i64.into()]), 0, 1),
+                (output(row("a", 15, 4), vec![6i64.into()]), 0, 1),
+            ]
+        );
+
+        // retract a row, only the rows with it in their frames are updated
+        let out = state.apply_updates(vec![(row("a", 0, 1), 1, -1)], 1, &err_collector);
+        assert_eq!(
+            out,
+            vec![
+                (output(row("a", 0, 1), vec![1i64.into()]), 1, -1),
+                (output(row("a", 5, 2), vec![2i64.into()]), 1, 1),
+                (output(row("a", 5, 2), vec![3i64.into()]), 1, -1),
+            ]
+        );
+        assert!(err_collector.is_empty());
+    }
+}
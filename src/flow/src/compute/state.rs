@@ -12,16 +12,22 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::{BTreeMap, VecDeque};
+use std::path::PathBuf;
 use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 use hydroflow::scheduled::graph::Hydroflow;
 use hydroflow::scheduled::SubgraphId;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
 
 use crate::compute::types::ErrCollector;
-use crate::repr::{self, Timestamp};
-use crate::utils::{ArrangeHandler, Arrangement};
+use crate::error::{Error, InternalSnafu};
+use crate::expr::{Batch, EvalError, ScalarExpr};
+use crate::repr::{self, Timestamp, BROADCAST_CAP};
+use crate::utils::{ArrangeHandler, Arrangement, ArrangementSnapshot, EvictionPolicy};
 
 /// input/output of a dataflow
 /// One `ComputeState` manage the input/output/schedule of one `Hydroflow`
@@ -42,13 +48,72 @@ pub struct DataflowState {
     /// save all used arrange in this dataflow, since usually there is no delete operation
     /// we can just keep track of all used arrange and schedule subgraph when they need to be updated
     arrange_used: Vec<ArrangeHandler>,
+    /// arrangements holding future updates and the subgraph to run when their updates are due,
+    /// so the subgraph can be rescheduled after the arrangement is restored from a checkpoint
+    timed_arranges: Vec<(ArrangeHandler, SubgraphId)>,
     /// the time arrangement need to be expired after a certain time in milliseconds
     expire_after: Option<Timestamp>,
+    /// the time each key expires at, evaluated on rows of the source table, see
+    /// [`KeyExpiryManager::new_expire_when`](crate::utils::KeyExpiryManager::new_expire_when)
+    expire_when: Option<ScalarExpr>,
+    /// spill cold keys of arrangements to disk once the state exceeds the memory budget
+    spill: Option<SpillOptions>,
+    /// evict keys of arrangements once the state exceeds the size limit
+    eviction: Option<EvictionOptions>,
+    /// execution statistics of each operator, only recorded when profiling is enabled
+    profiler: Profiler,
+    /// when the dataflow advances its current time and runs
+    tick_policy: TickPolicy,
+    /// the time of the last tick
+    last_tick: Option<Timestamp>,
+    /// the time input is first found pending since the last tick
+    pending_since: Option<Timestamp>,
+    /// channels of batch mode sources, checked for pending input
+    inputs: Vec<Rc<RefCell<mpsc::Receiver<Batch>>>>,
+}
+
+/// When a dataflow advances its current time and runs, which trades latency of results for
+/// throughput, as a dataflow processes inputs arrived since the last tick all at once
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TickPolicy {
+    /// tick every time the worker runs, which is driven by the wall clock
+    #[default]
+    EveryRun,
+    /// tick at most once per interval in milliseconds
+    Interval(repr::Duration),
+    /// tick once input arrives and has waited `max_delay` milliseconds for more input to be
+    /// processed in the same tick, or a source channel is half full
+    ///
+    /// Updates driven by time only, like expiring keys or temporal filters, are delayed until
+    /// the next tick
+    OnData { max_delay: repr::Duration },
+    /// only tick when requested with the time to tick to, so tests are deterministic
+    Manual,
+}
+
+/// Where and when to spill the state of a dataflow to disk
+#[derive(Debug, Clone)]
+pub struct SpillOptions {
+    /// directory spilled files of this dataflow are in, shouldn't be shared with other dataflows
+    pub dir: PathBuf,
+    /// estimated bytes of the in memory state of this dataflow, above which cold keys are spilled
+    pub memory_budget: usize,
+}
+
+/// When and how to evict the state of a dataflow, so one dataflow can't take up all memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EvictionOptions {
+    /// estimated bytes of the in memory state of this dataflow, above which keys are evicted
+    pub max_state_size: usize,
+    pub policy: EvictionPolicy,
 }
 
 impl DataflowState {
     pub fn new_arrange(&mut self, name: Option<Vec<String>>) -> ArrangeHandler {
-        let arrange = name.map(Arrangement::new_with_name).unwrap_or_default();
+        let mut arrange = name.map(Arrangement::new_with_name).unwrap_or_default();
+        if let Some(spill) = &self.spill {
+            arrange.enable_spill(spill.dir.join(self.arrange_used.len().to_string()));
+        }
 
         let arr = ArrangeHandler::from(arrange);
         // mark this arrange as used in this dataflow
@@ -59,6 +124,13 @@ impl DataflowState {
         arr
     }
 
+    /// Register `subgraph` to be scheduled at the time of the next update in `arrange`
+    /// once the arrangement is restored from a checkpoint, as the subgraph schedules itself only
+    /// when it runs
+    pub fn register_timed_arrange(&mut self, arrange: ArrangeHandler, subgraph: SubgraphId) {
+        self.timed_arranges.push((arrange, subgraph));
+    }
+
     /// schedule all subgraph that need to run with time <= `as_of` and run_available()
     ///
     /// return true if any subgraph actually executed
@@ -109,6 +181,296 @@ impl DataflowState {
     pub fn expire_after(&self) -> Option<Timestamp> {
         self.expire_after
     }
+
+    pub fn set_expire_when(&mut self, expire_when: Option<ScalarExpr>) {
+        self.expire_when = expire_when;
+    }
+
+    pub fn expire_when(&self) -> Option<&ScalarExpr> {
+        self.expire_when.as_ref()
+    }
+
+    /// Change `expire_after` of an already rendered dataflow, updating the expire state of its arrangements in place.
+    ///
+    /// Arrangements with a time index always have an expire state, so keys are expired by the new
+    /// `expire_after` from the next compaction on, even if the dataflow is rendered without it.
+    pub fn alter_expire_after(&mut self, after: Option<repr::Duration>) {
+        self.expire_after = after;
+        for arr in self.arrange_used.iter() {
+            arr.write().set_expire_after(after);
+        }
+    }
+
+    pub fn set_spill(&mut self, spill: Option<SpillOptions>) {
+        self.spill = spill;
+    }
+
+    /// Spill the least recently updated keys of the largest arrangements until the in memory
+    /// state is within the memory budget, return the number of keys spilled.
+    ///
+    /// Errors of spilling or reading spilled keys are pushed to the error collector.
+    pub fn spill_to_budget(&self) -> usize {
+        let Some(spill) = &self.spill else {
+            return 0;
+        };
+        for arr in self.arrange_used.iter() {
+            for err in arr.read().take_spill_errors() {
+                self.err_collector.push_err(err);
+            }
+        }
+        self.shrink_to(spill.memory_budget, |arr, bytes| match arr.spill(bytes) {
+            Ok(cnt) => cnt,
+            Err(err) => {
+                self.err_collector.push_err(err);
+                0
+            }
+        })
+    }
+
+    pub fn set_eviction(&mut self, eviction: Option<EvictionOptions>) {
+        self.eviction = eviction;
+    }
+
+    /// Evict keys of the largest arrangements by the [`EvictionPolicy`] until the in memory
+    /// state is within the size limit, return the number of keys evicted.
+    pub fn evict_to_limit(&self) -> usize {
+        let Some(eviction) = self.eviction else {
+            return 0;
+        };
+        self.shrink_to(eviction.max_state_size, |arr, bytes| {
+            arr.evict(bytes, eviction.policy)
+        })
+    }
+
+    /// Shrink the largest arrangements first by `shrink`, which is given the bytes to free and
+    /// returns the number of keys removed, until the in memory state is within `limit` bytes
+    fn shrink_to(
+        &self,
+        limit: usize,
+        mut shrink: impl FnMut(&mut Arrangement, usize) -> usize,
+    ) -> usize {
+        let (_, mut bytes) = self.state_size();
+        if bytes <= limit {
+            return 0;
+        }
+        let mut arrs = self
+            .arrange_used
+            .iter()
+            .map(|arr| (arr.read().state_size().1, arr))
+            .collect::<Vec<_>>();
+        arrs.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+        let mut removed = 0;
+        for (size, arr) in arrs {
+            if bytes <= limit {
+                break;
+            }
+            let mut arr = arr.write();
+            removed += shrink(&mut arr, bytes - limit);
+            bytes = bytes - size + arr.state_size().1;
+        }
+        removed
+    }
+
+    /// Number of updates in all arrangements used in this dataflow and their estimated size in bytes
+    pub fn state_size(&self) -> (usize, usize) {
+        self.arrange_used
+            .iter()
+            .map(|arr| arr.read().state_size())
+            .fold((0, 0), |(rows, bytes), (r, b)| (rows + r, bytes + b))
+    }
+
+    pub fn set_tick_policy(&mut self, policy: TickPolicy) {
+        self.tick_policy = policy;
+    }
+
+    /// Register the channel of a batch mode source, so pending input in it can trigger a tick
+    pub fn register_input(
+        &mut self,
+        input: mpsc::Receiver<Batch>,
+    ) -> Rc<RefCell<mpsc::Receiver<Batch>>> {
+        let input = Rc::new(RefCell::new(input));
+        self.inputs.push(input.clone());
+        input
+    }
+
+    /// Whether the dataflow should tick at `now` by its [`TickPolicy`]
+    pub fn should_tick(&mut self, now: Timestamp) -> bool {
+        match self.tick_policy {
+            TickPolicy::EveryRun => true,
+            TickPolicy::Interval(interval) => self
+                .last_tick
+                .map_or(true, |last_tick| now - last_tick >= interval),
+            TickPolicy::OnData { max_delay } => {
+                let pending = self.inputs.iter().map(|input| input.borrow().len()).max();
+                match pending {
+                    Some(len) if len > 0 => {
+                        let since = *self.pending_since.get_or_insert(now);
+                        len >= BROADCAST_CAP / 2 || now - since >= max_delay
+                    }
+                    _ => false,
+                }
+            }
+            TickPolicy::Manual => false,
+        }
+    }
+
+    /// Record a tick at `now`, which is when the dataflow is run with current time `now`
+    pub fn ticked(&mut self, now: Timestamp) {
+        self.last_tick = Some(now);
+        self.pending_since = None;
+    }
+
+    /// Register an operator to profile, in the order operators are rendered
+    pub fn profile_operator(&self, name: &'static str) -> OperatorProfiler {
+        let mut profiles = self.profiler.profiles.borrow_mut();
+        profiles.push(OperatorProfile::new(name));
+        OperatorProfiler {
+            enabled: self.profiler.enabled.clone(),
+            profiles: self.profiler.profiles.clone(),
+            idx: profiles.len() - 1,
+        }
+    }
+
+    /// Enable or disable profiling, statistics are reset when it's enabled
+    pub fn set_profiling(&self, enabled: bool) {
+        if enabled && !self.profiler.enabled.get() {
+            for profile in self.profiler.profiles.borrow_mut().iter_mut() {
+                *profile = OperatorProfile::new(profile.name);
+            }
+        }
+        self.profiler.enabled.set(enabled);
+    }
+
+    pub fn is_profiling(&self) -> bool {
+        self.profiler.enabled.get()
+    }
+
+    /// Execution statistics of each operator, in the order they are rendered
+    pub fn profiles(&self) -> Vec<OperatorProfile> {
+        self.profiler.profiles.borrow().clone()
+    }
+
+    /// Take a checkpoint of all arrangements used in this dataflow, in the order they are rendered
+    pub fn checkpoint(&self) -> Result<DataflowCheckpoint, EvalError> {
+        Ok(DataflowCheckpoint {
+            as_of: self.current_ts(),
+            arrangements: self
+                .arrange_used
+                .iter()
+                .map(|arr| arr.read().snapshot())
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Restore all arrangements from a checkpoint taken from a dataflow rendered from the same plan,
+    /// and reschedule subgraphs waiting for future updates in them
+    pub fn restore(&mut self, checkpoint: DataflowCheckpoint) -> Result<(), Error> {
+        if checkpoint.arrangements.len() != self.arrange_used.len() {
+            return InternalSnafu {
+                reason: format!(
+                    "Checkpoint has {} arrangements, but dataflow has {}",
+                    checkpoint.arrangements.len(),
+                    self.arrange_used.len()
+                ),
+            }
+            .fail();
+        }
+        for (arr, snapshot) in self.arrange_used.iter().zip(checkpoint.arrangements) {
+            arr.write().restore(snapshot);
+        }
+        self.set_current_ts(checkpoint.as_of);
+        let mut schedule_subgraph = self.schedule_subgraph.borrow_mut();
+        for (arr, subgraph) in self.timed_arranges.iter() {
+            if let Some(next_run_time) = arr.read().get_next_update_time(&checkpoint.as_of) {
+                schedule_subgraph
+                    .entry(next_run_time)
+                    .or_default()
+                    .push_back(*subgraph);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// State of a dataflow at time `as_of`, which can be restored into a dataflow rendered from the same plan
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DataflowCheckpoint {
+    pub as_of: Timestamp,
+    /// snapshots of arrangements in the order they are rendered
+    pub arrangements: Vec<ArrangementSnapshot>,
+}
+
+#[derive(Debug, Default)]
+struct Profiler {
+    enabled: Rc<Cell<bool>>,
+    profiles: Rc<RefCell<Vec<OperatorProfile>>>,
+}
+
+/// Execution statistics of an operator since profiling is enabled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperatorProfile {
+    pub name: &'static str,
+    /// number of times the operator's subgraph is run
+    pub ticks: u64,
+    /// number of rows the operator received
+    pub rows: u64,
+    /// time spent running the operator's subgraph, which is cpu time as the dataflow
+    /// is run by a single thread
+    pub cpu_time: Duration,
+}
+
+impl OperatorProfile {
+    fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            ticks: 0,
+            rows: 0,
+            cpu_time: Duration::ZERO,
+        }
+    }
+}
+
+/// Records execution statistics of one operator, shared with `DataflowState` and moved into
+/// the operator's subgraph
+#[derive(Debug, Clone)]
+pub struct OperatorProfiler {
+    enabled: Rc<Cell<bool>>,
+    profiles: Rc<RefCell<Vec<OperatorProfile>>>,
+    idx: usize,
+}
+
+impl OperatorProfiler {
+    /// Record a run of the operator's subgraph, which lasts until the returned timer is dropped
+    ///
+    /// return `None` if profiling is disabled
+    pub fn start(&self) -> Option<ProfileTimer<'_>> {
+        self.enabled.get().then(|| ProfileTimer {
+            profiler: self,
+            start: Instant::now(),
+        })
+    }
+
+    pub fn add_rows(&self, rows: usize) {
+        if self.enabled.get() {
+            self.profiles.borrow_mut()[self.idx].rows += rows as u64;
+        }
+    }
+}
+
+/// Add the elapsed time and a tick to the operator's statistics on drop
+pub struct ProfileTimer<'a> {
+    profiler: &'a OperatorProfiler,
+    start: Instant,
+}
+
+impl Drop for ProfileTimer<'_> {
+    fn drop(&mut self) {
+        let mut profiles = self.profiler.profiles.borrow_mut();
+        let profile = &mut profiles[self.profiler.idx];
+        profile.ticks += 1;
+        profile.cpu_time += self.start.elapsed();
+    }
 }
 
 #[derive(Debug, Clone)]
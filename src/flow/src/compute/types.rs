@@ -13,7 +13,7 @@
 // limitations under the License.
 
 use std::cell::RefCell;
-use std::collections::{BTreeMap, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use std::rc::Rc;
 use std::sync::Arc;
 
@@ -25,7 +25,7 @@ use itertools::Itertools;
 use tokio::sync::Mutex;
 
 use crate::expr::{Batch, EvalError, ScalarExpr};
-use crate::repr::DiffRow;
+use crate::repr::{self, DiffRow, Row};
 use crate::utils::ArrangeHandler;
 
 pub type Toff<T = DiffRow> = TeeingHandoff<T>;
@@ -185,14 +185,166 @@ impl<T: 'static + Clone> CollectionBundle<T> {
 /// when running dataflow continuously and need errors in order
 #[derive(Debug, Default, Clone)]
 pub struct ErrCollector {
-    pub inner: Arc<Mutex<VecDeque<EvalError>>>,
+    pub inner: Arc<Mutex<VecDeque<OperatorError>>>,
+    /// rows failed to be evaluated, only collected if the flow has a dead-letter table
+    dead_letters: Option<Arc<Mutex<VecDeque<DeadLetter>>>>,
+    /// name of the operator errors collected by this collector are raised by,
+    /// see [`ErrCollector::with_operator`]
+    operator: Option<&'static str>,
+    /// rows arrived later than allowed and ignored, see [`ErrCollector::push_late_row`]
+    late_data: Arc<Mutex<LateDataRecorder>>,
+}
+
+/// Maximum number of distinct key prefixes late rows are counted by in one flow, late rows of
+/// other key prefixes are counted as [`OTHER_KEY_PREFIX`], so the cardinality of metrics is bounded
+const MAX_LATE_KEY_PREFIXES: usize = 64;
+/// Key prefix late rows exceeding [`MAX_LATE_KEY_PREFIXES`] are counted as
+pub const OTHER_KEY_PREFIX: &str = "__other__";
+/// Log one in every this many late rows
+const LATE_ROW_LOG_INTERVAL: u64 = 1000;
+
+/// Statistics of rows arrived later than allowed since they are last taken
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LateDataStats {
+    /// number of late rows
+    pub rows: u64,
+    /// the maximum time in milliseconds a row is late by
+    pub max_lateness: repr::Duration,
+    /// number of late rows by the first column of their key
+    pub rows_by_key_prefix: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Default)]
+struct LateDataRecorder {
+    stats: LateDataStats,
+    /// key prefixes late rows are ever counted by
+    key_prefixes: BTreeSet<String>,
+    /// number of late rows ever recorded, for sampling rows to log
+    total_rows: u64,
+}
+
+/// An error raised by an operator of a dataflow
+#[derive(Debug, Clone)]
+pub struct OperatorError {
+    /// name of the operator, `dataflow` if the error isn't raised by a named operator
+    pub operator: &'static str,
+    /// the input row of the operator, only taken if the flow collects dead letters
+    /// since it costs a copy of every input row
+    pub row: Option<Row>,
+    pub error: Arc<EvalError>,
+    /// when the error is raised, in milliseconds since unix epoch
+    pub ts: i64,
+}
+
+/// A row dropped because it failed to be evaluated by an operator
+#[derive(Debug, Clone)]
+pub struct DeadLetter {
+    /// the input row of the operator
+    pub row: Row,
+    /// name of the operator failed to evaluate the row
+    pub operator: &'static str,
+    pub error: String,
 }
 
 impl ErrCollector {
-    pub fn get_all_blocking(&self) -> Vec<EvalError> {
+    /// Create a collector which also collects rows failed to be evaluated
+    pub fn with_dead_letters() -> Self {
+        Self {
+            inner: Default::default(),
+            dead_letters: Some(Default::default()),
+            operator: None,
+            late_data: Default::default(),
+        }
+    }
+
+    /// A collector sharing the same errors, with errors pushed by `run` or `push_err` attributed
+    /// to `operator`
+    pub fn with_operator(&self, operator: &'static str) -> Self {
+        Self {
+            operator: Some(operator),
+            ..self.clone()
+        }
+    }
+
+    /// Take all dead letters collected so far
+    pub async fn take_dead_letters(&self) -> Vec<DeadLetter> {
+        match &self.dead_letters {
+            Some(dead_letters) => dead_letters.lock().await.drain(..).collect_vec(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Whether rows failed to be evaluated are collected
+    pub fn collects_dead_letters(&self) -> bool {
+        self.dead_letters.is_some()
+    }
+
+    /// Collect the error, and the row as a dead letter if dead letters are collected
+    pub fn push_row_err(&self, row: Option<Row>, operator: &'static str, err: EvalError) {
+        if let (Some(dead_letters), Some(row)) = (&self.dead_letters, &row) {
+            dead_letters.blocking_lock().push_back(DeadLetter {
+                row: row.clone(),
+                operator,
+                error: format!("{:?}", err),
+            });
+        }
+        self.push_operator_err(operator, row, err)
+    }
+
+    /// Like [`ErrCollector::run`], but `row` is collected as a dead letter if `f` fails
+    pub fn run_on_row<F, R>(&self, operator: &'static str, row: &mut Row, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut Row) -> Result<R, EvalError>,
+    {
+        let input = self.collects_dead_letters().then(|| row.clone());
+        match f(row) {
+            Ok(r) => Some(r),
+            Err(e) => {
+                self.push_row_err(input, operator, e);
+                None
+            }
+        }
+    }
+
+    /// Record a row of `key` ignored since it's late by `lateness` milliseconds, that is it arrives
+    /// after its key is already expired, and log it if it's sampled
+    pub fn push_late_row(&self, key: &Row, lateness: repr::Duration) {
+        let mut recorder = self.late_data.blocking_lock();
+        let prefix = key.iter().next().map(|v| v.to_string()).unwrap_or_default();
+        let prefix = if recorder.key_prefixes.contains(&prefix)
+            || recorder.key_prefixes.len() < MAX_LATE_KEY_PREFIXES
+        {
+            recorder.key_prefixes.insert(prefix.clone());
+            prefix
+        } else {
+            OTHER_KEY_PREFIX.to_string()
+        };
+        let stats = &mut recorder.stats;
+        stats.rows += 1;
+        stats.max_lateness = stats.max_lateness.max(lateness);
+        *stats.rows_by_key_prefix.entry(prefix).or_default() += 1;
+
+        if recorder.total_rows % LATE_ROW_LOG_INTERVAL == 0 {
+            common_telemetry::warn!(
+                "Operator {} ignored a row of key {:?} late by {}ms, {} late rows ignored so far",
+                self.operator.unwrap_or("dataflow"),
+                key,
+                lateness,
+                recorder.total_rows + 1
+            );
+        }
+        recorder.total_rows += 1;
+    }
+
+    /// Take statistics of late rows recorded since the last call
+    pub fn take_late_data(&self) -> LateDataStats {
+        std::mem::take(&mut self.late_data.blocking_lock().stats)
+    }
+
+    pub fn get_all_blocking(&self) -> Vec<OperatorError> {
         self.inner.blocking_lock().drain(..).collect_vec()
     }
-    pub async fn get_all(&self) -> Vec<EvalError> {
+    pub async fn get_all(&self) -> Vec<OperatorError> {
         self.inner.lock().await.drain(..).collect_vec()
     }
 
@@ -201,7 +353,16 @@ impl ErrCollector {
     }
 
     pub fn push_err(&self, err: EvalError) {
-        self.inner.blocking_lock().push_back(err)
+        self.push_operator_err(self.operator.unwrap_or("dataflow"), None, err)
+    }
+
+    fn push_operator_err(&self, operator: &'static str, row: Option<Row>, err: EvalError) {
+        self.inner.blocking_lock().push_back(OperatorError {
+            operator,
+            row,
+            error: Arc::new(err),
+            ts: common_time::util::current_time_millis(),
+        })
     }
 
     pub fn run<F, R>(&self, f: F) -> Option<R>
@@ -217,3 +378,36 @@ impl ErrCollector {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_late_data() {
+        let err_collector = ErrCollector::default().with_operator("reduce");
+        let key = |k: i64| Row::new(vec![k.into(), 0i64.into()]);
+        err_collector.push_late_row(&key(1), 10);
+        err_collector.push_late_row(&key(1), 30);
+        err_collector.push_late_row(&key(2), 20);
+        assert_eq!(
+            err_collector.take_late_data(),
+            LateDataStats {
+                rows: 3,
+                max_lateness: 30,
+                rows_by_key_prefix: BTreeMap::from([("1".to_string(), 2), ("2".to_string(), 1)]),
+            }
+        );
+        assert_eq!(err_collector.take_late_data(), LateDataStats::default());
+
+        // key prefixes beyond the limit are counted together, while known ones are still counted
+        for k in 0..MAX_LATE_KEY_PREFIXES as i64 + 2 {
+            err_collector.push_late_row(&key(k), 1);
+        }
+        let stats = err_collector.take_late_data();
+        assert_eq!(stats.rows, MAX_LATE_KEY_PREFIXES as u64 + 2);
+        assert_eq!(stats.rows_by_key_prefix.len(), MAX_LATE_KEY_PREFIXES + 1);
+        assert_eq!(stats.rows_by_key_prefix["1"], 1);
+        assert_eq!(stats.rows_by_key_prefix[OTHER_KEY_PREFIX], 2);
+    }
+}
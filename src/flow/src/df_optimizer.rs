@@ -23,9 +23,12 @@ use common_error::ext::BoxedError;
 use common_telemetry::debug;
 use datafusion::config::ConfigOptions;
 use datafusion::error::DataFusionError;
+use datafusion::optimizer::analyzer::count_wildcard_rule::CountWildcardRule;
 use datafusion::optimizer::analyzer::type_coercion::TypeCoercion;
 use datafusion::optimizer::common_subexpr_eliminate::CommonSubexprEliminate;
+use datafusion::optimizer::decorrelate_predicate_subquery::DecorrelatePredicateSubquery;
 use datafusion::optimizer::optimize_projections::OptimizeProjections;
+use datafusion::optimizer::scalar_subquery_to_join::ScalarSubqueryToJoin;
 use datafusion::optimizer::simplify_expressions::SimplifyExpressions;
 use datafusion::optimizer::unwrap_cast_in_comparison::UnwrapCastInComparison;
 use datafusion::optimizer::utils::NamePreserver;
@@ -49,7 +52,7 @@ use snafu::ResultExt;
 use substrait::DFLogicalSubstraitConvertor;
 
 use crate::adapter::FlownodeContext;
-use crate::error::{DatafusionSnafu, Error, ExternalSnafu, UnexpectedSnafu};
+use crate::error::{DatafusionSnafu, Error, ExternalSnafu, PlanSnafu, UnexpectedSnafu};
 use crate::expr::{TUMBLE_END, TUMBLE_START};
 use crate::plan::TypedPlan;
 
@@ -59,9 +62,12 @@ pub async fn apply_df_optimizer(
 ) -> Result<datafusion_expr::LogicalPlan, Error> {
     let cfg = ConfigOptions::new();
     let analyzer = Analyzer::with_rules(vec![
+        // rewrite `count(*)` into `count(1)` so it can be encoded into substrait
+        Arc::new(CountWildcardRule::new()),
         Arc::new(AvgExpandRule::new()),
         Arc::new(TumbleExpandRule::new()),
         Arc::new(CheckGroupByRule::new()),
+        Arc::new(NowPlaceholderRule::new()),
         Arc::new(TypeCoercion::new()),
     ]);
     let plan = analyzer
@@ -74,6 +80,9 @@ pub async fn apply_df_optimizer(
 
     let ctx = OptimizerContext::new();
     let optimizer = Optimizer::with_rules(vec![
+        // decorrelate subqueries into joins, since flow can only evaluate subqueries as joins
+        Arc::new(DecorrelatePredicateSubquery::new()),
+        Arc::new(ScalarSubqueryToJoin::new()),
         Arc::new(OptimizeProjections::new()),
         Arc::new(CommonSubexprEliminate::new()),
         Arc::new(SimplifyExpressions::new()),
@@ -84,10 +93,46 @@ pub async fn apply_df_optimizer(
         .context(DatafusionSnafu {
             context: "Fail to apply optimizer",
         })?;
+    check_no_subquery(&plan)?;
 
     Ok(plan)
 }
 
+/// Make sure all subqueries are decorrelated into joins, return a plan error for those which are not
+fn check_no_subquery(plan: &datafusion_expr::LogicalPlan) -> Result<(), Error> {
+    let mut found = None;
+    plan.apply(|node| {
+        for expr in node.expressions() {
+            expr.apply(|e| {
+                if matches!(
+                    e,
+                    Expr::ScalarSubquery(_) | Expr::Exists(_) | Expr::InSubquery(_)
+                ) {
+                    found = Some(e.clone());
+                    return Ok(TreeNodeRecursion::Stop);
+                }
+                Ok(TreeNodeRecursion::Continue)
+            })?;
+        }
+        if found.is_some() {
+            Ok(TreeNodeRecursion::Stop)
+        } else {
+            Ok(TreeNodeRecursion::Continue)
+        }
+    })
+    .context(DatafusionSnafu {
+        context: "Fail to check subquery",
+    })?;
+
+    if let Some(expr) = found {
+        return PlanSnafu {
+            reason: format!("Subquery can't be decorrelated into join yet: {}", expr),
+        }
+        .fail();
+    }
+    Ok(())
+}
+
 /// To reuse existing code for parse sql, the sql is first parsed into a datafusion logical plan,
 /// then to a substrait plan, and finally to a flow plan.
 pub async fn sql_to_flow_plan(
@@ -119,7 +164,11 @@ pub async fn sql_to_flow_plan(
         .map_err(BoxedError::new)
         .context(ExternalSnafu)?;
 
-    let flow_plan = TypedPlan::from_substrait_plan(ctx, &sub_plan).await?;
+    let flow_plan = TypedPlan::from_substrait_plan(ctx, &sub_plan)
+        .await?
+        .optimize()?
+        .pick_by_monotonicity();
+    flow_plan.validate()?;
 
     Ok(flow_plan)
 }
@@ -525,6 +574,99 @@ impl ScalarUDFImpl for TumbleExpand {
     }
 }
 
+/// replace `now()` with [`NowPlaceholder`], so it would not be folded into a constant by optimizer,
+/// and flow can build temporal filter from it
+struct NowPlaceholderRule {}
+
+impl NowPlaceholderRule {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl AnalyzerRule for NowPlaceholderRule {
+    fn analyze(
+        &self,
+        plan: datafusion_expr::LogicalPlan,
+        _config: &ConfigOptions,
+    ) -> datafusion_common::Result<datafusion_expr::LogicalPlan> {
+        let transformed = plan
+            .transform_up_with_subqueries(replace_now_analyzer)?
+            .data;
+        Ok(transformed)
+    }
+
+    fn name(&self) -> &str {
+        "now_placeholder"
+    }
+}
+
+fn replace_now_analyzer(
+    plan: datafusion_expr::LogicalPlan,
+) -> Result<Transformed<datafusion_expr::LogicalPlan>, DataFusionError> {
+    plan.map_expressions(|expr| {
+        expr.transform_up(|expr| match &expr {
+            Expr::ScalarFunction(func) if func.name() == "now" && func.args.is_empty() => {
+                let now = datafusion_expr::expr::ScalarFunction::new_udf(
+                    Arc::new(NowPlaceholder::new().into()),
+                    vec![],
+                );
+                Ok(Transformed::yes(Expr::ScalarFunction(now)))
+            }
+            _ => Ok(Transformed::no(expr)),
+        })
+    })?
+    .map_data(|plan| plan.recompute_schema())
+}
+
+/// This is a placeholder for `now()` function, which is volatile so datafusion won't evaluate it
+/// at planning time, and return millisecond timestamp which is the same as flow's `now()`
+#[derive(Debug)]
+struct NowPlaceholder {
+    signature: Signature,
+}
+
+impl NowPlaceholder {
+    fn new() -> Self {
+        Self {
+            signature: Signature::exact(vec![], Volatility::Volatile),
+        }
+    }
+}
+
+impl ScalarUDFImpl for NowPlaceholder {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        "now"
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn return_type(
+        &self,
+        _arg_types: &[arrow_schema::DataType],
+    ) -> Result<arrow_schema::DataType, DataFusionError> {
+        Ok(arrow_schema::DataType::Timestamp(
+            arrow_schema::TimeUnit::Millisecond,
+            None,
+        ))
+    }
+
+    fn invoke(
+        &self,
+        _args: &[datafusion_expr::ColumnarValue],
+    ) -> Result<datafusion_expr::ColumnarValue, DataFusionError> {
+        Err(DataFusionError::Plan(
+            "This function should not be executed by datafusion".to_string(),
+        ))
+    }
+}
+
 /// This rule check all group by exprs, and make sure they are also in select clause in a aggr query
 struct CheckGroupByRule {}
 
@@ -562,7 +704,12 @@ fn check_group_by_analyzer(
                 .iter()
                 .map(|i| i.visit(&mut found_column_used))
                 .count();
-            for expr in aggr.group_expr.iter() {
+            // each column of grouping sets(i.e. `ROLLUP`) should be checked separately
+            let group_exprs = aggr.group_expr.iter().flat_map(|expr| match expr {
+                Expr::GroupingSet(set) => set.distinct_expr(),
+                _ => vec![expr],
+            });
+            for expr in group_exprs {
                 if !found_column_used
                     .names_for_alias
                     .contains(&expr.name_for_alias()?)
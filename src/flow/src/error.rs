@@ -22,6 +22,7 @@ use common_macro::stack_trace_debug;
 use common_telemetry::common_error::ext::ErrorExt;
 use common_telemetry::common_error::status_code::StatusCode;
 use snafu::{Location, Snafu};
+use table::metadata::{TableId, TableVersion};
 
 use crate::adapter::FlowId;
 use crate::expr::EvalError;
@@ -60,6 +61,29 @@ pub enum Error {
         location: Location,
     },
 
+    #[snafu(display(
+        "Schema of rows inserted to table id={table_id} doesn't match the one known by flownode: {reason}"
+    ))]
+    SourceSchemaMismatch {
+        table_id: TableId,
+        reason: String,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display(
+        "Source table id={table_id} of flow {flow_id} is altered from version {from} to {to}, and the flow can't be adapted to it: {reason}"
+    ))]
+    SourceTableAltered {
+        flow_id: FlowId,
+        table_id: TableId,
+        from: TableVersion,
+        to: TableVersion,
+        reason: String,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
     #[snafu(display("Table not found: {msg}, meta error: {source}"))]
     TableNotFoundMeta {
         source: common_meta::error::Error,
@@ -83,6 +107,50 @@ pub enum Error {
         location: Location,
     },
 
+    #[snafu(display("Failed to access checkpoint of flow, id={id}"))]
+    CheckpointStore {
+        id: FlowId,
+        source: common_meta::error::Error,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display("Failed to access paused state of flow, id={id}"))]
+    PausedFlowStore {
+        id: FlowId,
+        source: common_meta::error::Error,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display("Failed to report status of flows in flownode={id}"))]
+    ReportFlowStat {
+        id: common_meta::FlownodeId,
+        source: common_meta::error::Error,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display("Failed to serialize or deserialize checkpoint of flow, id={id}"))]
+    SerdeCheckpoint {
+        id: FlowId,
+        #[snafu(source)]
+        error: serde_json::Error,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display(
+        "Checkpoint of flow is too large, id={id}, size={size}, max_size={max_size}"
+    ))]
+    CheckpointTooLarge {
+        id: FlowId,
+        size: usize,
+        max_size: usize,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
     #[snafu(display("Flow already exist, id={id}"))]
     FlowAlreadyExist {
         id: FlowId,
@@ -90,6 +158,12 @@ pub enum Error {
         location: Location,
     },
 
+    #[snafu(display("Flownode is shutting down, not accepting inserts anymore"))]
+    ShuttingDown {
+        #[snafu(implicit)]
+        location: Location,
+    },
+
     #[snafu(display("Failed to join task"))]
     JoinTask {
         #[snafu(source)]
@@ -105,6 +179,23 @@ pub enum Error {
         location: Location,
     },
 
+    #[snafu(display("Invalid flow option '{key}': {reason}"))]
+    InvalidFlowOption {
+        key: String,
+        reason: String,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display("Flow {flow_id} has {rows} rows in its state, exceeding the limit {limit}"))]
+    StateLimitExceeded {
+        flow_id: u64,
+        rows: u64,
+        limit: u64,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
     #[snafu(display("Not implement in flow: {reason}"))]
     NotImplemented {
         reason: String,
@@ -112,6 +203,13 @@ pub enum Error {
         location: Location,
     },
 
+    #[snafu(display("Unsupported functions in flow: {}", names.join(", ")))]
+    UnsupportedFunctions {
+        names: Vec<String>,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
     #[snafu(display("Flow plan error: {reason}"))]
     Plan {
         reason: String,
@@ -192,9 +290,11 @@ pub type Result<T> = std::result::Result<T, Error>;
 impl ErrorExt for Error {
     fn status_code(&self) -> StatusCode {
         match self {
-            Self::Eval { .. } | Self::JoinTask { .. } | Self::Datafusion { .. } => {
-                StatusCode::Internal
-            }
+            Self::Eval { .. }
+            | Self::JoinTask { .. }
+            | Self::Datafusion { .. }
+            | Self::SerdeCheckpoint { .. }
+            | Self::CheckpointTooLarge { .. } => StatusCode::Internal,
             Self::FlowAlreadyExist { .. } => StatusCode::TableAlreadyExists,
             Self::TableNotFound { .. }
             | Self::TableNotFoundMeta { .. }
@@ -204,16 +304,24 @@ impl ErrorExt for Error {
                 StatusCode::PlanQuery
             }
             Self::Unexpected { .. } => StatusCode::Unexpected,
-            Self::NotImplemented { .. } | Self::UnsupportedTemporalFilter { .. } => {
-                StatusCode::Unsupported
-            }
+            Self::StateLimitExceeded { .. } => StatusCode::RuntimeResourcesExhausted,
+            Self::ShuttingDown { .. } => StatusCode::IllegalState,
+            Self::NotImplemented { .. }
+            | Self::UnsupportedTemporalFilter { .. }
+            | Self::UnsupportedFunctions { .. } => StatusCode::Unsupported,
             Self::External { source, .. } => source.status_code(),
             Self::Internal { .. } | Self::CacheRequired { .. } => StatusCode::Internal,
             Self::StartServer { source, .. } | Self::ShutdownServer { source, .. } => {
                 source.status_code()
             }
             Self::MetaClientInit { source, .. } => source.status_code(),
-            Self::ParseAddr { .. } => StatusCode::InvalidArguments,
+            Self::CheckpointStore { source, .. }
+            | Self::PausedFlowStore { source, .. }
+            | Self::ReportFlowStat { source, .. } => source.status_code(),
+            Self::ParseAddr { .. }
+            | Self::InvalidFlowOption { .. }
+            | Self::SourceSchemaMismatch { .. }
+            | Self::SourceTableAltered { .. } => StatusCode::InvalidArguments,
         }
     }
 
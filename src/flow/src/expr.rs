@@ -23,13 +23,15 @@ pub(crate) mod relation;
 mod scalar;
 mod signature;
 
+use std::sync::Arc;
+
 use arrow::compute::FilterBuilder;
 use datatypes::prelude::DataType;
 use datatypes::value::Value;
-use datatypes::vectors::{BooleanVector, Helper, VectorRef};
+use datatypes::vectors::{BooleanVector, Helper, Int64Vector, VectorRef};
 pub(crate) use df_func::{DfScalarFunction, RawDfScalarFn};
 pub(crate) use error::{EvalError, InvalidArgumentSnafu};
-pub(crate) use func::{BinaryFunc, UnaryFunc, UnmaterializableFunc, VariadicFunc};
+pub(crate) use func::{BinaryFunc, CachedRegex, UnaryFunc, UnmaterializableFunc, VariadicFunc};
 pub(crate) use id::{GlobalId, Id, LocalId};
 use itertools::Itertools;
 pub(crate) use linear::{MapFilterProject, MfpPlan, SafeMfpPlan};
@@ -38,7 +40,7 @@ pub(crate) use scalar::{ScalarExpr, TypedExpr};
 use snafu::{ensure, ResultExt};
 
 use crate::expr::error::{ArrowSnafu, DataTypeSnafu};
-use crate::repr::Diff;
+use crate::repr::{Diff, DiffRow, Row, Timestamp};
 
 pub const TUMBLE_START: &str = "tumble_start";
 pub const TUMBLE_END: &str = "tumble_end";
@@ -52,6 +54,8 @@ pub struct Batch {
     row_count: usize,
     /// describe if corresponding rows in batch is insert or delete, None means all rows are insert
     diffs: Option<VectorRef>,
+    /// timestamp of corresponding rows in batch, None means all rows are at the time the batch is sent
+    timestamps: Option<VectorRef>,
 }
 
 impl PartialEq for Batch {
@@ -65,14 +69,17 @@ impl PartialEq for Batch {
                 && <dyn arrow::array::Array>::eq(&left.to_arrow_array(), &right.to_arrow_array());
         }
 
-        let diff_eq = match (&self.diffs, &other.diffs) {
+        let meta_eq = |left: &Option<VectorRef>, right: &Option<VectorRef>| match (left, right) {
             (Some(left), Some(right)) => {
                 <dyn arrow::array::Array>::eq(&left.to_arrow_array(), &right.to_arrow_array())
             }
             (None, None) => true,
             _ => false,
         };
-        batch_eq && diff_eq && self.row_count == other.row_count
+        batch_eq
+            && meta_eq(&self.diffs, &other.diffs)
+            && meta_eq(&self.timestamps, &other.timestamps)
+            && self.row_count == other.row_count
     }
 }
 
@@ -85,7 +92,7 @@ impl Default for Batch {
 }
 
 impl Batch {
-    pub fn try_from_rows(rows: Vec<crate::repr::Row>) -> Result<Self, EvalError> {
+    pub fn try_from_rows(rows: Vec<Row>) -> Result<Self, EvalError> {
         if rows.is_empty() {
             return Ok(Self::empty());
         }
@@ -121,11 +128,62 @@ impl Batch {
         Ok(batch)
     }
 
+    /// Convert rows with their timestamps and diffs to a batch, diffs are omitted if all rows are
+    /// inserts
+    pub fn try_from_diff_rows(rows: Vec<DiffRow>) -> Result<Self, EvalError> {
+        let mut diffs = Vec::with_capacity(rows.len());
+        let mut timestamps = Vec::with_capacity(rows.len());
+        let rows = rows
+            .into_iter()
+            .map(|(row, ts, diff)| {
+                timestamps.push(ts);
+                diffs.push(diff);
+                row
+            })
+            .collect_vec();
+        let batch = Self::try_from_rows(rows)?;
+        let diffs = diffs
+            .iter()
+            .any(|diff| *diff != 1)
+            .then(|| Arc::new(Int64Vector::from_vec(diffs)) as VectorRef);
+        batch
+            .with_diffs(diffs)?
+            .with_timestamps(Some(Arc::new(Int64Vector::from_vec(timestamps))))
+    }
+
+    /// Convert the batch back to rows, only used at the boundary of an operator which works on
+    /// rows, rows without a timestamp are at `default_ts`
+    pub fn into_diff_rows(self, default_ts: Timestamp) -> Result<Vec<DiffRow>, EvalError> {
+        let diffs = self.diffs_or_inserts();
+        let timestamps = self.timestamps.clone();
+        (0..self.row_count)
+            .map(|idx| {
+                let row = Row::new(self.get_row(idx)?);
+                let ts = match &timestamps {
+                    Some(ts) => Self::get_i64(ts, idx)?,
+                    None => default_ts,
+                };
+                Ok((row, ts, Self::get_i64(&diffs, idx)?))
+            })
+            .collect()
+    }
+
+    fn get_i64(vector: &VectorRef, idx: usize) -> Result<i64, EvalError> {
+        match vector.get(idx) {
+            Value::Int64(v) => Ok(v),
+            v => InvalidArgumentSnafu {
+                reason: format!("Expect an i64 diff or timestamp, found {:?}", v),
+            }
+            .fail(),
+        }
+    }
+
     pub fn empty() -> Self {
         Self {
             batch: vec![],
             row_count: 0,
             diffs: None,
+            timestamps: None,
         }
     }
     pub fn try_new(batch: Vec<VectorRef>, row_count: usize) -> Result<Self, EvalError> {
@@ -140,6 +198,7 @@ impl Batch {
             batch,
             row_count,
             diffs: None,
+            timestamps: None,
         })
     }
 
@@ -148,9 +207,54 @@ impl Batch {
             batch,
             row_count,
             diffs: None,
+            timestamps: None,
         }
     }
 
+    /// Set the diff of each row, None means all rows are insert
+    pub fn with_diffs(mut self, diffs: Option<VectorRef>) -> Result<Self, EvalError> {
+        self.ensure_row_aligned(&diffs, "diffs")?;
+        self.diffs = diffs;
+        Ok(self)
+    }
+
+    /// Set the timestamp of each row, None means all rows are at the time the batch is sent
+    pub fn with_timestamps(mut self, timestamps: Option<VectorRef>) -> Result<Self, EvalError> {
+        self.ensure_row_aligned(&timestamps, "timestamps")?;
+        self.timestamps = timestamps;
+        Ok(self)
+    }
+
+    fn ensure_row_aligned(&self, vector: &Option<VectorRef>, name: &str) -> Result<(), EvalError> {
+        ensure!(
+            vector.as_ref().map_or(true, |v| v.len() == self.row_count),
+            InvalidArgumentSnafu {
+                reason: format!(
+                    "Expect {} to have {} rows, found {}",
+                    name,
+                    self.row_count,
+                    vector.as_ref().map(|v| v.len()).unwrap_or_default()
+                )
+            }
+        );
+        Ok(())
+    }
+
+    pub fn diffs(&self) -> Option<&VectorRef> {
+        self.diffs.as_ref()
+    }
+
+    pub fn timestamps(&self) -> Option<&VectorRef> {
+        self.timestamps.as_ref()
+    }
+
+    /// The diffs of rows, with all rows being insert if not set
+    fn diffs_or_inserts(&self) -> VectorRef {
+        self.diffs
+            .clone()
+            .unwrap_or_else(|| Arc::new(Int64Vector::from_vec(vec![1; self.row_count])))
+    }
+
     pub fn batch(&self) -> &[VectorRef] {
         &self.batch
     }
@@ -193,7 +297,9 @@ impl Batch {
             .iter()
             .map(|v| v.slice(offset, length))
             .collect_vec();
-        Batch::try_new(batch, length)
+        Batch::try_new(batch, length)?
+            .with_diffs(self.diffs.as_ref().map(|v| v.slice(offset, length)))?
+            .with_timestamps(self.timestamps.as_ref().map(|v| v.slice(offset, length)))
     }
 
     /// append another batch to self
@@ -214,12 +320,30 @@ impl Batch {
         );
 
         if self.batch.is_empty() {
-            self.batch = other.batch;
-            self.row_count = other.row_count;
+            *self = other;
             return Ok(());
         } else if other.batch.is_empty() {
             return Ok(());
         }
+        ensure!(
+            self.timestamps.is_some() == other.timestamps.is_some(),
+            InvalidArgumentSnafu {
+                reason: "Expect both or neither of two batch to have timestamps"
+            }
+        );
+
+        let diffs = if self.diffs.is_none() && other.diffs.is_none() {
+            None
+        } else {
+            Some(concat_vectors(
+                &self.diffs_or_inserts(),
+                &other.diffs_or_inserts(),
+            )?)
+        };
+        let timestamps = match (&self.timestamps, &other.timestamps) {
+            (Some(left), Some(right)) => Some(concat_vectors(left, right)?),
+            _ => None,
+        };
 
         let dts = if self.batch.is_empty() {
             other.batch.iter().map(|v| v.data_type()).collect_vec()
@@ -250,29 +374,47 @@ impl Batch {
         }
         self.batch = result;
         self.row_count = self_row_count + other_row_count;
+        self.diffs = diffs;
+        self.timestamps = timestamps;
         Ok(())
     }
 
-    /// filter the batch with given predicate
+    /// filter the batch with given predicate, diffs and timestamps of rows are filtered too
     pub fn filter(&self, predicate: &BooleanVector) -> Result<Self, EvalError> {
         let len = predicate.as_boolean_array().true_count();
         let filter_builder = FilterBuilder::new(predicate.as_boolean_array()).optimize();
         let filter_pred = filter_builder.build();
-        let filtered = self
-            .batch()
-            .iter()
-            .map(|col| filter_pred.filter(col.to_arrow_array().as_ref()))
-            .try_collect::<_, Vec<_>, _>()
-            .context(ArrowSnafu {
-                context: "Failed to filter val batches",
-            })?;
-        let res_vector = Helper::try_into_vectors(&filtered).context(DataTypeSnafu {
-            msg: "can't convert arrow array to vector",
-        })?;
-        Self::try_new(res_vector, len)
+        let filter_vector = |col: &VectorRef| {
+            let filtered =
+                filter_pred
+                    .filter(col.to_arrow_array().as_ref())
+                    .context(ArrowSnafu {
+                        context: "Failed to filter val batches",
+                    })?;
+            Helper::try_into_vector(filtered).context(DataTypeSnafu {
+                msg: "can't convert arrow array to vector",
+            })
+        };
+        let res_vector = self.batch().iter().map(&filter_vector).try_collect()?;
+        Self::try_new(res_vector, len)?
+            .with_diffs(self.diffs.as_ref().map(&filter_vector).transpose()?)?
+            .with_timestamps(self.timestamps.as_ref().map(&filter_vector).transpose()?)
     }
 }
 
+fn concat_vectors(left: &VectorRef, right: &VectorRef) -> Result<VectorRef, EvalError> {
+    let concated = arrow::compute::concat(&[
+        left.to_arrow_array().as_ref(),
+        right.to_arrow_array().as_ref(),
+    ])
+    .context(ArrowSnafu {
+        context: "Failed to concat vectors",
+    })?;
+    Helper::try_into_vector(concated).context(DataTypeSnafu {
+        msg: "can't convert arrow array to vector",
+    })
+}
+
 /// Vector with diff to note the insert and delete
 pub(crate) struct VectorDiff {
     vector: VectorRef,
@@ -290,7 +432,7 @@ impl VectorDiff {
         self.vector.len()
     }
 
-    fn try_new(vector: VectorRef, diff: Option<VectorRef>) -> Result<Self, EvalError> {
+    pub(crate) fn try_new(vector: VectorRef, diff: Option<VectorRef>) -> Result<Self, EvalError> {
         ensure!(
             diff.as_ref()
                 .map_or(true, |diff| diff.len() == vector.len()),
@@ -346,3 +488,44 @@ impl std::iter::Iterator for VectorDiffIter {
         Some((value, diff))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_batch_diff_rows() {
+        let rows = vec![
+            (Row::new(vec![1i64.into()]), 1, 1),
+            (Row::new(vec![2i64.into()]), 2, -1),
+            (Row::new(vec![3i64.into()]), 2, 1),
+        ];
+        let batch = Batch::try_from_diff_rows(rows.clone()).unwrap();
+        assert_eq!(batch.row_count(), 3);
+        assert_eq!(batch.clone().into_diff_rows(0).unwrap(), rows);
+
+        // diffs and timestamps are filtered and sliced along with rows
+        let filtered = batch
+            .filter(&BooleanVector::from(vec![false, true, true]))
+            .unwrap();
+        assert_eq!(filtered.into_diff_rows(0).unwrap(), rows[1..]);
+        assert_eq!(
+            batch.slice(1, 1).unwrap().into_diff_rows(0).unwrap(),
+            rows[1..2]
+        );
+
+        let mut appended = batch.slice(0, 1).unwrap();
+        appended.append_batch(batch.slice(1, 2).unwrap()).unwrap();
+        assert_eq!(appended, batch);
+        let without_ts = Batch::try_from_rows(vec![Row::new(vec![1i64.into()])]).unwrap();
+        assert!(appended.append_batch(without_ts).is_err());
+
+        // all rows are inserts at the default time if not set
+        let inserts = Batch::try_from_rows(vec![Row::new(vec![1i64.into()])]).unwrap();
+        assert!(inserts.diffs().is_none());
+        assert_eq!(
+            inserts.into_diff_rows(5).unwrap(),
+            vec![(Row::new(vec![1i64.into()]), 5, 1)]
+        );
+    }
+}
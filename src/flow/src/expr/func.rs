@@ -18,7 +18,9 @@ use std::collections::HashMap;
 use std::sync::{Arc, OnceLock};
 use std::time::Duration;
 
-use arrow::array::{ArrayRef, BooleanArray};
+use arrow::array::{ArrayRef, AsArray, BooleanArray, StringArray};
+use arrow::datatypes::{DataType as ArrowDataType, Int32Type, Int64Type};
+use arrow_schema::ArrowError;
 use common_error::ext::BoxedError;
 use common_time::timestamp::TimeUnit;
 use common_time::Timestamp;
@@ -27,7 +29,10 @@ use datatypes::data_type::ConcreteDataType;
 use datatypes::prelude::DataType;
 use datatypes::types::cast;
 use datatypes::value::Value;
-use datatypes::vectors::{BooleanVector, Helper, TimestampMillisecondVector, VectorRef};
+use datatypes::vectors::{
+    BooleanVector, Helper, StringVector, TimestampMillisecondVector, VectorRef,
+};
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use smallvec::smallvec;
 use snafu::{ensure, OptionExt, ResultExt};
@@ -105,6 +110,7 @@ impl UnmaterializableFunc {
 pub enum UnaryFunc {
     Not,
     IsNull,
+    IsNotNull,
     IsTrue,
     IsFalse,
     StepTimestamp,
@@ -117,6 +123,101 @@ pub enum UnaryFunc {
         window_size: Duration,
         start_time: Option<Timestamp>,
     },
+    /// Test whether the argument matches the regex, i.e. `col ~ 'pattern'` or `regexp_like(col, 'pattern')`
+    RegexpMatch {
+        regex: CachedRegex,
+        negated: bool,
+    },
+    /// Replace the part(s) of the argument matched by the regex with `replacement`,
+    /// only the first match is replaced unless `global` is set
+    RegexpReplace {
+        regex: CachedRegex,
+        replacement: String,
+        global: bool,
+    },
+    /// Test whether the argument is in a list of literals, i.e. `col IN (1, 2, 3)`
+    ///
+    /// invariant: `list` is sorted, deduplicated, without null and of the same type as the argument,
+    /// so membership can be tested with binary search. Use [`UnaryFunc::in_list`] to build it.
+    InList {
+        list: Vec<Value>,
+        contains_null: bool,
+    },
+}
+
+/// A regex which is compiled once when the expression is built, and then reused for every evaluation
+/// of the expression instance.
+///
+/// Only the pattern is used for comparing, hashing and (de)serialization, the compiled regex is derived from it.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct CachedRegex {
+    pattern: String,
+    regex: regex::Regex,
+}
+
+impl CachedRegex {
+    /// Compile the given pattern, return error if it's not a valid regex
+    pub fn new(pattern: &str) -> Result<Self, Error> {
+        let regex = regex::Regex::new(pattern).map_err(|err| {
+            InvalidQuerySnafu {
+                reason: format!("Invalid regex pattern {:?}: {}", pattern, err),
+            }
+            .build()
+        })?;
+        Ok(Self {
+            pattern: pattern.to_string(),
+            regex,
+        })
+    }
+
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    pub fn regex(&self) -> &regex::Regex {
+        &self.regex
+    }
+}
+
+impl TryFrom<String> for CachedRegex {
+    type Error = Error;
+
+    fn try_from(pattern: String) -> Result<Self, Self::Error> {
+        Self::new(&pattern)
+    }
+}
+
+impl From<CachedRegex> for String {
+    fn from(regex: CachedRegex) -> Self {
+        regex.pattern
+    }
+}
+
+impl PartialEq for CachedRegex {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern
+    }
+}
+
+impl Eq for CachedRegex {}
+
+impl PartialOrd for CachedRegex {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for CachedRegex {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.pattern.cmp(&other.pattern)
+    }
+}
+
+impl std::hash::Hash for CachedRegex {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.pattern.hash(state);
+    }
 }
 
 impl UnaryFunc {
@@ -128,6 +229,11 @@ impl UnaryFunc {
                 output: ConcreteDataType::boolean_datatype(),
                 generic_fn: GenericFn::IsNull,
             },
+            Self::IsNotNull => Signature {
+                input: smallvec![ConcreteDataType::null_datatype()],
+                output: ConcreteDataType::boolean_datatype(),
+                generic_fn: GenericFn::IsNotNull,
+            },
             Self::Not | Self::IsTrue | Self::IsFalse => Signature {
                 input: smallvec![ConcreteDataType::boolean_datatype()],
                 output: ConcreteDataType::boolean_datatype(),
@@ -158,13 +264,40 @@ impl UnaryFunc {
                 output: ConcreteDataType::timestamp_millisecond_datatype(),
                 generic_fn: GenericFn::TumbleWindow,
             },
+            Self::RegexpMatch { .. } => Signature {
+                input: smallvec![ConcreteDataType::string_datatype()],
+                output: ConcreteDataType::boolean_datatype(),
+                generic_fn: GenericFn::RegexpMatch,
+            },
+            Self::RegexpReplace { .. } => Signature {
+                input: smallvec![ConcreteDataType::string_datatype()],
+                output: ConcreteDataType::string_datatype(),
+                generic_fn: GenericFn::RegexpReplace,
+            },
+            Self::InList { .. } => Signature {
+                input: smallvec![ConcreteDataType::null_datatype()],
+                output: ConcreteDataType::boolean_datatype(),
+                generic_fn: GenericFn::InList,
+            },
+        }
+    }
+
+    /// Build a `IN` list function from a list of literals, the literals must already be cast to the type of the argument
+    pub fn in_list(list: Vec<Value>) -> Self {
+        let contains_null = list.iter().any(|v| v.is_null());
+        let mut list = list.into_iter().filter(|v| !v.is_null()).collect_vec();
+        list.sort();
+        list.dedup();
+        Self::InList {
+            list,
+            contains_null,
         }
     }
 
     pub fn is_valid_func_name(name: &str) -> bool {
         matches!(
             name.to_lowercase().as_str(),
-            "not" | "is_null" | "is_true" | "is_false" | "step_timestamp" | "cast"
+            "not" | "is_null" | "is_not_null" | "is_true" | "is_false" | "step_timestamp" | "cast"
         )
     }
 
@@ -176,6 +309,7 @@ impl UnaryFunc {
         match name {
             "not" => Ok(Self::Not),
             "is_null" => Ok(Self::IsNull),
+            "is_not_null" => Ok(Self::IsNotNull),
             "is_true" => Ok(Self::IsTrue),
             "is_false" => Ok(Self::IsFalse),
             "step_timestamp" => Ok(Self::StepTimestamp),
@@ -217,6 +351,14 @@ impl UnaryFunc {
                 let ret = BooleanVector::from(ret);
                 Ok(Arc::new(ret))
             }
+            Self::IsNotNull => {
+                let arrow_array = arg_col.to_arrow_array();
+                let ret = arrow::compute::is_not_null(&arrow_array).context(ArrowSnafu {
+                    context: "is_not_null",
+                })?;
+                let ret = BooleanVector::from(ret);
+                Ok(Arc::new(ret))
+            }
             Self::IsTrue | Self::IsFalse => {
                 let arrow_array = arg_col.to_arrow_array();
                 let bool_array = arrow_array
@@ -255,6 +397,11 @@ impl UnaryFunc {
             }
             Self::Cast(to) => {
                 let arrow_array = arg_col.to_arrow_array();
+                if let (Some(from_factor), Some(to_factor)) =
+                    (temporal_factor(&arg_col.data_type()), temporal_factor(to))
+                {
+                    return cast_temporal_array(&arrow_array, from_factor, to_factor, to);
+                }
                 let ret = arrow::compute::cast(&arrow_array, &to.as_arrow_type())
                     .context(ArrowSnafu { context: "cast" })?;
                 let vector = Helper::try_into_vector(ret).context(DataTypeSnafu {
@@ -312,9 +459,133 @@ impl UnaryFunc {
                 let ret = TimestampMillisecondVector::from(ret);
                 Ok(Arc::new(ret))
             }
+            Self::RegexpMatch { regex, negated } => {
+                let arrow_array = arg_col.to_arrow_array();
+                let string_array = get_string_array(&arrow_array, &arg_col)?;
+                let ret: BooleanArray = string_array
+                    .iter()
+                    .map(|s| s.map(|s| regex.regex().is_match(s) != *negated))
+                    .collect();
+                Ok(Arc::new(BooleanVector::from(ret)))
+            }
+            Self::RegexpReplace {
+                regex,
+                replacement,
+                global,
+            } => {
+                let arrow_array = arg_col.to_arrow_array();
+                let string_array = get_string_array(&arrow_array, &arg_col)?;
+                let ret: StringArray = string_array
+                    .iter()
+                    .map(|s| s.map(|s| regex_replace(regex, s, replacement, *global)))
+                    .collect();
+                Ok(Arc::new(StringVector::from(ret)))
+            }
+            Self::InList {
+                list,
+                contains_null,
+            } => {
+                let ret = (0..arg_col.len())
+                    .map(|i| eval_in_list(&arg_col.get(i), list, *contains_null))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Arc::new(BooleanVector::from(ret)))
+            }
         }
     }
 
+    pub fn is_regex_func_name(name: &str) -> bool {
+        matches!(
+            name.to_lowercase().as_str(),
+            "regex_match"
+                | "regex_imatch"
+                | "regex_not_match"
+                | "regex_not_imatch"
+                | "regexp_like"
+                | "regexp_replace"
+        )
+    }
+
+    /// Build a regex function from the function name and it's arguments, the first argument is the string
+    /// to be matched, and the rest(pattern, replacement and flags) must be string literals.
+    ///
+    /// Return `None` if the pattern is not a literal, in which case the regex can't be compiled ahead of time,
+    /// and the caller should fallback to other implementation.
+    pub fn from_regex_func(
+        name: &str,
+        args: &[TypedExpr],
+    ) -> Result<Option<(Self, TypedExpr)>, Error> {
+        let name = name.to_lowercase();
+        let (min_args, max_args) = if name == "regexp_replace" {
+            (3, 4)
+        } else if name == "regexp_like" {
+            (2, 3)
+        } else {
+            (2, 2)
+        };
+        ensure!(
+            args.len() >= min_args && args.len() <= max_args,
+            InvalidQuerySnafu {
+                reason: format!(
+                    "Regex function {} requires {} to {} arguments, found {}",
+                    name,
+                    min_args,
+                    max_args,
+                    args.len()
+                ),
+            }
+        );
+        let get_str_lit = |idx: usize| {
+            args.get(idx)
+                .and_then(|arg| arg.expr.as_literal())
+                .and_then(|lit| lit.as_string())
+        };
+        let Some(pattern) = get_str_lit(1) else {
+            return Ok(None);
+        };
+        let flags_idx = if name == "regexp_replace" { 3 } else { 2 };
+        let mut flags = match args.get(flags_idx) {
+            Some(_) => match get_str_lit(flags_idx) {
+                Some(flags) => flags,
+                None => return Ok(None),
+            },
+            None => String::new(),
+        };
+        if name == "regex_imatch" || name == "regex_not_imatch" {
+            flags.push('i');
+        }
+        let global = flags.contains('g');
+        let flags = flags.replace('g', "");
+        let pattern = if flags.is_empty() {
+            pattern
+        } else {
+            format!("(?{}){}", flags, pattern)
+        };
+        let regex = CachedRegex::new(&pattern)?;
+
+        let func = if name == "regexp_replace" {
+            let Some(replacement) = get_str_lit(2) else {
+                return Ok(None);
+            };
+            Self::RegexpReplace {
+                regex,
+                replacement: to_rust_regex_replacement(&replacement),
+                global,
+            }
+        } else {
+            ensure!(
+                !global,
+                InvalidQuerySnafu {
+                    reason: format!("Flag 'g' is not supported in regex function {}", name),
+                }
+            );
+            Self::RegexpMatch {
+                regex,
+                negated: name == "regex_not_match" || name == "regex_not_imatch",
+            }
+        };
+        Ok(Some((func, args[0].clone())))
+    }
+
     pub fn from_tumble_func(name: &str, args: &[TypedExpr]) -> Result<(Self, TypedExpr), Error> {
         match name.to_lowercase().as_str() {
             TUMBLE_START | TUMBLE_END => {
@@ -422,6 +693,8 @@ impl UnaryFunc {
             Self::Not => {
                 let bool = if let Value::Boolean(bool) = arg {
                     Ok(bool)
+                } else if arg.is_null() {
+                    return Ok(Value::Null);
                 } else {
                     TypeMismatchSnafu {
                         expected: ConcreteDataType::boolean_datatype(),
@@ -432,6 +705,7 @@ impl UnaryFunc {
                 Ok(Value::from(!bool))
             }
             Self::IsNull => Ok(Value::from(arg.is_null())),
+            Self::IsNotNull => Ok(Value::from(!arg.is_null())),
             Self::IsTrue | Self::IsFalse => {
                 let bool = if let Value::Boolean(bool) = arg {
                     Ok(bool)
@@ -450,12 +724,11 @@ impl UnaryFunc {
             }
             Self::StepTimestamp => {
                 let ty = arg.data_type();
-                if let Value::Timestamp(timestamp) = arg {
-                    let timestamp = Timestamp::new_millisecond(timestamp.value() + 1);
-                    Ok(Value::from(timestamp))
-                } else if let Ok(v) = value_to_internal_ts(arg) {
-                    let timestamp = Timestamp::new_millisecond(v + 1);
-                    Ok(Value::from(timestamp))
+                // a timestamp more precise than millisecond is rounded down first, so that
+                // `now > ts` iff `now >= step_timestamp(ts)` for any internal time `now`
+                if let Ok(v) = value_to_internal_ts(arg) {
+                    let step = v.checked_add(1).context(OverflowSnafu)?;
+                    Ok(Value::from(Timestamp::new_millisecond(step)))
                 } else {
                     TypeMismatchSnafu {
                         expected: ConcreteDataType::timestamp_millisecond_datatype(),
@@ -466,6 +739,11 @@ impl UnaryFunc {
             }
             Self::Cast(to) => {
                 let arg_ty = arg.data_type();
+                if let (Some(from_factor), Some(to_factor)) =
+                    (temporal_factor(&arg_ty), temporal_factor(to))
+                {
+                    return cast_temporal_value(arg, from_factor, to_factor, to);
+                }
                 cast(arg, to).context({
                     CastValueSnafu {
                         from: arg_ty,
@@ -498,8 +776,282 @@ impl UnaryFunc {
                 let ret = Timestamp::new_millisecond(window_end);
                 Ok(Value::from(ret))
             }
+            Self::RegexpMatch { regex, negated } => match arg {
+                Value::Null => Ok(Value::Null),
+                Value::String(s) => {
+                    Ok(Value::from(regex.regex().is_match(s.as_utf8()) != *negated))
+                }
+                _ => TypeMismatchSnafu {
+                    expected: ConcreteDataType::string_datatype(),
+                    actual: arg.data_type(),
+                }
+                .fail(),
+            },
+            Self::RegexpReplace {
+                regex,
+                replacement,
+                global,
+            } => match arg {
+                Value::Null => Ok(Value::Null),
+                Value::String(s) => Ok(Value::from(
+                    regex_replace(regex, s.as_utf8(), replacement, *global).into_owned(),
+                )),
+                _ => TypeMismatchSnafu {
+                    expected: ConcreteDataType::string_datatype(),
+                    actual: arg.data_type(),
+                }
+                .fail(),
+            },
+            Self::InList {
+                list,
+                contains_null,
+            } => Ok(eval_in_list(&arg, list, *contains_null)?
+                .map(Value::from)
+                .unwrap_or(Value::Null)),
+        }
+    }
+}
+
+/// Test if `arg` is in the sorted `list`, follow SQL's semantic that return null if `arg` is null,
+/// or `arg` is not found and the list contains null
+fn eval_in_list(
+    arg: &Value,
+    list: &[Value],
+    contains_null: bool,
+) -> Result<Option<bool>, EvalError> {
+    if arg.is_null() {
+        return Ok(None);
+    }
+    if let Some(first) = list.first() {
+        // values of different types can't be compared
+        ensure!(
+            first.data_type() == arg.data_type(),
+            TypeMismatchSnafu {
+                expected: first.data_type(),
+                actual: arg.data_type(),
+            }
+        );
+    }
+    if list.binary_search(arg).is_ok() {
+        Ok(Some(true))
+    } else if contains_null {
+        Ok(None)
+    } else {
+        Ok(Some(false))
+    }
+}
+
+fn get_string_array<'a>(
+    arrow_array: &'a ArrayRef,
+    vector: &VectorRef,
+) -> Result<&'a StringArray, EvalError> {
+    arrow_array
+        .as_any()
+        .downcast_ref::<StringArray>()
+        .with_context(|| TypeMismatchSnafu {
+            expected: ConcreteDataType::string_datatype(),
+            actual: vector.data_type(),
+        })
+}
+
+fn regex_replace<'a>(
+    regex: &CachedRegex,
+    s: &'a str,
+    replacement: &str,
+    global: bool,
+) -> std::borrow::Cow<'a, str> {
+    if global {
+        regex.regex().replace_all(s, replacement)
+    } else {
+        regex.regex().replace(s, replacement)
+    }
+}
+
+/// Convert a postgres style replacement string(which use `\1` to refer to capture group)
+/// to the syntax of the `regex` crate(which use `${1}` instead), a literal `$` is escaped as `$$`
+fn to_rust_regex_replacement(replacement: &str) -> String {
+    let mut ret = String::with_capacity(replacement.len());
+    let mut chars = replacement.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek().is_some_and(|c| c.is_ascii_digit()) => {
+                ret.push_str("${");
+                while let Some(d) = chars.next_if(|c| c.is_ascii_digit()) {
+                    ret.push(d);
+                }
+                ret.push('}');
+            }
+            '$' => ret.push_str("$$"),
+            _ => ret.push(c),
+        }
+    }
+    ret
+}
+
+/// Add(or subtract if `negate`) an interval to a timestamp, date or datetime,
+/// the interval can be on either side and the result is a millisecond timestamp
+fn timestamp_add_interval(left: Value, right: Value, negate: bool) -> Result<Value, EvalError> {
+    if left.is_null() || right.is_null() {
+        return Ok(Value::Null);
+    }
+    let (ts, interval) = if let ConcreteDataType::Interval(_) = left.data_type() {
+        (right, left)
+    } else {
+        (left, right)
+    };
+    let ts = value_to_internal_ts(ts)?;
+    let millis = interval_to_millis(&interval)?;
+    let ret = if negate {
+        ts.checked_sub(millis)
+    } else {
+        ts.checked_add(millis)
+    }
+    .context(OverflowSnafu)?;
+    Ok(Value::from(Timestamp::new_millisecond(ret)))
+}
+
+/// Vectorized version of [`timestamp_add_interval`], evaluate row by row to keep the same semantic
+fn timestamp_add_interval_batch(
+    left: &VectorRef,
+    right: &VectorRef,
+    negate: bool,
+) -> Result<VectorRef, EvalError> {
+    let ret = (0..left.len())
+        .map(
+            |idx| match timestamp_add_interval(left.get(idx), right.get(idx), negate)? {
+                Value::Timestamp(ts) => Ok(Some(ts.value())),
+                _ => Ok(None),
+            },
+        )
+        .collect::<Result<Vec<_>, EvalError>>()?;
+    Ok(Arc::new(TimestampMillisecondVector::from(ret)))
+}
+
+/// Get the length of an interval in milliseconds
+///
+/// interval with month part is not supported since the length of a month varies
+fn interval_to_millis(interval: &Value) -> Result<i64, EvalError> {
+    let (months, millis) = match interval {
+        Value::IntervalYearMonth(i) => (i.months, 0),
+        Value::IntervalDayTime(i) => (0, i.as_millis()),
+        Value::IntervalMonthDayNano(i) => (
+            i.months,
+            i.days as i64 * 86_400_000 + i.nanoseconds / 1_000_000,
+        ),
+        _ => {
+            return InvalidArgumentSnafu {
+                reason: format!("Expect an interval, found {:?}", interval),
+            }
+            .fail()
+        }
+    };
+    ensure!(
+        months == 0,
+        InvalidArgumentSnafu {
+            reason: format!(
+                "Interval with month part is not supported in timestamp arithmetic, found {:?}",
+                interval
+            ),
+        }
+    );
+    Ok(millis)
+}
+
+/// Nanoseconds of a day, which is the unit of `Date` when converting between temporal types
+const DAY_FACTOR: i64 = 86_400 * 1_000_000_000;
+
+/// Return the length(in nanoseconds) of the unit of given type if it's one of timestamp, date or datetime
+fn temporal_factor(typ: &ConcreteDataType) -> Option<i64> {
+    match typ {
+        ConcreteDataType::Timestamp(t) => Some(t.unit().factor() as i64),
+        ConcreteDataType::Date(_) => Some(DAY_FACTOR),
+        ConcreteDataType::DateTime(_) => Some(TimeUnit::Millisecond.factor() as i64),
+        _ => None,
+    }
+}
+
+/// Convert a temporal value from unit of `from_factor` to unit of `to_factor`,
+/// round down to floor when converting to a coarser unit, return `None` if overflow
+fn convert_temporal(value: i64, from_factor: i64, to_factor: i64) -> Option<i64> {
+    if from_factor >= to_factor {
+        value.checked_mul(from_factor / to_factor)
+    } else {
+        Some(value.div_euclid(to_factor / from_factor))
+    }
+}
+
+/// Cast between timestamp of different precisions, date and datetime
+///
+/// Unlike the generic cast which silently produce null, overflow is reported as [`EvalError::Overflow`]
+fn cast_temporal_value(
+    arg: Value,
+    from_factor: i64,
+    to_factor: i64,
+    to: &ConcreteDataType,
+) -> Result<Value, EvalError> {
+    let value = match &arg {
+        Value::Timestamp(ts) => ts.value(),
+        Value::Date(date) => date.val() as i64,
+        Value::DateTime(datetime) => datetime.val(),
+        _ => InvalidArgumentSnafu {
+            reason: format!("Expect a temporal value, found {:?}", arg),
+        }
+        .fail()?,
+    };
+    let value = convert_temporal(value, from_factor, to_factor).context(OverflowSnafu)?;
+    let ret = match to {
+        ConcreteDataType::Timestamp(t) => Value::from(Timestamp::new(value, t.unit())),
+        ConcreteDataType::Date(_) => {
+            let days = i32::try_from(value).ok().context(OverflowSnafu)?;
+            Value::from(common_time::Date::new(days))
+        }
+        ConcreteDataType::DateTime(_) => Value::from(common_time::DateTime::new(value)),
+        _ => InvalidArgumentSnafu {
+            reason: format!("Expect a temporal type to cast to, found {:?}", to),
         }
+        .fail()?,
+    };
+    Ok(ret)
+}
+
+/// Vectorized version of [`cast_temporal_value`]
+fn cast_temporal_array(
+    arrow_array: &ArrayRef,
+    from_factor: i64,
+    to_factor: i64,
+    to: &ConcreteDataType,
+) -> Result<VectorRef, EvalError> {
+    let overflow = || ArrowError::ComputeError("Overflow".to_string());
+    // reinterpret as i64 first, notice date is stored as i32
+    let int_array = if matches!(arrow_array.data_type(), ArrowDataType::Date32) {
+        let int32_array = arrow::compute::cast(arrow_array, &ArrowDataType::Int32)
+            .context(ArrowSnafu { context: "cast" })?;
+        arrow::compute::cast(&int32_array, &ArrowDataType::Int64)
+    } else {
+        arrow::compute::cast(arrow_array, &ArrowDataType::Int64)
     }
+    .context(ArrowSnafu { context: "cast" })?;
+
+    let converted = arrow::compute::try_unary::<Int64Type, _, Int64Type>(
+        int_array.as_primitive::<Int64Type>(),
+        |v| convert_temporal(v, from_factor, to_factor).ok_or_else(overflow),
+    )
+    .map_err(|_| OverflowSnafu.build())?;
+
+    let ret = if matches!(to, ConcreteDataType::Date(_)) {
+        let days = arrow::compute::try_unary::<Int64Type, _, Int32Type>(&converted, |v| {
+            i32::try_from(v).map_err(|_| overflow())
+        })
+        .map_err(|_| OverflowSnafu.build())?;
+        arrow::compute::cast(&days, &to.as_arrow_type())
+    } else {
+        arrow::compute::cast(&converted, &to.as_arrow_type())
+    }
+    .context(ArrowSnafu { context: "cast" })?;
+
+    Helper::try_into_vector(ret).context(DataTypeSnafu {
+        msg: "Fail to convert to Vector",
+    })
 }
 
 fn get_timestamp_array(vector: &VectorRef) -> Result<arrow::array::ArrayRef, EvalError> {
@@ -617,6 +1169,12 @@ pub enum BinaryFunc {
     ModUInt16,
     ModUInt32,
     ModUInt64,
+    /// Add an interval to a timestamp, date or datetime, i.e. `ts + INTERVAL '5 minutes'`,
+    /// the result is always a millisecond timestamp
+    AddTimestampInterval,
+    /// Subtract an interval from a timestamp, date or datetime, i.e. `now() - INTERVAL '5 minutes'`,
+    /// the result is always a millisecond timestamp
+    SubTimestampInterval,
 }
 
 /// Generate binary function signature based on the function and the input types
@@ -680,6 +1238,18 @@ impl BinaryFunc {
                         Self::Gte => GenericFn::Gte,
                         _ => unreachable!(),
                     },
+                },
+                Self::AddTimestampInterval | Self::SubTimestampInterval => Signature {
+                    input: smallvec![
+                        ConcreteDataType::timestamp_millisecond_datatype(),
+                        ConcreteDataType::null_datatype()
+                    ],
+                    output: ConcreteDataType::timestamp_millisecond_datatype(),
+                    generic_fn: match self {
+                        Self::AddTimestampInterval => GenericFn::Add,
+                        Self::SubTimestampInterval => GenericFn::Sub,
+                        _ => unreachable!(),
+                    },
                 }
             },
             [
@@ -746,6 +1316,13 @@ impl BinaryFunc {
         let rule = SPECIALIZATION.get_or_init(|| {
             let mut spec = HashMap::new();
             for func in BinaryFunc::iter() {
+                // timestamp-interval arithmetic is chosen by `timestamp_interval_specialization`
+                if matches!(
+                    func,
+                    Self::AddTimestampInterval | Self::SubTimestampInterval
+                ) {
+                    continue;
+                }
                 let sig = func.signature();
                 spec.insert((sig.generic_fn, sig.input[0].clone()), func);
             }
@@ -761,6 +1338,59 @@ impl BinaryFunc {
             })
     }
 
+    /// Choose `AddTimestampInterval`/`SubTimestampInterval` if one argument is a timestamp, date or datetime
+    /// and the other is an interval, return `None` otherwise
+    ///
+    /// unlike other arithmetic functions, the two arguments have different types so it's not in `SPECIALIZATION`
+    fn timestamp_interval_specialization(
+        generic: GenericFn,
+        arg_exprs: &[ScalarExpr],
+        arg_types: &[Option<ConcreteDataType>],
+    ) -> Option<(Self, Signature)> {
+        let types = arg_types
+            .iter()
+            .zip(arg_exprs)
+            .map(|(typ, expr)| {
+                typ.clone()
+                    .or_else(|| expr.as_literal().map(|lit| lit.data_type()))
+            })
+            .collect_vec();
+        let is_temporal = |typ: &Option<ConcreteDataType>| {
+            matches!(
+                typ,
+                Some(
+                    ConcreteDataType::Timestamp(_)
+                        | ConcreteDataType::Date(_)
+                        | ConcreteDataType::DateTime(_)
+                )
+            )
+        };
+        let is_interval =
+            |typ: &Option<ConcreteDataType>| matches!(typ, Some(ConcreteDataType::Interval(_)));
+
+        let func = match generic {
+            GenericFn::Add
+                if (is_temporal(&types[0]) && is_interval(&types[1]))
+                    || (is_interval(&types[0]) && is_temporal(&types[1])) =>
+            {
+                Self::AddTimestampInterval
+            }
+            GenericFn::Sub if is_temporal(&types[0]) && is_interval(&types[1]) => {
+                Self::SubTimestampInterval
+            }
+            _ => return None,
+        };
+        let signature = Signature {
+            input: types
+                .into_iter()
+                .map(|typ| typ.unwrap_or_else(ConcreteDataType::null_datatype))
+                .collect(),
+            output: func.signature().output,
+            generic_fn: generic,
+        };
+        Some((func, signature))
+    }
+
     /// try it's best to infer types from the input types and expressions
     ///
     /// if it can't found out types, will return None
@@ -877,6 +1507,11 @@ impl BinaryFunc {
             }
         );
 
+        if let Some(ret) = Self::timestamp_interval_specialization(generic_fn, arg_exprs, arg_types)
+        {
+            return Ok(ret);
+        }
+
         let arg_type = Self::infer_type_from(generic_fn, arg_exprs, arg_types)?;
 
         // if type is not needed, we can erase input type to null to find correct functions for
@@ -905,8 +1540,15 @@ impl BinaryFunc {
         expr2: &ScalarExpr,
     ) -> Result<VectorRef, EvalError> {
         let left = expr1.eval_batch(batch)?;
-        let left = left.to_arrow_array();
         let right = expr2.eval_batch(batch)?;
+        if let Self::AddTimestampInterval | Self::SubTimestampInterval = self {
+            return timestamp_add_interval_batch(
+                &left,
+                &right,
+                *self == Self::SubTimestampInterval,
+            );
+        }
+        let left = left.to_arrow_array();
         let right = right.to_arrow_array();
 
         let arrow_array: ArrayRef = match self {
@@ -982,6 +1624,10 @@ impl BinaryFunc {
             | Self::ModUInt32
             | Self::ModUInt64 => arrow::compute::kernels::numeric::rem(&left, &right)
                 .context(ArrowSnafu { context: "rem" })?,
+
+            Self::AddTimestampInterval | Self::SubTimestampInterval => {
+                unreachable!("Already handled above")
+            }
         };
 
         let vector = Helper::try_into_vector(arrow_array).context(DataTypeSnafu {
@@ -1057,6 +1703,9 @@ impl BinaryFunc {
             Self::ModUInt16 => Ok(rem::<u16>(left, right)?),
             Self::ModUInt32 => Ok(rem::<u32>(left, right)?),
             Self::ModUInt64 => Ok(rem::<u64>(left, right)?),
+
+            Self::AddTimestampInterval => timestamp_add_interval(left, right, false),
+            Self::SubTimestampInterval => timestamp_add_interval(left, right, true),
         }
     }
 
@@ -1086,23 +1735,51 @@ impl BinaryFunc {
 pub enum VariadicFunc {
     And,
     Or,
+    /// Return the first non-null argument, all arguments must be of the same type
+    Coalesce,
+    /// Return null if the two arguments are equal, otherwise return the first argument
+    NullIf,
 }
 
 impl VariadicFunc {
     /// Return the signature of the function
+    ///
+    /// For `Coalesce` and `NullIf`, the output type is the same as the input type, so null type is used
+    /// as a placeholder, see [`ScalarExpr::typ`] for the actual type
     pub fn signature(&self) -> Signature {
-        Signature {
-            input: smallvec![ConcreteDataType::boolean_datatype()],
-            output: ConcreteDataType::boolean_datatype(),
-            generic_fn: match self {
-                Self::And => GenericFn::And,
-                Self::Or => GenericFn::Or,
+        match self {
+            Self::And | Self::Or => Signature {
+                input: smallvec![ConcreteDataType::boolean_datatype()],
+                output: ConcreteDataType::boolean_datatype(),
+                generic_fn: match self {
+                    Self::And => GenericFn::And,
+                    Self::Or => GenericFn::Or,
+                    _ => unreachable!(),
+                },
+            },
+            Self::Coalesce | Self::NullIf => Signature {
+                input: smallvec![ConcreteDataType::null_datatype()],
+                output: ConcreteDataType::null_datatype(),
+                generic_fn: match self {
+                    Self::Coalesce => GenericFn::Coalesce,
+                    Self::NullIf => GenericFn::NullIf,
+                    _ => unreachable!(),
+                },
             },
         }
     }
 
     pub fn is_valid_func_name(name: &str) -> bool {
-        matches!(name.to_lowercase().as_str(), "and" | "or")
+        matches!(
+            name.to_lowercase().as_str(),
+            "and" | "or" | "coalesce" | "nullif"
+        )
+    }
+
+    /// Whether nested calls of the same function can be flattened into one call,
+    /// i.e. `and(and(a, b), c)` is `and(a, b, c)`
+    pub fn is_associative(&self) -> bool {
+        matches!(self, Self::And | Self::Or | Self::Coalesce)
     }
 
     /// Create a VariadicFunc from a string of the function name and given argument types(optional)
@@ -1110,11 +1787,30 @@ impl VariadicFunc {
         name: &str,
         arg_types: &[Option<ConcreteDataType>],
     ) -> Result<Self, Error> {
-        // TODO(discord9): future variadic funcs to be added might need to check arg_types
-        let _ = arg_types;
         match name {
             "and" => Ok(Self::And),
             "or" => Ok(Self::Or),
+            "coalesce" => {
+                ensure!(
+                    !arg_types.is_empty(),
+                    InvalidQuerySnafu {
+                        reason: "coalesce function requires at least 1 argument".to_string(),
+                    }
+                );
+                Ok(Self::Coalesce)
+            }
+            "nullif" => {
+                ensure!(
+                    arg_types.len() == 2,
+                    InvalidQuerySnafu {
+                        reason: format!(
+                            "nullif function requires exactly 2 arguments, found {}",
+                            arg_types.len()
+                        ),
+                    }
+                );
+                Ok(Self::NullIf)
+            }
             _ => InvalidQuerySnafu {
                 reason: format!("Unknown variadic function: {}", name),
             }
@@ -1133,6 +1829,51 @@ impl VariadicFunc {
             .iter()
             .map(|expr| expr.eval_batch(batch).map(|v| v.to_arrow_array()))
             .collect::<Result<Vec<_>, _>>()?;
+
+        match self {
+            Self::And | Self::Or => self.eval_batch_bool(args),
+            Self::Coalesce => {
+                // NOTE: all arguments are evaluated, which assume all given expr are pure
+                let mut iter = args.into_iter();
+                let mut ret = iter.next().context(InvalidArgumentSnafu {
+                    reason: "coalesce function requires at least 1 argument",
+                })?;
+                for next in iter {
+                    let is_not_null = arrow::compute::is_not_null(&ret).context(ArrowSnafu {
+                        context: "is_not_null",
+                    })?;
+                    ret = arrow::compute::kernels::zip::zip(&is_not_null, &ret, &next).context(
+                        ArrowSnafu {
+                            context: "coalesce",
+                        },
+                    )?;
+                }
+                Helper::try_into_vector(ret).context(DataTypeSnafu {
+                    msg: "Fail to convert to Vector",
+                })
+            }
+            Self::NullIf => {
+                ensure!(
+                    args.len() == 2,
+                    InvalidArgumentSnafu {
+                        reason: format!(
+                            "nullif function requires exactly 2 arguments, found {}",
+                            args.len()
+                        )
+                    }
+                );
+                let eq = arrow::compute::kernels::cmp::eq(&args[0], &args[1])
+                    .context(ArrowSnafu { context: "eq" })?;
+                let ret = arrow::compute::nullif(&args[0], &eq)
+                    .context(ArrowSnafu { context: "nullif" })?;
+                Helper::try_into_vector(ret).context(DataTypeSnafu {
+                    msg: "Fail to convert to Vector",
+                })
+            }
+        }
+    }
+
+    fn eval_batch_bool(&self, args: Vec<ArrayRef>) -> Result<VectorRef, EvalError> {
         let mut iter = args.into_iter();
 
         let first = iter.next().unwrap();
@@ -1161,6 +1902,7 @@ impl VariadicFunc {
                 Self::Or => {
                     arrow::compute::or(&left, right).context(ArrowSnafu { context: "or" })?
                 }
+                _ => unreachable!("Only boolean variadic functions are evaluated here"),
             }
         }
 
@@ -1172,7 +1914,44 @@ impl VariadicFunc {
         match self {
             VariadicFunc::And => and(values, exprs),
             VariadicFunc::Or => or(values, exprs),
+            VariadicFunc::Coalesce => coalesce(values, exprs),
+            VariadicFunc::NullIf => nullif(values, exprs),
+        }
+    }
+}
+
+fn coalesce(values: &[Value], exprs: &[ScalarExpr]) -> Result<Value, EvalError> {
+    ensure!(
+        !exprs.is_empty(),
+        InvalidArgumentSnafu {
+            reason: "coalesce function requires at least 1 argument",
         }
+    );
+    for expr in exprs {
+        let val = expr.eval(values)?;
+        if !val.is_null() {
+            return Ok(val); // short-circuit
+        }
+    }
+    Ok(Value::Null)
+}
+
+fn nullif(values: &[Value], exprs: &[ScalarExpr]) -> Result<Value, EvalError> {
+    ensure!(
+        exprs.len() == 2,
+        InvalidArgumentSnafu {
+            reason: format!(
+                "nullif function requires exactly 2 arguments, found {}",
+                exprs.len()
+            )
+        }
+    );
+    let left = exprs[0].eval(values)?;
+    let right = exprs[1].eval(values)?;
+    if !left.is_null() && left == right {
+        Ok(Value::Null)
+    } else {
+        Ok(left)
     }
 }
 
@@ -1464,6 +2243,352 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_regex_func() {
+        let arg = ScalarExpr::Column(0);
+        let regex_match = UnaryFunc::RegexpMatch {
+            regex: CachedRegex::new("^/api/").unwrap(),
+            negated: false,
+        };
+        let regex_not_match = UnaryFunc::RegexpMatch {
+            regex: CachedRegex::new("^/api/").unwrap(),
+            negated: true,
+        };
+        let regex_replace = UnaryFunc::RegexpReplace {
+            regex: CachedRegex::new("/(v\\d+)/").unwrap(),
+            replacement: to_rust_regex_replacement("/\\1/latest/"),
+            global: false,
+        };
+
+        let values = vec![Value::from("/api/v1/users")];
+        assert_eq!(regex_match.eval(&values, &arg).unwrap(), Value::from(true));
+        assert_eq!(
+            regex_not_match.eval(&values, &arg).unwrap(),
+            Value::from(false)
+        );
+        assert_eq!(
+            regex_replace.eval(&values, &arg).unwrap(),
+            Value::from("/api/v1/latest/users")
+        );
+        assert_eq!(regex_match.eval(&[Value::Null], &arg).unwrap(), Value::Null);
+        assert!(matches!(
+            regex_match.eval(&[Value::from(1i32)], &arg),
+            Err(EvalError::TypeMismatch { .. })
+        ));
+
+        let vector = StringVector::from(vec![Some("/api/v1/users"), Some("/static/v2/a"), None]);
+        let batch = Batch::try_new(vec![Arc::new(vector)], 3).unwrap();
+        assert_eq!(
+            regex_match
+                .eval_batch(&batch, &arg)
+                .unwrap()
+                .to_arrow_array()
+                .as_ref(),
+            BooleanVector::from(vec![Some(true), Some(false), None])
+                .to_arrow_array()
+                .as_ref()
+        );
+        assert_eq!(
+            regex_replace
+                .eval_batch(&batch, &arg)
+                .unwrap()
+                .to_arrow_array()
+                .as_ref(),
+            StringVector::from(vec![
+                Some("/api/v1/latest/users"),
+                Some("/static/v2/latest/a"),
+                None
+            ])
+            .to_arrow_array()
+            .as_ref()
+        );
+    }
+
+    #[test]
+    fn test_timestamp_interval_arith() {
+        let (func, signature) = BinaryFunc::from_str_expr_and_type(
+            "subtract",
+            &[
+                ScalarExpr::Column(0),
+                ScalarExpr::Literal(
+                    Value::from(common_time::IntervalDayTime::new(1, 1_000)),
+                    ConcreteDataType::interval_day_time_datatype(),
+                ),
+            ],
+            &[Some(ConcreteDataType::timestamp_second_datatype()), None],
+        )
+        .unwrap();
+        assert_eq!(func, BinaryFunc::SubTimestampInterval);
+        assert_eq!(
+            signature.input.to_vec(),
+            vec![
+                ConcreteDataType::timestamp_second_datatype(),
+                ConcreteDataType::interval_day_time_datatype()
+            ]
+        );
+
+        let interval = ScalarExpr::Literal(
+            Value::from(common_time::IntervalDayTime::new(1, 1_000)),
+            ConcreteDataType::interval_day_time_datatype(),
+        );
+        let values = vec![Value::from(Timestamp::new_second(86_402))];
+        assert_eq!(
+            func.eval(&values, &ScalarExpr::Column(0), &interval)
+                .unwrap(),
+            Value::from(Timestamp::new_millisecond(1_000))
+        );
+        assert_eq!(
+            BinaryFunc::AddTimestampInterval
+                .eval(&values, &interval, &ScalarExpr::Column(0))
+                .unwrap(),
+            Value::from(Timestamp::new_millisecond(172_803_000))
+        );
+
+        let vector = datatypes::vectors::TimestampSecondVector::from(vec![Some(86_402), None]);
+        let batch = Batch::try_new(vec![Arc::new(vector)], 2).unwrap();
+        let res = func
+            .eval_batch(&batch, &ScalarExpr::Column(0), &interval)
+            .unwrap();
+        let expected = TimestampMillisecondVector::from(vec![Some(1_000), None]);
+        assert_eq!(res.to_arrow_array(), expected.to_arrow_array());
+
+        // month has variable length so it's not supported
+        let month = ScalarExpr::Literal(
+            Value::from(common_time::IntervalYearMonth::new(1)),
+            ConcreteDataType::interval_year_month_datatype(),
+        );
+        assert!(func.eval(&values, &ScalarExpr::Column(0), &month).is_err());
+    }
+
+    #[test]
+    fn test_cast_temporal() {
+        let arg = ScalarExpr::Column(0);
+        let cases = [
+            (
+                Value::from(Timestamp::new_second(1)),
+                ConcreteDataType::timestamp_nanosecond_datatype(),
+                Value::from(Timestamp::new_nanosecond(1_000_000_000)),
+            ),
+            (
+                Value::from(Timestamp::new_microsecond(-1)),
+                ConcreteDataType::timestamp_millisecond_datatype(),
+                Value::from(Timestamp::new_millisecond(-1)),
+            ),
+            (
+                Value::from(Timestamp::new_millisecond(86_400_000 + 1)),
+                ConcreteDataType::date_datatype(),
+                Value::from(common_time::Date::new(1)),
+            ),
+            (
+                Value::from(common_time::Date::new(1)),
+                ConcreteDataType::timestamp_second_datatype(),
+                Value::from(Timestamp::new_second(86_400)),
+            ),
+            (
+                Value::from(common_time::DateTime::new(1_500)),
+                ConcreteDataType::timestamp_second_datatype(),
+                Value::from(Timestamp::new_second(1)),
+            ),
+            (
+                Value::Null,
+                ConcreteDataType::timestamp_second_datatype(),
+                Value::Null,
+            ),
+        ];
+        for (input, to, expected) in cases {
+            let func = UnaryFunc::Cast(to.clone());
+            assert_eq!(func.eval(&[input.clone()], &arg).unwrap(), expected);
+
+            let mut builder = input.data_type().create_mutable_vector(1);
+            builder.push_value_ref(input.as_value_ref());
+            let batch = Batch::try_new(vec![builder.to_vector()], 1).unwrap();
+            let res = func.eval_batch(&batch, &arg).unwrap();
+            if input.is_null() {
+                assert!(res.is_null(0));
+            } else {
+                assert_eq!(res.data_type(), to);
+                assert_eq!(res.get(0), expected);
+            }
+        }
+
+        // overflow is reported instead of silently producing null
+        let func = UnaryFunc::Cast(ConcreteDataType::timestamp_nanosecond_datatype());
+        let input = Value::from(Timestamp::new_second(i64::MAX / 2));
+        assert!(matches!(
+            func.eval(&[input], &arg),
+            Err(EvalError::Overflow { .. })
+        ));
+        let vector = datatypes::vectors::TimestampSecondVector::from_vec(vec![1, i64::MAX / 2]);
+        let batch = Batch::try_new(vec![Arc::new(vector)], 2).unwrap();
+        assert!(matches!(
+            func.eval_batch(&batch, &arg),
+            Err(EvalError::Overflow { .. })
+        ));
+    }
+
+    #[test]
+    fn test_null_handling_funcs() {
+        let values = vec![Value::Null, Value::from(1i64), Value::from(2i64)];
+        let coalesce = |exprs: Vec<ScalarExpr>| VariadicFunc::Coalesce.eval(&values, &exprs);
+        assert_eq!(
+            coalesce(vec![ScalarExpr::Column(0), ScalarExpr::Column(1)]).unwrap(),
+            Value::from(1i64)
+        );
+        assert_eq!(
+            coalesce(vec![ScalarExpr::Column(2), ScalarExpr::Column(1)]).unwrap(),
+            Value::from(2i64)
+        );
+        assert_eq!(
+            coalesce(vec![ScalarExpr::Column(0), ScalarExpr::Column(0)]).unwrap(),
+            Value::Null
+        );
+        assert!(matches!(
+            coalesce(vec![]),
+            Err(EvalError::InvalidArgument { .. })
+        ));
+        assert!(VariadicFunc::from_str_and_types("coalesce", &[]).is_err());
+
+        let nullif = |exprs: Vec<ScalarExpr>| VariadicFunc::NullIf.eval(&values, &exprs);
+        assert_eq!(
+            nullif(vec![ScalarExpr::Column(1), ScalarExpr::Column(1)]).unwrap(),
+            Value::Null
+        );
+        assert_eq!(
+            nullif(vec![ScalarExpr::Column(1), ScalarExpr::Column(2)]).unwrap(),
+            Value::from(1i64)
+        );
+        assert_eq!(
+            nullif(vec![ScalarExpr::Column(0), ScalarExpr::Column(2)]).unwrap(),
+            Value::Null
+        );
+
+        assert_eq!(
+            UnaryFunc::IsNotNull
+                .eval(&values, &ScalarExpr::Column(0))
+                .unwrap(),
+            Value::from(false)
+        );
+        assert_eq!(
+            UnaryFunc::IsNotNull
+                .eval(&values, &ScalarExpr::Column(1))
+                .unwrap(),
+            Value::from(true)
+        );
+
+        let batch = Batch::try_new(
+            vec![
+                Arc::new(datatypes::vectors::Int64Vector::from(vec![
+                    None,
+                    Some(1),
+                    Some(2),
+                    None,
+                ])),
+                Arc::new(datatypes::vectors::Int64Vector::from(vec![
+                    Some(10),
+                    Some(1),
+                    Some(20),
+                    None,
+                ])),
+            ],
+            4,
+        )
+        .unwrap();
+        let args = vec![ScalarExpr::Column(0), ScalarExpr::Column(1)];
+        assert_eq!(
+            VariadicFunc::Coalesce
+                .eval_batch(&batch, &args)
+                .unwrap()
+                .to_arrow_array()
+                .as_ref(),
+            datatypes::vectors::Int64Vector::from(vec![Some(10), Some(1), Some(2), None])
+                .to_arrow_array()
+                .as_ref()
+        );
+        assert_eq!(
+            VariadicFunc::NullIf
+                .eval_batch(&batch, &args)
+                .unwrap()
+                .to_arrow_array()
+                .as_ref(),
+            datatypes::vectors::Int64Vector::from(vec![None, None, Some(2), None])
+                .to_arrow_array()
+                .as_ref()
+        );
+        assert_eq!(
+            UnaryFunc::IsNotNull
+                .eval_batch(&batch, &ScalarExpr::Column(0))
+                .unwrap()
+                .to_arrow_array()
+                .as_ref(),
+            BooleanVector::from(vec![false, true, true, false])
+                .to_arrow_array()
+                .as_ref()
+        );
+    }
+
+    #[test]
+    fn test_in_list() {
+        let arg = ScalarExpr::Column(0);
+        let in_list = UnaryFunc::in_list(vec![
+            Value::from(3i64),
+            Value::from(1i64),
+            Value::from(3i64),
+            Value::from(2i64),
+        ]);
+        assert_eq!(
+            in_list,
+            UnaryFunc::InList {
+                list: vec![Value::from(1i64), Value::from(2i64), Value::from(3i64)],
+                contains_null: false,
+            }
+        );
+        assert_eq!(
+            in_list.eval(&[Value::from(2i64)], &arg).unwrap(),
+            Value::from(true)
+        );
+        assert_eq!(
+            in_list.eval(&[Value::from(4i64)], &arg).unwrap(),
+            Value::from(false)
+        );
+        assert_eq!(in_list.eval(&[Value::Null], &arg).unwrap(), Value::Null);
+        assert!(matches!(
+            in_list.eval(&[Value::from(2i32)], &arg),
+            Err(EvalError::TypeMismatch { .. })
+        ));
+
+        // `x IN (1, NULL)` is null instead of false if `x` is not found
+        let in_list_with_null = UnaryFunc::in_list(vec![Value::from(1i64), Value::Null]);
+        assert_eq!(
+            in_list_with_null.eval(&[Value::from(4i64)], &arg).unwrap(),
+            Value::Null
+        );
+        assert_eq!(
+            UnaryFunc::Not
+                .eval(&[Value::Null], &arg.clone().call_unary(in_list_with_null))
+                .unwrap(),
+            Value::Null
+        );
+
+        let vector = datatypes::vectors::Int64Vector::from(vec![Some(1), Some(5), None, Some(3)]);
+        let batch = Batch::try_new(vec![Arc::new(vector)], 4).unwrap();
+        assert_eq!(
+            in_list
+                .eval_batch(&batch, &arg)
+                .unwrap()
+                .to_arrow_array()
+                .as_ref(),
+            BooleanVector::from(vec![Some(true), Some(false), None, Some(true)])
+                .to_arrow_array()
+                .as_ref()
+        );
+    }
+
+    #[test]
+    fn test_regex_replacement() {
+        assert_eq!(to_rust_regex_replacement("\\1-\\23"), "${1}-${23}");
+        assert_eq!(to_rust_regex_replacement("$1\\a"), "$$1\\a");
+    }
+
     #[test]
     fn test_cast_int() {
         let interval = cast(
@@ -18,18 +18,17 @@ use std::collections::{BTreeMap, BTreeSet};
 
 use arrow::array::BooleanArray;
 use arrow::buffer::BooleanBuffer;
-use arrow::compute::FilterBuilder;
 use common_telemetry::trace;
 use datatypes::prelude::ConcreteDataType;
 use datatypes::value::Value;
-use datatypes::vectors::{BooleanVector, Helper};
+use datatypes::vectors::BooleanVector;
 use itertools::Itertools;
 use snafu::{ensure, OptionExt, ResultExt};
 
 use crate::error::{Error, InvalidQuerySnafu};
-use crate::expr::error::{ArrowSnafu, DataTypeSnafu, EvalError, InternalSnafu, TypeMismatchSnafu};
+use crate::expr::error::{ArrowSnafu, EvalError, InternalSnafu, TypeMismatchSnafu};
 use crate::expr::{Batch, InvalidArgumentSnafu, ScalarExpr};
-use crate::repr::{self, value_to_internal_ts, Diff, Row};
+use crate::repr::{self, value_to_internal_ts_ceil, Diff, Row};
 
 /// A compound operator that can be applied row-by-row.
 ///
@@ -54,7 +53,7 @@ use crate::repr::{self, value_to_internal_ts, Diff, Row};
 /// expressions in `self.expressions`, even though this is not something
 /// we can directly evaluate. The plan creation methods will defensively
 /// ensure that the right thing happens.
-#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct MapFilterProject {
     /// A sequence of expressions that should be appended to the row.
     ///
@@ -83,6 +82,21 @@ pub struct MapFilterProject {
     pub input_arity: usize,
 }
 
+/// Explain the mfp as `map=[..], filter=[..], project=[..]`, omitting empty expressions and predicates
+impl std::fmt::Display for MapFilterProject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.expressions.is_empty() {
+            write!(f, "map=[{}], ", self.expressions.iter().join(", "))?;
+        }
+        if !self.predicates.is_empty() {
+            let predicates = self.predicates.iter().map(|(_, p)| p);
+            write!(f, "filter=[{}], ", predicates.format(", "))?;
+        }
+        let projection = self.projection.iter().map(|c| format!("#{c}"));
+        write!(f, "project=[{}]", projection.format(", "))
+    }
+}
+
 impl MapFilterProject {
     /// Create a no-op operator for an input of a supplied arity.
     pub fn new(input_arity: usize) -> Self {
@@ -318,13 +332,98 @@ impl MapFilterProject {
 
 impl MapFilterProject {
     /// Convert the `MapFilterProject` into a safe evaluation plan. Marking it safe to evaluate.
+    /// The expression of output column `col` over the input columns, with expressions it
+    /// depends on inlined
+    pub fn output_expr(&self, col: usize) -> Result<ScalarExpr, Error> {
+        let mut inlined: Vec<ScalarExpr> = Vec::with_capacity(self.expressions.len());
+        for expr in &self.expressions {
+            let mut expr = expr.clone();
+            expr.inline_columns(self.input_arity, &inlined)?;
+            inlined.push(expr);
+        }
+        let col = *self
+            .projection
+            .get(col)
+            .with_context(|| InvalidQuerySnafu {
+                reason: format!(
+                    "output column {} is out of range, expect less than {}",
+                    col,
+                    self.projection.len()
+                ),
+            })?;
+        let mut expr = ScalarExpr::Column(col);
+        expr.inline_columns(self.input_arity, &inlined)?;
+        Ok(expr)
+    }
+
     pub fn into_safe(self) -> SafeMfpPlan {
         SafeMfpPlan { mfp: self }
     }
 
     /// Optimize the `MapFilterProject` in place.
+    ///
+    /// Expressions that merely reference another column are inlined, and expressions that are
+    /// neither projected nor referenced by predicates or other kept expressions are removed.
     pub fn optimize(&mut self) {
-        // TODO(discord9): optimize
+        // optimization is best effort, keep the original mfp if failed to rewrite it
+        if let Ok(optimized) = self.clone().inline_and_prune() {
+            *self = optimized;
+        }
+    }
+
+    /// Inline column reference expressions and remove unused expressions, see [`MapFilterProject::optimize`]
+    fn inline_and_prune(self) -> Result<Self, Error> {
+        let input_arity = self.input_arity;
+        let (mut map, mut filter, mut project) = self.into_map_filter_project();
+
+        // resolve references through expressions like `col(2)`, so they point to the original column
+        let mut inline: BTreeMap<usize, usize> =
+            (0..input_arity + map.len()).map(|c| (c, c)).collect();
+        for (index, expr) in map.iter_mut().enumerate() {
+            expr.permute_map(&inline)?;
+            if let ScalarExpr::Column(c) = expr {
+                inline.insert(input_arity + index, *c);
+            }
+        }
+        for pred in filter.iter_mut() {
+            pred.permute_map(&inline)?;
+        }
+        for proj in project.iter_mut() {
+            *proj = inline[proj];
+        }
+
+        let mut demanded: BTreeSet<usize> = project.iter().cloned().collect();
+        for pred in filter.iter() {
+            demanded.extend(pred.get_all_ref_columns());
+        }
+        for index in (0..map.len()).rev() {
+            if demanded.contains(&(input_arity + index)) {
+                demanded.extend(map[index].get_all_ref_columns());
+            }
+        }
+
+        // only keep demanded expressions, and shift references to them accordingly
+        let mut shuffle: BTreeMap<usize, usize> = (0..input_arity).map(|c| (c, c)).collect();
+        let mut kept = Vec::new();
+        for (index, expr) in map.into_iter().enumerate() {
+            if demanded.contains(&(input_arity + index)) {
+                shuffle.insert(input_arity + index, input_arity + kept.len());
+                kept.push(expr);
+            }
+        }
+        for expr in kept.iter_mut() {
+            expr.permute_map(&shuffle)?;
+        }
+        for pred in filter.iter_mut() {
+            pred.permute_map(&shuffle)?;
+        }
+        for proj in project.iter_mut() {
+            *proj = shuffle[proj];
+        }
+        Self::new(input_arity)
+            .map(kept)?
+            .filter(filter)?
+            .project(project)
     }
     /// get the mapping of old columns to new columns after the mfp
     pub fn get_old_to_new_mapping(&self) -> BTreeMap<usize, usize> {
@@ -467,7 +566,7 @@ impl MapFilterProject {
 }
 
 /// A wrapper type which indicates it is safe to simply evaluate all expressions.
-#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct SafeMfpPlan {
     /// the inner `MapFilterProject` that is safe to evaluate.
     pub(crate) mfp: MapFilterProject,
@@ -495,28 +594,16 @@ impl SafeMfpPlan {
         );
 
         let passed_predicates = self.eval_batch_inner(batch)?;
-        let filter = FilterBuilder::new(passed_predicates.as_boolean_array());
-        let pred = filter.build();
-        let mut result = vec![];
-        for col in batch.batch() {
-            let filtered = pred
-                .filter(col.to_arrow_array().as_ref())
-                .with_context(|_| ArrowSnafu {
-                    context: format!("failed to filter column for mfp operator {:?}", self),
-                })?;
-            result.push(Helper::try_into_vector(filtered).context(DataTypeSnafu {
-                msg: "Failed to convert arrow array to vector",
-            })?);
-        }
+        // diffs and timestamps of rows passing the predicates are kept along with them
+        let mut result = batch.filter(&passed_predicates)?;
         let projected = self
             .mfp
             .projection
             .iter()
-            .map(|c| result[*c].clone())
+            .map(|c| result.batch()[*c].clone())
             .collect_vec();
-        let row_count = pred.count();
-
-        Batch::try_new(projected, row_count)
+        *result.batch_mut() = projected;
+        Ok(result)
     }
 
     /// similar to [`MapFilterProject::evaluate_into`], just in batch.
@@ -757,7 +844,7 @@ impl MfpPlan {
                         null_eval = true;
                         continue;
                     }
-                    match value_to_internal_ts(v) {
+                    match value_to_internal_ts_ceil(v) {
                         Ok(ts) => lower_bound = lower_bound.max(ts),
                         Err(e) => return ret_err(e),
                     }
@@ -775,7 +862,7 @@ impl MfpPlan {
                             null_eval = true;
                             continue;
                         }
-                        let ts = match value_to_internal_ts(val) {
+                        let ts = match value_to_internal_ts_ceil(val) {
                             Ok(ts) => ts,
                             Err(e) => return ret_err(e),
                         };
@@ -890,6 +977,32 @@ mod test {
         }
     }
 
+    /// temporal bounds of nanosecond timestamps are rounded up to the next millisecond
+    #[test]
+    fn test_mfp_with_precise_time() {
+        let now = || ScalarExpr::CallUnmaterializable(UnmaterializableFunc::Now);
+        let mfp = MapFilterProject::new(2)
+            .filter(vec![
+                // col(0) <= now()
+                ScalarExpr::Column(0).call_binary(now(), BinaryFunc::Lte),
+                // now() < col(1)
+                now().call_binary(ScalarExpr::Column(1), BinaryFunc::Lt),
+            ])
+            .unwrap()
+            .project(vec![0])
+            .unwrap();
+        let mfp = MfpPlan::create_from(mfp).unwrap();
+
+        let ns = |v: i64| Value::from(common_time::Timestamp::new_nanosecond(v));
+        let mut values = vec![ns(1_500_000), ns(3_500_000)];
+        let ret = mfp
+            .evaluate::<EvalError>(&mut values, 0, 1)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let row = Row::new(vec![ns(1_500_000)]);
+        assert_eq!(ret, vec![(row.clone(), 2, 1), (row, 4, -1)]);
+    }
+
     #[test]
     fn test_mfp() {
         use crate::expr::func::BinaryFunc;
@@ -1119,6 +1232,27 @@ mod test {
             Batch::try_new(vec![Arc::new(BooleanVector::from(vec![false]))], 1).unwrap()
         );
     }
+
+    #[test]
+    fn test_mfp_output_expr() {
+        let mfp = MapFilterProject::new(2)
+            .map(vec![
+                ScalarExpr::Column(0).call_binary(ScalarExpr::Column(1), BinaryFunc::AddInt64),
+                ScalarExpr::Column(2).call_binary(ScalarExpr::Column(1), BinaryFunc::AddInt64),
+            ])
+            .unwrap()
+            .project(vec![3, 1])
+            .unwrap();
+        assert_eq!(
+            mfp.output_expr(0).unwrap(),
+            ScalarExpr::Column(0)
+                .call_binary(ScalarExpr::Column(1), BinaryFunc::AddInt64)
+                .call_binary(ScalarExpr::Column(1), BinaryFunc::AddInt64)
+        );
+        assert_eq!(mfp.output_expr(1).unwrap(), ScalarExpr::Column(1));
+        assert!(mfp.output_expr(2).is_err());
+    }
+
     #[test]
     fn test_mfp_chore() {
         // project keeps permute columns until it becomes the identity permutation
@@ -1131,4 +1265,40 @@ mod test {
             .unwrap();
         assert_eq!(mfp, MapFilterProject::new(3));
     }
+
+    #[test]
+    fn test_mfp_optimize() {
+        // map/project pair that merely reorders columns, plus an unused expression
+        let mut mfp = MapFilterProject::new(3)
+            .map(vec![
+                ScalarExpr::Column(2),
+                ScalarExpr::Column(0),
+                ScalarExpr::Column(0).call_binary(ScalarExpr::Column(1), BinaryFunc::AddInt32),
+                ScalarExpr::Column(4).call_binary(ScalarExpr::Column(1), BinaryFunc::Lt),
+            ])
+            .unwrap()
+            .filter(vec![ScalarExpr::Column(6)])
+            .unwrap()
+            .project(vec![3, 4])
+            .unwrap();
+        mfp.optimize();
+        let expected = MapFilterProject::new(3)
+            .map(vec![
+                ScalarExpr::Column(0).call_binary(ScalarExpr::Column(1), BinaryFunc::Lt)
+            ])
+            .unwrap()
+            .filter(vec![ScalarExpr::Column(3)])
+            .unwrap()
+            .project(vec![2, 0])
+            .unwrap();
+        assert_eq!(mfp, expected);
+
+        let mut mfp = MapFilterProject::new(2)
+            .map(vec![ScalarExpr::Column(0), ScalarExpr::Column(1)])
+            .unwrap()
+            .project(vec![2, 3])
+            .unwrap();
+        mfp.optimize();
+        assert!(mfp.is_identity());
+    }
 }
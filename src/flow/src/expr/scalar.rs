@@ -118,9 +118,19 @@ impl ScalarExpr {
             ScalarExpr::CallBinary { func, .. } => {
                 Ok(ColumnType::new_nullable(func.signature().output))
             }
-            ScalarExpr::CallVariadic { func, .. } => {
-                Ok(ColumnType::new_nullable(func.signature().output))
-            }
+            ScalarExpr::CallVariadic { func, exprs } => match func {
+                // output type is the same as the (already unified) type of the arguments
+                VariadicFunc::Coalesce | VariadicFunc::NullIf => {
+                    for expr in exprs {
+                        let typ = expr.typ(context)?;
+                        if !typ.scalar_type.is_null() {
+                            return Ok(ColumnType::new_nullable(typ.scalar_type));
+                        }
+                    }
+                    Ok(ColumnType::new_nullable(ConcreteDataType::null_datatype()))
+                }
+                _ => Ok(ColumnType::new_nullable(func.signature().output)),
+            },
             ScalarExpr::If { then, .. } => then.typ(context),
             ScalarExpr::CallDf { df_scalar_fn, .. } => {
                 let arrow_typ = df_scalar_fn
@@ -141,6 +151,34 @@ impl ScalarExpr {
     }
 }
 
+/// A compact form for explaining plans, i.e. `Gt(#0, 1)` where `#0` is the first column of input
+impl std::fmt::Display for ScalarExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScalarExpr::Column(i) => write!(f, "#{i}"),
+            ScalarExpr::Literal(val, _) => write!(f, "{val}"),
+            ScalarExpr::CallUnmaterializable(func) => write!(f, "{func:?}()"),
+            ScalarExpr::CallUnary { func, expr } => write!(f, "{func:?}({expr})"),
+            ScalarExpr::CallBinary { func, expr1, expr2 } => {
+                write!(f, "{func:?}({expr1}, {expr2})")
+            }
+            ScalarExpr::CallVariadic { func, exprs } => {
+                write!(f, "{func:?}({})", exprs.iter().join(", "))
+            }
+            ScalarExpr::CallDf {
+                df_scalar_fn,
+                exprs,
+            } => write!(
+                f,
+                "Df[{}]({})",
+                df_scalar_fn.fn_impl,
+                exprs.iter().join(", ")
+            ),
+            ScalarExpr::If { cond, then, els } => write!(f, "If({cond}, {then}, {els})"),
+        }
+    }
+}
+
 impl ScalarExpr {
     pub fn cast(self, typ: ConcreteDataType) -> Self {
         ScalarExpr::CallUnary {
@@ -156,28 +194,109 @@ impl ScalarExpr {
 
     /// Because Substrait's `And`/`Or` function is binary, but FlowPlan's
     /// `And`/`Or` function is variadic, we need to flatten the `And` function if multiple `And`/`Or` functions are nested.
+    ///
+    /// Only associative functions are flattened, other nested calls are kept as is.
     fn flatten_varidic_fn(&mut self) {
         if let ScalarExpr::CallVariadic { func, exprs } = self {
             let mut new_exprs = vec![];
             for expr in std::mem::take(exprs) {
-                if let ScalarExpr::CallVariadic {
-                    func: inner_func,
-                    exprs: mut inner_exprs,
-                } = expr
-                {
-                    if *func == inner_func {
+                match expr {
+                    ScalarExpr::CallVariadic {
+                        func: inner_func,
+                        exprs: mut inner_exprs,
+                    } if *func == inner_func && func.is_associative() => {
                         for inner_expr in inner_exprs.iter_mut() {
                             inner_expr.flatten_varidic_fn();
                         }
                         new_exprs.extend(inner_exprs);
                     }
-                } else {
-                    new_exprs.push(expr);
+                    _ => new_exprs.push(expr),
                 }
             }
             *exprs = new_exprs;
         }
     }
+
+    /// Simplify a predicate into a list of conjuncts which all must be true for a row to pass
+    ///
+    /// Nested `And`/`Or` are flattened, tautologies like `true` conjuncts are removed, and cheaper
+    /// conjuncts are put first, so most rows can be filtered out before evaluating expensive ones
+    pub fn into_conjuncts(self) -> Vec<ScalarExpr> {
+        match self.simplify_boolean() {
+            ScalarExpr::CallVariadic {
+                func: VariadicFunc::And,
+                exprs,
+            } => exprs,
+            expr if expr.is_literal_true() => vec![],
+            expr => vec![expr],
+        }
+    }
+
+    /// Flatten nested `And`/`Or` and fold away literal boolean operands, see [`ScalarExpr::into_conjuncts`]
+    fn simplify_boolean(self) -> ScalarExpr {
+        let ScalarExpr::CallVariadic {
+            func: func @ (VariadicFunc::And | VariadicFunc::Or),
+            exprs,
+        } = self
+        else {
+            return self;
+        };
+        // `true` for `And`, `false` for `Or`, which doesn't change the result thus can be removed
+        let identity = func == VariadicFunc::And;
+
+        let mut operands: Vec<ScalarExpr> = Vec::with_capacity(exprs.len());
+        for expr in exprs {
+            let expr = expr.simplify_boolean();
+            let flattened = match expr {
+                ScalarExpr::CallVariadic {
+                    func: inner_func,
+                    exprs,
+                } if inner_func == func => exprs,
+                expr => vec![expr],
+            };
+            for expr in flattened {
+                match expr.as_literal() {
+                    Some(Value::Boolean(b)) if b == identity => continue,
+                    // `false` for `And`, `true` for `Or` short-circuit the whole expression
+                    Some(Value::Boolean(_)) => return expr,
+                    _ => (),
+                }
+                if !operands.contains(&expr) {
+                    operands.push(expr);
+                }
+            }
+        }
+
+        match operands.len() {
+            0 if identity => ScalarExpr::literal_true(),
+            0 => ScalarExpr::literal_false(),
+            1 => operands.pop().unwrap(),
+            _ => {
+                operands.sort_by_cached_key(|expr| expr.eval_cost());
+                ScalarExpr::CallVariadic {
+                    func,
+                    exprs: operands,
+                }
+            }
+        }
+    }
+
+    /// A rough estimation of the cost to evaluate the expression, falling back to datafusion is
+    /// considered much more expensive than flow's builtin functions
+    fn eval_cost(&self) -> usize {
+        /// cost of calling a datafusion function, which need to convert values to and from arrow arrays
+        const DF_CALL_COST: usize = 16;
+
+        let mut cost = 0;
+        let _ = self.visit_post_nolimit(&mut |e| {
+            cost += match e {
+                ScalarExpr::CallDf { .. } => DF_CALL_COST,
+                _ => 1,
+            };
+            Ok(())
+        });
+        cost
+    }
 }
 
 impl ScalarExpr {
@@ -391,6 +510,27 @@ impl ScalarExpr {
         })
     }
 
+    /// Replace each reference to column `i` no less than `offset` with `exprs[i - offset]`
+    pub fn inline_columns(&mut self, offset: usize, exprs: &[ScalarExpr]) -> Result<(), Error> {
+        self.visit_mut_post_nolimit(&mut |e| {
+            if let ScalarExpr::Column(i) = e {
+                if *i >= offset {
+                    *e = exprs
+                        .get(*i - offset)
+                        .with_context(|| InvalidQuerySnafu {
+                            reason: format!(
+                                "column {} is out of range, expect less than {}",
+                                i,
+                                offset + exprs.len()
+                            ),
+                        })?
+                        .clone();
+                }
+            }
+            Ok(())
+        })
+    }
+
     /// Returns the set of columns that are referenced by `self`.
     pub fn get_all_ref_columns(&self) -> BTreeSet<usize> {
         let mut support = BTreeSet::new();
@@ -578,7 +718,9 @@ impl ScalarExpr {
     /// returned bool indicates whether the bound is upper bound:
     ///
     /// false for lower bound, true for upper bound
-    /// TODO(discord9): allow simple transform like `now() + a < b` to `now() < b - a`
+    ///
+    /// simple interval arithmetic on `now()` is moved to the other side, i.e. `ts > now() - INTERVAL '5 minutes'`
+    /// is the same as `now() < ts + INTERVAL '5 minutes'`
     pub fn extract_bound(&self) -> Result<(Option<Self>, Option<Self>), Error> {
         let unsupported_err = |msg: &str| {
             UnsupportedTemporalFilterSnafu {
@@ -596,20 +738,48 @@ impl ScalarExpr {
             return unsupported_err("Not a binary expression");
         };
 
-        // TODO(discord9): support simple transform like `now() + a < b` to `now() < b - a`
-
-        let expr1_is_now = *expr1 == ScalarExpr::CallUnmaterializable(UnmaterializableFunc::Now);
-        let expr2_is_now = *expr2 == ScalarExpr::CallUnmaterializable(UnmaterializableFunc::Now);
-
-        if !(expr1_is_now ^ expr2_is_now) {
+        if !(expr1.contains_temporal() ^ expr2.contains_temporal()) {
             return unsupported_err("None of the sides of the comparison is `now()`");
         }
 
-        if expr2_is_now {
+        if expr2.contains_temporal() {
             std::mem::swap(&mut expr1, &mut expr2);
             func = BinaryFunc::reverse_compare(&func)?;
         }
 
+        // `now() + a < b` is the same as `now() < b - a`, as adding a interval preserve order
+        let now = ScalarExpr::CallUnmaterializable(UnmaterializableFunc::Now);
+        while *expr1 != now {
+            let Self::CallBinary {
+                func: arith,
+                expr1: lhs,
+                expr2: rhs,
+            } = *expr1
+            else {
+                return unsupported_err("Expect `now()` or interval arithmetic on `now()`");
+            };
+            let (inner, inverse, interval) = match arith {
+                BinaryFunc::AddTimestampInterval if lhs.contains_temporal() => {
+                    (lhs, BinaryFunc::SubTimestampInterval, rhs)
+                }
+                // `INTERVAL + now()`
+                BinaryFunc::AddTimestampInterval => (rhs, BinaryFunc::SubTimestampInterval, lhs),
+                BinaryFunc::SubTimestampInterval if !rhs.contains_temporal() => {
+                    (lhs, BinaryFunc::AddTimestampInterval, rhs)
+                }
+                _ => {
+                    return unsupported_err(
+                        "Only adding or subtracting interval to `now()` is supported",
+                    )
+                }
+            };
+            if interval.contains_temporal() {
+                return unsupported_err("`now()` appears more than once in one side");
+            }
+            expr2 = Box::new(expr2.call_binary(*interval, inverse));
+            expr1 = inner;
+        }
+
         let step = |expr: ScalarExpr| expr.call_unary(UnaryFunc::StepTimestamp);
         match func {
             // now == expr2 -> now <= expr2 && now < expr2 + 1
@@ -622,7 +792,7 @@ impl ScalarExpr {
             BinaryFunc::Gt => Ok((Some(step(*expr2)), None)),
             // now >= expr2 -> now >= expr2
             BinaryFunc::Gte => Ok((Some(*expr2), None)),
-            _ => unreachable!("Already checked"),
+            _ => unsupported_err("Expect a comparison other than `!=` with `now()`"),
         }
     }
 }
@@ -711,6 +881,158 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_extract_bound_with_interval() {
+        let now = || ScalarExpr::CallUnmaterializable(UnmaterializableFunc::Now);
+        let five_min = || {
+            ScalarExpr::Literal(
+                Value::IntervalMonthDayNano(common_time::IntervalMonthDayNano::new(
+                    0,
+                    0,
+                    300_000_000_000,
+                )),
+                ConcreteDataType::interval_month_day_nano_datatype(),
+            )
+        };
+
+        // col(0) > now() - 5min -> now() < col(0) + 5min
+        let expr = ScalarExpr::Column(0).call_binary(
+            now().call_binary(five_min(), BinaryFunc::SubTimestampInterval),
+            BinaryFunc::Gt,
+        );
+        let expected = (
+            None,
+            Some(ScalarExpr::Column(0).call_binary(five_min(), BinaryFunc::AddTimestampInterval)),
+        );
+        assert_eq!(expr.extract_bound().unwrap(), expected);
+
+        // 5min + now() <= col(0) -> now() <= col(0) - 5min
+        let expr = five_min()
+            .call_binary(now(), BinaryFunc::AddTimestampInterval)
+            .call_binary(ScalarExpr::Column(0), BinaryFunc::Lte);
+        let expected = (
+            None,
+            Some(
+                ScalarExpr::Column(0)
+                    .call_binary(five_min(), BinaryFunc::SubTimestampInterval)
+                    .call_unary(UnaryFunc::StepTimestamp),
+            ),
+        );
+        assert_eq!(expr.extract_bound().unwrap(), expected);
+
+        // evaluate the bound
+        let (_, upper) = ScalarExpr::Column(0)
+            .call_binary(
+                now().call_binary(five_min(), BinaryFunc::SubTimestampInterval),
+                BinaryFunc::Gt,
+            )
+            .extract_bound()
+            .unwrap();
+        let values = vec![Value::from(common_time::Timestamp::new_second(1))];
+        assert_eq!(
+            upper.unwrap().eval(&values).unwrap(),
+            Value::from(common_time::Timestamp::new_millisecond(301_000))
+        );
+
+        // `now()` on both side or compared with `!=` is not supported
+        let unsupported = [
+            now()
+                .call_binary(five_min(), BinaryFunc::SubTimestampInterval)
+                .call_binary(now(), BinaryFunc::Gt),
+            now().call_binary(ScalarExpr::Column(0), BinaryFunc::NotEq),
+        ];
+        for expr in unsupported {
+            assert!(expr.extract_bound().is_err());
+        }
+    }
+
+    #[test]
+    fn test_flatten_variadic() {
+        let mut expr = ScalarExpr::CallVariadic {
+            func: VariadicFunc::And,
+            exprs: vec![
+                ScalarExpr::CallVariadic {
+                    func: VariadicFunc::And,
+                    exprs: vec![ScalarExpr::Column(0), ScalarExpr::Column(1)],
+                },
+                ScalarExpr::CallVariadic {
+                    func: VariadicFunc::Or,
+                    exprs: vec![ScalarExpr::Column(2), ScalarExpr::Column(3)],
+                },
+            ],
+        };
+        expr.optimize();
+        assert_eq!(
+            expr,
+            ScalarExpr::CallVariadic {
+                func: VariadicFunc::And,
+                exprs: vec![
+                    ScalarExpr::Column(0),
+                    ScalarExpr::Column(1),
+                    ScalarExpr::CallVariadic {
+                        func: VariadicFunc::Or,
+                        exprs: vec![ScalarExpr::Column(2), ScalarExpr::Column(3)],
+                    },
+                ],
+            }
+        );
+
+        // nullif is not associative, so it's not flattened
+        let nested_nullif = ScalarExpr::CallVariadic {
+            func: VariadicFunc::NullIf,
+            exprs: vec![
+                ScalarExpr::CallVariadic {
+                    func: VariadicFunc::NullIf,
+                    exprs: vec![ScalarExpr::Column(0), ScalarExpr::Column(1)],
+                },
+                ScalarExpr::Column(2),
+            ],
+        };
+        let mut expr = nested_nullif.clone();
+        expr.optimize();
+        assert_eq!(expr, nested_nullif);
+    }
+
+    #[test]
+    fn test_into_conjuncts() {
+        let lit = |v: i64| ScalarExpr::Literal(Value::from(v), ConcreteDataType::int64_datatype());
+        let expensive = ScalarExpr::Column(0)
+            .call_binary(lit(1), BinaryFunc::AddInt64)
+            .call_binary(lit(2), BinaryFunc::Gt);
+        let or = |exprs| ScalarExpr::CallVariadic {
+            func: VariadicFunc::Or,
+            exprs,
+        };
+        let and = |exprs| ScalarExpr::CallVariadic {
+            func: VariadicFunc::And,
+            exprs,
+        };
+
+        let expr = and(vec![
+            and(vec![expensive.clone(), ScalarExpr::literal_true()]),
+            or(vec![
+                ScalarExpr::Column(1),
+                or(vec![ScalarExpr::Column(2), ScalarExpr::literal_false()]),
+            ]),
+            ScalarExpr::Column(3),
+            ScalarExpr::Column(3),
+        ]);
+        assert_eq!(
+            expr.into_conjuncts(),
+            vec![
+                ScalarExpr::Column(3),
+                or(vec![ScalarExpr::Column(1), ScalarExpr::Column(2)]),
+                expensive,
+            ]
+        );
+
+        // short-circuited by literal
+        let expr = and(vec![ScalarExpr::Column(0), ScalarExpr::literal_false()]);
+        assert_eq!(expr.into_conjuncts(), vec![ScalarExpr::literal_false()]);
+        let expr = or(vec![ScalarExpr::Column(0), ScalarExpr::literal_true()]);
+        assert_eq!(expr.into_conjuncts(), Vec::<ScalarExpr>::new());
+    }
+
     #[test]
     fn test_bad_permute() {
         let mut expr = ScalarExpr::Column(4);
@@ -44,10 +44,14 @@ pub enum GenericFn {
     // unary func
     Not,
     IsNull,
+    IsNotNull,
     IsTrue,
     IsFalse,
     StepTimestamp,
     Cast,
+    RegexpMatch,
+    RegexpReplace,
+    InList,
     // binary func
     Eq,
     NotEq,
@@ -63,6 +67,8 @@ pub enum GenericFn {
     // varadic func
     And,
     Or,
+    Coalesce,
+    NullIf,
     // unmaterized func
     Now,
     CurrentSchema,
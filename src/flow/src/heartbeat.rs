@@ -33,6 +33,7 @@ use snafu::ResultExt;
 use tokio::sync::mpsc;
 use tokio::time::Duration;
 
+use crate::adapter::FlowWorkerManagerRef;
 use crate::error::ExternalSnafu;
 use crate::{Error, FlownodeOptions};
 
@@ -47,6 +48,8 @@ pub struct HeartbeatTask {
     resp_handler_executor: HeartbeatResponseHandlerExecutorRef,
     start_time_ms: u64,
     running: Arc<AtomicBool>,
+    /// The manager whose flows' status is reported to metasrv periodically
+    flow_worker_manager: Option<FlowWorkerManagerRef>,
 }
 
 impl HeartbeatTask {
@@ -65,6 +68,16 @@ impl HeartbeatTask {
             resp_handler_executor,
             start_time_ms: common_time::util::current_time_millis() as u64,
             running: Arc::new(AtomicBool::new(false)),
+            flow_worker_manager: None,
+        }
+    }
+
+    /// Reports status of flows in the manager to metasrv periodically, see
+    /// [`FlowWorkerManager::report_flow_stats`](crate::adapter::FlowWorkerManager::report_flow_stats)
+    pub fn with_flow_worker_manager(self, flow_worker_manager: FlowWorkerManagerRef) -> Self {
+        Self {
+            flow_worker_manager: Some(flow_worker_manager),
+            ..self
         }
     }
 
@@ -94,6 +107,8 @@ impl HeartbeatTask {
 
         self.start_heartbeat_report(req_sender, outgoing_rx);
 
+        self.start_flow_stat_report();
+
         Ok(())
     }
 
@@ -184,6 +199,26 @@ impl HeartbeatTask {
         });
     }
 
+    fn start_flow_stat_report(&self) {
+        let Some(manager) = self.flow_worker_manager.clone() else {
+            return;
+        };
+        let report_interval = self.report_interval;
+        let running = self.running.clone();
+
+        common_runtime::spawn_hb(async move {
+            let mut interval = tokio::time::interval(report_interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            while running.load(Ordering::Acquire) {
+                interval.tick().await;
+                if let Err(e) = manager.report_flow_stats().await {
+                    warn!(e; "Failed to report status of flows to metasrv");
+                }
+            }
+        });
+    }
+
     fn start_handle_resp_stream(&self, mut resp_stream: HeartbeatStream, mailbox: MailboxRef) {
         let capture_self = self.clone();
         let retry_interval = self.retry_interval;
@@ -38,6 +38,12 @@ mod server;
 mod transform;
 mod utils;
 
-pub use adapter::{FlowWorkerManager, FlowWorkerManagerRef, FlownodeOptions};
+pub use adapter::{
+    CheckpointOptions, FlowConfig, FlowWorkerManager, FlowWorkerManagerRef, FlownodeOptions,
+    TableIdNameCache, TableIdNameCacheRef,
+};
 pub use error::{Error, Result};
+pub use expr::{ScalarExpr, TypedExpr};
+pub use repr::ColumnType;
 pub use server::{FlownodeBuilder, FlownodeInstance, FlownodeServer, FrontendInvoker};
+pub use transform::{register_scalar_fn_resolver, ScalarFnResolver};
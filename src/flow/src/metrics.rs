@@ -15,8 +15,11 @@
 //! Some of the metrics used in the flow module.
 
 use lazy_static::lazy_static;
+use prometheus::core::Collector;
 use prometheus::*;
 
+use crate::adapter::FlowId;
+
 lazy_static! {
     pub static ref METRIC_FLOW_TASK_COUNT: IntGauge =
         register_int_gauge!("greptime_flow_task_count", "flow task count").unwrap();
@@ -30,4 +33,188 @@ lazy_static! {
     .unwrap();
     pub static ref METRIC_FLOW_RUN_INTERVAL_MS: IntGauge =
         register_int_gauge!("greptime_flow_run_interval_ms", "flow run interval in ms").unwrap();
+    pub static ref METRIC_FLOW_INPUT_ROWS: IntCounterVec = register_int_counter_vec!(
+        "greptime_flow_input_rows",
+        "flow input rows",
+        &["flow_id"]
+    )
+    .unwrap();
+    pub static ref METRIC_FLOW_OUTPUT_ROWS: IntCounterVec = register_int_counter_vec!(
+        "greptime_flow_output_rows",
+        "flow output rows",
+        &["flow_id"]
+    )
+    .unwrap();
+    pub static ref METRIC_FLOW_PROCESSING_TIME: HistogramVec = register_histogram_vec!(
+        "greptime_flow_processing_time",
+        "flow processing time of one run",
+        &["flow_id"]
+    )
+    .unwrap();
+    /// how far behind the current time of a run the flow finished computing, in ms
+    pub static ref METRIC_FLOW_PROCESSING_LAG_MS: IntGaugeVec = register_int_gauge_vec!(
+        "greptime_flow_processing_lag_ms",
+        "flow processing lag behind current time in ms",
+        &["flow_id"]
+    )
+    .unwrap();
+    /// the timestamp in ms a flow has computed up to
+    pub static ref METRIC_FLOW_WATERMARK_MS: IntGaugeVec = register_int_gauge_vec!(
+        "greptime_flow_watermark_ms",
+        "flow watermark in ms",
+        &["flow_id"]
+    )
+    .unwrap();
+    pub static ref METRIC_FLOW_STATE_ROWS: IntGaugeVec = register_int_gauge_vec!(
+        "greptime_flow_state_rows",
+        "flow state rows",
+        &["flow_id"]
+    )
+    .unwrap();
+    pub static ref METRIC_FLOW_STATE_BYTES: IntGaugeVec = register_int_gauge_vec!(
+        "greptime_flow_state_bytes",
+        "flow estimated state size in bytes",
+        &["flow_id"]
+    )
+    .unwrap();
+    /// keys evicted from a flow's state exceeding its max state size, results of them are wrong since then
+    pub static ref METRIC_FLOW_STATE_EVICTED_KEYS: IntCounterVec = register_int_counter_vec!(
+        "greptime_flow_state_evicted_keys",
+        "flow state evicted keys",
+        &["flow_id"]
+    )
+    .unwrap();
+    /// rows arrived after their keys are expired and ignored by a flow
+    pub static ref METRIC_FLOW_LATE_ROWS: IntCounterVec = register_int_counter_vec!(
+        "greptime_flow_late_rows",
+        "flow late rows",
+        &["flow_id"]
+    )
+    .unwrap();
+    /// late rows of a flow by the first column of their key, with a bounded number of key prefixes
+    pub static ref METRIC_FLOW_LATE_ROWS_BY_KEY: IntCounterVec = register_int_counter_vec!(
+        "greptime_flow_late_rows_by_key",
+        "flow late rows by key prefix",
+        &["flow_id", "key_prefix"]
+    )
+    .unwrap();
+    /// the maximum time in ms a row arrived after its key is expired in a flow
+    pub static ref METRIC_FLOW_MAX_LATENESS_MS: IntGaugeVec = register_int_gauge_vec!(
+        "greptime_flow_max_lateness_ms",
+        "flow max lateness of late rows in ms",
+        &["flow_id"]
+    )
+    .unwrap();
+    pub static ref METRIC_FLOW_ERRORS: IntCounterVec = register_int_counter_vec!(
+        "greptime_flow_errors",
+        "flow errors",
+        &["flow_id"]
+    )
+    .unwrap();
+}
+
+/// Remove metrics of a removed flow, so they are no longer exported
+pub fn remove_flow_metrics(flow_id: FlowId) {
+    let label = flow_id.to_string();
+    let labels = [label.as_str()];
+    let _ = METRIC_FLOW_INPUT_ROWS.remove_label_values(&labels);
+    let _ = METRIC_FLOW_OUTPUT_ROWS.remove_label_values(&labels);
+    let _ = METRIC_FLOW_PROCESSING_TIME.remove_label_values(&labels);
+    let _ = METRIC_FLOW_PROCESSING_LAG_MS.remove_label_values(&labels);
+    let _ = METRIC_FLOW_WATERMARK_MS.remove_label_values(&labels);
+    let _ = METRIC_FLOW_STATE_ROWS.remove_label_values(&labels);
+    let _ = METRIC_FLOW_STATE_BYTES.remove_label_values(&labels);
+    let _ = METRIC_FLOW_STATE_EVICTED_KEYS.remove_label_values(&labels);
+    let _ = METRIC_FLOW_LATE_ROWS.remove_label_values(&labels);
+    let _ = METRIC_FLOW_MAX_LATENESS_MS.remove_label_values(&labels);
+    let _ = METRIC_FLOW_ERRORS.remove_label_values(&labels);
+    // the late rows of the flow by each key prefix
+    let key_prefixes = METRIC_FLOW_LATE_ROWS_BY_KEY
+        .collect()
+        .iter()
+        .flat_map(|family| family.get_metric())
+        .filter_map(|m| {
+            let label_of = |name: &str| {
+                m.get_label()
+                    .iter()
+                    .find(|l| l.get_name() == name)
+                    .map(|l| l.get_value().to_string())
+            };
+            (label_of("flow_id")? == label).then(|| label_of("key_prefix"))?
+        })
+        .collect::<Vec<_>>();
+    for key_prefix in key_prefixes {
+        let _ = METRIC_FLOW_LATE_ROWS_BY_KEY.remove_label_values(&[label.as_str(), &key_prefix]);
+    }
+}
+
+/// Get the value of the given flow's series in a metric vector of gauges or counters,
+/// return `None` if the flow has no series
+///
+/// Unlike `get_metric_with_label_values`, this doesn't create the series if it doesn't exist
+pub fn flow_metric_value(metric: &impl Collector, flow_id: FlowId) -> Option<f64> {
+    let label = flow_id.to_string();
+    metric
+        .collect()
+        .iter()
+        .flat_map(|family| family.get_metric())
+        .find(|m| {
+            m.get_label()
+                .iter()
+                .any(|l| l.get_name() == "flow_id" && l.get_value() == label)
+        })
+        .map(|m| {
+            if m.has_gauge() {
+                m.get_gauge().get_value()
+            } else {
+                m.get_counter().get_value()
+            }
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_flow_metric_value() {
+        let flow_id = 1552;
+        assert_eq!(flow_metric_value(&*METRIC_FLOW_STATE_ROWS, flow_id), None);
+        // looking up doesn't create the series
+        assert_eq!(flow_metric_value(&*METRIC_FLOW_STATE_ROWS, flow_id), None);
+
+        METRIC_FLOW_STATE_ROWS
+            .with_label_values(&[&flow_id.to_string()])
+            .set(42);
+        METRIC_FLOW_INPUT_ROWS
+            .with_label_values(&[&flow_id.to_string()])
+            .inc_by(3);
+        assert_eq!(
+            flow_metric_value(&*METRIC_FLOW_STATE_ROWS, flow_id),
+            Some(42.0)
+        );
+        assert_eq!(
+            flow_metric_value(&*METRIC_FLOW_INPUT_ROWS, flow_id),
+            Some(3.0)
+        );
+
+        METRIC_FLOW_LATE_ROWS_BY_KEY
+            .with_label_values(&[&flow_id.to_string(), "host1"])
+            .inc();
+        METRIC_FLOW_LATE_ROWS_BY_KEY
+            .with_label_values(&["1553", "host1"])
+            .inc();
+
+        remove_flow_metrics(flow_id);
+        assert_eq!(flow_metric_value(&*METRIC_FLOW_STATE_ROWS, flow_id), None);
+        assert_eq!(
+            flow_metric_value(&*METRIC_FLOW_LATE_ROWS_BY_KEY, flow_id),
+            None
+        );
+        // series of other flows are kept
+        assert_eq!(
+            flow_metric_value(&*METRIC_FLOW_LATE_ROWS_BY_KEY, 1553),
+            Some(1.0)
+        );
+    }
 }
@@ -15,19 +15,31 @@
 //! This module contain basic definition for dataflow's plan
 //! that can be translate to hydro dataflow
 
+mod delta_join;
+mod explain;
+mod fingerprint;
 mod join;
+mod monotonic;
+mod optimize;
 mod reduce;
+mod top_k;
+mod validate;
+mod window;
 
 use std::collections::BTreeSet;
 
 use crate::error::Error;
 use crate::expr::{GlobalId, Id, LocalId, MapFilterProject, SafeMfpPlan, TypedExpr};
-use crate::plan::join::JoinPlan;
+pub(crate) use crate::plan::join::{
+    DeltaJoinPlan, JoinFilter, JoinKind, JoinPlan, LinearJoinPlan, LinearStagePlan,
+};
 pub(crate) use crate::plan::reduce::{AccumulablePlan, AggrWithIndex, KeyValPlan, ReducePlan};
+pub(crate) use crate::plan::top_k::ColumnOrder;
+pub(crate) use crate::plan::window::{FrameUnits, WindowExpr, WindowFrame, WindowFunc};
 use crate::repr::{DiffRow, RelationDesc};
 
 /// A plan for a dataflow component. But with type to indicate the output type of the relation.
-#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct TypedPlan {
     /// output type of the relation
     pub schema: RelationDesc,
@@ -94,19 +106,22 @@ impl TypedPlan {
     }
 
     /// Add a new filter to the plan, will filter out the records that do not satisfy the filter
+    ///
+    /// The filter is simplified into conjuncts first, see [`ScalarExpr::into_conjuncts`](crate::expr::ScalarExpr::into_conjuncts)
     pub fn filter(self, filter: TypedExpr) -> Result<Self, Error> {
         let typ = self.schema.clone();
+        let predicates = filter.expr.into_conjuncts();
         let plan = match self.plan {
             Plan::Mfp {
                 input,
                 mfp: old_mfp,
             } => Plan::Mfp {
                 input,
-                mfp: old_mfp.filter(vec![filter.expr])?,
+                mfp: old_mfp.filter(predicates)?,
             },
             _ => Plan::Mfp {
                 input: Box::new(self),
-                mfp: MapFilterProject::new(typ.typ.column_types.len()).filter(vec![filter.expr])?,
+                mfp: MapFilterProject::new(typ.typ.column_types.len()).filter(predicates)?,
             },
         };
         Ok(TypedPlan { schema: typ, plan })
@@ -117,7 +132,7 @@ impl TypedPlan {
 /// Plan describe how to transform data in dataflow
 ///
 /// This can be considered as a physical plan in dataflow, which describe how to transform data in a streaming manner.
-#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum Plan {
     /// A constant collection of rows.
     Constant { rows: Vec<DiffRow> },
@@ -184,6 +199,38 @@ pub enum Plan {
         /// Whether to consolidate the output, e.g., cancel negated records.
         consolidate_output: bool,
     },
+    /// Keep only the first `limit` rows of each group(grouped by `per_key`) ordered by `order_by`
+    ///
+    /// The output is updated incrementally, i.e. when a row enter or leave the top-k of a group
+    /// because of insertion or retraction, the corresponding update is emitted
+    TopK {
+        /// The input collection.
+        input: Box<TypedPlan>,
+        /// The columns to sort by, in order of priority
+        order_by: Vec<ColumnOrder>,
+        /// Number of rows to keep in each group
+        limit: usize,
+        /// The columns to group by, empty means a single global group
+        per_key: Vec<usize>,
+        /// Whether the input never retracts a row, in which case only the top-k rows of each group
+        /// are kept, since a row out of the top-k can never be brought back
+        append_only: bool,
+    },
+    /// Evaluate window functions over the rows of each partition sorted by `order_by`,
+    /// and append their results to the input row
+    ///
+    /// When rows of a partition change, the window values of the whole partition are recomputed,
+    /// and only the rows whose output changed are emitted as updates
+    Window {
+        /// The input collection.
+        input: Box<TypedPlan>,
+        /// The columns to partition by, empty means a single global partition
+        partition_by: Vec<usize>,
+        /// The columns to sort rows in a partition by, in order of priority
+        order_by: Vec<ColumnOrder>,
+        /// The window functions to evaluate, each output one column after the input columns
+        exprs: Vec<WindowExpr>,
+    },
 }
 
 impl Plan {
@@ -219,6 +266,12 @@ impl Plan {
                         recur_find_use(&input.plan, used);
                     }
                 }
+                Plan::TopK { input, .. } => {
+                    recur_find_use(&input.plan, used);
+                }
+                Plan::Window { input, .. } => {
+                    recur_find_use(&input.plan, used);
+                }
                 _ => {}
             }
         }
@@ -0,0 +1,474 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Planning multi-way inner joins as delta joins, see [`DeltaJoinPlan`]
+//!
+//! A `JoinRel` is always planned as a binary join, so a join of three or more inputs is a tree
+//! of binary joins, each keeping its intermediate results as state. Nested binary inner joins
+//! are merged into one multi-way join and planned as a delta join instead, which only keeps the
+//! indexes of its inputs.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::error::Error;
+use crate::expr::{BinaryFunc, MapFilterProject, ScalarExpr};
+use crate::plan::{
+    DeltaJoinPlan, JoinFilter, JoinKind, JoinPlan, LinearJoinPlan, LinearStagePlan, Plan, TypedPlan,
+};
+
+/// Plan the join as a delta join if it's a binary inner join whose inputs contain other binary
+/// inner joins, which together join three or more inputs, otherwise return it as is
+pub(super) fn plan_delta_join(
+    inputs: Vec<TypedPlan>,
+    plan: JoinPlan,
+) -> Result<(Vec<TypedPlan>, JoinPlan), Error> {
+    if !is_binary_inner_join(&inputs, &plan) || flat_input_count(&inputs) < 3 {
+        return Ok((inputs, plan));
+    }
+    let JoinPlan::Linear(LinearJoinPlan {
+        mut stage_plans, ..
+    }) = plan
+    else {
+        unreachable!("checked by `is_binary_inner_join`")
+    };
+    let multi_join = MultiJoin::from_binary_join(inputs, stage_plans.remove(0))?;
+    multi_join.into_delta_join()
+}
+
+/// Whether the join is a binary inner join which only filters the concatenated rows of its two
+/// inputs, so it can be merged with other such joins
+fn is_binary_inner_join(inputs: &[TypedPlan], plan: &JoinPlan) -> bool {
+    let JoinPlan::Linear(plan) = plan else {
+        return false;
+    };
+    let ([stage], [left, _]) = (plan.stage_plans.as_slice(), inputs) else {
+        return false;
+    };
+    let left_arity = left.schema.typ().column_types.len();
+    let closure = &stage.closure.before.mfp;
+    plan.source_relation == 0
+        && plan.initial_closure.is_none()
+        && plan.final_closure.is_none()
+        && stage.lookup_relation == 1
+        && stage.kind == JoinKind::Inner
+        && stage.stream_thinning == (0..left_arity).collect::<Vec<_>>()
+        && stage.closure.ready_equivalences.is_empty()
+        && closure.expressions.is_empty()
+        && closure.projection == (0..closure.input_arity).collect::<Vec<_>>()
+}
+
+/// Number of inputs after merging all nested binary inner joins
+fn flat_input_count(inputs: &[TypedPlan]) -> usize {
+    inputs
+        .iter()
+        .map(|input| match &input.plan {
+            Plan::Join { inputs, plan } if is_binary_inner_join(inputs, plan) => {
+                flat_input_count(inputs)
+            }
+            _ => 1,
+        })
+        .sum()
+}
+
+/// A multi-way inner join, whose expressions are over the concatenated columns of all inputs
+struct MultiJoin {
+    inputs: Vec<TypedPlan>,
+    /// pairs of expressions which must be equal, usable as join keys
+    equivalences: Vec<(ScalarExpr, ScalarExpr)>,
+    /// other predicates the joined rows must satisfy
+    predicates: Vec<ScalarExpr>,
+}
+
+impl MultiJoin {
+    /// Flatten an input of a binary inner join, merging it if it's also a binary inner join
+    fn from_input(input: TypedPlan) -> Result<Self, Error> {
+        match input.plan {
+            Plan::Join { inputs, plan } if is_binary_inner_join(&inputs, &plan) => {
+                let JoinPlan::Linear(LinearJoinPlan {
+                    mut stage_plans, ..
+                }) = plan
+                else {
+                    unreachable!("checked by `is_binary_inner_join`")
+                };
+                Self::from_binary_join(inputs, stage_plans.remove(0))
+            }
+            plan => Ok(Self {
+                inputs: vec![plan.with_types(input.schema)],
+                equivalences: vec![],
+                predicates: vec![],
+            }),
+        }
+    }
+
+    fn from_binary_join(inputs: Vec<TypedPlan>, stage: LinearStagePlan) -> Result<Self, Error> {
+        let mut inputs = inputs.into_iter();
+        let (Some(left), Some(right)) = (inputs.next(), inputs.next()) else {
+            unreachable!("checked by `is_binary_inner_join`")
+        };
+        let mut join = Self::from_input(left)?;
+        let offset = join.arity();
+        let right = Self::from_input(right)?;
+
+        join.inputs.extend(right.inputs);
+        for (mut l, mut r) in right.equivalences {
+            offset_columns(&mut l, offset)?;
+            offset_columns(&mut r, offset)?;
+            join.equivalences.push((l, r));
+        }
+        for mut predicate in right.predicates {
+            offset_columns(&mut predicate, offset)?;
+            join.predicates.push(predicate);
+        }
+        for (l, mut r) in stage.stream_key.into_iter().zip(stage.lookup_key) {
+            offset_columns(&mut r, offset)?;
+            join.equivalences.push((l, r));
+        }
+        join.predicates.extend(
+            stage
+                .closure
+                .before
+                .mfp
+                .predicates
+                .into_iter()
+                .map(|(_, predicate)| predicate),
+        );
+        Ok(join)
+    }
+
+    fn arity(&self) -> usize {
+        self.inputs
+            .iter()
+            .map(|input| input.schema.typ().column_types.len())
+            .sum()
+    }
+
+    /// Plan one path per input, each looks up other inputs in the order of how many join keys
+    /// they have with inputs already joined, so inputs without any join key are looked up last
+    ///
+    /// Predicates and equivalences not used as join keys are applied as soon as all inputs they
+    /// refer to are joined
+    fn into_delta_join(self) -> Result<(Vec<TypedPlan>, JoinPlan), Error> {
+        let arities = self
+            .inputs
+            .iter()
+            .map(|input| input.schema.typ().column_types.len())
+            .collect::<Vec<_>>();
+        let offsets = arities
+            .iter()
+            .scan(0, |offset, arity| {
+                let cur = *offset;
+                *offset += arity;
+                Some(cur)
+            })
+            .collect::<Vec<_>>();
+        let input_of = |col: usize| offsets.iter().rposition(|offset| *offset <= col);
+        let inputs_of = |expr: &ScalarExpr| {
+            expr.get_all_ref_columns()
+                .into_iter()
+                .filter_map(input_of)
+                .collect::<BTreeSet<_>>()
+        };
+        let equivalences = self
+            .equivalences
+            .iter()
+            .map(|(l, r)| (inputs_of(l), inputs_of(r)))
+            .collect::<Vec<_>>();
+        let predicates = self.predicates.iter().map(inputs_of).collect::<Vec<_>>();
+
+        let mut path_plans = Vec::with_capacity(self.inputs.len());
+        for source in 0..self.inputs.len() {
+            let mut joined = BTreeSet::from([source]);
+            // the global column of each column of the joined rows
+            let mut layout =
+                (offsets[source]..offsets[source] + arities[source]).collect::<Vec<_>>();
+            let mut used_equivalences = vec![false; equivalences.len()];
+            let mut used_predicates = vec![false; predicates.len()];
+            let mut take_ready_predicates = |joined: &BTreeSet<usize>,
+                                             used_equivalences: &mut [bool]|
+             -> Vec<ScalarExpr> {
+                let mut ready = vec![];
+                for (i, (l, r)) in equivalences.iter().enumerate() {
+                    if !used_equivalences[i] && l.union(r).all(|input| joined.contains(input)) {
+                        used_equivalences[i] = true;
+                        let (l, r) = self.equivalences[i].clone();
+                        ready.push(l.call_binary(r, BinaryFunc::Eq));
+                    }
+                }
+                for (i, inputs) in predicates.iter().enumerate() {
+                    if !used_predicates[i] && inputs.iter().all(|input| joined.contains(input)) {
+                        used_predicates[i] = true;
+                        ready.push(self.predicates[i].clone());
+                    }
+                }
+                ready
+            };
+
+            let ready = take_ready_predicates(&joined, &mut used_equivalences);
+            let initial_closure = if ready.is_empty() {
+                None
+            } else {
+                Some(join_filter(ready, &layout)?)
+            };
+
+            let mut stage_plans = vec![];
+            while joined.len() < self.inputs.len() {
+                // keys between joined inputs and each input not joined yet
+                let mut keys: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+                for (i, (l, r)) in equivalences.iter().enumerate() {
+                    if used_equivalences[i] {
+                        continue;
+                    }
+                    let is_joined = |inputs: &BTreeSet<usize>| {
+                        !inputs.is_empty() && inputs.iter().all(|input| joined.contains(input))
+                    };
+                    let single = |inputs: &BTreeSet<usize>| {
+                        (inputs.len() == 1)
+                            .then(|| *inputs.first().unwrap())
+                            .filter(|input| !joined.contains(input))
+                    };
+                    if let Some(lookup) = single(r).filter(|_| is_joined(l)) {
+                        keys.entry(lookup).or_default().push(i);
+                    } else if let Some(lookup) = single(l).filter(|_| is_joined(r)) {
+                        keys.entry(lookup).or_default().push(i);
+                    }
+                }
+                let lookup = keys
+                    .iter()
+                    .max_by_key(|(lookup, keys)| (keys.len(), std::cmp::Reverse(**lookup)))
+                    .map(|(lookup, _)| *lookup)
+                    .or_else(|| (0..self.inputs.len()).find(|input| !joined.contains(input)))
+                    .unwrap();
+
+                let stream_cols = global_to_local(&layout);
+                let lookup_layout =
+                    (offsets[lookup]..offsets[lookup] + arities[lookup]).collect::<Vec<_>>();
+                let lookup_cols = global_to_local(&lookup_layout);
+                let mut stream_key = vec![];
+                let mut lookup_key = vec![];
+                for i in keys.remove(&lookup).unwrap_or_default() {
+                    used_equivalences[i] = true;
+                    let (mut l, mut r) = self.equivalences[i].clone();
+                    if !equivalences[i].1.contains(&lookup) {
+                        std::mem::swap(&mut l, &mut r);
+                    }
+                    l.permute_map(&stream_cols)?;
+                    r.permute_map(&lookup_cols)?;
+                    stream_key.push(l);
+                    lookup_key.push(r);
+                }
+
+                let stream_thinning = (0..layout.len()).collect();
+                layout.extend(lookup_layout);
+                joined.insert(lookup);
+                let ready = take_ready_predicates(&joined, &mut used_equivalences);
+                stage_plans.push(LinearStagePlan {
+                    lookup_relation: lookup,
+                    stream_key,
+                    stream_thinning,
+                    lookup_key,
+                    lookup_arity: arities[lookup],
+                    closure: join_filter(ready, &layout)?,
+                    kind: JoinKind::Inner,
+                });
+            }
+
+            // all paths output columns in the order of inputs
+            let final_closure = if layout.iter().enumerate().all(|(i, col)| i == *col) {
+                None
+            } else {
+                let cols = global_to_local(&layout);
+                Some(JoinFilter {
+                    ready_equivalences: vec![],
+                    before: MapFilterProject::new(layout.len())
+                        .project((0..layout.len()).map(|col| cols[&col]).collect::<Vec<_>>())?
+                        .into_safe(),
+                })
+            };
+            path_plans.push(LinearJoinPlan {
+                source_relation: source,
+                source_key: None,
+                initial_closure,
+                stage_plans,
+                final_closure,
+            });
+        }
+        Ok((self.inputs, JoinPlan::Delta(DeltaJoinPlan { path_plans })))
+    }
+}
+
+/// Map each global column to its position in `layout`
+fn global_to_local(layout: &[usize]) -> BTreeMap<usize, usize> {
+    layout
+        .iter()
+        .enumerate()
+        .map(|(local, global)| (*global, local))
+        .collect()
+}
+
+/// Filter rows whose columns are the global columns in `layout` by `predicates` over global columns
+fn join_filter(mut predicates: Vec<ScalarExpr>, layout: &[usize]) -> Result<JoinFilter, Error> {
+    let cols = global_to_local(layout);
+    for predicate in predicates.iter_mut() {
+        predicate.permute_map(&cols)?;
+    }
+    Ok(JoinFilter {
+        ready_equivalences: vec![],
+        before: MapFilterProject::new(layout.len())
+            .filter(predicates)?
+            .into_safe(),
+    })
+}
+
+fn offset_columns(expr: &mut ScalarExpr, offset: usize) -> Result<(), Error> {
+    let cols = expr
+        .get_all_ref_columns()
+        .into_iter()
+        .map(|col| (col, col + offset))
+        .collect();
+    expr.permute_map(&cols)
+}
+
+#[cfg(test)]
+mod test {
+    use datatypes::data_type::ConcreteDataType as CDT;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::expr::{GlobalId, Id};
+    use crate::repr::{ColumnType, RelationType};
+
+    fn source(id: u64, arity: usize) -> TypedPlan {
+        Plan::Get {
+            id: Id::Global(GlobalId::User(id)),
+        }
+        .with_types(
+            RelationType::new(vec![ColumnType::new(CDT::int64_datatype(), true); arity])
+                .into_unnamed(),
+        )
+    }
+
+    /// An inner join of `left` and `right` on `left.l = right.r`, planned like a `JoinRel`
+    fn inner_join(left: TypedPlan, right: TypedPlan, l: usize, r: usize) -> TypedPlan {
+        let left_arity = left.schema.typ().column_types.len();
+        let right_arity = right.schema.typ().column_types.len();
+        let columns = left
+            .schema
+            .typ()
+            .column_types
+            .iter()
+            .chain(right.schema.typ().column_types.iter())
+            .cloned()
+            .collect();
+        let plan = LinearJoinPlan {
+            source_relation: 0,
+            source_key: None,
+            initial_closure: None,
+            stage_plans: vec![LinearStagePlan {
+                lookup_relation: 1,
+                stream_key: vec![ScalarExpr::Column(l)],
+                stream_thinning: (0..left_arity).collect(),
+                lookup_key: vec![ScalarExpr::Column(r)],
+                lookup_arity: right_arity,
+                closure: JoinFilter {
+                    ready_equivalences: vec![],
+                    before: MapFilterProject::new(left_arity + right_arity).into_safe(),
+                },
+                kind: JoinKind::Inner,
+            }],
+            final_closure: None,
+        };
+        Plan::Join {
+            inputs: vec![left, right],
+            plan: JoinPlan::Linear(plan),
+        }
+        .with_types(RelationType::new(columns).into_unnamed())
+    }
+
+    fn stages(path: &LinearJoinPlan) -> Vec<(usize, Vec<ScalarExpr>, Vec<ScalarExpr>)> {
+        path.stage_plans
+            .iter()
+            .map(|stage| {
+                (
+                    stage.lookup_relation,
+                    stage.stream_key.clone(),
+                    stage.lookup_key.clone(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_plan_delta_join() {
+        // a(id, name) JOIN b(id, cid) ON a.id = b.id JOIN c(cid, value) ON b.cid = c.cid
+        let plan = inner_join(
+            inner_join(source(1, 2), source(2, 2), 0, 0),
+            source(3, 2),
+            3,
+            0,
+        );
+        let optimized = plan.clone().optimize().unwrap();
+        optimized.validate().unwrap();
+        assert_eq!(optimized.schema, plan.schema);
+        let Plan::Join {
+            inputs,
+            plan: JoinPlan::Delta(delta),
+        } = optimized.plan
+        else {
+            panic!("Expect a delta join, found {:?}", optimized.plan);
+        };
+        assert_eq!(inputs, vec![source(1, 2), source(2, 2), source(3, 2)]);
+
+        let col = ScalarExpr::Column;
+        let [a, b, c] = delta.path_plans.as_slice() else {
+            panic!("Expect three paths, found {:?}", delta.path_plans);
+        };
+        // a -> b -> c, already in the order of inputs
+        assert_eq!(
+            stages(a),
+            vec![
+                (1, vec![col(0)], vec![col(0)]),
+                (2, vec![col(3)], vec![col(0)])
+            ]
+        );
+        assert!(a.final_closure.is_none());
+        // b -> a -> c, then reordered as a, b, c
+        assert_eq!(
+            stages(b),
+            vec![
+                (0, vec![col(0)], vec![col(0)]),
+                (2, vec![col(1)], vec![col(0)])
+            ]
+        );
+        assert_eq!(
+            b.final_closure.as_ref().unwrap().before.mfp.projection,
+            vec![2, 3, 0, 1, 4, 5]
+        );
+        // c -> b -> a, then reordered as a, b, c
+        assert_eq!(
+            stages(c),
+            vec![
+                (1, vec![col(0)], vec![col(1)]),
+                (0, vec![col(2)], vec![col(0)])
+            ]
+        );
+        assert_eq!(
+            c.final_closure.as_ref().unwrap().before.mfp.projection,
+            vec![4, 5, 2, 3, 0, 1]
+        );
+
+        // a single binary join is kept as is
+        let plan = inner_join(source(1, 2), source(2, 2), 0, 0);
+        assert_eq!(plan.clone().optimize().unwrap(), plan);
+    }
+}
@@ -0,0 +1,419 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Human-readable explanation of [`TypedPlan`], one operator per line and indented by depth
+
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter, Result as FmtResult, Write};
+
+use itertools::Itertools;
+
+use crate::adapter::node_context::FlownodeContext;
+use crate::expr::Id;
+use crate::plan::{ColumnOrder, JoinPlan, LinearJoinPlan, Plan, ReducePlan, TypedPlan, WindowExpr};
+
+impl TypedPlan {
+    /// Explain the plan like its [`Display`] does, but with source tables shown by their names
+    /// registered in `ctx`
+    pub fn explain(&self, ctx: &FlownodeContext) -> String {
+        Explain {
+            plan: self,
+            ctx: Some(ctx),
+        }
+        .to_string()
+    }
+
+    /// Describe the operators keeping state across ticks, one per line with the keys their
+    /// state is indexed by, in the same order as [`TypedPlan::explain`]
+    pub fn explain_state(&self) -> String {
+        let mut output = String::new();
+        fmt_state(self, &mut output);
+        output
+    }
+}
+
+impl Display for TypedPlan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        Explain {
+            plan: self,
+            ctx: None,
+        }
+        .fmt(f)
+    }
+}
+
+/// A plan to be explained, with an optional context to resolve names of source tables
+struct Explain<'a> {
+    plan: &'a TypedPlan,
+    ctx: Option<&'a FlownodeContext>,
+}
+
+impl Display for Explain<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        self.fmt_plan(self.plan, 0, f)
+    }
+}
+
+impl Explain<'_> {
+    /// Write one line for `plan` at given depth, then its inputs one level deeper
+    fn fmt_plan(&self, plan: &TypedPlan, depth: usize, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}", "  ".repeat(depth))?;
+        let inputs: Vec<&TypedPlan> = match &plan.plan {
+            Plan::Constant { rows } => {
+                writeln!(f, "Constant: rows={}", rows.len())?;
+                vec![]
+            }
+            Plan::Get { id } => {
+                writeln!(f, "Get: {}", self.source_name(id))?;
+                vec![]
+            }
+            Plan::Let { id, value, body } => {
+                writeln!(f, "Let: {:?}", Id::Local(*id))?;
+                vec![value.as_ref(), body.as_ref()]
+            }
+            Plan::Mfp { input, mfp } => {
+                writeln!(f, "Mfp: {mfp}")?;
+                vec![input.as_ref()]
+            }
+            Plan::Reduce {
+                input,
+                key_val_plan,
+                reduce_plan,
+            } => {
+                writeln!(
+                    f,
+                    "Reduce: key=({}), val=({}), aggrs={reduce_plan}",
+                    key_val_plan.key_plan.mfp, key_val_plan.val_plan.mfp
+                )?;
+                vec![input.as_ref()]
+            }
+            Plan::Join {
+                inputs,
+                plan: JoinPlan::Linear(plan),
+            } => {
+                writeln!(f, "Join: {}", fmt_join_path(plan))?;
+                inputs.iter().collect()
+            }
+            Plan::Join {
+                inputs,
+                plan: JoinPlan::Delta(plan),
+            } => {
+                writeln!(
+                    f,
+                    "DeltaJoin: {}",
+                    plan.path_plans
+                        .iter()
+                        .map(|path| format!("[{}]", fmt_join_path(path)))
+                        .join(", ")
+                )?;
+                inputs.iter().collect()
+            }
+            Plan::Union {
+                inputs,
+                consolidate_output,
+            } => {
+                writeln!(f, "Union: consolidate_output={consolidate_output}")?;
+                inputs.iter().collect()
+            }
+            Plan::TopK {
+                input,
+                order_by,
+                limit,
+                per_key,
+                append_only,
+            } => {
+                writeln!(
+                    f,
+                    "TopK: limit={limit}, order_by=[{}], per_key=[{}], append_only={append_only}",
+                    order_by.iter().map(fmt_order).join(", "),
+                    per_key.iter().map(|c| format!("#{c}")).join(", ")
+                )?;
+                vec![input.as_ref()]
+            }
+            Plan::Window {
+                input,
+                partition_by,
+                order_by,
+                exprs,
+            } => {
+                writeln!(
+                    f,
+                    "Window: partition_by=[{}], order_by=[{}], exprs=[{}]",
+                    partition_by.iter().map(|c| format!("#{c}")).join(", "),
+                    order_by.iter().map(fmt_order).join(", "),
+                    exprs.iter().map(fmt_window_expr).join(", ")
+                )?;
+                vec![input.as_ref()]
+            }
+        };
+        for input in inputs {
+            self.fmt_plan(input, depth + 1, f)?;
+        }
+        Ok(())
+    }
+
+    /// Name of the source, i.e. `greptime.public.numbers(User(0))` if the name is known
+    fn source_name(&self, id: &Id) -> String {
+        let Id::Global(global_id) = id else {
+            return format!("{id:?}");
+        };
+        self.ctx
+            .and_then(|ctx| ctx.table_repr.get_by_global_id(global_id))
+            .and_then(|(name, _)| name)
+            .map(|name| format!("{}({global_id:?})", name.join(".")))
+            .unwrap_or_else(|| format!("{global_id:?}"))
+    }
+}
+
+/// Write one line for each stateful operator of `plan` and its inputs
+fn fmt_state(plan: &TypedPlan, output: &mut String) {
+    let inputs: Vec<&TypedPlan> = match &plan.plan {
+        Plan::Constant { .. } | Plan::Get { .. } => vec![],
+        Plan::Let { value, body, .. } => vec![value.as_ref(), body.as_ref()],
+        Plan::Mfp { input, .. } => vec![input.as_ref()],
+        Plan::Reduce {
+            input,
+            key_val_plan,
+            reduce_plan,
+        } => {
+            let kept = match reduce_plan {
+                ReducePlan::Distinct => "the key".to_string(),
+                ReducePlan::Accumulable(plan) if plan.distinct_aggrs.is_empty() => {
+                    format!("accumulators of {} aggregations", plan.full_aggrs.len())
+                }
+                ReducePlan::Accumulable(plan) => format!(
+                    "accumulators of {} aggregations and distinct values of {}",
+                    plan.full_aggrs.len(),
+                    plan.distinct_aggrs.len()
+                ),
+            };
+            let _ = writeln!(
+                output,
+                "Reduce: key=({}), keeps {kept} per key",
+                key_val_plan.key_plan.mfp
+            );
+            vec![input.as_ref()]
+        }
+        Plan::Join {
+            inputs,
+            plan: JoinPlan::Linear(plan),
+        } => {
+            for stage in &plan.stage_plans {
+                let _ = writeln!(
+                    output,
+                    "Join: arranges #{} by [{}] and the stream by [{}]",
+                    stage.lookup_relation,
+                    stage.lookup_key.iter().join(", "),
+                    stage.stream_key.iter().join(", ")
+                );
+            }
+            inputs.iter().collect()
+        }
+        Plan::Join {
+            inputs,
+            plan: JoinPlan::Delta(plan),
+        } => {
+            // paths looking up the same input by the same key share its arrangement
+            let lookups: BTreeSet<_> = plan
+                .path_plans
+                .iter()
+                .flat_map(|path| &path.stage_plans)
+                .map(|stage| {
+                    format!(
+                        "#{} by [{}]",
+                        stage.lookup_relation,
+                        stage.lookup_key.iter().join(", ")
+                    )
+                })
+                .collect();
+            let _ = writeln!(output, "DeltaJoin: arranges {}", lookups.iter().join(", "));
+            inputs.iter().collect()
+        }
+        Plan::Union { inputs, .. } => inputs.iter().collect(),
+        Plan::TopK {
+            input,
+            limit,
+            per_key,
+            append_only,
+            ..
+        } => {
+            let kept = if *append_only {
+                format!("top {limit} rows")
+            } else {
+                "all rows".to_string()
+            };
+            let _ = writeln!(
+                output,
+                "TopK: key=[{}], keeps {kept} per key",
+                per_key.iter().map(|c| format!("#{c}")).join(", ")
+            );
+            vec![input.as_ref()]
+        }
+        Plan::Window {
+            input,
+            partition_by,
+            ..
+        } => {
+            let _ = writeln!(
+                output,
+                "Window: key=[{}], keeps all rows per key",
+                partition_by.iter().map(|c| format!("#{c}")).join(", ")
+            );
+            vec![input.as_ref()]
+        }
+    };
+    for input in inputs {
+        fmt_state(input, output);
+    }
+}
+
+/// Format the stages of a linear join like `source=#0, Inner #1 on [#0] = [#0] with (..)`
+fn fmt_join_path(plan: &LinearJoinPlan) -> String {
+    let mut output = format!("source=#{}", plan.source_relation);
+    if let Some(closure) = &plan.initial_closure {
+        let _ = write!(output, " with ({})", closure.before.mfp);
+    }
+    for stage in &plan.stage_plans {
+        let _ = write!(
+            output,
+            ", {:?} #{} on [{}] = [{}]",
+            stage.kind,
+            stage.lookup_relation,
+            stage.stream_key.iter().join(", "),
+            stage.lookup_key.iter().join(", ")
+        );
+        if !stage.closure.before.mfp.is_identity() {
+            let _ = write!(output, " with ({})", stage.closure.before.mfp);
+        }
+    }
+    if let Some(closure) = &plan.final_closure {
+        let _ = write!(output, ", then ({})", closure.before.mfp);
+    }
+    output
+}
+
+/// Format a sort column like `#0 DESC NULLS LAST`
+fn fmt_order(order: &ColumnOrder) -> String {
+    format!(
+        "#{}{}{}",
+        order.column,
+        if order.desc { " DESC" } else { "" },
+        if order.nulls_last {
+            " NULLS LAST"
+        } else {
+            " NULLS FIRST"
+        }
+    )
+}
+
+/// Format a window function like `Sum(#0) Rows(2 PRECEDING, 0 FOLLOWING)`
+fn fmt_window_expr(expr: &WindowExpr) -> String {
+    let bound = |b: Option<i64>| {
+        b.map(|b| b.to_string())
+            .unwrap_or_else(|| "UNBOUNDED".to_string())
+    };
+    format!(
+        "{:?}({}) {:?}({} PRECEDING, {} FOLLOWING)",
+        expr.func,
+        expr.arg.map(|c| format!("#{c}")).unwrap_or_default(),
+        expr.frame.units,
+        bound(expr.frame.preceding),
+        bound(expr.frame.following)
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use datatypes::data_type::ConcreteDataType as CDT;
+    use datatypes::value::Value;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::expr::{
+        AggregateExpr, AggregateFunc, BinaryFunc, GlobalId, MapFilterProject, ScalarExpr,
+    };
+    use crate::plan::{AccumulablePlan, AggrWithIndex, KeyValPlan, ReducePlan};
+    use crate::repr::{ColumnType, RelationType};
+
+    #[test]
+    fn test_explain() {
+        let source = Plan::Get {
+            id: Id::Global(GlobalId::User(0)),
+        }
+        .with_types(
+            RelationType::new(vec![ColumnType::new(CDT::uint32_datatype(), false)]).into_unnamed(),
+        );
+        let aggr = AggregateExpr {
+            func: AggregateFunc::SumUInt32,
+            expr: ScalarExpr::Column(0),
+            distinct: false,
+        };
+        let reduce = Plan::Reduce {
+            input: Box::new(source),
+            key_val_plan: KeyValPlan {
+                key_plan: MapFilterProject::new(1)
+                    .project(vec![])
+                    .unwrap()
+                    .into_safe(),
+                val_plan: MapFilterProject::new(1).into_safe(),
+            },
+            reduce_plan: ReducePlan::Accumulable(AccumulablePlan {
+                full_aggrs: vec![aggr.clone()],
+                simple_aggrs: vec![AggrWithIndex::new(aggr, 0, 0)],
+                distinct_aggrs: vec![],
+            }),
+        }
+        .with_types(
+            RelationType::new(vec![ColumnType::new(CDT::uint64_datatype(), true)]).into_unnamed(),
+        );
+        let plan = Plan::Mfp {
+            input: Box::new(reduce),
+            mfp: MapFilterProject::new(1)
+                .filter(vec![ScalarExpr::Column(0).call_binary(
+                    ScalarExpr::Literal(Value::from(10u64), CDT::uint64_datatype()),
+                    BinaryFunc::Gt,
+                )])
+                .unwrap(),
+        }
+        .with_types(
+            RelationType::new(vec![ColumnType::new(CDT::uint64_datatype(), true)]).into_unnamed(),
+        );
+
+        let expected = "\
+Mfp: filter=[Gt(#0, 10)], project=[#0]
+  Reduce: key=(project=[]), val=(project=[#0]), aggrs=Accumulable[#0 = SumUInt32(#0)]
+    Get: User(0)
+";
+        assert_eq!(plan.to_string(), expected);
+
+        let mut ctx = FlownodeContext::default();
+        ctx.table_repr.insert(
+            Some([
+                "greptime".to_string(),
+                "public".to_string(),
+                "numbers".to_string(),
+            ]),
+            Some(1024),
+            GlobalId::User(0),
+        );
+        assert_eq!(
+            plan.explain(&ctx),
+            expected.replace("Get: User(0)", "Get: greptime.public.numbers(User(0))")
+        );
+        assert_eq!(
+            plan.explain_state(),
+            "Reduce: key=(project=[]), keeps accumulators of 1 aggregations per key\n"
+        );
+    }
+}
@@ -0,0 +1,172 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Deterministic fingerprint of [`TypedPlan`], to tell whether two plans describe the same dataflow
+
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+
+use table::metadata::TableId;
+
+use crate::adapter::TableName;
+use crate::expr::{GlobalId, Id, LocalId};
+use crate::plan::{Plan, TypedPlan};
+
+impl TypedPlan {
+    /// A fingerprint of the dataflow built from this plan, which read from the tables in `table_ids`
+    /// and write to `sink_table_name`, it's the same for the same dataflow across runs of the same build
+    ///
+    /// The plan is normalized before hashing by renumbering ids of collections in their order of
+    /// first appearance, since global ids of tables are assigned by the order of registration in
+    /// flownode, and may change after restart. The table id each renumbered global id refers to
+    /// and the sink table are hashed as well, so plans of the same shape reading from or writing to
+    /// different tables have different fingerprints
+    pub fn fingerprint(
+        &self,
+        table_ids: &BTreeMap<GlobalId, TableId>,
+        sink_table_name: &TableName,
+    ) -> u64 {
+        let mut normalized = self.clone();
+        let mut ids = BTreeMap::new();
+        normalize_ids(&mut normalized.plan, &mut ids);
+        let mut hasher = Fnv64::default();
+        normalized.hash(&mut hasher);
+
+        let sources: BTreeMap<Id, Option<TableId>> = ids
+            .into_iter()
+            .filter_map(|(id, renumbered)| match id {
+                Id::Global(id) => Some((renumbered, table_ids.get(&id).copied())),
+                Id::Local(_) => None,
+            })
+            .collect();
+        sources.hash(&mut hasher);
+        sink_table_name.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// Renumber ids of collections used in the plan in their order of first appearance
+fn normalize_ids(plan: &mut Plan, ids: &mut BTreeMap<Id, Id>) {
+    let mut renumber = |id: Id| {
+        let next = ids.len() as u64;
+        *ids.entry(id).or_insert(match id {
+            Id::Global(_) => Id::Global(GlobalId::User(next)),
+            Id::Local(_) => Id::Local(LocalId(next)),
+        })
+    };
+    match plan {
+        Plan::Constant { .. } => (),
+        Plan::Get { id } => *id = renumber(*id),
+        Plan::Let { id, value, body } => {
+            if let Id::Local(new) = renumber(Id::Local(*id)) {
+                *id = new;
+            }
+            normalize_ids(&mut value.plan, ids);
+            normalize_ids(&mut body.plan, ids);
+        }
+        Plan::Mfp { input, .. }
+        | Plan::Reduce { input, .. }
+        | Plan::TopK { input, .. }
+        | Plan::Window { input, .. } => normalize_ids(&mut input.plan, ids),
+        Plan::Join { inputs, .. } | Plan::Union { inputs, .. } => {
+            for input in inputs {
+                normalize_ids(&mut input.plan, ids);
+            }
+        }
+    }
+}
+
+/// 64-bit FNV-1a hasher, unlike `DefaultHasher` its algorithm is fixed and not randomly keyed
+struct Fnv64(u64);
+
+impl Default for Fnv64 {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for Fnv64 {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::transform::test::{create_test_ctx, create_test_query_engine, sql_to_substrait};
+
+    #[tokio::test]
+    async fn test_fingerprint() {
+        let engine = create_test_query_engine();
+        let sink = [
+            "greptime".to_string(),
+            "public".to_string(),
+            "sink".to_string(),
+        ];
+        let mut fingerprints = Vec::new();
+        for sql in [
+            "SELECT sum(number) FROM numbers GROUP BY number",
+            "SELECT sum(number) FROM numbers GROUP BY number",
+            "SELECT max(number) FROM numbers GROUP BY number",
+        ] {
+            let plan = sql_to_substrait(engine.clone(), sql).await;
+            let mut ctx = create_test_ctx();
+            let flow_plan = TypedPlan::from_substrait_plan(&mut ctx, &plan)
+                .await
+                .unwrap();
+            fingerprints.push(flow_plan.fingerprint(&BTreeMap::new(), &sink));
+        }
+        assert_eq!(fingerprints[0], fingerprints[1]);
+        assert_ne!(fingerprints[0], fingerprints[2]);
+
+        // same plan reading from the same table with different global id
+        let get = |id: u64| {
+            Plan::Get {
+                id: Id::Global(GlobalId::User(id)),
+            }
+            .with_types(crate::repr::RelationDesc::empty())
+        };
+        let table_ids = BTreeMap::from([(GlobalId::User(0), 1024), (GlobalId::User(42), 1024)]);
+        assert_eq!(
+            get(0).fingerprint(&table_ids, &sink),
+            get(42).fingerprint(&table_ids, &sink)
+        );
+
+        // same plan reading from a different table
+        let table_ids = BTreeMap::from([(GlobalId::User(0), 1024), (GlobalId::User(42), 1025)]);
+        assert_ne!(
+            get(0).fingerprint(&table_ids, &sink),
+            get(42).fingerprint(&table_ids, &sink)
+        );
+
+        // same plan writing to a different table
+        let other_sink = [
+            "greptime".to_string(),
+            "public".to_string(),
+            "other".to_string(),
+        ];
+        assert_ne!(
+            get(0).fingerprint(&table_ids, &sink),
+            get(0).fingerprint(&table_ids, &other_sink)
+        );
+    }
+}
@@ -16,13 +16,50 @@ use crate::expr::ScalarExpr;
 use crate::plan::SafeMfpPlan;
 
 /// TODO(discord9): consider impl more join strategies
-#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum JoinPlan {
     Linear(LinearJoinPlan),
+    Delta(DeltaJoinPlan),
+}
+
+/// The kind of a binary join, decide which side(s) of the join should be padded with nulls
+/// if a row can't find any matching row on the other side
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum JoinKind {
+    /// Only output matched rows
+    #[default]
+    Inner,
+    /// Also output unmatched rows from the stream(left) side, padded with nulls
+    Left,
+    /// Also output unmatched rows from the lookup(right) side, padded with nulls
+    Right,
+    /// Also output unmatched rows from both sides, padded with nulls
+    Full,
+    /// Only output stream rows which have at least one matching row, without any lookup columns
+    Semi,
+    /// Only output stream rows which have no matching row, without any lookup columns
+    Anti,
+}
+
+impl JoinKind {
+    /// Whether unmatched stream rows should be padded with nulls and output
+    pub fn pad_stream(&self) -> bool {
+        matches!(self, Self::Left | Self::Full)
+    }
+
+    /// Whether unmatched lookup rows should be padded with nulls and output
+    pub fn pad_lookup(&self) -> bool {
+        matches!(self, Self::Right | Self::Full)
+    }
+
+    /// Whether each pair of matching rows is output
+    pub fn output_pairs(&self) -> bool {
+        !matches!(self, Self::Semi | Self::Anti)
+    }
 }
 
 /// Determine if a given row should stay in the output. And apply a map filter project before output the row
-#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct JoinFilter {
     /// each element in the outer vector will check if each expr in itself can be eval to same value
     /// if not, the row will be filtered out. Useful for equi-join(join based on equality of some columns)
@@ -35,7 +72,7 @@ pub struct JoinFilter {
 ///
 /// A linear join is a sequence of stages, each of which introduces
 /// a new collection. Each stage is represented by a [LinearStagePlan].
-#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct LinearJoinPlan {
     /// The source relation from which we start the join.
     pub source_relation: usize,
@@ -53,12 +90,27 @@ pub struct LinearJoinPlan {
     pub final_closure: Option<JoinFilter>,
 }
 
+/// A plan for the execution of a multi-way inner join as a delta join.
+///
+/// Instead of a chain of binary joins which keeps the intermediate join results of each stage,
+/// only each input is indexed, by the keys other inputs look it up with. Updates of each input
+/// drive their own path of lookups into the indexes of all other inputs, and the outputs of all
+/// paths together are the updates of the join.
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct DeltaJoinPlan {
+    /// One path per input, `path_plans[i]` starts from updates of input `i` and looks up all
+    /// other inputs by inner join stages, all paths output the same columns
+    ///
+    /// `source_key` of a path is unused, since updates of an input are never looked up
+    pub path_plans: Vec<LinearJoinPlan>,
+}
+
 /// A plan for the execution of one stage of a linear join.
 ///
 /// Each stage is a binary join between the current accumulated
 /// join results, and a new collection. The former is referred to
 /// as the "stream" and the latter the "lookup".
-#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct LinearStagePlan {
     /// The index of the relation into which we will look up.
     pub lookup_relation: usize,
@@ -70,7 +122,16 @@ pub struct LinearStagePlan {
     pub stream_thinning: Vec<usize>,
     /// The key expressions to use for the lookup relation.
     pub lookup_key: Vec<ScalarExpr>,
-    /// The closure to apply to the concatenation of the key columns,
-    /// the stream value columns, and the lookup value colunms.
+    /// Number of columns of the lookup relation, used to pad unmatched stream rows with nulls
+    pub lookup_arity: usize,
+    /// The closure to apply to the concatenation of the retained stream columns(see `stream_thinning`)
+    /// and all columns of the lookup relation.
+    ///
+    /// For outer join, the predicates of the closure decide whether two rows match, and its map and project
+    /// are applied to both matched rows and rows padded with nulls.
+    /// For semi and anti join, the closure is only used to decide whether two rows match,
+    /// and the output row is the retained stream columns.
     pub closure: JoinFilter,
+    /// The kind of this join stage
+    pub kind: JoinKind,
 }
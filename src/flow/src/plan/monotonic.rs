@@ -0,0 +1,335 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Monotonicity analysis over [`TypedPlan`], which finds the operators whose input is
+//! append-only(never retracts a row), so cheaper operators that can't handle retractions
+//! can be picked for them
+
+use std::collections::BTreeSet;
+
+use crate::expr::{Id, LocalId, MfpPlan};
+use crate::plan::{AccumulablePlan, JoinKind, JoinPlan, Plan, ReducePlan, TypedPlan};
+
+impl TypedPlan {
+    /// Whether the output of the plan never retracts a row
+    pub fn is_append_only(&self) -> bool {
+        self.is_append_only_in(&BTreeSet::new())
+    }
+
+    /// Same as [`TypedPlan::is_append_only`], with the `Let` bindings known to be append-only
+    fn is_append_only_in(&self, append_only_lets: &BTreeSet<LocalId>) -> bool {
+        match &self.plan {
+            Plan::Constant { rows } => rows.iter().all(|(_, _, diff)| *diff > 0),
+            // a flownode only receives inserts of source tables
+            Plan::Get { id: Id::Global(_) } => true,
+            Plan::Get { id: Id::Local(id) } => append_only_lets.contains(id),
+            Plan::Let { id, value, body } => {
+                let mut append_only_lets = append_only_lets.clone();
+                if value.is_append_only_in(&append_only_lets) {
+                    append_only_lets.insert(*id);
+                }
+                body.is_append_only_in(&append_only_lets)
+            }
+            // a row passing an upper bound of `now` is retracted once `now` exceeds it
+            Plan::Mfp { input, mfp } => {
+                MfpPlan::create_from(mfp.clone()).is_ok_and(|plan| plan.upper_bounds.is_empty())
+                    && input.is_append_only_in(append_only_lets)
+            }
+            // an accumulable reduce only inserts the latest output of each group(as upsert),
+            // while a distinct reduce retracts a key once all its rows are retracted
+            Plan::Reduce {
+                input, reduce_plan, ..
+            } => {
+                matches!(reduce_plan, ReducePlan::Accumulable(_))
+                    || input.is_append_only_in(append_only_lets)
+            }
+            // rows padded with nulls or without a match are retracted once a match comes in
+            Plan::Join {
+                inputs,
+                plan: JoinPlan::Linear(plan),
+            } => {
+                plan.stage_plans
+                    .iter()
+                    .all(|stage| matches!(stage.kind, JoinKind::Inner | JoinKind::Semi))
+                    && inputs
+                        .iter()
+                        .all(|input| input.is_append_only_in(append_only_lets))
+            }
+            // a delta join only has inner join stages
+            Plan::Join {
+                inputs,
+                plan: JoinPlan::Delta(_),
+            } => inputs
+                .iter()
+                .all(|input| input.is_append_only_in(append_only_lets)),
+            Plan::Union { inputs, .. } => inputs
+                .iter()
+                .all(|input| input.is_append_only_in(append_only_lets)),
+            // rows leaving the top-k or with a changed window value are retracted
+            Plan::TopK { .. } | Plan::Window { .. } => false,
+        }
+    }
+
+    /// Pick operators by whether their input is append-only:
+    /// 1. min/max over an append-only input only keep the current min/max of each group, otherwise
+    ///    they are computed from the distinct values of each group, so a retracted min/max can be
+    ///    replaced by the next one
+    /// 2. top-k over an append-only input only keep the top-k rows of each group
+    pub fn pick_by_monotonicity(self) -> Self {
+        self.pick_by_monotonicity_in(&BTreeSet::new())
+    }
+
+    fn pick_by_monotonicity_in(self, append_only_lets: &BTreeSet<LocalId>) -> Self {
+        let schema = self.schema;
+        let plan = match self.plan {
+            Plan::Let { id, value, body } => {
+                let value = value.pick_by_monotonicity_in(append_only_lets);
+                let mut body_lets = append_only_lets.clone();
+                if value.is_append_only_in(append_only_lets) {
+                    body_lets.insert(id);
+                }
+                Plan::Let {
+                    id,
+                    value: Box::new(value),
+                    body: Box::new(body.pick_by_monotonicity_in(&body_lets)),
+                }
+            }
+            Plan::Mfp { input, mfp } => Plan::Mfp {
+                input: Box::new(input.pick_by_monotonicity_in(append_only_lets)),
+                mfp,
+            },
+            Plan::Reduce {
+                input,
+                key_val_plan,
+                reduce_plan,
+            } => {
+                let input = input.pick_by_monotonicity_in(append_only_lets);
+                let reduce_plan = match reduce_plan {
+                    ReducePlan::Accumulable(plan) if !input.is_append_only_in(append_only_lets) => {
+                        ReducePlan::Accumulable(retractable_min_max(plan))
+                    }
+                    plan => plan,
+                };
+                Plan::Reduce {
+                    input: Box::new(input),
+                    key_val_plan,
+                    reduce_plan,
+                }
+            }
+            Plan::Join { inputs, plan } => Plan::Join {
+                inputs: inputs
+                    .into_iter()
+                    .map(|input| input.pick_by_monotonicity_in(append_only_lets))
+                    .collect(),
+                plan,
+            },
+            Plan::Union {
+                inputs,
+                consolidate_output,
+            } => Plan::Union {
+                inputs: inputs
+                    .into_iter()
+                    .map(|input| input.pick_by_monotonicity_in(append_only_lets))
+                    .collect(),
+                consolidate_output,
+            },
+            Plan::TopK {
+                input,
+                order_by,
+                limit,
+                per_key,
+                append_only: _,
+            } => {
+                let input = input.pick_by_monotonicity_in(append_only_lets);
+                Plan::TopK {
+                    append_only: input.is_append_only_in(append_only_lets),
+                    input: Box::new(input),
+                    order_by,
+                    limit,
+                    per_key,
+                }
+            }
+            Plan::Window {
+                input,
+                partition_by,
+                order_by,
+                exprs,
+            } => Plan::Window {
+                input: Box::new(input.pick_by_monotonicity_in(append_only_lets)),
+                partition_by,
+                order_by,
+                exprs,
+            },
+            plan @ (Plan::Constant { .. } | Plan::Get { .. }) => plan,
+        };
+        TypedPlan { schema, plan }
+    }
+}
+
+/// Move min/max out of the simple aggregations, whose accumulator only keeps the current min/max
+/// and can't handle retractions, into the distinct aggregations(as `min(x)` equals to
+/// `min(DISTINCT x)`), which keep the distinct values of each group
+fn retractable_min_max(mut plan: AccumulablePlan) -> AccumulablePlan {
+    let (min_max, simple): (Vec<_>, Vec<_>) = plan
+        .simple_aggrs
+        .into_iter()
+        .partition(|aggr| aggr.expr.func.is_min() || aggr.expr.func.is_max());
+    plan.simple_aggrs = simple;
+    for mut aggr in min_max {
+        aggr.expr.distinct = true;
+        if let Some(expr) = plan.full_aggrs.get_mut(aggr.output_idx) {
+            expr.distinct = true;
+        }
+        plan.distinct_aggrs.push(aggr);
+    }
+    plan
+}
+
+#[cfg(test)]
+mod test {
+    use datatypes::data_type::ConcreteDataType as CDT;
+
+    use super::*;
+    use crate::expr::{AggregateExpr, AggregateFunc, GlobalId, MapFilterProject, ScalarExpr};
+    use crate::plan::{AggrWithIndex, ColumnOrder, KeyValPlan};
+    use crate::repr::{ColumnType, RelationType, Row};
+
+    fn source() -> TypedPlan {
+        Plan::Get {
+            id: Id::Global(GlobalId::User(1)),
+        }
+        .with_types(
+            RelationType::new(vec![ColumnType::new(CDT::int64_datatype(), false)]).into_unnamed(),
+        )
+    }
+
+    /// a constant with a retraction
+    fn retracting() -> TypedPlan {
+        Plan::Constant {
+            rows: vec![
+                (Row::new(vec![1i64.into()]), 0, 1),
+                (Row::new(vec![1i64.into()]), 1, -1),
+            ],
+        }
+        .with_types(
+            RelationType::new(vec![ColumnType::new(CDT::int64_datatype(), false)]).into_unnamed(),
+        )
+    }
+
+    /// SELECT max(col) FROM input
+    fn max_of(input: TypedPlan) -> TypedPlan {
+        let max = AggregateExpr {
+            func: AggregateFunc::MaxInt64,
+            expr: ScalarExpr::Column(0),
+            distinct: false,
+        };
+        Plan::Reduce {
+            input: Box::new(input),
+            key_val_plan: KeyValPlan {
+                key_plan: MapFilterProject::new(1).project([]).unwrap().into_safe(),
+                val_plan: MapFilterProject::new(1).project([0]).unwrap().into_safe(),
+            },
+            reduce_plan: ReducePlan::Accumulable(AccumulablePlan {
+                full_aggrs: vec![max.clone()],
+                simple_aggrs: vec![AggrWithIndex::new(max, 0, 0)],
+                distinct_aggrs: vec![],
+            }),
+        }
+        .with_types(
+            RelationType::new(vec![ColumnType::new(CDT::int64_datatype(), true)]).into_unnamed(),
+        )
+    }
+
+    fn top_k_of(input: TypedPlan) -> TypedPlan {
+        Plan::TopK {
+            input: Box::new(input.clone()),
+            order_by: vec![ColumnOrder::new(0, true, false)],
+            limit: 3,
+            per_key: vec![],
+            append_only: false,
+        }
+        .with_types(input.schema)
+    }
+
+    fn accum_plan(plan: &TypedPlan) -> &AccumulablePlan {
+        let Plan::Reduce {
+            reduce_plan: ReducePlan::Accumulable(plan),
+            ..
+        } = &plan.plan
+        else {
+            panic!("Expect an accumulable reduce, found {:?}", plan);
+        };
+        plan
+    }
+
+    #[test]
+    fn test_is_append_only() {
+        assert!(source().is_append_only());
+        assert!(!retracting().is_append_only());
+        assert!(max_of(retracting()).is_append_only());
+        assert!(!top_k_of(source()).is_append_only());
+
+        let union = Plan::Union {
+            inputs: vec![source(), retracting()],
+            consolidate_output: false,
+        }
+        .with_types(source().schema);
+        assert!(!union.is_append_only());
+
+        let let_plan = Plan::Let {
+            id: LocalId(0),
+            value: Box::new(source()),
+            body: Box::new(
+                Plan::Get {
+                    id: Id::Local(LocalId(0)),
+                }
+                .with_types(source().schema),
+            ),
+        }
+        .with_types(source().schema);
+        assert!(let_plan.is_append_only());
+    }
+
+    #[test]
+    fn test_pick_min_max() {
+        // append-only input keeps the cheap accumulator
+        let plan = max_of(source()).pick_by_monotonicity();
+        let plan = accum_plan(&plan);
+        assert_eq!(plan.simple_aggrs.len(), 1);
+        assert!(plan.distinct_aggrs.is_empty());
+
+        // input with retraction computes max from the distinct values
+        let plan = max_of(retracting()).pick_by_monotonicity();
+        let plan = accum_plan(&plan);
+        assert!(plan.simple_aggrs.is_empty());
+        assert_eq!(plan.distinct_aggrs.len(), 1);
+        assert!(plan.distinct_aggrs[0].expr.distinct);
+        assert!(plan.full_aggrs[0].distinct);
+
+        // top-k retracts rows leaving it
+        let plan = max_of(top_k_of(source())).pick_by_monotonicity();
+        assert_eq!(accum_plan(&plan).distinct_aggrs.len(), 1);
+    }
+
+    #[test]
+    fn test_pick_top_k() {
+        let is_append_only_top_k = |plan: TypedPlan| match plan.pick_by_monotonicity().plan {
+            Plan::TopK { append_only, .. } => append_only,
+            plan => panic!("Expect a top-k plan, found {:?}", plan),
+        };
+        assert!(is_append_only_top_k(top_k_of(source())));
+        assert!(!is_append_only_top_k(top_k_of(retracting())));
+        assert!(!is_append_only_top_k(top_k_of(top_k_of(source()))));
+    }
+}
@@ -0,0 +1,295 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Rewrite rules over [`TypedPlan`] that reduce per-row work without changing its output
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::error::Error;
+use crate::expr::MapFilterProject;
+use crate::plan::delta_join::plan_delta_join;
+use crate::plan::{KeyValPlan, Plan, TypedPlan};
+use crate::repr::RelationDesc;
+
+impl TypedPlan {
+    /// Optimize the plan bottom-up by:
+    /// 1. fusing adjacent [`Plan::Mfp`] into one, and removing the identity ones
+    /// 2. inlining column references and removing unused expressions in each mfp
+    /// 3. pushing the columns demanded by a [`Plan::Reduce`] down into the mfp below it(or right
+    ///    after its source), so unused columns are dropped as early as possible
+    /// 4. merging nested binary inner joins of three or more inputs into one delta join, which
+    ///    doesn't keep the intermediate results of each binary join as state
+    pub fn optimize(self) -> Result<Self, Error> {
+        let schema = self.schema;
+        let plan = match self.plan {
+            Plan::Mfp { input, mfp } => {
+                let input = input.optimize()?;
+                let (input, mfp) = match input.plan {
+                    Plan::Mfp {
+                        input: inner,
+                        mfp: inner_mfp,
+                    } => (*inner, MapFilterProject::compose(inner_mfp, mfp)?),
+                    plan => (plan.with_types(input.schema), mfp),
+                };
+                return Ok(with_mfp(input, mfp, schema));
+            }
+            Plan::Reduce {
+                input,
+                key_val_plan,
+                reduce_plan,
+            } => {
+                let (input, key_val_plan) = push_down_demand(input.optimize()?, key_val_plan)?;
+                Plan::Reduce {
+                    input: Box::new(input),
+                    key_val_plan,
+                    reduce_plan,
+                }
+            }
+            Plan::Let { id, value, body } => Plan::Let {
+                id,
+                value: Box::new(value.optimize()?),
+                body: Box::new(body.optimize()?),
+            },
+            Plan::Join { inputs, plan } => {
+                let (inputs, plan) = plan_delta_join(inputs, plan)?;
+                Plan::Join {
+                    inputs: inputs
+                        .into_iter()
+                        .map(|input| input.optimize())
+                        .collect::<Result<_, _>>()?,
+                    plan,
+                }
+            }
+            Plan::Union {
+                inputs,
+                consolidate_output,
+            } => Plan::Union {
+                inputs: inputs
+                    .into_iter()
+                    .map(|input| input.optimize())
+                    .collect::<Result<_, _>>()?,
+                consolidate_output,
+            },
+            Plan::TopK {
+                input,
+                order_by,
+                limit,
+                per_key,
+                append_only,
+            } => Plan::TopK {
+                input: Box::new(input.optimize()?),
+                order_by,
+                limit,
+                per_key,
+                append_only,
+            },
+            Plan::Window {
+                input,
+                partition_by,
+                order_by,
+                exprs,
+            } => Plan::Window {
+                input: Box::new(input.optimize()?),
+                partition_by,
+                order_by,
+                exprs,
+            },
+            plan @ (Plan::Constant { .. } | Plan::Get { .. }) => plan,
+        };
+        Ok(TypedPlan { schema, plan })
+    }
+}
+
+/// Apply an optimized `mfp` to `input`, or return `input` as is if `mfp` is the identity
+///
+/// `schema` is the output schema of the mfp, which is kept so column names are preserved
+fn with_mfp(input: TypedPlan, mut mfp: MapFilterProject, schema: RelationDesc) -> TypedPlan {
+    mfp.optimize();
+    if mfp.is_identity() {
+        input.plan.with_types(schema)
+    } else {
+        Plan::Mfp {
+            input: Box::new(input),
+            mfp,
+        }
+        .with_types(schema)
+    }
+}
+
+/// Project the input of a reduce to only the columns its key and value plans demand, either by
+/// narrowing the mfp below the reduce or adding one right after the source
+fn push_down_demand(
+    input: TypedPlan,
+    mut key_val_plan: KeyValPlan,
+) -> Result<(TypedPlan, KeyValPlan), Error> {
+    let arity = input.schema.len()?;
+    let demand: BTreeSet<usize> = key_val_plan
+        .key_plan
+        .mfp
+        .demand()
+        .union(&key_val_plan.val_plan.mfp.demand())
+        .cloned()
+        .collect();
+    if demand.len() == arity {
+        return Ok((input, key_val_plan));
+    }
+    let project = MapFilterProject::new(arity)
+        .project(demand.iter().cloned())?
+        .into_safe();
+    let schema = input.schema.apply_mfp(&project)?;
+
+    let (inner, mfp) = match input.plan {
+        Plan::Mfp { input: inner, mfp } => (*inner, mfp),
+        Plan::Get { id } => (
+            Plan::Get { id }.with_types(input.schema),
+            MapFilterProject::new(arity),
+        ),
+        plan => return Ok((plan.with_types(input.schema), key_val_plan)),
+    };
+    let mfp = MapFilterProject::compose(mfp, project.mfp)?;
+
+    let shuffle: BTreeMap<usize, usize> = demand
+        .iter()
+        .enumerate()
+        .map(|(new, old)| (*old, new))
+        .collect();
+    key_val_plan
+        .key_plan
+        .permute(shuffle.clone(), demand.len())?;
+    key_val_plan.val_plan.permute(shuffle, demand.len())?;
+    Ok((with_mfp(inner, mfp, schema), key_val_plan))
+}
+
+#[cfg(test)]
+mod test {
+    use datatypes::data_type::ConcreteDataType as CDT;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::expr::{GlobalId, Id, ScalarExpr};
+    use crate::plan::ReducePlan;
+    use crate::repr::{ColumnType, RelationType};
+
+    fn number_ts_source() -> TypedPlan {
+        Plan::Get {
+            id: Id::Global(GlobalId::User(1)),
+        }
+        .with_types(
+            RelationType::new(vec![
+                ColumnType::new(CDT::uint32_datatype(), false),
+                ColumnType::new(CDT::timestamp_millisecond_datatype(), false),
+            ])
+            .into_named(vec![Some("number".to_string()), Some("ts".to_string())]),
+        )
+    }
+
+    fn number_schema() -> RelationDesc {
+        RelationType::new(vec![ColumnType::new(CDT::uint32_datatype(), false)])
+            .into_named(vec![Some("number".to_string())])
+    }
+
+    fn ts_schema() -> RelationDesc {
+        RelationType::new(vec![ColumnType::new(
+            CDT::timestamp_millisecond_datatype(),
+            false,
+        )])
+        .into_named(vec![Some("ts".to_string())])
+    }
+
+    #[test]
+    fn test_fuse_mfp() {
+        // swap columns, then pick the `number` column
+        let swap = Plan::Mfp {
+            input: Box::new(number_ts_source()),
+            mfp: MapFilterProject::new(2)
+                .map(vec![ScalarExpr::Column(1), ScalarExpr::Column(0)])
+                .unwrap()
+                .project(vec![2, 3])
+                .unwrap(),
+        }
+        .with_types(
+            RelationType::new(vec![
+                ColumnType::new(CDT::timestamp_millisecond_datatype(), false),
+                ColumnType::new(CDT::uint32_datatype(), false),
+            ])
+            .into_named(vec![Some("ts".to_string()), Some("number".to_string())]),
+        );
+        let plan = Plan::Mfp {
+            input: Box::new(swap),
+            mfp: MapFilterProject::new(2).project(vec![1]).unwrap(),
+        }
+        .with_types(number_schema());
+
+        let expected = Plan::Mfp {
+            input: Box::new(number_ts_source()),
+            mfp: MapFilterProject::new(2).project(vec![0]).unwrap(),
+        }
+        .with_types(number_schema());
+        assert_eq!(plan.optimize().unwrap(), expected);
+
+        // identity mfp is removed
+        let plan = Plan::Mfp {
+            input: Box::new(number_ts_source()),
+            mfp: MapFilterProject::new(2),
+        }
+        .with_types(number_ts_source().schema);
+        assert_eq!(plan.optimize().unwrap(), number_ts_source());
+    }
+
+    #[test]
+    fn test_push_down_reduce_demand() {
+        // SELECT DISTINCT ts FROM numbers_with_ts
+        let plan = Plan::Reduce {
+            input: Box::new(
+                Plan::Mfp {
+                    input: Box::new(number_ts_source()),
+                    mfp: MapFilterProject::new(2),
+                }
+                .with_types(number_ts_source().schema),
+            ),
+            key_val_plan: KeyValPlan {
+                key_plan: MapFilterProject::new(2)
+                    .project(vec![1])
+                    .unwrap()
+                    .into_safe(),
+                val_plan: MapFilterProject::new(2)
+                    .project(vec![])
+                    .unwrap()
+                    .into_safe(),
+            },
+            reduce_plan: ReducePlan::Distinct,
+        }
+        .with_types(ts_schema());
+
+        let expected = Plan::Reduce {
+            input: Box::new(
+                Plan::Mfp {
+                    input: Box::new(number_ts_source()),
+                    mfp: MapFilterProject::new(2).project(vec![1]).unwrap(),
+                }
+                .with_types(ts_schema()),
+            ),
+            key_val_plan: KeyValPlan {
+                key_plan: MapFilterProject::new(1).into_safe(),
+                val_plan: MapFilterProject::new(1)
+                    .project(vec![])
+                    .unwrap()
+                    .into_safe(),
+            },
+            reduce_plan: ReducePlan::Distinct,
+        }
+        .with_types(ts_schema());
+        assert_eq!(plan.optimize().unwrap(), expected);
+    }
+}
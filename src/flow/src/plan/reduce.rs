@@ -12,10 +12,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use itertools::Itertools;
+
 use crate::expr::{AggregateExpr, SafeMfpPlan};
 
 /// Describe how to extract key-value pair from a `Row`
-#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct KeyValPlan {
     /// Extract key from row
     pub key_plan: SafeMfpPlan,
@@ -25,7 +27,7 @@ pub struct KeyValPlan {
 
 /// TODO(discord9): def&impl of Hierarchical aggregates(for min/max with support to deletion) and
 /// basic aggregates(for other aggregate functions) and mixed aggregate
-#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum ReducePlan {
     /// Plan for not computing any aggregations, just determining the set of
     /// distinct keys.
@@ -35,8 +37,35 @@ pub enum ReducePlan {
     Accumulable(AccumulablePlan),
 }
 
+/// Explain the aggregations as `#output = func(#input)`, where `#output` is the index among
+/// aggregation outputs and `#input` is the index among values extracted by `KeyValPlan::val_plan`
+impl std::fmt::Display for ReducePlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ReducePlan::Accumulable(plan) = self else {
+            return write!(f, "Distinct");
+        };
+        let simple = plan.simple_aggrs.iter().map(|aggr| (aggr, false));
+        let distinct = plan.distinct_aggrs.iter().map(|aggr| (aggr, true));
+        let aggrs = simple
+            .chain(distinct)
+            .sorted_by_key(|(aggr, _)| aggr.output_idx)
+            .map(|(aggr, distinct)| {
+                let distinct = if distinct { "DISTINCT " } else { "" };
+                let filter = aggr
+                    .filter_idx
+                    .map(|idx| format!(" FILTER #{idx}"))
+                    .unwrap_or_default();
+                format!(
+                    "#{} = {:?}({distinct}#{}){filter}",
+                    aggr.output_idx, aggr.expr.func, aggr.input_idx
+                )
+            });
+        write!(f, "Accumulable[{}]", aggrs.format(", "))
+    }
+}
+
 /// Accumulable plan for the execution of a reduction.
-#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct AccumulablePlan {
     /// All of the aggregations we were asked to compute, stored
     /// in order.
@@ -55,7 +84,7 @@ pub struct AccumulablePlan {
 
 /// Invariant: the output index is the index of the aggregation in `full_aggrs`
 /// which means output index is always smaller than the length of `full_aggrs`
-#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct AggrWithIndex {
     /// aggregation expression
     pub expr: AggregateExpr,
@@ -63,6 +92,9 @@ pub struct AggrWithIndex {
     pub input_idx: usize,
     /// index of aggr output among output row
     pub output_idx: usize,
+    /// index of a boolean value among input row, if exist, only rows with this value being `true`
+    /// are fed into the aggregation, i.e. `sum(x) FILTER (WHERE cond)`
+    pub filter_idx: Option<usize>,
 }
 
 impl AggrWithIndex {
@@ -72,6 +104,13 @@ impl AggrWithIndex {
             expr,
             input_idx,
             output_idx,
+            filter_idx: None,
         }
     }
+
+    /// Only feed rows with the boolean value at `filter_idx` of input row being `true` into the aggregation
+    pub fn with_filter(mut self, filter_idx: Option<usize>) -> Self {
+        self.filter_idx = filter_idx;
+        self
+    }
 }
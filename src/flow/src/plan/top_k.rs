@@ -0,0 +1,34 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// Sort order of a single column, used by top-k
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct ColumnOrder {
+    /// index of the column to sort by
+    pub column: usize,
+    /// whether to sort in descending order
+    pub desc: bool,
+    /// whether nulls are placed after non-null values
+    pub nulls_last: bool,
+}
+
+impl ColumnOrder {
+    pub fn new(column: usize, desc: bool, nulls_last: bool) -> Self {
+        Self {
+            column,
+            desc,
+            nulls_last,
+        }
+    }
+}
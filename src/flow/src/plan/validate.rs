@@ -0,0 +1,542 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Type checking of [`TypedPlan`], so invalid plans are rejected when creating the flow instead
+//! of failing with `EvalError` at runtime
+
+use std::fmt::Display;
+
+use datatypes::prelude::ConcreteDataType;
+
+use crate::error::{Error, PlanSnafu};
+use crate::expr::{MapFilterProject, ScalarExpr};
+use crate::plan::{
+    JoinFilter, JoinKind, JoinPlan, KeyValPlan, LinearJoinPlan, Plan, ReducePlan, TypedPlan,
+};
+use crate::repr::ColumnType;
+
+impl TypedPlan {
+    /// Check every expression in the plan against the column types of its input, as well as the
+    /// column indices and arity each operator relies on
+    ///
+    /// The error tells where the invalid expression is, i.e. `Mfp/Reduce: key_plan.expressions[0] ...`
+    /// means the first expression of the key plan of the reduce below the top level mfp
+    pub fn validate(&self) -> Result<(), Error> {
+        self.validate_at("").map(|_| ())
+    }
+
+    /// Validate the plan located at `parent` in the whole plan, and return its output column types
+    fn validate_at(&self, parent: &str) -> Result<Vec<ColumnType>, Error> {
+        let path = if parent.is_empty() {
+            self.plan.name().to_string()
+        } else {
+            format!("{}/{}", parent, self.plan.name())
+        };
+        let path = path.as_str();
+        let output = match &self.plan {
+            Plan::Constant { rows } => {
+                let arity = self.schema.typ().column_types.len();
+                for (i, (row, _, _)) in rows.iter().enumerate() {
+                    check(path, row.len() == arity, || {
+                        format!("rows[{i}] has {} columns, expect {arity}", row.len())
+                    })?;
+                }
+                self.schema.typ().column_types.clone()
+            }
+            Plan::Get { .. } => self.schema.typ().column_types.clone(),
+            Plan::Let { value, body, .. } => {
+                value.validate_at(&format!("{path}.value"))?;
+                body.validate_at(&format!("{path}.body"))?
+            }
+            Plan::Mfp { input, mfp } => {
+                let input = input.validate_at(path)?;
+                check_mfp(path, "mfp", mfp, &input)?
+            }
+            Plan::Reduce {
+                input,
+                key_val_plan,
+                reduce_plan,
+            } => {
+                let input = input.validate_at(path)?;
+                let arity = check_reduce(path, key_val_plan, reduce_plan, &input)?;
+                let output = self.schema.typ().column_types.clone();
+                check(path, arity == output.len(), || {
+                    format!(
+                        "keys and aggregations have {arity} columns, but the schema has {} columns",
+                        output.len()
+                    )
+                })?;
+                output
+            }
+            Plan::Join { inputs, plan } => {
+                let inputs = inputs
+                    .iter()
+                    .enumerate()
+                    .map(|(i, input)| input.validate_at(&format!("{path}.inputs[{i}]")))
+                    .collect::<Result<Vec<_>, _>>()?;
+                check_join(path, plan, &inputs)?
+            }
+            Plan::Union { inputs, .. } => {
+                let arity = self.schema.typ().column_types.len();
+                for (i, input) in inputs.iter().enumerate() {
+                    let input = input.validate_at(&format!("{path}.inputs[{i}]"))?;
+                    check(path, input.len() == arity, || {
+                        format!("inputs[{i}] has {} columns, expect {arity}", input.len())
+                    })?;
+                }
+                self.schema.typ().column_types.clone()
+            }
+            Plan::TopK {
+                input,
+                order_by,
+                per_key,
+                ..
+            } => {
+                let input = input.validate_at(path)?;
+                let order_by = order_by.iter().map(|o| o.column);
+                check_columns(path, "order_by", order_by, input.len())?;
+                check_columns(path, "per_key", per_key.iter().cloned(), input.len())?;
+                input
+            }
+            Plan::Window {
+                input,
+                partition_by,
+                order_by,
+                exprs,
+            } => {
+                let mut input = input.validate_at(path)?;
+                let arity = input.len();
+                let order_by = order_by.iter().map(|o| o.column);
+                check_columns(path, "order_by", order_by, arity)?;
+                check_columns(path, "partition_by", partition_by.iter().cloned(), arity)?;
+                for (i, expr) in exprs.iter().enumerate() {
+                    check_columns(path, format!("exprs[{i}].arg"), expr.arg, arity)?;
+                }
+                // the types of window function outputs are only known by the plan's schema
+                let schema = &self.schema.typ().column_types;
+                input.extend(schema.iter().skip(arity).cloned());
+                input
+            }
+        };
+        let expected = self.schema.typ().column_types.len();
+        check(path, output.len() == expected, || {
+            format!(
+                "output has {} columns, but the schema has {expected} columns",
+                output.len()
+            )
+        })?;
+        Ok(output)
+    }
+}
+
+impl Plan {
+    /// Name of the plan's variant, used to locate a plan node in error messages
+    fn name(&self) -> &'static str {
+        match self {
+            Plan::Constant { .. } => "Constant",
+            Plan::Get { .. } => "Get",
+            Plan::Let { .. } => "Let",
+            Plan::Mfp { .. } => "Mfp",
+            Plan::Reduce { .. } => "Reduce",
+            Plan::Join { .. } => "Join",
+            Plan::Union { .. } => "Union",
+            Plan::TopK { .. } => "TopK",
+            Plan::Window { .. } => "Window",
+        }
+    }
+}
+
+/// Fail with the message from `msg` at `path` if `cond` doesn't hold
+fn check(path: &str, cond: bool, msg: impl FnOnce() -> String) -> Result<(), Error> {
+    if cond {
+        Ok(())
+    } else {
+        PlanSnafu {
+            reason: format!("Invalid plan at {path}: {}", msg()),
+        }
+        .fail()
+    }
+}
+
+/// Check all columns are in range of the input's `arity`
+fn check_columns(
+    path: &str,
+    what: impl Display,
+    columns: impl IntoIterator<Item = usize>,
+    arity: usize,
+) -> Result<(), Error> {
+    for c in columns {
+        check(path, c < arity, || {
+            format!("{what} refers to column {c}, but the input only has {arity} columns")
+        })?;
+    }
+    Ok(())
+}
+
+/// Check the expression against the input column types, and return its type
+fn check_expr(
+    path: &str,
+    what: impl Display,
+    expr: &ScalarExpr,
+    input: &[ColumnType],
+) -> Result<ColumnType, Error> {
+    check_columns(path, &what, expr.get_all_ref_columns(), input.len())?;
+    expr.typ(input).map_err(|err| {
+        PlanSnafu {
+            reason: format!("Invalid plan at {path}: {what} is ill-typed: {err}"),
+        }
+        .build()
+    })
+}
+
+/// Check the predicate against the input column types, and that it evaluates to a boolean
+fn check_predicate(
+    path: &str,
+    what: impl Display,
+    expr: &ScalarExpr,
+    input: &[ColumnType],
+) -> Result<(), Error> {
+    let typ = check_expr(path, &what, expr, input)?;
+    check(
+        path,
+        typ.scalar_type == ConcreteDataType::boolean_datatype() || typ.scalar_type.is_null(),
+        || format!("{what} should be a boolean, found {:?}", typ.scalar_type),
+    )
+}
+
+/// Check every expression and predicate of the mfp in order, and return its output column types
+fn check_mfp(
+    path: &str,
+    what: &str,
+    mfp: &MapFilterProject,
+    input: &[ColumnType],
+) -> Result<Vec<ColumnType>, Error> {
+    check(path, mfp.input_arity == input.len(), || {
+        format!(
+            "{what} expects {} input columns, but the input has {} columns",
+            mfp.input_arity,
+            input.len()
+        )
+    })?;
+    let mut types = input.to_vec();
+    for (i, expr) in mfp.expressions.iter().enumerate() {
+        let typ = check_expr(path, format!("{what}.expressions[{i}]"), expr, &types)?;
+        types.push(typ);
+    }
+    for (i, (_, pred)) in mfp.predicates.iter().enumerate() {
+        check_predicate(path, format!("{what}.predicates[{i}]"), pred, &types)?;
+    }
+    let projection = mfp.projection.iter().cloned();
+    check_columns(path, format!("{what}.projection"), projection, types.len())?;
+    Ok(mfp.projection.iter().map(|i| types[*i].clone()).collect())
+}
+
+/// Check the key value plan and aggregations of a reduce, and return its number of output columns
+fn check_reduce(
+    path: &str,
+    key_val_plan: &KeyValPlan,
+    reduce_plan: &ReducePlan,
+    input: &[ColumnType],
+) -> Result<usize, Error> {
+    let keys = check_mfp(path, "key_plan", &key_val_plan.key_plan.mfp, input)?;
+    let vals = check_mfp(path, "val_plan", &key_val_plan.val_plan.mfp, input)?;
+    let ReducePlan::Accumulable(accum) = reduce_plan else {
+        return Ok(keys.len());
+    };
+
+    for (i, aggr) in accum.full_aggrs.iter().enumerate() {
+        check_expr(path, format!("full_aggrs[{i}]"), &aggr.expr, &vals)?;
+    }
+    let simple = accum.simple_aggrs.iter().map(|a| ("simple_aggrs", a));
+    let distinct = accum.distinct_aggrs.iter().map(|a| ("distinct_aggrs", a));
+    for (i, (kind, aggr)) in simple.chain(distinct).enumerate() {
+        let what = format!("{kind}[{i}]");
+        check_columns(
+            path,
+            format!("{what}.input_idx"),
+            [aggr.input_idx],
+            vals.len(),
+        )?;
+        check(path, aggr.output_idx < accum.full_aggrs.len(), || {
+            format!(
+                "{what}.output_idx is {}, but there are only {} aggregations",
+                aggr.output_idx,
+                accum.full_aggrs.len()
+            )
+        })?;
+        if let Some(filter_idx) = aggr.filter_idx {
+            check_predicate(
+                path,
+                format!("{what}.filter_idx"),
+                &ScalarExpr::Column(filter_idx),
+                &vals,
+            )?;
+        }
+    }
+    Ok(keys.len() + accum.full_aggrs.len())
+}
+
+/// Check the join, and return its output column types
+fn check_join(
+    path: &str,
+    plan: &JoinPlan,
+    inputs: &[Vec<ColumnType>],
+) -> Result<Vec<ColumnType>, Error> {
+    let plan = match plan {
+        JoinPlan::Linear(plan) => return check_linear_join(path, plan, inputs),
+        JoinPlan::Delta(plan) => plan,
+    };
+    check(path, plan.path_plans.len() == inputs.len(), || {
+        format!(
+            "delta join has {} paths, but there are {} inputs",
+            plan.path_plans.len(),
+            inputs.len()
+        )
+    })?;
+    let mut output: Option<Vec<ColumnType>> = None;
+    for (i, path_plan) in plan.path_plans.iter().enumerate() {
+        let path = format!("{path}.path_plans[{i}]");
+        check(&path, path_plan.source_relation == i, || {
+            format!(
+                "source_relation is {}, expect {i}",
+                path_plan.source_relation
+            )
+        })?;
+        let mut relations = path_plan
+            .stage_plans
+            .iter()
+            .map(|stage| stage.lookup_relation)
+            .chain([i])
+            .collect::<Vec<_>>();
+        relations.sort();
+        check(
+            &path,
+            relations == (0..inputs.len()).collect::<Vec<_>>(),
+            || format!("each input should be joined exactly once, found {relations:?}"),
+        )?;
+        for (j, stage) in path_plan.stage_plans.iter().enumerate() {
+            check(&path, stage.kind == JoinKind::Inner, || {
+                format!("stage_plans[{j}].kind is {:?}, expect Inner", stage.kind)
+            })?;
+        }
+        let path_output = check_linear_join(&path, path_plan, inputs)?;
+        if let Some(output) = &output {
+            check(&path, &path_output == output, || {
+                format!("output {path_output:?} differs from the first path's {output:?}")
+            })?;
+        } else {
+            output = Some(path_output);
+        }
+    }
+    Ok(output.unwrap_or_default())
+}
+
+/// Check every stage of the linear join in order, and return its output column types
+fn check_linear_join(
+    path: &str,
+    plan: &LinearJoinPlan,
+    inputs: &[Vec<ColumnType>],
+) -> Result<Vec<ColumnType>, Error> {
+    let input = |what: &str, idx: usize| {
+        inputs.get(idx).cloned().ok_or_else(|| {
+            PlanSnafu {
+                reason: format!(
+                    "Invalid plan at {path}: {what} is {idx}, but there are only {} inputs",
+                    inputs.len()
+                ),
+            }
+            .build()
+        })
+    };
+
+    let mut stream = input("source_relation", plan.source_relation)?;
+    if let Some(closure) = &plan.initial_closure {
+        stream = check_join_filter(path, "initial_closure", closure, &stream)?;
+    }
+    if let Some(source_key) = &plan.source_key {
+        for (i, key) in source_key.iter().enumerate() {
+            check_expr(path, format!("source_key[{i}]"), key, &stream)?;
+        }
+    }
+    for (i, stage) in plan.stage_plans.iter().enumerate() {
+        let what = format!("stage_plans[{i}]");
+        let lookup = input(&format!("{what}.lookup_relation"), stage.lookup_relation)?;
+        check(path, stage.lookup_arity == lookup.len(), || {
+            format!(
+                "{what}.lookup_arity is {}, but the lookup input has {} columns",
+                stage.lookup_arity,
+                lookup.len()
+            )
+        })?;
+        for (j, key) in stage.stream_key.iter().enumerate() {
+            check_expr(path, format!("{what}.stream_key[{j}]"), key, &stream)?;
+        }
+        for (j, key) in stage.lookup_key.iter().enumerate() {
+            check_expr(path, format!("{what}.lookup_key[{j}]"), key, &lookup)?;
+        }
+        let thinning = stage.stream_thinning.iter().cloned();
+        check_columns(
+            path,
+            format!("{what}.stream_thinning"),
+            thinning,
+            stream.len(),
+        )?;
+        let thinned = stage
+            .stream_thinning
+            .iter()
+            .map(|c| stream[*c].clone())
+            .collect::<Vec<_>>();
+        let joined = thinned
+            .iter()
+            .chain(lookup.iter())
+            .cloned()
+            .collect::<Vec<_>>();
+        let closure_output =
+            check_join_filter(path, &format!("{what}.closure"), &stage.closure, &joined)?;
+        stream = match stage.kind {
+            JoinKind::Inner | JoinKind::Left | JoinKind::Right | JoinKind::Full => closure_output,
+            JoinKind::Semi | JoinKind::Anti => thinned,
+        };
+    }
+    if let Some(closure) = &plan.final_closure {
+        stream = check_join_filter(path, "final_closure", closure, &stream)?;
+    }
+    Ok(stream)
+}
+
+/// Check the equivalences and mfp of a join filter, and return its output column types
+fn check_join_filter(
+    path: &str,
+    what: &str,
+    filter: &JoinFilter,
+    input: &[ColumnType],
+) -> Result<Vec<ColumnType>, Error> {
+    for (i, class) in filter.ready_equivalences.iter().enumerate() {
+        for (j, expr) in class.iter().enumerate() {
+            check_expr(
+                path,
+                format!("{what}.ready_equivalences[{i}][{j}]"),
+                expr,
+                input,
+            )?;
+        }
+    }
+    check_mfp(path, &format!("{what}.before"), &filter.before.mfp, input)
+}
+
+#[cfg(test)]
+mod test {
+    use datatypes::data_type::ConcreteDataType as CDT;
+
+    use super::*;
+    use crate::expr::{AggregateExpr, AggregateFunc, BinaryFunc, GlobalId, Id};
+    use crate::plan::{AccumulablePlan, AggrWithIndex};
+    use crate::repr::RelationType;
+    use crate::transform::test::{create_test_ctx, create_test_query_engine, sql_to_substrait};
+
+    fn numbers() -> TypedPlan {
+        Plan::Get {
+            id: Id::Global(GlobalId::User(0)),
+        }
+        .with_types(
+            RelationType::new(vec![ColumnType::new(CDT::uint32_datatype(), false)]).into_unnamed(),
+        )
+    }
+
+    fn assert_invalid(plan: TypedPlan, msg: &str) {
+        let err = plan.validate().unwrap_err();
+        assert!(err.to_string().contains(msg), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_validate_transformed() {
+        let engine = create_test_query_engine();
+        let sql = "SELECT number, sum(number) FROM numbers WHERE number > 1 GROUP BY number";
+        let plan = sql_to_substrait(engine.clone(), sql).await;
+
+        let mut ctx = create_test_ctx();
+        let flow_plan = TypedPlan::from_substrait_plan(&mut ctx, &plan)
+            .await
+            .unwrap();
+        flow_plan.validate().unwrap();
+        flow_plan.optimize().unwrap().validate().unwrap();
+    }
+
+    #[test]
+    fn test_validate_invalid() {
+        let schema =
+            RelationType::new(vec![ColumnType::new(CDT::uint32_datatype(), false)]).into_unnamed();
+
+        let plan = Plan::Mfp {
+            input: Box::new(numbers()),
+            mfp: MapFilterProject::new(1)
+                .map(vec![
+                    ScalarExpr::Column(0).call_binary(ScalarExpr::Column(0), BinaryFunc::AddUInt32)
+                ])
+                .unwrap()
+                .project(vec![1])
+                .unwrap(),
+        }
+        .with_types(schema.clone());
+        plan.validate().unwrap();
+
+        let mut mfp = MapFilterProject::new(1);
+        mfp.expressions.push(ScalarExpr::Column(3));
+        mfp.projection = vec![1];
+        let plan = Plan::Mfp {
+            input: Box::new(numbers()),
+            mfp,
+        }
+        .with_types(schema.clone());
+        assert_invalid(
+            plan,
+            "Invalid plan at Mfp: mfp.expressions[0] refers to column 3, but the input only has 1 columns",
+        );
+
+        let plan = Plan::Mfp {
+            input: Box::new(numbers()),
+            mfp: MapFilterProject::new(1)
+                .filter(vec![ScalarExpr::Column(0)])
+                .unwrap(),
+        }
+        .with_types(schema.clone());
+        assert_invalid(plan, "mfp.predicates[0] should be a boolean");
+
+        let aggr = AggregateExpr {
+            func: AggregateFunc::SumUInt32,
+            expr: ScalarExpr::Column(0),
+            distinct: false,
+        };
+        let plan = Plan::Reduce {
+            input: Box::new(numbers()),
+            key_val_plan: KeyValPlan {
+                key_plan: MapFilterProject::new(1)
+                    .project(vec![])
+                    .unwrap()
+                    .into_safe(),
+                val_plan: MapFilterProject::new(1).into_safe(),
+            },
+            reduce_plan: ReducePlan::Accumulable(AccumulablePlan {
+                full_aggrs: vec![aggr.clone()],
+                simple_aggrs: vec![AggrWithIndex::new(aggr, 1, 0)],
+                distinct_aggrs: vec![],
+            }),
+        }
+        .with_types(schema);
+        assert_invalid(
+            plan,
+            "Invalid plan at Reduce: simple_aggrs[0].input_idx refers to column 1",
+        );
+    }
+}
@@ -0,0 +1,58 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// A window function evaluated over the sorted rows of a partition
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum WindowFunc {
+    /// 1-based position of the row in its partition
+    RowNumber,
+    /// value of the argument `offset` rows before current row, or null if there is no such row
+    Lag { offset: usize },
+    /// value of the argument `offset` rows after current row, or null if there is no such row
+    Lead { offset: usize },
+    /// sum of the non-null argument in the frame of current row
+    Sum,
+}
+
+/// How the bounds of a [`WindowFrame`] are measured
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum FrameUnits {
+    /// bounds are number of rows before/after current row
+    #[default]
+    Rows,
+    /// bounds are distance of the first sort column's value(i.e. a time range in milliseconds
+    /// if sort by a time index) from current row's value
+    Range,
+}
+
+/// The frame of rows a window function is evaluated over, relative to current row
+///
+/// `None` bounds mean unbounded, so the default frame is the whole partition
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct WindowFrame {
+    pub units: FrameUnits,
+    /// how far the frame extends before current row
+    pub preceding: Option<i64>,
+    /// how far the frame extends after current row
+    pub following: Option<i64>,
+}
+
+/// A window function with its argument and frame
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct WindowExpr {
+    pub func: WindowFunc,
+    /// index of the argument column, `None` for functions without argument like `row_number`
+    pub arg: Option<usize>,
+    pub frame: WindowFrame,
+}
@@ -15,19 +15,22 @@
 //! basically a wrapper around the `datatype` crate
 //! for basic Data Representation
 
+mod codec;
 mod relation;
 
 use api::helper::{pb_value_to_value_ref, value_to_grpc_value};
 use api::v1::Row as ProtoRow;
+pub(crate) use codec::RowCodec;
+use common_time::timestamp::TimeUnit;
 use datatypes::data_type::ConcreteDataType;
 use datatypes::types::cast;
 use datatypes::value::Value;
 use itertools::Itertools;
 pub(crate) use relation::{ColumnType, Key, RelationDesc, RelationType};
 use serde::{Deserialize, Serialize};
-use snafu::ResultExt;
+use snafu::{OptionExt, ResultExt};
 
-use crate::expr::error::{CastValueSnafu, EvalError, InvalidArgumentSnafu};
+use crate::expr::error::{CastValueSnafu, EvalError, InvalidArgumentSnafu, OverflowSnafu};
 
 /// System-wide Record count difference type. Useful for capture data change
 ///
@@ -60,8 +63,20 @@ pub const BATCH_SIZE: usize = 32 * 16384;
 
 /// Convert a value that is or can be converted to Datetime to internal timestamp
 ///
-/// support types are: `Date`, `DateTime`, `TimeStamp`, `i64`
+/// support types are: `Date`, `DateTime`, `TimeStamp`, `i64`, a timestamp more precise than
+/// millisecond is rounded down to the millisecond it's in
 pub fn value_to_internal_ts(value: Value) -> Result<i64, EvalError> {
+    value_to_internal_ts_rounded(value, false)
+}
+
+/// Like [`value_to_internal_ts`], but a timestamp more precise than millisecond is rounded up, so
+/// for any internal time `t`, `t >= ts` holds iff `t >= value_to_internal_ts_ceil(ts)`, which is
+/// what temporal filters need to decide when a row with such timestamp becomes visible or expires
+pub fn value_to_internal_ts_ceil(value: Value) -> Result<i64, EvalError> {
+    value_to_internal_ts_rounded(value, true)
+}
+
+fn value_to_internal_ts_rounded(value: Value, round_up: bool) -> Result<i64, EvalError> {
     let is_supported_time_type = |arg: &Value| {
         let ty = arg.data_type();
         matches!(
@@ -74,6 +89,7 @@ pub fn value_to_internal_ts(value: Value) -> Result<i64, EvalError> {
     match value {
         Value::DateTime(ts) => Ok(ts.val()),
         Value::Int64(ts) => Ok(ts),
+        Value::Timestamp(ts) => timestamp_to_millis(ts, round_up),
         arg if is_supported_time_type(&arg) => {
             let arg_ty = arg.data_type();
             let res = cast(arg, &ConcreteDataType::timestamp_millisecond_datatype()).context({
@@ -95,6 +111,24 @@ pub fn value_to_internal_ts(value: Value) -> Result<i64, EvalError> {
     }
 }
 
+/// Convert a timestamp of any unit to milliseconds, fail instead of wrapping around on overflow
+fn timestamp_to_millis(ts: common_time::Timestamp, round_up: bool) -> Result<i64, EvalError> {
+    let value = ts.value();
+    let per_milli = match ts.unit() {
+        TimeUnit::Second => return value.checked_mul(1000).context(OverflowSnafu),
+        TimeUnit::Millisecond => return Ok(value),
+        TimeUnit::Microsecond => 1_000,
+        TimeUnit::Nanosecond => 1_000_000,
+    };
+    // euclid division rounds down for negative timestamps too
+    let millis = value.div_euclid(per_milli);
+    if round_up && value.rem_euclid(per_milli) != 0 {
+        Ok(millis + 1)
+    } else {
+        Ok(millis)
+    }
+}
+
 /// A row is a vector of values.
 ///
 /// TODO(discord9): use a more efficient representation
@@ -176,6 +210,20 @@ impl Row {
     pub fn len(&self) -> usize {
         self.inner.len()
     }
+
+    /// Convert only the columns at `indices` of a proto row, in the order of `indices`, so values
+    /// not needed or reordered are never converted or cloned
+    pub fn from_proto_columns(row: &ProtoRow, indices: &[usize]) -> Option<Self> {
+        indices
+            .iter()
+            .map(|&i| {
+                row.values
+                    .get(i)
+                    .map(|pb_val| -> Value { pb_value_to_value_ref(pb_val, &None).into() })
+            })
+            .collect::<Option<Vec<_>>>()
+            .map(Row::new)
+    }
 }
 
 impl From<Vec<Value>> for Row {
@@ -257,5 +305,24 @@ mod test {
                 Err(EvalError::InvalidArgument { .. })
             ));
         }
+
+        {
+            // timestamps more precise than millisecond are rounded down or up
+            let ns = |v| Value::Timestamp(common_time::Timestamp::new_nanosecond(v));
+            let us = |v| Value::Timestamp(common_time::Timestamp::new_microsecond(v));
+            assert_eq!(value_to_internal_ts(ns(1_500_000)).unwrap(), 1);
+            assert_eq!(value_to_internal_ts_ceil(ns(1_500_000)).unwrap(), 2);
+            assert_eq!(value_to_internal_ts_ceil(ns(2_000_000)).unwrap(), 2);
+            assert_eq!(value_to_internal_ts(ns(-1_500_000)).unwrap(), -2);
+            assert_eq!(value_to_internal_ts_ceil(ns(-1_500_000)).unwrap(), -1);
+            assert_eq!(value_to_internal_ts(us(-1_000)).unwrap(), -1);
+            assert_eq!(value_to_internal_ts_ceil(us(1_001)).unwrap(), 2);
+
+            let overflow = Value::Timestamp(common_time::Timestamp::new_second(i64::MAX));
+            assert!(matches!(
+                value_to_internal_ts(overflow),
+                Err(EvalError::Overflow { .. })
+            ));
+        }
     }
 }
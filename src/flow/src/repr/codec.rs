@@ -0,0 +1,166 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Encode [`Row`]s in the same memory-comparable format the region server uses for primary keys,
+//! so rows can be exchanged with storage without converting between formats
+
+use common_error::ext::BoxedError;
+use datatypes::data_type::ConcreteDataType;
+use datatypes::value::Value;
+use mito2::row_converter::{McmpRowCodec, RowCodec as _, SortField};
+use snafu::{ensure, ResultExt};
+use store_api::metadata::RegionMetadata;
+
+use crate::expr::error::{EvalError, ExternalSnafu, InvalidArgumentSnafu};
+use crate::repr::Row;
+
+/// Encoder/decoder of rows with columns of given types
+///
+/// Encoded rows compare(as bytes) in the same order as the region server sorts primary keys
+#[derive(Debug)]
+pub struct RowCodec {
+    codec: McmpRowCodec,
+}
+
+impl RowCodec {
+    pub fn new(column_types: impl IntoIterator<Item = ConcreteDataType>) -> Self {
+        Self {
+            codec: McmpRowCodec::new(column_types.into_iter().map(SortField::new).collect()),
+        }
+    }
+
+    /// Codec of the primary key of a region, the encoded row is the same as the primary key
+    /// stored in the region
+    pub fn with_primary_keys(metadata: &RegionMetadata) -> Self {
+        Self {
+            codec: McmpRowCodec::new_with_primary_keys(metadata),
+        }
+    }
+
+    pub fn num_columns(&self) -> usize {
+        self.codec.num_fields()
+    }
+
+    pub fn encode(&self, row: &Row) -> Result<Vec<u8>, EvalError> {
+        let mut buf = Vec::new();
+        self.encode_to(row, &mut buf)?;
+        Ok(buf)
+    }
+
+    /// Append the encoded row to `buf`, so the same buffer can be reused for many rows
+    pub fn encode_to(&self, row: &Row, buf: &mut Vec<u8>) -> Result<(), EvalError> {
+        ensure!(
+            row.len() == self.num_columns(),
+            InvalidArgumentSnafu {
+                reason: format!(
+                    "Expect a row of {} columns to encode, found {}",
+                    self.num_columns(),
+                    row.len()
+                )
+            }
+        );
+        self.codec
+            .encode_to_vec(row.iter().map(|v| v.as_value_ref()), buf)
+            .map_err(BoxedError::new)
+            .context(ExternalSnafu)
+    }
+
+    pub fn decode(&self, bytes: &[u8]) -> Result<Row, EvalError> {
+        self.codec
+            .decode(bytes)
+            .map(Row::new)
+            .map_err(BoxedError::new)
+            .context(ExternalSnafu)
+    }
+
+    /// Decode only the value of column `idx` without decoding the others
+    ///
+    /// `offsets` caches where each column starts, it can be reused to decode more columns of the
+    /// same encoded row
+    pub fn decode_column(
+        &self,
+        bytes: &[u8],
+        idx: usize,
+        offsets: &mut Vec<usize>,
+    ) -> Result<Value, EvalError> {
+        ensure!(
+            idx < self.num_columns(),
+            InvalidArgumentSnafu {
+                reason: format!(
+                    "Expect column index to be less than {}, found {}",
+                    self.num_columns(),
+                    idx
+                )
+            }
+        );
+        self.codec
+            .decode_value_at(bytes, idx, offsets)
+            .map_err(BoxedError::new)
+            .context(ExternalSnafu)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use common_time::Timestamp;
+
+    use super::*;
+
+    #[test]
+    fn test_row_codec() {
+        let codec = RowCodec::new([
+            ConcreteDataType::string_datatype(),
+            ConcreteDataType::int64_datatype(),
+            ConcreteDataType::timestamp_millisecond_datatype(),
+        ]);
+        let row = |host: &str, v: Option<i64>, ts: i64| {
+            Row::new(vec![
+                host.into(),
+                v.into(),
+                Timestamp::new_millisecond(ts).into(),
+            ])
+        };
+
+        let rows = [
+            row("a", Some(1), 2),
+            row("a", Some(2), 1),
+            row("b", None, 0),
+        ];
+        let mut buf = Vec::new();
+        for r in &rows {
+            buf.clear();
+            codec.encode_to(r, &mut buf).unwrap();
+            assert_eq!(codec.decode(&buf).unwrap(), *r);
+            let mut offsets = vec![];
+            assert_eq!(
+                codec.decode_column(&buf, 2, &mut offsets).unwrap(),
+                r.inner[2]
+            );
+            assert_eq!(
+                codec.decode_column(&buf, 0, &mut offsets).unwrap(),
+                r.inner[0]
+            );
+        }
+
+        // encoded rows are ordered the same as rows
+        let encoded = rows
+            .iter()
+            .map(|r| codec.encode(r).unwrap())
+            .collect::<Vec<_>>();
+        assert!(encoded.windows(2).all(|w| w[0] < w[1]));
+
+        assert!(codec.encode(&Row::new(vec![1i64.into()])).is_err());
+        assert!(codec.decode_column(&encoded[0], 3, &mut vec![]).is_err());
+    }
+}
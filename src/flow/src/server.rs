@@ -21,6 +21,7 @@ use api::v1::{RowDeleteRequests, RowInsertRequests};
 use cache::{TABLE_FLOWNODE_SET_CACHE_NAME, TABLE_ROUTE_CACHE_NAME};
 use catalog::CatalogManagerRef;
 use common_base::Plugins;
+use common_catalog::consts::default_engine;
 use common_error::ext::BoxedError;
 use common_meta::cache::{LayeredCacheRegistryRef, TableFlownodeSetCacheRef, TableRouteCacheRef};
 use common_meta::ddl::ProcedureExecutorRef;
@@ -28,28 +29,39 @@ use common_meta::key::flow::FlowMetadataManagerRef;
 use common_meta::key::TableMetadataManagerRef;
 use common_meta::kv_backend::KvBackendRef;
 use common_meta::node_manager::{Flownode, NodeManagerRef};
-use common_query::Output;
+use common_query::request::QueryRequest;
+use common_query::{Output, OutputData};
+use common_recordbatch::SendableRecordBatchStream;
+use common_runtime::JoinHandle;
+use common_telemetry::error;
 use common_telemetry::tracing::info;
 use futures::{FutureExt, TryStreamExt};
 use greptime_proto::v1::flow::{flow_server, FlowRequest, FlowResponse, InsertRequests};
 use itertools::Itertools;
 use operator::delete::Deleter;
+use operator::expr_factory::CreateExprFactory;
 use operator::insert::Inserter;
 use operator::statement::StatementExecutor;
 use partition::manager::PartitionRuleManager;
+use query::error::RegionQuerySnafu;
+use query::parser::QueryLanguageParser;
+use query::region_query::RegionQueryHandler;
 use query::stats::StatementStatistics;
 use query::{QueryEngine, QueryEngineFactory};
 use servers::error::{AlreadyStartedSnafu, StartGrpcSnafu, TcpBindSnafu, TcpIncomingSnafu};
 use servers::server::Server;
 use session::context::{QueryContextBuilder, QueryContextRef};
-use snafu::{ensure, OptionExt, ResultExt};
+use snafu::{ensure, IntoError, OptionExt, ResultExt};
+use table::table_reference::TableReference;
 use tokio::net::TcpListener;
 use tokio::sync::{broadcast, oneshot, Mutex};
 use tonic::codec::CompressionEncoding;
 use tonic::transport::server::TcpIncoming;
 use tonic::{Request, Response, Status};
 
-use crate::adapter::FlowWorkerManagerRef;
+use crate::adapter::checkpoint::CheckpointStore;
+use crate::adapter::worker::create_worker;
+use crate::adapter::{FlowOptions, FlowWorkerManagerRef, TableIdNameCacheRef};
 use crate::error::{
     CacheRequiredSnafu, ExternalSnafu, FlowNotFoundSnafu, ListFlowsSnafu, ParseAddrSnafu,
     ShutdownServerSnafu, StartServerSnafu, UnexpectedSnafu,
@@ -82,10 +94,7 @@ impl flow_server::Flow for FlowService {
             .handle(request)
             .await
             .map(Response::new)
-            .map_err(|e| {
-                let msg = format!("failed to handle request: {:?}", e);
-                Status::internal(msg)
-            })
+            .map_err(to_status)
     }
 
     async fn handle_mirror_request(
@@ -108,15 +117,20 @@ impl flow_server::Flow for FlowService {
             .handle_inserts(request)
             .await
             .map(Response::new)
-            .map_err(|e| {
-                let msg = format!("failed to handle request: {:?}", e);
-                Status::internal(msg)
-            })
+            .map_err(to_status)
     }
 }
 
+/// Convert an error of handling a request to a status, which carries the status code of the error
+/// in its header, so requesters(i.e. procedures of metasrv) can tell whether to retry
+fn to_status(err: common_meta::error::Error) -> Status {
+    ExternalSnafu.into_error(BoxedError::new(err)).into()
+}
+
 pub struct FlownodeServer {
     shutdown_tx: Mutex<Option<broadcast::Sender<()>>>,
+    /// the background task running flows, which drains the flownode on shutdown
+    run_handle: Mutex<Option<JoinHandle<()>>>,
     flow_service: FlowService,
 }
 
@@ -125,6 +139,7 @@ impl FlownodeServer {
         Self {
             flow_service,
             shutdown_tx: Mutex::new(None),
+            run_handle: Mutex::new(None),
         }
     }
 }
@@ -148,6 +163,12 @@ impl servers::server::Server for FlownodeServer {
                 info!("Receiver dropped, the flow node server has already shutdown");
             }
         }
+        // wait for flows to be drained, so results of accepted inserts are not lost
+        if let Some(handle) = self.run_handle.lock().await.take() {
+            if let Err(err) = handle.await {
+                error!(err; "Failed to wait for flows to be drained");
+            }
+        }
         info!("Shutdown flow node server");
 
         Ok(())
@@ -184,7 +205,8 @@ impl servers::server::Server for FlownodeServer {
         });
 
         let manager_ref = self.flow_service.manager.clone();
-        let _handle = manager_ref.clone().run_background(Some(rx));
+        let handle = manager_ref.clone().run_background(Some(rx));
+        *self.run_handle.lock().await = Some(handle);
 
         Ok(addr)
     }
@@ -237,6 +259,7 @@ pub struct FlownodeBuilder {
     catalog_manager: CatalogManagerRef,
     flow_metadata_manager: FlowMetadataManagerRef,
     heartbeat_task: Option<HeartbeatTask>,
+    table_id_name_cache: Option<TableIdNameCacheRef>,
 }
 
 impl FlownodeBuilder {
@@ -255,6 +278,7 @@ impl FlownodeBuilder {
             catalog_manager,
             flow_metadata_manager,
             heartbeat_task: None,
+            table_id_name_cache: None,
         }
     }
 
@@ -265,6 +289,15 @@ impl FlownodeBuilder {
         }
     }
 
+    /// Use a cache of table name <-> table id mapping which is registered to the node's cache
+    /// registry, so it's invalidated by DDL
+    pub fn with_table_id_name_cache(self, cache: TableIdNameCacheRef) -> Self {
+        Self {
+            table_id_name_cache: Some(cache),
+            ..self
+        }
+    }
+
     pub async fn build(self) -> Result<FlownodeInstance, Error> {
         // TODO(discord9): does this query engine need those?
         let query_engine_factory = QueryEngineFactory::new_with_plugins(
@@ -288,7 +321,9 @@ impl FlownodeBuilder {
 
         let server = FlownodeServer::new(FlowService::new(manager.clone()));
 
-        let heartbeat_task = self.heartbeat_task;
+        let heartbeat_task = self
+            .heartbeat_task
+            .map(|task| task.with_flow_worker_manager(manager.clone()));
 
         let addr = self.opts.grpc.addr;
         let instance = FlownodeInstance {
@@ -303,7 +338,7 @@ impl FlownodeBuilder {
     ///
     /// or recover all existing flow tasks if in standalone mode(nodeid is None)
     ///
-    /// TODO(discord9): persisent flow tasks with internal state
+    /// flows are resumed from their last checkpoints if any, see [`CheckpointStore`]
     async fn recover_flows(&self, manager: &FlowWorkerManagerRef) -> Result<usize, Error> {
         let nodeid = self.opts.node_id;
         let to_be_recovered: Vec<_> = if let Some(nodeid) = nodeid {
@@ -365,7 +400,7 @@ impl FlownodeBuilder {
                     info.expire_after(),
                     Some(info.comment().clone()),
                     info.raw_sql().clone(),
-                    info.options().clone(),
+                    FlowOptions::parse(info.options())?,
                     Some(
                         QueryContextBuilder::default()
                             .current_catalog(info.catalog_name().clone())
@@ -388,24 +423,41 @@ impl FlownodeBuilder {
 
         register_function_to_query_engine(&query_engine);
 
-        let (tx, rx) = oneshot::channel();
-
         let node_id = self.opts.node_id.map(|id| id as u32);
-        let _handle = std::thread::Builder::new()
-            .name("flow-worker".to_string())
-            .spawn(move || {
-                let (flow_node_manager, mut worker) =
-                    FlowWorkerManager::new_with_worker(node_id, query_engine, table_meta);
-                let _ = tx.send(flow_node_manager);
-                info!("Flow Worker started in new thread");
-                worker.run();
-            });
-        let man = rx.await.map_err(|_e| {
-            UnexpectedSnafu {
-                reason: "sender is dropped, failed to create flow node manager",
-            }
-            .build()
-        })?;
+        let mut man = FlowWorkerManager::new(node_id, query_engine, table_meta);
+        for worker_id in 0..self.opts.flow.num_workers.max(1) {
+            let (tx, rx) = oneshot::channel();
+            let _handle = std::thread::Builder::new()
+                .name(format!("flow-worker-{}", worker_id))
+                .spawn(move || {
+                    let (handle, mut worker) = create_worker();
+                    let _ = tx.send(handle);
+                    info!("Flow Worker {} started in new thread", worker_id);
+                    worker.run();
+                });
+            let handle = rx.await.map_err(|_e| {
+                UnexpectedSnafu {
+                    reason: "sender is dropped, failed to create flow worker",
+                }
+                .build()
+            })?;
+            man.add_worker_handle(handle);
+        }
+        if self.opts.checkpoint.enable {
+            man.set_checkpoint_store(CheckpointStore::new(
+                self.table_meta.kv_backend().clone(),
+                self.opts.checkpoint.clone(),
+            ));
+        }
+        if let Some(dir) = &self.opts.flow.spill_dir {
+            man.set_spill(dir.into(), self.opts.flow.state_memory_budget)?;
+        }
+        if let Some(table) = &self.opts.flow.error_table {
+            man.set_error_table(table);
+        }
+        if let Some(cache) = &self.table_id_name_cache {
+            man.set_table_id_name_cache(cache.clone());
+        }
         info!("Flow Node Manager started");
         Ok(man)
     }
@@ -415,6 +467,9 @@ pub struct FrontendInvoker {
     inserter: Arc<Inserter>,
     deleter: Arc<Deleter>,
     statement_executor: Arc<StatementExecutor>,
+    /// the query engine able to read data of tables, unlike the one in [`FlowWorkerManager`]
+    /// which is only used for planning flows
+    query_engine: Arc<dyn QueryEngine>,
 }
 
 impl FrontendInvoker {
@@ -422,11 +477,13 @@ impl FrontendInvoker {
         inserter: Arc<Inserter>,
         deleter: Arc<Deleter>,
         statement_executor: Arc<StatementExecutor>,
+        query_engine: Arc<dyn QueryEngine>,
     ) -> Self {
         Self {
             inserter,
             deleter,
             statement_executor,
+            query_engine,
         }
     }
 
@@ -457,15 +514,30 @@ impl FrontendInvoker {
             catalog_manager.clone(),
             partition_manager.clone(),
             node_manager.clone(),
-            table_flownode_cache,
+            table_flownode_cache.clone(),
         ));
 
         let deleter = Arc::new(Deleter::new(
             catalog_manager.clone(),
             partition_manager.clone(),
             node_manager.clone(),
+            table_flownode_cache,
         ));
 
+        let region_query_handler = Arc::new(FlowRegionQueryHandler {
+            partition_manager: partition_manager.clone(),
+            node_manager: node_manager.clone(),
+        });
+        let read_query_engine = QueryEngineFactory::new(
+            catalog_manager.clone(),
+            Some(region_query_handler),
+            None,
+            None,
+            None,
+            true,
+        )
+        .query_engine();
+
         let query_engine = flow_worker_manager.query_engine.clone();
 
         let statement_executor = Arc::new(StatementExecutor::new(
@@ -479,7 +551,8 @@ impl FrontendInvoker {
             StatementStatistics::default(),
         ));
 
-        let invoker = FrontendInvoker::new(inserter, deleter, statement_executor);
+        let invoker =
+            FrontendInvoker::new(inserter, deleter, statement_executor, read_query_engine);
         Ok(invoker)
     }
 }
@@ -508,4 +581,132 @@ impl FrontendInvoker {
             .map_err(BoxedError::new)
             .context(common_frontend::error::ExternalSnafu)
     }
+
+    /// Create the table with columns of `column_schemas` if it doesn't exist
+    pub async fn create_table(
+        &self,
+        table_name: &[String; 3],
+        column_schemas: &[api::v1::ColumnSchema],
+        desc: String,
+    ) -> common_frontend::error::Result<()> {
+        let [catalog, schema, table] = table_name;
+        let mut expr = CreateExprFactory
+            .create_table_expr_by_column_schemas(
+                &TableReference::full(catalog, schema, table),
+                column_schemas,
+                default_engine(),
+            )
+            .map_err(BoxedError::new)
+            .context(common_frontend::error::ExternalSnafu)?;
+        expr.desc = desc;
+        let query_ctx = QueryContextBuilder::default()
+            .current_catalog(catalog.clone())
+            .current_schema(schema.clone())
+            .build()
+            .into();
+        self.statement_executor
+            .create_table_inner(&mut expr, None, query_ctx)
+            .await
+            .map_err(BoxedError::new)
+            .context(common_frontend::error::ExternalSnafu)?;
+        Ok(())
+    }
+
+    /// Scan all rows of the table, with columns in the order of the table's schema
+    pub async fn scan_table(
+        &self,
+        table_name: &[String; 3],
+    ) -> Result<SendableRecordBatchStream, Error> {
+        let [catalog, schema, table] = table_name;
+        let query_ctx = QueryContextBuilder::default()
+            .current_catalog(catalog.clone())
+            .current_schema(schema.clone())
+            .build()
+            .into();
+        let quote = |name: &str| format!("\"{}\"", name.replace('"', "\"\""));
+        let sql = format!(
+            "SELECT * FROM {}.{}.{}",
+            quote(catalog),
+            quote(schema),
+            quote(table)
+        );
+        let stmt = QueryLanguageParser::parse_sql(&sql, &query_ctx)
+            .map_err(BoxedError::new)
+            .context(ExternalSnafu)?;
+        let plan = self
+            .query_engine
+            .planner()
+            .plan(stmt, query_ctx.clone())
+            .await
+            .map_err(BoxedError::new)
+            .context(ExternalSnafu)?;
+        let output = self
+            .query_engine
+            .execute(plan, query_ctx)
+            .await
+            .map_err(BoxedError::new)
+            .context(ExternalSnafu)?;
+        match output.data {
+            OutputData::Stream(stream) => Ok(stream),
+            OutputData::RecordBatches(batches) => Ok(batches.as_stream()),
+            OutputData::AffectedRows(_) => UnexpectedSnafu {
+                reason: format!("Expect rows of table {}, found affected rows", sql),
+            }
+            .fail(),
+        }
+    }
+}
+
+/// Reads regions of tables from the datanodes serving them, like the frontend does
+struct FlowRegionQueryHandler {
+    partition_manager: Arc<PartitionRuleManager>,
+    node_manager: NodeManagerRef,
+}
+
+#[async_trait::async_trait]
+impl RegionQueryHandler for FlowRegionQueryHandler {
+    async fn do_get(
+        &self,
+        request: QueryRequest,
+    ) -> query::error::Result<SendableRecordBatchStream> {
+        let region_id = request.region_id;
+        let peer = self
+            .partition_manager
+            .find_region_leader(region_id)
+            .await
+            .map_err(BoxedError::new)
+            .context(RegionQuerySnafu)?;
+        self.node_manager
+            .datanode(&peer)
+            .await
+            .handle_query(request)
+            .await
+            .map_err(BoxedError::new)
+            .context(RegionQuerySnafu)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use common_error::ext::ErrorExt;
+    use common_error::status_code::StatusCode;
+    use common_error::GREPTIME_DB_HEADER_ERROR_CODE;
+
+    use super::*;
+
+    #[test]
+    fn test_to_status() {
+        let err = BoxedError::new(FlowNotFoundSnafu { id: 1u64 }.build());
+        let err = common_meta::error::ExternalSnafu.into_error(err);
+        assert_eq!(err.status_code(), StatusCode::TableNotFound);
+
+        let status = to_status(err);
+        assert_eq!(status.code(), tonic::Code::NotFound);
+        let code = status
+            .metadata()
+            .get(GREPTIME_DB_HEADER_ERROR_CODE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u32>().ok());
+        assert_eq!(code, Some(StatusCode::TableNotFound as u32));
+    }
 }
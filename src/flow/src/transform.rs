@@ -52,8 +52,11 @@ mod aggr;
 mod expr;
 mod literal;
 mod plan;
+mod resolver;
+mod window;
 
 pub(crate) use expr::from_scalar_fn_to_df_fn_impl;
+pub use resolver::{register_scalar_fn_resolver, ScalarFnResolver};
 
 /// In Substrait, a function can be define by an u32 anchor, and the anchor can be mapped to a name
 ///
@@ -12,6 +12,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use datatypes::data_type::ConcreteDataType as CDT;
+use datatypes::value::Value;
 use itertools::Itertools;
 use snafu::OptionExt;
 use substrait_proto::proto::aggregate_function::AggregationInvocation;
@@ -27,44 +29,54 @@ use crate::plan::{AccumulablePlan, AggrWithIndex, KeyValPlan, Plan, ReducePlan,
 use crate::repr::{ColumnType, RelationDesc, RelationType};
 use crate::transform::{substrait_proto, FlownodeContext, FunctionExtensions};
 
+/// Name of the column appended to the output of grouping sets, to tell which grouping set a row belongs to
+pub const GROUPING_ID_COLUMN: &str = "__grouping_id";
+
 impl TypedExpr {
+    /// Convert groupings into a list of distinct group exprs, and each grouping set as indices into it
+    ///
+    /// the group exprs are in the order of their first appearance, same as the output of datafusion's aggregate
     async fn from_substrait_agg_grouping(
         ctx: &mut FlownodeContext,
         groupings: &[Grouping],
         typ: &RelationDesc,
         extensions: &FunctionExtensions,
-    ) -> Result<Vec<TypedExpr>, Error> {
+    ) -> Result<(Vec<TypedExpr>, Vec<Vec<usize>>), Error> {
         let _ = ctx;
-        let mut group_expr = vec![];
-        match groupings.len() {
-            1 => {
-                for e in &groupings[0].grouping_expressions {
-                    let x = TypedExpr::from_substrait_rex(e, typ, extensions).await?;
-                    group_expr.push(x);
-                }
-            }
-            _ => {
-                return not_impl_err!(
-                    "Grouping sets not support yet, use union all with group by instead."
-                );
+        let mut group_expr: Vec<TypedExpr> = vec![];
+        let mut grouping_sets = Vec::with_capacity(groupings.len());
+        for grouping in groupings {
+            let mut set = vec![];
+            for e in &grouping.grouping_expressions {
+                let x = TypedExpr::from_substrait_rex(e, typ, extensions).await?;
+                let idx = match group_expr.iter().position(|expr| *expr == x) {
+                    Some(idx) => idx,
+                    None => {
+                        group_expr.push(x);
+                        group_expr.len() - 1
+                    }
+                };
+                set.push(idx);
             }
-        };
-        Ok(group_expr)
+            grouping_sets.push(set);
+        }
+        Ok((group_expr, grouping_sets))
     }
 }
 
 impl AggregateExpr {
     /// Convert list of `Measure` into Flow's AggregateExpr
     ///
-    /// Return both the AggregateExpr and a MapFilterProject that is the final output of the aggregate function
+    /// Return both the AggregateExpr and the optional filter of each aggregate function(i.e. `FILTER (WHERE cond)`)
     async fn from_substrait_agg_measures(
         ctx: &mut FlownodeContext,
         measures: &[Measure],
         typ: &RelationDesc,
         extensions: &FunctionExtensions,
-    ) -> Result<Vec<AggregateExpr>, Error> {
+    ) -> Result<(Vec<AggregateExpr>, Vec<Option<ScalarExpr>>), Error> {
         let _ = ctx;
         let mut all_aggr_exprs = vec![];
+        let mut all_aggr_filters = vec![];
 
         for m in measures {
             let filter = match m
@@ -85,7 +97,7 @@ impl AggregateExpr {
                         _ => false,
                     };
                     AggregateExpr::from_substrait_agg_func(
-                        f, typ, extensions, &filter, // TODO(discord9): impl order_by
+                        f, typ, extensions, // TODO(discord9): impl order_by
                         &None, distinct,
                     )
                     .await?
@@ -95,10 +107,11 @@ impl AggregateExpr {
                 }
             };
 
+            all_aggr_filters.extend(aggr_expr.iter().map(|_| filter.clone().map(|f| f.expr)));
             all_aggr_exprs.extend(aggr_expr);
         }
 
-        Ok(all_aggr_exprs)
+        Ok((all_aggr_exprs, all_aggr_filters))
     }
 
     /// Convert AggregateFunction into Flow's AggregateExpr
@@ -109,12 +122,9 @@ impl AggregateExpr {
         f: &proto::AggregateFunction,
         input_schema: &RelationDesc,
         extensions: &FunctionExtensions,
-        filter: &Option<TypedExpr>,
         order_by: &Option<Vec<TypedExpr>>,
         distinct: bool,
     ) -> Result<Vec<AggregateExpr>, Error> {
-        // TODO(discord9): impl filter
-        let _ = filter;
         let _ = order_by;
         let mut args = vec![];
         for arg in &f.arguments {
@@ -166,12 +176,14 @@ impl AggregateExpr {
 impl KeyValPlan {
     /// Generate KeyValPlan from AggregateExpr and group_exprs
     ///
-    /// will also change aggregate expr to use column ref if necessary
+    /// will also change aggregate expr to use column ref if necessary,
+    /// and return the index of each aggregate's filter among the value row if it has one
     fn from_substrait_gen_key_val_plan(
         aggr_exprs: &mut [AggregateExpr],
+        aggr_filters: &[Option<ScalarExpr>],
         group_exprs: &[TypedExpr],
         input_arity: usize,
-    ) -> Result<KeyValPlan, Error> {
+    ) -> Result<(KeyValPlan, Vec<Option<usize>>), Error> {
         let group_expr_val = group_exprs
             .iter()
             .cloned()
@@ -184,11 +196,13 @@ impl KeyValPlan {
 
         // val_plan is extracted from aggr_exprs to give aggr function it's necessary input
         // and since aggr func need inputs that is column ref, we just add a prefix mfp to transform any expr that is not into a column ref
-        let val_plan = {
-            let need_mfp = aggr_exprs.iter().any(|agg| agg.expr.as_column().is_none());
+        // filters are evaluated in val_plan too, and placed after the aggr inputs
+        let (val_plan, filter_indices) = {
+            let need_mfp = aggr_exprs.iter().any(|agg| agg.expr.as_column().is_none())
+                || aggr_filters.iter().any(Option::is_some);
             if need_mfp {
                 // create mfp from aggr_expr, and modify aggr_expr to use the output column of mfp
-                let input_exprs = aggr_exprs
+                let mut input_exprs = aggr_exprs
                     .iter_mut()
                     .enumerate()
                     .map(|(idx, aggr)| {
@@ -197,20 +211,34 @@ impl KeyValPlan {
                         ret
                     })
                     .collect_vec();
-                let aggr_arity = aggr_exprs.len();
+                let mut filter_indices = Vec::with_capacity(aggr_filters.len());
+                for filter in aggr_filters {
+                    filter_indices.push(filter.as_ref().map(|filter| {
+                        input_exprs.push(filter.clone());
+                        input_exprs.len() - 1
+                    }));
+                }
+                let val_arity = input_exprs.len();
 
-                MapFilterProject::new(input_arity)
+                let val_plan = MapFilterProject::new(input_arity)
                     .map(input_exprs)?
-                    .project(input_arity..input_arity + aggr_arity)?
+                    .project(input_arity..input_arity + val_arity)?;
+                (val_plan, filter_indices)
             } else {
                 // simply take all inputs as value
-                MapFilterProject::new(input_arity)
+                (
+                    MapFilterProject::new(input_arity),
+                    vec![None; aggr_exprs.len()],
+                )
             }
         };
-        Ok(KeyValPlan {
-            key_plan: key_plan.into_safe(),
-            val_plan: val_plan.into_safe(),
-        })
+        Ok((
+            KeyValPlan {
+                key_plan: key_plan.into_safe(),
+                val_plan: val_plan.into_safe(),
+            },
+            filter_indices,
+        ))
     }
 }
 
@@ -234,6 +262,8 @@ impl TypedPlan {
     /// The output of aggr plan is:
     ///
     /// <group_exprs>..<aggr_exprs>
+    ///
+    /// with multiple grouping sets(i.e. `ROLLUP`), see [`TypedPlan::reduce_by_grouping_sets`] for the output
     #[async_recursion::async_recursion]
     pub async fn from_substrait_agg_rel(
         ctx: &mut FlownodeContext,
@@ -246,13 +276,11 @@ impl TypedPlan {
             return not_impl_err!("Aggregate without an input is not supported");
         };
 
-        let group_exprs =
+        let (group_exprs, grouping_sets) =
             TypedExpr::from_substrait_agg_grouping(ctx, &agg.groupings, &input.schema, extensions)
                 .await?;
 
-        let time_index = find_time_index_in_group_exprs(&group_exprs);
-
-        let mut aggr_exprs = AggregateExpr::from_substrait_agg_measures(
+        let (aggr_exprs, aggr_filters) = AggregateExpr::from_substrait_agg_measures(
             ctx,
             &agg.measures,
             &input.schema,
@@ -260,10 +288,114 @@ impl TypedPlan {
         )
         .await?;
 
-        let key_val_plan = KeyValPlan::from_substrait_gen_key_val_plan(
+        if grouping_sets.len() <= 1 {
+            let group_exprs = grouping_sets
+                .first()
+                .map(|set| set.iter().map(|i| group_exprs[*i].clone()).collect_vec())
+                .unwrap_or_default();
+            return input.reduce_by_group(group_exprs, aggr_exprs, aggr_filters);
+        }
+        input.reduce_by_grouping_sets(group_exprs, grouping_sets, aggr_exprs, aggr_filters)
+    }
+
+    /// Reduce grouped by each grouping set, and union the results together
+    ///
+    /// The output of each reduce is aligned to:
+    ///
+    /// <group_exprs>..<aggr_exprs>..<grouping_id>
+    ///
+    /// where group exprs not in the grouping set are null, and `grouping_id` is the index of
+    /// the grouping set, so rows from different grouping sets never share the same key
+    fn reduce_by_grouping_sets(
+        self,
+        group_exprs: Vec<TypedExpr>,
+        grouping_sets: Vec<Vec<usize>>,
+        aggr_exprs: Vec<AggregateExpr>,
+        aggr_filters: Vec<Option<ScalarExpr>>,
+    ) -> Result<TypedPlan, Error> {
+        let mut inputs = Vec::with_capacity(grouping_sets.len());
+        for (grouping_id, set) in grouping_sets.iter().enumerate() {
+            let set_exprs = set.iter().map(|i| group_exprs[*i].clone()).collect_vec();
+            let reduce = self.clone().reduce_by_group(
+                set_exprs,
+                aggr_exprs.clone(),
+                aggr_filters.clone(),
+            )?;
+
+            let mut exprs = Vec::with_capacity(group_exprs.len() + aggr_exprs.len() + 1);
+            for (i, group_expr) in group_exprs.iter().enumerate() {
+                let typ = ColumnType::new_nullable(group_expr.typ.scalar_type.clone());
+                let expr = match set.iter().position(|j| *j == i) {
+                    Some(pos) => ScalarExpr::Column(pos),
+                    None => ScalarExpr::Literal(Value::Null, typ.scalar_type.clone()),
+                };
+                exprs.push(TypedExpr::new(expr, typ));
+            }
+            for i in set.len()..set.len() + aggr_exprs.len() {
+                let typ = reduce.schema.typ.column_types[i].clone();
+                exprs.push(TypedExpr::new(ScalarExpr::Column(i), typ));
+            }
+            exprs.push(TypedExpr::new(
+                ScalarExpr::Literal(Value::from(grouping_id as u32), CDT::uint32_datatype()),
+                ColumnType::new(CDT::uint32_datatype(), false),
+            ));
+            inputs.push(reduce.projection(exprs)?);
+        }
+
+        let mut output_types = Vec::new();
+        let mut output_names = Vec::new();
+        for expr in &group_exprs {
+            output_types.push(ColumnType::new_nullable(expr.typ.scalar_type.clone()));
+            let col_name = match &expr.expr {
+                ScalarExpr::Column(col) => self.schema.get_name(*col).clone(),
+                _ => None,
+            };
+            output_names.push(col_name);
+        }
+        for aggr in &aggr_exprs {
+            output_types.push(ColumnType::new_nullable(
+                aggr.func.signature().output.clone(),
+            ));
+            output_names.push(None);
+        }
+        output_types.push(ColumnType::new(CDT::uint32_datatype(), false));
+        output_names.push(Some(GROUPING_ID_COLUMN.to_string()));
+
+        // the time window is only a time index if every grouping set contains it
+        let time_index = find_time_index_in_group_exprs(&group_exprs)
+            .filter(|idx| grouping_sets.iter().all(|set| set.contains(idx)));
+        let mut key = (0..group_exprs.len()).collect_vec();
+        key.push(group_exprs.len() + aggr_exprs.len());
+        let schema = RelationType::new(output_types)
+            .with_key(key)
+            .with_time_index(time_index)
+            .into_named(output_names);
+
+        Ok(TypedPlan {
+            schema,
+            plan: Plan::Union {
+                inputs,
+                consolidate_output: false,
+            },
+        })
+    }
+
+    /// Reduce grouped by `group_exprs`, the output is:
+    ///
+    /// <group_exprs>..<aggr_exprs>
+    fn reduce_by_group(
+        self,
+        group_exprs: Vec<TypedExpr>,
+        mut aggr_exprs: Vec<AggregateExpr>,
+        aggr_filters: Vec<Option<ScalarExpr>>,
+    ) -> Result<TypedPlan, Error> {
+        let time_index = find_time_index_in_group_exprs(&group_exprs);
+
+        let (key_val_plan, filter_indices) = KeyValPlan::from_substrait_gen_key_val_plan(
             &mut aggr_exprs,
+            &aggr_filters,
             &group_exprs,
-            input.schema.typ.column_types.len(),
+            self.schema.typ.column_types.len(),
         )?;
 
         // output type is group_exprs + aggr_exprs
@@ -276,7 +408,7 @@ impl TypedPlan {
             for expr in group_exprs.iter() {
                 output_types.push(expr.typ.clone());
                 let col_name = match &expr.expr {
-                    ScalarExpr::Column(col) => input.schema.get_name(*col).clone(),
+                    ScalarExpr::Column(col) => self.schema.get_name(*col).clone(),
                     // TODO(discord9): impl& use ScalarExpr.display_name, which recursively build expr's name
                     _ => None,
                 };
@@ -300,6 +432,19 @@ impl TypedPlan {
             .into_named(output_names)
         };
 
+        // no aggregation at all, i.e. `SELECT DISTINCT` or `GROUP BY` without aggregate functions
+        if aggr_exprs.is_empty() {
+            let plan = Plan::Reduce {
+                input: Box::new(self),
+                key_val_plan,
+                reduce_plan: ReducePlan::Distinct,
+            };
+            return Ok(TypedPlan {
+                schema: output_type,
+                plan,
+            });
+        }
+
         // copy aggr_exprs to full_aggrs, and split them into simple_aggrs and distinct_aggrs
         // also set them input/output column
         let full_aggrs = aggr_exprs;
@@ -309,18 +454,21 @@ impl TypedPlan {
             let input_column = aggr_expr.expr.as_column().with_context(|| PlanSnafu {
                 reason: "Expect aggregate argument to be transformed into a column at this point",
             })?;
+            let filter_idx = filter_indices[output_column];
             if aggr_expr.distinct {
+                if filter_idx.is_some() {
+                    return not_impl_err!("FILTER on DISTINCT aggregation is not supported yet");
+                }
                 distinct_aggrs.push(AggrWithIndex::new(
                     aggr_expr.clone(),
                     input_column,
                     output_column,
                 ));
             } else {
-                simple_aggrs.push(AggrWithIndex::new(
-                    aggr_expr.clone(),
-                    input_column,
-                    output_column,
-                ));
+                simple_aggrs.push(
+                    AggrWithIndex::new(aggr_expr.clone(), input_column, output_column)
+                        .with_filter(filter_idx),
+                );
             }
         }
         let accum_plan = AccumulablePlan {
@@ -329,16 +477,16 @@ impl TypedPlan {
             distinct_aggrs,
         };
         let plan = Plan::Reduce {
-            input: Box::new(input),
+            input: Box::new(self),
             key_val_plan,
             reduce_plan: ReducePlan::Accumulable(accum_plan),
         };
         // FIX(discord9): deal with key first
 
-        return Ok(TypedPlan {
+        Ok(TypedPlan {
             schema: output_type,
             plan,
-        });
+        })
     }
 }
 
@@ -350,7 +498,6 @@ mod test {
     use bytes::BytesMut;
     use common_time::{IntervalMonthDayNano, Timestamp};
     use datatypes::prelude::ConcreteDataType;
-    use datatypes::value::Value;
     use pretty_assertions::assert_eq;
 
     use super::*;
@@ -358,7 +505,6 @@ mod test {
     use crate::plan::{Plan, TypedPlan};
     use crate::repr::{ColumnType, RelationType};
     use crate::transform::test::{create_test_ctx, create_test_query_engine, sql_to_substrait};
-    use crate::transform::CDT;
 
     #[tokio::test]
     async fn test_df_func_basic() {
@@ -1134,6 +1280,144 @@ mod test {
         assert_eq!(flow_plan.unwrap(), expected);
     }
 
+    #[tokio::test]
+    async fn test_having() {
+        let engine = create_test_query_engine();
+        let sql = "SELECT count(*) FROM numbers GROUP BY number HAVING count(*) > 10";
+        let plan = sql_to_substrait(engine.clone(), sql).await;
+
+        let mut ctx = create_test_ctx();
+        let flow_plan = TypedPlan::from_substrait_plan(&mut ctx, &plan)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            flow_plan.schema.typ().column_types,
+            vec![ColumnType::new(CDT::int64_datatype(), true)]
+        );
+        // the filter is fused into the mfp right after reduce
+        let Plan::Mfp { input, mfp } = flow_plan.plan else {
+            panic!("Expect a mfp plan, found {:?}", flow_plan.plan);
+        };
+        assert!(matches!(input.plan, Plan::Reduce { .. }));
+        assert_eq!(mfp.predicates.len(), 1);
+        let (_, predicate) = &mfp.predicates[0];
+        assert!(matches!(
+            predicate,
+            ScalarExpr::CallBinary {
+                func: BinaryFunc::Gt,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_distinct() {
+        let engine = create_test_query_engine();
+        let sql = "SELECT DISTINCT number FROM numbers";
+        let plan = sql_to_substrait(engine.clone(), sql).await;
+
+        let mut ctx = create_test_ctx();
+        let flow_plan = TypedPlan::from_substrait_plan(&mut ctx, &plan)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            flow_plan.schema.typ().column_types,
+            vec![ColumnType::new(CDT::uint32_datatype(), false)]
+        );
+        let reduce = match flow_plan.plan {
+            Plan::Mfp { input, .. } => input.plan,
+            plan => plan,
+        };
+        let Plan::Reduce { reduce_plan, .. } = reduce else {
+            panic!("Expect a reduce plan, found {:?}", reduce);
+        };
+        assert_eq!(reduce_plan, ReducePlan::Distinct);
+    }
+
+    #[tokio::test]
+    async fn test_sum_filter() {
+        let engine = create_test_query_engine();
+        let sql = "SELECT sum(number) FILTER (WHERE number > 5) FROM numbers";
+        let plan = sql_to_substrait(engine.clone(), sql).await;
+
+        let mut ctx = create_test_ctx();
+        let flow_plan = TypedPlan::from_substrait_plan(&mut ctx, &plan)
+            .await
+            .unwrap();
+
+        let reduce = match flow_plan.plan {
+            Plan::Mfp { input, .. } => input.plan,
+            plan => plan,
+        };
+        let Plan::Reduce {
+            key_val_plan,
+            reduce_plan: ReducePlan::Accumulable(accum_plan),
+            ..
+        } = reduce
+        else {
+            panic!("Expect an accumulable reduce plan, found {:?}", reduce);
+        };
+        // value row is (number, number > 5)
+        assert_eq!(key_val_plan.val_plan.output_arity(), 2);
+        assert_eq!(accum_plan.simple_aggrs.len(), 1);
+        assert_eq!(accum_plan.simple_aggrs[0].input_idx, 0);
+        assert_eq!(accum_plan.simple_aggrs[0].filter_idx, Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_rollup() {
+        let engine = create_test_query_engine();
+        let sql = "SELECT number, count(*) FROM numbers GROUP BY ROLLUP(number)";
+        let plan = sql_to_substrait(engine.clone(), sql).await;
+
+        let mut ctx = create_test_ctx();
+        let flow_plan = TypedPlan::from_substrait_plan(&mut ctx, &plan)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            flow_plan.schema.typ().column_types,
+            vec![
+                ColumnType::new(CDT::uint32_datatype(), true),
+                ColumnType::new(CDT::int64_datatype(), true),
+            ]
+        );
+        let Plan::Mfp { input, .. } = flow_plan.plan else {
+            panic!(
+                "Expect a projection on top of union, found {:?}",
+                flow_plan.plan
+            );
+        };
+        // group column is nullable since it's null in the `()` grouping set
+        assert_eq!(
+            input.schema.typ().column_types,
+            vec![
+                ColumnType::new(CDT::uint32_datatype(), true),
+                ColumnType::new(CDT::int64_datatype(), true),
+                ColumnType::new(CDT::uint32_datatype(), false),
+            ]
+        );
+        assert_eq!(
+            input.schema.get_name(2),
+            &Some(GROUPING_ID_COLUMN.to_string())
+        );
+        let Plan::Union { inputs, .. } = input.plan else {
+            panic!("Expect a union of reduces, found {:?}", input.plan);
+        };
+        assert_eq!(inputs.len(), 2);
+        for input in inputs {
+            let Plan::Mfp { input, .. } = input.plan else {
+                panic!(
+                    "Expect a projection on top of reduce, found {:?}",
+                    input.plan
+                );
+            };
+            assert!(matches!(input.plan, Plan::Reduce { .. }));
+        }
+    }
+
     #[tokio::test]
     async fn test_avg() {
         let engine = create_test_query_engine();
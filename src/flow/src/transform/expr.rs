@@ -18,6 +18,7 @@ use std::sync::Arc;
 
 use common_error::ext::BoxedError;
 use common_telemetry::debug;
+use datafusion_expr::type_coercion::binary::comparison_coercion;
 use datafusion_physical_expr::PhysicalExpr;
 use datatypes::data_type::ConcreteDataType as CDT;
 use snafu::{ensure, OptionExt, ResultExt};
@@ -39,6 +40,7 @@ use crate::repr::{ColumnType, RelationDesc, RelationType};
 use crate::transform::literal::{
     from_substrait_literal, from_substrait_type, to_substrait_literal,
 };
+use crate::transform::resolver::find_scalar_fn_resolver;
 use crate::transform::{substrait_proto, FunctionExtensions};
 
 // TODO(discord9): refactor plan to substrait convert of `arrow_cast` function thus remove this function
@@ -138,6 +140,63 @@ pub(crate) fn proto_col(i: usize) -> substrait_proto::proto::FunctionArgument {
     }
 }
 
+/// Find the common type of all branches of a `CASE WHEN`, so every branch can be cast to it
+///
+/// branch of null type(i.e. a `NULL` literal or a missing `ELSE`) is ignored when unifying
+fn unify_branch_types<'a>(
+    types: impl IntoIterator<Item = &'a ColumnType>,
+) -> Result<ColumnType, Error> {
+    let mut nullable = false;
+    let mut unified: Option<CDT> = None;
+    for typ in types {
+        nullable |= typ.nullable;
+        if typ.scalar_type.is_null() {
+            continue;
+        }
+        unified = match unified {
+            None => Some(typ.scalar_type.clone()),
+            Some(prev) if prev == typ.scalar_type => Some(prev),
+            Some(prev) => {
+                let coerced =
+                    comparison_coercion(&prev.as_arrow_type(), &typ.scalar_type.as_arrow_type())
+                        .with_context(|| InvalidQuerySnafu {
+                            reason: format!(
+                                "Can't find a common type for branches of type {:?} and {:?}",
+                                prev, typ.scalar_type
+                            ),
+                        })?;
+                let coerced = CDT::try_from(&coerced)
+                    .map_err(BoxedError::new)
+                    .context(ExternalSnafu)?;
+                Some(coerced)
+            }
+        };
+    }
+    Ok(ColumnType::new(
+        unified.unwrap_or_else(CDT::null_datatype),
+        nullable,
+    ))
+}
+
+/// Cast a branch of `CASE WHEN` to the unified type, literal is cast in place
+fn cast_branch(branch: TypedExpr, dest: &ColumnType) -> Result<TypedExpr, Error> {
+    let dest_type = &dest.scalar_type;
+    let expr = match branch.expr {
+        expr if branch.typ.scalar_type == *dest_type || dest_type.is_null() => expr,
+        ScalarExpr::Literal(val, _) if val.is_null() => ScalarExpr::Literal(val, dest_type.clone()),
+        ScalarExpr::Literal(val, _) => {
+            let dest_val = datatypes::types::cast(val.clone(), dest_type).with_context(|_| {
+                DatatypesSnafu {
+                    extra: format!("Failed to cast literal {val:?} to type {dest_type:?}"),
+                }
+            })?;
+            ScalarExpr::Literal(dest_val, dest_type.clone())
+        }
+        expr => expr.cast(dest_type.clone()),
+    };
+    Ok(TypedExpr::new(expr, dest.clone()))
+}
+
 fn is_proto_literal(arg: &substrait_proto::proto::FunctionArgument) -> bool {
     use substrait_proto::proto::expression;
     matches!(
@@ -287,6 +346,11 @@ impl TypedExpr {
             )
             .unzip();
 
+        let lower_fn_name = fn_name.to_lowercase();
+        if let Some(resolver) = find_scalar_fn_resolver(&lower_fn_name) {
+            return resolver.resolve(&lower_fn_name, arg_typed_exprs);
+        }
+
         match arg_len {
             1 if UnaryFunc::is_valid_func_name(fn_name) => {
                 let func = UnaryFunc::from_str_and_type(fn_name, None)?;
@@ -361,6 +425,16 @@ impl TypedExpr {
                 Ok(TypedExpr::new(ret_expr, ret_type))
             }
             _var => {
+                if UnaryFunc::is_regex_func_name(fn_name) {
+                    // only regex with literal pattern can be compiled ahead of time, otherwise fallback to datafusion
+                    if let Some((func, arg)) =
+                        UnaryFunc::from_regex_func(fn_name, &arg_typed_exprs)?
+                    {
+                        let ret_type = ColumnType::new_nullable(func.signature().output.clone());
+                        return Ok(TypedExpr::new(arg.expr.call_unary(func), ret_type));
+                    }
+                }
+
                 if fn_name == TUMBLE_START || fn_name == TUMBLE_END {
                     let (func, arg) = UnaryFunc::from_tumble_func(fn_name, &arg_typed_exprs)?;
 
@@ -369,7 +443,15 @@ impl TypedExpr {
                     Ok(TypedExpr::new(arg.expr.call_unary(func), ret_type))
                 } else if VariadicFunc::is_valid_func_name(fn_name) {
                     let func = VariadicFunc::from_str_and_types(fn_name, &arg_types)?;
-                    let ret_type = ColumnType::new_nullable(func.signature().output.clone());
+                    let (arg_exprs, ret_type) = match func {
+                        VariadicFunc::Coalesce | VariadicFunc::NullIf => {
+                            Self::unify_variadic_args(func, arg_typed_exprs)?
+                        }
+                        _ => (
+                            arg_exprs,
+                            ColumnType::new_nullable(func.signature().output.clone()),
+                        ),
+                    };
                     let mut expr = ScalarExpr::CallVariadic {
                         func,
                         exprs: arg_exprs,
@@ -396,6 +478,83 @@ impl TypedExpr {
         }
     }
 
+    /// Cast arguments of `coalesce`/`nullif` to the same type, and return the output type
+    ///
+    /// `coalesce` is only nullable if all arguments are nullable, while `nullif` is always nullable
+    fn unify_variadic_args(
+        func: VariadicFunc,
+        args: Vec<TypedExpr>,
+    ) -> Result<(Vec<ScalarExpr>, ColumnType), Error> {
+        let unified = match (func, args.first()) {
+            // the output of `nullif` is always of the type of the first argument
+            (VariadicFunc::NullIf, Some(first)) if !first.typ.scalar_type.is_null() => {
+                first.typ.clone()
+            }
+            _ => unify_branch_types(args.iter().map(|arg| &arg.typ))?,
+        };
+        let nullable = match func {
+            VariadicFunc::Coalesce => args.iter().all(|arg| arg.typ.nullable),
+            _ => true,
+        };
+        let exprs = args
+            .into_iter()
+            .map(|arg| cast_branch(arg, &unified).map(|arg| arg.expr))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok((exprs, ColumnType::new(unified.scalar_type, nullable)))
+    }
+
+    /// Convert `value IN (options)` into Flow's ScalarExpr
+    ///
+    /// If all options are literals, they are cast to the type of `value` and tested with a sorted list,
+    /// otherwise fallback to `value = option_0 OR value = option_1 ...`
+    fn from_in_list(value: TypedExpr, options: Vec<TypedExpr>) -> Result<TypedExpr, Error> {
+        let value_type = value.typ.scalar_type.clone();
+        let ret_type = ColumnType::new_nullable(CDT::boolean_datatype());
+        let all_literal = options.iter().all(|option| option.expr.is_literal());
+
+        if !all_literal || value_type.is_null() {
+            let exprs = options
+                .into_iter()
+                .map(|option| value.expr.clone().call_binary(option.expr, BinaryFunc::Eq))
+                .collect();
+            let expr = ScalarExpr::CallVariadic {
+                func: VariadicFunc::Or,
+                exprs,
+            };
+            return Ok(TypedExpr::new(expr, ret_type));
+        }
+
+        let mut list = Vec::with_capacity(options.len());
+        for option in options {
+            let Some(val) = option.expr.as_literal() else {
+                unreachable!("Already checked all options are literal")
+            };
+            let val = if val.is_null() {
+                val
+            } else {
+                datatypes::types::cast(val.clone(), &value_type).with_context(|_| {
+                    DatatypesSnafu {
+                        extra: format!(
+                            "Failed to implicitly cast literal {val:?} in IN list to type {value_type:?}"
+                        ),
+                    }
+                })?
+            };
+            list.push(val);
+        }
+        let func = UnaryFunc::in_list(list);
+
+        // constant folding here
+        if value.expr.is_literal() {
+            let res = func.eval(&[], &value.expr).context(EvalSnafu)?;
+            return Ok(TypedExpr::new(
+                ScalarExpr::Literal(res, CDT::boolean_datatype()),
+                ret_type,
+            ));
+        }
+        Ok(TypedExpr::new(value.expr.call_unary(func), ret_type))
+    }
+
     /// Convert IfThen into Flow's ScalarExpr
     pub async fn from_substrait_ifthen_rex(
         if_then: &IfThen,
@@ -436,12 +595,24 @@ impl TypedExpr {
             )
         });
 
+        // all branches must be of the same type, otherwise the evaluated vectors can't be merged
+        let ret_type = unify_branch_types(
+            ifs.iter()
+                .map(|(_, then)| &then.typ)
+                .chain(std::iter::once(&els.typ)),
+        )?;
+        let ifs = ifs
+            .into_iter()
+            .map(|(cond, then)| Ok((cond, cast_branch(then, &ret_type)?)))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let els = cast_branch(els, &ret_type)?;
+
         fn build_if_then_recur(
             mut next_if_then: impl Iterator<Item = (TypedExpr, TypedExpr)>,
             els: TypedExpr,
         ) -> TypedExpr {
             if let Some((cond, then)) = next_if_then.next() {
-                // always assume the type of `if`` expr is the same with the `then`` expr
+                // all branches are already unified to the same type
                 TypedExpr::new(
                     ScalarExpr::If {
                         cond: Box::new(cond.expr),
@@ -476,11 +647,18 @@ impl TypedExpr {
                 let substrait_expr = s.value.as_ref().with_context(|| InvalidQuerySnafu {
                     reason: "SingularOrList expression without value",
                 })?;
-                // Note that we didn't impl support to in list expr
-                if !s.options.is_empty() {
-                    return not_impl_err!("In list expression is not supported");
+                let value =
+                    TypedExpr::from_substrait_rex(substrait_expr, input_schema, extensions).await?;
+                if s.options.is_empty() {
+                    return Ok(value);
+                }
+                let mut options = Vec::with_capacity(s.options.len());
+                for option in s.options.iter() {
+                    options.push(
+                        TypedExpr::from_substrait_rex(option, input_schema, extensions).await?,
+                    );
                 }
-                TypedExpr::from_substrait_rex(substrait_expr, input_schema, extensions).await
+                TypedExpr::from_in_list(value, options)
             }
             Some(RexType::Selection(field_ref)) => match &field_ref.reference_type {
                 Some(DirectReference(direct)) => match &direct.reference_type.as_ref() {
@@ -525,9 +703,8 @@ impl TypedExpr {
                 ))
             }
             Some(RexType::WindowFunction(_)) => PlanSnafu {
-                reason:
-                    "Window function is not supported yet. Please use aggregation function instead."
-                        .to_string(),
+                reason: "Window function is only supported as a top level expression of projection"
+                    .to_string(),
             }
             .fail(),
             _ => not_impl_err!("unsupported rex_type"),
@@ -539,10 +716,11 @@ impl TypedExpr {
 mod test {
     use datatypes::prelude::ConcreteDataType;
     use datatypes::value::Value;
+    use datatypes::vectors::Vector;
     use pretty_assertions::assert_eq;
 
     use super::*;
-    use crate::expr::{GlobalId, MapFilterProject};
+    use crate::expr::{CachedRegex, GlobalId, MapFilterProject};
     use crate::plan::{Plan, TypedPlan};
     use crate::repr::{self, ColumnType, RelationType};
     use crate::transform::test::{create_test_ctx, create_test_query_engine, sql_to_substrait};
@@ -557,24 +735,63 @@ mod test {
         let mut ctx = create_test_ctx();
         let flow_plan = TypedPlan::from_substrait_plan(&mut ctx, &plan).await;
 
-        // optimize binary and to variadic and
-        let filter = ScalarExpr::CallVariadic {
-            func: VariadicFunc::And,
-            exprs: vec![
-                ScalarExpr::Column(0).call_binary(
-                    ScalarExpr::Literal(Value::from(1u32), CDT::uint32_datatype()),
-                    BinaryFunc::Gte,
-                ),
-                ScalarExpr::Column(0).call_binary(
-                    ScalarExpr::Literal(Value::from(3u32), CDT::uint32_datatype()),
-                    BinaryFunc::Lte,
-                ),
-                ScalarExpr::Column(0).call_binary(
-                    ScalarExpr::Literal(Value::from(2u32), CDT::uint32_datatype()),
-                    BinaryFunc::NotEq,
+        // nested binary and is flattened into separated predicates
+        let filters = vec![
+            ScalarExpr::Column(0).call_binary(
+                ScalarExpr::Literal(Value::from(1u32), CDT::uint32_datatype()),
+                BinaryFunc::Gte,
+            ),
+            ScalarExpr::Column(0).call_binary(
+                ScalarExpr::Literal(Value::from(3u32), CDT::uint32_datatype()),
+                BinaryFunc::Lte,
+            ),
+            ScalarExpr::Column(0).call_binary(
+                ScalarExpr::Literal(Value::from(2u32), CDT::uint32_datatype()),
+                BinaryFunc::NotEq,
+            ),
+        ];
+        let expected = TypedPlan {
+            schema: RelationType::new(vec![ColumnType::new(CDT::uint32_datatype(), false)])
+                .into_named(vec![Some("numbers.number".to_string())]),
+            plan: Plan::Mfp {
+                input: Box::new(
+                    Plan::Get {
+                        id: crate::expr::Id::Global(GlobalId::User(0)),
+                    }
+                    .with_types(
+                        RelationType::new(vec![ColumnType::new(
+                            ConcreteDataType::uint32_datatype(),
+                            false,
+                        )])
+                        .into_named(vec![Some("number".to_string())]),
+                    ),
                 ),
-            ],
+                mfp: MapFilterProject::new(1).filter(filters).unwrap(),
+            },
         };
+        assert_eq!(flow_plan.unwrap(), expected);
+    }
+
+    /// test if `BETWEEN` is simplified into `>= AND <=`, which is converted to a normal filter
+    #[tokio::test]
+    async fn test_where_between() {
+        let engine = create_test_query_engine();
+        let sql = "SELECT number FROM numbers WHERE number BETWEEN 1 AND 3";
+        let plan = sql_to_substrait(engine.clone(), sql).await;
+
+        let mut ctx = create_test_ctx();
+        let flow_plan = TypedPlan::from_substrait_plan(&mut ctx, &plan).await;
+
+        let filters = vec![
+            ScalarExpr::Column(0).call_binary(
+                ScalarExpr::Literal(Value::from(1u32), CDT::uint32_datatype()),
+                BinaryFunc::Gte,
+            ),
+            ScalarExpr::Column(0).call_binary(
+                ScalarExpr::Literal(Value::from(3u32), CDT::uint32_datatype()),
+                BinaryFunc::Lte,
+            ),
+        ];
         let expected = TypedPlan {
             schema: RelationType::new(vec![ColumnType::new(CDT::uint32_datatype(), false)])
                 .into_named(vec![Some("numbers.number".to_string())]),
@@ -591,12 +808,102 @@ mod test {
                         .into_named(vec![Some("number".to_string())]),
                     ),
                 ),
-                mfp: MapFilterProject::new(1).filter(vec![filter]).unwrap(),
+                mfp: MapFilterProject::new(1).filter(filters).unwrap(),
             },
         };
         assert_eq!(flow_plan.unwrap(), expected);
     }
 
+    #[tokio::test]
+    async fn test_in_list() {
+        use substrait_proto::proto::expression::literal::LiteralType;
+        use substrait_proto::proto::expression::SingularOrList;
+
+        fn col(i: usize) -> Expression {
+            match proto_col(i).arg_type {
+                Some(ArgType::Value(e)) => e,
+                _ => unreachable!(),
+            }
+        }
+        fn lit_i64(v: i64) -> Expression {
+            Expression {
+                rex_type: Some(RexType::Literal(
+                    substrait_proto::proto::expression::Literal {
+                        nullable: false,
+                        type_variation_reference: 0,
+                        literal_type: Some(LiteralType::I64(v)),
+                    },
+                )),
+            }
+        }
+        fn in_list(value: Expression, options: Vec<Expression>) -> Expression {
+            Expression {
+                rex_type: Some(RexType::SingularOrList(Box::new(SingularOrList {
+                    value: Some(Box::new(value)),
+                    options,
+                }))),
+            }
+        }
+
+        let input_schema = RelationType::new(vec![
+            ColumnType::new(CDT::uint32_datatype(), false),
+            ColumnType::new(CDT::uint32_datatype(), false),
+        ])
+        .into_unnamed();
+        let extensions = FunctionExtensions::from_iter(Vec::<(u32, String)>::new());
+
+        // c0 IN (3, 1, 2), literals are cast to the type of c0 and sorted
+        let expr = in_list(col(0), vec![lit_i64(3), lit_i64(1), lit_i64(2)]);
+        let res = TypedExpr::from_substrait_rex(&expr, &input_schema, &extensions)
+            .await
+            .unwrap();
+        assert_eq!(
+            res,
+            TypedExpr::new(
+                ScalarExpr::Column(0).call_unary(UnaryFunc::InList {
+                    list: vec![Value::from(1u32), Value::from(2u32), Value::from(3u32)],
+                    contains_null: false,
+                }),
+                ColumnType::new_nullable(CDT::boolean_datatype())
+            )
+        );
+
+        // c0 IN (1, c1) fallback to OR chain
+        let expr = in_list(col(0), vec![lit_i64(1), col(1)]);
+        let res = TypedExpr::from_substrait_rex(&expr, &input_schema, &extensions)
+            .await
+            .unwrap();
+        assert_eq!(
+            res,
+            TypedExpr::new(
+                ScalarExpr::CallVariadic {
+                    func: VariadicFunc::Or,
+                    exprs: vec![
+                        ScalarExpr::Column(0).call_binary(
+                            ScalarExpr::Literal(Value::from(1i64), CDT::int64_datatype()),
+                            BinaryFunc::Eq
+                        ),
+                        ScalarExpr::Column(0).call_binary(ScalarExpr::Column(1), BinaryFunc::Eq),
+                    ],
+                },
+                ColumnType::new_nullable(CDT::boolean_datatype())
+            )
+        );
+
+        // 2 IN (1, 2) is constant folded
+        let expr = in_list(lit_i64(2), vec![lit_i64(1), lit_i64(2)]);
+        let res = TypedExpr::from_substrait_rex(&expr, &input_schema, &extensions)
+            .await
+            .unwrap();
+        assert_eq!(
+            res,
+            TypedExpr::new(
+                ScalarExpr::Literal(Value::from(true), CDT::boolean_datatype()),
+                ColumnType::new_nullable(CDT::boolean_datatype())
+            )
+        );
+    }
+
     /// case: binary functions&constant folding can happen in converting substrait plan
     #[tokio::test]
     async fn test_binary_func_and_constant_folding() {
@@ -794,4 +1101,330 @@ mod test {
             }
         );
     }
+
+    #[tokio::test]
+    async fn test_if_then_type_unify() {
+        use substrait_proto::proto::expression::if_then::IfClause;
+        use substrait_proto::proto::expression::literal::LiteralType;
+
+        fn col(i: usize) -> Expression {
+            match proto_col(i).arg_type {
+                Some(ArgType::Value(e)) => e,
+                _ => unreachable!(),
+            }
+        }
+        fn lit_i64(v: i64) -> Expression {
+            Expression {
+                rex_type: Some(RexType::Literal(
+                    substrait_proto::proto::expression::Literal {
+                        nullable: false,
+                        type_variation_reference: 0,
+                        literal_type: Some(LiteralType::I64(v)),
+                    },
+                )),
+            }
+        }
+        fn if_then(ifs: Vec<(Expression, Expression)>, els: Option<Expression>) -> Expression {
+            Expression {
+                rex_type: Some(RexType::IfThen(Box::new(IfThen {
+                    ifs: ifs
+                        .into_iter()
+                        .map(|(cond, then)| IfClause {
+                            r#if: Some(cond),
+                            then: Some(then),
+                        })
+                        .collect(),
+                    r#else: els.map(Box::new),
+                }))),
+            }
+        }
+
+        let input_schema = RelationType::new(vec![
+            ColumnType::new(CDT::boolean_datatype(), false),
+            ColumnType::new(CDT::int32_datatype(), false),
+            ColumnType::new(CDT::boolean_datatype(), false),
+        ])
+        .into_unnamed();
+        let extensions = FunctionExtensions::from_iter(Vec::<(u32, String)>::new());
+
+        // CASE WHEN c0 THEN c1 ELSE 10 END, where c1 is int32 and `10` is int64
+        let expr = if_then(vec![(col(0), col(1))], Some(lit_i64(10)));
+        let res = TypedExpr::from_substrait_rex(&expr, &input_schema, &extensions)
+            .await
+            .unwrap();
+        assert_eq!(
+            res,
+            TypedExpr::new(
+                ScalarExpr::If {
+                    cond: Box::new(ScalarExpr::Column(0)),
+                    then: Box::new(ScalarExpr::Column(1).cast(CDT::int64_datatype())),
+                    els: Box::new(ScalarExpr::Literal(
+                        Value::from(10i64),
+                        CDT::int64_datatype()
+                    )),
+                },
+                ColumnType::new_nullable(CDT::int64_datatype())
+            )
+        );
+
+        // CASE WHEN c0 THEN (CASE WHEN c2 THEN c1 END) WHEN c2 THEN 1 END
+        let inner = if_then(vec![(col(2), col(1))], None);
+        let expr = if_then(vec![(col(0), inner), (col(2), lit_i64(1))], None);
+        let res = TypedExpr::from_substrait_rex(&expr, &input_schema, &extensions)
+            .await
+            .unwrap();
+        let expected_inner = ScalarExpr::If {
+            cond: Box::new(ScalarExpr::Column(2)),
+            then: Box::new(ScalarExpr::Column(1)),
+            els: Box::new(ScalarExpr::Literal(Value::Null, CDT::int32_datatype())),
+        };
+        assert_eq!(
+            res,
+            TypedExpr::new(
+                ScalarExpr::If {
+                    cond: Box::new(ScalarExpr::Column(0)),
+                    then: Box::new(expected_inner.cast(CDT::int64_datatype())),
+                    els: Box::new(ScalarExpr::If {
+                        cond: Box::new(ScalarExpr::Column(2)),
+                        then: Box::new(ScalarExpr::Literal(
+                            Value::from(1i64),
+                            CDT::int64_datatype()
+                        )),
+                        els: Box::new(ScalarExpr::Literal(Value::Null, CDT::int64_datatype())),
+                    }),
+                },
+                ColumnType::new_nullable(CDT::int64_datatype())
+            )
+        );
+
+        // unified branches can be evaluated in batch
+        let batch = crate::expr::Batch::try_new(
+            vec![
+                Arc::new(datatypes::vectors::BooleanVector::from(vec![
+                    true, false, false,
+                ])),
+                Arc::new(datatypes::vectors::Int32Vector::from_slice([1, 2, 3])),
+                Arc::new(datatypes::vectors::BooleanVector::from(vec![
+                    true, true, false,
+                ])),
+            ],
+            3,
+        )
+        .unwrap();
+        let res = res.expr.eval_batch(&batch).unwrap();
+        assert_eq!(
+            res.to_arrow_array().as_ref(),
+            datatypes::vectors::Int64Vector::from(vec![Some(1), Some(1), None])
+                .to_arrow_array()
+                .as_ref()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_null_handling_func() {
+        let input_schema = RelationType::new(vec![
+            ColumnType::new(CDT::int32_datatype(), true),
+            ColumnType::new(CDT::int64_datatype(), false),
+        ])
+        .into_unnamed();
+
+        // coalesce(c0, c1) cast c0 to int64 and is not nullable since c1 is not nullable
+        let f = substrait_proto::proto::expression::ScalarFunction {
+            function_reference: 0,
+            arguments: vec![proto_col(0), proto_col(1)],
+            options: vec![],
+            output_type: None,
+            ..Default::default()
+        };
+        let extensions = FunctionExtensions::from_iter([(0, "coalesce".to_string())]);
+        let res = TypedExpr::from_substrait_scalar_func(&f, &input_schema, &extensions)
+            .await
+            .unwrap();
+        assert_eq!(
+            res,
+            TypedExpr::new(
+                ScalarExpr::CallVariadic {
+                    func: VariadicFunc::Coalesce,
+                    exprs: vec![
+                        ScalarExpr::Column(0).cast(CDT::int64_datatype()),
+                        ScalarExpr::Column(1),
+                    ],
+                },
+                ColumnType::new(CDT::int64_datatype(), false)
+            )
+        );
+
+        // nullif(c1, c0) cast c0 to the type of c1
+        let f = substrait_proto::proto::expression::ScalarFunction {
+            function_reference: 0,
+            arguments: vec![proto_col(1), proto_col(0)],
+            options: vec![],
+            output_type: None,
+            ..Default::default()
+        };
+        let extensions = FunctionExtensions::from_iter([(0, "nullif".to_string())]);
+        let res = TypedExpr::from_substrait_scalar_func(&f, &input_schema, &extensions)
+            .await
+            .unwrap();
+        assert_eq!(
+            res,
+            TypedExpr::new(
+                ScalarExpr::CallVariadic {
+                    func: VariadicFunc::NullIf,
+                    exprs: vec![
+                        ScalarExpr::Column(1),
+                        ScalarExpr::Column(0).cast(CDT::int64_datatype()),
+                    ],
+                },
+                ColumnType::new_nullable(CDT::int64_datatype())
+            )
+        );
+        assert_eq!(
+            res.expr.typ(&input_schema.typ().column_types).unwrap(),
+            ColumnType::new_nullable(CDT::int64_datatype())
+        );
+
+        let f = substrait_proto::proto::expression::ScalarFunction {
+            function_reference: 0,
+            arguments: vec![proto_col(0)],
+            options: vec![],
+            output_type: None,
+            ..Default::default()
+        };
+        let extensions = FunctionExtensions::from_iter([(0, "is_not_null".to_string())]);
+        let res = TypedExpr::from_substrait_scalar_func(&f, &input_schema, &extensions)
+            .await
+            .unwrap();
+        assert_eq!(
+            res,
+            TypedExpr::new(
+                ScalarExpr::Column(0).call_unary(UnaryFunc::IsNotNull),
+                ColumnType::new_nullable(CDT::boolean_datatype())
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_regex_func() {
+        fn lit(v: impl ToString) -> substrait_proto::proto::FunctionArgument {
+            use substrait_proto::proto::expression;
+            build_proto_lit(expression::Literal {
+                nullable: false,
+                type_variation_reference: 0,
+                literal_type: Some(expression::literal::LiteralType::String(v.to_string())),
+            })
+        }
+
+        let input_schema =
+            RelationType::new(vec![ColumnType::new(CDT::string_datatype(), false)]).into_unnamed();
+
+        // `path ~ '^/api/'`
+        let f = substrait_proto::proto::expression::ScalarFunction {
+            function_reference: 0,
+            arguments: vec![proto_col(0), lit("^/api/")],
+            options: vec![],
+            output_type: None,
+            ..Default::default()
+        };
+        let extensions = FunctionExtensions::from_iter([(0, "regex_match".to_string())]);
+        let res = TypedExpr::from_substrait_scalar_func(&f, &input_schema, &extensions)
+            .await
+            .unwrap();
+        assert_eq!(
+            res,
+            TypedExpr {
+                expr: ScalarExpr::Column(0).call_unary(UnaryFunc::RegexpMatch {
+                    regex: CachedRegex::new("^/api/").unwrap(),
+                    negated: false,
+                }),
+                typ: ColumnType {
+                    scalar_type: CDT::boolean_datatype(),
+                    nullable: true,
+                },
+            }
+        );
+
+        // `regexp_replace(path, '/(v\d+)/', '/\1/latest/', 'gi')`
+        let f = substrait_proto::proto::expression::ScalarFunction {
+            function_reference: 0,
+            arguments: vec![
+                proto_col(0),
+                lit("/(v\\d+)/"),
+                lit("/\\1/latest/"),
+                lit("gi"),
+            ],
+            options: vec![],
+            output_type: None,
+            ..Default::default()
+        };
+        let extensions = FunctionExtensions::from_iter([(0, "regexp_replace".to_string())]);
+        let res = TypedExpr::from_substrait_scalar_func(&f, &input_schema, &extensions)
+            .await
+            .unwrap();
+        assert_eq!(
+            res,
+            TypedExpr {
+                expr: ScalarExpr::Column(0).call_unary(UnaryFunc::RegexpReplace {
+                    regex: CachedRegex::new("(?i)/(v\\d+)/").unwrap(),
+                    replacement: "/${1}/latest/".to_string(),
+                    global: true,
+                }),
+                typ: ColumnType {
+                    scalar_type: CDT::string_datatype(),
+                    nullable: true,
+                },
+            }
+        );
+
+        // invalid pattern is reported at plan time
+        let f = substrait_proto::proto::expression::ScalarFunction {
+            function_reference: 0,
+            arguments: vec![proto_col(0), lit("(unclosed")],
+            options: vec![],
+            output_type: None,
+            ..Default::default()
+        };
+        let extensions = FunctionExtensions::from_iter([(0, "regex_match".to_string())]);
+        let res = TypedExpr::from_substrait_scalar_func(&f, &input_schema, &extensions).await;
+        assert!(matches!(res, Err(Error::InvalidQuery { .. })));
+    }
+
+    /// `now()` is kept by the df optimizer and interval arithmetic on it can be lowered into temporal filter
+    #[tokio::test]
+    async fn test_now_temporal_filter() {
+        use crate::expr::{EvalError, MfpPlan};
+        use crate::repr::Row;
+
+        let engine = create_test_query_engine();
+        let sql = "SELECT number FROM numbers_with_ts WHERE ts > now() - INTERVAL '5 minutes'";
+        let plan = sql_to_substrait(engine.clone(), sql).await;
+
+        let mut ctx = create_test_ctx();
+        let flow_plan = TypedPlan::from_substrait_plan(&mut ctx, &plan)
+            .await
+            .unwrap();
+        let Plan::Mfp { mfp, .. } = flow_plan.plan else {
+            panic!("Expect a mfp plan, found {:?}", flow_plan.plan);
+        };
+        let mfp = MfpPlan::create_from(mfp).unwrap();
+        assert!(mfp.lower_bounds.is_empty());
+        assert_eq!(mfp.upper_bounds.len(), 1);
+
+        // inserted at sys time and retracted five minutes after `ts`
+        let mut values = vec![
+            Value::from(1u32),
+            Value::from(common_time::Timestamp::new_millisecond(1_000)),
+        ];
+        let ret = mfp
+            .evaluate::<EvalError>(&mut values, 0, 1)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            ret,
+            vec![
+                (Row::new(vec![Value::from(1u32)]), 0, 1),
+                (Row::new(vec![Value::from(1u32)]), 301_000, -1),
+            ]
+        );
+    }
 }
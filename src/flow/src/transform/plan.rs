@@ -12,20 +12,28 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 
 use itertools::Itertools;
 use snafu::OptionExt;
 use substrait::substrait_proto_df::proto::{FilterRel, ReadRel};
-use substrait_proto::proto::expression::MaskExpression;
+use substrait_proto::proto::expression::{MaskExpression, RexType};
+use substrait_proto::proto::join_rel::JoinType;
 use substrait_proto::proto::read_rel::ReadType;
 use substrait_proto::proto::rel::RelType;
-use substrait_proto::proto::{plan_rel, Plan as SubPlan, ProjectRel, Rel};
+use substrait_proto::proto::set_rel::SetOp;
+use substrait_proto::proto::sort_field::{SortDirection, SortKind};
+use substrait_proto::proto::{
+    plan_rel, FetchRel, JoinRel, Plan as SubPlan, ProjectRel, Rel, SetRel, SortField,
+};
 
 use crate::error::{Error, InvalidQuerySnafu, NotImplementedSnafu, PlanSnafu, UnexpectedSnafu};
-use crate::expr::{MapFilterProject, TypedExpr};
-use crate::plan::{Plan, TypedPlan};
-use crate::repr::{self, RelationType};
+use crate::expr::{BinaryFunc, MapFilterProject, ScalarExpr, TypedExpr, UnaryFunc};
+use crate::plan::{
+    ColumnOrder, JoinFilter, JoinKind, JoinPlan, LinearJoinPlan, LinearStagePlan, Plan, TypedPlan,
+};
+use crate::repr::{self, ColumnType, RelationDesc, RelationType};
+use crate::transform::resolver::check_functions_supported;
 use crate::transform::{substrait_proto, FlownodeContext, FunctionExtensions};
 
 impl TypedPlan {
@@ -36,6 +44,7 @@ impl TypedPlan {
     ) -> Result<TypedPlan, Error> {
         // Register function extension
         let function_extension = FunctionExtensions::try_from_proto(&plan.extensions)?;
+        check_functions_supported(&function_extension)?;
 
         // Parse relations
         match plan.relations.len() {
@@ -81,6 +90,13 @@ impl TypedPlan {
             return not_impl_err!("Projection without an input is not supported");
         };
 
+        if p.expressions
+            .iter()
+            .any(|e| matches!(e.rex_type, Some(RexType::WindowFunction(_))))
+        {
+            return input.window_projection(&p.expressions, extensions).await;
+        }
+
         // because this `input.schema` is incorrect for pre-expand substrait plan, so we have to use schema before expand multi-value
         // function to correctly transform it, and late rewrite it
         let schema_before_expand = {
@@ -126,6 +142,10 @@ impl TypedPlan {
         }
     }
 
+    /// Convert a `FilterRel` into a filter mfp on top of its input
+    ///
+    /// A filter after aggregation(i.e. `HAVING`) is fused into the mfp after the reduce,
+    /// so it's applied to the aggregated output each time the reduce emits updates
     #[async_recursion::async_recursion]
     pub async fn from_substrait_filter(
         ctx: &mut FlownodeContext,
@@ -146,6 +166,228 @@ impl TypedPlan {
         input.filter(expr)
     }
 
+    /// Convert a `JoinRel` into a two input linear join, support inner, outer, semi and anti join
+    ///
+    /// Equality conditions between a left expression and a right expression in the join condition
+    /// are used as join keys, the rest of the condition is applied as a filter on the joined row
+    #[async_recursion::async_recursion]
+    pub async fn from_substrait_join(
+        ctx: &mut FlownodeContext,
+        join: &JoinRel,
+        extensions: &FunctionExtensions,
+    ) -> Result<TypedPlan, Error> {
+        let (Some(left), Some(right)) = (join.left.as_ref(), join.right.as_ref()) else {
+            return not_impl_err!("Join without left or right input is not supported");
+        };
+        let kind = match join.r#type() {
+            JoinType::Inner => JoinKind::Inner,
+            JoinType::Left => JoinKind::Left,
+            JoinType::Right => JoinKind::Right,
+            JoinType::Outer => JoinKind::Full,
+            // decorrelated `EXISTS`/`IN` subquery
+            JoinType::Semi => JoinKind::Semi,
+            JoinType::Anti => JoinKind::Anti,
+            ty => return not_impl_err!("Unsupported join type: {:?}", ty),
+        };
+        let left = TypedPlan::from_substrait_rel(ctx, left, extensions).await?;
+        let right = TypedPlan::from_substrait_rel(ctx, right, extensions).await?;
+        let left_arity = left.schema.typ().column_types.len();
+        let right_arity = right.schema.typ().column_types.len();
+        let schema = {
+            let (mut left, mut right) = (left.schema.clone(), right.schema.clone());
+            // the padded side of an outer join can be null
+            if kind.pad_lookup() {
+                left.typ
+                    .column_types
+                    .iter_mut()
+                    .for_each(|c| c.nullable = true);
+            }
+            if kind.pad_stream() {
+                right
+                    .typ
+                    .column_types
+                    .iter_mut()
+                    .for_each(|c| c.nullable = true);
+            }
+            left.concat(right).without_keys()
+        };
+        // semi and anti join only output columns from left side
+        let output_schema = if kind.output_pairs() {
+            schema.clone()
+        } else {
+            left.schema.clone().without_keys()
+        };
+        let output_arity = output_schema.typ().column_types.len();
+
+        let mut conditions = vec![];
+        if let Some(cond) = &join.expression {
+            let cond = TypedExpr::from_substrait_rex(cond, &schema, extensions).await?;
+            conditions.extend(cond.expr.into_conjuncts());
+        }
+        // filter after join, for inner join it can be merged into join condition,
+        // but for other join it need to be applied to the output of join
+        let mut post_filter = vec![];
+        if let Some(cond) = &join.post_join_filter {
+            let cond = TypedExpr::from_substrait_rex(cond, &output_schema, extensions).await?;
+            if kind == JoinKind::Inner {
+                conditions.extend(cond.expr.into_conjuncts());
+            } else {
+                post_filter.extend(cond.expr.into_conjuncts());
+            }
+        }
+
+        let mut left_key = vec![];
+        let mut right_key = vec![];
+        let mut residual = vec![];
+        for cond in conditions {
+            match split_equi_key(&cond, left_arity)? {
+                Some((l, r)) => {
+                    left_key.push(l);
+                    right_key.push(r);
+                }
+                None => residual.push(cond),
+            }
+        }
+
+        let closure = JoinFilter {
+            ready_equivalences: vec![],
+            before: MapFilterProject::new(left_arity + right_arity)
+                .filter(residual)?
+                .into_safe(),
+        };
+        let final_closure = if post_filter.is_empty() {
+            None
+        } else {
+            Some(JoinFilter {
+                ready_equivalences: vec![],
+                before: MapFilterProject::new(output_arity)
+                    .filter(post_filter)?
+                    .into_safe(),
+            })
+        };
+        let plan = LinearJoinPlan {
+            source_relation: 0,
+            source_key: Some(left_key.clone()),
+            initial_closure: None,
+            stage_plans: vec![LinearStagePlan {
+                lookup_relation: 1,
+                stream_key: left_key,
+                stream_thinning: (0..left_arity).collect(),
+                lookup_key: right_key,
+                lookup_arity: right_arity,
+                closure,
+                kind,
+            }],
+            final_closure,
+        };
+        Ok(TypedPlan {
+            schema: output_schema,
+            plan: Plan::Join {
+                inputs: vec![left, right],
+                plan: JoinPlan::Linear(plan),
+            },
+        })
+    }
+
+    /// Convert a `SetRel` into a union of all inputs, only `UNION ALL` is supported for now
+    ///
+    /// Inputs are aligned to the schema of the first input by casting columns of different type,
+    /// and a column is nullable if it's nullable in any of the inputs
+    #[async_recursion::async_recursion]
+    pub async fn from_substrait_set(
+        ctx: &mut FlownodeContext,
+        set: &SetRel,
+        extensions: &FunctionExtensions,
+    ) -> Result<TypedPlan, Error> {
+        if set.op() != SetOp::UnionAll {
+            return not_impl_err!("Unsupported set operation: {:?}", set.op());
+        }
+        let mut inputs = Vec::with_capacity(set.inputs.len());
+        for input in &set.inputs {
+            inputs.push(TypedPlan::from_substrait_rel(ctx, input, extensions).await?);
+        }
+        let Some(first) = inputs.first() else {
+            return InvalidQuerySnafu {
+                reason: "Union without any input",
+            }
+            .fail();
+        };
+
+        let mut schema = first.schema.clone().without_keys();
+        let arity = schema.typ().column_types.len();
+        for input in &inputs {
+            let input_types = &input.schema.typ().column_types;
+            if input_types.len() != arity {
+                return InvalidQuerySnafu {
+                    reason: format!(
+                        "Union inputs have different number of columns: {} and {}",
+                        arity,
+                        input_types.len()
+                    ),
+                }
+                .fail();
+            }
+            for (col, input_col) in schema.typ.column_types.iter_mut().zip(input_types) {
+                col.nullable |= input_col.nullable;
+            }
+        }
+
+        let inputs = inputs
+            .into_iter()
+            .map(|input| align_schema(input, &schema))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(TypedPlan {
+            schema,
+            plan: Plan::Union {
+                inputs,
+                consolidate_output: false,
+            },
+        })
+    }
+
+    /// Convert a `FetchRel` on top of a `SortRel`(i.e. `ORDER BY ... LIMIT n`) into a global top-k
+    #[async_recursion::async_recursion]
+    pub async fn from_substrait_fetch(
+        ctx: &mut FlownodeContext,
+        fetch: &FetchRel,
+        extensions: &FunctionExtensions,
+    ) -> Result<TypedPlan, Error> {
+        let Some(input) = fetch.input.as_ref() else {
+            return not_impl_err!("Fetch without an input is not supported");
+        };
+        if fetch.offset != 0 {
+            return not_impl_err!("OFFSET is not supported");
+        }
+        let Ok(limit) = usize::try_from(fetch.count) else {
+            return not_impl_err!("LIMIT without a count is not supported");
+        };
+        // without a sort order the output would be nondeterministic
+        let Some(RelType::Sort(sort)) = &input.rel_type else {
+            return not_impl_err!("LIMIT without ORDER BY is not supported");
+        };
+        let Some(input) = sort.input.as_ref() else {
+            return not_impl_err!("Sort without an input is not supported");
+        };
+        let input = TypedPlan::from_substrait_rel(ctx, input, extensions).await?;
+
+        let mut order_by = Vec::with_capacity(sort.sorts.len());
+        for field in &sort.sorts {
+            order_by.push(from_substrait_sort_field(field, &input.schema, extensions).await?);
+        }
+
+        Ok(TypedPlan {
+            schema: input.schema.clone(),
+            plan: Plan::TopK {
+                input: Box::new(input),
+                order_by,
+                limit,
+                per_key: vec![],
+                append_only: false,
+            },
+        })
+    }
+
     pub async fn from_substrait_read(
         ctx: &mut FlownodeContext,
         read: &ReadRel,
@@ -207,7 +449,6 @@ impl TypedPlan {
     }
 
     /// Convert Substrait Rel into Flow's TypedPlan
-    /// TODO(discord9): SELECT DISTINCT(does it get compile with something else?)
     pub async fn from_substrait_rel(
         ctx: &mut FlownodeContext,
         rel: &Rel,
@@ -224,11 +465,110 @@ impl TypedPlan {
             Some(RelType::Aggregate(agg)) => {
                 Self::from_substrait_agg_rel(ctx, agg, extensions).await
             }
+            Some(RelType::Join(join)) => {
+                Self::from_substrait_join(ctx, join.as_ref(), extensions).await
+            }
+            Some(RelType::Set(set)) => Self::from_substrait_set(ctx, set, extensions).await,
+            Some(RelType::Fetch(fetch)) => {
+                Self::from_substrait_fetch(ctx, fetch.as_ref(), extensions).await
+            }
             _ => not_impl_err!("Unsupported relation type: {:?}", rel.rel_type),
         }
     }
 }
 
+/// Cast columns of `input` to the scalar types of `schema` if they differ
+fn align_schema(input: TypedPlan, schema: &RelationDesc) -> Result<TypedPlan, Error> {
+    let input_types = &input.schema.typ().column_types;
+    if input_types
+        .iter()
+        .zip(&schema.typ().column_types)
+        .all(|(input_col, col)| input_col.scalar_type() == col.scalar_type())
+    {
+        return Ok(input);
+    }
+    let exprs = input_types
+        .iter()
+        .zip(&schema.typ().column_types)
+        .enumerate()
+        .map(|(idx, (input_col, col))| {
+            let expr = ScalarExpr::Column(idx);
+            if input_col.scalar_type() == col.scalar_type() {
+                TypedExpr::new(expr, input_col.clone())
+            } else {
+                TypedExpr::new(
+                    expr.call_unary(UnaryFunc::Cast(col.scalar_type().clone())),
+                    ColumnType::new(col.scalar_type().clone(), input_col.nullable),
+                )
+            }
+        })
+        .collect_vec();
+    input.projection(exprs)
+}
+
+/// Convert a sort field into a [`ColumnOrder`], only sort by column is supported
+pub(crate) async fn from_substrait_sort_field(
+    field: &SortField,
+    schema: &RelationDesc,
+    extensions: &FunctionExtensions,
+) -> Result<ColumnOrder, Error> {
+    let expr = field.expr.as_ref().with_context(|| InvalidQuerySnafu {
+        reason: "Sort field without an expression",
+    })?;
+    let expr = TypedExpr::from_substrait_rex(expr, schema, extensions).await?;
+    let Some(column) = expr.expr.as_column() else {
+        return not_impl_err!("Only sort by column is supported, found {:?}", expr.expr);
+    };
+    let (desc, nulls_last) = match field.sort_kind {
+        Some(SortKind::Direction(d)) if d == SortDirection::AscNullsFirst as i32 => (false, false),
+        Some(SortKind::Direction(d)) if d == SortDirection::AscNullsLast as i32 => (false, true),
+        Some(SortKind::Direction(d)) if d == SortDirection::DescNullsFirst as i32 => (true, false),
+        Some(SortKind::Direction(d)) if d == SortDirection::DescNullsLast as i32 => (true, true),
+        _ => return not_impl_err!("Unsupported sort kind: {:?}", field.sort_kind),
+    };
+    Ok(ColumnOrder::new(column, desc, nulls_last))
+}
+
+/// If `cond` is `l = r` with `l` only referring to left input and `r` only referring to right input(or the other way round),
+/// return `l` and `r` with `r`'s column index rebased to the right input
+fn split_equi_key(
+    cond: &ScalarExpr,
+    left_arity: usize,
+) -> Result<Option<(ScalarExpr, ScalarExpr)>, Error> {
+    let ScalarExpr::CallBinary {
+        func: BinaryFunc::Eq,
+        expr1,
+        expr2,
+    } = cond
+    else {
+        return Ok(None);
+    };
+    let side = |expr: &ScalarExpr| {
+        let cols = expr.get_all_ref_columns();
+        if cols.is_empty() {
+            None
+        } else if cols.iter().all(|c| *c < left_arity) {
+            Some(true)
+        } else if cols.iter().all(|c| *c >= left_arity) {
+            Some(false)
+        } else {
+            None
+        }
+    };
+    let (l, mut r) = match (side(expr1), side(expr2)) {
+        (Some(true), Some(false)) => (*expr1.clone(), *expr2.clone()),
+        (Some(false), Some(true)) => (*expr2.clone(), *expr1.clone()),
+        _ => return Ok(None),
+    };
+    let rebase = r
+        .get_all_ref_columns()
+        .into_iter()
+        .map(|c| (c, c - left_arity))
+        .collect::<BTreeMap<_, _>>();
+    r.permute_map(&rebase)?;
+    Ok(Some((l, r)))
+}
+
 #[cfg(test)]
 mod test {
     use datatypes::prelude::ConcreteDataType;
@@ -272,4 +612,224 @@ mod test {
 
         assert_eq!(flow_plan.unwrap(), expected);
     }
+
+    #[tokio::test]
+    async fn test_inner_join() {
+        let engine = create_test_query_engine();
+        let sql = "SELECT numbers.number, numbers_with_ts.ts FROM numbers JOIN numbers_with_ts ON numbers.number = numbers_with_ts.number AND numbers_with_ts.number > 1";
+        let plan = sql_to_substrait(engine.clone(), sql).await;
+
+        let mut ctx = create_test_ctx();
+        let flow_plan = TypedPlan::from_substrait_plan(&mut ctx, &plan)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            flow_plan.schema.typ().column_types,
+            vec![
+                ColumnType::new(CDT::uint32_datatype(), false),
+                ColumnType::new(CDT::timestamp_millisecond_datatype(), false),
+            ]
+        );
+
+        let Plan::Mfp { input, .. } = flow_plan.plan else {
+            panic!(
+                "Expect a projection on top of join, found {:?}",
+                flow_plan.plan
+            );
+        };
+        let Plan::Join {
+            inputs,
+            plan: JoinPlan::Linear(join),
+        } = input.plan
+        else {
+            panic!("Expect a join plan, found {:?}", input.plan);
+        };
+        assert_eq!(inputs.len(), 2);
+        assert_eq!(join.stage_plans.len(), 1);
+        let stage = &join.stage_plans[0];
+        // join on `number` of both side
+        assert_eq!(stage.stream_key, vec![ScalarExpr::Column(0)]);
+        assert_eq!(stage.lookup_key, vec![ScalarExpr::Column(0)]);
+    }
+
+    #[tokio::test]
+    async fn test_left_join() {
+        let engine = create_test_query_engine();
+        let sql = "SELECT numbers.number, numbers_with_ts.ts FROM numbers LEFT JOIN numbers_with_ts ON numbers.number = numbers_with_ts.number";
+        let plan = sql_to_substrait(engine.clone(), sql).await;
+
+        let mut ctx = create_test_ctx();
+        let flow_plan = TypedPlan::from_substrait_plan(&mut ctx, &plan)
+            .await
+            .unwrap();
+
+        // right side of a left join can be null
+        assert_eq!(
+            flow_plan.schema.typ().column_types,
+            vec![
+                ColumnType::new(CDT::uint32_datatype(), false),
+                ColumnType::new(CDT::timestamp_millisecond_datatype(), true),
+            ]
+        );
+
+        let Plan::Mfp { input, .. } = flow_plan.plan else {
+            panic!(
+                "Expect a projection on top of join, found {:?}",
+                flow_plan.plan
+            );
+        };
+        let Plan::Join {
+            plan: JoinPlan::Linear(join),
+            ..
+        } = input.plan
+        else {
+            panic!("Expect a join plan, found {:?}", input.plan);
+        };
+        let stage = &join.stage_plans[0];
+        assert_eq!(stage.kind, JoinKind::Left);
+        assert_eq!(stage.lookup_arity, 2);
+    }
+
+    #[tokio::test]
+    async fn test_union_all() {
+        let engine = create_test_query_engine();
+        let sql = "SELECT number FROM numbers UNION ALL SELECT number FROM numbers_with_ts";
+        let plan = sql_to_substrait(engine.clone(), sql).await;
+
+        let mut ctx = create_test_ctx();
+        let flow_plan = TypedPlan::from_substrait_plan(&mut ctx, &plan)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            flow_plan.schema.typ().column_types,
+            vec![ColumnType::new(CDT::uint32_datatype(), false)]
+        );
+        let union = match flow_plan.plan {
+            Plan::Mfp { input, .. } => input.plan,
+            plan => plan,
+        };
+        let Plan::Union {
+            inputs,
+            consolidate_output,
+        } = union
+        else {
+            panic!("Expect a union plan, found {:?}", union);
+        };
+        assert_eq!(inputs.len(), 2);
+        assert!(!consolidate_output);
+    }
+
+    #[tokio::test]
+    async fn test_top_k() {
+        let engine = create_test_query_engine();
+        let sql = "SELECT number FROM numbers ORDER BY number DESC LIMIT 10";
+        let plan = sql_to_substrait(engine.clone(), sql).await;
+
+        let mut ctx = create_test_ctx();
+        let flow_plan = TypedPlan::from_substrait_plan(&mut ctx, &plan)
+            .await
+            .unwrap();
+
+        let top_k = match flow_plan.plan {
+            Plan::Mfp { input, .. } => input.plan,
+            plan => plan,
+        };
+        let Plan::TopK {
+            order_by,
+            limit,
+            per_key,
+            ..
+        } = top_k
+        else {
+            panic!("Expect a top-k plan, found {:?}", top_k);
+        };
+        // `DESC` implies `NULLS FIRST`
+        assert_eq!(order_by, vec![ColumnOrder::new(0, true, false)]);
+        assert_eq!(limit, 10);
+        assert!(per_key.is_empty());
+    }
+
+    /// find the first join stage in the plan tree
+    fn find_join_stage(plan: &TypedPlan) -> Option<&LinearStagePlan> {
+        match &plan.plan {
+            Plan::Join {
+                plan: JoinPlan::Linear(join),
+                ..
+            } => join.stage_plans.first(),
+            Plan::Mfp { input, .. } | Plan::Reduce { input, .. } | Plan::TopK { input, .. } => {
+                find_join_stage(input)
+            }
+            _ => None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exists_subquery() {
+        let engine = create_test_query_engine();
+        let sql = "SELECT number FROM numbers WHERE EXISTS (SELECT ts FROM numbers_with_ts WHERE numbers_with_ts.number = numbers.number)";
+        let plan = sql_to_substrait(engine.clone(), sql).await;
+
+        let mut ctx = create_test_ctx();
+        let flow_plan = TypedPlan::from_substrait_plan(&mut ctx, &plan)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            flow_plan.schema.typ().column_types,
+            vec![ColumnType::new(CDT::uint32_datatype(), false)]
+        );
+        let stage = find_join_stage(&flow_plan).expect("Expect a join");
+        assert_eq!(stage.kind, JoinKind::Semi);
+        assert_eq!(stage.stream_key, vec![ScalarExpr::Column(0)]);
+    }
+
+    #[tokio::test]
+    async fn test_scalar_subquery() {
+        let engine = create_test_query_engine();
+        let sql =
+            "SELECT number FROM numbers WHERE number > (SELECT max(number) FROM numbers_with_ts)";
+        let plan = sql_to_substrait(engine.clone(), sql).await;
+
+        let mut ctx = create_test_ctx();
+        let flow_plan = TypedPlan::from_substrait_plan(&mut ctx, &plan)
+            .await
+            .unwrap();
+
+        // uncorrelated scalar subquery is joined without any key
+        let stage = find_join_stage(&flow_plan).expect("Expect a join");
+        assert_eq!(stage.kind, JoinKind::Left);
+        assert!(stage.stream_key.is_empty());
+    }
+
+    #[test]
+    fn test_split_equi_key() {
+        // col(0) = col(3) + 1 with left arity 2
+        let cond = ScalarExpr::Column(0).call_binary(
+            ScalarExpr::Column(3).call_binary(
+                ScalarExpr::literal(1u32.into(), CDT::uint32_datatype()),
+                BinaryFunc::AddUInt32,
+            ),
+            BinaryFunc::Eq,
+        );
+        assert_eq!(
+            split_equi_key(&cond, 2).unwrap(),
+            Some((
+                ScalarExpr::Column(0),
+                ScalarExpr::Column(1).call_binary(
+                    ScalarExpr::literal(1u32.into(), CDT::uint32_datatype()),
+                    BinaryFunc::AddUInt32,
+                )
+            ))
+        );
+
+        // both sides refer to left input
+        let cond = ScalarExpr::Column(0).call_binary(ScalarExpr::Column(1), BinaryFunc::Eq);
+        assert_eq!(split_equi_key(&cond, 2).unwrap(), None);
+
+        // not an equality
+        let cond = ScalarExpr::Column(0).call_binary(ScalarExpr::Column(2), BinaryFunc::Lt);
+        assert_eq!(split_equi_key(&cond, 2).unwrap(), None);
+    }
 }
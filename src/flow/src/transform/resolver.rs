@@ -0,0 +1,184 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Pluggable resolution of substrait scalar functions, so embedders can lower their own functions
+//! into flow's [`ScalarExpr`](crate::expr::ScalarExpr)
+
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+
+use itertools::Itertools;
+use snafu::ensure;
+
+use crate::error::{Error, UnsupportedFunctionsSnafu};
+use crate::expr::{
+    BinaryFunc, TypedExpr, UnaryFunc, UnmaterializableFunc, VariadicFunc, TUMBLE_END, TUMBLE_START,
+};
+use crate::transform::FunctionExtensions;
+
+/// Lower a scalar function in substrait plan into flow's expression by its name
+///
+/// Registered resolvers are tried before the builtin lowering rules, so they can also override builtin functions
+pub trait ScalarFnResolver: Send + Sync + std::fmt::Debug {
+    /// Whether this resolver can lower the function with given(lowercase) name
+    fn can_resolve(&self, name: &str) -> bool;
+
+    /// Lower the function with given name and already transformed arguments
+    fn resolve(&self, name: &str, args: Vec<TypedExpr>) -> Result<TypedExpr, Error>;
+}
+
+static SCALAR_FN_RESOLVERS: RwLock<Vec<Arc<dyn ScalarFnResolver>>> = RwLock::new(Vec::new());
+
+/// Register a resolver for custom scalar functions, later registered resolvers take precedence
+pub fn register_scalar_fn_resolver(resolver: Arc<dyn ScalarFnResolver>) {
+    SCALAR_FN_RESOLVERS.write().unwrap().push(resolver);
+}
+
+/// Find the latest registered resolver that can resolve the function with given name
+pub(crate) fn find_scalar_fn_resolver(name: &str) -> Option<Arc<dyn ScalarFnResolver>> {
+    SCALAR_FN_RESOLVERS
+        .read()
+        .unwrap()
+        .iter()
+        .rev()
+        .find(|resolver| resolver.can_resolve(name))
+        .cloned()
+}
+
+/// Aggregate and window functions that can be lowered into flow's reduce or window plan
+const BUILTIN_AGGR_WINDOW_FUNCS: &[&str] = &[
+    "max",
+    "min",
+    "sum",
+    "count",
+    "bool_or",
+    "bool_and",
+    "row_number",
+    "lag",
+    "lead",
+];
+
+/// Functions that datafusion's substrait consumer turns into builtin expressions instead of udfs
+const DF_BUILTIN_EXPR_FUNCS: &[&str] = &[
+    "not",
+    "like",
+    "ilike",
+    "is_null",
+    "is_not_null",
+    "is_true",
+    "is_false",
+    "is_not_true",
+    "is_not_false",
+    "is_unknown",
+    "is_not_unknown",
+    "negative",
+];
+
+/// Whether the function with given name can be transformed, either by a registered resolver,
+/// by flow's builtin functions, or by falling back to datafusion
+fn is_function_supported(name: &str, df_scalar_funcs: &HashSet<String>) -> bool {
+    let name = name.to_lowercase();
+    let name = name.as_str();
+    find_scalar_fn_resolver(name).is_some()
+        || UnaryFunc::is_valid_func_name(name)
+        || UnaryFunc::is_regex_func_name(name)
+        || BinaryFunc::is_valid_func_name(name)
+        || VariadicFunc::is_valid_func_name(name)
+        || UnmaterializableFunc::is_valid_func_name(name)
+        || [TUMBLE_START, TUMBLE_END, "tumble", "arrow_cast"].contains(&name)
+        || BUILTIN_AGGR_WINDOW_FUNCS.contains(&name)
+        || DF_BUILTIN_EXPR_FUNCS.contains(&name)
+        || substrait::df_logical_plan::consumer::name_to_op(name).is_ok()
+        || df_scalar_funcs.contains(name)
+}
+
+/// Check all functions referenced by the plan up front, and report all unsupported ones in one error,
+/// instead of failing on the first one deep inside transform
+pub(crate) fn check_functions_supported(extensions: &FunctionExtensions) -> Result<(), Error> {
+    // same as the session context used when falling back to datafusion's scalar function
+    let df_scalar_funcs: HashSet<String> = datafusion::prelude::SessionContext::new()
+        .state()
+        .scalar_functions()
+        .keys()
+        .cloned()
+        .collect();
+    let unsupported = extensions
+        .inner_ref()
+        .into_values()
+        .filter(|name| !is_function_supported(name, &df_scalar_funcs))
+        .cloned()
+        .sorted()
+        .dedup()
+        .collect_vec();
+    ensure!(
+        unsupported.is_empty(),
+        UnsupportedFunctionsSnafu { names: unsupported }
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use datatypes::data_type::ConcreteDataType as CDT;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::expr::ScalarExpr;
+    use crate::repr::ColumnType;
+
+    /// `my_double(x)` is lowered into `x + x`
+    #[derive(Debug)]
+    struct MyDouble;
+
+    impl ScalarFnResolver for MyDouble {
+        fn can_resolve(&self, name: &str) -> bool {
+            name == "my_double"
+        }
+
+        fn resolve(&self, _name: &str, args: Vec<TypedExpr>) -> Result<TypedExpr, Error> {
+            let arg = args[0].clone();
+            Ok(TypedExpr::new(
+                arg.expr.clone().call_binary(arg.expr, BinaryFunc::AddInt64),
+                ColumnType::new_nullable(CDT::int64_datatype()),
+            ))
+        }
+    }
+
+    #[test]
+    fn test_check_functions_supported() {
+        let extensions =
+            FunctionExtensions::from_iter([(0, "add"), (1, "my_triple"), (2, "unknown_fn")]);
+        let err = check_functions_supported(&extensions).unwrap_err();
+        assert!(
+            matches!(&err, Error::UnsupportedFunctions { names, .. } if names == &["my_triple", "unknown_fn"]),
+            "{err:?}"
+        );
+
+        let extensions = FunctionExtensions::from_iter([(0, "add"), (1, "my_double")]);
+        assert!(check_functions_supported(&extensions).is_err());
+        register_scalar_fn_resolver(Arc::new(MyDouble));
+        assert!(check_functions_supported(&extensions).is_ok());
+
+        let resolver = find_scalar_fn_resolver("my_double").unwrap();
+        let arg = TypedExpr::new(
+            ScalarExpr::Column(0),
+            ColumnType::new_nullable(CDT::int64_datatype()),
+        );
+        let res = resolver.resolve("my_double", vec![arg]).unwrap();
+        assert_eq!(
+            res.expr,
+            ScalarExpr::Column(0).call_binary(ScalarExpr::Column(0), BinaryFunc::AddInt64)
+        );
+    }
+}
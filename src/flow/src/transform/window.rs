@@ -0,0 +1,289 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use datatypes::data_type::ConcreteDataType as CDT;
+use snafu::OptionExt;
+use substrait_proto::proto::expression::window_function::bound::Kind as BoundKind;
+use substrait_proto::proto::expression::window_function::Bound;
+use substrait_proto::proto::expression::{RexType, WindowFunction};
+use substrait_proto::proto::function_argument::ArgType;
+use substrait_proto::proto::Expression;
+
+use crate::error::{Error, NotImplementedSnafu, PlanSnafu};
+use crate::expr::{ScalarExpr, TypedExpr};
+use crate::plan::{ColumnOrder, FrameUnits, Plan, TypedPlan, WindowExpr, WindowFrame, WindowFunc};
+use crate::repr::{ColumnType, RelationDesc, RelationType};
+use crate::transform::plan::from_substrait_sort_field;
+use crate::transform::{substrait_proto, FunctionExtensions};
+
+impl TypedPlan {
+    /// Convert the expressions of a projection containing window functions into a [`Plan::Window`]
+    /// and a projection on top of it, which pick the window values and evaluate other expressions
+    ///
+    /// All window functions must share the same `PARTITION BY` and `ORDER BY`, and their frames
+    /// are always treated as `ROWS` frames, since substrait plan from datafusion doesn't tell
+    /// `ROWS` from `RANGE`
+    pub async fn window_projection(
+        self,
+        exprs: &[Expression],
+        extensions: &FunctionExtensions,
+    ) -> Result<TypedPlan, Error> {
+        if !self.schema.typ().auto_columns.is_empty() {
+            return not_impl_err!("Window function with auto added columns is not supported yet");
+        }
+        let input_arity = self.schema.len()?;
+
+        let mut window: Option<(Vec<usize>, Vec<ColumnOrder>)> = None;
+        let mut window_exprs = Vec::new();
+        let mut window_types = Vec::new();
+        let mut proj_exprs = Vec::with_capacity(exprs.len());
+        for e in exprs {
+            let Some(RexType::WindowFunction(f)) = &e.rex_type else {
+                proj_exprs.push(TypedExpr::from_substrait_rex(e, &self.schema, extensions).await?);
+                continue;
+            };
+            let (partition_by, order_by, expr, typ) =
+                from_substrait_window_func(f, &self.schema, extensions).await?;
+            match &window {
+                Some(w) if *w != (partition_by.clone(), order_by.clone()) => {
+                    return not_impl_err!(
+                        "Window functions with different PARTITION BY or ORDER BY are not supported yet"
+                    );
+                }
+                Some(_) => (),
+                None => window = Some((partition_by, order_by)),
+            }
+            proj_exprs.push(TypedExpr::new(
+                ScalarExpr::Column(input_arity + window_exprs.len()),
+                typ.clone(),
+            ));
+            window_exprs.push(expr);
+            window_types.push(typ);
+        }
+        let (partition_by, order_by) = window.with_context(|| PlanSnafu {
+            reason: "Expect at least one window function in projection",
+        })?;
+
+        let schema = self
+            .schema
+            .clone()
+            .concat(RelationType::new(window_types).into_unnamed());
+        let window = TypedPlan {
+            schema,
+            plan: Plan::Window {
+                input: Box::new(self),
+                partition_by,
+                order_by,
+                exprs: window_exprs,
+            },
+        };
+        window.projection(proj_exprs)
+    }
+}
+
+/// Convert a window function into its partition columns, sort order, [`WindowExpr`] and output type
+async fn from_substrait_window_func(
+    f: &WindowFunction,
+    schema: &RelationDesc,
+    extensions: &FunctionExtensions,
+) -> Result<(Vec<usize>, Vec<ColumnOrder>, WindowExpr, ColumnType), Error> {
+    let fn_name = extensions
+        .get(&f.function_reference)
+        .with_context(|| NotImplementedSnafu {
+            reason: format!(
+                "Window function not found: function reference = {:?}",
+                f.function_reference
+            ),
+        })?
+        .to_lowercase();
+
+    let mut partition_by = Vec::with_capacity(f.partitions.len());
+    for p in &f.partitions {
+        let expr = TypedExpr::from_substrait_rex(p, schema, extensions).await?;
+        let Some(column) = expr.expr.as_column() else {
+            return not_impl_err!(
+                "Only partition by column is supported, found {:?}",
+                expr.expr
+            );
+        };
+        partition_by.push(column);
+    }
+    let mut order_by = Vec::with_capacity(f.sorts.len());
+    for field in &f.sorts {
+        order_by.push(from_substrait_sort_field(field, schema, extensions).await?);
+    }
+
+    let mut args = Vec::with_capacity(f.arguments.len());
+    for arg in &f.arguments {
+        match &arg.arg_type {
+            Some(ArgType::Value(e)) => {
+                args.push(TypedExpr::from_substrait_rex(e, schema, extensions).await?)
+            }
+            _ => return not_impl_err!("Window function argument non-Value type not supported"),
+        }
+    }
+    let arg_column = |args: &[TypedExpr]| -> Result<(usize, ColumnType), Error> {
+        let arg = args.first().with_context(|| PlanSnafu {
+            reason: format!("Window function {} expect an argument", fn_name),
+        })?;
+        match arg.expr.as_column() {
+            Some(column) => Ok((column, arg.typ.clone())),
+            None => not_impl_err!(
+                "Only column argument of window function is supported, found {:?}",
+                arg.expr
+            ),
+        }
+    };
+    let offset = |args: &[TypedExpr]| -> Result<usize, Error> {
+        match args.get(1) {
+            None => Ok(1),
+            Some(arg) => arg
+                .expr
+                .as_literal()
+                .and_then(|v| v.as_i64().ok().flatten())
+                .and_then(|v| usize::try_from(v).ok())
+                .with_context(|| PlanSnafu {
+                    reason: format!("Expect a non-negative integer offset, found {:?}", arg.expr),
+                }),
+        }
+    };
+
+    let (func, arg, typ) = match fn_name.as_str() {
+        "row_number" => (
+            WindowFunc::RowNumber,
+            None,
+            ColumnType::new(CDT::uint64_datatype(), false),
+        ),
+        "lag" | "lead" => {
+            if args.len() > 2 {
+                return not_impl_err!("Default value of {} is not supported yet", fn_name);
+            }
+            let offset = offset(&args)?;
+            let (column, typ) = arg_column(&args)?;
+            let func = if fn_name == "lag" {
+                WindowFunc::Lag { offset }
+            } else {
+                WindowFunc::Lead { offset }
+            };
+            (
+                func,
+                Some(column),
+                ColumnType::new_nullable(typ.scalar_type),
+            )
+        }
+        "sum" => {
+            let (column, typ) = arg_column(&args)?;
+            let scalar_type = if typ.scalar_type.is_float() {
+                CDT::float64_datatype()
+            } else if typ.scalar_type.is_signed() || typ.scalar_type.is_unsigned() {
+                CDT::int64_datatype()
+            } else {
+                return not_impl_err!("Sum of type {:?} is not supported", typ.scalar_type);
+            };
+            (
+                WindowFunc::Sum,
+                Some(column),
+                ColumnType::new_nullable(scalar_type),
+            )
+        }
+        _ => return not_impl_err!("Unsupported window function: {}", fn_name),
+    };
+
+    let frame = WindowFrame {
+        units: FrameUnits::Rows,
+        preceding: from_substrait_bound(&f.lower_bound, true),
+        following: from_substrait_bound(&f.upper_bound, false),
+    };
+    Ok((partition_by, order_by, WindowExpr { func, arg, frame }, typ))
+}
+
+/// Convert a frame bound into how far the frame extends before(if `is_lower`) or after current row,
+/// `None` means unbounded
+fn from_substrait_bound(bound: &Option<Bound>, is_lower: bool) -> Option<i64> {
+    let extent = match bound.as_ref()?.kind.as_ref()? {
+        BoundKind::Preceding(p) => -p.offset,
+        BoundKind::Following(f) => f.offset,
+        BoundKind::CurrentRow(_) => 0,
+        BoundKind::Unbounded(_) => return None,
+    };
+    Some(if is_lower { -extent } else { extent })
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::expr::GlobalId;
+    use crate::transform::test::{create_test_ctx, create_test_query_engine, sql_to_substrait};
+
+    #[tokio::test]
+    async fn test_window() {
+        let engine = create_test_query_engine();
+        let sql = "SELECT number, row_number() OVER (ORDER BY number), \
+            sum(number) OVER (ORDER BY number ROWS BETWEEN 2 PRECEDING AND CURRENT ROW) \
+            FROM numbers";
+        let plan = sql_to_substrait(engine.clone(), sql).await;
+
+        let mut ctx = create_test_ctx();
+        let flow_plan = TypedPlan::from_substrait_plan(&mut ctx, &plan)
+            .await
+            .unwrap();
+
+        let Plan::Mfp { input, .. } = flow_plan.plan else {
+            panic!(
+                "Expect a projection on top of window, found {:?}",
+                flow_plan.plan
+            );
+        };
+        let Plan::Window {
+            input,
+            partition_by,
+            order_by,
+            exprs,
+        } = input.plan
+        else {
+            panic!("Expect a window plan, found {:?}", input.plan);
+        };
+        assert_eq!(
+            input.plan.find_used_collection(),
+            [GlobalId::User(0)].into_iter().collect()
+        );
+        assert_eq!(partition_by, Vec::<usize>::new());
+        assert_eq!(order_by, vec![ColumnOrder::new(0, false, true)]);
+        assert_eq!(
+            exprs,
+            vec![
+                WindowExpr {
+                    func: WindowFunc::RowNumber,
+                    arg: None,
+                    frame: WindowFrame {
+                        units: FrameUnits::Rows,
+                        preceding: None,
+                        following: Some(0),
+                    },
+                },
+                WindowExpr {
+                    func: WindowFunc::Sum,
+                    arg: Some(0),
+                    frame: WindowFrame {
+                        units: FrameUnits::Rows,
+                        preceding: Some(2),
+                        following: Some(0),
+                    },
+                },
+            ]
+        );
+    }
+}
@@ -16,14 +16,22 @@
 
 use std::collections::{BTreeMap, BTreeSet};
 use std::ops::Bound;
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use common_telemetry::trace;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 use smallvec::{smallvec, SmallVec};
 use tokio::sync::RwLock;
 
 use crate::expr::{EvalError, ScalarExpr};
-use crate::repr::{value_to_internal_ts, DiffRow, Duration, KeyValDiffRow, Row, Timestamp};
+use crate::repr::{value_to_internal_ts, Diff, DiffRow, Duration, KeyValDiffRow, Row, Timestamp};
+pub use crate::utils::spill::SpilledRuns;
+pub use crate::utils::state_backend::StateBackend;
+
+mod spill;
+mod state_backend;
 
 /// A batch of updates, arranged by key
 pub type Batch = BTreeMap<Row, SmallVec<[DiffRow; 2]>>;
@@ -32,6 +40,18 @@ pub type Batch = BTreeMap<Row, SmallVec<[DiffRow; 2]>>;
 /// TODO(discord9): consider internally index by key, value, and timestamp for faster lookup
 pub type Spine = BTreeMap<Timestamp, Batch>;
 
+/// Which keys of an arrangement are evicted first once the state of a dataflow exceeds its
+/// size limit, see [`Arrangement::evict`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EvictionPolicy {
+    /// the least recently updated keys
+    #[default]
+    Lru,
+    /// the keys with the oldest event timestamp, which are the next to expire, or the least
+    /// recently updated keys if the arrangement has no time index
+    OldestTimestamp,
+}
+
 /// Determine when should a key expire according to it's event timestamp in key.
 ///
 /// If a key is expired, any future updates to it should be ignored.
@@ -47,6 +67,10 @@ pub struct KeyExpiryManager {
 
     /// Expression to get timestamp from key row
     event_timestamp_from_row: Option<ScalarExpr>,
+
+    /// Whether the timestamp from key row is the time the key expires at, as given by the
+    /// flow's `EXPIRE WHEN`, hence the expiration duration is always zero
+    expire_when: bool,
 }
 
 impl KeyExpiryManager {
@@ -58,6 +82,18 @@ impl KeyExpiryManager {
             event_ts_to_key: Default::default(),
             key_expiration_duration,
             event_timestamp_from_row,
+            expire_when: false,
+        }
+    }
+
+    /// Create a manager expiring each key once `now` passes the timestamp `expire_at` evaluates
+    /// to on the key row, which is given by the flow's `EXPIRE WHEN`
+    pub fn new_expire_when(expire_at: ScalarExpr) -> Self {
+        Self {
+            event_ts_to_key: Default::default(),
+            key_expiration_duration: Some(0),
+            event_timestamp_from_row: Some(expire_at),
+            expire_when: true,
         }
     }
 
@@ -70,11 +106,30 @@ impl KeyExpiryManager {
             .as_ref()
             .map(|e| e.eval(&row.inner))
             .transpose()?
+            // a null event timestamp never expires
+            .filter(|v| !v.is_null())
             .map(value_to_internal_ts)
             .transpose()?;
         Ok(ts)
     }
 
+    /// Change the duration after which a key is considered expired, keeping the tracked keys.
+    ///
+    /// A manager created by [`KeyExpiryManager::new_expire_when`] is not affected.
+    pub fn set_expiration_duration(&mut self, key_expiration_duration: Option<Duration>) {
+        if !self.expire_when {
+            self.key_expiration_duration = key_expiration_duration;
+        }
+    }
+
+    /// Whether the event timestamp is evaluated only from the first `arity` columns of the key
+    /// row, so the manager also applies to keys with more columns appended
+    pub fn is_evaluated_from_prefix(&self, arity: usize) -> bool {
+        self.event_timestamp_from_row
+            .as_ref()
+            .map_or(true, |e| e.get_all_ref_columns().iter().all(|c| *c < arity))
+    }
+
     /// Return timestamp that should be expired by the time `now` by compute `now - expiration_duration`
     pub fn compute_expiration_timestamp(&self, now: Timestamp) -> Option<Timestamp> {
         self.key_expiration_duration.map(|d| now - d)
@@ -89,6 +144,11 @@ impl KeyExpiryManager {
         now: Timestamp,
         row: &Row,
     ) -> Result<Option<Duration>, EvalError> {
+        // a manager without expiration duration only keeps the time index, so that the duration
+        // can be set later, keys are tracked once it's set since compaction visits all of them
+        if self.key_expiration_duration.is_none() {
+            return Ok(None);
+        }
         let Some(event_ts) = self.extract_event_ts(row)? else {
             return Ok(None);
         };
@@ -154,7 +214,7 @@ impl KeyExpiryManager {
 ///
 /// Note the two way arrow between reduce operator and arrange, it's because reduce operator need to query existing state
 /// and also need to update existing state.
-#[derive(Debug, Clone, Default, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
 pub struct Arrangement {
     /// A name or identifier for the arrangement which can be used for debugging or logging purposes.
     /// This field is not critical to the functionality but aids in monitoring and management of arrangements.
@@ -196,6 +256,13 @@ pub struct Arrangement {
 
     /// The time that the last compaction happened, also known as the current time.
     last_compaction_time: Option<Timestamp>,
+
+    /// Cold keys of the current state spilled to a [`StateBackend`], see [`Arrangement::spill`].
+    /// Keys are never spilled if not set.
+    ///
+    /// The keys in the current state batch and the spilled keys are disjoint, a spilled key is
+    /// loaded back into the current state once it's updated.
+    spilled: Option<Box<dyn StateBackend>>,
 }
 
 impl Arrangement {
@@ -206,10 +273,132 @@ impl Arrangement {
             is_written: false,
             expire_state: None,
             last_compaction_time: None,
+            spilled: None,
             name,
         }
     }
 
+    /// Allow spilling cold keys of the current state to files in `dir`, see [`Arrangement::spill`]
+    pub fn enable_spill(&mut self, dir: PathBuf) {
+        self.set_state_backend(Box::new(SpilledRuns::new(dir)));
+    }
+
+    /// Allow spilling cold keys of the current state to `backend`, see [`Arrangement::spill`]
+    pub fn set_state_backend(&mut self, backend: Box<dyn StateBackend>) {
+        self.spilled = Some(backend);
+    }
+
+    /// Spill the least recently updated keys of the current state to disk until at least
+    /// `bytes` of state is freed or no key is left, return the number of keys spilled.
+    ///
+    /// Only a full arrangement with spilling enabled spills, since others have no current state.
+    pub fn spill(&mut self, bytes: usize) -> Result<usize, EvalError> {
+        let (Some(spilled), Some(now), true) = (
+            &mut self.spilled,
+            self.last_compaction_time,
+            self.full_arrangement,
+        ) else {
+            return Ok(0);
+        };
+        let Some(current) = self.spine.get_mut(&now) else {
+            return Ok(0);
+        };
+        // keys with late updates not compacted yet are kept in memory
+        let mut candidates = current
+            .iter()
+            .filter(|(_, updates)| updates.len() == 1)
+            .map(|(key, updates)| (key, &updates[0]))
+            .collect_vec();
+        // the time of the current state of a key is when it's last updated
+        candidates.sort_by_key(|(_, (_, ts, _))| *ts);
+
+        let mut cold = BTreeMap::new();
+        let mut freed = 0;
+        for (key, update) in candidates {
+            if freed >= bytes {
+                break;
+            }
+            freed += update_size(key, &update.0);
+            cold.insert(key.clone(), update.clone());
+        }
+        spilled.put(cold.clone())?;
+        current.retain(|key, _| !cold.contains_key(key));
+        Ok(cold.len())
+    }
+
+    /// Drop keys of the current state until at least `bytes` of state is freed or no key is left,
+    /// return the number of keys evicted, see [`EvictionPolicy`] for which keys go first.
+    ///
+    /// Unlike [`Arrangement::spill`], evicted keys are lost, so later updates to them start from
+    /// empty state. Only a full arrangement evicts, since others have no current state.
+    pub fn evict(&mut self, bytes: usize, policy: EvictionPolicy) -> usize {
+        let (Some(now), true) = (self.last_compaction_time, self.full_arrangement) else {
+            return 0;
+        };
+        let Some(current) = self.spine.get_mut(&now) else {
+            return 0;
+        };
+        let expire_state = self.expire_state.as_ref();
+        // keys with late updates not compacted yet are kept
+        let mut candidates = current
+            .iter()
+            .filter(|(_, updates)| updates.len() == 1)
+            .map(|(key, updates)| {
+                let event_ts = match policy {
+                    EvictionPolicy::Lru => None,
+                    EvictionPolicy::OldestTimestamp => expire_state
+                        .and_then(|s| s.extract_event_ts(key).ok())
+                        .flatten(),
+                };
+                // keys without event timestamp go after those with one, then by the time of the
+                // current state of a key, which is when it's last updated
+                (
+                    (event_ts.is_none(), event_ts, updates[0].1),
+                    key,
+                    &updates[0].0,
+                )
+            })
+            .collect_vec();
+        candidates.sort_by_key(|(order, _, _)| *order);
+
+        let mut evicted = BTreeSet::new();
+        let mut freed = 0;
+        for (_, key, val) in candidates {
+            if freed >= bytes {
+                break;
+            }
+            freed += update_size(key, val);
+            evicted.insert(key.clone());
+        }
+        current.retain(|key, _| !evicted.contains(key));
+        evicted.len()
+    }
+
+    /// Number of keys spilled to disk, including stale ones not merged away yet
+    pub fn spilled_len(&self) -> usize {
+        self.spilled.as_ref().map(|s| s.len()).unwrap_or_default()
+    }
+
+    /// Take errors of reading spilled keys in methods which can't return them
+    pub fn take_spill_errors(&self) -> Vec<EvalError> {
+        self.spilled
+            .as_ref()
+            .map(|s| s.take_errors())
+            .unwrap_or_default()
+    }
+
+    /// Whether `key` may be spilled, i.e. spilling is enabled and it's not in the current state
+    fn may_be_spilled(&self, key: &Row) -> bool {
+        let Some(spilled) = &self.spilled else {
+            return false;
+        };
+        !spilled.is_empty()
+            && !self
+                .last_compaction_time
+                .and_then(|now| self.spine.get(&now))
+                .is_some_and(|current| current.contains_key(key))
+    }
+
     pub fn get_expire_state(&self) -> Option<&KeyExpiryManager> {
         self.expire_state.as_ref()
     }
@@ -218,6 +407,19 @@ impl Arrangement {
         self.expire_state = Some(expire_state);
     }
 
+    /// Change the expiration duration of the arrangement in place.
+    ///
+    /// Return false if the arrangement has no expire state, i.e. it's not rendered with one.
+    pub fn set_expire_after(&mut self, expire_after: Option<Duration>) -> bool {
+        match &mut self.expire_state {
+            Some(s) => {
+                s.set_expiration_duration(expire_after);
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Apply updates into spine, with no respect of whether the updates are in futures, past, or now.
     ///
     /// Return the maximum expire time (already expire by how much time) of all updates if any keys is already expired.
@@ -256,12 +458,24 @@ impl Arrangement {
             }
 
             // Get the first batch with key that's greater or equal to `update_ts`.
-            let (_, batch) = self
+            let (batch_ts, batch) = self
                 .spine
                 .range_mut(update_ts..)
                 .next()
                 .expect("Previous insert should have created the batch");
 
+            // a late update into the current state needs the spilled state of the key to apply on
+            if let (Some(spilled), true) = (
+                &mut self.spilled,
+                Some(*batch_ts) == self.last_compaction_time,
+            ) {
+                if !batch.contains_key(&key) {
+                    if let Some(row) = spilled.take(&key)? {
+                        batch.insert(key.clone(), smallvec![row]);
+                    }
+                }
+            }
+
             let key_updates = batch.entry(key).or_default();
             key_updates.push((val, update_ts, diff));
 
@@ -352,6 +566,7 @@ impl Arrangement {
         let mut max_expired_by: Option<Duration> = None;
 
         let batches_to_compact = self.split_spine_le(&now);
+        let prev_compaction_time = self.last_compaction_time;
         self.last_compaction_time = Some(now);
 
         // If a full arrangement is not needed, we can just discard everything before and including now,
@@ -362,7 +577,9 @@ impl Arrangement {
         // else we update them into current state.
         let mut compacting_batch = Batch::default();
 
-        for (_, batch) in batches_to_compact {
+        for (ts, batch) in batches_to_compact {
+            // keys of the previous current state are in memory, keys updated since then may be spilled
+            let is_update = Some(ts) != prev_compaction_time;
             for (key, updates) in batch {
                 // check if the key is expired
                 if let Some(s) = &mut self.expire_state {
@@ -370,6 +587,9 @@ impl Arrangement {
                         s.get_expire_duration_and_update_event_ts(now, &key)?
                     {
                         max_expired_by = max_expired_by.max(Some(expired_by));
+                        if let Some(spilled) = &mut self.spilled {
+                            spilled.remove(&key);
+                        }
                         continue;
                     }
                 }
@@ -378,6 +598,10 @@ impl Arrangement {
                     .remove(&key)
                     // only one row in the updates during compaction
                     .and_then(|mut updates| updates.pop());
+                // load the spilled state of the key back to apply updates on it
+                if let (true, None, Some(spilled)) = (is_update, &row, &mut self.spilled) {
+                    row = spilled.take(&key)?;
+                }
 
                 for update in updates {
                     row = compact_diff_row(row, &update);
@@ -423,6 +647,19 @@ impl Arrangement {
                 }
             }
         }
+        // spilled keys are part of the current state
+        if let (Some(spilled), Some(now)) = (&self.spilled, self.last_compaction_time) {
+            if !spilled.is_empty() && range.contains(&now) {
+                match spilled.range(&Row::empty()) {
+                    Ok(all) => res.extend(
+                        all.into_iter()
+                            .filter(|(_, (_, ts, _))| range.contains(ts))
+                            .map(|(key, (val, ts, diff))| ((key, val), ts, diff)),
+                    ),
+                    Err(err) => spilled.report(&err),
+                }
+            }
+        }
         res
     }
 
@@ -434,6 +671,9 @@ impl Arrangement {
                     for (_, batch) in self.spine.iter_mut() {
                         batch.remove(&key);
                     }
+                    if let Some(spilled) = &mut self.spilled {
+                        spilled.remove(&key);
+                    }
                 }
             }
         }
@@ -456,7 +696,8 @@ impl Arrangement {
                 .spine
                 .get(&last_compaction_time)
                 .and_then(|batch| batch.get(key))
-                .and_then(|updates| updates.first().cloned());
+                .and_then(|updates| updates.first().cloned())
+                .or_else(|| self.get_spilled(key));
         }
 
         // SLOW PATH:
@@ -475,7 +716,8 @@ impl Arrangement {
             )
         };
 
-        let mut final_val = None;
+        // the spilled state of the key is older than any update in memory
+        let mut final_val = self.get_spilled(key);
         for (ts, batch) in batches {
             if let Some(updates) = batch.get(key) {
                 if *ts <= now {
@@ -491,6 +733,156 @@ impl Arrangement {
         }
         final_val
     }
+
+    /// Get current state of all keys starting with `prefix`, in the order of keys.
+    ///
+    /// Useful for operators that index multiple rows under the same key, by making the key part of the arrangement's key
+    /// (i.e. join operator index rows by join key)
+    pub fn get_by_prefix(&self, now: Timestamp, prefix: &Row) -> Vec<(Row, DiffRow)> {
+        let batches = self.spine.range(..=now).chain(
+            self.spine
+                .range((Bound::Excluded(now), Bound::Unbounded))
+                .next(),
+        );
+
+        // spilled keys are not in memory, and older than any update in memory
+        let mut res: BTreeMap<Row, DiffRow> = match &self.spilled {
+            Some(spilled) if !spilled.is_empty() => spilled
+                .range(prefix)
+                .inspect_err(|err| spilled.report(err))
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|(_, (_, ts, _))| *ts <= now)
+                .collect(),
+            _ => BTreeMap::new(),
+        };
+        for (_, batch) in batches {
+            let keys = batch
+                .range(prefix.clone()..)
+                .take_while(|(key, _)| key.inner.starts_with(&prefix.inner));
+            for (key, updates) in keys {
+                for update in updates.iter().filter(|(_, ts, _)| *ts <= now) {
+                    match compact_diff_row(res.remove(key), update) {
+                        Some(val) => res.insert(key.clone(), val),
+                        None => None,
+                    };
+                }
+            }
+        }
+        res.into_iter().collect()
+    }
+
+    /// Get the spilled state of `key` if it may be spilled, errors are kept to be taken by
+    /// [`Arrangement::take_spill_errors`]
+    fn get_spilled(&self, key: &Row) -> Option<DiffRow> {
+        if !self.may_be_spilled(key) {
+            return None;
+        }
+        self.spilled.as_ref()?.get_or_report(key)
+    }
+}
+
+impl Arrangement {
+    /// Number of updates in the arrangement, including spilled ones, and the estimated size in
+    /// bytes of those in memory
+    pub fn state_size(&self) -> (usize, usize) {
+        let (mut rows, mut bytes) = (self.spilled_len(), 0);
+        for batch in self.spine.values() {
+            for (key, updates) in batch {
+                rows += updates.len();
+                bytes += row_size(key) * updates.len();
+                bytes += updates
+                    .iter()
+                    .map(|(val, _, _)| row_size(val) + std::mem::size_of::<(Timestamp, Diff)>())
+                    .sum::<usize>();
+            }
+        }
+        (rows, bytes)
+    }
+}
+
+fn row_size(row: &Row) -> usize {
+    row.inner.iter().map(|v| v.as_value_ref().data_size()).sum()
+}
+
+/// Estimated size of a consolidated update of the current state
+fn update_size(key: &Row, val: &Row) -> usize {
+    row_size(key) + row_size(val) + std::mem::size_of::<(Timestamp, Diff)>()
+}
+
+/// Serializable snapshot of an [`Arrangement`]'s updates, used to checkpoint the state of a dataflow
+///
+/// Only the data is kept, settings like name or how to expire keys come from the rendered dataflow
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArrangementSnapshot {
+    spine: Vec<(Timestamp, Vec<(Row, Vec<DiffRow>)>)>,
+    event_ts_to_key: Vec<(Timestamp, Vec<Row>)>,
+    last_compaction_time: Option<Timestamp>,
+}
+
+impl Arrangement {
+    /// Take a snapshot of all updates in the arrangement, with spilled keys in the current state
+    pub fn snapshot(&self) -> Result<ArrangementSnapshot, EvalError> {
+        let mut spilled = match &self.spilled {
+            Some(spilled) => spilled.range(&Row::empty())?,
+            None => BTreeMap::new(),
+        };
+        Ok(ArrangementSnapshot {
+            spine: self
+                .spine
+                .iter()
+                .map(|(ts, batch)| {
+                    let mut batch = batch
+                        .iter()
+                        .map(|(key, updates)| (key.clone(), updates.to_vec()))
+                        .collect_vec();
+                    if Some(*ts) == self.last_compaction_time && !spilled.is_empty() {
+                        batch.extend(
+                            std::mem::take(&mut spilled)
+                                .into_iter()
+                                .map(|(key, update)| (key, vec![update])),
+                        );
+                        batch.sort_by(|(a, _), (b, _)| a.cmp(b));
+                    }
+                    (*ts, batch)
+                })
+                .collect(),
+            event_ts_to_key: self
+                .expire_state
+                .iter()
+                .flat_map(|s| s.event_ts_to_key.iter())
+                .map(|(ts, keys)| (*ts, keys.iter().cloned().collect()))
+                .collect(),
+            last_compaction_time: self.last_compaction_time,
+        })
+    }
+
+    /// Replace all updates in the arrangement with the ones in `snapshot`
+    pub fn restore(&mut self, snapshot: ArrangementSnapshot) {
+        self.spine = snapshot
+            .spine
+            .into_iter()
+            .map(|(ts, batch)| {
+                let batch = batch
+                    .into_iter()
+                    .map(|(key, updates)| (key, SmallVec::from_vec(updates)))
+                    .collect();
+                (ts, batch)
+            })
+            .collect();
+        if let Some(s) = &mut self.expire_state {
+            s.event_ts_to_key = snapshot
+                .event_ts_to_key
+                .into_iter()
+                .map(|(ts, keys)| (ts, keys.into_iter().collect()))
+                .collect();
+        }
+        self.last_compaction_time = snapshot.last_compaction_time;
+        if let Some(spilled) = &mut self.spilled {
+            spilled.clear();
+        }
+        self.is_written = true;
+    }
 }
 
 fn compact_diff_row(old_row: Option<DiffRow>, new_row: &DiffRow) -> Option<DiffRow> {
@@ -683,6 +1075,7 @@ mod test {
             event_ts_to_key: Default::default(),
             key_expiration_duration: Some(10),
             event_timestamp_from_row: Some(ScalarExpr::Column(0)),
+            expire_when: false,
         };
         arr.expire_state = Some(expire_state);
         arr.full_arrangement = true;
@@ -755,6 +1148,7 @@ mod test {
             event_ts_to_key: Default::default(),
             key_expiration_duration: Some(10),
             event_timestamp_from_row: Some(ScalarExpr::Column(0)),
+            expire_when: false,
         };
         arr.expire_state = Some(expire_state);
 
@@ -906,4 +1300,220 @@ mod test {
             Some((lit("y"), 1, 1)) /* fast path */
         );
     }
+
+    #[test]
+    fn test_get_by_prefix() {
+        let mut arr = Arrangement::default();
+        let key = |k: i64, v: i64| Row::new(vec![k.into(), v.into()]);
+        let updates = vec![
+            (
+                (key(1, 1), Row::empty()),
+                1, /* ts */
+                1, /* diff */
+            ),
+            (
+                (key(1, 2), Row::empty()),
+                1, /* ts */
+                1, /* diff */
+            ),
+            (
+                (key(2, 1), Row::empty()),
+                1, /* ts */
+                1, /* diff */
+            ),
+            (
+                (key(1, 1), Row::empty()),
+                3,  /* ts */
+                -1, /* diff */
+            ),
+        ];
+        arr.apply_updates(0, updates).unwrap();
+        let prefix = Row::new(vec![1i64.into()]);
+        assert_eq!(
+            arr.get_by_prefix(2, &prefix),
+            vec![
+                (key(1, 1), (Row::empty(), 1, 1)),
+                (key(1, 2), (Row::empty(), 1, 1))
+            ]
+        );
+        // deleted key is not returned
+        assert_eq!(
+            arr.get_by_prefix(3, &prefix),
+            vec![(key(1, 2), (Row::empty(), 1, 1))]
+        );
+    }
+
+    #[test]
+    fn test_state_size() {
+        let mut arr = Arrangement::default();
+        assert_eq!(arr.state_size(), (0, 0));
+        let updates = vec![
+            (kv(lit(1i64), lit(2i64)), 1 /* ts */, 1 /* diff */),
+            (kv(lit(1i64), lit(3i64)), 2 /* ts */, 1 /* diff */),
+        ];
+        arr.apply_updates(0, updates).unwrap();
+        // each update is a key, a value, a timestamp and a diff of 8 bytes
+        assert_eq!(arr.state_size(), (2, 2 * 4 * 8));
+    }
+
+    #[test]
+    fn test_set_expire_after() {
+        let mut arr = Arrangement::default();
+        assert!(!arr.set_expire_after(Some(10)));
+
+        arr.expire_state = Some(KeyExpiryManager::new(Some(10), Some(ScalarExpr::Column(0))));
+        let key = lit(5i64);
+        // expired by 5 with expire_after=10
+        assert_eq!(
+            arr.get_expire_state()
+                .unwrap()
+                .get_expire_duration(20, &key)
+                .unwrap(),
+            Some(5)
+        );
+        assert!(arr.set_expire_after(Some(100)));
+        assert_eq!(
+            arr.get_expire_state()
+                .unwrap()
+                .get_expire_duration(20, &key)
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_expire_when() {
+        let mut arr = Arrangement::default();
+        arr.full_arrangement = true;
+        // key row is the time it expires at
+        arr.set_expire_state(KeyExpiryManager::new_expire_when(ScalarExpr::Column(0)));
+        let updates = vec![
+            (kv(lit(5i64), lit("x")), 1 /* ts */, 1 /* diff */),
+            (kv(lit(15i64), lit("y")), 1 /* ts */, 1 /* diff */),
+        ];
+        assert_eq!(arr.apply_updates(1, updates).unwrap(), None);
+        // not affected by `expire_after`
+        assert!(arr.set_expire_after(Some(100)));
+        let expire_state = arr.get_expire_state().unwrap();
+        assert_eq!(
+            expire_state.get_expire_duration(10, &lit(5i64)).unwrap(),
+            Some(5)
+        );
+        assert_eq!(
+            expire_state.get_expire_duration(10, &lit(15i64)).unwrap(),
+            None
+        );
+
+        assert_eq!(arr.compact_to(10).unwrap(), Some(5));
+        assert_eq!(arr.get(10, &lit(5i64)), None);
+        assert!(arr.get(10, &lit(15i64)).is_some());
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let new_arr = || {
+            let mut arr = Arrangement::default();
+            arr.expire_state = Some(KeyExpiryManager::new(Some(10), Some(ScalarExpr::Column(0))));
+            arr.full_arrangement = true;
+            arr
+        };
+        let mut arr = new_arr();
+        let updates = vec![
+            (kv(lit(1i64), lit("x")), 1 /* ts */, 1 /* diff */),
+            (kv(lit(2i64), lit("y")), 2 /* ts */, 1 /* diff */),
+            (kv(lit(3i64), lit("z")), 5 /* ts */, 1 /* diff */),
+        ];
+        arr.apply_updates(0, updates).unwrap();
+        arr.compact_to(2).unwrap();
+
+        // round trip through serialization like when persisted
+        let snapshot = arr.snapshot().unwrap();
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let snapshot: ArrangementSnapshot = serde_json::from_str(&json).unwrap();
+
+        let mut restored = new_arr();
+        restored.restore(snapshot);
+        assert_eq!(restored, arr);
+        assert_eq!(restored.get(5, &lit(3i64)), Some((lit("z"), 5, 1)));
+    }
+
+    #[test]
+    fn test_evict_arrangement() {
+        let new_arr = || {
+            let mut arr = Arrangement::default();
+            arr.full_arrangement = true;
+            // key row is its event timestamp
+            arr.set_expire_state(KeyExpiryManager::new(None, Some(ScalarExpr::Column(0))));
+            let updates = vec![
+                (kv(lit(3i64), lit("a")), 1 /* ts */, 1 /* diff */),
+                (kv(lit(1i64), lit("b")), 2 /* ts */, 1 /* diff */),
+                (kv(lit(2i64), lit("c")), 3 /* ts */, 1 /* diff */),
+            ];
+            arr.apply_updates(0, updates).unwrap();
+            arr.compact_to(3).unwrap();
+            arr
+        };
+
+        // the least recently updated key is evicted first
+        let mut arr = new_arr();
+        assert_eq!(arr.evict(1, EvictionPolicy::Lru), 1);
+        assert_eq!(arr.get(3, &lit(3i64)), None);
+        assert_eq!(arr.state_size().0, 2);
+
+        // the key with the oldest event timestamp is evicted first
+        let mut arr = new_arr();
+        assert_eq!(arr.evict(1, EvictionPolicy::OldestTimestamp), 1);
+        assert_eq!(arr.get(3, &lit(1i64)), None);
+        assert_eq!(arr.get(3, &lit(3i64)), Some((lit("a"), 1, 1)));
+
+        assert_eq!(arr.evict(usize::MAX, EvictionPolicy::Lru), 2);
+        assert_eq!(arr.state_size(), (0, 0));
+    }
+
+    #[test]
+    fn test_spill_arrangement() {
+        let dir = common_test_util::temp_dir::create_temp_dir("test_spill_arrangement");
+        let mut arr = Arrangement::default();
+        arr.full_arrangement = true;
+        arr.enable_spill(dir.path().to_path_buf());
+
+        let updates = vec![
+            (kv(lit(1i64), lit("a")), 1 /* ts */, 1 /* diff */),
+            (kv(lit(2i64), lit("b")), 2 /* ts */, 1 /* diff */),
+            (kv(lit(3i64), lit("c")), 3 /* ts */, 1 /* diff */),
+        ];
+        arr.apply_updates(0, updates).unwrap();
+        arr.compact_to(3).unwrap();
+
+        // the least recently updated key is spilled first
+        assert_eq!(arr.spill(1).unwrap(), 1);
+        assert_eq!(arr.state_size().0, 3);
+        assert_eq!(arr.get(3, &lit(1i64)), Some((lit("a"), 1, 1)));
+        assert_eq!(arr.spill(usize::MAX).unwrap(), 2);
+        assert_eq!(arr.get(3, &lit(2i64)), Some((lit("b"), 2, 1)));
+        assert_eq!(arr.get_by_prefix(3, &Row::empty()).len(), 3);
+
+        // spilled keys are loaded back when updated
+        let updates = vec![
+            (kv(lit(1i64), lit("a")), 4 /* ts */, -1 /* diff */),
+            (kv(lit(2i64), lit("b")), 4 /* ts */, -1 /* diff */),
+            (kv(lit(2i64), lit("d")), 4 /* ts */, 1 /* diff */),
+        ];
+        arr.apply_updates(3, updates).unwrap();
+        arr.compact_to(4).unwrap();
+        assert_eq!(arr.get(4, &lit(1i64)), None);
+        assert_eq!(arr.get(4, &lit(2i64)), Some((lit("d"), 4, 1)));
+        assert_eq!(arr.get(4, &lit(3i64)), Some((lit("c"), 3, 1)));
+
+        // snapshot includes spilled keys
+        let snapshot = arr.snapshot().unwrap();
+        let mut restored = Arrangement::default();
+        restored.full_arrangement = true;
+        restored.restore(snapshot);
+        assert_eq!(
+            restored.get_by_prefix(4, &Row::empty()),
+            vec![(lit(2i64), (lit("d"), 4, 1)), (lit(3i64), (lit("c"), 3, 1)),]
+        );
+        assert!(arr.take_spill_errors().is_empty());
+    }
 }
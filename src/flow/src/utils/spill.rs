@@ -0,0 +1,355 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Spilling cold keys of arrangements to local disk as sorted runs, so the state of flows over
+//! high-cardinality keys is not bounded by memory
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use common_telemetry::warn;
+use itertools::Itertools;
+
+use crate::expr::error::InternalSnafu;
+use crate::expr::EvalError;
+use crate::repr::{DiffRow, Row, Timestamp};
+use crate::utils::state_backend::StateBackend;
+
+/// Number of keys in a block of a run, which is the unit of reading a run
+const BLOCK_SIZE: usize = 256;
+/// Runs are merged into one once there are more of them, which bounds the runs a lookup reads
+const MAX_RUNS: usize = 4;
+
+/// Used to name run files uniquely in this process
+static NEXT_RUN_ID: AtomicU64 = AtomicU64::new(0);
+
+/// An immutable sorted run of spilled keys, its file is deleted once dropped
+#[derive(Debug)]
+struct SpilledRun {
+    path: PathBuf,
+    /// the first key of each block, and the offset and length of the block in the file
+    index: Vec<(Row, u64, u64)>,
+    /// number of keys in the run
+    len: usize,
+}
+
+impl SpilledRun {
+    /// Write entries sorted by keys into a new run in `dir`
+    fn write(
+        dir: &Path,
+        entries: impl IntoIterator<Item = (Row, DiffRow)>,
+    ) -> Result<Self, EvalError> {
+        std::fs::create_dir_all(dir).map_err(|e| spill_err("create spill dir", e))?;
+        let path = dir.join(format!(
+            "{}.run",
+            NEXT_RUN_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+        let mut run = SpilledRun {
+            path,
+            index: vec![],
+            len: 0,
+        };
+        let file = File::create(&run.path).map_err(|e| spill_err("create spill file", e))?;
+        let mut writer = BufWriter::new(file);
+        let mut offset = 0;
+        for block in &entries.into_iter().chunks(BLOCK_SIZE) {
+            let block = block.collect_vec();
+            let bytes = serde_json::to_vec(&block).map_err(|e| spill_err("encode block", e))?;
+            writer
+                .write_all(&bytes)
+                .map_err(|e| spill_err("write spill file", e))?;
+            run.index
+                .push((block[0].0.clone(), offset, bytes.len() as u64));
+            offset += bytes.len() as u64;
+            run.len += block.len();
+        }
+        writer
+            .flush()
+            .map_err(|e| spill_err("write spill file", e))?;
+        Ok(run)
+    }
+
+    fn read_block(&self, i: usize) -> Result<Vec<(Row, DiffRow)>, EvalError> {
+        let (_, offset, len) = &self.index[i];
+        let mut file = File::open(&self.path).map_err(|e| spill_err("open spill file", e))?;
+        file.seek(SeekFrom::Start(*offset))
+            .map_err(|e| spill_err("read spill file", e))?;
+        let mut buf = vec![0; *len as usize];
+        file.read_exact(&mut buf)
+            .map_err(|e| spill_err("read spill file", e))?;
+        serde_json::from_slice(&buf).map_err(|e| spill_err("decode block", e))
+    }
+
+    /// The only block that may contain `key`
+    fn block_of(&self, key: &Row) -> Option<usize> {
+        self.index
+            .partition_point(|(first, _, _)| first <= key)
+            .checked_sub(1)
+    }
+
+    fn get(&self, key: &Row) -> Result<Option<DiffRow>, EvalError> {
+        let Some(i) = self.block_of(key) else {
+            return Ok(None);
+        };
+        let mut block = self.read_block(i)?;
+        Ok(block
+            .binary_search_by(|(k, _)| k.cmp(key))
+            .ok()
+            .map(|j| block.swap_remove(j).1))
+    }
+
+    /// All entries with keys starting with `prefix`, in the order of keys
+    fn get_by_prefix(&self, prefix: &Row) -> Result<Vec<(Row, DiffRow)>, EvalError> {
+        let start = self.block_of(prefix).unwrap_or(0);
+        let mut res = vec![];
+        for i in start..self.index.len() {
+            // keys with the prefix are contiguous, and blocks after `start` begin after `prefix`
+            if i > start && !self.index[i].0.inner.starts_with(&prefix.inner) {
+                break;
+            }
+            res.extend(
+                self.read_block(i)?
+                    .into_iter()
+                    .filter(|(key, _)| key.inner.starts_with(&prefix.inner)),
+            );
+        }
+        Ok(res)
+    }
+
+    fn read_all(&self) -> Result<Vec<(Row, DiffRow)>, EvalError> {
+        let mut res = Vec::with_capacity(self.len);
+        for i in 0..self.index.len() {
+            res.extend(self.read_block(i)?);
+        }
+        Ok(res)
+    }
+}
+
+impl Drop for SpilledRun {
+    fn drop(&mut self) {
+        if let Err(err) = std::fs::remove_file(&self.path) {
+            warn!(err; "Failed to remove spill file {}", self.path.display());
+        }
+    }
+}
+
+/// Keys spilled from the current state of an arrangement, as sorted runs on local disk
+///
+/// A key is either in memory or spilled, a spilled key is loaded back into memory by
+/// [`StateBackend::take`] once it's updated. Cloning is cheap, since runs are immutable and shared.
+///
+/// Runs are deleted once dropped, so the state is recovered from checkpoints after a restart.
+#[derive(Debug, Clone)]
+pub struct SpilledRuns {
+    dir: PathBuf,
+    /// newest first, a key in a newer run overrides the ones in older runs
+    runs: Vec<Arc<SpilledRun>>,
+    /// keys whose spilled values are stale, since they're loaded back into memory or removed
+    shadowed: BTreeSet<Row>,
+    /// errors of reads which can't return them, see [`StateBackend::get_or_report`]
+    errors: Arc<Mutex<Vec<EvalError>>>,
+}
+
+impl SpilledRuns {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            runs: vec![],
+            shadowed: Default::default(),
+            errors: Default::default(),
+        }
+    }
+
+    /// All spilled entries
+    fn read_all(&self) -> Result<BTreeMap<Row, DiffRow>, EvalError> {
+        let mut res = BTreeMap::new();
+        for run in &self.runs {
+            for (key, val) in run.read_all()? {
+                if !self.shadowed.contains(&key) {
+                    res.entry(key).or_insert(val);
+                }
+            }
+        }
+        Ok(res)
+    }
+
+    /// Replace all runs with one of `entries`
+    fn rewrite(&mut self, entries: BTreeMap<Row, DiffRow>) -> Result<(), EvalError> {
+        self.runs = if entries.is_empty() {
+            vec![]
+        } else {
+            vec![Arc::new(SpilledRun::write(&self.dir, entries)?)]
+        };
+        self.shadowed.clear();
+        Ok(())
+    }
+}
+
+impl StateBackend for SpilledRuns {
+    fn get(&self, key: &Row) -> Result<Option<DiffRow>, EvalError> {
+        if self.shadowed.contains(key) {
+            return Ok(None);
+        }
+        for run in &self.runs {
+            if let Some(val) = run.get(key)? {
+                return Ok(Some(val));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Spill entries into a new run, merging all runs into one if there are too many
+    fn put(&mut self, entries: BTreeMap<Row, DiffRow>) -> Result<(), EvalError> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        for key in entries.keys() {
+            self.shadowed.remove(key);
+        }
+        let run = SpilledRun::write(&self.dir, entries)?;
+        self.runs.insert(0, Arc::new(run));
+        if self.runs.len() > MAX_RUNS {
+            let all = self.read_all()?;
+            self.rewrite(all)?;
+        }
+        Ok(())
+    }
+
+    fn range(&self, prefix: &Row) -> Result<BTreeMap<Row, DiffRow>, EvalError> {
+        let mut res = BTreeMap::new();
+        for run in &self.runs {
+            for (key, val) in run.get_by_prefix(prefix)? {
+                if !self.shadowed.contains(&key) {
+                    res.entry(key).or_insert(val);
+                }
+            }
+        }
+        Ok(res)
+    }
+
+    /// Mark `key` stale if it's spilled, it's dropped once runs are rewritten
+    fn remove(&mut self, key: &Row) {
+        if !self.runs.is_empty() {
+            self.shadowed.insert(key.clone());
+        }
+    }
+
+    fn truncate(&mut self, up_to: Timestamp) -> Result<usize, EvalError> {
+        if self.runs.is_empty() {
+            return Ok(0);
+        }
+        let (kept, truncated): (BTreeMap<_, _>, BTreeMap<_, _>) = self
+            .read_all()?
+            .into_iter()
+            .partition(|(_, (_, ts, _))| *ts > up_to);
+        self.rewrite(kept)?;
+        Ok(truncated.len())
+    }
+
+    /// Number of keys spilled, including stale ones not merged away yet
+    fn len(&self) -> usize {
+        self.runs.iter().map(|run| run.len).sum()
+    }
+
+    fn report(&self, err: &EvalError) {
+        let err = spill_err("read spilled keys", err);
+        self.errors.lock().unwrap().push(err);
+    }
+
+    fn take_errors(&self) -> Vec<EvalError> {
+        std::mem::take(&mut *self.errors.lock().unwrap())
+    }
+
+    fn boxed_clone(&self) -> Box<dyn StateBackend> {
+        Box::new(self.clone())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.runs.is_empty()
+    }
+}
+
+fn spill_err(action: &str, err: impl Display) -> EvalError {
+    InternalSnafu {
+        reason: format!("Failed to {action}: {err}"),
+    }
+    .build()
+}
+
+#[cfg(test)]
+mod test {
+    use common_test_util::temp_dir::create_temp_dir;
+    use datatypes::value::Value;
+
+    use super::*;
+
+    fn lit(v: impl Into<Value>) -> Row {
+        Row::new(vec![v.into()])
+    }
+
+    #[test]
+    fn test_spilled_runs() {
+        let dir = create_temp_dir("test_spilled_runs");
+        let mut runs = SpilledRuns::new(dir.path().to_path_buf());
+        let entries = (0..1000i64)
+            .map(|i| (lit(i), (lit(i * 10), 1, 1)))
+            .collect::<BTreeMap<_, _>>();
+        runs.put(entries).unwrap();
+        assert_eq!(runs.len(), 1000);
+        assert_eq!(runs.get(&lit(0i64)).unwrap(), Some((lit(0i64), 1, 1)));
+        assert_eq!(runs.get(&lit(999i64)).unwrap(), Some((lit(9990i64), 1, 1)));
+        assert_eq!(runs.get(&lit(1000i64)).unwrap(), None);
+
+        // a key loaded back into memory is no longer seen, until it's spilled again
+        assert_eq!(runs.take(&lit(5i64)).unwrap(), Some((lit(50i64), 1, 1)));
+        assert_eq!(runs.get(&lit(5i64)).unwrap(), None);
+        runs.put(BTreeMap::from([(lit(5i64), (lit(51i64), 2, 1))]))
+            .unwrap();
+        assert_eq!(runs.get(&lit(5i64)).unwrap(), Some((lit(51i64), 2, 1)));
+        runs.remove(&lit(6i64));
+        assert_eq!(runs.get(&lit(6i64)).unwrap(), None);
+
+        // runs are merged once there are too many, without stale entries
+        for i in 0..MAX_RUNS as i64 - 1 {
+            runs.put(BTreeMap::from([(lit(2000 + i), (lit(i), 3, 1))]))
+                .unwrap();
+        }
+        assert_eq!(runs.runs.len(), 1);
+        assert_eq!(runs.len(), 999 + MAX_RUNS - 1);
+        assert_eq!(runs.get(&lit(5i64)).unwrap(), Some((lit(51i64), 2, 1)));
+        assert_eq!(runs.get(&lit(6i64)).unwrap(), None);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_spilled_runs_get_by_prefix() {
+        let dir = create_temp_dir("test_spilled_runs_get_by_prefix");
+        let mut runs = SpilledRuns::new(dir.path().to_path_buf());
+        let entries = (0..10i64)
+            .flat_map(|i| (0..100i64).map(move |j| (Row::new(vec![i.into(), j.into()]), i)))
+            .map(|(key, i)| (key, (lit(i), 1, 1)))
+            .collect::<BTreeMap<_, _>>();
+        runs.put(entries).unwrap();
+        runs.remove(&Row::new(vec![3i64.into(), 0i64.into()]));
+        let res = runs.range(&lit(3i64)).unwrap();
+        assert_eq!(res.len(), 99);
+        assert!(res.values().all(|(val, _, _)| *val == lit(3i64)));
+        assert!(runs.range(&lit(10i64)).unwrap().is_empty());
+    }
+}
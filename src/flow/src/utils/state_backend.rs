@@ -0,0 +1,201 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Pluggable backends keeping the cold keys of arrangements' current state, so the state of a
+//! flow isn't bounded by memory
+
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+use crate::expr::EvalError;
+use crate::repr::{DiffRow, Row, Timestamp};
+
+/// A store of the current state of some keys of an arrangement, each key with its latest value,
+/// the tick it's last updated at and its multiplicity
+///
+/// Keys in a backend and the ones in memory of the arrangement are disjoint, a key is taken back
+/// into memory by [`StateBackend::take`] once it's updated
+pub trait StateBackend: Debug + Send + Sync {
+    fn get(&self, key: &Row) -> Result<Option<DiffRow>, EvalError>;
+
+    /// Put entries into the backend, replacing the values of existing keys
+    fn put(&mut self, entries: BTreeMap<Row, DiffRow>) -> Result<(), EvalError>;
+
+    /// All entries with keys starting with `prefix`, which are all entries if `prefix` is empty
+    fn range(&self, prefix: &Row) -> Result<BTreeMap<Row, DiffRow>, EvalError>;
+
+    /// Remove `key` if it's in the backend
+    fn remove(&mut self, key: &Row);
+
+    /// Remove entries last updated at or before tick `up_to`, return the number of entries removed
+    fn truncate(&mut self, up_to: Timestamp) -> Result<usize, EvalError>;
+
+    /// Number of entries, which may include stale ones not cleaned up yet
+    fn len(&self) -> usize;
+
+    /// Keep the error of a read which can't return it, to be taken by [`StateBackend::take_errors`]
+    fn report(&self, err: &EvalError);
+
+    /// Take errors of reads which can't return them
+    fn take_errors(&self) -> Vec<EvalError>;
+
+    fn boxed_clone(&self) -> Box<dyn StateBackend>;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the value of `key` and remove it, since it's loaded back into memory
+    fn take(&mut self, key: &Row) -> Result<Option<DiffRow>, EvalError> {
+        let val = self.get(key)?;
+        if val.is_some() {
+            self.remove(key);
+        }
+        Ok(val)
+    }
+
+    /// Like [`StateBackend::get`], but the error is reported, for reads which can't return errors
+    fn get_or_report(&self, key: &Row) -> Option<DiffRow> {
+        self.get(key)
+            .inspect_err(|err| self.report(err))
+            .ok()
+            .flatten()
+    }
+
+    /// Remove all entries
+    fn clear(&mut self) {
+        if let Err(err) = self.truncate(Timestamp::MAX) {
+            self.report(&err);
+        }
+    }
+}
+
+impl Clone for Box<dyn StateBackend> {
+    fn clone(&self) -> Self {
+        self.boxed_clone()
+    }
+}
+
+/// Two backends are equal if they hold the same entries, regardless of how they're stored
+impl PartialEq for dyn StateBackend {
+    fn eq(&self, other: &Self) -> bool {
+        self.range(&Row::empty()).ok() == other.range(&Row::empty()).ok()
+    }
+}
+
+impl Eq for dyn StateBackend {}
+
+/// Keeps entries in memory, reads never fail
+impl StateBackend for BTreeMap<Row, DiffRow> {
+    fn get(&self, key: &Row) -> Result<Option<DiffRow>, EvalError> {
+        Ok(BTreeMap::get(self, key).cloned())
+    }
+
+    fn put(&mut self, entries: BTreeMap<Row, DiffRow>) -> Result<(), EvalError> {
+        self.extend(entries);
+        Ok(())
+    }
+
+    fn range(&self, prefix: &Row) -> Result<BTreeMap<Row, DiffRow>, EvalError> {
+        Ok(BTreeMap::range(self, prefix.clone()..)
+            .take_while(|(key, _)| key.inner.starts_with(&prefix.inner))
+            .map(|(key, val)| (key.clone(), val.clone()))
+            .collect())
+    }
+
+    fn remove(&mut self, key: &Row) {
+        BTreeMap::remove(self, key);
+    }
+
+    fn truncate(&mut self, up_to: Timestamp) -> Result<usize, EvalError> {
+        let len = BTreeMap::len(self);
+        self.retain(|_, (_, ts, _)| *ts > up_to);
+        Ok(len - BTreeMap::len(self))
+    }
+
+    fn len(&self) -> usize {
+        BTreeMap::len(self)
+    }
+
+    fn report(&self, _err: &EvalError) {}
+
+    fn take_errors(&self) -> Vec<EvalError> {
+        vec![]
+    }
+
+    fn boxed_clone(&self) -> Box<dyn StateBackend> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use common_test_util::temp_dir::create_temp_dir;
+    use datatypes::value::Value;
+
+    use super::*;
+    use crate::utils::SpilledRuns;
+
+    fn lit(v: impl Into<Value>) -> Row {
+        Row::new(vec![v.into()])
+    }
+
+    /// every backend should behave the same
+    #[test]
+    fn test_state_backends() {
+        let dir = create_temp_dir("test_state_backends");
+        let backends: Vec<Box<dyn StateBackend>> = vec![
+            Box::new(BTreeMap::<Row, DiffRow>::new()),
+            Box::new(SpilledRuns::new(dir.path().to_path_buf())),
+        ];
+        for mut backend in backends {
+            assert!(backend.is_empty());
+            let entries = (0..10i64)
+                .map(|i| (Row::new(vec![(i % 2).into(), i.into()]), (lit(i), i, 1)))
+                .collect::<BTreeMap<_, _>>();
+            backend.put(entries).unwrap();
+            assert_eq!(
+                backend
+                    .get(&Row::new(vec![1i64.into(), 3i64.into()]))
+                    .unwrap(),
+                Some((lit(3i64), 3, 1))
+            );
+            assert_eq!(backend.range(&lit(1i64)).unwrap().len(), 5);
+            assert_eq!(backend.range(&Row::empty()).unwrap().len(), 10);
+
+            // a taken key is no longer in the backend
+            let key = Row::new(vec![0i64.into(), 4i64.into()]);
+            assert_eq!(backend.take(&key).unwrap(), Some((lit(4i64), 4, 1)));
+            assert_eq!(backend.get(&key).unwrap(), None);
+
+            // keys last updated at or before tick 5 are truncated
+            assert_eq!(backend.truncate(5).unwrap(), 5);
+            assert_eq!(
+                backend
+                    .range(&Row::empty())
+                    .unwrap()
+                    .into_values()
+                    .map(|(_, ts, _)| ts)
+                    .collect::<Vec<_>>(),
+                vec![6, 8, 7, 9]
+            );
+
+            let cloned = backend.clone();
+            assert!(*cloned == *backend);
+            backend.clear();
+            assert!(backend.range(&Row::empty()).unwrap().is_empty());
+            assert!(backend.take_errors().is_empty());
+        }
+    }
+}
@@ -306,6 +306,13 @@ pub enum Error {
         name: String,
     },
 
+    #[snafu(display("Unexpected output: {}", reason))]
+    UnexpectedOutput {
+        reason: String,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
     #[snafu(display("Invalid tls config"))]
     InvalidTlsConfig {
         #[snafu(source)]
@@ -361,6 +368,8 @@ impl ErrorExt for Error {
 
             Error::CacheRequired { .. } => StatusCode::Internal,
 
+            Error::UnexpectedOutput { .. } => StatusCode::Unexpected,
+
             Error::InvalidRegionRequest { .. } => StatusCode::IllegalState,
 
             Error::TableNotFound { .. } => StatusCode::TableNotFound,
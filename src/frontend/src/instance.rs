@@ -73,6 +73,7 @@ use snafu::prelude::*;
 use sql::dialect::Dialect;
 use sql::parser::{ParseOptions, ParserContext};
 use sql::statements::copy::{CopyDatabase, CopyTable};
+use sql::statements::explain::ExplainFlow;
 use sql::statements::statement::Statement;
 use sqlparser::ast::ObjectName;
 pub use standalone::StandaloneDatanodeManager;
@@ -451,6 +452,7 @@ pub fn check_permission(
         | Statement::ShowDatabases(_)
         | Statement::DropDatabase(_)
         | Statement::DropFlow(_)
+        | Statement::AlterFlow(_)
         | Statement::Use(_) => {}
         Statement::ShowCreateDatabase(stmt) => {
             validate_database(&stmt.database_name, query_ctx)?;
@@ -461,6 +463,12 @@ pub fn check_permission(
         Statement::ShowCreateFlow(stmt) => {
             validate_param(&stmt.flow_name, query_ctx)?;
         }
+        Statement::ExplainFlow(ExplainFlow::Flow(flow_name)) => {
+            validate_param(flow_name, query_ctx)?;
+        }
+        Statement::ExplainFlow(ExplainFlow::Create(stmt)) => {
+            validate_param(&stmt.sink_table_name, query_ctx)?;
+        }
         Statement::ShowCreateView(stmt) => {
             validate_param(&stmt.view_name, query_ctx)?;
         }
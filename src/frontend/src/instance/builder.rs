@@ -137,12 +137,13 @@ impl FrontendBuilder {
             self.catalog_manager.clone(),
             partition_manager.clone(),
             node_manager.clone(),
-            table_flownode_cache,
+            table_flownode_cache.clone(),
         ));
         let deleter = Arc::new(Deleter::new(
             self.catalog_manager.clone(),
             partition_manager.clone(),
             node_manager.clone(),
+            table_flownode_cache,
         ));
         let requester = Arc::new(Requester::new(
             self.catalog_manager.clone(),
@@ -160,7 +161,11 @@ impl FrontendBuilder {
         ));
 
         let flow_metadata_manager = Arc::new(FlowMetadataManager::new(kv_backend.clone()));
-        let flow_service = FlowServiceOperator::new(flow_metadata_manager, node_manager.clone());
+        let flow_service = FlowServiceOperator::new(
+            flow_metadata_manager,
+            node_manager.clone(),
+            inserter.flow_mirror().clone(),
+        );
 
         let query_engine = QueryEngineFactory::new_with_plugins(
             self.catalog_manager.clone(),
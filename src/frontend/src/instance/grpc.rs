@@ -18,7 +18,10 @@ use api::v1::query_request::Query;
 use api::v1::{DeleteRequests, DropFlowExpr, InsertRequests, RowDeleteRequests, RowInsertRequests};
 use async_trait::async_trait;
 use auth::{PermissionChecker, PermissionCheckerRef, PermissionReq};
-use common_query::Output;
+use common_base::AffectedRows;
+use common_catalog::format_full_table_name;
+use common_query::{Output, OutputData};
+use common_recordbatch::RecordBatch;
 use common_telemetry::tracing;
 use query::parser::PromQuery;
 use servers::interceptor::{GrpcQueryInterceptor, GrpcQueryInterceptorRef};
@@ -26,11 +29,13 @@ use servers::query_handler::grpc::GrpcQueryHandler;
 use servers::query_handler::sql::SqlQueryHandler;
 use session::context::QueryContextRef;
 use snafu::{ensure, OptionExt, ResultExt};
+use table::requests::InsertRequest as TableInsertRequest;
 use table::table_name::TableName;
 
 use crate::error::{
-    Error, IncompleteGrpcRequestSnafu, NotSupportedSnafu, PermissionSnafu, Result,
-    TableOperationSnafu,
+    CatalogSnafu, ColumnNotFoundSnafu, Error, IncompleteGrpcRequestSnafu,
+    InvalidInsertRequestSnafu, NotSupportedSnafu, PermissionSnafu, Result, TableNotFoundSnafu,
+    TableOperationSnafu, UnexpectedOutputSnafu,
 };
 use crate::instance::{attach_timer, Instance};
 use crate::metrics::{GRPC_HANDLE_PROMQL_ELAPSED, GRPC_HANDLE_SQL_ELAPSED};
@@ -177,6 +182,84 @@ impl GrpcQueryHandler for Instance {
         let output = interceptor.post_execute(output, ctx)?;
         Ok(output)
     }
+
+    async fn put_record_batch(
+        &self,
+        table_name: &str,
+        record_batch: RecordBatch,
+        ctx: QueryContextRef,
+    ) -> Result<AffectedRows> {
+        let interceptor_ref = self.plugins.get::<GrpcQueryInterceptorRef<Error>>();
+        let interceptor = interceptor_ref.as_ref();
+        interceptor.pre_put_record_batch(table_name, &record_batch, ctx.clone())?;
+
+        self.plugins
+            .get::<PermissionCheckerRef>()
+            .as_ref()
+            .check_permission(ctx.current_user(), PermissionReq::BulkInsert)
+            .context(PermissionSnafu)?;
+
+        let catalog_name = ctx.current_catalog().to_string();
+        let schema_name = ctx.current_schema();
+        let table = self
+            .catalog_manager
+            .table(&catalog_name, &schema_name, table_name, Some(&ctx))
+            .await
+            .context(CatalogSnafu)?
+            .with_context(|| TableNotFoundSnafu {
+                table_name: format_full_table_name(&catalog_name, &schema_name, table_name),
+            })?;
+        let table_schema = table.schema();
+        for column in record_batch.schema.column_schemas() {
+            let expected = table_schema
+                .column_schema_by_name(&column.name)
+                .with_context(|| ColumnNotFoundSnafu {
+                    msg: format!(
+                        "unable to find column {} in table {table_name}",
+                        column.name
+                    ),
+                })?;
+            ensure!(
+                expected.data_type == column.data_type,
+                InvalidInsertRequestSnafu {
+                    reason: format!(
+                        "column {} is expected to be {:?}, actual: {:?}",
+                        column.name, expected.data_type, column.data_type
+                    ),
+                }
+            );
+        }
+        if record_batch.num_rows() == 0 {
+            return Ok(0);
+        }
+
+        let columns_values = record_batch
+            .schema
+            .column_schemas()
+            .iter()
+            .map(|column| column.name.clone())
+            .zip(record_batch.columns().iter().cloned())
+            .collect();
+        let request = TableInsertRequest {
+            catalog_name,
+            schema_name,
+            table_name: table_name.to_string(),
+            columns_values,
+        };
+        let output = self
+            .inserter
+            .handle_table_insert(request, ctx.clone())
+            .await
+            .context(TableOperationSnafu)?;
+        let output = interceptor.post_execute(output, ctx)?;
+        match output.data {
+            OutputData::AffectedRows(rows) => Ok(rows),
+            OutputData::Stream(_) | OutputData::RecordBatches(_) => UnexpectedOutputSnafu {
+                reason: "expect affected rows of the inserted record batch",
+            }
+            .fail(),
+        }
+    }
 }
 
 fn fill_catalog_and_schema_from_context(ddl_expr: &mut DdlExpr, ctx: &QueryContextRef) {
@@ -70,6 +70,7 @@ mod python {
     use arc_swap::ArcSwap;
     use catalog::RegisterSystemTableRequest;
     use common_error::ext::{BoxedError, ErrorExt};
+    use common_recordbatch::RecordBatch;
     use common_telemetry::{error, info};
     use script::manager::ScriptManager;
     use servers::query_handler::grpc::GrpcQueryHandler;
@@ -103,6 +104,15 @@ mod python {
         ) -> std::result::Result<Output, Self::Error> {
             unreachable!();
         }
+
+        async fn put_record_batch(
+            &self,
+            _table_name: &str,
+            _record_batch: RecordBatch,
+            _ctx: QueryContextRef,
+        ) -> std::result::Result<usize, Self::Error> {
+            unreachable!();
+        }
     }
 
     pub struct ScriptExecutor {
@@ -18,10 +18,16 @@ mod load_balance;
 mod procedure;
 
 mod cluster;
+mod retry;
 mod store;
+mod summary;
 mod util;
 
+use std::future::Future;
+use std::time::{Duration, Instant};
+
 use api::v1::meta::{ProcedureDetailResponse, Role};
+use ask_leader::LeaderWatch;
 use cluster::Client as ClusterClient;
 use common_error::ext::BoxedError;
 use common_grpc::channel_manager::{ChannelConfig, ChannelManager};
@@ -41,16 +47,23 @@ use common_meta::rpc::store::{
     DeleteRangeResponse, PutRequest, PutResponse, RangeRequest, RangeResponse,
 };
 use common_meta::ClusterId;
-use common_telemetry::info;
+use common_telemetry::{info, warn};
 use heartbeat::Client as HeartbeatClient;
 use procedure::Client as ProcedureClient;
 use snafu::{OptionExt, ResultExt};
 use store::Client as StoreClient;
+use summary::RequestSummary;
+use tokio::sync::watch;
 
 pub use self::heartbeat::{HeartbeatSender, HeartbeatStream};
+pub use self::retry::RetryPolicy;
 use crate::error::{
     ConvertMetaRequestSnafu, ConvertMetaResponseSnafu, Error, NotStartedSnafu, Result,
 };
+use crate::metrics::{
+    METRIC_META_CLIENT_REQUEST_ELAPSED, METRIC_META_CLIENT_REQUEST_ERRORS,
+    METRIC_META_CLIENT_SLOW_REQUESTS,
+};
 
 pub type Id = (u64, u64);
 
@@ -69,6 +82,9 @@ pub struct MetaClientBuilder {
     channel_manager: Option<ChannelManager>,
     ddl_channel_manager: Option<ChannelManager>,
     heartbeat_channel_manager: Option<ChannelManager>,
+    follower_reads: Option<Duration>,
+    slow_call_threshold: Option<Duration>,
+    retry_policy: RetryPolicy,
 }
 
 impl MetaClientBuilder {
@@ -154,12 +170,39 @@ impl MetaClientBuilder {
         }
     }
 
+    /// Sends the reads of the store to the followers of metasrv, to reduce the load of the
+    /// leader. Writes are sent to the leader, and so are the reads within `max_staleness` after
+    /// a write, since followers may not see the write yet.
+    pub fn enable_follower_reads(self, max_staleness: Duration) -> Self {
+        Self {
+            follower_reads: Some(max_staleness),
+            ..self
+        }
+    }
+
+    /// Logs the requests slower than the `threshold`, with summaries of them.
+    pub fn slow_call_threshold(self, threshold: Duration) -> Self {
+        Self {
+            slow_call_threshold: Some(threshold),
+            ..self
+        }
+    }
+
+    /// Sets how the requests are retried by whether it's safe to send them again.
+    pub fn retry_policy(self, retry_policy: RetryPolicy) -> Self {
+        Self {
+            retry_policy,
+            ..self
+        }
+    }
+
     pub fn build(self) -> MetaClient {
         let mut client = if let Some(mgr) = self.channel_manager {
             MetaClient::with_channel_manager(self.id, mgr)
         } else {
             MetaClient::new(self.id)
         };
+        client.slow_call_threshold = self.slow_call_threshold;
 
         let mgr = client.channel_manager.clone();
 
@@ -170,11 +213,19 @@ impl MetaClientBuilder {
                 self.role,
                 mgr,
                 DEFAULT_ASK_LEADER_MAX_RETRY,
+                client.leader.clone(),
             ));
         }
 
         if self.enable_store {
-            client.store = Some(StoreClient::new(self.id, self.role, mgr.clone()));
+            client.store = Some(StoreClient::new(
+                self.id,
+                self.role,
+                mgr.clone(),
+                client.leader.clone(),
+                self.follower_reads,
+                self.retry_policy.clone(),
+            ));
         }
 
         if self.enable_procedure {
@@ -184,6 +235,8 @@ impl MetaClientBuilder {
                 self.role,
                 mgr,
                 DEFAULT_SUBMIT_DDL_MAX_RETRY,
+                self.retry_policy.clone(),
+                client.leader.clone(),
             ));
         }
 
@@ -193,6 +246,8 @@ impl MetaClientBuilder {
                 self.role,
                 mgr,
                 DEFAULT_CLUSTER_CLIENT_MAX_RETRY,
+                self.retry_policy,
+                client.leader.clone(),
             ))
         }
 
@@ -204,10 +259,12 @@ impl MetaClientBuilder {
 pub struct MetaClient {
     id: Id,
     channel_manager: ChannelManager,
+    leader: LeaderWatch,
     heartbeat: Option<HeartbeatClient>,
     store: Option<StoreClient>,
     procedure: Option<ProcedureClient>,
     cluster: Option<ClusterClient>,
+    slow_call_threshold: Option<Duration>,
 }
 
 #[async_trait::async_trait]
@@ -385,6 +442,12 @@ impl MetaClient {
         self.heartbeat_client()?.ask_leader().await
     }
 
+    /// Returns a receiver notified once the leader of `metasrv` changes, found by any of the
+    /// clients, e.g., the heartbeat stream is rejected by the old leader.
+    pub fn subscribe_leader_change(&self) -> watch::Receiver<Option<String>> {
+        self.leader.subscribe()
+    }
+
     /// Returns a heartbeat bidirectional streaming: (sender, recever), the
     /// other end is the leader of `metasrv`.
     ///
@@ -397,47 +460,52 @@ impl MetaClient {
 
     /// Range gets the keys in the range from the key-value store.
     pub async fn range(&self, req: RangeRequest) -> Result<RangeResponse> {
-        self.store_client()?
-            .range(req.into())
-            .await?
-            .try_into()
-            .context(ConvertMetaResponseSnafu)
+        let summary = self.summarize(|| req.summary());
+        self.observe("range", summary, async {
+            let res = self.store_client()?.range(req.into()).await?;
+            RangeResponse::try_from(res).context(ConvertMetaResponseSnafu)
+        })
+        .await
     }
 
     /// Put puts the given key into the key-value store.
     pub async fn put(&self, req: PutRequest) -> Result<PutResponse> {
-        self.store_client()?
-            .put(req.into())
-            .await?
-            .try_into()
-            .context(ConvertMetaResponseSnafu)
+        let summary = self.summarize(|| req.summary());
+        self.observe("put", summary, async {
+            let res = self.store_client()?.put(req.into()).await?;
+            PutResponse::try_from(res).context(ConvertMetaResponseSnafu)
+        })
+        .await
     }
 
     /// BatchGet atomically get values by the given keys from the key-value store.
     pub async fn batch_get(&self, req: BatchGetRequest) -> Result<BatchGetResponse> {
-        self.store_client()?
-            .batch_get(req.into())
-            .await?
-            .try_into()
-            .context(ConvertMetaResponseSnafu)
+        let summary = self.summarize(|| req.summary());
+        self.observe("batch_get", summary, async {
+            let res = self.store_client()?.batch_get(req.into()).await?;
+            BatchGetResponse::try_from(res).context(ConvertMetaResponseSnafu)
+        })
+        .await
     }
 
     /// BatchPut atomically puts the given keys into the key-value store.
     pub async fn batch_put(&self, req: BatchPutRequest) -> Result<BatchPutResponse> {
-        self.store_client()?
-            .batch_put(req.into())
-            .await?
-            .try_into()
-            .context(ConvertMetaResponseSnafu)
+        let summary = self.summarize(|| req.summary());
+        self.observe("batch_put", summary, async {
+            let res = self.store_client()?.batch_put(req.into()).await?;
+            BatchPutResponse::try_from(res).context(ConvertMetaResponseSnafu)
+        })
+        .await
     }
 
     /// BatchDelete atomically deletes the given keys from the key-value store.
     pub async fn batch_delete(&self, req: BatchDeleteRequest) -> Result<BatchDeleteResponse> {
-        self.store_client()?
-            .batch_delete(req.into())
-            .await?
-            .try_into()
-            .context(ConvertMetaResponseSnafu)
+        let summary = self.summarize(|| req.summary());
+        self.observe("batch_delete", summary, async {
+            let res = self.store_client()?.batch_delete(req.into()).await?;
+            BatchDeleteResponse::try_from(res).context(ConvertMetaResponseSnafu)
+        })
+        .await
     }
 
     /// CompareAndPut atomically puts the value to the given updated
@@ -446,25 +514,41 @@ impl MetaClient {
         &self,
         req: CompareAndPutRequest,
     ) -> Result<CompareAndPutResponse> {
-        self.store_client()?
-            .compare_and_put(req.into())
-            .await?
-            .try_into()
-            .context(ConvertMetaResponseSnafu)
+        let summary = self.summarize(|| req.summary());
+        self.observe("compare_and_put", summary, async {
+            let res = self.store_client()?.compare_and_put(req.into()).await?;
+            CompareAndPutResponse::try_from(res).context(ConvertMetaResponseSnafu)
+        })
+        .await
     }
 
     /// DeleteRange deletes the given range from the key-value store.
     pub async fn delete_range(&self, req: DeleteRangeRequest) -> Result<DeleteRangeResponse> {
-        self.store_client()?
-            .delete_range(req.into())
-            .await?
-            .try_into()
-            .context(ConvertMetaResponseSnafu)
+        let summary = self.summarize(|| req.summary());
+        self.observe("delete_range", summary, async {
+            let res = self.store_client()?.delete_range(req.into()).await?;
+            DeleteRangeResponse::try_from(res).context(ConvertMetaResponseSnafu)
+        })
+        .await
     }
 
     /// Query the procedure state by its id.
     pub async fn query_procedure_state(&self, pid: &str) -> Result<ProcedureStateResponse> {
-        self.procedure_client()?.query_procedure_state(pid).await
+        let summary = self.summarize(|| format!("procedure_id: {pid}"));
+        self.observe("query_procedure_state", summary, async {
+            self.procedure_client()?.query_procedure_state(pid).await
+        })
+        .await
+    }
+
+    /// Waits for the procedure until it's done and returns its final state, or fails if the
+    /// procedure failed or it's not done within the `timeout`.
+    pub async fn wait_procedure(
+        &self,
+        pid: &str,
+        timeout: Duration,
+    ) -> Result<ProcedureStateResponse> {
+        self.procedure_client()?.wait_procedure(pid, timeout).await
     }
 
     /// Submit a region migration task.
@@ -472,14 +556,18 @@ impl MetaClient {
         &self,
         request: MigrateRegionRequest,
     ) -> Result<MigrateRegionResponse> {
-        self.procedure_client()?
-            .migrate_region(
-                request.region_id,
-                request.from_peer,
-                request.to_peer,
-                request.timeout,
-            )
-            .await
+        let summary = self.summarize(|| request.summary());
+        self.observe("migrate_region", summary, async {
+            self.procedure_client()?
+                .migrate_region(
+                    request.region_id,
+                    request.from_peer,
+                    request.to_peer,
+                    request.timeout,
+                )
+                .await
+        })
+        .await
     }
 
     /// Submit a DDL task
@@ -487,14 +575,57 @@ impl MetaClient {
         &self,
         req: SubmitDdlTaskRequest,
     ) -> Result<SubmitDdlTaskResponse> {
-        let res = self
-            .procedure_client()?
-            .submit_ddl_task(req.try_into().context(ConvertMetaRequestSnafu)?)
-            .await?
-            .try_into()
-            .context(ConvertMetaResponseSnafu)?;
+        let summary = self.summarize(|| req.summary());
+        self.observe("submit_ddl_task", summary, async {
+            let res = self
+                .procedure_client()?
+                .submit_ddl_task(req.try_into().context(ConvertMetaRequestSnafu)?)
+                .await?;
+            SubmitDdlTaskResponse::try_from(res).context(ConvertMetaResponseSnafu)
+        })
+        .await
+    }
 
-        Ok(res)
+    /// Returns the summary of a request if slow calls are logged, so it's not built otherwise.
+    fn summarize(&self, summary: impl FnOnce() -> String) -> Option<String> {
+        self.slow_call_threshold.map(|_| summary())
+    }
+
+    /// Waits for the request, recording its elapsed time and failure by the `method`, and logs
+    /// it with its `summary` if it's slower than the slow call threshold.
+    async fn observe<T>(
+        &self,
+        method: &'static str,
+        summary: Option<String>,
+        request: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        let start = Instant::now();
+        let res = request.await;
+        let elapsed = start.elapsed();
+
+        METRIC_META_CLIENT_REQUEST_ELAPSED
+            .with_label_values(&[method])
+            .observe(elapsed.as_secs_f64());
+        if res.is_err() {
+            METRIC_META_CLIENT_REQUEST_ERRORS
+                .with_label_values(&[method])
+                .inc();
+        }
+        if let (Some(threshold), Some(summary)) = (self.slow_call_threshold, summary) {
+            if elapsed >= threshold {
+                METRIC_META_CLIENT_SLOW_REQUESTS
+                    .with_label_values(&[method])
+                    .inc();
+                warn!(
+                    "Slow request to metasrv, method: {}, elapsed: {:?}, success: {}, request: {{{}}}",
+                    method,
+                    elapsed,
+                    res.is_ok(),
+                    summary
+                );
+            }
+        }
+        res
     }
 
     pub fn heartbeat_client(&self) -> Result<HeartbeatClient> {
@@ -640,10 +771,32 @@ mod tests {
         assert!(matches!(res.err(), Some(error::Error::NotStarted { .. })));
     }
 
+    #[tokio::test]
+    async fn test_observe_request() {
+        let urls = &["127.0.0.1:3001", "127.0.0.1:3002"];
+        let mut meta_client = MetaClientBuilder::new(0, 0, Role::Datanode)
+            .enable_heartbeat()
+            .slow_call_threshold(Duration::ZERO)
+            .build();
+        meta_client.start(urls).await.unwrap();
+
+        let errors = METRIC_META_CLIENT_REQUEST_ERRORS.with_label_values(&["put"]);
+        let slow_requests = METRIC_META_CLIENT_SLOW_REQUESTS.with_label_values(&["put"]);
+        let (errors_before, slow_requests_before) = (errors.get(), slow_requests.get());
+        assert!(meta_client.put(PutRequest::default()).await.is_err());
+        // other tests may put concurrently
+        assert!(errors.get() > errors_before);
+        assert!(slow_requests.get() > slow_requests_before);
+    }
+
     #[tokio::test]
     async fn test_ask_leader() {
         let tc = new_client("test_ask_leader").await;
-        tc.client.ask_leader().await.unwrap();
+        let mut leader_change = tc.client.subscribe_leader_change();
+        let leader = tc.client.ask_leader().await.unwrap();
+        // the leader is already found by the mock client
+        assert_eq!(Some(leader), *leader_change.borrow_and_update());
+        assert!(!leader_change.has_changed().unwrap());
     }
 
     #[tokio::test]
@@ -12,27 +12,145 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::Duration;
 
 use api::v1::meta::heartbeat_client::HeartbeatClient;
 use api::v1::meta::{AskLeaderRequest, RequestHeader, Role};
 use common_grpc::channel_manager::ChannelManager;
 use common_meta::distributed_time_constants::META_KEEP_ALIVE_INTERVAL_SECS;
+use common_meta::rpc::{META_LEADER_LEASE_REMAINING, META_LEADER_TERM};
 use common_telemetry::tracing_context::TracingContext;
-use common_telemetry::warn;
+use common_telemetry::{info, warn};
 use rand::seq::SliceRandom;
 use snafu::{OptionExt, ResultExt};
+use tokio::sync::watch;
 use tokio::time::timeout;
+use tonic::metadata::MetadataMap;
 use tonic::transport::Channel;
 
 use crate::client::Id;
 use crate::error;
 use crate::error::Result;
+use crate::metrics::METRIC_META_CLIENT_LEADER_CHANGES;
+
+/// The delay before asking the leader again, increased by itself for each following retry.
+const ASK_LEADER_BACKOFF: Duration = Duration::from_millis(500);
+
+/// The leader renews its lease every keep alive interval, so a lease remaining less than that
+/// means the leader failed to renew it, and it's about to step down.
+const LEASE_NEAR_EXPIRY: Duration = Duration::from_secs(META_KEEP_ALIVE_INTERVAL_SECS);
+
+/// The leader of metasrv, shared by the clients of a meta client, so a leader found by one of
+/// them is used by all of them.
+#[derive(Clone, Debug)]
+pub struct LeaderWatch {
+    sender: Arc<watch::Sender<Option<String>>>,
+    lease: Arc<Mutex<LeaseState>>,
+}
+
+/// The lease of the leader observed from the responses of metasrv.
+#[derive(Debug, Default)]
+struct LeaseState {
+    /// The highest term observed.
+    term: i64,
+    /// Whether the leader is about to step down, or has been replaced, so it should be asked
+    /// again before sending requests to it.
+    stale: bool,
+}
+
+impl Default for LeaderWatch {
+    fn default() -> Self {
+        let (sender, _) = watch::channel(None);
+        Self {
+            sender: Arc::new(sender),
+            lease: Arc::default(),
+        }
+    }
+}
+
+impl LeaderWatch {
+    /// Returns the leader, or `None` if it's not found yet or it's stale.
+    pub fn get(&self) -> Option<String> {
+        if self.lease.lock().unwrap().stale {
+            return None;
+        }
+        self.sender.borrow().clone()
+    }
+
+    /// Sets the leader, returns `true` if it's changed.
+    pub(crate) fn set(&self, leader: String) -> bool {
+        self.lease.lock().unwrap().stale = false;
+        self.sender.send_if_modified(|current| {
+            if current.as_ref() == Some(&leader) {
+                return false;
+            }
+            if current.is_some() {
+                METRIC_META_CLIENT_LEADER_CHANGES.inc();
+            }
+            *current = Some(leader);
+            true
+        })
+    }
+
+    /// Returns a receiver notified once the leader changes.
+    pub fn subscribe(&self) -> watch::Receiver<Option<String>> {
+        self.sender.subscribe()
+    }
+
+    /// Observes the lease of the leader attached to a response from `peer`.
+    ///
+    /// A higher term means `peer` is a newly elected leader, which is switched to at once. A
+    /// lower term, or a lease near expiry, means the current leader is about to step down, so
+    /// it's marked as stale and asked again by the next request, instead of waiting for the
+    /// requests to it to fail.
+    pub(crate) fn observe(&self, peer: &str, metadata: &MetadataMap) {
+        let Some(term) = parse_metadata::<i64>(metadata, META_LEADER_TERM) else {
+            // only the leader attaches its lease
+            return;
+        };
+        let remaining =
+            parse_metadata::<u64>(metadata, META_LEADER_LEASE_REMAINING).map(Duration::from_millis);
+        let is_leader = self.sender.borrow().as_deref() == Some(peer);
+
+        let mut lease = self.lease.lock().unwrap();
+        if term < lease.term {
+            if is_leader && !lease.stale {
+                warn!(
+                    "Metasrv leader {peer} is replaced, term {term} < {}",
+                    lease.term
+                );
+                lease.stale = true;
+            }
+            return;
+        }
+        let new_term = term > lease.term;
+        lease.term = term;
+
+        if remaining.map_or(false, |remaining| remaining < LEASE_NEAR_EXPIRY) {
+            if is_leader && !lease.stale {
+                warn!(
+                    "The lease of metasrv leader {peer} is near expiry, remaining: {remaining:?}"
+                );
+                lease.stale = true;
+            }
+            return;
+        }
+        if new_term && !is_leader {
+            drop(lease);
+            if self.set(peer.to_string()) {
+                info!("Metasrv leader changed to: {peer}, term: {term}");
+            }
+        }
+    }
+}
+
+fn parse_metadata<T: std::str::FromStr>(metadata: &MetadataMap, key: &str) -> Option<T> {
+    metadata.get(key)?.to_str().ok()?.parse().ok()
+}
 
 #[derive(Debug)]
 struct LeadershipGroup {
-    leader: Option<String>,
     peers: Vec<String>,
 }
 
@@ -40,6 +158,7 @@ struct LeadershipGroup {
 pub struct AskLeader {
     id: Id,
     role: Role,
+    leader: LeaderWatch,
     leadership_group: Arc<RwLock<LeadershipGroup>>,
     channel_manager: ChannelManager,
     max_retry: usize,
@@ -52,14 +171,15 @@ impl AskLeader {
         peers: impl Into<Vec<String>>,
         channel_manager: ChannelManager,
         max_retry: usize,
+        leader: LeaderWatch,
     ) -> Self {
         let leadership_group = Arc::new(RwLock::new(LeadershipGroup {
-            leader: None,
             peers: peers.into(),
         }));
         Self {
             id,
             role,
+            leader,
             leadership_group,
             channel_manager,
             max_retry,
@@ -67,7 +187,7 @@ impl AskLeader {
     }
 
     pub fn get_leader(&self) -> Option<String> {
-        self.leadership_group.read().unwrap().leader.clone()
+        self.leader.get()
     }
 
     async fn ask_leader_inner(&self) -> Result<String> {
@@ -119,8 +239,9 @@ impl AskLeader {
         .context(error::AskLeaderTimeoutSnafu)?
         .context(error::NoLeaderSnafu)?;
 
-        let mut leadership_group = self.leadership_group.write().unwrap();
-        leadership_group.leader = Some(leader.clone());
+        if self.leader.set(leader.clone()) {
+            info!("Metasrv leader changed to: {leader}");
+        }
 
         Ok(leader)
     }
@@ -135,6 +256,10 @@ impl AskLeader {
                 Err(err) => {
                     warn!("Failed to ask leader, source: {err}, retry {times} times");
                     times += 1;
+                    // there may be no leader while electing, so wait for a new one
+                    if times < self.max_retry {
+                        tokio::time::sleep(ASK_LEADER_BACKOFF * times as u32).await;
+                    }
                     continue;
                 }
             }
@@ -155,3 +280,74 @@ impl AskLeader {
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_leader_watch() {
+        let leader = LeaderWatch::default();
+        let shared = leader.clone();
+        let mut receiver = leader.subscribe();
+        assert!(leader.get().is_none());
+
+        assert!(leader.set("127.0.0.1:3002".to_string()));
+        assert_eq!(shared.get().unwrap(), "127.0.0.1:3002");
+        receiver.changed().await.unwrap();
+        assert_eq!(
+            receiver.borrow_and_update().as_deref(),
+            Some("127.0.0.1:3002")
+        );
+
+        // not notified if the leader is not changed
+        assert!(!shared.set("127.0.0.1:3002".to_string()));
+        assert!(!receiver.has_changed().unwrap());
+    }
+
+    fn lease_metadata(term: i64, remaining_ms: u64) -> MetadataMap {
+        let mut metadata = MetadataMap::new();
+        let _ = metadata.insert(META_LEADER_TERM, term.into());
+        let _ = metadata.insert(META_LEADER_LEASE_REMAINING, remaining_ms.into());
+        metadata
+    }
+
+    #[test]
+    fn test_leader_watch_observe() {
+        let leader = LeaderWatch::default();
+        assert!(leader.set("127.0.0.1:3002".to_string()));
+
+        // responses without lease are ignored
+        leader.observe("127.0.0.1:3003", &MetadataMap::new());
+        assert_eq!(leader.get().unwrap(), "127.0.0.1:3002");
+
+        leader.observe("127.0.0.1:3002", &lease_metadata(10, 4000));
+        assert_eq!(leader.get().unwrap(), "127.0.0.1:3002");
+
+        // switches to the leader of a higher term
+        leader.observe("127.0.0.1:3003", &lease_metadata(12, 5000));
+        assert_eq!(leader.get().unwrap(), "127.0.0.1:3003");
+
+        // the old leader doesn't take it back
+        leader.observe("127.0.0.1:3002", &lease_metadata(10, 3000));
+        assert_eq!(leader.get().unwrap(), "127.0.0.1:3003");
+
+        // the lease near expiry makes the leader stale, until it's asked again
+        leader.observe("127.0.0.1:3003", &lease_metadata(12, 500));
+        assert!(leader.get().is_none());
+        assert!(!leader.set("127.0.0.1:3003".to_string()));
+        assert_eq!(leader.get().unwrap(), "127.0.0.1:3003");
+    }
+
+    #[test]
+    fn test_leader_watch_observe_lower_term() {
+        let leader = LeaderWatch::default();
+        leader.observe("127.0.0.1:3002", &lease_metadata(12, 5000));
+        assert_eq!(leader.get().unwrap(), "127.0.0.1:3002");
+
+        // the leader answers with a lower term after a newer leader is observed
+        leader.lease.lock().unwrap().term = 13;
+        leader.observe("127.0.0.1:3002", &lease_metadata(12, 5000));
+        assert!(leader.get().is_none());
+    }
+}
@@ -20,18 +20,20 @@ use api::v1::meta::cluster_client::ClusterClient;
 use api::v1::meta::{MetasrvNodeInfo, MetasrvPeersRequest, ResponseHeader, Role};
 use common_grpc::channel_manager::ChannelManager;
 use common_meta::rpc::store::{BatchGetRequest, BatchGetResponse, RangeRequest, RangeResponse};
-use common_telemetry::{info, warn};
+use common_telemetry::warn;
 use snafu::{ensure, ResultExt};
 use tokio::sync::RwLock;
 use tonic::transport::Channel;
 use tonic::Status;
 
-use crate::client::ask_leader::AskLeader;
+use crate::client::ask_leader::{AskLeader, LeaderWatch};
+use crate::client::retry::{RequestKind, RetryPolicy};
 use crate::client::{util, Id};
 use crate::error::{
     ConvertMetaResponseSnafu, CreateChannelSnafu, Error, IllegalGrpcClientStateSnafu, Result,
     RetryTimesExceededSnafu,
 };
+use crate::metrics::METRIC_META_CLIENT_RETRIES;
 
 #[derive(Clone, Debug)]
 pub struct Client {
@@ -39,13 +41,22 @@ pub struct Client {
 }
 
 impl Client {
-    pub fn new(id: Id, role: Role, channel_manager: ChannelManager, max_retry: usize) -> Self {
+    pub fn new(
+        id: Id,
+        role: Role,
+        channel_manager: ChannelManager,
+        max_retry: usize,
+        retry_policy: RetryPolicy,
+        leader: LeaderWatch,
+    ) -> Self {
         let inner = Arc::new(RwLock::new(Inner {
             id,
             role,
             channel_manager,
             ask_leader: None,
             max_retry,
+            retry_policy,
+            leader,
         }));
 
         Self { inner }
@@ -86,6 +97,8 @@ struct Inner {
     channel_manager: ChannelManager,
     ask_leader: Option<AskLeader>,
     max_retry: usize,
+    retry_policy: RetryPolicy,
+    leader: LeaderWatch,
 }
 
 impl Inner {
@@ -112,6 +125,7 @@ impl Inner {
             peers,
             self.channel_manager.clone(),
             self.max_retry,
+            self.leader.clone(),
         ));
 
         Ok(())
@@ -139,39 +153,52 @@ impl Inner {
         Ok(self.ask_leader.as_ref().unwrap())
     }
 
-    async fn with_retry<T, F, R, H>(&self, task: &str, body_fn: F, get_header: H) -> Result<T>
+    async fn with_retry<T, F, R, H>(
+        &self,
+        task: &str,
+        kind: RequestKind,
+        body_fn: F,
+        get_header: H,
+    ) -> Result<T>
     where
-        R: Future<Output = std::result::Result<T, Status>>,
+        R: Future<Output = std::result::Result<tonic::Response<T>, Status>>,
         F: Fn(ClusterClient<Channel>) -> R,
         H: Fn(&T) -> &Option<ResponseHeader>,
     {
         let ask_leader = self.ask_leader()?;
+        let max_attempts = self.retry_policy.max_attempts(kind);
         let mut times = 0;
         let mut last_error = None;
 
-        while times < self.max_retry {
+        while times < max_attempts {
             if let Some(leader) = &ask_leader.get_leader() {
                 let client = self.make_client(leader)?;
                 match body_fn(client).await {
                     Ok(res) => {
+                        self.leader.observe(leader, res.metadata());
+                        let res = res.into_inner();
                         if util::is_not_leader(get_header(&res)) {
                             last_error = Some(format!("{leader} is not a leader"));
                             warn!("Failed to {task} to {leader}, not a leader");
-                            let leader = ask_leader.ask_leader().await?;
-                            info!("Cluster client updated to new leader addr: {leader}");
                             times += 1;
+                            METRIC_META_CLIENT_RETRIES.with_label_values(&[task]).inc();
+                            if let Err(err) = ask_leader.ask_leader().await {
+                                last_error = Some(err.to_string());
+                            }
                             continue;
                         }
                         return Ok(res);
                     }
                     Err(status) => {
-                        // The leader may be unreachable.
-                        if util::is_unreachable(&status) {
+                        // The leader may be unreachable, retry if it is safe to send again.
+                        if kind.should_retry(&status) {
                             last_error = Some(status.to_string());
                             warn!("Failed to {task} to {leader}, source: {status}");
-                            let leader = ask_leader.ask_leader().await?;
-                            info!("Cluster client updated to new leader addr: {leader}");
                             times += 1;
+                            METRIC_META_CLIENT_RETRIES.with_label_values(&[task]).inc();
+                            if let Err(err) = ask_leader.ask_leader().await {
+                                last_error = Some(err.to_string());
+                            }
                             continue;
                         } else {
                             return Err(Error::from(status));
@@ -179,13 +206,16 @@ impl Inner {
                     }
                 }
             } else if let Err(err) = ask_leader.ask_leader().await {
-                return Err(err);
+                // there may be no leader while electing, retry instead of failing
+                last_error = Some(err.to_string());
+                times += 1;
+                METRIC_META_CLIENT_RETRIES.with_label_values(&[task]).inc();
             }
         }
 
         RetryTimesExceededSnafu {
             msg: format!("Failed to {task}, last error: {:?}", last_error),
-            times: self.max_retry,
+            times: max_attempts,
         }
         .fail()
     }
@@ -193,10 +223,11 @@ impl Inner {
     async fn range(&self, request: RangeRequest) -> Result<RangeResponse> {
         self.with_retry(
             "range",
+            RequestKind::Read,
             move |mut client| {
                 let inner_req = tonic::Request::new(v1::meta::RangeRequest::from(request.clone()));
 
-                async move { client.range(inner_req).await.map(|res| res.into_inner()) }
+                async move { client.range(inner_req).await }
             },
             |res| &res.header,
         )
@@ -208,16 +239,12 @@ impl Inner {
     async fn batch_get(&self, request: BatchGetRequest) -> Result<BatchGetResponse> {
         self.with_retry(
             "batch_get",
+            RequestKind::Read,
             move |mut client| {
                 let inner_req =
                     tonic::Request::new(v1::meta::BatchGetRequest::from(request.clone()));
 
-                async move {
-                    client
-                        .batch_get(inner_req)
-                        .await
-                        .map(|res| res.into_inner())
-                }
+                async move { client.batch_get(inner_req).await }
             },
             |res| &res.header,
         )
@@ -229,15 +256,11 @@ impl Inner {
     async fn get_metasrv_peers(&self) -> Result<(Option<MetasrvNodeInfo>, Vec<MetasrvNodeInfo>)> {
         self.with_retry(
             "get_metasrv_peers",
+            RequestKind::Read,
             move |mut client| {
                 let inner_req = tonic::Request::new(MetasrvPeersRequest::default());
 
-                async move {
-                    client
-                        .metasrv_peers(inner_req)
-                        .await
-                        .map(|res| res.into_inner())
-                }
+                async move { client.metasrv_peers(inner_req).await }
             },
             |res| &res.header,
         )
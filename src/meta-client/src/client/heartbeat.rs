@@ -18,15 +18,16 @@ use api::v1::meta::heartbeat_client::HeartbeatClient;
 use api::v1::meta::{HeartbeatRequest, HeartbeatResponse, RequestHeader, Role};
 use common_grpc::channel_manager::ChannelManager;
 use common_meta::util;
-use common_telemetry::info;
 use common_telemetry::tracing_context::TracingContext;
+use common_telemetry::{info, warn};
 use snafu::{ensure, OptionExt, ResultExt};
 use tokio::sync::{mpsc, RwLock};
 use tokio_stream::wrappers::ReceiverStream;
 use tonic::transport::Channel;
 use tonic::Streaming;
 
-use crate::client::ask_leader::AskLeader;
+use crate::client::ask_leader::{AskLeader, LeaderWatch};
+use crate::client::util::{is_not_leader, is_unreachable};
 use crate::client::Id;
 use crate::error;
 use crate::error::{InvalidResponseHeaderSnafu, Result};
@@ -68,12 +69,17 @@ impl HeartbeatSender {
 pub struct HeartbeatStream {
     id: Id,
     stream: Streaming<HeartbeatResponse>,
+    ask_leader: AskLeader,
 }
 
 impl HeartbeatStream {
     #[inline]
-    fn new(id: Id, stream: Streaming<HeartbeatResponse>) -> Self {
-        Self { id, stream }
+    fn new(id: Id, stream: Streaming<HeartbeatResponse>, ask_leader: AskLeader) -> Self {
+        Self {
+            id,
+            stream,
+            ask_leader,
+        }
     }
 
     #[inline]
@@ -82,9 +88,27 @@ impl HeartbeatStream {
     }
 
     /// Fetch the next message from this stream.
+    ///
+    /// Once the leader is found changed, the new leader is asked in the background, so the
+    /// other clients of the meta client switch to it without failing their requests first.
     #[inline]
     pub async fn message(&mut self) -> Result<Option<HeartbeatResponse>> {
-        let res = self.stream.message().await.map_err(error::Error::from);
+        let res = self.stream.message().await;
+        let leader_changed = match &res {
+            Ok(Some(heartbeat)) => is_not_leader(&heartbeat.header),
+            Ok(None) => false,
+            Err(status) => is_unreachable(status),
+        };
+        if leader_changed {
+            let ask_leader = self.ask_leader.clone();
+            let _handle = tokio::spawn(async move {
+                if let Err(err) = ask_leader.ask_leader().await {
+                    warn!("Failed to ask the new leader, source: {err}");
+                }
+            });
+        }
+
+        let res = res.map_err(error::Error::from);
         if let Ok(Some(heartbeat)) = &res {
             util::check_response_header(heartbeat.header.as_ref())
                 .context(InvalidResponseHeaderSnafu)?;
@@ -99,12 +123,19 @@ pub struct Client {
 }
 
 impl Client {
-    pub fn new(id: Id, role: Role, channel_manager: ChannelManager, max_retry: usize) -> Self {
+    pub fn new(
+        id: Id,
+        role: Role,
+        channel_manager: ChannelManager,
+        max_retry: usize,
+        leader: LeaderWatch,
+    ) -> Self {
         let inner = Arc::new(RwLock::new(Inner::new(
             id,
             role,
             channel_manager,
             max_retry,
+            leader,
         )));
         Self { inner }
     }
@@ -137,16 +168,24 @@ struct Inner {
     channel_manager: ChannelManager,
     ask_leader: Option<AskLeader>,
     max_retry: usize,
+    leader: LeaderWatch,
 }
 
 impl Inner {
-    fn new(id: Id, role: Role, channel_manager: ChannelManager, max_retry: usize) -> Self {
+    fn new(
+        id: Id,
+        role: Role,
+        channel_manager: ChannelManager,
+        max_retry: usize,
+        leader: LeaderWatch,
+    ) -> Self {
         Self {
             id,
             role,
             channel_manager,
             ask_leader: None,
             max_retry,
+            leader,
         }
     }
 
@@ -173,6 +212,7 @@ impl Inner {
             peers,
             self.channel_manager.clone(),
             self.max_retry,
+            self.leader.clone(),
         ));
 
         Ok(())
@@ -197,13 +237,9 @@ impl Inner {
             }
         );
 
-        let leader = self
-            .ask_leader
-            .as_ref()
-            .unwrap()
-            .get_leader()
-            .context(error::NoLeaderSnafu)?;
-        let mut leader = self.make_client(leader)?;
+        let ask_leader = self.ask_leader.as_ref().unwrap();
+        let leader_addr = ask_leader.get_leader().context(error::NoLeaderSnafu)?;
+        let mut leader = self.make_client(&leader_addr)?;
 
         let (sender, receiver) = mpsc::channel::<HeartbeatRequest>(128);
 
@@ -224,11 +260,12 @@ impl Inner {
         })?;
         let receiver = ReceiverStream::new(receiver);
 
-        let mut stream = leader
+        let stream = leader
             .heartbeat(receiver)
             .await
-            .map_err(error::Error::from)?
-            .into_inner();
+            .map_err(error::Error::from)?;
+        self.leader.observe(&leader_addr, stream.metadata());
+        let mut stream = stream.into_inner();
 
         let res = stream
             .message()
@@ -239,7 +276,7 @@ impl Inner {
 
         Ok((
             HeartbeatSender::new(self.id, self.role, sender),
-            HeartbeatStream::new(self.id, stream),
+            HeartbeatStream::new(self.id, stream, ask_leader.clone()),
         ))
     }
 
@@ -264,7 +301,13 @@ mod test {
 
     #[tokio::test]
     async fn test_already_start() {
-        let mut client = Client::new((0, 0), Role::Datanode, ChannelManager::default(), 3);
+        let mut client = Client::new(
+            (0, 0),
+            Role::Datanode,
+            ChannelManager::default(),
+            3,
+            LeaderWatch::default(),
+        );
         client
             .start(&["127.0.0.1:1000", "127.0.0.1:1001"])
             .await
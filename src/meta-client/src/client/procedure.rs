@@ -20,20 +20,30 @@ use api::v1::meta::procedure_service_client::ProcedureServiceClient;
 use api::v1::meta::{
     DdlTaskRequest, DdlTaskResponse, MigrateRegionRequest, MigrateRegionResponse,
     ProcedureDetailRequest, ProcedureDetailResponse, ProcedureId, ProcedureStateResponse,
-    QueryProcedureRequest, ResponseHeader, Role,
+    ProcedureStatus, QueryProcedureRequest, ResponseHeader, Role,
 };
 use common_grpc::channel_manager::ChannelManager;
+use common_meta::rpc::ddl::DDL_IDEMPOTENCY_KEY;
 use common_telemetry::tracing_context::TracingContext;
-use common_telemetry::{info, warn};
+use common_telemetry::warn;
 use snafu::{ensure, ResultExt};
 use tokio::sync::RwLock;
+use tokio::time::Instant;
+use tonic::metadata::MetadataValue;
 use tonic::transport::Channel;
 use tonic::Status;
 
-use crate::client::ask_leader::AskLeader;
+use crate::client::ask_leader::{AskLeader, LeaderWatch};
+use crate::client::retry::{RequestKind, RetryPolicy};
 use crate::client::{util, Id};
 use crate::error;
 use crate::error::Result;
+use crate::metrics::METRIC_META_CLIENT_RETRIES;
+
+/// The interval to poll the state of a procedure at first, doubled after each poll until
+/// [MAX_WAIT_PROCEDURE_INTERVAL].
+const MIN_WAIT_PROCEDURE_INTERVAL: Duration = Duration::from_millis(100);
+const MAX_WAIT_PROCEDURE_INTERVAL: Duration = Duration::from_secs(1);
 
 #[derive(Clone, Debug)]
 pub struct Client {
@@ -41,13 +51,22 @@ pub struct Client {
 }
 
 impl Client {
-    pub fn new(id: Id, role: Role, channel_manager: ChannelManager, max_retry: usize) -> Self {
+    pub fn new(
+        id: Id,
+        role: Role,
+        channel_manager: ChannelManager,
+        max_retry: usize,
+        retry_policy: RetryPolicy,
+        leader: LeaderWatch,
+    ) -> Self {
         let inner = Arc::new(RwLock::new(Inner {
             id,
             role,
             channel_manager,
             ask_leader: None,
             max_retry,
+            retry_policy,
+            leader,
         }));
 
         Self { inner }
@@ -73,6 +92,16 @@ impl Client {
         inner.query_procedure_state(pid).await
     }
 
+    /// Waits for the procedure until it's done and returns its final state, or fails if the
+    /// procedure failed or it's not done within the `timeout`.
+    pub async fn wait_procedure(
+        &self,
+        pid: &str,
+        timeout: Duration,
+    ) -> Result<ProcedureStateResponse> {
+        wait_procedure(pid, timeout, || self.query_procedure_state(pid)).await
+    }
+
     /// Migrate the region from one datanode to the other datanode:
     /// - `region_id`:  the migrated region id
     /// - `from_peer`:  the source datanode id
@@ -97,6 +126,51 @@ impl Client {
     }
 }
 
+/// Polls the state of the procedure by `query_state` until it's done, see [Client::wait_procedure].
+async fn wait_procedure<F, Fut>(
+    pid: &str,
+    timeout: Duration,
+    mut query_state: F,
+) -> Result<ProcedureStateResponse>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<ProcedureStateResponse>>,
+{
+    let deadline = Instant::now() + timeout;
+    let mut interval = MIN_WAIT_PROCEDURE_INTERVAL;
+    loop {
+        let state = query_state().await?;
+        let status = state.status();
+        match status {
+            ProcedureStatus::Done => return Ok(state),
+            ProcedureStatus::Failed => {
+                return error::ProcedureFailedSnafu {
+                    procedure_id: pid,
+                    err_msg: state.error,
+                }
+                .fail()
+            }
+            ProcedureStatus::Running
+            | ProcedureStatus::Retrying
+            | ProcedureStatus::PrepareRollback
+            | ProcedureStatus::RollingBack => {}
+        }
+
+        let now = Instant::now();
+        ensure!(
+            now < deadline,
+            error::WaitProcedureTimeoutSnafu {
+                procedure_id: pid,
+                timeout,
+                status: status.as_str_name(),
+                err_msg: state.error,
+            }
+        );
+        tokio::time::sleep(interval.min(deadline - now)).await;
+        interval = (interval * 2).min(MAX_WAIT_PROCEDURE_INTERVAL);
+    }
+}
+
 #[derive(Debug)]
 struct Inner {
     id: Id,
@@ -104,6 +178,8 @@ struct Inner {
     channel_manager: ChannelManager,
     ask_leader: Option<AskLeader>,
     max_retry: usize,
+    retry_policy: RetryPolicy,
+    leader: LeaderWatch,
 }
 
 impl Inner {
@@ -130,6 +206,7 @@ impl Inner {
             peers,
             self.channel_manager.clone(),
             self.max_retry,
+            self.leader.clone(),
         ));
 
         Ok(())
@@ -160,39 +237,52 @@ impl Inner {
         Ok(self.ask_leader.as_ref().unwrap())
     }
 
-    async fn with_retry<T, F, R, H>(&self, task: &str, body_fn: F, get_header: H) -> Result<T>
+    async fn with_retry<T, F, R, H>(
+        &self,
+        task: &str,
+        kind: RequestKind,
+        body_fn: F,
+        get_header: H,
+    ) -> Result<T>
     where
-        R: Future<Output = std::result::Result<T, Status>>,
+        R: Future<Output = std::result::Result<tonic::Response<T>, Status>>,
         F: Fn(ProcedureServiceClient<Channel>) -> R,
         H: Fn(&T) -> &Option<ResponseHeader>,
     {
         let ask_leader = self.ask_leader()?;
+        let max_attempts = self.retry_policy.max_attempts(kind);
         let mut times = 0;
         let mut last_error = None;
 
-        while times < self.max_retry {
+        while times < max_attempts {
             if let Some(leader) = &ask_leader.get_leader() {
                 let client = self.make_client(leader)?;
                 match body_fn(client).await {
                     Ok(res) => {
+                        self.leader.observe(leader, res.metadata());
+                        let res = res.into_inner();
                         if util::is_not_leader(get_header(&res)) {
                             last_error = Some(format!("{leader} is not a leader"));
                             warn!("Failed to {task} to {leader}, not a leader");
-                            let leader = ask_leader.ask_leader().await?;
-                            info!("DDL client updated to new leader addr: {leader}");
                             times += 1;
+                            METRIC_META_CLIENT_RETRIES.with_label_values(&[task]).inc();
+                            if let Err(err) = ask_leader.ask_leader().await {
+                                last_error = Some(err.to_string());
+                            }
                             continue;
                         }
                         return Ok(res);
                     }
                     Err(status) => {
-                        // The leader may be unreachable.
-                        if util::is_unreachable(&status) {
+                        // The leader may be unreachable, retry if it is safe to send again.
+                        if kind.should_retry(&status) {
                             last_error = Some(status.to_string());
                             warn!("Failed to {task} to {leader}, source: {status}");
-                            let leader = ask_leader.ask_leader().await?;
-                            info!("Procedure client updated to new leader addr: {leader}");
                             times += 1;
+                            METRIC_META_CLIENT_RETRIES.with_label_values(&[task]).inc();
+                            if let Err(err) = ask_leader.ask_leader().await {
+                                last_error = Some(err.to_string());
+                            }
                             continue;
                         } else {
                             return Err(error::Error::from(status));
@@ -200,13 +290,16 @@ impl Inner {
                     }
                 }
             } else if let Err(err) = ask_leader.ask_leader().await {
-                return Err(err);
+                // there may be no leader while electing, retry instead of failing
+                last_error = Some(err.to_string());
+                times += 1;
+                METRIC_META_CLIENT_RETRIES.with_label_values(&[task]).inc();
             }
         }
 
         error::RetryTimesExceededSnafu {
             msg: format!("Failed to {task}, last error: {:?}", last_error),
-            times: self.max_retry,
+            times: max_attempts,
         }
         .fail()
     }
@@ -234,10 +327,11 @@ impl Inner {
 
         self.with_retry(
             "migrate region",
+            RequestKind::Write,
             move |mut client| {
                 let req = req.clone();
 
-                async move { client.migrate(req).await.map(|res| res.into_inner()) }
+                async move { client.migrate(req).await }
             },
             |resp: &MigrateRegionResponse| &resp.header,
         )
@@ -258,10 +352,11 @@ impl Inner {
 
         self.with_retry(
             "query procedure state",
+            RequestKind::Read,
             move |mut client| {
                 let req = req.clone();
 
-                async move { client.query(req).await.map(|res| res.into_inner()) }
+                async move { client.query(req).await }
             },
             |resp: &ProcedureStateResponse| &resp.header,
         )
@@ -275,11 +370,20 @@ impl Inner {
             TracingContext::from_current_span().to_w3c(),
         );
 
+        // the same key for all the attempts, so metasrv runs the task once even if a response
+        // is lost
+        // Safety: a hex string is a valid metadata value
+        let idempotency_key =
+            MetadataValue::try_from(format!("{:032x}", rand::random::<u128>())).unwrap();
         self.with_retry(
             "submit ddl task",
+            RequestKind::Ddl,
             move |mut client| {
-                let req = req.clone();
-                async move { client.ddl(req).await.map(|res| res.into_inner()) }
+                let mut req = tonic::Request::new(req.clone());
+                let _ = req
+                    .metadata_mut()
+                    .insert(DDL_IDEMPOTENCY_KEY, idempotency_key.clone());
+                async move { client.ddl(req).await }
             },
             |resp: &DdlTaskResponse| &resp.header,
         )
@@ -296,12 +400,71 @@ impl Inner {
 
         self.with_retry(
             "list procedure",
+            RequestKind::Read,
             move |mut client| {
                 let req = req.clone();
-                async move { client.details(req).await.map(|res| res.into_inner()) }
+                async move { client.details(req).await }
             },
             |resp: &ProcedureDetailResponse| &resp.header,
         )
         .await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use common_error::ext::ErrorExt;
+    use common_error::status_code::StatusCode;
+
+    use super::*;
+    use crate::error::Error;
+
+    fn state(status: ProcedureStatus, error: &str) -> ProcedureStateResponse {
+        ProcedureStateResponse {
+            status: status.into(),
+            error: error.to_string(),
+            ..Default::default()
+        }
+    }
+
+    async fn wait(
+        states: Vec<ProcedureStateResponse>,
+        timeout: Duration,
+    ) -> Result<ProcedureStateResponse> {
+        let states = Mutex::new(states.into_iter());
+        wait_procedure("pid", timeout, || {
+            let state = states.lock().unwrap().next().unwrap();
+            async move { Ok(state) }
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_wait_procedure() {
+        let states = vec![
+            state(ProcedureStatus::Running, ""),
+            state(ProcedureStatus::Retrying, "retry later"),
+            state(ProcedureStatus::Done, ""),
+        ];
+        let state = wait(states, Duration::from_secs(10)).await.unwrap();
+        assert_eq!(state.status(), ProcedureStatus::Done);
+
+        let states = vec![
+            state(ProcedureStatus::Running, ""),
+            state(ProcedureStatus::RollingBack, "table exists"),
+            state(ProcedureStatus::Failed, "table exists"),
+        ];
+        let err = wait(states, Duration::from_secs(10)).await.unwrap_err();
+        assert!(
+            matches!(&err, Error::ProcedureFailed { err_msg, .. } if err_msg == "table exists"),
+            "{err:?}"
+        );
+
+        let states = vec![state(ProcedureStatus::Running, ""); 100];
+        let err = wait(states, Duration::from_secs(1)).await.unwrap_err();
+        assert!(matches!(err, Error::WaitProcedureTimeout { .. }), "{err:?}");
+        assert_eq!(err.status_code(), StatusCode::Internal);
+    }
+}
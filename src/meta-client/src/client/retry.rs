@@ -0,0 +1,145 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Retrying requests to metasrv by whether it's safe to send them again.
+
+use common_error::error_info::ErrorInfo;
+use tonic::{Code, Status};
+
+use crate::client::util;
+
+/// How requests to metasrv are retried, by their [RequestKind].
+///
+/// A request is retried on another metasrv or after the leader is found again, within the max
+/// attempts of its kind, including the first one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The max attempts of reads, e.g., range and batch get, retried on any transient error.
+    pub max_read_attempts: usize,
+    /// The max attempts of writes, e.g., put and compare and put, only retried if the request
+    /// is not sent, since a write timed out may have been applied.
+    pub max_write_attempts: usize,
+    /// The max attempts of DDL submissions, retried on transient errors with the same
+    /// idempotency key, so metasrv doesn't run them twice.
+    pub max_ddl_attempts: usize,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_read_attempts: 3,
+            max_write_attempts: 3,
+            max_ddl_attempts: 3,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub(crate) fn max_attempts(&self, kind: RequestKind) -> usize {
+        let max_attempts = match kind {
+            RequestKind::Read => self.max_read_attempts,
+            RequestKind::Write => self.max_write_attempts,
+            RequestKind::Ddl => self.max_ddl_attempts,
+        };
+        max_attempts.max(1)
+    }
+}
+
+/// The kinds of requests to metasrv, by whether it's safe to send them again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RequestKind {
+    /// Idempotent reads.
+    Read,
+    /// Writes, including compare and put and procedures submitted without idempotency keys.
+    Write,
+    /// DDL submissions with idempotency keys.
+    Ddl,
+}
+
+impl RequestKind {
+    /// Returns true if the request failed by `status` can be sent again.
+    ///
+    /// Reads are also retried if the [StatusCode](common_error::status_code::StatusCode) of the
+    /// error is retryable.
+    ///
+    /// Besides, a request rejected by a metasrv not the leader is always safe to send again.
+    pub(crate) fn should_retry(&self, status: &Status) -> bool {
+        match self {
+            RequestKind::Read => {
+                matches!(
+                    status.code(),
+                    Code::Unavailable
+                        | Code::DeadlineExceeded
+                        | Code::Aborted
+                        | Code::ResourceExhausted
+                ) || ErrorInfo::from_status(status)
+                    .code
+                    .map_or(false, |code| code.is_retryable())
+            }
+            // the transport failed to send the request
+            RequestKind::Write => status.code() == Code::Unavailable,
+            RequestKind::Ddl => util::is_unreachable(status),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common_error::mock::MockError;
+    use common_error::status_code::{to_tonic_status, StatusCode};
+
+    use super::*;
+
+    #[test]
+    fn test_should_retry() {
+        let unavailable = Status::unavailable("connection refused");
+        let timeout = Status::deadline_exceeded("timeout");
+        let invalid = Status::invalid_argument("invalid");
+
+        assert!(RequestKind::Read.should_retry(&unavailable));
+        assert!(RequestKind::Read.should_retry(&timeout));
+        assert!(!RequestKind::Read.should_retry(&invalid));
+
+        assert!(RequestKind::Write.should_retry(&unavailable));
+        // a write timed out may have been applied
+        assert!(!RequestKind::Write.should_retry(&timeout));
+        assert!(!RequestKind::Write.should_retry(&invalid));
+
+        assert!(RequestKind::Ddl.should_retry(&unavailable));
+        assert!(RequestKind::Ddl.should_retry(&timeout));
+        assert!(!RequestKind::Ddl.should_retry(&invalid));
+
+        // retried by the status code of metasrv
+        let err = MockError::new(StatusCode::Internal);
+        let internal = to_tonic_status(&err, false);
+        assert_eq!(internal.code(), Code::Internal);
+        assert!(RequestKind::Read.should_retry(&internal));
+        assert!(!RequestKind::Write.should_retry(&internal));
+        let err = MockError::new(StatusCode::TableNotFound);
+        assert!(!RequestKind::Read.should_retry(&to_tonic_status(&err, false)));
+    }
+
+    #[test]
+    fn test_max_attempts() {
+        let policy = RetryPolicy {
+            max_read_attempts: 5,
+            max_write_attempts: 0,
+            ..Default::default()
+        };
+        assert_eq!(policy.max_attempts(RequestKind::Read), 5);
+        // the request is sent at least once
+        assert_eq!(policy.max_attempts(RequestKind::Write), 1);
+        assert_eq!(policy.max_attempts(RequestKind::Ddl), 3);
+    }
+}
@@ -13,7 +13,9 @@
 // limitations under the License.
 
 use std::collections::HashSet;
-use std::sync::Arc;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use api::v1::meta::store_client::StoreClient;
 use api::v1::meta::{
@@ -23,13 +25,23 @@ use api::v1::meta::{
 };
 use common_grpc::channel_manager::ChannelManager;
 use common_telemetry::tracing_context::TracingContext;
+use common_telemetry::warn;
+use prost::Message;
 use snafu::{ensure, OptionExt, ResultExt};
 use tokio::sync::RwLock;
 use tonic::transport::Channel;
+use tonic::Status;
 
+use crate::client::ask_leader::LeaderWatch;
+use crate::client::retry::{RequestKind, RetryPolicy};
 use crate::client::{load_balance as lb, Id};
 use crate::error;
 use crate::error::Result;
+use crate::metrics::METRIC_META_CLIENT_RETRIES;
+
+/// The max size of a batch request. Larger ones are split into requests under it in order,
+/// since the gRPC server and the kv backend of metasrv reject too large requests.
+const MAX_BATCH_REQUEST_SIZE: usize = 1024 * 1024;
 
 #[derive(Clone, Debug)]
 pub struct Client {
@@ -37,12 +49,25 @@ pub struct Client {
 }
 
 impl Client {
-    pub fn new(id: Id, role: Role, channel_manager: ChannelManager) -> Self {
+    /// Creates a store client. If `follower_reads` is set, the writes are sent to the leader and
+    /// the reads to followers, except the reads within the max staleness after a write.
+    pub fn new(
+        id: Id,
+        role: Role,
+        channel_manager: ChannelManager,
+        leader: LeaderWatch,
+        follower_reads: Option<Duration>,
+        retry_policy: RetryPolicy,
+    ) -> Self {
         let inner = Arc::new(RwLock::new(Inner {
             id,
             role,
             channel_manager,
             peers: vec![],
+            leader,
+            follower_reads,
+            last_write: Mutex::new(None),
+            retry_policy,
         }));
 
         Self { inner }
@@ -67,19 +92,85 @@ impl Client {
         inner.put(req).await
     }
 
+    /// Gets the keys, split into requests if the request is too large, see
+    /// [MAX_BATCH_REQUEST_SIZE].
     pub async fn batch_get(&self, req: BatchGetRequest) -> Result<BatchGetResponse> {
         let inner = self.inner.read().await;
-        inner.batch_get(req).await
+        if req.encoded_len() <= MAX_BATCH_REQUEST_SIZE {
+            return inner.batch_get(req).await;
+        }
+
+        let BatchGetRequest { header, keys } = req;
+        let mut resp = BatchGetResponse::default();
+        for keys in split_by_size(keys, MAX_BATCH_REQUEST_SIZE, |key| field_len(key.len())) {
+            let chunk = inner
+                .batch_get(BatchGetRequest {
+                    header: header.clone(),
+                    keys,
+                })
+                .await?;
+            resp.header = chunk.header;
+            resp.kvs.extend(chunk.kvs);
+        }
+        Ok(resp)
     }
 
+    /// Puts the key-values, split into requests if the request is too large, see
+    /// [MAX_BATCH_REQUEST_SIZE]. The split requests are not atomic as a whole.
     pub async fn batch_put(&self, req: BatchPutRequest) -> Result<BatchPutResponse> {
         let inner = self.inner.read().await;
-        inner.batch_put(req).await
+        if req.encoded_len() <= MAX_BATCH_REQUEST_SIZE {
+            return inner.batch_put(req).await;
+        }
+
+        let BatchPutRequest {
+            header,
+            kvs,
+            prev_kv,
+        } = req;
+        let mut resp = BatchPutResponse::default();
+        for kvs in split_by_size(kvs, MAX_BATCH_REQUEST_SIZE, |kv| {
+            field_len(kv.encoded_len())
+        }) {
+            let chunk = inner
+                .batch_put(BatchPutRequest {
+                    header: header.clone(),
+                    kvs,
+                    prev_kv,
+                })
+                .await?;
+            resp.header = chunk.header;
+            resp.prev_kvs.extend(chunk.prev_kvs);
+        }
+        Ok(resp)
     }
 
+    /// Deletes the keys, split into requests if the request is too large, see
+    /// [MAX_BATCH_REQUEST_SIZE]. The split requests are not atomic as a whole.
     pub async fn batch_delete(&self, req: BatchDeleteRequest) -> Result<BatchDeleteResponse> {
         let inner = self.inner.read().await;
-        inner.batch_delete(req).await
+        if req.encoded_len() <= MAX_BATCH_REQUEST_SIZE {
+            return inner.batch_delete(req).await;
+        }
+
+        let BatchDeleteRequest {
+            header,
+            keys,
+            prev_kv,
+        } = req;
+        let mut resp = BatchDeleteResponse::default();
+        for keys in split_by_size(keys, MAX_BATCH_REQUEST_SIZE, |key| field_len(key.len())) {
+            let chunk = inner
+                .batch_delete(BatchDeleteRequest {
+                    header: header.clone(),
+                    keys,
+                    prev_kv,
+                })
+                .await?;
+            resp.header = chunk.header;
+            resp.prev_kvs.extend(chunk.prev_kvs);
+        }
+        Ok(resp)
     }
 
     pub async fn compare_and_put(
@@ -96,12 +187,42 @@ impl Client {
     }
 }
 
+/// Returns the encoded size of a repeated field item of `len` bytes, including its tag and length.
+fn field_len(len: usize) -> usize {
+    1 + prost::length_delimiter_len(len) + len
+}
+
+/// Splits the items into chunks in order, each of them no larger than `max_size` in total,
+/// unless it has only one item.
+fn split_by_size<T>(items: Vec<T>, max_size: usize, size_of: impl Fn(&T) -> usize) -> Vec<Vec<T>> {
+    let mut chunks = vec![];
+    let mut chunk = vec![];
+    let mut chunk_size = 0;
+    for item in items {
+        let size = size_of(&item);
+        if !chunk.is_empty() && chunk_size + size > max_size {
+            chunks.push(std::mem::take(&mut chunk));
+            chunk_size = 0;
+        }
+        chunk_size += size;
+        chunk.push(item);
+    }
+    if !chunk.is_empty() {
+        chunks.push(chunk);
+    }
+    chunks
+}
+
 #[derive(Debug)]
 struct Inner {
     id: Id,
     role: Role,
     channel_manager: ChannelManager,
     peers: Vec<String>,
+    leader: LeaderWatch,
+    follower_reads: Option<Duration>,
+    last_write: Mutex<Option<Instant>>,
+    retry_policy: RetryPolicy,
 }
 
 impl Inner {
@@ -129,105 +250,189 @@ impl Inner {
     }
 
     async fn range(&self, mut req: RangeRequest) -> Result<RangeResponse> {
-        let mut client = self.random_client()?;
         req.set_header(
             self.id,
             self.role,
             TracingContext::from_current_span().to_w3c(),
         );
-        let res = client.range(req).await.map_err(error::Error::from)?;
 
-        Ok(res.into_inner())
+        self.with_retry("range", RequestKind::Read, move |mut client| {
+            let req = req.clone();
+            async move { client.range(req).await }
+        })
+        .await
     }
 
     async fn put(&self, mut req: PutRequest) -> Result<PutResponse> {
-        let mut client = self.random_client()?;
         req.set_header(
             self.id,
             self.role,
             TracingContext::from_current_span().to_w3c(),
         );
-        let res = client.put(req).await.map_err(error::Error::from)?;
 
-        Ok(res.into_inner())
+        self.with_retry("put", RequestKind::Write, move |mut client| {
+            let req = req.clone();
+            async move { client.put(req).await }
+        })
+        .await
     }
 
     async fn batch_get(&self, mut req: BatchGetRequest) -> Result<BatchGetResponse> {
-        let mut client = self.random_client()?;
         req.set_header(
             self.id,
             self.role,
             TracingContext::from_current_span().to_w3c(),
         );
 
-        let res = client.batch_get(req).await.map_err(error::Error::from)?;
-
-        Ok(res.into_inner())
+        self.with_retry("batch_get", RequestKind::Read, move |mut client| {
+            let req = req.clone();
+            async move { client.batch_get(req).await }
+        })
+        .await
     }
 
     async fn batch_put(&self, mut req: BatchPutRequest) -> Result<BatchPutResponse> {
-        let mut client = self.random_client()?;
         req.set_header(
             self.id,
             self.role,
             TracingContext::from_current_span().to_w3c(),
         );
-        let res = client.batch_put(req).await.map_err(error::Error::from)?;
 
-        Ok(res.into_inner())
+        self.with_retry("batch_put", RequestKind::Write, move |mut client| {
+            let req = req.clone();
+            async move { client.batch_put(req).await }
+        })
+        .await
     }
 
     async fn batch_delete(&self, mut req: BatchDeleteRequest) -> Result<BatchDeleteResponse> {
-        let mut client = self.random_client()?;
         req.set_header(
             self.id,
             self.role,
             TracingContext::from_current_span().to_w3c(),
         );
-        let res = client.batch_delete(req).await.map_err(error::Error::from)?;
 
-        Ok(res.into_inner())
+        self.with_retry("batch_delete", RequestKind::Write, move |mut client| {
+            let req = req.clone();
+            async move { client.batch_delete(req).await }
+        })
+        .await
     }
 
     async fn compare_and_put(
         &self,
         mut req: CompareAndPutRequest,
     ) -> Result<CompareAndPutResponse> {
-        let mut client = self.random_client()?;
         req.set_header(
             self.id,
             self.role,
             TracingContext::from_current_span().to_w3c(),
         );
-        let res = client
-            .compare_and_put(req)
-            .await
-            .map_err(error::Error::from)?;
 
-        Ok(res.into_inner())
+        self.with_retry("compare_and_put", RequestKind::Write, move |mut client| {
+            let req = req.clone();
+            async move { client.compare_and_put(req).await }
+        })
+        .await
     }
 
     async fn delete_range(&self, mut req: DeleteRangeRequest) -> Result<DeleteRangeResponse> {
-        let mut client = self.random_client()?;
         req.set_header(
             self.id,
             self.role,
             TracingContext::from_current_span().to_w3c(),
         );
-        let res = client.delete_range(req).await.map_err(error::Error::from)?;
 
-        Ok(res.into_inner())
+        self.with_retry("delete_range", RequestKind::Write, move |mut client| {
+            let req = req.clone();
+            async move { client.delete_range(req).await }
+        })
+        .await
+    }
+
+    /// Sends the request by `body_fn` to a peer picked by its `kind`, and retries it on another
+    /// peer if it's safe to send again, see [RetryPolicy].
+    async fn with_retry<T, F, R>(&self, task: &str, kind: RequestKind, body_fn: F) -> Result<T>
+    where
+        R: Future<Output = std::result::Result<tonic::Response<T>, Status>>,
+        F: Fn(StoreClient<Channel>) -> R,
+    {
+        let max_attempts = self.retry_policy.max_attempts(kind);
+        let mut attempt = 1;
+        loop {
+            let peer = match kind {
+                RequestKind::Read => self.read_peer()?,
+                RequestKind::Write | RequestKind::Ddl => self.write_peer()?,
+            };
+            match body_fn(self.make_client(&peer)?).await {
+                Ok(res) => {
+                    self.leader.observe(&peer, res.metadata());
+                    return Ok(res.into_inner());
+                }
+                Err(status) if attempt < max_attempts && kind.should_retry(&status) => {
+                    warn!("Failed to {task}, attempt {attempt}/{max_attempts}: {status}");
+                    METRIC_META_CLIENT_RETRIES.with_label_values(&[task]).inc();
+                    attempt += 1;
+                }
+                Err(status) => return Err(error::Error::from(status)),
+            }
+        }
+    }
+
+    /// Returns the peer to send a read to.
+    ///
+    /// With follower reads, the read is sent to a follower, unless this client wrote within the
+    /// max staleness, in which case the follower may not see the write yet and the read is sent
+    /// to the leader, like the writes.
+    fn read_peer(&self) -> Result<String> {
+        let Some(max_staleness) = self.follower_reads else {
+            return self.random_peer();
+        };
+        let recently_written = self
+            .last_write
+            .lock()
+            .unwrap()
+            .map_or(false, |last_write| last_write.elapsed() < max_staleness);
+        if recently_written {
+            return self.leader_peer();
+        }
+
+        let leader = self.leader.get();
+        let followers = self
+            .peers
+            .iter()
+            .filter(|peer| Some(*peer) != leader.as_ref())
+            .collect::<Vec<_>>();
+        match lb::random_get(followers.len(), |i| Some(followers[i].clone())) {
+            Some(peer) => Ok(peer),
+            None => self.random_peer(),
+        }
+    }
+
+    /// Returns the peer to send a write to.
+    fn write_peer(&self) -> Result<String> {
+        if self.follower_reads.is_none() {
+            return self.random_peer();
+        }
+        *self.last_write.lock().unwrap() = Some(Instant::now());
+        self.leader_peer()
+    }
+
+    /// Returns the leader, or a random peer if the leader is not found yet.
+    fn leader_peer(&self) -> Result<String> {
+        match self.leader.get() {
+            Some(leader) => Ok(leader),
+            None => self.random_peer(),
+        }
     }
 
-    fn random_client(&self) -> Result<StoreClient<Channel>> {
+    fn random_peer(&self) -> Result<String> {
         let len = self.peers.len();
-        let peer = lb::random_get(len, |i| Some(&self.peers[i])).context(
+        lb::random_get(len, |i| Some(self.peers[i].clone())).context(
             error::IllegalGrpcClientStateSnafu {
                 err_msg: "Empty peers, store client may not start yet",
             },
-        )?;
-
-        self.make_client(peer)
+        )
     }
 
     fn make_client(&self, addr: impl AsRef<str>) -> Result<StoreClient<Channel>> {
@@ -251,7 +456,14 @@ mod test {
 
     #[tokio::test]
     async fn test_already_start() {
-        let mut client = Client::new((0, 0), Role::Frontend, ChannelManager::default());
+        let mut client = Client::new(
+            (0, 0),
+            Role::Frontend,
+            ChannelManager::default(),
+            LeaderWatch::default(),
+            None,
+            RetryPolicy::default(),
+        );
         client
             .start(&["127.0.0.1:1000", "127.0.0.1:1001"])
             .await
@@ -266,11 +478,91 @@ mod test {
 
     #[tokio::test]
     async fn test_start_with_duplicate_peers() {
-        let mut client = Client::new((0, 0), Role::Frontend, ChannelManager::default());
+        let mut client = Client::new(
+            (0, 0),
+            Role::Frontend,
+            ChannelManager::default(),
+            LeaderWatch::default(),
+            None,
+            RetryPolicy::default(),
+        );
         client
             .start(&["127.0.0.1:1000", "127.0.0.1:1000", "127.0.0.1:1000"])
             .await
             .unwrap();
         assert_eq!(1, client.inner.write().await.peers.len());
     }
+
+    #[tokio::test]
+    async fn test_follower_reads() {
+        let leader = LeaderWatch::default();
+        let mut client = Client::new(
+            (0, 0),
+            Role::Frontend,
+            ChannelManager::default(),
+            leader.clone(),
+            Some(Duration::from_secs(60)),
+            RetryPolicy::default(),
+        );
+        client
+            .start(&["127.0.0.1:1000", "127.0.0.1:1001"])
+            .await
+            .unwrap();
+        let inner = client.inner.read().await;
+        let _ = leader.set("127.0.0.1:1000".to_string());
+
+        // reads are sent to the follower
+        for _ in 0..10 {
+            assert_eq!(inner.read_peer().unwrap(), "127.0.0.1:1001");
+        }
+        // writes are sent to the leader, so are the reads after them
+        assert_eq!(inner.write_peer().unwrap(), "127.0.0.1:1000");
+        assert_eq!(inner.read_peer().unwrap(), "127.0.0.1:1000");
+
+        // reads are sent to the follower again once the max staleness passes
+        *inner.last_write.lock().unwrap() = Some(Instant::now() - Duration::from_secs(61));
+        assert_eq!(inner.read_peer().unwrap(), "127.0.0.1:1001");
+    }
+
+    #[tokio::test]
+    async fn test_retry() {
+        let mut client = Client::new(
+            (0, 0),
+            Role::Frontend,
+            ChannelManager::default(),
+            LeaderWatch::default(),
+            None,
+            RetryPolicy {
+                max_read_attempts: 3,
+                max_write_attempts: 1,
+                ..Default::default()
+            },
+        );
+        // nothing listens on it
+        client.start(&["127.0.0.1:1"]).await.unwrap();
+
+        let retries = METRIC_META_CLIENT_RETRIES.with_label_values(&["range"]);
+        let before = retries.get();
+        assert!(client.range(RangeRequest::default()).await.is_err());
+        assert!(retries.get() - before >= 2);
+
+        let retries = METRIC_META_CLIENT_RETRIES.with_label_values(&["put"]);
+        let before = retries.get();
+        assert!(client.put(PutRequest::default()).await.is_err());
+        assert_eq!(retries.get(), before);
+    }
+
+    #[test]
+    fn test_split_by_size() {
+        let split = |items: Vec<usize>, max_size| split_by_size(items, max_size, |item| *item);
+
+        assert!(split(vec![], 10).is_empty());
+        assert_eq!(split(vec![1, 2, 3], 10), vec![vec![1, 2, 3]]);
+        assert_eq!(
+            split(vec![4, 5, 1, 6, 4], 10),
+            vec![vec![4, 5, 1], vec![6, 4]]
+        );
+        // an item larger than the max size is in a chunk of its own
+        assert_eq!(split(vec![3, 20, 3], 10), vec![vec![3], vec![20], vec![3]]);
+    }
 }
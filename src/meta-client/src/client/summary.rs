@@ -0,0 +1,186 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Summaries of requests for logging slow calls, without the values that may be large.
+
+use common_meta::rpc::ddl::{DdlTask, SubmitDdlTaskRequest};
+use common_meta::rpc::procedure::MigrateRegionRequest;
+use common_meta::rpc::store::{
+    BatchDeleteRequest, BatchGetRequest, BatchPutRequest, CompareAndPutRequest, DeleteRangeRequest,
+    PutRequest, RangeRequest,
+};
+
+/// The max bytes of a key in a summary, longer keys are truncated.
+const MAX_SUMMARY_KEY_LEN: usize = 128;
+
+pub(crate) trait RequestSummary {
+    fn summary(&self) -> String;
+}
+
+/// Formats the key as a string, truncated to [MAX_SUMMARY_KEY_LEN].
+fn fmt_key(key: &[u8]) -> String {
+    if key.len() <= MAX_SUMMARY_KEY_LEN {
+        String::from_utf8_lossy(key).to_string()
+    } else {
+        format!(
+            "{}...",
+            String::from_utf8_lossy(&key[..MAX_SUMMARY_KEY_LEN])
+        )
+    }
+}
+
+/// Formats the number of keys and the first one.
+fn fmt_keys<'a>(mut keys: impl ExactSizeIterator<Item = &'a [u8]>) -> String {
+    let len = keys.len();
+    match keys.next() {
+        Some(first) => format!("{len} keys, first: '{}'", fmt_key(first)),
+        None => "0 keys".to_string(),
+    }
+}
+
+impl RequestSummary for RangeRequest {
+    fn summary(&self) -> String {
+        format!(
+            "key: '{}', range_end: '{}', limit: {}, keys_only: {}",
+            fmt_key(&self.key),
+            fmt_key(&self.range_end),
+            self.limit,
+            self.keys_only
+        )
+    }
+}
+
+impl RequestSummary for PutRequest {
+    fn summary(&self) -> String {
+        format!(
+            "key: '{}', value: {} bytes, prev_kv: {}",
+            fmt_key(&self.key),
+            self.value.len(),
+            self.prev_kv
+        )
+    }
+}
+
+impl RequestSummary for BatchGetRequest {
+    fn summary(&self) -> String {
+        fmt_keys(self.keys.iter().map(|key| key.as_slice()))
+    }
+}
+
+impl RequestSummary for BatchPutRequest {
+    fn summary(&self) -> String {
+        format!(
+            "{}, values: {} bytes, prev_kv: {}",
+            fmt_keys(self.kvs.iter().map(|kv| kv.key.as_slice())),
+            self.kvs.iter().map(|kv| kv.value.len()).sum::<usize>(),
+            self.prev_kv
+        )
+    }
+}
+
+impl RequestSummary for BatchDeleteRequest {
+    fn summary(&self) -> String {
+        format!(
+            "{}, prev_kv: {}",
+            fmt_keys(self.keys.iter().map(|key| key.as_slice())),
+            self.prev_kv
+        )
+    }
+}
+
+impl RequestSummary for CompareAndPutRequest {
+    fn summary(&self) -> String {
+        format!(
+            "key: '{}', expect: {} bytes, value: {} bytes",
+            fmt_key(&self.key),
+            self.expect.len(),
+            self.value.len()
+        )
+    }
+}
+
+impl RequestSummary for DeleteRangeRequest {
+    fn summary(&self) -> String {
+        format!(
+            "key: '{}', range_end: '{}', prev_kv: {}",
+            fmt_key(&self.key),
+            fmt_key(&self.range_end),
+            self.prev_kv
+        )
+    }
+}
+
+impl RequestSummary for MigrateRegionRequest {
+    fn summary(&self) -> String {
+        format!(
+            "region_id: {}, from_peer: {}, to_peer: {}, timeout: {:?}",
+            self.region_id, self.from_peer, self.to_peer, self.timeout
+        )
+    }
+}
+
+impl RequestSummary for SubmitDdlTaskRequest {
+    fn summary(&self) -> String {
+        let task = match &self.task {
+            DdlTask::CreateTable(_) => "CreateTable",
+            DdlTask::DropTable(_) => "DropTable",
+            DdlTask::AlterTable(_) => "AlterTable",
+            DdlTask::TruncateTable(_) => "TruncateTable",
+            DdlTask::CreateLogicalTables(tasks) => {
+                return format!("task: CreateLogicalTables, tables: {}", tasks.len())
+            }
+            DdlTask::DropLogicalTables(tasks) => {
+                return format!("task: DropLogicalTables, tables: {}", tasks.len())
+            }
+            DdlTask::AlterLogicalTables(tasks) => {
+                return format!("task: AlterLogicalTables, tables: {}", tasks.len())
+            }
+            DdlTask::CreateDatabase(_) => "CreateDatabase",
+            DdlTask::DropDatabase(_) => "DropDatabase",
+            DdlTask::CreateFlow(_) => "CreateFlow",
+            DdlTask::DropFlow(_) => "DropFlow",
+            DdlTask::AlterFlow(_) => "AlterFlow",
+            DdlTask::CreateView(_) => "CreateView",
+            DdlTask::DropView(_) => "DropView",
+        };
+        format!("task: {task}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        let req = RangeRequest::new().with_prefix(b"__table_route/".to_vec());
+        assert_eq!(
+            req.summary(),
+            "key: '__table_route/', range_end: '__table_route0', limit: 0, keys_only: false"
+        );
+
+        let req = BatchPutRequest::new()
+            .add_kv(vec![b'k'; 200], vec![0; 1024])
+            .add_kv(b"k2".to_vec(), vec![0; 1024]);
+        assert_eq!(
+            req.summary(),
+            format!(
+                "2 keys, first: '{}...', values: 2048 bytes, prev_kv: false",
+                "k".repeat(MAX_SUMMARY_KEY_LEN)
+            )
+        );
+
+        assert_eq!(BatchGetRequest::new().summary(), "0 keys");
+    }
+}
@@ -12,9 +12,11 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use common_error::ext::ErrorExt;
+use std::time::Duration;
+
+use common_error::error_info::ErrorInfo;
+use common_error::ext::{ErrorExt, RemoteStackError};
 use common_error::status_code::StatusCode;
-use common_error::{GREPTIME_DB_HEADER_ERROR_CODE, GREPTIME_DB_HEADER_ERROR_MSG};
 use common_macro::stack_trace_debug;
 use snafu::{Location, Snafu};
 use tonic::Status;
@@ -31,7 +33,11 @@ pub enum Error {
     },
 
     #[snafu(display("{}", msg))]
-    MetaServer { code: StatusCode, msg: String },
+    MetaServer {
+        code: StatusCode,
+        msg: String,
+        source: RemoteStackError,
+    },
 
     #[snafu(display("No leader, should ask leader first"))]
     NoLeader {
@@ -97,6 +103,30 @@ pub enum Error {
 
     #[snafu(display("Retry exceeded max times({}), message: {}", times, msg))]
     RetryTimesExceeded { times: usize, msg: String },
+
+    #[snafu(display("Procedure {} failed: {}", procedure_id, err_msg))]
+    ProcedureFailed {
+        procedure_id: String,
+        err_msg: String,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display(
+        "Procedure {} is not done within {:?}, status: {}, error: {}",
+        procedure_id,
+        timeout,
+        status,
+        err_msg
+    ))]
+    WaitProcedureTimeout {
+        procedure_id: String,
+        timeout: Duration,
+        status: String,
+        err_msg: String,
+        #[snafu(implicit)]
+        location: Location,
+    },
 }
 
 #[allow(dead_code)]
@@ -116,7 +146,9 @@ impl ErrorExt for Error {
             | Error::SendHeartbeat { .. }
             | Error::CreateHeartbeatStream { .. }
             | Error::CreateChannel { .. }
-            | Error::RetryTimesExceeded { .. } => StatusCode::Internal,
+            | Error::RetryTimesExceeded { .. }
+            | Error::ProcedureFailed { .. }
+            | Error::WaitProcedureTimeout { .. } => StatusCode::Internal,
 
             Error::MetaServer { code, .. } => *code,
 
@@ -127,28 +159,76 @@ impl ErrorExt for Error {
     }
 }
 
-// FIXME(dennis): partial duplicated with src/client/src/error.rs
 impl From<Status> for Error {
     fn from(e: Status) -> Self {
-        fn get_metadata_value(s: &Status, key: &str) -> Option<String> {
-            s.metadata()
-                .get(key)
-                .and_then(|v| String::from_utf8(v.as_bytes().to_vec()).ok())
+        let info = ErrorInfo::from_status(&e);
+        let code = info.code.unwrap_or(StatusCode::Internal);
+        let msg = info.msg.unwrap_or_else(|| e.message().to_string());
+
+        Self::MetaServer {
+            code,
+            source: RemoteStackError::new(msg.clone(), info.stack, info.hops)
+                .with_frames(info.frames),
+            msg,
         }
+    }
+}
 
-        let code = get_metadata_value(&e, GREPTIME_DB_HEADER_ERROR_CODE)
-            .and_then(|s| {
-                if let Ok(code) = s.parse::<u32>() {
-                    StatusCode::from_u32(code)
-                } else {
-                    None
-                }
-            })
-            .unwrap_or(StatusCode::Internal);
-
-        let msg = get_metadata_value(&e, GREPTIME_DB_HEADER_ERROR_MSG)
-            .unwrap_or_else(|| e.message().to_string());
+impl Error {
+    /// Returns the error stack of metasrv if the error is returned by it, from the outermost
+    /// layer to the innermost one. It's empty if metasrv doesn't send its stack.
+    pub fn stack_errors(&self) -> Option<&[String]> {
+        match self {
+            Error::MetaServer { source, .. } => Some(source.stack()),
+            _ => None,
+        }
+    }
+}
 
-        Self::MetaServer { code, msg }
+#[cfg(test)]
+mod tests {
+    use common_error::{GREPTIME_DB_HEADER_ERROR_CODE, GREPTIME_DB_HEADER_ERROR_STACK};
+    use tonic::metadata::MetadataValue;
+    use tonic::Code;
+
+    use super::*;
+
+    #[test]
+    fn test_meta_server_error_stack() {
+        let mut status = Status::new(Code::NotFound, "Table not found: t");
+        let _ = status.metadata_mut().insert(
+            GREPTIME_DB_HEADER_ERROR_CODE,
+            MetadataValue::from(StatusCode::TableNotFound as u32),
+        );
+        let _ = status.metadata_mut().insert(
+            GREPTIME_DB_HEADER_ERROR_STACK,
+            MetadataValue::from_static(
+                "Failed to get table route, at src/a.rs:1:1\tTable not found: t",
+            ),
+        );
+        let err = Error::from(status);
+        assert_eq!(err.status_code(), StatusCode::TableNotFound);
+        assert_eq!(err.output_msg(), "Table not found: t");
+        assert_eq!(
+            err.stack_errors().unwrap(),
+            [
+                "Failed to get table route, at src/a.rs:1:1",
+                "Table not found: t"
+            ]
+        );
+        assert_eq!(
+            format!("{err:?}"),
+            "0: Table not found: t\n1: Failed to get table route, at src/a.rs:1:1\n2: Table not found: t"
+        );
+
+        // metasrv doesn't send its stack
+        let err = Error::from(Status::new(Code::Internal, "internal"));
+        assert!(err.stack_errors().unwrap().is_empty());
+        assert_eq!(format!("{err:?}"), "0: internal");
+        assert!(Error::NoLeader {
+            location: snafu::location!()
+        }
+        .stack_errors()
+        .is_none());
     }
 }
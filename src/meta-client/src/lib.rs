@@ -23,6 +23,7 @@ use crate::client::MetaClientBuilder;
 
 pub mod client;
 pub mod error;
+mod metrics;
 
 // Options for meta client in datanode instance.
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -43,6 +44,14 @@ pub struct MetaClientOptions {
     pub metadata_cache_ttl: Duration,
     #[serde(with = "humantime_serde")]
     pub metadata_cache_tti: Duration,
+    /// Sends the reads of metadata to followers of the metasrv.
+    pub follower_reads: bool,
+    /// The time reads are still sent to the leader after a write, with follower reads.
+    #[serde(with = "humantime_serde")]
+    pub follower_read_max_staleness: Duration,
+    /// Logs the requests to the metasrv slower than it.
+    #[serde(with = "humantime_serde")]
+    pub slow_call_threshold: Duration,
 }
 
 impl Default for MetaClientOptions {
@@ -57,6 +66,9 @@ impl Default for MetaClientOptions {
             metadata_cache_max_capacity: 100_000u64,
             metadata_cache_ttl: Duration::from_secs(600u64),
             metadata_cache_tti: Duration::from_secs(300u64),
+            follower_reads: false,
+            follower_read_max_staleness: Duration::from_secs(1u64),
+            slow_call_threshold: Duration::from_secs(5u64),
         }
     }
 }
@@ -103,7 +115,12 @@ pub async fn create_meta_client(
 
     builder = builder
         .channel_manager(ChannelManager::with_config(base_config))
-        .heartbeat_channel_manager(ChannelManager::with_config(heartbeat_config));
+        .heartbeat_channel_manager(ChannelManager::with_config(heartbeat_config))
+        .slow_call_threshold(meta_client_options.slow_call_threshold);
+
+    if meta_client_options.follower_reads {
+        builder = builder.enable_follower_reads(meta_client_options.follower_read_max_staleness);
+    }
 
     let mut meta_client = builder.build();
 
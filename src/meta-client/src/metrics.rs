@@ -0,0 +1,50 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use lazy_static::lazy_static;
+use prometheus::*;
+
+lazy_static! {
+    pub static ref METRIC_META_CLIENT_REQUEST_ELAPSED: HistogramVec = register_histogram_vec!(
+        "greptime_meta_client_request_elapsed",
+        "meta client request elapsed",
+        &["method"]
+    )
+    .unwrap();
+    pub static ref METRIC_META_CLIENT_REQUEST_ERRORS: IntCounterVec = register_int_counter_vec!(
+        "greptime_meta_client_request_errors",
+        "meta client request errors",
+        &["method"]
+    )
+    .unwrap();
+    pub static ref METRIC_META_CLIENT_SLOW_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "greptime_meta_client_slow_requests",
+        "meta client slow requests",
+        &["method"]
+    )
+    .unwrap();
+    /// The retries of requests to another metasrv, after the leader is not reachable or is not a
+    /// leader anymore.
+    pub static ref METRIC_META_CLIENT_RETRIES: IntCounterVec = register_int_counter_vec!(
+        "greptime_meta_client_retries",
+        "meta client retries",
+        &["task"]
+    )
+    .unwrap();
+    pub static ref METRIC_META_CLIENT_LEADER_CHANGES: IntCounter = register_int_counter!(
+        "greptime_meta_client_leader_changes",
+        "meta client leader changes"
+    )
+    .unwrap();
+}
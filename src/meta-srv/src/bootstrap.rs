@@ -54,6 +54,7 @@ use crate::selector::load_based::LoadBasedSelector;
 use crate::selector::round_robin::RoundRobinSelector;
 use crate::selector::SelectorType;
 use crate::service::admin;
+use crate::service::leader_lease::LeaderLeaseService;
 use crate::{error, Result};
 
 pub struct MetasrvInstance {
@@ -179,12 +180,25 @@ pub async fn bootstrap_metasrv_with_router(
 }
 
 pub fn router(metasrv: Arc<Metasrv>) -> Router {
+    let election = metasrv.election().cloned();
     tonic::transport::Server::builder()
         .accept_http1(true) // for admin services
-        .add_service(HeartbeatServer::from_arc(metasrv.clone()))
-        .add_service(StoreServer::from_arc(metasrv.clone()))
-        .add_service(ClusterServer::from_arc(metasrv.clone()))
-        .add_service(ProcedureServiceServer::from_arc(metasrv.clone()))
+        .add_service(LeaderLeaseService::new(
+            HeartbeatServer::from_arc(metasrv.clone()),
+            election.clone(),
+        ))
+        .add_service(LeaderLeaseService::new(
+            StoreServer::from_arc(metasrv.clone()),
+            election.clone(),
+        ))
+        .add_service(LeaderLeaseService::new(
+            ClusterServer::from_arc(metasrv.clone()),
+            election.clone(),
+        ))
+        .add_service(LeaderLeaseService::new(
+            ProcedureServiceServer::from_arc(metasrv.clone()),
+            election,
+        ))
         .add_service(admin::make_admin_service(metasrv))
 }
 
@@ -16,6 +16,7 @@ pub mod etcd;
 
 use std::fmt;
 use std::sync::Arc;
+use std::time::Instant;
 
 use etcd_client::LeaderKey;
 use tokio::sync::broadcast::Receiver;
@@ -53,6 +54,15 @@ impl fmt::Display for LeaderChangeMessage {
     }
 }
 
+/// The lease of the leadership, renewed by the leader before it expires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LeaderLease {
+    /// The term of the leadership, a leader elected later has a higher term.
+    pub term: i64,
+    /// When the leadership expires if the lease is not renewed.
+    pub expire_at: Instant,
+}
+
 #[async_trait::async_trait]
 pub trait Election: Send + Sync {
     type Leader;
@@ -67,6 +77,11 @@ pub trait Election: Send + Sync {
     /// note: a new leader will only return true on the first call.
     fn in_infancy(&self) -> bool;
 
+    /// Returns the lease of the leadership if current node is the leader.
+    fn leader_lease(&self) -> Option<LeaderLease> {
+        None
+    }
+
     /// Registers a candidate for the election.
     async fn register_candidate(&self, node_info: &MetasrvNodeInfo) -> Result<()>;
 
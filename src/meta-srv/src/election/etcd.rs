@@ -13,8 +13,8 @@
 // limitations under the License.
 
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use std::time::Duration;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 
 use common_meta::distributed_time_constants::{META_KEEP_ALIVE_INTERVAL_SECS, META_LEASE_SECS};
 use common_telemetry::{error, info, warn};
@@ -25,7 +25,7 @@ use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::broadcast::Receiver;
 use tokio::time::{timeout, MissedTickBehavior};
 
-use crate::election::{Election, LeaderChangeMessage, CANDIDATES_ROOT, ELECTION_KEY};
+use crate::election::{Election, LeaderChangeMessage, LeaderLease, CANDIDATES_ROOT, ELECTION_KEY};
 use crate::error;
 use crate::error::Result;
 use crate::metasrv::{ElectionRef, LeaderValue, MetasrvNodeInfo};
@@ -35,6 +35,7 @@ pub struct EtcdElection {
     client: Client,
     is_leader: AtomicBool,
     infancy: AtomicBool,
+    leader_lease: RwLock<Option<LeaderLease>>,
     leader_watcher: broadcast::Sender<LeaderChangeMessage>,
     store_key_prefix: String,
 }
@@ -100,6 +101,7 @@ impl EtcdElection {
             client,
             is_leader: AtomicBool::new(false),
             infancy: AtomicBool::new(false),
+            leader_lease: RwLock::new(None),
             leader_watcher: tx,
             store_key_prefix,
         }))
@@ -132,6 +134,13 @@ impl Election for EtcdElection {
             .is_ok()
     }
 
+    fn leader_lease(&self) -> Option<LeaderLease> {
+        if !self.is_leader() {
+            return None;
+        }
+        *self.leader_lease.read().unwrap()
+    }
+
     async fn register_candidate(&self, node_info: &MetasrvNodeInfo) -> Result<()> {
         const CANDIDATE_LEASE_SECS: u64 = 600;
         const KEEP_ALIVE_INTERVAL_SECS: u64 = CANDIDATE_LEASE_SECS / 2;
@@ -257,6 +266,7 @@ impl Election for EtcdElection {
                 }
             }
 
+            *self.leader_lease.write().unwrap() = None;
             if self
                 .is_leader
                 .compare_exchange(true, false, Ordering::Relaxed, Ordering::Relaxed)
@@ -305,6 +315,8 @@ impl EtcdElection {
         receiver: &mut LeaseKeepAliveStream,
         leader: &LeaderKey,
     ) -> Result<()> {
+        // the lease expires in ttl after it's renewed, at the latest
+        let renewed_at = Instant::now();
         keeper.keep_alive().await.context(error::EtcdFailedSnafu)?;
         if let Some(res) = receiver.message().await.context(error::EtcdFailedSnafu)? {
             ensure!(
@@ -313,6 +325,10 @@ impl EtcdElection {
                     violated: "Failed to refresh the lease",
                 }
             );
+            *self.leader_lease.write().unwrap() = Some(LeaderLease {
+                term: leader.rev(),
+                expire_at: renewed_at + Duration::from_secs(res.ttl() as u64),
+            });
 
             // Only after a successful `keep_alive` is the leader considered official.
             if self
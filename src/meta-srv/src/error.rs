@@ -728,7 +728,8 @@ impl Error {
 
 pub type Result<T> = std::result::Result<T, Error>;
 
-define_into_tonic_status!(Error);
+// The clients of metasrv are other nodes, which log the stack of the error for diagnosis.
+define_into_tonic_status!(Error, with_stack);
 
 impl ErrorExt for Error {
     fn status_code(&self) -> StatusCode {
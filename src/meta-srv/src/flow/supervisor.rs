@@ -0,0 +1,222 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use common_meta::ddl_manager::DdlManagerRef;
+use common_meta::key::flow::FlowMetadataManagerRef;
+use common_meta::key::MAINTENANCE_KEY;
+use common_meta::kv_backend::KvBackendRef;
+use common_meta::leadership_notifier::LeadershipChangeListener;
+use common_meta::{ClusterId, FlownodeId};
+use common_runtime::JoinHandle;
+use common_telemetry::{error, info};
+use futures::TryStreamExt;
+use snafu::ResultExt;
+use tokio::time::{interval, MissedTickBehavior};
+
+use crate::cluster::MetaPeerClientRef;
+use crate::error::{self, Result};
+use crate::lease;
+
+pub type FlowSupervisorRef = Arc<FlowSupervisor>;
+
+/// The default tick interval.
+pub const DEFAULT_TICK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The [`FlowSupervisor`] detects failed flownodes by their expired leases, and moves flows on
+/// them to healthy flownodes, so flows keep running after losing a flownode.
+///
+/// It only runs on the leader.
+pub struct FlowSupervisor {
+    /// The [`Option`] wrapper allows us to abort the job while dropping the [`FlowSupervisor`].
+    tick_handle: Mutex<Option<JoinHandle<()>>>,
+    /// The interval of tick.
+    tick_interval: Duration,
+    failover: Arc<FlowFailover>,
+}
+
+#[async_trait]
+impl LeadershipChangeListener for FlowSupervisor {
+    fn name(&self) -> &'static str {
+        "FlowSupervisor"
+    }
+
+    async fn on_leader_start(&self) -> common_meta::error::Result<()> {
+        self.start();
+        Ok(())
+    }
+
+    async fn on_leader_stop(&self) -> common_meta::error::Result<()> {
+        self.stop();
+        Ok(())
+    }
+}
+
+impl FlowSupervisor {
+    pub(crate) fn new(
+        tick_interval: Duration,
+        meta_peer_client: MetaPeerClientRef,
+        kv_backend: KvBackendRef,
+        flow_metadata_manager: FlowMetadataManagerRef,
+        ddl_manager: DdlManagerRef,
+        flownode_lease_secs: u64,
+    ) -> Self {
+        Self {
+            tick_handle: Mutex::new(None),
+            tick_interval,
+            failover: Arc::new(FlowFailover {
+                meta_peer_client,
+                kv_backend,
+                flow_metadata_manager,
+                ddl_manager,
+                flownode_lease_secs,
+            }),
+        }
+    }
+
+    /// Starts the supervisor.
+    pub fn start(&self) {
+        let mut handle = self.tick_handle.lock().unwrap();
+        if handle.is_none() {
+            let failover = self.failover.clone();
+            let tick_interval = self.tick_interval;
+            let ticker_loop = tokio::spawn(async move {
+                let mut interval = interval(tick_interval);
+                interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+                loop {
+                    interval.tick().await;
+                    if let Err(err) = failover.handle_tick().await {
+                        error!(err; "Failed to fail over flows of failed flownodes");
+                    }
+                }
+            });
+            *handle = Some(ticker_loop);
+        }
+    }
+
+    /// Stops the supervisor.
+    pub fn stop(&self) {
+        let handle = self.tick_handle.lock().unwrap().take();
+        if let Some(handle) = handle {
+            handle.abort();
+            info!("The flow supervisor is stopped.");
+        }
+    }
+}
+
+impl Drop for FlowSupervisor {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+struct FlowFailover {
+    meta_peer_client: MetaPeerClientRef,
+    kv_backend: KvBackendRef,
+    flow_metadata_manager: FlowMetadataManagerRef,
+    ddl_manager: DdlManagerRef,
+    flownode_lease_secs: u64,
+}
+
+impl FlowFailover {
+    async fn handle_tick(&self) -> Result<()> {
+        let failed_flownodes =
+            lease::expired_flownodes(&self.meta_peer_client, self.flownode_lease_secs).await?;
+        if failed_flownodes.is_empty() {
+            return Ok(());
+        }
+        if self.is_maintenance_mode().await? {
+            info!("Maintenance mode is enabled, skip flow failover");
+            return Ok(());
+        }
+
+        for lease_key in failed_flownodes.keys() {
+            // flows are reassigned one by one, a failed one is retried on the next tick
+            if let Err(err) = self
+                .failover_flownode(lease_key.cluster_id, lease_key.node_id)
+                .await
+            {
+                error!(err; "Failed to fail over flows of flownode {}", lease_key.node_id);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn is_maintenance_mode(&self) -> Result<bool> {
+        self.kv_backend
+            .exists(MAINTENANCE_KEY.as_bytes())
+            .await
+            .context(error::KvBackendSnafu)
+    }
+
+    /// Moves all flows on the failed flownode to other flownodes.
+    async fn failover_flownode(
+        &self,
+        cluster_id: ClusterId,
+        flownode_id: FlownodeId,
+    ) -> Result<()> {
+        let flow_ids = self
+            .flow_metadata_manager
+            .flownode_flow_manager()
+            .flows(flownode_id)
+            .map_ok(|(flow_id, _)| flow_id)
+            .try_collect::<BTreeSet<_>>()
+            .await
+            .context(error::TableMetadataManagerSnafu)?;
+
+        for flow_id in flow_ids {
+            let Some(flow_info) = self
+                .flow_metadata_manager
+                .flow_info_manager()
+                .get(flow_id)
+                .await
+                .context(error::TableMetadataManagerSnafu)?
+            else {
+                continue;
+            };
+
+            info!(
+                "Flownode {} is failed, moving flow {}.{}({}) to other flownodes",
+                flownode_id,
+                flow_info.catalog_name(),
+                flow_info.flow_name(),
+                flow_id
+            );
+            let (procedure_id, _) = self
+                .ddl_manager
+                .submit_reassign_flow_task(
+                    cluster_id,
+                    flow_id,
+                    flow_info.catalog_name().clone(),
+                    flow_info.flow_name().clone(),
+                    flownode_id,
+                )
+                .await
+                .context(error::SubmitDdlTaskSnafu)?;
+            info!(
+                "Flow {}.{}({}) is reassigned via procedure_id {procedure_id:?}",
+                flow_info.catalog_name(),
+                flow_info.flow_name(),
+                flow_id
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -31,6 +31,11 @@ pub struct FlownodeLeaseKey {
 }
 
 impl FlownodeLeaseKey {
+    /// Returns the prefix of lease keys of flownodes in all clusters.
+    pub fn prefix_key() -> Vec<u8> {
+        format!("{FLOWNODE_LEASE_PREFIX}-").into_bytes()
+    }
+
     pub fn prefix_key_by_cluster(cluster_id: ClusterId) -> Vec<u8> {
         format!("{FLOWNODE_LEASE_PREFIX}-{cluster_id}-").into_bytes()
     }
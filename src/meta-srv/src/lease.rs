@@ -120,6 +120,18 @@ pub async fn alive_flownodes(
     .await
 }
 
+/// Find all flownodes of all clusters whose lease is expired, i.e. stop sending heartbeats
+pub async fn expired_flownodes(
+    meta_peer_client: &MetaPeerClientRef,
+    lease_secs: u64,
+) -> Result<HashMap<FlownodeLeaseKey, LeaseValue>> {
+    let predicate = build_lease_filter(lease_secs);
+    filter(FlownodeLeaseKey::prefix_key(), meta_peer_client, |v| {
+        !predicate(v)
+    })
+    .await
+}
+
 pub async fn filter<P, K>(
     key: Vec<u8>,
     meta_peer_client: &MetaPeerClientRef,
@@ -23,6 +23,7 @@ pub mod cluster;
 pub mod election;
 pub mod error;
 mod failure_detector;
+pub mod flow;
 pub mod flow_meta_alloc;
 pub mod handler;
 pub mod key;
@@ -23,9 +23,11 @@ use clap::ValueEnum;
 use common_base::readable_size::ReadableSize;
 use common_base::Plugins;
 use common_config::Configurable;
+use common_error::status_code::DEFAULT_MAX_ERROR_STACK_SIZE;
 use common_greptimedb_telemetry::GreptimeDBTelemetryTask;
 use common_grpc::channel_manager;
 use common_meta::cache_invalidator::CacheInvalidatorRef;
+use common_meta::ddl::flow_meta::FlowQuotaOptions;
 use common_meta::ddl::ProcedureExecutorRef;
 use common_meta::key::TableMetadataManagerRef;
 use common_meta::kv_backend::{KvBackendRef, ResettableKvBackend, ResettableKvBackendRef};
@@ -55,6 +57,7 @@ use crate::error::{
     StartTelemetryTaskSnafu, StopProcedureManagerSnafu,
 };
 use crate::failure_detector::PhiAccrualFailureDetectorOptions;
+use crate::flow::supervisor::FlowSupervisorRef;
 use crate::handler::{HeartbeatHandlerGroupBuilder, HeartbeatHandlerGroupRef};
 use crate::lease::lookup_datanode_peer;
 use crate::procedure::region_migration::manager::RegionMigrationManagerRef;
@@ -63,6 +66,7 @@ use crate::pubsub::{PublisherRef, SubscriptionManagerRef};
 use crate::region::supervisor::RegionSupervisorTickerRef;
 use crate::selector::{Selector, SelectorType};
 use crate::service::mailbox::MailboxRef;
+use crate::service::procedure::DdlDeduplicator;
 use crate::service::store::cached_kv::LeaderCachedKvBackend;
 use crate::state::{become_follower, become_leader, StateRef};
 
@@ -98,6 +102,13 @@ pub struct MetasrvOptions {
     pub use_memory_store: bool,
     /// Whether to enable region failover.
     pub enable_region_failover: bool,
+    /// Whether to enable flow failover.
+    pub enable_flow_failover: bool,
+    /// The limits of flows.
+    pub flow_quota: FlowQuotaOptions,
+    /// The max size of the error stack sent to the clients, in all the headers it's encoded
+    /// into in total, the layers in the middle of the stack are truncated if exceeded.
+    pub max_error_stack_size: ReadableSize,
     /// The HTTP server options.
     pub http: HttpOptions,
     /// The logging options.
@@ -145,6 +156,9 @@ impl Default for MetasrvOptions {
             selector: SelectorType::default(),
             use_memory_store: false,
             enable_region_failover: false,
+            enable_flow_failover: false,
+            flow_quota: FlowQuotaOptions::default(),
+            max_error_stack_size: ReadableSize(DEFAULT_MAX_ERROR_STACK_SIZE as u64),
             http: HttpOptions::default(),
             logging: LoggingOptions {
                 dir: format!("{METASRV_HOME}/logs"),
@@ -368,7 +382,9 @@ pub struct Metasrv {
     greptimedb_telemetry_task: Arc<GreptimeDBTelemetryTask>,
     region_migration_manager: RegionMigrationManagerRef,
     region_supervisor_ticker: Option<RegionSupervisorTickerRef>,
+    flow_supervisor: Option<FlowSupervisorRef>,
     cache_invalidator: CacheInvalidatorRef,
+    ddl_deduplicator: DdlDeduplicator,
 
     plugins: Plugins,
 }
@@ -419,6 +435,9 @@ impl Metasrv {
             if let Some(region_supervisor_ticker) = &self.region_supervisor_ticker {
                 leadership_change_notifier.add_listener(region_supervisor_ticker.clone() as _);
             }
+            if let Some(flow_supervisor) = &self.flow_supervisor {
+                leadership_change_notifier.add_listener(flow_supervisor.clone() as _);
+            }
             if let Some(customizer) = self.plugins.get::<LeadershipChangeNotifierCustomizerRef>() {
                 customizer.customize(&mut leadership_change_notifier);
             }
@@ -504,6 +523,9 @@ impl Metasrv {
                 .start()
                 .await
                 .context(StartProcedureManagerSnafu)?;
+            if let Some(flow_supervisor) = &self.flow_supervisor {
+                flow_supervisor.start();
+            }
         }
 
         info!("Metasrv started");
@@ -588,6 +610,10 @@ impl Metasrv {
         &self.procedure_executor
     }
 
+    pub(crate) fn ddl_deduplicator(&self) -> &DdlDeduplicator {
+        &self.ddl_deduplicator
+    }
+
     pub fn procedure_manager(&self) -> &ProcedureManagerRef {
         &self.procedure_manager
     }
@@ -27,7 +27,7 @@ use common_meta::ddl::{
 use common_meta::ddl_manager::DdlManager;
 use common_meta::distributed_time_constants;
 use common_meta::key::flow::FlowMetadataManager;
-use common_meta::key::TableMetadataManager;
+use common_meta::key::{FlowId, TableMetadataManager};
 use common_meta::kv_backend::memory::MemoryKvBackend;
 use common_meta::kv_backend::{KvBackendRef, ResettableKvBackendRef};
 use common_meta::node_manager::NodeManagerRef;
@@ -43,6 +43,9 @@ use super::{SelectTarget, FLOW_ID_SEQ};
 use crate::cache_invalidator::MetasrvCacheInvalidator;
 use crate::cluster::{MetaPeerClientBuilder, MetaPeerClientRef};
 use crate::error::{self, Result};
+use crate::flow::supervisor::{
+    FlowSupervisor, DEFAULT_TICK_INTERVAL as FLOW_SUPERVISOR_TICK_INTERVAL,
+};
 use crate::flow_meta_alloc::FlowPeerAllocator;
 use crate::greptimedb_telemetry::get_greptimedb_telemetry_task;
 use crate::handler::failure_handler::RegionFailureHandler;
@@ -238,6 +241,7 @@ impl MetasrvBuilder {
                 SequenceBuilder::new(FLOW_ID_SEQ, kv_backend.clone())
                     .initial(MIN_USER_FLOW_ID as u64)
                     .step(10)
+                    .max(FlowId::MAX as u64)
                     .build(),
             );
 
@@ -327,12 +331,23 @@ impl MetasrvBuilder {
                     flow_metadata_manager: flow_metadata_manager.clone(),
                     flow_metadata_allocator: flow_metadata_allocator.clone(),
                     region_failure_detector_controller,
+                    flow_quota: options.flow_quota.clone(),
                 },
                 procedure_manager.clone(),
                 true,
             )
             .context(error::InitDdlManagerSnafu)?,
         );
+        let flow_supervisor = options.enable_flow_failover.then(|| {
+            Arc::new(FlowSupervisor::new(
+                FLOW_SUPERVISOR_TICK_INTERVAL,
+                meta_peer_client.clone(),
+                leader_cached_kv_backend.clone() as _,
+                flow_metadata_manager.clone(),
+                ddl_manager.clone(),
+                distributed_time_constants::FLOWNODE_LEASE_SECS,
+            ))
+        });
 
         let handler_group_builder = match handler_group_builder {
             Some(handler_group_builder) => handler_group_builder,
@@ -384,7 +399,9 @@ impl MetasrvBuilder {
             memory_region_keeper,
             region_migration_manager,
             region_supervisor_ticker,
+            flow_supervisor,
             cache_invalidator,
+            ddl_deduplicator: Default::default(),
         })
     }
 }
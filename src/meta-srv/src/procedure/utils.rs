@@ -227,6 +227,7 @@ pub mod test_data {
             flow_metadata_allocator,
             memory_region_keeper: Arc::new(MemoryRegionKeeper::new()),
             region_failure_detector_controller: Arc::new(NoopRegionFailureDetectorControl),
+            flow_quota: Default::default(),
         }
     }
 }
@@ -20,6 +20,7 @@ use tonic::{Response, Status};
 pub mod admin;
 pub mod cluster;
 mod heartbeat;
+pub mod leader_lease;
 pub mod mailbox;
 pub mod procedure;
 pub mod store;
@@ -0,0 +1,100 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use common_meta::rpc::{META_LEADER_LEASE_REMAINING, META_LEADER_TERM};
+use tonic::codegen::{http, BoxFuture, Service};
+use tonic::server::NamedService;
+
+use crate::election::LeaderLease;
+use crate::metasrv::ElectionRef;
+
+/// Wraps a gRPC service and attaches the lease of the leader to its responses,
+/// so that clients can tell a stale leader before its lease expires.
+#[derive(Clone)]
+pub struct LeaderLeaseService<S> {
+    inner: S,
+    election: Option<ElectionRef>,
+}
+
+impl<S> LeaderLeaseService<S> {
+    pub fn new(inner: S, election: Option<ElectionRef>) -> Self {
+        Self { inner, election }
+    }
+}
+
+impl<S: NamedService> NamedService for LeaderLeaseService<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+impl<S, ReqBody, ResBody> Service<http::Request<ReqBody>> for LeaderLeaseService<S>
+where
+    S: Service<http::Request<ReqBody>, Response = http::Response<ResBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<ReqBody>) -> Self::Future {
+        let election = self.election.clone();
+        let fut = self.inner.call(req);
+        Box::pin(async move {
+            let mut res = fut.await?;
+            if let Some(lease) = election.and_then(|e| e.leader_lease()) {
+                attach_lease(res.headers_mut(), lease, Instant::now());
+            }
+            Ok(res)
+        })
+    }
+}
+
+fn attach_lease(headers: &mut http::HeaderMap, lease: LeaderLease, now: Instant) {
+    let remaining = lease.expire_at.saturating_duration_since(now).as_millis();
+    let _ = headers.insert(META_LEADER_TERM, http::HeaderValue::from(lease.term));
+    let _ = headers.insert(
+        META_LEADER_LEASE_REMAINING,
+        http::HeaderValue::from(remaining as u64),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_attach_lease() {
+        let now = Instant::now();
+        let mut headers = http::HeaderMap::new();
+        let lease = LeaderLease {
+            term: 42,
+            expire_at: now + Duration::from_millis(1500),
+        };
+        attach_lease(&mut headers, lease, now);
+        assert_eq!("42", headers.get(META_LEADER_TERM).unwrap());
+        assert_eq!("1500", headers.get(META_LEADER_LEASE_REMAINING).unwrap());
+
+        // an expired lease has no remaining time
+        attach_lease(&mut headers, lease, now + Duration::from_secs(3));
+        assert_eq!("0", headers.get(META_LEADER_LEASE_REMAINING).unwrap());
+    }
+}
@@ -12,22 +12,26 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::sync::Arc;
-use std::time::Duration;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use api::v1::meta::{
     procedure_service_server, DdlTaskRequest as PbDdlTaskRequest,
     DdlTaskResponse as PbDdlTaskResponse, MigrateRegionRequest, MigrateRegionResponse,
     ProcedureDetailRequest, ProcedureDetailResponse, ProcedureStateResponse, QueryProcedureRequest,
 };
-use common_meta::ddl::ExecutorContext;
-use common_meta::rpc::ddl::{DdlTask, SubmitDdlTaskRequest};
+use common_meta::ddl::{ExecutorContext, ProcedureExecutorRef};
+use common_meta::rpc::ddl::{DdlTask, SubmitDdlTaskRequest, DDL_IDEMPOTENCY_KEY};
 use common_meta::rpc::procedure;
 use snafu::{ensure, OptionExt, ResultExt};
+use tokio::sync::OnceCell;
 use tonic::{Request, Response};
 
 use super::GrpcResult;
 use crate::error;
+use crate::error::Result;
 use crate::metasrv::Metasrv;
 use crate::procedure::region_migration::manager::RegionMigrationProcedureTask;
 
@@ -57,40 +61,22 @@ impl procedure_service_server::ProcedureService for Metasrv {
     }
 
     async fn ddl(&self, request: Request<PbDdlTaskRequest>) -> GrpcResult<PbDdlTaskResponse> {
-        let PbDdlTaskRequest {
-            header,
-            query_context,
-            task,
-            ..
-        } = request.into_inner();
+        let idempotency_key = request
+            .metadata()
+            .get(DDL_IDEMPOTENCY_KEY)
+            .and_then(|key| key.to_str().ok())
+            .map(ToString::to_string);
+        let request = request.into_inner();
 
-        let header = header.context(error::MissingRequestHeaderSnafu)?;
-        let cluster_id = header.cluster_id;
-        let query_context = query_context
-            .context(error::MissingRequiredParameterSnafu {
-                param: "query_context",
-            })?
-            .into();
-        let task: DdlTask = task
-            .context(error::MissingRequiredParameterSnafu { param: "task" })?
-            .try_into()
-            .context(error::ConvertProtoDataSnafu)?;
-
-        let resp = self
-            .procedure_executor()
-            .submit_ddl_task(
-                &ExecutorContext {
-                    cluster_id: Some(cluster_id),
-                    tracing_context: Some(header.tracing_context),
-                },
-                SubmitDdlTaskRequest {
-                    query_context: Arc::new(query_context),
-                    task,
-                },
-            )
-            .await
-            .context(error::SubmitDdlTaskSnafu)?
-            .into();
+        let executor = self.procedure_executor().clone();
+        let resp = match idempotency_key {
+            Some(key) => {
+                self.ddl_deduplicator()
+                    .run(key, submit_ddl_task(executor, request))
+                    .await?
+            }
+            None => submit_ddl_task(executor, request).await?,
+        };
 
         Ok(Response::new(resp))
     }
@@ -162,3 +148,165 @@ impl procedure_service_server::ProcedureService for Metasrv {
         )))
     }
 }
+
+async fn submit_ddl_task(
+    executor: ProcedureExecutorRef,
+    request: PbDdlTaskRequest,
+) -> Result<PbDdlTaskResponse> {
+    let PbDdlTaskRequest {
+        header,
+        query_context,
+        task,
+        ..
+    } = request;
+
+    let header = header.context(error::MissingRequestHeaderSnafu)?;
+    let cluster_id = header.cluster_id;
+    let query_context = query_context
+        .context(error::MissingRequiredParameterSnafu {
+            param: "query_context",
+        })?
+        .into();
+    let task: DdlTask = task
+        .context(error::MissingRequiredParameterSnafu { param: "task" })?
+        .try_into()
+        .context(error::ConvertProtoDataSnafu)?;
+
+    let resp = executor
+        .submit_ddl_task(
+            &ExecutorContext {
+                cluster_id: Some(cluster_id),
+                tracing_context: Some(header.tracing_context),
+            },
+            SubmitDdlTaskRequest {
+                query_context: Arc::new(query_context),
+                task,
+            },
+        )
+        .await
+        .context(error::SubmitDdlTaskSnafu)?
+        .into();
+
+    Ok(resp)
+}
+
+/// How long the response of a DDL task is kept for the tasks submitted again with the same
+/// idempotency key.
+const DDL_IDEMPOTENCY_KEY_TTL: Duration = Duration::from_secs(600);
+
+/// Runs the DDL tasks with the same idempotency key once, see [DDL_IDEMPOTENCY_KEY].
+///
+/// A task submitted again while it's running waits for it, and one submitted after it's done
+/// gets its response. A failed task is run again. The keys are only kept in memory, so a task
+/// submitted again to a new leader is run again.
+///
+/// Tasks are run in their own tokio tasks, so a task keeps running and its response is kept
+/// even if the request submitting it is dropped, e.g. its connection is broken, and the client
+/// retrying it waits for it instead of running it again.
+#[derive(Debug, Default)]
+pub(crate) struct DdlDeduplicator {
+    tasks: Mutex<HashMap<String, (Instant, Arc<OnceCell<PbDdlTaskResponse>>)>>,
+}
+
+impl DdlDeduplicator {
+    /// Runs the task by `submit` unless a task with the same `key` has run.
+    pub(crate) async fn run<F>(&self, key: String, submit: F) -> Result<PbDdlTaskResponse>
+    where
+        F: Future<Output = Result<PbDdlTaskResponse>> + Send + 'static,
+    {
+        let task = {
+            let mut tasks = self.tasks.lock().unwrap();
+            tasks.retain(|_, (submitted, _)| submitted.elapsed() < DDL_IDEMPOTENCY_KEY_TTL);
+            tasks
+                .entry(key)
+                .or_insert_with(|| (Instant::now(), Arc::default()))
+                .1
+                .clone()
+        };
+        // `submit` is only run if no task of the key is running or done
+        tokio::spawn(async move { task.get_or_try_init(|| submit).await.cloned() })
+            .await
+            .context(error::JoinSnafu)?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use tokio::sync::oneshot;
+
+    use super::*;
+
+    async fn submit(runs: Arc<AtomicUsize>, fail: bool) -> Result<PbDdlTaskResponse> {
+        let run = runs.fetch_add(1, Ordering::Relaxed) as u32 + 1;
+        if fail {
+            return error::UnexpectedSnafu {
+                violated: "mock failure",
+            }
+            .fail();
+        }
+        Ok(PbDdlTaskResponse {
+            table_ids: vec![api::v1::TableId { id: run }],
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn test_ddl_deduplicator() {
+        let deduplicator = DdlDeduplicator::default();
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        // a failed task is run again
+        assert!(deduplicator
+            .run("key1".to_string(), submit(runs.clone(), true))
+            .await
+            .is_err());
+        let resp = deduplicator
+            .run("key1".to_string(), submit(runs.clone(), false))
+            .await
+            .unwrap();
+        assert_eq!(resp.table_ids[0].id, 2);
+
+        // a task done is not run again
+        let resp = deduplicator
+            .run("key1".to_string(), submit(runs.clone(), false))
+            .await
+            .unwrap();
+        assert_eq!(resp.table_ids[0].id, 2);
+        assert_eq!(runs.load(Ordering::Relaxed), 2);
+
+        // tasks of other keys run
+        let resp = deduplicator
+            .run("key2".to_string(), submit(runs.clone(), false))
+            .await
+            .unwrap();
+        assert_eq!(resp.table_ids[0].id, 3);
+    }
+
+    #[tokio::test]
+    async fn test_ddl_deduplicator_caller_dropped() {
+        let deduplicator = DdlDeduplicator::default();
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        let (tx, rx) = oneshot::channel::<()>();
+        let first = deduplicator.run("key".to_string(), {
+            let runs = runs.clone();
+            async move {
+                rx.await.unwrap();
+                submit(runs, false).await
+            }
+        });
+        // the first caller is dropped while its task is running, e.g. its connection is broken
+        assert!(tokio::time::timeout(Duration::from_millis(100), first)
+            .await
+            .is_err());
+
+        // the retry waits for the task instead of running it again
+        let retry = deduplicator.run("key".to_string(), submit(runs.clone(), false));
+        tx.send(()).unwrap();
+        let resp = retry.await.unwrap();
+        assert_eq!(resp.table_ids[0].id, 1);
+        assert_eq!(runs.load(Ordering::Relaxed), 1);
+    }
+}
@@ -19,14 +19,17 @@ use std::{iter, mem};
 use api::v1::region::{DeleteRequests as RegionDeleteRequests, RegionRequestHeader};
 use api::v1::{DeleteRequests, RowDeleteRequests};
 use catalog::CatalogManagerRef;
+use common_meta::cache::TableFlownodeSetCacheRef;
 use common_meta::node_manager::{AffectedRows, NodeManagerRef};
 use common_meta::peer::Peer;
 use common_query::Output;
 use common_telemetry::tracing_context::TracingContext;
+use common_telemetry::warn;
 use futures_util::future;
 use partition::manager::PartitionRuleManagerRef;
 use session::context::QueryContextRef;
 use snafu::{ensure, OptionExt, ResultExt};
+use store_api::storage::{RegionId, TableId};
 use table::requests::DeleteRequest as TableDeleteRequest;
 use table::TableRef;
 
@@ -41,6 +44,7 @@ pub struct Deleter {
     catalog_manager: CatalogManagerRef,
     partition_manager: PartitionRuleManagerRef,
     node_manager: NodeManagerRef,
+    table_flownode_set_cache: TableFlownodeSetCacheRef,
 }
 
 pub type DeleterRef = Arc<Deleter>;
@@ -50,11 +54,13 @@ impl Deleter {
         catalog_manager: CatalogManagerRef,
         partition_manager: PartitionRuleManagerRef,
         node_manager: NodeManagerRef,
+        table_flownode_set_cache: TableFlownodeSetCacheRef,
     ) -> Self {
         Self {
             catalog_manager,
             partition_manager,
             node_manager,
+            table_flownode_set_cache,
         }
     }
 
@@ -126,6 +132,11 @@ impl Deleter {
             dbname: ctx.get_db_string(),
             ..Default::default()
         });
+        let table_ids = requests
+            .requests
+            .iter()
+            .map(|req| RegionId::from_u64(req.region_id).table_id())
+            .collect::<HashSet<_>>();
 
         let tasks = self
             .group_requests_by_peer(requests)
@@ -150,9 +161,47 @@ impl Deleter {
             .map(|resp| resp.map(|r| r.affected_rows))
             .sum::<Result<AffectedRows>>()?;
         crate::metrics::DIST_DELETE_ROW_COUNT.inc_by(affected_rows as u64);
+
+        if affected_rows > 0 {
+            self.reset_flows(table_ids).await;
+        }
         Ok(affected_rows)
     }
 
+    /// Ask flownodes to recompute flows reading from the tables rows are deleted from, since
+    /// deletes can't be mirrored to flownode like inserts
+    ///
+    /// Recomputing is done in background and failures are only logged, as the rows are deleted
+    /// anyway.
+    async fn reset_flows(&self, table_ids: HashSet<TableId>) {
+        let mut peer_tables: HashMap<Peer, Vec<TableId>> = HashMap::new();
+        for table_id in table_ids {
+            match self.table_flownode_set_cache.get(table_id).await {
+                Ok(Some(flownodes)) => {
+                    for peer in flownodes.into_values() {
+                        peer_tables.entry(peer).or_default().push(table_id);
+                    }
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    warn!(err; "Failed to find flownodes of flows reading from table {}", table_id)
+                }
+            }
+        }
+
+        for (peer, table_ids) in peer_tables {
+            let node_manager = self.node_manager.clone();
+            let _handle = common_runtime::spawn_global(async move {
+                let flownode = node_manager.flownode(&peer).await;
+                for table_id in table_ids {
+                    if let Err(err) = flownode.reset_source_table(table_id).await {
+                        warn!(err; "Failed to reset flows reading from table {}", table_id);
+                    }
+                }
+            });
+        }
+    }
+
     async fn group_requests_by_peer(
         &self,
         requests: RegionDeleteRequests,
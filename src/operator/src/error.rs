@@ -228,6 +228,21 @@ pub enum Error {
     #[snafu(display("Flow not found: {}", flow_name))]
     FlowNotFound { flow_name: String },
 
+    #[snafu(display("Failed to explain flow: {}", flow_name))]
+    ExplainFlow {
+        flow_name: String,
+        #[snafu(implicit)]
+        location: Location,
+        source: common_meta::error::Error,
+    },
+
+    #[snafu(display("No flownode is available to explain flow: {}", flow_name))]
+    NoAvailableFlownode {
+        flow_name: String,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
     #[snafu(display("Failed to join task"))]
     JoinTask {
         #[snafu(source)]
@@ -859,6 +874,10 @@ impl ErrorExt for Error {
 
             Error::FlowNotFound { .. } => StatusCode::FlowNotFound,
 
+            Error::ExplainFlow { source, .. } => source.status_code(),
+
+            Error::NoAvailableFlownode { .. } => StatusCode::Unsupported,
+
             Error::JoinTask { .. } => StatusCode::Internal,
 
             Error::BuildParquetRecordBatchStream { .. }
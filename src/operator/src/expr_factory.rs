@@ -24,6 +24,7 @@ use api::v1::{
 };
 use common_error::ext::BoxedError;
 use common_grpc_expr::util::ColumnExpr;
+use common_meta::rpc::ddl::AlterFlowTask;
 use common_time::Timezone;
 use datafusion::sql::planner::object_name_to_table_reference;
 use datatypes::schema::{ColumnSchema, COMMENT_KEY};
@@ -36,14 +37,16 @@ use session::context::QueryContextRef;
 use session::table_name::table_idents_to_full_name;
 use snafu::{ensure, OptionExt, ResultExt};
 use sql::ast::{ColumnOption, TableConstraint};
-use sql::statements::alter::{AlterTable, AlterTableOperation};
+use sql::statements::alter::{AlterFlow, AlterTable, AlterTableOperation};
 use sql::statements::create::{
     Column as SqlColumn, CreateExternalTable, CreateFlow, CreateTable, CreateView, TIME_INDEX,
 };
 use sql::statements::{
     column_to_schema, sql_column_def_to_grpc_column_def, sql_data_type_to_concrete_data_type,
 };
-use sql::util::extract_tables_from_query;
+use sql::util::{extract_tables_from_query, format_raw_object_name};
+use sql::FLOW_OPT_KEY_EXPIRE_WHEN;
+use sqlparser::ast::Query;
 use table::requests::{TableOptions, FILE_TABLE_META_KEY};
 use table::table_reference::TableReference;
 
@@ -548,7 +551,34 @@ pub fn to_create_flow_task_expr(
         table_name: sink_table_ref.table().to_string(),
     };
 
-    let source_table_names = extract_tables_from_query(&create_flow.query)
+    let source_table_names = flow_source_table_names(&create_flow.query, query_ctx)?;
+
+    let mut flow_options = create_flow.flow_options.into_map();
+    if let Some(expire_when) = &create_flow.expire_when {
+        // the create flow request has no field for `EXPIRE WHEN`
+        flow_options.insert(
+            FLOW_OPT_KEY_EXPIRE_WHEN.to_string(),
+            expire_when.to_string(),
+        );
+    }
+
+    Ok(CreateFlowExpr {
+        catalog_name: query_ctx.current_catalog().to_string(),
+        flow_name: create_flow.flow_name.to_string(),
+        source_table_names,
+        sink_table_name: Some(sink_table_name),
+        or_replace: create_flow.or_replace,
+        create_if_not_exists: create_flow.if_not_exists,
+        expire_after: create_flow.expire_after.map(|value| ExpireAfter { value }),
+        comment: create_flow.comment.unwrap_or_default(),
+        sql: create_flow.query.to_string(),
+        flow_options,
+    })
+}
+
+/// Full names of the tables the query of a flow reads from
+fn flow_source_table_names(query: &Query, query_ctx: &QueryContextRef) -> Result<Vec<TableName>> {
+    extract_tables_from_query(query)
         .map(|name| {
             let reference = object_name_to_table_reference(name.clone().into(), true)
                 .with_context(|_| ConvertIdentifierSnafu {
@@ -570,19 +600,30 @@ pub fn to_create_flow_task_expr(
             };
             Ok(table_name)
         })
-        .collect::<Result<Vec<_>>>()?;
+        .collect()
+}
 
-    Ok(CreateFlowExpr {
+pub fn to_alter_flow_task(
+    alter_flow: AlterFlow,
+    query_ctx: &QueryContextRef,
+) -> Result<AlterFlowTask> {
+    let source_table_names = match &alter_flow.query {
+        Some(query) => flow_source_table_names(query, query_ctx)?
+            .into_iter()
+            .map(Into::into)
+            .collect(),
+        None => vec![],
+    };
+
+    Ok(AlterFlowTask {
         catalog_name: query_ctx.current_catalog().to_string(),
-        flow_name: create_flow.flow_name.to_string(),
+        flow_name: format_raw_object_name(&alter_flow.flow_name),
+        expire_after: alter_flow.expire_after,
+        comment: alter_flow.comment,
+        flow_options: alter_flow.flow_options.into_map(),
+        sql: alter_flow.query.map(|query| query.to_string()),
         source_table_names,
-        sink_table_name: Some(sink_table_name),
-        or_replace: create_flow.or_replace,
-        create_if_not_exists: create_flow.if_not_exists,
-        expire_after: create_flow.expire_after.map(|value| ExpireAfter { value }),
-        comment: create_flow.comment.unwrap_or_default(),
-        sql: create_flow.query.to_string(),
-        flow_options: HashMap::new(),
+        discard_state: alter_flow.discard_state,
     })
 }
 
@@ -25,20 +25,25 @@ use futures::{StreamExt, TryStreamExt};
 use session::context::QueryContextRef;
 use snafu::{OptionExt, ResultExt};
 
+use crate::flow_mirror::FlowMirrorRef;
+
 /// The operator for flow service which implements [`FlowServiceHandler`].
 pub struct FlowServiceOperator {
     flow_metadata_manager: FlowMetadataManagerRef,
     node_manager: NodeManagerRef,
+    flow_mirror: FlowMirrorRef,
 }
 
 impl FlowServiceOperator {
     pub fn new(
         flow_metadata_manager: FlowMetadataManagerRef,
         node_manager: NodeManagerRef,
+        flow_mirror: FlowMirrorRef,
     ) -> Self {
         Self {
             flow_metadata_manager,
             node_manager,
+            flow_mirror,
         }
     }
 }
@@ -86,6 +91,11 @@ impl FlowServiceOperator {
             .map_err(BoxedError::new)
             .context(common_query::error::ExecuteSnafu)?;
 
+        // so inserts mirrored before flushing are flushed too
+        for (_key, peer) in &all_flownode_peers {
+            self.flow_mirror.wait_sent(peer.peer()).await;
+        }
+
         // order of flownodes doesn't matter here
         let all_flow_nodes = FuturesUnordered::from_iter(
             all_flownode_peers
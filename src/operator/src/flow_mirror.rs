@@ -0,0 +1,205 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Mirrors inserts of source tables to flownodes in background.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use api::v1::region::InsertRequests as RegionInsertRequests;
+use common_meta::node_manager::NodeManagerRef;
+use common_meta::peer::Peer;
+use common_telemetry::{debug, warn};
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::sync::oneshot;
+
+use crate::metrics::{DIST_MIRROR_DROPPED_ROW_COUNT, DIST_MIRROR_ROW_COUNT};
+
+/// The default number of insert requests buffered for each flownode.
+pub const DEFAULT_FLOW_MIRROR_BUFFER_SIZE: usize = 1024;
+
+pub type FlowMirrorRef = Arc<FlowMirror>;
+
+enum MirrorTask {
+    Insert(RegionInsertRequests),
+    /// Notifies the sender once all inserts before it are sent to the flownode.
+    Barrier(oneshot::Sender<()>),
+}
+
+/// Sends inserts of source tables to flownodes without blocking the inserts to datanodes.
+///
+/// Inserts are buffered for each flownode and sent in order by a background task. Inserts
+/// exceeding the buffer are dropped, so a slow flownode can't slow down ingestion.
+pub struct FlowMirror {
+    node_manager: NodeManagerRef,
+    buffer_size: usize,
+    senders: Mutex<HashMap<Peer, Sender<MirrorTask>>>,
+}
+
+impl FlowMirror {
+    pub fn new(node_manager: NodeManagerRef, buffer_size: usize) -> Self {
+        Self {
+            node_manager,
+            buffer_size,
+            senders: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Buffers the inserts to send them to the flownode in background.
+    pub fn mirror(&self, peer: Peer, inserts: RegionInsertRequests) {
+        let rows = inserts
+            .requests
+            .iter()
+            .map(|req| req.rows.as_ref().map_or(0, |rows| rows.rows.len()))
+            .sum::<usize>();
+        let mut task = MirrorTask::Insert(inserts);
+        // retries once if the background task of the flownode is gone
+        for _ in 0..2 {
+            let sender = self.sender(&peer);
+            match sender.try_send(task) {
+                Ok(()) => return,
+                Err(TrySendError::Full(_)) => {
+                    warn!(
+                        "Mirror buffer of flownode {} is full, dropped {} rows",
+                        peer, rows
+                    );
+                    DIST_MIRROR_DROPPED_ROW_COUNT.inc_by(rows as u64);
+                    return;
+                }
+                Err(TrySendError::Closed(t)) => {
+                    self.senders.lock().unwrap().remove(&peer);
+                    task = t;
+                }
+            }
+        }
+        DIST_MIRROR_DROPPED_ROW_COUNT.inc_by(rows as u64);
+    }
+
+    /// Waits until inserts buffered before are sent to the flownode.
+    pub async fn wait_sent(&self, peer: &Peer) {
+        let Some(sender) = self.senders.lock().unwrap().get(peer).cloned() else {
+            return;
+        };
+        let (tx, rx) = oneshot::channel();
+        if sender.send(MirrorTask::Barrier(tx)).await.is_ok() {
+            let _ = rx.await;
+        }
+    }
+
+    fn sender(&self, peer: &Peer) -> Sender<MirrorTask> {
+        let mut senders = self.senders.lock().unwrap();
+        if let Some(sender) = senders.get(peer) {
+            return sender.clone();
+        }
+        let (tx, rx) = mpsc::channel(self.buffer_size);
+        common_runtime::spawn_global(Self::run(self.node_manager.clone(), peer.clone(), rx));
+        senders.insert(peer.clone(), tx.clone());
+        tx
+    }
+
+    /// Sends buffered inserts to the flownode until the [FlowMirror] is dropped.
+    async fn run(node_manager: NodeManagerRef, peer: Peer, mut rx: Receiver<MirrorTask>) {
+        while let Some(task) = rx.recv().await {
+            match task {
+                MirrorTask::Insert(inserts) => {
+                    match node_manager
+                        .flownode(&peer)
+                        .await
+                        .handle_inserts(inserts)
+                        .await
+                    {
+                        Ok(resp) => DIST_MIRROR_ROW_COUNT.inc_by(resp.affected_rows),
+                        Err(err) => {
+                            warn!(err; "Failed to mirror inserts to flownode {}", peer)
+                        }
+                    }
+                }
+                MirrorTask::Barrier(tx) => {
+                    let _ = tx.send(());
+                }
+            }
+        }
+        debug!("Stopped mirroring inserts to flownode {}", peer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use api::v1::flow::FlowResponse;
+    use api::v1::region::InsertRequest;
+    use api::v1::{Row, Rows};
+    use common_meta::error::Result;
+    use common_meta::test_util::{MockFlownodeHandler, MockFlownodeManager};
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct RecordingFlownodeHandler {
+        rows: Arc<Mutex<Vec<(u64, usize)>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl MockFlownodeHandler for RecordingFlownodeHandler {
+        async fn handle_inserts(
+            &self,
+            peer: &Peer,
+            requests: RegionInsertRequests,
+        ) -> Result<FlowResponse> {
+            let rows = requests
+                .requests
+                .iter()
+                .map(|req| req.rows.as_ref().map_or(0, |rows| rows.rows.len()))
+                .sum::<usize>();
+            self.rows.lock().unwrap().push((peer.id, rows));
+            Ok(FlowResponse {
+                affected_rows: rows as u64,
+                ..Default::default()
+            })
+        }
+    }
+
+    fn new_inserts(rows: usize) -> RegionInsertRequests {
+        RegionInsertRequests {
+            requests: vec![InsertRequest {
+                region_id: 1024,
+                rows: Some(Rows {
+                    schema: vec![],
+                    rows: vec![Row { values: vec![] }; rows],
+                }),
+            }],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_flow_mirror() {
+        let handler = RecordingFlownodeHandler::default();
+        let node_manager = Arc::new(MockFlownodeManager::new(handler.clone()));
+        let mirror = FlowMirror::new(node_manager, DEFAULT_FLOW_MIRROR_BUFFER_SIZE);
+
+        // Nothing is buffered
+        mirror.wait_sent(&Peer::empty(1)).await;
+
+        mirror.mirror(Peer::empty(1), new_inserts(1));
+        mirror.mirror(Peer::empty(2), new_inserts(2));
+        mirror.mirror(Peer::empty(1), new_inserts(3));
+        mirror.wait_sent(&Peer::empty(1)).await;
+        mirror.wait_sent(&Peer::empty(2)).await;
+
+        let mut rows = handler.rows.lock().unwrap().clone();
+        // Inserts to the same flownode are sent in order
+        rows.sort_by_key(|(peer_id, _)| *peer_id);
+        assert_eq!(rows, vec![(1, 1), (1, 3), (2, 2)]);
+    }
+}
@@ -53,6 +53,7 @@ use crate::error::{
     JoinTaskSnafu, RequestInsertsSnafu, Result, TableNotFoundSnafu,
 };
 use crate::expr_factory::CreateExprFactory;
+use crate::flow_mirror::{FlowMirror, FlowMirrorRef, DEFAULT_FLOW_MIRROR_BUFFER_SIZE};
 use crate::region_req_factory::RegionRequestFactory;
 use crate::req_convert::insert::{ColumnToRow, RowToRegion, StatementToRegion, TableToRegion};
 use crate::statement::StatementExecutor;
@@ -62,6 +63,7 @@ pub struct Inserter {
     partition_manager: PartitionRuleManagerRef,
     node_manager: NodeManagerRef,
     table_flownode_set_cache: TableFlownodeSetCacheRef,
+    flow_mirror: FlowMirrorRef,
 }
 
 pub type InserterRef = Arc<Inserter>;
@@ -97,14 +99,29 @@ impl Inserter {
         node_manager: NodeManagerRef,
         table_flownode_set_cache: TableFlownodeSetCacheRef,
     ) -> Self {
+        let flow_mirror = Arc::new(FlowMirror::new(
+            node_manager.clone(),
+            DEFAULT_FLOW_MIRROR_BUFFER_SIZE,
+        ));
         Self {
             catalog_manager,
             partition_manager,
             node_manager,
             table_flownode_set_cache,
+            flow_mirror,
         }
     }
 
+    /// Returns the [NodeManagerRef] to reach datanodes and flownodes.
+    pub fn node_manager(&self) -> &NodeManagerRef {
+        &self.node_manager
+    }
+
+    /// Returns the [FlowMirrorRef] sending inserts of source tables to flownodes.
+    pub fn flow_mirror(&self) -> &FlowMirrorRef {
+        &self.flow_mirror
+    }
+
     pub async fn handle_column_inserts(
         &self,
         requests: InsertRequests,
@@ -284,34 +301,8 @@ impl Inserter {
         // Mirror requests for source table to flownode
         match self.mirror_flow_node_requests(&requests).await {
             Ok(flow_requests) => {
-                let node_manager = self.node_manager.clone();
-                let flow_tasks = flow_requests.into_iter().map(|(peer, inserts)| {
-                    let node_manager = node_manager.clone();
-                    common_runtime::spawn_global(async move {
-                        node_manager
-                            .flownode(&peer)
-                            .await
-                            .handle_inserts(inserts)
-                            .await
-                            .context(RequestInsertsSnafu)
-                    })
-                });
-
-                match future::try_join_all(flow_tasks)
-                    .await
-                    .context(JoinTaskSnafu)
-                {
-                    Ok(ret) => {
-                        let affected_rows = ret
-                            .into_iter()
-                            .map(|resp| resp.map(|r| r.affected_rows))
-                            .sum::<Result<u64>>()
-                            .unwrap_or(0);
-                        crate::metrics::DIST_MIRROR_ROW_COUNT.inc_by(affected_rows);
-                    }
-                    Err(err) => {
-                        warn!(err; "Failed to insert data into flownode");
-                    }
+                for (peer, inserts) in flow_requests {
+                    self.flow_mirror.mirror(peer, inserts);
                 }
             }
             Err(err) => warn!(err; "Failed to mirror request to flownode"),
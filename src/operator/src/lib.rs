@@ -19,6 +19,7 @@ pub mod delete;
 pub mod error;
 pub mod expr_factory;
 pub mod flow;
+pub mod flow_mirror;
 pub mod insert;
 pub mod metrics;
 pub mod procedure;
@@ -41,6 +41,11 @@ lazy_static! {
         "table operator mirror rows"
     )
     .unwrap();
+    pub static ref DIST_MIRROR_DROPPED_ROW_COUNT: IntCounter = register_int_counter!(
+        "greptime_table_operator_mirror_dropped_rows",
+        "table operator mirror dropped rows"
+    )
+    .unwrap();
     pub static ref DIST_DELETE_ROW_COUNT: IntCounter = register_int_counter!(
         "greptime_table_operator_delete_rows",
         "table operator delete rows"
@@ -19,6 +19,7 @@ mod copy_table_to;
 mod ddl;
 mod describe;
 mod dml;
+mod explain;
 mod set;
 mod show;
 mod tql;
@@ -194,6 +195,7 @@ impl StatementExecutor {
                 Ok(Output::new_with_affected_rows(0))
             }
             Statement::CreateFlow(stmt) => self.create_flow(stmt, query_ctx).await,
+            Statement::ExplainFlow(stmt) => self.explain_flow(stmt, query_ctx).await,
             Statement::DropFlow(stmt) => {
                 self.drop_flow(
                     query_ctx.current_catalog().to_string(),
@@ -223,6 +225,7 @@ impl StatementExecutor {
                 .await
             }
             Statement::Alter(alter_table) => self.alter_table(alter_table, query_ctx).await,
+            Statement::AlterFlow(stmt) => self.alter_flow(stmt, query_ctx).await,
             Statement::DropTable(stmt) => {
                 let mut table_names = Vec::with_capacity(stmt.table_names().len());
                 for table_name_stmt in stmt.table_names() {
@@ -51,7 +51,7 @@ use regex::Regex;
 use session::context::QueryContextRef;
 use session::table_name::table_idents_to_full_name;
 use snafu::{ensure, OptionExt, ResultExt};
-use sql::statements::alter::AlterTable;
+use sql::statements::alter::{AlterFlow, AlterTable};
 use sql::statements::create::{
     CreateExternalTable, CreateFlow, CreateTable, CreateTableLike, CreateView, Partitions,
 };
@@ -652,6 +652,27 @@ impl StatementExecutor {
             .context(error::ExecuteDdlSnafu)
     }
 
+    /// Alter options or the query of a flow, without recreating it
+    #[tracing::instrument(skip_all)]
+    pub async fn alter_flow(
+        &self,
+        stmt: AlterFlow,
+        query_context: QueryContextRef,
+    ) -> Result<Output> {
+        let task = expr_factory::to_alter_flow_task(stmt, &query_context)?;
+        let request = SubmitDdlTaskRequest {
+            query_context,
+            task: DdlTask::new_alter_flow(task),
+        };
+
+        self.procedure_executor
+            .submit_ddl_task(&ExecutorContext::default(), request)
+            .await
+            .context(error::ExecuteDdlSnafu)?;
+
+        Ok(Output::new_with_affected_rows(0))
+    }
+
     /// Drop a view
     #[tracing::instrument(skip_all)]
     pub(crate) async fn drop_view(
@@ -0,0 +1,181 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use catalog::information_schema::InformationExtension;
+use catalog::kvbackend::KvBackendCatalogManager;
+use common_meta::cluster::NodeStatus;
+use common_meta::node_manager::ExplainFlowRequest;
+use common_meta::peer::Peer;
+use common_query::Output;
+use common_telemetry::tracing;
+use futures::TryStreamExt;
+use session::context::{QueryContextBuilder, QueryContextRef};
+use snafu::{OptionExt, ResultExt};
+use sql::statements::explain::ExplainFlow;
+use sqlparser::ast::ObjectName;
+use table::table_name::TableName;
+
+use crate::error::{
+    CatalogSnafu, ExecuteStatementSnafu, ExplainFlowSnafu, FlowNotFoundSnafu, InvalidSqlSnafu,
+    NoAvailableFlownodeSnafu, Result, TableMetadataManagerSnafu, TableNotFoundSnafu,
+    UpgradeCatalogManagerRefSnafu,
+};
+use crate::expr_factory;
+use crate::statement::StatementExecutor;
+
+impl StatementExecutor {
+    /// Plans the flow on a flownode without creating it, for `EXPLAIN FLOW <flow_name>` or
+    /// `EXPLAIN CREATE FLOW ...`.
+    #[tracing::instrument(skip_all)]
+    pub(super) async fn explain_flow(
+        &self,
+        stmt: ExplainFlow,
+        query_ctx: QueryContextRef,
+    ) -> Result<Output> {
+        let (flow_name, request, peer) = match stmt {
+            ExplainFlow::Flow(obj_name) => {
+                self.explain_existing_flow(&obj_name, &query_ctx).await?
+            }
+            ExplainFlow::Create(create_flow) => {
+                let flow_name = create_flow.flow_name.to_string();
+                let expr = expr_factory::to_create_flow_task_expr(create_flow, &query_ctx)?;
+                let mut source_table_ids = Vec::with_capacity(expr.source_table_names.len());
+                for name in &expr.source_table_names {
+                    let table = self
+                        .catalog_manager
+                        .table(
+                            &name.catalog_name,
+                            &name.schema_name,
+                            &name.table_name,
+                            Some(&query_ctx),
+                        )
+                        .await
+                        .context(CatalogSnafu)?
+                        .with_context(|| TableNotFoundSnafu {
+                            table_name: TableName::from(name.clone()).to_string(),
+                        })?;
+                    source_table_ids.push(table.table_info().table_id());
+                }
+                let request = ExplainFlowRequest {
+                    source_table_ids,
+                    // Safety: the sink table name is always set by `to_create_flow_task_expr`
+                    sink_table_name: expr.sink_table_name.unwrap().into(),
+                    sql: expr.sql,
+                    flow_options: expr.flow_options,
+                    query_context: query_ctx.clone().into(),
+                };
+                let peer = self.any_flownode(&flow_name).await?;
+                (flow_name, request, peer)
+            }
+        };
+
+        let plan = self
+            .inserter
+            .node_manager()
+            .flownode(&peer)
+            .await
+            .explain_flow(request)
+            .await
+            .context(ExplainFlowSnafu {
+                flow_name: &flow_name,
+            })?;
+        query::sql::explain_flow(flow_name, plan).context(ExecuteStatementSnafu)
+    }
+
+    /// Builds the request to explain an existing flow, on the flownode it's running on.
+    async fn explain_existing_flow(
+        &self,
+        obj_name: &ObjectName,
+        query_ctx: &QueryContextRef,
+    ) -> Result<(String, ExplainFlowRequest, Peer)> {
+        let (catalog_name, flow_name) = match &obj_name.0[..] {
+            [flow] => (query_ctx.current_catalog().to_string(), flow.value.clone()),
+            [catalog, flow] => (catalog.value.clone(), flow.value.clone()),
+            _ => {
+                return InvalidSqlSnafu {
+                    err_msg: format!(
+                        "expect flow name to be <catalog>.<flow_name> or <flow_name>, actual: {obj_name}",
+                    ),
+                }
+                .fail()
+            }
+        };
+
+        let flow_id = self
+            .flow_metadata_manager
+            .flow_name_manager()
+            .get(&catalog_name, &flow_name)
+            .await
+            .context(TableMetadataManagerSnafu)?
+            .context(FlowNotFoundSnafu {
+                flow_name: &flow_name,
+            })?
+            .flow_id();
+        let flow_info = self
+            .flow_metadata_manager
+            .flow_info_manager()
+            .get(flow_id)
+            .await
+            .context(TableMetadataManagerSnafu)?
+            .context(FlowNotFoundSnafu {
+                flow_name: &flow_name,
+            })?;
+        let (_, route) = self
+            .flow_metadata_manager
+            .flow_route_manager()
+            .routes(flow_id)
+            .try_next()
+            .await
+            .context(TableMetadataManagerSnafu)?
+            .context(NoAvailableFlownodeSnafu {
+                flow_name: &flow_name,
+            })?;
+
+        // planned in the same context as the flownode recovering the flow
+        let flow_ctx: QueryContextRef = QueryContextBuilder::default()
+            .current_catalog(flow_info.catalog_name().clone())
+            .build()
+            .into();
+        let request = ExplainFlowRequest {
+            source_table_ids: flow_info.source_table_ids().to_vec(),
+            sink_table_name: flow_info.sink_table_name().clone(),
+            sql: flow_info.raw_sql().clone(),
+            flow_options: flow_info.options().clone(),
+            query_context: flow_ctx.into(),
+        };
+        Ok((flow_name, request, route.peer().clone()))
+    }
+
+    /// Picks the most recently active flownode to plan a flow not created yet.
+    async fn any_flownode(&self, flow_name: &str) -> Result<Peer> {
+        let information_extension = self
+            .catalog_manager
+            .as_any()
+            .downcast_ref::<KvBackendCatalogManager>()
+            .map(|manager| manager.information_extension())
+            .context(UpgradeCatalogManagerRefSnafu)?;
+        let nodes = information_extension.nodes().await.context(CatalogSnafu)?;
+        nodes
+            .into_iter()
+            .filter(|node| {
+                matches!(
+                    node.status,
+                    NodeStatus::Flownode(_) | NodeStatus::Standalone
+                )
+            })
+            .max_by_key(|node| node.last_activity_ts)
+            .map(|node| node.peer)
+            .context(NoAvailableFlownodeSnafu { flow_name })
+    }
+}
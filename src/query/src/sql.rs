@@ -59,6 +59,7 @@ use sql::statements::show::{
 };
 use sql::statements::statement::Statement;
 use sql::statements::OptionMap;
+use sql::FLOW_OPT_KEY_EXPIRE_WHEN;
 use sqlparser::ast::ObjectName;
 use table::requests::{FILE_TABLE_LOCATION_KEY, FILE_TABLE_PATTERN_KEY};
 use table::TableRef;
@@ -162,6 +163,13 @@ static SHOW_CREATE_FLOW_OUTPUT_SCHEMA: Lazy<Arc<Schema>> = Lazy::new(|| {
     ]))
 });
 
+static EXPLAIN_FLOW_OUTPUT_SCHEMA: Lazy<Arc<Schema>> = Lazy::new(|| {
+    Arc::new(Schema::new(vec![
+        ColumnSchema::new("Flow", ConcreteDataType::string_datatype(), false),
+        ColumnSchema::new("Plan", ConcreteDataType::string_datatype(), false),
+    ]))
+});
+
 static SHOW_CREATE_VIEW_OUTPUT_SCHEMA: Lazy<Arc<Schema>> = Lazy::new(|| {
     Arc::new(Schema::new(vec![
         ColumnSchema::new("View", ConcreteDataType::string_datatype(), false),
@@ -840,6 +848,15 @@ pub fn show_create_flow(
         Some(flow_val.comment().clone())
     };
 
+    let mut flow_options = flow_val.options().clone();
+    let expire_when = flow_options
+        .remove(FLOW_OPT_KEY_EXPIRE_WHEN)
+        .map(|expr| {
+            ParserContext::new(query_ctx.sql_dialect(), &expr).and_then(|mut ctx| ctx.parser_expr())
+        })
+        .transpose()
+        .context(error::SqlSnafu)?;
+
     let stmt = CreateFlow {
         flow_name,
         sink_table_name: ObjectName(vec![Ident {
@@ -849,7 +866,9 @@ pub fn show_create_flow(
         or_replace: true,
         if_not_exists: true,
         expire_after: flow_val.expire_after(),
+        expire_when,
         comment,
+        flow_options: flow_options.into(),
         query,
     };
 
@@ -864,6 +883,18 @@ pub fn show_create_flow(
     Ok(Output::new_with_record_batches(records))
 }
 
+/// The output of `EXPLAIN FLOW`, with the explanation of the flow given by the flownode.
+pub fn explain_flow(flow_name: String, plan: String) -> Result<Output> {
+    let columns = vec![
+        Arc::new(StringVector::from(vec![flow_name])) as _,
+        Arc::new(StringVector::from(vec![plan])) as _,
+    ];
+    let records = RecordBatches::try_from_columns(EXPLAIN_FLOW_OUTPUT_SCHEMA.clone(), columns)
+        .context(error::CreateRecordBatchSnafu)?;
+
+    Ok(Output::new_with_record_batches(records))
+}
+
 pub fn describe_table(table: TableRef) -> Result<Output> {
     let table_info = table.table_info();
     let columns_schemas = table_info.meta.schema.column_schemas();
@@ -75,4 +75,13 @@ impl GrpcQueryHandler for MockGrpcQueryHandler {
     async fn do_query(&self, _query: Request, _ctx: QueryContextRef) -> Result<Output> {
         Ok(Output::new_with_affected_rows(1))
     }
+
+    async fn put_record_batch(
+        &self,
+        _table_name: &str,
+        _record_batch: RecordBatch,
+        _ctx: QueryContextRef,
+    ) -> Result<usize> {
+        Ok(1)
+    }
 }
@@ -21,16 +21,19 @@ use axum::{http, Json};
 use base64::DecodeError;
 use common_error::define_into_tonic_status;
 use common_error::ext::{BoxedError, ErrorExt};
+use common_error::sampler::{ErrorSampler, Sample};
 use common_error::status_code::StatusCode;
 use common_macro::stack_trace_debug;
 use common_telemetry::{error, warn};
 use datatypes::prelude::ConcreteDataType;
 use headers::ContentType;
+use once_cell::sync::Lazy;
 use query::parser::PromQuery;
 use serde_json::json;
 use snafu::{Location, Snafu};
 
 use crate::http::error_result::status_code_to_http_status;
+use crate::metrics::METRIC_SUPPRESSED_ERRORS;
 
 #[derive(Snafu)]
 #[snafu(visibility(pub))]
@@ -294,6 +297,13 @@ pub enum Error {
         location: Location,
     },
 
+    #[snafu(display("Invalid Flight data of DoPut stream"))]
+    InvalidFlightData {
+        #[snafu(implicit)]
+        location: Location,
+        source: common_grpc::error::Error,
+    },
+
     #[snafu(display("Tls is required for {}, plain connection is rejected", server))]
     TlsRequired { server: String },
 
@@ -614,7 +624,7 @@ impl ErrorExt for Error {
             | UnsupportedJsonDataTypeForTag { .. } => StatusCode::InvalidArguments,
 
             Catalog { source, .. } => source.status_code(),
-            RowWriter { source, .. } => source.status_code(),
+            RowWriter { source, .. } | InvalidFlightData { source, .. } => source.status_code(),
 
             Hyper { .. } => StatusCode::Unknown,
             TlsRequired { .. } => StatusCode::Unknown,
@@ -672,9 +682,29 @@ impl From<std::io::Error> for Error {
     }
 }
 
+/// Samples the errors of the requests to log, so a failure of many requests doesn't flood the
+/// logs, see [ErrorSampler].
+pub(crate) static REQUEST_ERROR_SAMPLER: Lazy<ErrorSampler> = Lazy::new(ErrorSampler::default);
+
+/// Samples the error of a request by [REQUEST_ERROR_SAMPLER], returns the note to log it with,
+/// see [Sample::suppressed_note], or `None` if it's suppressed, in which case it's counted by
+/// [METRIC_SUPPRESSED_ERRORS].
+pub(crate) fn sample_request_error<E: ErrorExt>(err: &E) -> Option<String> {
+    let sample = REQUEST_ERROR_SAMPLER.sample(err);
+    if sample == Sample::Suppress {
+        METRIC_SUPPRESSED_ERRORS
+            .with_label_values(&[err.status_code().as_ref()])
+            .inc();
+        return None;
+    }
+    Some(sample.suppressed_note())
+}
+
 fn log_error_if_necessary(error: &Error) {
     if error.status_code().should_log_error() {
-        error!(error; "Failed to handle HTTP request ");
+        if let Some(note) = sample_request_error(error) {
+            error!(error; "Failed to handle HTTP request{}", note);
+        }
     } else {
         warn!(error; "Failed to handle HTTP request ");
     }
@@ -24,13 +24,20 @@ use arrow_flight::{
     HandshakeRequest, HandshakeResponse, PollInfo, PutResult, SchemaResult, Ticket,
 };
 use async_trait::async_trait;
-use common_grpc::flight::{FlightEncoder, FlightMessage};
+use common_error::ext::ErrorExt;
+use common_error::status_code::StatusCode;
+use common_grpc::flight::{
+    encode_put_result, parse_do_put_descriptor, FlightDecoder, FlightEncoder, FlightMessage,
+};
 use common_query::{Output, OutputData};
+use common_recordbatch::RecordBatch;
 use common_telemetry::tracing::info_span;
 use common_telemetry::tracing_context::{FutureExt, TracingContext};
-use futures::Stream;
+use futures::{Stream, StreamExt};
 use prost::Message;
-use snafu::ResultExt;
+use snafu::{OptionExt, ResultExt};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
 use tonic::{Request, Response, Status, Streaming};
 
 use crate::error;
@@ -38,6 +45,10 @@ pub use crate::grpc::flight::stream::FlightRecordBatchStream;
 use crate::grpc::greptime_handler::{get_request_type, GreptimeRequestHandler};
 use crate::grpc::TonicResult;
 
+/// The number of [PutResult]s buffered for a DoPut stream, so the record batches received stop
+/// being inserted while the client doesn't read their results.
+const DO_PUT_RESULT_BUFFER_SIZE: usize = 16;
+
 pub type TonicStream<T> = Pin<Box<dyn Stream<Item = TonicResult<T>> + Send + Sync + 'static>>;
 
 /// A subset of [FlightService]
@@ -47,6 +58,13 @@ pub trait FlightCraft: Send + Sync + 'static {
         &self,
         request: Request<Ticket>,
     ) -> TonicResult<Response<TonicStream<FlightData>>>;
+
+    async fn do_put(
+        &self,
+        _request: Request<Streaming<FlightData>>,
+    ) -> TonicResult<Response<TonicStream<PutResult>>> {
+        Err(Status::unimplemented("Not yet implemented"))
+    }
 }
 
 pub type FlightCraftRef = Arc<dyn FlightCraft>;
@@ -67,6 +85,13 @@ impl FlightCraft for FlightCraftRef {
     ) -> TonicResult<Response<TonicStream<FlightData>>> {
         (**self).do_get(request).await
     }
+
+    async fn do_put(
+        &self,
+        request: Request<Streaming<FlightData>>,
+    ) -> TonicResult<Response<TonicStream<PutResult>>> {
+        (**self).do_put(request).await
+    }
 }
 
 #[async_trait]
@@ -120,9 +145,9 @@ impl<T: FlightCraft> FlightService for FlightCraftWrapper<T> {
 
     async fn do_put(
         &self,
-        _: Request<Streaming<FlightData>>,
+        request: Request<Streaming<FlightData>>,
     ) -> TonicResult<Response<Self::DoPutStream>> {
-        Err(Status::unimplemented("Not yet implemented"))
+        self.0.do_put(request).await
     }
 
     type DoExchangeStream = TonicStream<FlightData>;
@@ -175,6 +200,81 @@ impl FlightCraft for GreptimeRequestHandler {
         .trace(span)
         .await
     }
+
+    /// Inserts record batches into a table.
+    ///
+    /// The first message carries the descriptor built by
+    /// [do_put_descriptor](common_grpc::flight::do_put_descriptor) and the schema of
+    /// the record batches, which is checked against the table before any record batch. A
+    /// [PutResult] is sent for each record batch in order, carrying its affected rows or error.
+    async fn do_put(
+        &self,
+        request: Request<Streaming<FlightData>>,
+    ) -> TonicResult<Response<TonicStream<PutResult>>> {
+        let mut stream = request.into_inner();
+        let first = stream.next().await.context(error::InvalidParameterSnafu {
+            reason: "DoPut stream is empty",
+        })??;
+        let descriptor = first
+            .flight_descriptor
+            .clone()
+            .context(error::InvalidParameterSnafu {
+                reason: "Expecting the descriptor in the first message of DoPut stream",
+            })?;
+        let (header, table_name) =
+            parse_do_put_descriptor(&descriptor).context(error::InvalidFlightDataSnafu)?;
+        let mut decoder = FlightDecoder::default();
+        let FlightMessage::Schema(schema) = decoder
+            .try_decode(first)
+            .context(error::InvalidFlightDataSnafu)?
+        else {
+            return Err(error::InvalidParameterSnafu {
+                reason: "Expecting the schema in the first message of DoPut stream",
+            }
+            .build()
+            .into());
+        };
+
+        let query_ctx = self.put_query_context(&header).await?;
+        // rejects the stream early if the schema doesn't fit the table
+        let _ = self
+            .put_record_batch(
+                &table_name,
+                RecordBatch::new_empty(schema),
+                query_ctx.clone(),
+            )
+            .await?;
+
+        let (tx, rx) = mpsc::channel(DO_PUT_RESULT_BUFFER_SIZE);
+        let handler = self.clone();
+        let _handle = common_runtime::spawn_global(async move {
+            while let Some(flight_data) = stream.next().await {
+                let flight_data = match flight_data {
+                    Ok(flight_data) => flight_data,
+                    Err(status) => {
+                        let _ = tx.send(Err(status)).await;
+                        break;
+                    }
+                };
+                let result = match decoder.try_decode(flight_data) {
+                    Ok(FlightMessage::Recordbatch(record_batch)) => handler
+                        .put_record_batch(&table_name, record_batch, query_ctx.clone())
+                        .await
+                        .map_err(|e| (e.status_code(), e.output_msg())),
+                    Ok(_) => Err((
+                        StatusCode::InvalidArguments,
+                        "Expecting only record batches after the schema".to_string(),
+                    )),
+                    Err(e) => Err((e.status_code(), e.output_msg())),
+                };
+                if tx.send(Ok(encode_put_result(result))).await.is_err() {
+                    // the client is gone
+                    break;
+                }
+            }
+        });
+        Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
+    }
 }
 
 fn to_flight_data_stream(
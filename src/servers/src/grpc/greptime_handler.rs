@@ -20,11 +20,13 @@ use api::helper::request_type;
 use api::v1::auth_header::AuthScheme;
 use api::v1::{Basic, GreptimeRequest, RequestHeader};
 use auth::{Identity, Password, UserInfoRef, UserProviderRef};
+use common_base::AffectedRows;
 use common_catalog::consts::{DEFAULT_CATALOG_NAME, DEFAULT_SCHEMA_NAME};
 use common_catalog::parse_catalog_and_schema_from_db_string;
 use common_error::ext::ErrorExt;
 use common_error::status_code::StatusCode;
 use common_query::Output;
+use common_recordbatch::RecordBatch;
 use common_runtime::runtime::RuntimeTrait;
 use common_runtime::Runtime;
 use common_telemetry::tracing_context::{FutureExt, TracingContext};
@@ -34,7 +36,10 @@ use session::context::{QueryContextBuilder, QueryContextRef};
 use snafu::{OptionExt, ResultExt};
 
 use crate::error::Error::UnsupportedAuthScheme;
-use crate::error::{AuthSnafu, InvalidQuerySnafu, JoinTaskSnafu, NotFoundAuthHeaderSnafu, Result};
+use crate::error::{
+    sample_request_error, AuthSnafu, InvalidQuerySnafu, JoinTaskSnafu, NotFoundAuthHeaderSnafu,
+    Result,
+};
 use crate::metrics::{METRIC_AUTH_FAILURE, METRIC_SERVER_GRPC_DB_REQUEST_TIMER};
 use crate::query_handler::grpc::ServerGrpcQueryHandlerRef;
 
@@ -88,8 +93,14 @@ impl GreptimeRequestHandler {
                 .await
                 .map_err(|e| {
                     if e.status_code().should_log_error() {
-                        let root_error = e.root_cause().unwrap_or(&e);
-                        error!(e; "Failed to handle request, error: {}", root_error.to_string());
+                        if let Some(note) = sample_request_error(&e) {
+                            let root_error = e.root_cause().unwrap_or(&e);
+                            error!(
+                                e; "Failed to handle request, error: {}{}",
+                                root_error.to_string(),
+                                note
+                            );
+                        }
                     } else {
                         // Currently, we still print a debug log.
                         debug!("Failed to handle request, err: {:?}", e);
@@ -120,6 +131,40 @@ impl GreptimeRequestHandler {
     }
 }
 
+impl GreptimeRequestHandler {
+    /// Authenticates the DoPut stream by its request `header`, returns its query context.
+    pub(crate) async fn put_query_context(
+        &self,
+        header: &RequestHeader,
+    ) -> Result<QueryContextRef> {
+        let query_ctx = create_query_context(Some(header), vec![]);
+        let user_info = auth(self.user_provider.clone(), Some(header), &query_ctx).await?;
+        query_ctx.set_current_user(user_info);
+        Ok(query_ctx)
+    }
+
+    /// Inserts the record batch of a DoPut stream into the table.
+    #[tracing::instrument(skip_all, fields(protocol = "grpc", request_type = "put_record_batch"))]
+    pub(crate) async fn put_record_batch(
+        &self,
+        table_name: &str,
+        record_batch: RecordBatch,
+        query_ctx: QueryContextRef,
+    ) -> Result<AffectedRows> {
+        let timer = RequestTimer::new(query_ctx.get_db_string(), "put_record_batch".to_string());
+        self.handler
+            .put_record_batch(table_name, record_batch, query_ctx)
+            .await
+            .inspect_err(|e| {
+                debug!(
+                    "Failed to put record batch into table {}, err: {:?}",
+                    table_name, e
+                );
+                timer.record(e.status_code());
+            })
+    }
+}
+
 pub fn get_request_type(request: &GreptimeRequest) -> &'static str {
     request
         .request
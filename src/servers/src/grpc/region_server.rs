@@ -26,7 +26,7 @@ use common_telemetry::{debug, error, warn};
 use snafu::{OptionExt, ResultExt};
 use tonic::{Request, Response, Status};
 
-use crate::error::{InvalidQuerySnafu, JoinTaskSnafu, Result};
+use crate::error::{sample_request_error, InvalidQuerySnafu, JoinTaskSnafu, Result};
 use crate::grpc::{cancellation, TonicResult};
 
 #[async_trait]
@@ -76,7 +76,9 @@ impl RegionServerRequestHandler {
                 .await
                 .map_err(|e| {
                     if e.status_code().should_log_error() {
-                        error!(e; "Failed to handle request");
+                        if let Some(note) = sample_request_error(&e) {
+                            error!(e; "Failed to handle request{}", note);
+                        }
                     } else {
                         // Currently, we still print a debug log.
                         debug!("Failed to handle request, err: {}", e);
@@ -1021,6 +1021,15 @@ mod test {
         ) -> std::result::Result<Output, Self::Error> {
             unimplemented!()
         }
+
+        async fn put_record_batch(
+            &self,
+            _table_name: &str,
+            _record_batch: RecordBatch,
+            _ctx: QueryContextRef,
+        ) -> std::result::Result<usize, Self::Error> {
+            unimplemented!()
+        }
     }
 
     #[async_trait]
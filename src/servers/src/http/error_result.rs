@@ -16,7 +16,7 @@ use axum::http::{HeaderValue, StatusCode as HttpStatusCode};
 use axum::response::{IntoResponse, Response};
 use axum::Json;
 use common_error::ext::ErrorExt;
-use common_error::status_code::StatusCode;
+use common_error::status_code::{StatusCategory, StatusCode};
 use common_telemetry::{debug, error};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -88,26 +88,15 @@ impl IntoResponse for ErrorResponse {
     }
 }
 
+/// Maps the [StatusCode] to the HTTP status, by its [StatusCategory] except for the
+/// authentication and permission errors.
+///
+/// Note that the resource exhausted errors, i.e. [StatusCode::RegionBusy] (previously `503`) and
+/// [StatusCode::RuntimeResourcesExhausted] (previously `500`), map to `429 Too Many Requests`.
 pub fn status_code_to_http_status(status_code: &StatusCode) -> HttpStatusCode {
     match status_code {
         StatusCode::Success | StatusCode::Cancelled => HttpStatusCode::OK,
 
-        StatusCode::Unsupported
-        | StatusCode::InvalidArguments
-        | StatusCode::InvalidSyntax
-        | StatusCode::RequestOutdated
-        | StatusCode::RegionAlreadyExists
-        | StatusCode::TableColumnExists
-        | StatusCode::TableAlreadyExists
-        | StatusCode::RegionNotFound
-        | StatusCode::DatabaseNotFound
-        | StatusCode::TableNotFound
-        | StatusCode::TableColumnNotFound
-        | StatusCode::PlanQuery
-        | StatusCode::DatabaseAlreadyExists
-        | StatusCode::FlowNotFound
-        | StatusCode::FlowAlreadyExists => HttpStatusCode::BAD_REQUEST,
-
         StatusCode::AuthHeaderNotFound
         | StatusCode::InvalidAuthHeader
         | StatusCode::UserNotFound
@@ -117,19 +106,12 @@ pub fn status_code_to_http_status(status_code: &StatusCode) -> HttpStatusCode {
 
         StatusCode::PermissionDenied | StatusCode::AccessDenied => HttpStatusCode::FORBIDDEN,
 
-        StatusCode::RateLimited => HttpStatusCode::TOO_MANY_REQUESTS,
-
-        StatusCode::RegionNotReady
-        | StatusCode::TableUnavailable
-        | StatusCode::RegionBusy
-        | StatusCode::StorageUnavailable
-        | StatusCode::External => HttpStatusCode::SERVICE_UNAVAILABLE,
-
-        StatusCode::Internal
-        | StatusCode::Unexpected
-        | StatusCode::IllegalState
-        | StatusCode::Unknown
-        | StatusCode::RuntimeResourcesExhausted
-        | StatusCode::EngineExecuteQuery => HttpStatusCode::INTERNAL_SERVER_ERROR,
+        _ => match status_code.category() {
+            StatusCategory::Success => HttpStatusCode::OK,
+            StatusCategory::User => HttpStatusCode::BAD_REQUEST,
+            StatusCategory::Resource => HttpStatusCode::TOO_MANY_REQUESTS,
+            StatusCategory::Unavailable => HttpStatusCode::SERVICE_UNAVAILABLE,
+            StatusCategory::Internal => HttpStatusCode::INTERNAL_SERVER_ERROR,
+        },
     }
 }
@@ -21,6 +21,7 @@ use api::v1::RowInsertRequests;
 use async_trait::async_trait;
 use common_error::ext::ErrorExt;
 use common_query::Output;
+use common_recordbatch::RecordBatch;
 use datafusion_expr::LogicalPlan;
 use query::parser::PromQuery;
 use serde_json::Value;
@@ -147,6 +148,16 @@ pub trait GrpcQueryInterceptor {
         Ok(())
     }
 
+    /// Called before a record batch put by Arrow Flight DoPut is inserted into the table.
+    fn pre_put_record_batch(
+        &self,
+        _table_name: &str,
+        _record_batch: &RecordBatch,
+        _query_ctx: QueryContextRef,
+    ) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
     /// Called after execution finished. The implementation can modify the
     /// output if needed.
     fn post_execute(
@@ -179,6 +190,19 @@ where
         }
     }
 
+    fn pre_put_record_batch(
+        &self,
+        table_name: &str,
+        record_batch: &RecordBatch,
+        query_ctx: QueryContextRef,
+    ) -> Result<(), Self::Error> {
+        if let Some(this) = self {
+            this.pre_put_record_batch(table_name, record_batch, query_ctx)
+        } else {
+            Ok(())
+        }
+    }
+
     fn post_execute(
         &self,
         output: Output,
@@ -72,6 +72,14 @@ lazy_static! {
         vec![0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0, 60.0, 300.0]
     )
     .unwrap();
+    /// Errors of requests not logged by [REQUEST_ERROR_SAMPLER](crate::error::REQUEST_ERROR_SAMPLER)
+    /// since many similar ones are logged.
+    pub static ref METRIC_SUPPRESSED_ERRORS: IntCounterVec = register_int_counter_vec!(
+        "greptime_servers_suppressed_errors",
+        "servers suppressed errors",
+        &[METRIC_CODE_LABEL]
+    )
+    .unwrap();
     pub static ref METRIC_AUTH_FAILURE: IntCounterVec = register_int_counter_vec!(
         "greptime_servers_auth_failure_count",
         "servers auth failure count",
@@ -369,5 +369,6 @@ fn mysql_error_kind(status_code: &StatusCode) -> ErrorKind {
         StatusCode::RateLimited => ErrorKind::ER_TOO_MANY_CONCURRENT_TRXS,
         StatusCode::FlowAlreadyExists => ErrorKind::ER_TABLE_EXISTS_ERROR,
         StatusCode::FlowNotFound => ErrorKind::ER_NO_SUCH_TABLE,
+        StatusCode::FlowQuotaExceeded => ErrorKind::ER_OUT_OF_RESOURCES,
     }
 }
@@ -409,7 +409,7 @@ impl From<StatusCode> for PgErrorCode {
             // ====== Begin of storage & server related status code =====
             StatusCode::StorageUnavailable | StatusCode::RequestOutdated => PgErrorCode::EcXX000,
             StatusCode::RuntimeResourcesExhausted => PgErrorCode::Ec53000,
-            StatusCode::RateLimited => PgErrorCode::Ec54000,
+            StatusCode::RateLimited | StatusCode::FlowQuotaExceeded => PgErrorCode::Ec54000,
             // ====== End of storage & server related status code =======
 
             // ====== Begin of auth related status code =====
@@ -16,8 +16,10 @@ use std::sync::Arc;
 
 use api::v1::greptime_request::Request;
 use async_trait::async_trait;
+use common_base::AffectedRows;
 use common_error::ext::{BoxedError, ErrorExt};
 use common_query::Output;
+use common_recordbatch::RecordBatch;
 use session::context::QueryContextRef;
 use snafu::ResultExt;
 
@@ -35,6 +37,17 @@ pub trait GrpcQueryHandler {
         query: Request,
         ctx: QueryContextRef,
     ) -> std::result::Result<Output, Self::Error>;
+
+    /// Inserts the record batch into the table, returns the number of rows inserted.
+    ///
+    /// An empty record batch only checks its schema is compatible with the table, so a DoPut
+    /// stream with an incompatible schema is rejected before any data is sent.
+    async fn put_record_batch(
+        &self,
+        table_name: &str,
+        record_batch: RecordBatch,
+        ctx: QueryContextRef,
+    ) -> std::result::Result<AffectedRows, Self::Error>;
 }
 
 pub struct ServerGrpcQueryHandlerAdapter<E>(GrpcQueryHandlerRef<E>);
@@ -59,4 +72,17 @@ where
             .map_err(BoxedError::new)
             .context(error::ExecuteGrpcQuerySnafu)
     }
+
+    async fn put_record_batch(
+        &self,
+        table_name: &str,
+        record_batch: RecordBatch,
+        ctx: QueryContextRef,
+    ) -> Result<AffectedRows> {
+        self.0
+            .put_record_batch(table_name, record_batch, ctx)
+            .await
+            .map_err(BoxedError::new)
+            .context(error::ExecuteGrpcQuerySnafu)
+    }
 }
@@ -20,6 +20,7 @@ use async_trait::async_trait;
 use auth::tests::{DatabaseAuthInfo, MockUserProvider};
 use axum::{http, Router};
 use common_query::Output;
+use common_recordbatch::RecordBatch;
 use common_test_util::ports;
 use datafusion_expr::LogicalPlan;
 use query::parser::PromQuery;
@@ -50,6 +51,15 @@ impl GrpcQueryHandler for DummyInstance {
     ) -> std::result::Result<Output, Self::Error> {
         unimplemented!()
     }
+
+    async fn put_record_batch(
+        &self,
+        _table_name: &str,
+        _record_batch: RecordBatch,
+        _ctx: QueryContextRef,
+    ) -> std::result::Result<usize, Self::Error> {
+        unimplemented!()
+    }
 }
 
 #[async_trait]
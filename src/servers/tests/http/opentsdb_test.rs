@@ -18,6 +18,7 @@ use api::v1::greptime_request::Request;
 use async_trait::async_trait;
 use axum::Router;
 use common_query::Output;
+use common_recordbatch::RecordBatch;
 use common_test_util::ports;
 use datafusion_expr::LogicalPlan;
 use query::parser::PromQuery;
@@ -47,6 +48,15 @@ impl GrpcQueryHandler for DummyInstance {
     ) -> std::result::Result<Output, Self::Error> {
         unimplemented!()
     }
+
+    async fn put_record_batch(
+        &self,
+        _table_name: &str,
+        _record_batch: RecordBatch,
+        _ctx: QueryContextRef,
+    ) -> std::result::Result<usize, Self::Error> {
+        unimplemented!()
+    }
 }
 
 #[async_trait]
@@ -13,11 +13,14 @@
 // limitations under the License.
 
 use std::borrow::Cow;
+use std::sync::Arc;
 
 use api::v1::greptime_request::Request;
 use api::v1::{InsertRequest, InsertRequests};
 use client::OutputData;
 use common_query::Output;
+use common_recordbatch::RecordBatch;
+use datatypes::schema::Schema;
 use query::parser::PromQuery;
 use servers::error::{self, InternalSnafu, NotSupportedSnafu, Result};
 use servers::interceptor::{GrpcQueryInterceptor, PromQueryInterceptor, SqlQueryInterceptor};
@@ -65,6 +68,16 @@ impl GrpcQueryInterceptor for NoopInterceptor {
         };
         Ok(())
     }
+
+    fn pre_put_record_batch(
+        &self,
+        table_name: &str,
+        _record_batch: &RecordBatch,
+        _query_ctx: QueryContextRef,
+    ) -> std::result::Result<(), Self::Error> {
+        ensure!(table_name != "forbidden", NotSupportedSnafu { feat: "" });
+        Ok(())
+    }
 }
 
 #[test]
@@ -80,7 +93,13 @@ fn test_grpc_interceptor() {
     assert!(fail.is_err());
 
     let req = Request::Inserts(InsertRequests::default());
-    GrpcQueryInterceptor::pre_execute(&di, &req, ctx).unwrap();
+    GrpcQueryInterceptor::pre_execute(&di, &req, ctx.clone()).unwrap();
+
+    let record_batch = RecordBatch::new_empty(Arc::new(Schema::new(vec![])));
+    assert!(di
+        .pre_put_record_batch("forbidden", &record_batch, ctx.clone())
+        .is_err());
+    di.pre_put_record_batch("t", &record_batch, ctx).unwrap();
 }
 
 impl PromQueryInterceptor for NoopInterceptor {
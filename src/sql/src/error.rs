@@ -175,6 +175,13 @@ pub enum Error {
         location: Location,
     },
 
+    #[snafu(display("Unrecognized flow option key: {}", key))]
+    InvalidFlowOption {
+        key: String,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
     #[snafu(display("Invalid table name: {}", name))]
     InvalidTableName {
         name: String,
@@ -355,6 +362,7 @@ impl ErrorExt for Error {
             | InvalidTableOptionValue { .. }
             | InvalidDatabaseName { .. }
             | InvalidDatabaseOption { .. }
+            | InvalidFlowOption { .. }
             | ColumnTypeMismatch { .. }
             | InvalidTableName { .. }
             | InvalidFlowName { .. }
@@ -25,7 +25,12 @@ pub mod statements;
 pub mod util;
 
 pub use parsers::create_parser::{
-    COLUMN_FULLTEXT_OPT_KEY_ANALYZER, COLUMN_FULLTEXT_OPT_KEY_CASE_SENSITIVE, ENGINE, MAXVALUE,
+    COLUMN_FULLTEXT_OPT_KEY_ANALYZER, COLUMN_FULLTEXT_OPT_KEY_CASE_SENSITIVE, ENGINE,
+    FLOW_OPT_KEY_ALLOWED_LATENESS, FLOW_OPT_KEY_BACKFILL, FLOW_OPT_KEY_CHECKPOINT_INTERVAL,
+    FLOW_OPT_KEY_CREATE_SINK_TABLE, FLOW_OPT_KEY_DEAD_LETTER_TABLE, FLOW_OPT_KEY_EMIT_INTERVAL,
+    FLOW_OPT_KEY_EXPIRE_WHEN, FLOW_OPT_KEY_MAX_BATCH_DELAY, FLOW_OPT_KEY_MAX_STATE_ROWS,
+    FLOW_OPT_KEY_MAX_STATE_SIZE, FLOW_OPT_KEY_MEMORY_BUDGET, FLOW_OPT_KEY_STATE_EVICTION,
+    FLOW_OPT_KEY_TICK_INTERVAL, MAXVALUE,
 };
 pub use parsers::tql_parser::TQL;
 pub use statements::create::TIME_INDEX;
@@ -53,6 +53,11 @@ impl ParserContext<'_> {
         Ok(Box::new(self.parser.parse_query().context(SyntaxSnafu)?))
     }
 
+    /// Parses parser context to an expression.
+    pub fn parser_expr(&mut self) -> Result<Expr> {
+        self.parser.parse_expr().context(SyntaxSnafu)
+    }
+
     /// Parses SQL with given dialect
     pub fn create_with_dialect(
         sql: &str,
@@ -19,16 +19,98 @@ use sqlparser::parser::ParserError;
 use sqlparser::tokenizer::Token;
 
 use crate::error::{self, Result};
-use crate::parser::ParserContext;
-use crate::statements::alter::{AlterTable, AlterTableOperation};
+use crate::parser::{ParserContext, FLOW};
+use crate::parsers::create_parser::{AFTER, EXPIRE};
+use crate::statements::alter::{AlterFlow, AlterTable, AlterTableOperation};
 use crate::statements::statement::Statement;
 
+const DISCARD: &str = "DISCARD";
+const STATE: &str = "STATE";
+
 impl ParserContext<'_> {
     pub(crate) fn parse_alter(&mut self) -> Result<Statement> {
+        if let Token::Word(w) = self.parser.peek_nth_token(1).token {
+            if w.value.eq_ignore_ascii_case(FLOW) {
+                return self.parse_alter_flow();
+            }
+        }
         let alter_table = self.parse_alter_table().context(error::SyntaxSnafu)?;
         Ok(Statement::Alter(alter_table))
     }
 
+    /// "ALTER FLOW" clause, changing `EXPIRE AFTER`, `COMMENT`, options in `WITH` or the query
+    /// of a flow, i.e.
+    ///
+    /// ```sql
+    /// ALTER FLOW <flow_name>
+    /// [EXPIRE AFTER <interval> | NULL]
+    /// [COMMENT '<comment>']
+    /// [WITH (<option> = '<value>', ...)]
+    /// [DISCARD STATE]
+    /// [AS <query>]
+    /// ```
+    fn parse_alter_flow(&mut self) -> Result<Statement> {
+        let _ = self.parser.next_token();
+        let _ = self.parser.next_token();
+
+        let flow_name = self.intern_parse_table_name()?;
+
+        let expire_after = if self
+            .parser
+            .consume_tokens(&[Token::make_keyword(EXPIRE), Token::make_keyword(AFTER)])
+        {
+            if self.parser.parse_keyword(Keyword::NULL) {
+                Some(None)
+            } else {
+                Some(Some(self.parse_flow_expire_after()?))
+            }
+        } else {
+            None
+        };
+
+        let comment = if self.parser.parse_keyword(Keyword::COMMENT) {
+            Some(self.parse_flow_comment()?)
+        } else {
+            None
+        };
+
+        let flow_options = self.parse_flow_options()?;
+
+        let discard_state = self
+            .parser
+            .consume_tokens(&[Token::make_keyword(DISCARD), Token::make_keyword(STATE)]);
+
+        let query = if self.parser.parse_keyword(Keyword::AS) {
+            Some(Box::new(
+                self.parser.parse_query().context(error::SyntaxSnafu)?,
+            ))
+        } else {
+            None
+        };
+
+        if expire_after.is_none()
+            && comment.is_none()
+            && flow_options.is_empty()
+            && !discard_state
+            && query.is_none()
+        {
+            return Err(ParserError::ParserError(format!(
+                "expect EXPIRE AFTER, COMMENT, WITH, DISCARD STATE or AS after ALTER FLOW, found {}",
+                self.parser.peek_token()
+            )))
+            .context(error::SyntaxSnafu);
+        }
+
+        Ok(Statement::AlterFlow(AlterFlow {
+            flow_name,
+            expire_after,
+            comment,
+            flow_options: flow_options.into(),
+            discard_state,
+            query,
+        }))
+    }
+
     fn parse_alter_table(&mut self) -> std::result::Result<AlterTable, ParserError> {
         self.parser
             .expect_keywords(&[Keyword::ALTER, Keyword::TABLE])?;
@@ -406,4 +488,66 @@ mod tests {
             _ => unreachable!(),
         }
     }
+
+    #[test]
+    fn test_parse_alter_flow() {
+        let sql = "ALTER FLOW my_flow EXPIRE AFTER INTERVAL '1 hour' COMMENT 'hourly'";
+        let mut result =
+            ParserContext::create_with_dialect(sql, &GreptimeDbDialect {}, ParseOptions::default())
+                .unwrap();
+        assert_eq!(1, result.len());
+        let Statement::AlterFlow(alter_flow) = result.remove(0) else {
+            unreachable!()
+        };
+        assert_eq!("my_flow", alter_flow.flow_name.0[0].value);
+        assert_eq!(Some(Some(3600)), alter_flow.expire_after);
+        assert_eq!(Some("hourly".to_string()), alter_flow.comment);
+
+        let sql = "ALTER FLOW my_flow EXPIRE AFTER NULL";
+        let mut result =
+            ParserContext::create_with_dialect(sql, &GreptimeDbDialect {}, ParseOptions::default())
+                .unwrap();
+        let Statement::AlterFlow(alter_flow) = result.remove(0) else {
+            unreachable!()
+        };
+        assert_eq!(Some(None), alter_flow.expire_after);
+        assert_eq!(None, alter_flow.comment);
+
+        let sql = "ALTER FLOW my_flow WITH (emit_interval = '10s') DISCARD STATE AS SELECT max(n) FROM numbers";
+        let mut result =
+            ParserContext::create_with_dialect(sql, &GreptimeDbDialect {}, ParseOptions::default())
+                .unwrap();
+        let Statement::AlterFlow(alter_flow) = result.remove(0) else {
+            unreachable!()
+        };
+        assert_eq!(None, alter_flow.expire_after);
+        assert_eq!(
+            Some("10s"),
+            alter_flow
+                .flow_options
+                .get("emit_interval")
+                .map(String::as_str)
+        );
+        assert!(alter_flow.discard_state);
+        assert_eq!(
+            "SELECT max(n) FROM numbers",
+            alter_flow.query.unwrap().to_string()
+        );
+
+        let sql = "ALTER FLOW my_flow";
+        let err =
+            ParserContext::create_with_dialect(sql, &GreptimeDbDialect {}, ParseOptions::default())
+                .unwrap_err();
+        assert!(err
+            .output_msg()
+            .contains("expect EXPIRE AFTER, COMMENT, WITH, DISCARD STATE or AS after ALTER FLOW"));
+
+        let sql = "ALTER FLOW my_flow WITH (unknown_option = 'x')";
+        assert!(ParserContext::create_with_dialect(
+            sql,
+            &GreptimeDbDialect {},
+            ParseOptions::default()
+        )
+        .is_err());
+    }
 }
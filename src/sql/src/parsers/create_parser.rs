@@ -31,9 +31,9 @@ use table::requests::validate_table_option;
 use super::utils;
 use crate::ast::{ColumnDef, Ident, TableConstraint};
 use crate::error::{
-    self, InvalidColumnOptionSnafu, InvalidDatabaseOptionSnafu, InvalidIntervalSnafu,
-    InvalidSqlSnafu, InvalidTableOptionSnafu, InvalidTimeIndexSnafu, MissingTimeIndexSnafu, Result,
-    SyntaxSnafu, UnexpectedSnafu, UnsupportedSnafu,
+    self, InvalidColumnOptionSnafu, InvalidDatabaseOptionSnafu, InvalidFlowOptionSnafu,
+    InvalidIntervalSnafu, InvalidSqlSnafu, InvalidTableOptionSnafu, InvalidTimeIndexSnafu,
+    MissingTimeIndexSnafu, Result, SyntaxSnafu, UnexpectedSnafu, UnsupportedSnafu,
 };
 use crate::parser::{ParserContext, FLOW};
 use crate::statements::create::{
@@ -51,6 +51,7 @@ pub const MAXVALUE: &str = "MAXVALUE";
 pub const SINK: &str = "SINK";
 pub const EXPIRE: &str = "EXPIRE";
 pub const AFTER: &str = "AFTER";
+pub const WHEN: &str = "WHEN";
 
 const DB_OPT_KEY_TTL: &str = "ttl";
 
@@ -58,6 +59,51 @@ fn validate_database_option(key: &str) -> bool {
     [DB_OPT_KEY_TTL].contains(&key)
 }
 
+/// Whether to backfill a newly created flow with the existing data of its source tables
+pub const FLOW_OPT_KEY_BACKFILL: &str = "backfill";
+/// Whether to create the sink table from the output of the flow if it doesn't exist
+pub const FLOW_OPT_KEY_CREATE_SINK_TABLE: &str = "create_sink_table";
+/// The table where rows failed to be evaluated by the flow are written to
+pub const FLOW_OPT_KEY_DEAD_LETTER_TABLE: &str = "dead_letter_table";
+/// The `EXPIRE WHEN` of a flow, which is carried in flow options instead of `WITH`
+pub const FLOW_OPT_KEY_EXPIRE_WHEN: &str = "expire_when";
+/// The minimal interval between writing the flow's results to its sink table
+pub const FLOW_OPT_KEY_EMIT_INTERVAL: &str = "emit_interval";
+/// How late a row can arrive and still update the flow's results
+pub const FLOW_OPT_KEY_ALLOWED_LATENESS: &str = "allowed_lateness";
+/// The max number of rows in the flow's state
+pub const FLOW_OPT_KEY_MAX_STATE_ROWS: &str = "max_state_rows";
+/// The interval of checkpointing the flow's state
+pub const FLOW_OPT_KEY_CHECKPOINT_INTERVAL: &str = "checkpoint_interval";
+/// The memory budget of the flow's state, above which its cold keys are spilled to disk
+pub const FLOW_OPT_KEY_MEMORY_BUDGET: &str = "memory_budget";
+/// The flow only ticks at most once per interval
+pub const FLOW_OPT_KEY_TICK_INTERVAL: &str = "tick_interval";
+/// The flow only ticks once inputs arrive, waiting at most this long for more inputs
+pub const FLOW_OPT_KEY_MAX_BATCH_DELAY: &str = "max_batch_delay";
+/// The max estimated size of the flow's state, above which its keys are evicted
+pub const FLOW_OPT_KEY_MAX_STATE_SIZE: &str = "max_state_size";
+/// Which keys of the flow's state are evicted first, `lru` or `oldest_timestamp`
+pub const FLOW_OPT_KEY_STATE_EVICTION: &str = "state_eviction";
+
+fn validate_flow_option(key: &str) -> bool {
+    [
+        FLOW_OPT_KEY_BACKFILL,
+        FLOW_OPT_KEY_CREATE_SINK_TABLE,
+        FLOW_OPT_KEY_DEAD_LETTER_TABLE,
+        FLOW_OPT_KEY_EMIT_INTERVAL,
+        FLOW_OPT_KEY_ALLOWED_LATENESS,
+        FLOW_OPT_KEY_MAX_STATE_ROWS,
+        FLOW_OPT_KEY_CHECKPOINT_INTERVAL,
+        FLOW_OPT_KEY_MEMORY_BUDGET,
+        FLOW_OPT_KEY_TICK_INTERVAL,
+        FLOW_OPT_KEY_MAX_BATCH_DELAY,
+        FLOW_OPT_KEY_MAX_STATE_SIZE,
+        FLOW_OPT_KEY_STATE_EVICTION,
+    ]
+    .contains(&key)
+}
+
 pub const COLUMN_FULLTEXT_OPT_KEY_ANALYZER: &str = "analyzer";
 pub const COLUMN_FULLTEXT_OPT_KEY_CASE_SENSITIVE: &str = "case_sensitive";
 
@@ -281,45 +327,28 @@ impl<'a> ParserContext<'a> {
             .parser
             .consume_tokens(&[Token::make_keyword(EXPIRE), Token::make_keyword(AFTER)])
         {
-            let expire_after_expr = self.parser.parse_expr().context(error::SyntaxSnafu)?;
-            let expire_after_lit = utils::parser_expr_to_scalar_value(expire_after_expr.clone())?
-                .cast_to(&ArrowDataType::Interval(IntervalUnit::MonthDayNano))
-                .ok()
-                .with_context(|| InvalidIntervalSnafu {
-                    reason: format!("cannot cast {} to interval type", expire_after_expr),
-                })?;
-            if let ScalarValue::IntervalMonthDayNano(Some(nanoseconds)) = expire_after_lit {
-                Some(
-                    i64::try_from(nanoseconds / 1_000_000_000)
-                        .ok()
-                        .with_context(|| InvalidIntervalSnafu {
-                            reason: format!("interval {} overflows", nanoseconds),
-                        })?,
-                )
-            } else {
-                unreachable!()
-            }
+            Some(self.parse_flow_expire_after()?)
+        } else {
+            None
+        };
+
+        let expire_when = if self
+            .parser
+            .consume_tokens(&[Token::make_keyword(EXPIRE), Token::make_keyword(WHEN)])
+        {
+            Some(self.parser.parse_expr().context(SyntaxSnafu)?)
         } else {
             None
         };
 
         let comment = if self.parser.parse_keyword(Keyword::COMMENT) {
-            match self.parser.next_token() {
-                TokenWithLocation {
-                    token: Token::SingleQuotedString(value, ..),
-                    ..
-                } => Some(value),
-                unexpected => {
-                    return self
-                        .parser
-                        .expected("string", unexpected)
-                        .context(SyntaxSnafu)
-                }
-            }
+            Some(self.parse_flow_comment()?)
         } else {
             None
         };
 
+        let flow_options = self.parse_flow_options()?;
+
         self.parser
             .expect_keyword(Keyword::AS)
             .context(SyntaxSnafu)?;
@@ -332,11 +361,70 @@ impl<'a> ParserContext<'a> {
             or_replace,
             if_not_exists,
             expire_after,
+            expire_when,
             comment,
+            flow_options: flow_options.into(),
             query,
         }))
     }
 
+    /// Parses the options in `WITH` of a flow, which are empty if there is no `WITH`
+    pub(crate) fn parse_flow_options(&mut self) -> Result<HashMap<String, String>> {
+        let flow_options = self
+            .parser
+            .parse_options(Keyword::WITH)
+            .context(SyntaxSnafu)?
+            .into_iter()
+            .map(parse_option_string)
+            .collect::<Result<HashMap<String, String>>>()?;
+        for key in flow_options.keys() {
+            ensure!(
+                validate_flow_option(key),
+                InvalidFlowOptionSnafu {
+                    key: key.to_string()
+                }
+            );
+        }
+        Ok(flow_options)
+    }
+
+    /// Parses the interval after "EXPIRE AFTER" of a flow into seconds
+    pub(crate) fn parse_flow_expire_after(&mut self) -> Result<i64> {
+        let expire_after_expr = self.parser.parse_expr().context(error::SyntaxSnafu)?;
+        let expire_after_lit = utils::parser_expr_to_scalar_value(expire_after_expr.clone())?
+            .cast_to(&ArrowDataType::Interval(IntervalUnit::MonthDayNano))
+            .ok()
+            .with_context(|| InvalidIntervalSnafu {
+                reason: format!("cannot cast {} to interval type", expire_after_expr),
+            })?;
+        if let ScalarValue::IntervalMonthDayNano(Some(nanoseconds)) = expire_after_lit {
+            i64::try_from(nanoseconds / 1_000_000_000)
+                .ok()
+                .with_context(|| InvalidIntervalSnafu {
+                    reason: format!("interval {} overflows", nanoseconds),
+                })
+        } else {
+            InvalidIntervalSnafu {
+                reason: format!("{} is not a valid interval", expire_after_expr),
+            }
+            .fail()
+        }
+    }
+
+    /// Parses the string after "COMMENT" of a flow
+    pub(crate) fn parse_flow_comment(&mut self) -> Result<String> {
+        match self.parser.next_token() {
+            TokenWithLocation {
+                token: Token::SingleQuotedString(value, ..),
+                ..
+            } => Ok(value),
+            unexpected => self
+                .parser
+                .expected("string", unexpected)
+                .context(SyntaxSnafu),
+        }
+    }
+
     fn parse_if_not_exist(&mut self) -> Result<bool> {
         match self.parser.peek_token().token {
             Token::Word(w) if Keyword::IF != w.keyword => return Ok(false),
@@ -1254,7 +1342,9 @@ SELECT max(c1), min(c2) FROM schema_2.table_2;";
             or_replace: true,
             if_not_exists: true,
             expire_after: Some(300),
+            expire_when: None,
             comment: Some("test comment".to_string()),
+            flow_options: OptionMap::default(),
             // ignore query parse result
             query: create_task.query.clone(),
         };
@@ -1278,6 +1368,62 @@ SELECT max(c1), min(c2) FROM schema_2.table_2;";
         assert!(!create_task.if_not_exists);
         assert!(create_task.expire_after.is_none());
         assert!(create_task.comment.is_none());
+        assert!(create_task.flow_options.is_empty());
+
+        let sql = r"
+CREATE FLOW task_5
+SINK TO schema_1.table_1
+EXPIRE WHEN ts + INTERVAL '1 hour'
+AS
+SELECT max(c1), ts FROM schema_2.table_2 GROUP BY ts;";
+        let stmts =
+            ParserContext::create_with_dialect(sql, &GreptimeDbDialect {}, ParseOptions::default())
+                .unwrap();
+        let create_task = match &stmts[0] {
+            Statement::CreateFlow(c) => c,
+            _ => unreachable!(),
+        };
+        assert!(create_task.expire_after.is_none());
+        assert_eq!(
+            "ts + INTERVAL '1 hour'",
+            create_task.expire_when.as_ref().unwrap().to_string()
+        );
+
+        let sql = r"
+CREATE FLOW task_3
+SINK TO schema_1.table_1
+WITH (backfill = 'true', dead_letter_table = 'dlq', emit_interval = '10s')
+AS
+SELECT max(c1), min(c2) FROM schema_2.table_2;";
+        let stmts =
+            ParserContext::create_with_dialect(sql, &GreptimeDbDialect {}, ParseOptions::default())
+                .unwrap();
+        let create_task = match &stmts[0] {
+            Statement::CreateFlow(c) => c,
+            _ => unreachable!(),
+        };
+        assert_eq!(
+            Some(&"true".to_string()),
+            create_task.flow_options.get(FLOW_OPT_KEY_BACKFILL)
+        );
+        assert_eq!(
+            Some(&"dlq".to_string()),
+            create_task.flow_options.get(FLOW_OPT_KEY_DEAD_LETTER_TABLE)
+        );
+        assert_eq!(
+            Some(&"10s".to_string()),
+            create_task.flow_options.get(FLOW_OPT_KEY_EMIT_INTERVAL)
+        );
+
+        let sql = r"
+CREATE FLOW task_4
+SINK TO schema_1.table_1
+WITH (foo = 'bar')
+AS
+SELECT max(c1), min(c2) FROM schema_2.table_2;";
+        let result =
+            ParserContext::create_with_dialect(sql, &GreptimeDbDialect {}, ParseOptions::default());
+        assert!(result.is_err());
     }
 
     #[test]
@@ -14,15 +14,32 @@
 
 use snafu::ResultExt;
 use sqlparser::ast::DescribeAlias;
+use sqlparser::keywords::Keyword;
+use sqlparser::tokenizer::Token;
 
 use crate::error::{self, Result};
-use crate::parser::ParserContext;
-use crate::statements::explain::Explain;
+use crate::parser::{ParserContext, FLOW};
+use crate::statements::explain::{Explain, ExplainFlow};
 use crate::statements::statement::Statement;
 
 /// EXPLAIN statement parser implementation
 impl ParserContext<'_> {
     pub(crate) fn parse_explain(&mut self) -> Result<Statement> {
+        if self.peek_word_is(0, FLOW) {
+            let _ = self.parser.next_token();
+            let flow_name = self.intern_parse_table_name()?;
+            return Ok(Statement::ExplainFlow(ExplainFlow::Flow(flow_name)));
+        }
+        if self.is_create_flow() {
+            let _ = self.parser.next_token();
+            return match self.parse_create()? {
+                Statement::CreateFlow(create_flow) => {
+                    Ok(Statement::ExplainFlow(ExplainFlow::Create(create_flow)))
+                }
+                stmt => self.unsupported(format!("EXPLAIN {stmt}")),
+            };
+        }
+
         let explain_statement = self
             .parser
             .parse_explain(DescribeAlias::Explain)
@@ -33,6 +50,27 @@ impl ParserContext<'_> {
 
         Ok(Statement::Explain(Explain::try_from(explain_statement)?))
     }
+
+    /// Whether the following tokens are `CREATE [OR REPLACE] FLOW`
+    fn is_create_flow(&self) -> bool {
+        if !matches!(&self.parser.peek_token().token, Token::Word(w) if w.keyword == Keyword::CREATE)
+        {
+            return false;
+        }
+        if self.peek_word_is(1, "OR") {
+            self.peek_word_is(3, FLOW)
+        } else {
+            self.peek_word_is(1, FLOW)
+        }
+    }
+
+    /// Whether the `n`th token from the current one is the unquoted word `word`
+    fn peek_word_is(&self, n: usize, word: &str) -> bool {
+        matches!(
+            &self.parser.peek_nth_token(n).token,
+            Token::Word(w) if w.quote_style.is_none() && w.value.eq_ignore_ascii_case(word)
+        )
+    }
 }
 
 #[cfg(test)]
@@ -106,4 +144,29 @@ mod tests {
 
         assert_eq!(stmts[0], Statement::Explain(explain))
     }
+
+    #[test]
+    fn test_explain_flow() {
+        let sql = "EXPLAIN FLOW my_catalog.my_flow";
+        let stmts =
+            ParserContext::create_with_dialect(sql, &GreptimeDbDialect {}, ParseOptions::default())
+                .unwrap();
+        let Statement::ExplainFlow(ExplainFlow::Flow(flow_name)) = &stmts[0] else {
+            unreachable!()
+        };
+        assert_eq!(flow_name.to_string(), "my_catalog.my_flow");
+        assert_eq!(stmts[0].to_string(), sql);
+
+        let sql =
+            "EXPLAIN CREATE OR REPLACE FLOW my_flow SINK TO out AS SELECT max(n) FROM numbers";
+        let stmts =
+            ParserContext::create_with_dialect(sql, &GreptimeDbDialect {}, ParseOptions::default())
+                .unwrap();
+        let Statement::ExplainFlow(ExplainFlow::Create(create_flow)) = &stmts[0] else {
+            unreachable!()
+        };
+        assert!(create_flow.or_replace);
+        assert_eq!(create_flow.flow_name.to_string(), "my_flow");
+        assert_eq!(create_flow.sink_table_name.to_string(), "out");
+    }
 }
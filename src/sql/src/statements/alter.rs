@@ -15,9 +15,11 @@
 use std::fmt::{Debug, Display};
 
 use common_query::AddColumnLocation;
-use sqlparser::ast::{ColumnDef, DataType, Ident, ObjectName, TableConstraint};
+use sqlparser::ast::{ColumnDef, DataType, Ident, ObjectName, Query, TableConstraint};
 use sqlparser_derive::{Visit, VisitMut};
 
+use crate::statements::OptionMap;
+
 #[derive(Debug, Clone, PartialEq, Eq, Visit, VisitMut)]
 pub struct AlterTable {
     table_name: ObjectName,
@@ -101,6 +103,48 @@ impl Display for AlterTableOperation {
     }
 }
 
+/// `ALTER FLOW` statement, changing options or the query of a flow without recreating it.
+#[derive(Debug, Clone, PartialEq, Eq, Visit, VisitMut)]
+pub struct AlterFlow {
+    /// Flow name
+    pub flow_name: ObjectName,
+    /// `EXPIRE AFTER`, duration in second as `i64`.
+    /// `None` to keep it unchanged and `Some(None)` for `EXPIRE AFTER NULL` which removes it.
+    pub expire_after: Option<Option<i64>>,
+    /// `COMMENT`, `None` to keep it unchanged
+    pub comment: Option<String>,
+    /// Options in `WITH`, overriding the flow's options of the same keys
+    pub flow_options: OptionMap,
+    /// `DISCARD STATE`, rebuild the flow with empty state instead of keeping it
+    pub discard_state: bool,
+    /// `AS <query>`, the new query of the flow, `None` to keep it unchanged
+    pub query: Option<Box<Query>>,
+}
+
+impl Display for AlterFlow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ALTER FLOW {}", self.flow_name)?;
+        match &self.expire_after {
+            Some(Some(expire_after)) => write!(f, " EXPIRE AFTER {expire_after}")?,
+            Some(None) => write!(f, " EXPIRE AFTER NULL")?,
+            None => {}
+        }
+        if let Some(comment) = &self.comment {
+            write!(f, " COMMENT '{comment}'")?;
+        }
+        if !self.flow_options.is_empty() {
+            write!(f, " WITH ({})", self.flow_options.kv_pairs().join(", "))?;
+        }
+        if self.discard_state {
+            write!(f, " DISCARD STATE")?;
+        }
+        if let Some(query) = &self.query {
+            write!(f, " AS {query}")?;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::assert_matches::assert_matches;
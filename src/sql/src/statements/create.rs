@@ -352,8 +352,12 @@ pub struct CreateFlow {
     /// `EXPIRE AFTER`
     /// Duration in second as `i64`
     pub expire_after: Option<i64>,
+    /// `EXPIRE WHEN`, the time a key of the flow's state expires at
+    pub expire_when: Option<Expr>,
     /// Comment string
     pub comment: Option<String>,
+    /// Options in `WITH`, i.e. whether to backfill the flow with existing data
+    pub flow_options: OptionMap,
     /// SQL statement
     pub query: Box<Query>,
 }
@@ -373,9 +377,16 @@ impl Display for CreateFlow {
         if let Some(expire_after) = &self.expire_after {
             writeln!(f, "EXPIRE AFTER {} ", expire_after)?;
         }
+        if let Some(expire_when) = &self.expire_when {
+            writeln!(f, "EXPIRE WHEN {}", expire_when)?;
+        }
         if let Some(comment) = &self.comment {
             writeln!(f, "COMMENT '{}'", comment)?;
         }
+        if !self.flow_options.is_empty() {
+            let options = self.flow_options.kv_pairs();
+            writeln!(f, "WITH(\n{}\n)", format_list_indent!(options))?;
+        }
         write!(f, "AS {}", &self.query)
     }
 }
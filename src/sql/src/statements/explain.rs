@@ -14,10 +14,11 @@
 
 use std::fmt::{Display, Formatter};
 
-use sqlparser::ast::Statement as SpStatement;
+use sqlparser::ast::{ObjectName, Statement as SpStatement};
 use sqlparser_derive::{Visit, VisitMut};
 
 use crate::error::Error;
+use crate::statements::create::CreateFlow;
 
 /// Explain statement.
 #[derive(Debug, Clone, PartialEq, Eq, Visit, VisitMut)]
@@ -38,3 +39,21 @@ impl Display for Explain {
         write!(f, "{}", self.inner)
     }
 }
+
+/// `EXPLAIN FLOW <flow_name>` or `EXPLAIN CREATE FLOW ...`, planning the flow without creating it.
+#[derive(Debug, Clone, PartialEq, Eq, Visit, VisitMut)]
+pub enum ExplainFlow {
+    /// An existing flow.
+    Flow(ObjectName),
+    /// A flow to be created.
+    Create(CreateFlow),
+}
+
+impl Display for ExplainFlow {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExplainFlow::Flow(flow_name) => write!(f, "EXPLAIN FLOW {flow_name}"),
+            ExplainFlow::Create(create_flow) => write!(f, "EXPLAIN {create_flow}"),
+        }
+    }
+}
@@ -20,14 +20,14 @@ use sqlparser_derive::{Visit, VisitMut};
 
 use crate::error::{ConvertToDfStatementSnafu, Error};
 use crate::statements::admin::Admin;
-use crate::statements::alter::AlterTable;
+use crate::statements::alter::{AlterFlow, AlterTable};
 use crate::statements::create::{
     CreateDatabase, CreateExternalTable, CreateFlow, CreateTable, CreateTableLike, CreateView,
 };
 use crate::statements::delete::Delete;
 use crate::statements::describe::DescribeTable;
 use crate::statements::drop::{DropDatabase, DropFlow, DropTable, DropView};
-use crate::statements::explain::Explain;
+use crate::statements::explain::{Explain, ExplainFlow};
 use crate::statements::insert::Insert;
 use crate::statements::query::Query;
 use crate::statements::set_variables::SetVariables;
@@ -71,6 +71,8 @@ pub enum Statement {
     CreateDatabase(CreateDatabase),
     /// ALTER TABLE
     Alter(AlterTable),
+    /// ALTER FLOW
+    AlterFlow(AlterFlow),
     // Databases.
     ShowDatabases(ShowDatabases),
     // SHOW TABLES
@@ -103,6 +105,8 @@ pub enum Statement {
     DescribeTable(DescribeTable),
     // EXPLAIN QUERY
     Explain(Explain),
+    /// EXPLAIN FLOW or EXPLAIN CREATE FLOW
+    ExplainFlow(ExplainFlow),
     // COPY
     Copy(crate::statements::copy::Copy),
     Tql(Tql),
@@ -134,6 +138,7 @@ impl Display for Statement {
             Statement::DropView(s) => s.fmt(f),
             Statement::CreateDatabase(s) => s.fmt(f),
             Statement::Alter(s) => s.fmt(f),
+            Statement::AlterFlow(s) => s.fmt(f),
             Statement::ShowDatabases(s) => s.fmt(f),
             Statement::ShowTables(s) => s.fmt(f),
             Statement::ShowTableStatus(s) => s.fmt(f),
@@ -148,6 +153,7 @@ impl Display for Statement {
             Statement::ShowStatus(s) => s.fmt(f),
             Statement::DescribeTable(s) => s.fmt(f),
             Statement::Explain(s) => s.fmt(f),
+            Statement::ExplainFlow(s) => s.fmt(f),
             Statement::Copy(s) => s.fmt(f),
             Statement::Tql(s) => s.fmt(f),
             Statement::TruncateTable(s) => s.fmt(f),
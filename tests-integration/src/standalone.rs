@@ -28,7 +28,7 @@ use common_meta::ddl::table_meta::TableMetadataAllocator;
 use common_meta::ddl::{DdlContext, NoopRegionFailureDetectorControl};
 use common_meta::ddl_manager::DdlManager;
 use common_meta::key::flow::FlowMetadataManager;
-use common_meta::key::TableMetadataManager;
+use common_meta::key::{FlowId, TableMetadataManager};
 use common_meta::kv_backend::KvBackendRef;
 use common_meta::region_keeper::MemoryRegionKeeper;
 use common_meta::sequence::SequenceBuilder;
@@ -177,6 +177,7 @@ impl GreptimeDbStandaloneBuilder {
             SequenceBuilder::new(FLOW_ID_SEQ, kv_backend.clone())
                 .initial(MIN_USER_FLOW_ID as u64)
                 .step(10)
+                .max(FlowId::MAX as u64)
                 .build(),
         );
         let wal_options_allocator = Arc::new(WalOptionsAllocator::new(
@@ -202,6 +203,7 @@ impl GreptimeDbStandaloneBuilder {
                     flow_metadata_manager,
                     flow_metadata_allocator,
                     region_failure_detector_controller: Arc::new(NoopRegionFailureDetectorControl),
+                    flow_quota: Default::default(),
                 },
                 procedure_manager.clone(),
                 register_procedure_loaders,
@@ -12,22 +12,31 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::sync::Arc;
+
 use api::v1::alter_expr::Kind;
 use api::v1::promql_request::Promql;
+use api::v1::value::ValueData;
 use api::v1::{
     column, AddColumn, AddColumns, AlterExpr, Basic, Column, ColumnDataType, ColumnDef,
-    CreateTableExpr, InsertRequest, InsertRequests, PromInstantQuery, PromRangeQuery,
-    PromqlRequest, RequestHeader, SemanticType,
+    ColumnSchema, CreateTableExpr, InsertRequest, InsertRequests, PromInstantQuery, PromRangeQuery,
+    PromqlRequest, RequestHeader, Row, RowInsertRequest, Rows, SemanticType, Value,
 };
 use auth::user_provider_from_option;
-use client::{Client, Database, OutputData, DEFAULT_CATALOG_NAME, DEFAULT_SCHEMA_NAME};
+use client::{
+    Client, CompressionEncoding, Database, OutputData, DEFAULT_CATALOG_NAME, DEFAULT_SCHEMA_NAME,
+};
 use common_catalog::consts::MITO_ENGINE;
 use common_grpc::channel_manager::ClientTlsOption;
 use common_query::Output;
-use common_recordbatch::RecordBatches;
+use common_recordbatch::{RecordBatch, RecordBatches};
 use common_runtime::runtime::{BuilderBuild, RuntimeTrait};
 use common_runtime::Runtime;
 use common_test_util::find_workspace_path;
+use datatypes::prelude::{ConcreteDataType, VectorRef};
+use datatypes::schema::Schema;
+use datatypes::vectors::{Float64Vector, StringVector, TimestampMillisecondVector};
+use futures::StreamExt;
 use servers::grpc::builder::GrpcServerBuilder;
 use servers::grpc::GrpcServerConfig;
 use servers::http::prometheus::{
@@ -73,6 +82,9 @@ macro_rules! grpc_tests {
                 test_invalid_dbname,
                 test_auto_create_table,
                 test_auto_create_table_with_hints,
+                test_stream_insert,
+                test_put_record_batches,
+                test_sql_stream,
                 test_insert_and_select,
                 test_dbname,
                 test_grpc_message_size_ok,
@@ -164,9 +176,14 @@ pub async fn test_grpc_zstd_compression(store_type: StorageType) {
     let grpc_client = Client::with_urls(vec![addr]);
     let db = Database::new_with_dbname(
         format!("{}-{}", DEFAULT_CATALOG_NAME, DEFAULT_SCHEMA_NAME),
-        grpc_client,
+        grpc_client.clone(),
     );
     db.sql("show tables;").await.unwrap();
+
+    for encoding in [Some(CompressionEncoding::Gzip), None] {
+        grpc_client.set_compression(encoding);
+        db.sql("show tables;").await.unwrap();
+    }
     let _ = fe_grpc_server.shutdown().await;
     guard.remove_all().await;
 }
@@ -290,6 +307,177 @@ pub async fn test_auto_create_table_with_hints(store_type: StorageType) {
     guard.remove_all().await;
 }
 
+pub async fn test_stream_insert(store_type: StorageType) {
+    let (addr, mut guard, fe_grpc_server) = setup_grpc_server(store_type, "stream_insert").await;
+
+    let grpc_client = Client::with_urls(vec![addr]);
+    let db = Database::new(DEFAULT_CATALOG_NAME, DEFAULT_SCHEMA_NAME, grpc_client);
+
+    let schema = vec![
+        ColumnSchema {
+            column_name: "host".to_string(),
+            datatype: ColumnDataType::String as i32,
+            semantic_type: SemanticType::Tag as i32,
+            ..Default::default()
+        },
+        ColumnSchema {
+            column_name: "ts".to_string(),
+            datatype: ColumnDataType::TimestampMillisecond as i32,
+            semantic_type: SemanticType::Timestamp as i32,
+            ..Default::default()
+        },
+    ];
+    let mut inserter = db.streaming_inserter(16).unwrap();
+    for batch in 0..3 {
+        let rows = (0..10)
+            .map(|i| Row {
+                values: vec![
+                    Value {
+                        value_data: Some(ValueData::StringValue(format!("host{batch}"))),
+                    },
+                    Value {
+                        value_data: Some(ValueData::TimestampMillisecondValue(i)),
+                    },
+                ],
+            })
+            .collect();
+        inserter
+            .insert(vec![RowInsertRequest {
+                table_name: "demo".to_string(),
+                rows: Some(Rows {
+                    schema: schema.clone(),
+                    rows,
+                }),
+            }])
+            .await
+            .unwrap();
+    }
+    assert_eq!(inserter.sent().rows, 30);
+
+    let summary = inserter.finish().await.unwrap();
+    assert_eq!(summary.requests, 3);
+    assert_eq!(summary.rows, 30);
+    assert_eq!(summary.affected_rows, 30);
+
+    let output = db.sql("SELECT count(*) FROM demo").await.unwrap();
+    let record_batches = match output.data {
+        OutputData::RecordBatches(record_batches) => record_batches,
+        OutputData::Stream(stream) => RecordBatches::try_collect(stream).await.unwrap(),
+        OutputData::AffectedRows(_) => unreachable!(),
+    };
+    let expected = "\
++----------+
+| COUNT(*) |
++----------+
+| 30       |
++----------+";
+    assert_eq!(record_batches.pretty_print().unwrap(), expected);
+
+    let _ = fe_grpc_server.shutdown().await;
+    guard.remove_all().await;
+}
+
+pub async fn test_put_record_batches(store_type: StorageType) {
+    let (addr, mut guard, fe_grpc_server) =
+        setup_grpc_server(store_type, "put_record_batches").await;
+
+    let grpc_client = Client::with_urls(vec![addr]);
+    let db = Database::new(DEFAULT_CATALOG_NAME, DEFAULT_SCHEMA_NAME, grpc_client);
+    let _ = db
+        .sql("CREATE TABLE demo (host STRING, cpu DOUBLE, ts TIMESTAMP TIME INDEX, PRIMARY KEY(host))")
+        .await
+        .unwrap();
+
+    let schema = Arc::new(Schema::new(vec![
+        datatypes::schema::ColumnSchema::new("host", ConcreteDataType::string_datatype(), true),
+        datatypes::schema::ColumnSchema::new("cpu", ConcreteDataType::float64_datatype(), true),
+        datatypes::schema::ColumnSchema::new(
+            "ts",
+            ConcreteDataType::timestamp_millisecond_datatype(),
+            false,
+        ),
+    ]));
+    let record_batches = (0..3)
+        .map(|batch| {
+            let columns: Vec<VectorRef> = vec![
+                Arc::new(StringVector::from(vec![format!("host{batch}"); 10])),
+                Arc::new(Float64Vector::from_vec(vec![batch as f64; 10])),
+                Arc::new(TimestampMillisecondVector::from_vec((0..10).collect())),
+            ];
+            RecordBatch::new(schema.clone(), columns).unwrap()
+        })
+        .collect::<Vec<_>>();
+    let results = db
+        .put_record_batches("demo", schema, futures::stream::iter(record_batches))
+        .await
+        .unwrap();
+    assert_eq!(results.len(), 3);
+    for result in results {
+        assert_eq!(result.unwrap(), 10);
+    }
+
+    let output = db.sql("SELECT count(*) FROM demo").await.unwrap();
+    let record_batches = match output.data {
+        OutputData::RecordBatches(record_batches) => record_batches,
+        OutputData::Stream(stream) => RecordBatches::try_collect(stream).await.unwrap(),
+        OutputData::AffectedRows(_) => unreachable!(),
+    };
+    let expected = "\
++----------+
+| COUNT(*) |
++----------+
+| 30       |
++----------+";
+    assert_eq!(record_batches.pretty_print().unwrap(), expected);
+
+    // columns of the record batches must match the table
+    let schema = Arc::new(Schema::new(vec![datatypes::schema::ColumnSchema::new(
+        "cpu",
+        ConcreteDataType::string_datatype(),
+        true,
+    )]));
+    let result = db
+        .put_record_batches("demo", schema, futures::stream::empty())
+        .await;
+    assert!(result.is_err());
+
+    let _ = fe_grpc_server.shutdown().await;
+    guard.remove_all().await;
+}
+
+pub async fn test_sql_stream(store_type: StorageType) {
+    let (addr, mut guard, fe_grpc_server) = setup_grpc_server(store_type, "sql_stream").await;
+
+    let grpc_client = Client::with_urls(vec![addr]);
+    let db = Database::new(DEFAULT_CATALOG_NAME, DEFAULT_SCHEMA_NAME, grpc_client);
+
+    let _ = db
+        .sql("CREATE TABLE demo (host STRING, ts TIMESTAMP TIME INDEX, PRIMARY KEY(host))")
+        .await
+        .unwrap();
+    let values = (0..100)
+        .map(|i| format!("('host{i}', {i})"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let _ = db
+        .sql(format!("INSERT INTO demo VALUES {values}"))
+        .await
+        .unwrap();
+
+    let mut stream = db.sql_stream("SELECT * FROM demo").await.unwrap();
+    let mut rows = 0;
+    while let Some(record_batch) = stream.next().await {
+        rows += record_batch.unwrap().num_rows();
+    }
+    assert_eq!(rows, 100);
+
+    // statements without record batches
+    assert!(db.sql_stream("DELETE FROM demo").await.is_err());
+
+    let _ = fe_grpc_server.shutdown().await;
+    guard.remove_all().await;
+}
+
 fn expect_data() -> (Column, Column, Column, Column) {
     // testing data:
     let expected_host_col = Column {
@@ -566,6 +754,15 @@ pub async fn test_health_check(store_type: StorageType) {
     guard.remove_all().await;
 }
 
+async fn num_rows(output: Output) -> usize {
+    let record_batches = match output.data {
+        OutputData::RecordBatches(record_batches) => record_batches,
+        OutputData::Stream(stream) => RecordBatches::try_collect(stream).await.unwrap(),
+        OutputData::AffectedRows(_) => unreachable!(),
+    };
+    record_batches.iter().map(|r| r.num_rows()).sum()
+}
+
 pub async fn test_prom_gateway_query(store_type: StorageType) {
     common_telemetry::init_default_ut_logging();
 
@@ -735,6 +932,28 @@ pub async fn test_prom_gateway_query(store_type: StorageType) {
     };
     assert_eq!(range_query_result, expected);
 
+    // Instant query and range query using database service
+    let output = db
+        .promql_instant_query(PromInstantQuery {
+            query: "test".to_string(),
+            time: "5".to_string(),
+            lookback: "5m".to_string(),
+        })
+        .await
+        .unwrap();
+    assert_eq!(num_rows(output).await, 2);
+    let output = db
+        .promql_range_query(PromRangeQuery {
+            query: "test".to_string(),
+            start: "0".to_string(),
+            end: "10".to_string(),
+            step: "5s".to_string(),
+            lookback: "5m".to_string(),
+        })
+        .await
+        .unwrap();
+    assert_eq!(num_rows(output).await, 4);
+
     // clean up
     let _ = fe_grpc_server.shutdown().await;
     guard.remove_all().await;
@@ -828,6 +1047,7 @@ pub async fn test_grpc_tls_config(store_type: StorageType) {
         server_ca_cert_path: ca_path,
         client_cert_path,
         client_key_path,
+        watch: false,
     };
     {
         let grpc_client =
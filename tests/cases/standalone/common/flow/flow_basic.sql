@@ -120,11 +120,6 @@ CREATE TABLE numbers_input_basic (
     TIME INDEX(ts)
 );
 
-create table out_num_cnt_basic (
-    number INT,
-    ts TIMESTAMP DEFAULT CURRENT_TIMESTAMP TIME INDEX
-);
-
 CREATE FLOW filter_numbers_basic SINK TO out_num_cnt_basic AS
 SELECT
     INTERVAL '1 day 1 second',
@@ -139,8 +134,6 @@ SHOW CREATE FLOW filter_numbers_basic;
 
 drop flow filter_numbers_basic;
 
-drop table out_num_cnt_basic;
-
 drop table numbers_input_basic;
 
 CREATE TABLE bytes_log (